@@ -0,0 +1,157 @@
+//! `--a11y` text mode: one explicitly labeled line per value for screen readers and braille
+//! displays. No glyphs or charts, and values are rounded so lines only change when something
+//! meaningful does.
+
+use std::time::Duration;
+
+use crate::hints::{empty_hint, Panel};
+use crate::types::Metrics;
+
+// Processes listed in the text view
+const A11Y_TOP_PROCS: usize = 5;
+
+/// Live values the text view needs besides the metrics payload.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct A11yStatus<'a> {
+    pub rx_kb: u64,
+    pub tx_kb: u64,
+    pub is_tls: bool,
+    pub has_token: bool,
+    // Header warnings (cadence, fds, clock, ...), decorations are stripped
+    pub notice: Option<&'a str>,
+}
+
+pub fn a11y_lines(m: Option<&Metrics>, st: A11yStatus<'_>) -> Vec<String> {
+    let Some(mm) = m else {
+        return vec!["Status: connecting to agent".into()];
+    };
+    let mut out = Vec::new();
+    let mut host = mm.hostname.clone();
+    if let Some(i) = mm.instance.as_deref() {
+        host.push_str(&format!(", instance {i}"));
+    }
+    if !mm.labels.is_empty() {
+        let labels: Vec<String> = mm.labels.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        host.push_str(&format!(" (labels {})", labels.join(", ")));
+    }
+    out.push(format!("Host: {host}"));
+    out.push(format!(
+        "Connection: TLS {}, token {}",
+        on_off(st.is_tls),
+        on_off(st.has_token)
+    ));
+    if let Some(n) = st.notice {
+        out.push(format!("Warnings: {}", plain(n)));
+    }
+    out.push(format!(
+        "CPU: {} percent average over {} cores",
+        mm.cpu_total.round(),
+        mm.cpu_per_core.len()
+    ));
+    out.push(match mm.cpu_temp_c {
+        Some(t) => format!("CPU temperature: {} degrees Celsius", t.round()),
+        None => format!(
+            "CPU temperature: {}",
+            empty_hint(m, Panel::Temp, Duration::ZERO).unwrap_or_default()
+        ),
+    });
+    out.push(format!(
+        "Memory: {} used of {} ({} percent)",
+        bytes(mm.mem_used),
+        bytes(mm.mem_total),
+        pct(mm.mem_used, mm.mem_total)
+    ));
+    out.push(format!(
+        "Swap: {} used of {} ({} percent)",
+        bytes(mm.swap_used),
+        bytes(mm.swap_total),
+        pct(mm.swap_used, mm.swap_total)
+    ));
+    out.push(format!(
+        "Network: download {} kilobytes per second, upload {} kilobytes per second",
+        st.rx_kb, st.tx_kb
+    ));
+    match mm.gpus.as_deref().filter(|g| !g.is_empty()) {
+        Some(gpus) => {
+            for (i, g) in gpus.iter().enumerate() {
+                let mut line = format!(
+                    "GPU {}: {}, {} percent busy",
+                    i + 1,
+                    g.name.as_deref().unwrap_or("unknown"),
+                    g.utilization.unwrap_or(0.0).round()
+                );
+                if let (Some(used), Some(total)) = (g.mem_used, g.mem_total) {
+                    line.push_str(&format!(", memory {} of {}", bytes(used), bytes(total)));
+                }
+                out.push(line);
+            }
+        }
+        None => out.push(format!(
+            "GPU: {}",
+            empty_hint(m, Panel::Gpu, Duration::ZERO).unwrap_or_default()
+        )),
+    }
+    for d in &mm.disks {
+        let used = d.total.saturating_sub(d.available);
+        out.push(format!(
+            "Disk {}: {} used of {} ({} percent)",
+            d.name,
+            bytes(used),
+            bytes(d.total),
+            pct(used, d.total)
+        ));
+    }
+    if let Some(n) = mm.process_count {
+        out.push(format!("Processes: {n} total"));
+    }
+    let mut procs: Vec<_> = mm.top_processes.iter().collect();
+    procs.sort_by(|a, b| a.table_cmp(b, false));
+    for (i, p) in procs.iter().take(A11Y_TOP_PROCS).enumerate() {
+        out.push(format!(
+            "Top process {}: {} (PID {}), {} percent CPU, {} memory",
+            i + 1,
+            p.name,
+            p.pid,
+            p.cpu_usage.round(),
+            bytes(p.mem_bytes)
+        ));
+    }
+    out
+}
+
+/// Drop decorative symbols (emoji, arrows, box drawing) but keep words, numbers and punctuation.
+pub fn plain(s: &str) -> String {
+    let kept: String = s
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || c.is_ascii_punctuation())
+        .collect();
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn on_off(b: bool) -> &'static str {
+    if b {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn pct(used: u64, total: u64) -> u64 {
+    if total == 0 {
+        0
+    } else {
+        (used as f64 / total as f64 * 100.0).round() as u64
+    }
+}
+
+// One decimal at GB scale, whole megabytes below, so values don't flicker
+fn bytes(b: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    let fb = b as f64;
+    if fb >= GB {
+        format!("{:.1} gigabytes", fb / GB)
+    } else {
+        format!("{} megabytes", (fb / MB).round())
+    }
+}
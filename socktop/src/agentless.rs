@@ -0,0 +1,521 @@
+//! `--ssh user@host`: metrics from a host without socktop_agent, read from /proc over a plain
+//! SSH session (`ssh DEST sh`), for devices where no binary can be installed.
+//!
+//! `SshLink` answers the client's requests itself: each command runs a few `cat`s on the
+//! remote shell and `Collector` turns the output into the reply an agent would send, so the
+//! app gets the usual `Metrics`. The set is degraded: CPU, memory, swap, network, disk space
+//! (`df`), per-process CPU and memory, and the I/O panel. There is no GPU, temperature,
+//! history, or anything that needs a control token.
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::types::SCHEMA_VERSION;
+use crate::ws::AgentLink;
+
+// Section header lines in the remote output
+const MARK: &str = "@@socktop:";
+// A remote that stops answering counts as a dropped connection
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+// Opening may wait on a password or host key prompt
+const OPEN_TIMEOUT: Duration = Duration::from_secs(120);
+// Clock ticks per second (USER_HZ) and page size, as on every Linux socktop targets
+const MS_PER_TICK: u64 = 10;
+const PAGE_SIZE: u64 = 4096;
+
+/// `ssh://` URLs (from `--ssh`) select agentless collection.
+pub fn is_ssh_url(url: &str) -> bool {
+    url.starts_with("ssh://")
+}
+
+/// `--ssh DEST` as a URL: `user@host[:port]` -> `ssh://user@host[:port]`.
+pub fn ssh_url(dest: &str) -> String {
+    if is_ssh_url(dest) {
+        dest.to_string()
+    } else {
+        format!("ssh://{dest}")
+    }
+}
+
+// What each section runs on the remote; a missing file just leaves its section empty
+fn section_cmd(name: &str) -> &'static str {
+    match name {
+        "stat" => "cat /proc/stat",
+        "meminfo" => "cat /proc/meminfo",
+        "netdev" => "cat /proc/net/dev",
+        "diskstats" => "cat /proc/diskstats",
+        "pressure" => "cat /proc/pressure/io",
+        "hostname" => "cat /proc/sys/kernel/hostname",
+        "uptime" => "cat /proc/uptime",
+        "loadavg" => "cat /proc/loadavg",
+        "boot_id" => "cat /proc/sys/kernel/random/boot_id",
+        "pids" => "set -- /proc/[0-9]*; echo $#",
+        "procs" => "cat /proc/[0-9]*/stat",
+        "df" => "df -kP",
+        _ => "true",
+    }
+}
+
+/// Sections a request needs; None for requests agentless mode doesn't answer.
+pub fn sections_for(cmd: &str) -> Option<&'static [&'static str]> {
+    Some(match cmd.split('?').next().unwrap_or(cmd) {
+        "get_metrics" => &[
+            "stat", "meminfo", "netdev", "hostname", "uptime", "loadavg", "boot_id", "pids",
+        ],
+        "get_disks" => &["df"],
+        // The total tick count turns per-process ticks into a share of the host
+        "get_processes" => &["stat", "procs"],
+        "get_io" => &["stat", "diskstats", "pressure"],
+        _ => return None,
+    })
+}
+
+/// Shell input printing each section after its header line, then the end marker.
+pub fn script(sections: &[&str]) -> String {
+    let mut s = String::new();
+    for name in sections {
+        s.push_str(&format!(
+            "echo '{MARK}{name}'; {} 2>/dev/null\n",
+            section_cmd(name)
+        ));
+    }
+    s.push_str(&format!("echo '{MARK}end'\n"));
+    s
+}
+
+type Sections<'a> = HashMap<&'a str, String>;
+
+/// Remote output split at the header lines.
+pub fn split_sections(out: &str) -> Sections<'_> {
+    let mut map: Sections = HashMap::new();
+    let mut current: Option<&str> = None;
+    for line in out.lines() {
+        if let Some(name) = line.strip_prefix(MARK) {
+            current = Some(name);
+            map.entry(name).or_default();
+        } else if let Some(body) = current.and_then(|c| map.get_mut(c)) {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    map
+}
+
+// A section's text; empty when the remote printed nothing for it
+fn section<'a>(s: &'a Sections, name: &str) -> &'a str {
+    s.get(name).map_or("", String::as_str)
+}
+
+/// Tick counters of one `cpu` line of /proc/stat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTicks {
+    pub total: u64,
+    pub idle: u64,
+    pub iowait: u64,
+}
+
+/// /proc/stat: the aggregate `cpu` line first, then one per online core, plus
+/// (procs_running, procs_blocked).
+pub fn parse_stat(stat: &str) -> (Vec<CpuTicks>, Option<u32>, Option<u32>) {
+    let mut cpus = Vec::new();
+    let (mut running, mut blocked) = (None, None);
+    for line in stat.lines() {
+        let mut it = line.split_whitespace();
+        match it.next() {
+            Some(k) if k.starts_with("cpu") => {
+                let v: Vec<u64> = it.filter_map(|x| x.parse().ok()).collect();
+                // user nice system idle iowait irq softirq steal; guest is already in user
+                let field = |i: usize| v.get(i).copied().unwrap_or(0);
+                cpus.push(CpuTicks {
+                    total: (0..8).map(field).sum(),
+                    idle: field(3) + field(4),
+                    iowait: field(4),
+                });
+            }
+            Some("procs_running") => running = it.next().and_then(|x| x.parse().ok()),
+            Some("procs_blocked") => blocked = it.next().and_then(|x| x.parse().ok()),
+            _ => {}
+        }
+    }
+    (cpus, running, blocked)
+}
+
+// Busy share between two samples of the same counter, in percent
+fn busy_pct(prev: CpuTicks, cur: CpuTicks) -> f32 {
+    let total = cur.total.saturating_sub(prev.total);
+    if total == 0 {
+        return 0.0;
+    }
+    let idle = cur.idle.saturating_sub(prev.idle).min(total);
+    (total - idle) as f32 / total as f32 * 100.0
+}
+
+/// /proc/meminfo as (mem_total, mem_used, swap_total, swap_used) in bytes.
+pub fn parse_meminfo(meminfo: &str) -> (u64, u64, u64, u64) {
+    let kb: HashMap<&str, u64> = meminfo
+        .lines()
+        .filter_map(|l| {
+            let (k, v) = l.split_once(':')?;
+            Some((k, v.split_whitespace().next()?.parse::<u64>().ok()? * 1024))
+        })
+        .collect();
+    let get = |k: &str| kb.get(k).copied().unwrap_or(0);
+    let total = get("MemTotal");
+    // Kernels before 3.14 have no MemAvailable
+    let available = kb
+        .get("MemAvailable")
+        .copied()
+        .unwrap_or_else(|| get("MemFree") + get("Buffers") + get("Cached"));
+    (
+        total,
+        total.saturating_sub(available),
+        get("SwapTotal"),
+        get("SwapTotal").saturating_sub(get("SwapFree")),
+    )
+}
+
+/// The 1, 5 and 15 minute load averages from /proc/loadavg.
+pub fn parse_loadavg(loadavg: &str) -> Option<(f64, f64, f64)> {
+    let mut v = loadavg.split_whitespace().map(|x| x.parse::<f64>().ok());
+    Some((v.next()??, v.next()??, v.next()??))
+}
+
+/// /proc/net/dev as (interface, received bytes, transmitted bytes).
+pub fn parse_net_dev(dev: &str) -> Vec<(String, u64, u64)> {
+    dev.lines()
+        .filter_map(|l| {
+            let (name, rest) = l.split_once(':')?;
+            let v: Vec<u64> = rest
+                .split_whitespace()
+                .filter_map(|x| x.parse().ok())
+                .collect();
+            Some((name.trim().to_string(), *v.first()?, *v.get(8)?))
+        })
+        .collect()
+}
+
+/// `df -kP` rows for block devices as (filesystem, total bytes, available bytes).
+pub fn parse_df(df: &str) -> Vec<(String, u64, u64)> {
+    let mut out: Vec<(String, u64, u64)> = Vec::new();
+    for l in df.lines().skip(1) {
+        let f: Vec<&str> = l.split_whitespace().collect();
+        let (Some(fs), Some(total), Some(avail)) = (
+            f.first(),
+            f.get(1).and_then(|x| x.parse::<u64>().ok()),
+            f.get(3).and_then(|x| x.parse::<u64>().ok()),
+        ) else {
+            continue;
+        };
+        // Bind mounts list the same filesystem again
+        if fs.starts_with("/dev/") && !out.iter().any(|(n, ..)| n == fs) {
+            out.push((fs.to_string(), total * 1024, avail * 1024));
+        }
+    }
+    out
+}
+
+/// Sectors read and written, and ms with I/O in flight, of one disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskCounters {
+    pub read_sectors: u64,
+    pub write_sectors: u64,
+    pub io_ms: u64,
+}
+
+/// /proc/diskstats for whole devices: partitions and loop/ram devices are left out.
+pub fn parse_diskstats(stats: &str) -> Vec<(String, DiskCounters)> {
+    let rows: Vec<(String, DiskCounters)> = stats
+        .lines()
+        .filter_map(|l| {
+            let f: Vec<&str> = l.split_whitespace().collect();
+            let n = |i: usize| f.get(i).and_then(|x| x.parse::<u64>().ok());
+            Some((
+                f.get(2)?.to_string(),
+                DiskCounters {
+                    read_sectors: n(5)?,
+                    write_sectors: n(9)?,
+                    io_ms: n(12)?,
+                },
+            ))
+        })
+        .filter(|(name, _)| !name.starts_with("loop") && !name.starts_with("ram"))
+        .collect();
+    // sda1 of sda, nvme0n1p1 of nvme0n1, mmcblk0p1 of mmcblk0
+    let is_partition = |name: &str| {
+        rows.iter().any(|(base, _)| {
+            name.strip_prefix(base.as_str()).is_some_and(|rest| {
+                let digits = rest.strip_prefix('p').unwrap_or(rest);
+                !rest.is_empty() && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+            })
+        })
+    };
+    rows.iter()
+        .filter(|(name, _)| !is_partition(name))
+        .cloned()
+        .collect()
+}
+
+/// /proc/pressure/io as the `pressure` object of `get_io`; None without PSI.
+pub fn parse_pressure(psi: &str) -> Option<Value> {
+    let avg = |kind: &str, key: &str| -> Option<f32> {
+        let line = psi.lines().find(|l| l.starts_with(kind))?;
+        line.split_whitespace()
+            .find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))?
+            .parse()
+            .ok()
+    };
+    Some(json!({
+        "some_avg10": avg("some", "avg10")?,
+        "some_avg60": avg("some", "avg60")?,
+        "full_avg10": avg("full", "avg10").unwrap_or(0.0),
+        "full_avg60": avg("full", "avg60").unwrap_or(0.0),
+    }))
+}
+
+/// `/proc/PID/stat` lines as (pid, name, utime + stime ticks, resident bytes).
+pub fn parse_proc_stats(stats: &str) -> Vec<(u32, String, u64, u64)> {
+    stats
+        .lines()
+        .filter_map(|l| {
+            let (pid, rest) = l.split_once(" (")?;
+            // The name may itself contain ") ", so split at the last one
+            let (name, rest) = rest.rsplit_once(") ")?;
+            let f: Vec<&str> = rest.split_whitespace().collect();
+            // Fields from 3 (state) on: utime is 14, stime 15, rss 24
+            let n = |field: usize| f.get(field - 3).and_then(|x| x.parse::<u64>().ok());
+            Some((
+                pid.trim().parse().ok()?,
+                name.to_string(),
+                n(14)? + n(15)?,
+                n(24)? * PAGE_SIZE,
+            ))
+        })
+        .collect()
+}
+
+/// Turns remote output into agent replies; keeps the previous counters for rates.
+#[derive(Debug, Default)]
+pub struct Collector {
+    cpu: Vec<CpuTicks>,
+    // Process ticks by pid, with the host's total ticks when they were read
+    procs: HashMap<u32, u64>,
+    procs_total: Option<u64>,
+    io: Option<(Instant, CpuTicks, HashMap<String, DiskCounters>)>,
+}
+
+impl Collector {
+    /// JSON reply to `cmd` from its script's output; None for requests not answered here.
+    pub fn reply(&mut self, cmd: &str, out: &str, now: Instant) -> Option<String> {
+        let s = split_sections(out);
+        let v = match cmd.split('?').next().unwrap_or(cmd) {
+            "get_metrics" => self.metrics(&s),
+            "get_disks" => Value::Array(
+                parse_df(section(&s, "df"))
+                    .into_iter()
+                    .map(|(name, total, available)| {
+                        json!({ "name": name, "total": total, "available": available })
+                    })
+                    .collect(),
+            ),
+            "get_processes" => self.processes(&s),
+            "get_io" => self.io(&s, now),
+            _ => return None,
+        };
+        Some(v.to_string())
+    }
+
+    fn metrics(&mut self, s: &Sections) -> Value {
+        let get = |k: &str| section(s, k);
+        let (cpus, running, blocked) = parse_stat(get("stat"));
+        // First sample: no previous counters, so 0% until the next one
+        let pct = |i: usize| {
+            let prev = self.cpu.get(i).copied().unwrap_or(cpus[i]);
+            busy_pct(prev, cpus[i])
+        };
+        let cpu_total = if cpus.is_empty() { 0.0 } else { pct(0) };
+        let per_core: Vec<f32> = (1..cpus.len()).map(pct).collect();
+        self.cpu = cpus;
+        let (mem_total, mem_used, swap_total, swap_used) = parse_meminfo(get("meminfo"));
+        let networks: Vec<Value> = parse_net_dev(get("netdev"))
+            .into_iter()
+            .map(|(name, rx, tx)| json!({ "name": name, "received": rx, "transmitted": tx }))
+            .collect();
+        let first_word = |k: &str| get(k).split_whitespace().next().map(str::to_string);
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "cpu_total": cpu_total,
+            "cpu_per_core": per_core,
+            "procs_running": running,
+            "procs_blocked": blocked,
+            "mem_total": mem_total,
+            "mem_used": mem_used,
+            "swap_total": swap_total,
+            "swap_used": swap_used,
+            "hostname": get("hostname").trim(),
+            "uptime_secs": first_word("uptime").and_then(|u| u.parse::<f64>().ok()).map(|u| u as u64),
+            "load_avg": parse_loadavg(get("loadavg")),
+            "boot_id": first_word("boot_id"),
+            "cpu_temp_c": null,
+            "disks": [],
+            "networks": networks,
+            "top_processes": [],
+            "gpus": null,
+            "process_count": first_word("pids").and_then(|n| n.parse::<usize>().ok()),
+            "capabilities": ["io"],
+        })
+    }
+
+    fn processes(&mut self, s: &Sections) -> Value {
+        let total = parse_stat(section(s, "stat")).0.first().map(|c| c.total);
+        let dt = total
+            .zip(self.procs_total)
+            .map_or(0, |(now, prev)| now.saturating_sub(prev));
+        let rows = parse_proc_stats(section(s, "procs"));
+        let mut ticks = HashMap::with_capacity(rows.len());
+        let top: Vec<Value> = rows
+            .into_iter()
+            .map(|(pid, name, t, rss)| {
+                // Share of all cores, like the agent; a reused pid starts over
+                let du = self.procs.get(&pid).map_or(0, |&p| t.saturating_sub(p));
+                let cpu = if dt > 0 {
+                    (du as f32 / dt as f32 * 100.0).clamp(0.0, 100.0)
+                } else {
+                    0.0
+                };
+                ticks.insert(pid, t);
+                json!({
+                    "pid": pid,
+                    "name": name,
+                    "cpu_usage": cpu,
+                    "mem_bytes": rss,
+                    "cpu_time_ms": t * MS_PER_TICK,
+                })
+            })
+            .collect();
+        self.procs = ticks;
+        self.procs_total = total;
+        json!({ "process_count": top.len(), "top_processes": top })
+    }
+
+    fn io(&mut self, s: &Sections, now: Instant) -> Value {
+        let (cpus, _, blocked) = parse_stat(section(s, "stat"));
+        let cpu = cpus.first().copied().unwrap_or_default();
+        let disks: HashMap<String, DiskCounters> = parse_diskstats(section(s, "diskstats"))
+            .into_iter()
+            .collect();
+        let pressure = parse_pressure(section(s, "pressure"));
+        // Like the agent: the first reply has no interval and no rates
+        let Some((at, prev_cpu, prev_disks)) = self.io.replace((now, cpu, disks.clone())) else {
+            return json!({
+                "interval_ms": null,
+                "pressure": pressure,
+                "procs_blocked": blocked,
+                "disks": [],
+                "top": [],
+            });
+        };
+        let ms = now.duration_since(at).as_millis().max(1) as u64;
+        let total = cpu.total.saturating_sub(prev_cpu.total);
+        let iowait = (total > 0)
+            .then(|| cpu.iowait.saturating_sub(prev_cpu.iowait) as f32 / total as f32 * 100.0);
+        let mut rows: Vec<(&String, &DiskCounters)> = disks.iter().collect();
+        rows.sort_by(|a, b| a.0.cmp(b.0));
+        let disks: Vec<Value> = rows
+            .into_iter()
+            .filter_map(|(name, c)| {
+                let p = prev_disks.get(name)?;
+                let per_sec = |d: u64| d * 512 * 1000 / ms;
+                Some(json!({
+                    "name": name,
+                    "util_pct": (c.io_ms.saturating_sub(p.io_ms) as f32 / ms as f32 * 100.0).min(100.0),
+                    "read_bps": per_sec(c.read_sectors.saturating_sub(p.read_sectors)),
+                    "write_bps": per_sec(c.write_sectors.saturating_sub(p.write_sectors)),
+                }))
+            })
+            .collect();
+        json!({
+            "interval_ms": ms,
+            "pressure": pressure,
+            "procs_blocked": blocked,
+            "iowait_pct": iowait,
+            "disks": disks,
+            "top": [],
+        })
+    }
+}
+
+/// A remote shell over `ssh` standing in for an agent connection.
+pub struct SshLink {
+    // Killed when the link is dropped
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    collector: Collector,
+}
+
+impl SshLink {
+    // Send a script and collect its output up to the end marker
+    async fn run(&mut self, script: &str, wait: Duration) -> Option<String> {
+        self.stdin.write_all(script.as_bytes()).await.ok()?;
+        self.stdin.flush().await.ok()?;
+        let end = format!("{MARK}end");
+        let read = async {
+            let mut out = String::new();
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if self.stdout.read_line(&mut line).await.ok()? == 0 {
+                    return None;
+                }
+                if line.trim_end() == end {
+                    return Some(out);
+                }
+                out.push_str(&line);
+            }
+        };
+        timeout(wait, read).await.ok()?
+    }
+}
+
+impl AgentLink for SshLink {
+    async fn open(url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        // ssh takes ssh://user@host:port destinations as they are
+        let mut child = Command::new("ssh")
+            .args(["-T", "-o", "ServerAliveInterval=15", url, "sh"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("cannot run ssh: {e}"))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err("ssh has no stdin/stdout".into());
+        };
+        let mut link = SshLink {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            collector: Collector::default(),
+        };
+        let dest = url.trim_start_matches("ssh://");
+        link.run(&script(&[]), OPEN_TIMEOUT)
+            .await
+            .ok_or_else(|| format!("no shell on {dest} (try `ssh {dest}` to see why)"))?;
+        Ok(link)
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        let sections = sections_for(cmd)?;
+        let out = self.run(&script(sections), REPLY_TIMEOUT).await?;
+        self.collector
+            .reply(cmd, &out, Instant::now())
+            .map(Message::Text)
+    }
+}
@@ -0,0 +1,363 @@
+//! Alert transition log: records when a condition fires and clears, persisted as JSON lines so the
+//! history survives restarts. Timestamps come from the caller (wall clock live, sample time on replay).
+//!
+//! Threshold alerts (CPU, memory, swap and disk use, CPU temperature) go through `Hysteresis` first: a condition fires only
+//! after it held for `for_secs`, and clears only once the value fell below a lower `clear_below`
+//! mark, so a value hovering around the limit doesn't flap. Limits come from
+//! $XDG_CONFIG_HOME/socktop/alerts.json (fallback ~/.config/socktop/alerts.json):
+//! `{"mem": {"above": 90, "clear_below": 85, "for_secs": 120}, "swap": null}`. A profile's
+//! `alerts` section replaces that file for its host.
+//!
+//! Besides the log, a firing alert can ring the terminal bell, flash its panel's border and stay
+//! on the status line until it clears, chosen per severity under `sinks`:
+//! `"sinks": {"critical": {"bell": true, "flash": true, "status": true}, "warning": {"status": true}}`
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Events kept in memory / rewritten on open when the file grows past twice this
+const KEEP: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    Fired,
+    Cleared,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    /// Unix seconds
+    pub at: u64,
+    pub host: String,
+    pub key: String,
+    pub transition: Transition,
+    pub message: String,
+    /// Recorded during a maintenance window (no notification)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub silenced: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct AlertLog {
+    events: VecDeque<AlertEvent>,
+    // Keys of currently firing alerts
+    active: BTreeSet<String>,
+    path: Option<PathBuf>,
+    // Maintenance mode: transitions before this Unix time are logged as silenced
+    silenced_until: Option<u64>,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl AlertLog {
+    /// Log backed by a JSON-lines file; previous events are loaded (newest `KEEP`).
+    pub fn open(path: &Path) -> Self {
+        let mut log = AlertLog {
+            path: Some(path.to_path_buf()),
+            ..Default::default()
+        };
+        let Ok(s) = fs::read_to_string(path) else {
+            return log;
+        };
+        let all: Vec<AlertEvent> = s
+            .lines()
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect();
+        let start = all.len().saturating_sub(KEEP);
+        log.events = all[start..].iter().cloned().collect();
+        if all.len() > KEEP * 2 {
+            let body: String = log
+                .events
+                .iter()
+                .filter_map(|e| serde_json::to_string(e).ok())
+                .map(|l| l + "\n")
+                .collect();
+            let _ = fs::write(path, body);
+        }
+        log
+    }
+
+    /// Update a condition; only fired/cleared transitions are recorded. Returns true on a transition.
+    pub fn set(&mut self, at: u64, host: &str, key: &str, firing: bool, message: &str) -> bool {
+        if firing == self.active.contains(key) {
+            return false;
+        }
+        let transition = if firing {
+            self.active.insert(key.to_string());
+            Transition::Fired
+        } else {
+            self.active.remove(key);
+            Transition::Cleared
+        };
+        self.push(AlertEvent {
+            at,
+            host: host.to_string(),
+            key: key.to_string(),
+            transition,
+            message: message.to_string(),
+            silenced: self.silenced_at(at),
+        });
+        true
+    }
+
+    fn push(&mut self, ev: AlertEvent) {
+        if let Some(path) = &self.path {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            if let (Ok(mut f), Ok(line)) = (
+                OpenOptions::new().create(true).append(true).open(path),
+                serde_json::to_string(&ev),
+            ) {
+                let _ = writeln!(f, "{line}");
+            }
+        }
+        if self.events.len() == KEEP {
+            self.events.pop_front();
+        }
+        self.events.push_back(ev);
+    }
+
+    pub fn silence_until(&mut self, until: Option<u64>) {
+        self.silenced_until = until;
+    }
+
+    /// End of the maintenance window if it is still running at `now`.
+    pub fn silenced_until(&self, now: u64) -> Option<u64> {
+        self.silenced_until.filter(|&u| now < u)
+    }
+
+    pub fn silenced_at(&self, now: u64) -> bool {
+        self.silenced_until(now).is_some()
+    }
+
+    /// Oldest first.
+    pub fn events(&self) -> impl DoubleEndedIterator<Item = &AlertEvent> {
+        self.events.iter()
+    }
+}
+
+/// Fire when the value stays above `above` for `for_secs`; clear when it drops below
+/// `clear_below` (at most `above`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub above: f64,
+    #[serde(default)]
+    pub clear_below: Option<f64>,
+    #[serde(default)]
+    pub for_secs: u64,
+}
+
+impl Threshold {
+    pub fn new(above: f64, clear_below: f64, for_secs: u64) -> Self {
+        Threshold {
+            above,
+            clear_below: Some(clear_below),
+            for_secs,
+        }
+    }
+
+    fn clear_below(&self) -> f64 {
+        self.clear_below.map_or(self.above, |c| c.min(self.above))
+    }
+}
+
+/// How loud an alert is; the sinks are chosen per severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// Losing the agent, running out of memory or disk space, a pegged or overheating CPU are
+    /// critical; everything else is a warning.
+    pub fn of(key: &str) -> Self {
+        match key {
+            "disconnected" | "mem_high" | "disk_full" | "cpu_high" | "temp_high" => {
+                Severity::Critical
+            }
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// Where a firing alert is announced besides the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Sinks {
+    /// Terminal bell (BEL)
+    #[serde(default)]
+    pub bell: bool,
+    /// Border of the alert's panel (the header for host-wide alerts) drawn in the alert color
+    #[serde(default)]
+    pub flash: bool,
+    /// Message on the status line until the alert clears
+    #[serde(default)]
+    pub status: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SinkRules {
+    #[serde(default = "default_critical_sinks")]
+    pub critical: Sinks,
+    // Warnings already show up in the header: quiet by default
+    #[serde(default)]
+    pub warning: Sinks,
+}
+
+fn default_critical_sinks() -> Sinks {
+    Sinks {
+        bell: false,
+        flash: true,
+        status: true,
+    }
+}
+
+impl Default for SinkRules {
+    fn default() -> Self {
+        SinkRules {
+            critical: default_critical_sinks(),
+            warning: Sinks::default(),
+        }
+    }
+}
+
+impl SinkRules {
+    pub fn for_severity(&self, severity: Severity) -> Sinks {
+        match severity {
+            Severity::Critical => self.critical,
+            Severity::Warning => self.warning,
+        }
+    }
+}
+
+/// Threshold alerts evaluated on every metrics sample (percent of total, °C for `temp`); None
+/// disables one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AlertRules {
+    #[serde(default = "default_cpu")]
+    pub cpu: Option<Threshold>,
+    #[serde(default = "default_mem")]
+    pub mem: Option<Threshold>,
+    #[serde(default = "default_swap")]
+    pub swap: Option<Threshold>,
+    /// Fullest disk
+    #[serde(default = "default_disk")]
+    pub disk: Option<Threshold>,
+    /// CPU package temperature
+    #[serde(default = "default_temp")]
+    pub temp: Option<Threshold>,
+    #[serde(default)]
+    pub sinks: SinkRules,
+}
+
+fn default_cpu() -> Option<Threshold> {
+    Some(Threshold::new(90.0, 80.0, 30))
+}
+
+fn default_mem() -> Option<Threshold> {
+    Some(Threshold::new(90.0, 85.0, 120))
+}
+
+fn default_swap() -> Option<Threshold> {
+    Some(Threshold::new(50.0, 40.0, 120))
+}
+
+fn default_disk() -> Option<Threshold> {
+    Some(Threshold::new(95.0, 90.0, 0))
+}
+
+fn default_temp() -> Option<Threshold> {
+    Some(Threshold::new(85.0, 80.0, 0))
+}
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        AlertRules {
+            cpu: default_cpu(),
+            mem: default_mem(),
+            swap: default_swap(),
+            disk: default_disk(),
+            temp: default_temp(),
+            sinks: SinkRules::default(),
+        }
+    }
+}
+
+impl AlertRules {
+    /// Rules from `path`; the defaults when it is missing, with a warning when it doesn't parse.
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(s) = fs::read_to_string(path) else {
+            return AlertRules::default();
+        };
+        serde_json::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("socktop: ignoring {}: {e}", path.display());
+            AlertRules::default()
+        })
+    }
+}
+
+/// Debounced state of one threshold condition.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hysteresis {
+    firing: bool,
+    // When the value first crossed `above` while not firing
+    breached_since: Option<u64>,
+}
+
+impl Hysteresis {
+    /// Feed a sample taken at `now` (Unix secs) and return whether the alert is firing.
+    /// A missing value (no swap, metric unavailable) clears it.
+    pub fn update(&mut self, rule: &Threshold, now: u64, value: Option<f64>) -> bool {
+        let Some(v) = value else {
+            *self = Hysteresis::default();
+            return false;
+        };
+        if self.firing {
+            if v < rule.clear_below() {
+                self.firing = false;
+            }
+        } else if v > rule.above {
+            let since = *self.breached_since.get_or_insert(now);
+            self.firing = now.saturating_sub(since) >= rule.for_secs;
+        } else {
+            self.breached_since = None;
+        }
+        if self.firing {
+            self.breached_since = None;
+        }
+        self.firing
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (UTC) for a Unix timestamp.
+pub fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (Howard Hinnant)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!(
+        "{y:04}-{m:02}-{d:02} {:02}:{:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,106 @@
+//! Embedding API: connect to an agent and read typed metrics, once or as a stream, without the
+//! TUI. This is the surface other tools (statusbars, multiplexer plugins) should use; the rest
+//! of the crate is shared with the `socktop` binary and may change between releases.
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//! use std::time::Duration;
+//!
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//! let conn = socktop::api::connect("ws://10.0.0.5:3000/ws", None).await?;
+//! let mut metrics = conn.subscribe(Duration::from_secs(2));
+//! while let Some(m) = metrics.next().await {
+//!     println!("{}: {:.0}% CPU", m.hostname, m.cpu_total);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every URL the binary accepts works: `ws://`/`wss://` (falling back to HTTP polling),
+//! `ssh://` (agentless), `pipe://` on Windows and `https://` with the `webtransport` feature.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::stream::{self, Stream};
+use tokio::time::{interval, MissedTickBehavior};
+
+pub use crate::types::{DiskInfo, GpuInfo, Metrics, NetworkInfo, ProcessInfo, ProcessesPayload};
+use crate::ws::{request_disks, request_metrics, request_processes, AgentLink, Link};
+
+/// An open agent connection.
+pub struct Connection {
+    url: String,
+    tls_ca: Option<String>,
+    link: Link,
+}
+
+/// Connect to the agent at `url`; `tls_ca` is the agent's certificate for `wss://`.
+pub async fn connect(
+    url: &str,
+    tls_ca: Option<&str>,
+) -> Result<Connection, Box<dyn std::error::Error>> {
+    Ok(Connection {
+        url: url.to_string(),
+        tls_ca: tls_ca.map(str::to_string),
+        link: Link::open(url, tls_ca).await?,
+    })
+}
+
+impl Connection {
+    /// One metrics sample; None when the connection dropped or the reply didn't parse.
+    pub async fn metrics(&mut self) -> Option<Metrics> {
+        request_metrics(&mut self.link).await
+    }
+
+    /// The process list (all processes, unsorted).
+    pub async fn processes(&mut self) -> Option<ProcessesPayload> {
+        request_processes(&mut self.link).await
+    }
+
+    pub async fn disks(&mut self) -> Option<Vec<DiskInfo>> {
+        request_disks(&mut self.link).await
+    }
+
+    /// Reopen the connection to the same URL.
+    pub async fn reconnect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.link = Link::open(&self.url, self.tls_ca.as_deref()).await?;
+        Ok(())
+    }
+
+    /// A sample every `every`. A dropped connection is reopened once; the stream ends when
+    /// that fails too.
+    pub fn subscribe(self, every: Duration) -> MetricsSubscription {
+        let mut tick = interval(every.max(Duration::from_millis(1)));
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let inner = stream::unfold((self, tick), |(mut conn, mut tick)| async move {
+            tick.tick().await;
+            let m = match conn.metrics().await {
+                Some(m) => m,
+                None => {
+                    conn.reconnect().await.ok()?;
+                    conn.metrics().await?
+                }
+            };
+            Some((m, (conn, tick)))
+        });
+        MetricsSubscription {
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+/// Stream of metrics samples from `Connection::subscribe`. Not `Send`: poll it from the task
+/// that created it.
+pub struct MetricsSubscription {
+    inner: Pin<Box<dyn Stream<Item = Metrics>>>,
+}
+
+impl Stream for MetricsSubscription {
+    type Item = Metrics;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Metrics>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -1,36 +1,84 @@
 //! App state and main loop: input handling, fetching metrics, updating history, and drawing.
 
 use std::{
-    collections::VecDeque,
-    io,
+    collections::{BTreeMap, VecDeque},
+    io::{self, Write},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Rect},
+    layout::Rect,
     //style::Color, // + add Color
     Terminal,
 };
 use tokio::time::sleep;
 
-use crate::history::{push_capped, PerCoreHistory};
-use crate::types::Metrics;
+use crate::a11y::{a11y_lines, A11yStatus};
+use crate::alerts::{format_utc, now_secs, AlertLog, AlertRules, Hysteresis, Severity};
+use crate::budget::{BandwidthBudget, BUDGET_TOP_K};
+use crate::derived::{sample_vars, DerivedMetric};
+use crate::disk_mirror::DiskMirror;
+use crate::focus::FocusThrottle;
+use crate::frame_budget::FrameBudget;
+use crate::governor::{GovernorOutcome, GovernorPicker};
+use crate::history::{expand_history, now_millis, push_capped, stable_core_rows, PerCoreHistory};
+use crate::keymap::{action_for, Action};
+use crate::layout::{Layout, PanelAreas};
+use crate::mark::Mark;
+use crate::palette::{Palette, PaletteOutcome};
+use crate::peers::{switch_url, PeerOutcome, PeerPicker};
+use crate::proc_sample::{TailSampler, SAMPLE_ABOVE, SAMPLE_TOP_K};
+use crate::proc_trend::ProcTrends;
+use crate::rate::RateCounter;
+use crate::reconnect::Backoff;
+use crate::recording;
+use crate::replay::{Playback, ReplayHandle, ReplayLink};
+use crate::sanity::{check_disks, check_metrics, check_processes, Suspect};
+use crate::search::{find, matches, HitKind, Search, SearchOutcome};
+use crate::session::{HostIdentity, IdentityChange};
+use crate::types::{AgentHistory, CpuFreq, Metrics, ProcessesPayload, Scope};
+use crate::ui::cache::{debug_key, key_of, PanelCache};
 use crate::ui::cpu::{
-    draw_cpu_avg_graph, draw_per_core_bars, per_core_clamp, per_core_content_area,
+    cpu_avg_title, draw_cpu_avg_graph, draw_per_core_bars, per_core_clamp, per_core_content_area,
     per_core_handle_key, per_core_handle_mouse, per_core_handle_scrollbar_mouse, PerCoreScrollDrag,
 };
-use crate::ui::processes::{processes_handle_key, processes_handle_mouse, ProcSortBy};
+use crate::ui::io::{draw_io_panel, IoPanel};
+use crate::ui::proc_detail::{draw_process_detail, ProcessDetail};
+use crate::ui::processes::{
+    processes_handle_key, processes_handle_mouse, processes_row_at, visible_rows, ProcSortBy,
+};
+use crate::ui::raster::{draw_raster_panel, Protocol, RasterCharts};
 use crate::ui::{
     disks::draw_disks, gpu::draw_gpu, header::draw_header, mem::draw_mem, net::draw_net_spark,
-    swap::draw_swap,
+    swap::draw_swap, theme::theme,
+};
+use crate::ws::{
+    request_cpufreq, request_disks, request_disks_delta, request_hello, request_history,
+    request_io, request_metrics, request_peers, request_process_env, request_process_fds,
+    request_processes, request_processes_sampled, request_processes_top, request_set_governor,
+    AgentLink, Push,
 };
-use crate::ws::{connect, request_disks, request_metrics, request_processes};
+
+// Entropy estimate (bits) below which headless boxes stall on blocking RNG reads
+const ENTROPY_WARN_BITS: u32 = 200;
+// --a11y: minimum time between redraws of changed text (keypresses redraw immediately)
+const A11Y_REDRAW: Duration = Duration::from_secs(5);
+// How long an alert's panel border stays highlighted (`flash` sink)
+const ALERT_FLASH: Duration = Duration::from_secs(2);
+// How long "copied ..." stays in the header
+const COPY_NOTICE: Duration = Duration::from_secs(4);
+// Fraction of fs.file-max in use that triggers the file handle warning
+const FD_WARN_RATIO: f64 = 0.9;
 
 pub struct App {
     // Latest metrics + histories
@@ -43,11 +91,16 @@ pub struct App {
     per_core_hist: PerCoreHistory,
 
     // Network totals snapshot + histories of KB/s
-    last_net_totals: Option<(u64, u64, Instant)>,
+    rx_rate: RateCounter,
+    tx_rate: RateCounter,
     rx_hist: VecDeque<u64>,
     tx_hist: VecDeque<u64>,
     rx_peak: u64,
     tx_peak: u64,
+    // Unix ms of each cpu/rx/tx sample (same length as those deques)
+    sample_ts: VecDeque<u64>,
+    // Chart inspection ('x'): crosshair position in samples before the newest
+    inspect: Option<usize>,
 
     // Quit flag
     should_quit: bool,
@@ -57,6 +110,8 @@ pub struct App {
     pub procs_scroll_offset: usize,
     pub procs_drag: Option<PerCoreScrollDrag>,
     pub procs_sort_by: ProcSortBy,
+    // Selected process row, pinned by PID across refreshes/re-sorts (click to select)
+    pub procs_selected: Option<u32>,
     last_procs_area: Option<ratatui::layout::Rect>,
 
     last_procs_poll: Instant,
@@ -67,6 +122,94 @@ pub struct App {
 
     // For reconnects
     ws_url: String,
+    tls_ca: Option<String>,
+    // Identity of the host seen before the last reconnect (hostname + uptime)
+    host_identity: Option<HostIdentity>,
+    reconnected: bool,
+    host_notice: Option<String>,
+
+    // Debug overlay (wire stats) toggle + bandwidth estimate
+    show_debug: bool,
+    frame_budget: FrameBudget,
+    panel_cache: PanelCache,
+    focus: FocusThrottle,
+    // Alert transitions (fired/cleared) + panel toggle
+    alerts: AlertLog,
+    show_alerts: bool,
+    // Memory/swap threshold alerts and their debounced state
+    alert_rules: AlertRules,
+    cpu_alert: Hysteresis,
+    mem_alert: Hysteresis,
+    swap_alert: Hysteresis,
+    disk_alert: Hysteresis,
+    temp_alert: Hysteresis,
+    // Alert sinks: firing alerts kept on the status line, and the panel border being flashed
+    alert_status: BTreeMap<String, String>,
+    alert_flash: Option<(String, Instant)>,
+    // Outcome of the last clipboard copy, shown in the header for a moment
+    copy_notice: Option<(String, Instant)>,
+    // Per-core irq/softirq mini bars ('i')
+    show_irq: bool,
+    // Reboot-required detail popup ('r')
+    show_reboot: bool,
+    // Fill charts from the agent's get_history before the next live sample (new session / host)
+    prefill_pending: bool,
+    // Token scope from the agent's `hello` (None: not asked yet, or an agent without it)
+    scope: Option<Scope>,
+    // Agent session from `hello`, offered back for resumption after a reconnect
+    session_id: Option<String>,
+    // Disk list maintained from get_disks?delta replies
+    disk_mirror: DiskMirror,
+    // Fields clamped by the sanity checks in the latest payloads
+    suspect: Suspect,
+    hello_pending: bool,
+    // Agent pushes samples (`subscribe`); None until tried on this connection
+    subscribed: Option<bool>,
+    // Connection lost: when to try again (None while connected)
+    reconnect: Option<Backoff>,
+    // --replay: the recording played back instead of an agent
+    replay: Option<ReplayHandle>,
+    // Maintenance mode ('m'): silence window length and the profile it is saved to
+    profile: Option<String>,
+    silence_minutes: u64,
+    wire_rate: RateCounter,
+    wire_bps: f64,
+    // --max-bandwidth: stretches poll intervals to stay under a byte rate
+    budget: Option<BandwidthBudget>,
+    // User-defined expression panels (derived.json)
+    derived: Vec<DerivedMetric>,
+    // Screen-reader text mode (--a11y) + the text last put on screen
+    a11y: bool,
+    // --plain: no inline bar glyphs in table cells
+    plain: bool,
+    // --graphics: CPU/net history as kitty or sixel images instead of sparklines
+    raster: Option<RasterCharts>,
+    // Ctrl-P command palette while open
+    palette: Option<Palette>,
+    // '/' search popup while open; the last query highlights matches after it closes
+    search: Option<Search>,
+    search_query: Option<String>,
+    // Disk card to keep in view (last search jump)
+    disk_focus: Option<String>,
+    // Diff mode: the marked moment ('b') and whether the changes popup is open ('v')
+    mark: Option<Mark>,
+    show_mark_diff: bool,
+    // Peer switcher ('f') while open, and the peer chosen in it (reconnected to next loop)
+    peers: Option<PeerPicker>,
+    peer_switch: Option<String>,
+    // CPU governor popup ('g')
+    governor: Option<GovernorPicker>,
+    // Env/open-files popup for the selected process (Enter)
+    detail: Option<ProcessDetail>,
+    // Long-tail rows kept between sampled process polls
+    tail: TailSampler,
+    // Recent CPU % per PID for the rows' trend glyphs
+    proc_trends: ProcTrends,
+    // Panels shown and the maximized one (profile `layout`/`panels`)
+    layout: Layout,
+    // I/O wait panel ('o'), refreshed with each metrics sample while open
+    io: Option<IoPanel>,
+    a11y_shown: Option<(Instant, Vec<String>)>,
     // Security / status flags
     pub is_tls: bool,
     pub has_token: bool,
@@ -78,17 +221,21 @@ impl App {
             last_metrics: None,
             cpu_hist: VecDeque::with_capacity(600),
             per_core_hist: PerCoreHistory::new(60),
-            last_net_totals: None,
+            rx_rate: RateCounter::new(),
+            tx_rate: RateCounter::new(),
             rx_hist: VecDeque::with_capacity(600),
             tx_hist: VecDeque::with_capacity(600),
             rx_peak: 0,
             tx_peak: 0,
+            sample_ts: VecDeque::with_capacity(600),
+            inspect: None,
             should_quit: false,
             per_core_scroll: 0,
             per_core_drag: None,
             procs_scroll_offset: 0,
             procs_drag: None,
             procs_sort_by: ProcSortBy::CpuDesc,
+            procs_selected: None,
             last_procs_area: None,
             last_procs_poll: Instant::now()
                 .checked_sub(Duration::from_secs(2))
@@ -100,6 +247,60 @@ impl App {
             disks_interval: Duration::from_secs(5),
             metrics_interval: Duration::from_millis(500),
             ws_url: String::new(),
+            tls_ca: None,
+            host_identity: None,
+            reconnected: false,
+            host_notice: None,
+            show_debug: false,
+            frame_budget: FrameBudget::default(),
+            panel_cache: PanelCache::default(),
+            focus: FocusThrottle::default(),
+            alerts: AlertLog::default(),
+            show_alerts: false,
+            alert_rules: AlertRules::default(),
+            cpu_alert: Hysteresis::default(),
+            mem_alert: Hysteresis::default(),
+            swap_alert: Hysteresis::default(),
+            disk_alert: Hysteresis::default(),
+            temp_alert: Hysteresis::default(),
+            alert_status: BTreeMap::new(),
+            alert_flash: None,
+            copy_notice: None,
+            show_irq: false,
+            show_reboot: false,
+            prefill_pending: true,
+            scope: None,
+            session_id: None,
+            disk_mirror: DiskMirror::default(),
+            suspect: Suspect::default(),
+            hello_pending: true,
+            subscribed: None,
+            reconnect: None,
+            replay: None,
+            profile: None,
+            silence_minutes: 60,
+            wire_rate: RateCounter::new(),
+            wire_bps: 0.0,
+            budget: None,
+            derived: Vec::new(),
+            a11y: false,
+            plain: false,
+            raster: None,
+            palette: None,
+            search: None,
+            search_query: None,
+            disk_focus: None,
+            mark: None,
+            show_mark_diff: false,
+            peers: None,
+            governor: None,
+            peer_switch: None,
+            detail: None,
+            tail: TailSampler::default(),
+            proc_trends: ProcTrends::default(),
+            layout: Layout::default(),
+            io: None,
+            a11y_shown: None,
             is_tls: false,
             has_token: false,
         }
@@ -121,6 +322,74 @@ impl App {
         self
     }
 
+    pub fn with_alert_log(mut self, log: AlertLog) -> Self {
+        self.alerts = log;
+        self
+    }
+
+    pub fn with_alert_rules(mut self, rules: AlertRules) -> Self {
+        self.alert_rules = rules;
+        self
+    }
+
+    pub fn with_maintenance(
+        mut self,
+        profile: Option<String>,
+        silenced_until: Option<u64>,
+        minutes: Option<u64>,
+    ) -> Self {
+        self.profile = profile;
+        self.alerts.silence_until(silenced_until);
+        if let Some(m) = minutes.filter(|m| *m > 0) {
+            self.silence_minutes = m;
+        }
+        self
+    }
+
+    pub fn with_unfocused_poll(mut self, ms: Option<u64>) -> Self {
+        self.focus = FocusThrottle::new(ms.map(Duration::from_millis));
+        self
+    }
+
+    pub fn with_a11y(mut self, on: bool) -> Self {
+        self.a11y = on;
+        self
+    }
+
+    pub fn with_plain(mut self, on: bool) -> Self {
+        self.plain = on;
+        self
+    }
+
+    pub fn with_graphics(mut self, proto: Option<Protocol>) -> Self {
+        self.raster = proto.map(RasterCharts::new);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_derived(mut self, derived: Vec<DerivedMetric>) -> Self {
+        self.derived = derived;
+        self
+    }
+
+    pub fn with_bandwidth_budget(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.budget = bytes_per_sec.map(|b| {
+            BandwidthBudget::new(
+                b,
+                [
+                    self.metrics_interval,
+                    self.procs_interval,
+                    self.disks_interval,
+                ],
+            )
+        });
+        self
+    }
+
     pub async fn run(
         &mut self,
         url: &str,
@@ -129,12 +398,37 @@ impl App {
         // Connect to agent
         //let mut ws = connect(url, tls_ca).await?;
         self.ws_url = url.to_string();
-        let mut ws = connect(url, tls_ca).await?;
+        self.tls_ca = tls_ca.map(str::to_string);
+        crate::crash::set_url(url);
+        // ws:// (or its HTTP polling fallback), ssh://, pipe:// or https:// by URL
+        let link = crate::ws::Link::open(url, tls_ca).await?;
+        self.run_with(link).await
+    }
 
+    /// Play a `--record` file back instead of connecting.
+    pub async fn run_replay(
+        &mut self,
+        playback: Playback,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.ws_url = playback.url().to_string();
+        let handle: ReplayHandle = Arc::new(std::sync::Mutex::new(playback));
+        self.replay = Some(handle.clone());
+        self.run_with(ReplayLink::new(handle)).await
+    }
+
+    async fn run_with<L: AgentLink>(
+        &mut self,
+        mut ws: L,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         // Terminal setup
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableFocusChange
+        )?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
         terminal.clear()?;
@@ -145,61 +439,140 @@ impl App {
         // Teardown
         disable_raw_mode()?;
         let backend = terminal.backend_mut();
-        execute!(backend, DisableMouseCapture, LeaveAlternateScreen)?;
+        execute!(
+            backend,
+            DisableFocusChange,
+            DisableMouseCapture,
+            LeaveAlternateScreen
+        )?;
         terminal.show_cursor()?;
 
         res
     }
 
-    async fn event_loop<B: ratatui::backend::Backend>(
+    async fn event_loop<B: ratatui::backend::Backend, L: AgentLink>(
         &mut self,
         terminal: &mut Terminal<B>,
-        ws: &mut crate::ws::WsStream,
+        ws: &mut L,
     ) -> Result<(), Box<dyn std::error::Error>> {
         loop {
             // Input (non-blocking)
             while event::poll(Duration::from_millis(10))? {
                 match event::read()? {
                     Event::Key(k) => {
-                        if matches!(
-                            k.code,
-                            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc
-                        ) {
-                            self.should_quit = true;
+                        // Any key re-reads the text view right away
+                        self.a11y_shown = None;
+                        // The open palette takes every key until it closes
+                        if let Some(p) = self.palette.as_mut() {
+                            match p.handle_key(k) {
+                                PaletteOutcome::Open => {}
+                                PaletteOutcome::Close => self.palette = None,
+                                PaletteOutcome::Run(a) => {
+                                    self.palette = None;
+                                    self.apply_action(a);
+                                }
+                            }
+                            continue;
+                        }
+                        // Likewise the search popup
+                        if let Some(s) = self.search.as_mut() {
+                            let hits = self
+                                .last_metrics
+                                .as_ref()
+                                .map_or(0, |mm| find(mm, &s.query).len());
+                            match s.handle_key(k, hits) {
+                                SearchOutcome::Open => {}
+                                SearchOutcome::Close => {
+                                    self.search = None;
+                                    self.search_query = None;
+                                    self.disk_focus = None;
+                                }
+                                SearchOutcome::Jump(hit) => self.jump_to_search_hit(hit),
+                            }
+                            continue;
+                        }
+                        // And the peer switcher
+                        if let Some(p) = self.peers.as_mut() {
+                            match p.handle_key(k) {
+                                PeerOutcome::Open => {}
+                                PeerOutcome::Close => self.peers = None,
+                                PeerOutcome::Switch(url) => {
+                                    self.peers = None;
+                                    self.peer_switch = Some(url);
+                                }
+                            }
+                            continue;
+                        }
+                        // And the governor popup
+                        if let Some(g) = self.governor.as_mut() {
+                            if g.handle_key(k) == GovernorOutcome::Close {
+                                self.governor = None;
+                            }
+                            continue;
+                        }
+                        // The detail popup scrolls with Up/Down instead of the tables
+                        if let Some(d) = self.detail.as_mut() {
+                            match k.code {
+                                KeyCode::Up => d.scroll = d.scroll.saturating_sub(1),
+                                KeyCode::Down => d.scroll = d.scroll.saturating_add(1),
+                                KeyCode::PageUp => d.scroll = d.scroll.saturating_sub(10),
+                                KeyCode::PageDown => d.scroll = d.scroll.saturating_add(10),
+                                _ => {}
+                            }
+                            if matches!(
+                                k.code,
+                                KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown
+                            ) {
+                                continue;
+                            }
+                        }
+                        if let Some(a) = action_for(k) {
+                            self.apply_action(a);
+                        }
+                        if let Some(back) = self.inspect.as_mut() {
+                            // Left = older, Right = newer; Shift moves 10 samples
+                            let step = if k.modifiers.contains(KeyModifiers::SHIFT) {
+                                10
+                            } else {
+                                1
+                            };
+                            match k.code {
+                                KeyCode::Left => *back = back.saturating_add(step),
+                                KeyCode::Right => *back = back.saturating_sub(step),
+                                _ => {}
+                            }
+                            *back = (*back).min(self.sample_ts.len().saturating_sub(1));
                         }
                         // Per-core scroll via keys (Up/Down/PageUp/PageDown/Home/End)
                         let sz = terminal.size()?;
                         let area = Rect::new(0, 0, sz.width, sz.height);
-                        let rows = ratatui::layout::Layout::default()
-                            .direction(Direction::Vertical)
-                            .constraints([
-                                Constraint::Length(1),
-                                Constraint::Ratio(1, 3),
-                                Constraint::Length(3),
-                                Constraint::Length(3),
-                                Constraint::Min(10),
-                            ])
-                            .split(area);
-                        let top = ratatui::layout::Layout::default()
-                            .direction(Direction::Horizontal)
-                            .constraints([Constraint::Percentage(66), Constraint::Percentage(34)])
-                            .split(rows[1]);
-                        let content = per_core_content_area(top[1]);
-
-                        per_core_handle_key(&mut self.per_core_scroll, k, content.height as usize);
-
-                        let total_rows = self
-                            .last_metrics
-                            .as_ref()
-                            .map(|mm| mm.cpu_per_core.len())
-                            .unwrap_or(0);
-                        per_core_clamp(
-                            &mut self.per_core_scroll,
-                            total_rows,
-                            content.height as usize,
-                        );
+                        if let Some(cores) = self.panel_areas(area).cores {
+                            let content = per_core_content_area(cores);
+
+                            per_core_handle_key(
+                                &mut self.per_core_scroll,
+                                k,
+                                content.height as usize,
+                            );
+
+                            let total_rows = self
+                                .last_metrics
+                                .as_ref()
+                                .map(|mm| mm.cpu_per_core.len())
+                                .unwrap_or(0);
+                            per_core_clamp(
+                                &mut self.per_core_scroll,
+                                total_rows,
+                                content.height as usize,
+                            );
+                        }
 
-                        if let Some(p_area) = self.last_procs_area {
+                        if self.procs_selected.is_some()
+                            && matches!(k.code, KeyCode::Up | KeyCode::Down)
+                        {
+                            // Up/Down move the selection; draw() scrolls to keep it visible
+                            self.move_proc_selection(k.code == KeyCode::Down);
+                        } else if let Some(p_area) = self.last_procs_area {
                             // page size = visible rows (inner height minus header = 1)
                             let page = p_area.height.saturating_sub(3).max(1) as usize; // borders (2) + header (1)
                             processes_handle_key(&mut self.procs_scroll_offset, k, page);
@@ -209,67 +582,67 @@ impl App {
                         // Layout to get areas
                         let sz = terminal.size()?;
                         let area = Rect::new(0, 0, sz.width, sz.height);
-                        let rows = ratatui::layout::Layout::default()
-                            .direction(Direction::Vertical)
-                            .constraints([
-                                Constraint::Length(1),
-                                Constraint::Ratio(1, 3),
-                                Constraint::Length(3),
-                                Constraint::Length(3),
-                                Constraint::Min(10),
-                            ])
-                            .split(area);
-                        let top = ratatui::layout::Layout::default()
-                            .direction(Direction::Horizontal)
-                            .constraints([Constraint::Percentage(66), Constraint::Percentage(34)])
-                            .split(rows[1]);
-
-                        // Content wheel scrolling
-                        let content = per_core_content_area(top[1]);
-                        per_core_handle_mouse(
-                            &mut self.per_core_scroll,
-                            m,
-                            content,
-                            content.height as usize,
-                        );
+                        if let Some(cores) = self.panel_areas(area).cores {
+                            // Content wheel scrolling
+                            let content = per_core_content_area(cores);
+                            per_core_handle_mouse(
+                                &mut self.per_core_scroll,
+                                m,
+                                content,
+                                content.height as usize,
+                            );
 
-                        // Scrollbar clicks/drag
-                        let total_rows = self
-                            .last_metrics
-                            .as_ref()
-                            .map(|mm| mm.cpu_per_core.len())
-                            .unwrap_or(0);
-                        per_core_handle_scrollbar_mouse(
-                            &mut self.per_core_scroll,
-                            &mut self.per_core_drag,
-                            m,
-                            top[1],
-                            total_rows,
-                        );
+                            // Scrollbar clicks/drag
+                            let total_rows = self
+                                .last_metrics
+                                .as_ref()
+                                .map(|mm| mm.cpu_per_core.len())
+                                .unwrap_or(0);
+                            per_core_handle_scrollbar_mouse(
+                                &mut self.per_core_scroll,
+                                &mut self.per_core_drag,
+                                m,
+                                cores,
+                                total_rows,
+                            );
 
-                        // Clamp to bounds
-                        per_core_clamp(
-                            &mut self.per_core_scroll,
-                            total_rows,
-                            content.height as usize,
-                        );
+                            // Clamp to bounds
+                            per_core_clamp(
+                                &mut self.per_core_scroll,
+                                total_rows,
+                                content.height as usize,
+                            );
+                        }
 
                         // Processes table: sort by column on header click
                         if let (Some(mm), Some(p_area)) =
                             (self.last_metrics.as_ref(), self.last_procs_area)
                         {
+                            // Click a row to select its process; clicking it again clears
+                            if let Some(pos) = processes_row_at(m, p_area, self.procs_scroll_offset)
+                            {
+                                let rows =
+                                    visible_rows(mm, self.procs_sort_by, self.process_filter());
+                                if let Some(&ix) = rows.get(pos) {
+                                    let pid = mm.top_processes[ix].pid;
+                                    self.procs_selected =
+                                        (self.procs_selected != Some(pid)).then_some(pid);
+                                }
+                            }
                             if let Some(new_sort) = processes_handle_mouse(
                                 &mut self.procs_scroll_offset,
                                 &mut self.procs_drag,
                                 m,
                                 p_area,
-                                mm.top_processes.len(),
+                                mm,
                             ) {
                                 self.procs_sort_by = new_sort;
                             }
                         }
                     }
-                    Event::Resize(_, _) => {}
+                    Event::Resize(_, _) => self.a11y_shown = None,
+                    Event::FocusGained => self.focus.set_focused(true),
+                    Event::FocusLost => self.focus.set_focused(false),
                     _ => {}
                 }
             }
@@ -277,24 +650,160 @@ impl App {
                 break;
             }
 
-            // Fetch and update
-            if let Some(m) = request_metrics(ws).await {
-                self.update_with_metrics(m);
+            if let Some(peer) = self.peer_switch.take() {
+                self.switch_to_peer(ws, &peer).await;
+            }
 
-                // Only poll processes every 2s
-                if self.last_procs_poll.elapsed() >= self.procs_interval {
-                    if let Some(procs) = request_processes(ws).await {
-                        if let Some(mm) = self.last_metrics.as_mut() {
-                            mm.top_processes = procs.top_processes;
-                            mm.process_count = Some(procs.process_count);
+            // Unfocused with --unfocused-poll-ms: nothing new to fetch or draw yet
+            if !self.focus.fetch_due(Instant::now()) {
+                sleep(self.metrics_interval).await;
+                continue;
+            }
+            self.focus.fetched(Instant::now());
+
+            // A paused or finished replay keeps the last sample on screen
+            let hold = self
+                .replay
+                .as_ref()
+                .is_some_and(|r| r.lock().is_ok_and(|p| p.holding()));
+
+            // Fetch and update (once subscribed, wait for the agent's next push instead)
+            let fetched = if hold || self.reconnect.is_some() {
+                None
+            } else if self.subscribed == Some(true) {
+                self.next_pushed_metrics(ws).await
+            } else {
+                request_metrics(ws).await
+            };
+            if self.replay.is_none() {
+                self.record_alert(
+                    "disconnected",
+                    fetched.is_none(),
+                    &format!("lost connection to {}", self.ws_url),
+                );
+            }
+            if fetched.is_none() && self.replay.is_none() {
+                self.try_reconnect(ws).await;
+            }
+            if let Some(m) = fetched {
+                if self.hello_pending {
+                    self.hello_pending = false;
+                    if m.has_capability("hello") {
+                        let hello = request_hello(ws, self.session_id.as_deref()).await;
+                        self.scope = hello.as_ref().map(|h| h.scope);
+                        self.session_id = hello.and_then(|h| h.session);
+                    }
+                }
+                if self.prefill_pending {
+                    self.prefill_pending = false;
+                    if m.has_capability("history") {
+                        if let Some(h) = request_history(ws).await {
+                            self.prefill_from_history(&h);
                         }
                     }
+                }
+                if self.subscribed.is_none() {
+                    let wanted = self.push_wanted(&m);
+                    self.subscribed = Some(
+                        wanted
+                            && ws
+                                .subscribe(self.metrics_interval, self.procs_interval)
+                                .await,
+                    );
+                }
+                self.update_with_metrics(m);
+
+                // Detail popup just opened: fetch env and open files once
+                if let Some(d) = self.detail.as_mut().filter(|d| d.pending()) {
+                    d.env = Some(request_process_env(ws, d.pid).await.unwrap_or_default());
+                    d.fds = Some(request_process_fds(ws, d.pid).await.unwrap_or_default());
+                }
+
+                if let Some(p) = self.peers.as_mut().filter(|p| p.pending()) {
+                    p.peers = Some(request_peers(ws).await.unwrap_or_default());
+                }
+
+                if let Some(g) = self.governor.as_mut().filter(|g| g.pending()) {
+                    match request_cpufreq(ws).await {
+                        Some(info) => g.set_info(info),
+                        None => g.info = Some(CpuFreq::default()),
+                    }
+                }
+                if let Some(g) = self.governor.as_mut() {
+                    if let Some(name) = g.apply.take() {
+                        // The reply is the state afterwards, with the error if nothing changed
+                        let info =
+                            request_set_governor(ws, &name)
+                                .await
+                                .unwrap_or_else(|| CpuFreq {
+                                    error: Some("no reply from the agent".into()),
+                                    ..g.info.clone().unwrap_or_default()
+                                });
+                        g.set_info(info);
+                    }
+                }
+
+                if let Some(p) = self.io.as_mut().filter(|p| p.note.is_none()) {
+                    if let Some(r) = request_io(ws).await {
+                        p.report = Some(r);
+                    }
+                }
+
+                // Only poll processes every 2s (pushed instead while subscribed)
+                if self.subscribed != Some(true)
+                    && self.last_procs_poll.elapsed() >= self.procs_interval
+                {
+                    let mm = self.last_metrics.as_ref();
+                    let top_k = self.budget.is_some()
+                        && mm.is_some_and(|mm| mm.has_capability("processes_top"));
+                    // Big hosts (or a budget): top rows plus a rotating sample of the rest
+                    let count = mm.and_then(|mm| mm.process_count).unwrap_or(0);
+                    let sample = (self.budget.is_some() || count > SAMPLE_ABOVE)
+                        && mm.is_some_and(|mm| mm.has_capability("processes_sample"));
+                    let k = if self.budget.is_some() {
+                        BUDGET_TOP_K
+                    } else {
+                        SAMPLE_TOP_K
+                    };
+                    let sort = self.procs_sort_by.agent_key();
+                    let procs = if sample {
+                        let n = self.tail.take(Instant::now(), count.saturating_sub(k));
+                        request_processes_sampled(ws, k, sort, n, self.tail.after()).await
+                    } else if top_k {
+                        request_processes_top(ws, BUDGET_TOP_K, sort).await
+                    } else {
+                        request_processes(ws).await
+                    };
+                    if !sample {
+                        self.tail = TailSampler::default();
+                    }
+                    if let Some(procs) = procs {
+                        self.apply_processes(procs, sample);
+                    }
                     self.last_procs_poll = Instant::now();
                 }
 
                 // Only poll disks every 5s
                 if self.last_disks_poll.elapsed() >= self.disks_interval {
-                    if let Some(disks) = request_disks(ws).await {
+                    // Agents with a session can send just the disks that changed
+                    let delta = self.session_id.is_some()
+                        && self
+                            .last_metrics
+                            .as_ref()
+                            .is_some_and(|mm| mm.has_capability("disks_delta"));
+                    let disks = if delta {
+                        request_disks_delta(ws, self.disk_mirror.seq())
+                            .await
+                            .map(|d| {
+                                self.disk_mirror.apply(d);
+                                self.disk_mirror.disks().to_vec()
+                            })
+                    } else {
+                        request_disks(ws).await
+                    };
+                    if let Some(mut disks) = disks {
+                        self.suspect.disks = check_disks(&mut disks);
+                        recording::record_value(recording::Kind::Disks, &disks);
                         if let Some(mm) = self.last_metrics.as_mut() {
                             mm.disks = disks;
                         }
@@ -303,17 +812,168 @@ impl App {
                 }
             }
 
-            // Draw
-            terminal.draw(|f| self.draw(f))?;
+            self.update_wire_rate();
+            let lag = self.cadence_warning();
+            self.record_alert("agent_lag", lag.is_some(), lag.as_deref().unwrap_or(""));
+            let fds = self.fd_warning();
+            self.record_alert("fd_pressure", fds.is_some(), fds.as_deref().unwrap_or(""));
+            let rng = self.entropy_warning();
+            self.record_alert("low_entropy", rng.is_some(), rng.as_deref().unwrap_or(""));
+            let clock = self.clock_warning();
+            self.record_alert(
+                "clock_unsynced",
+                clock.is_some(),
+                clock.as_deref().unwrap_or(""),
+            );
+            self.record_threshold_alerts(self.sample_secs());
+            if let (Some(mark), Some(mm)) = (self.mark.as_mut(), self.last_metrics.as_ref()) {
+                mark.observe(mm);
+            }
+            if let Some(b) = self.budget.as_mut() {
+                let [m, p, d] = b.adjust(self.wire_bps);
+                self.metrics_interval = m;
+                self.procs_interval = p;
+                self.disks_interval = d;
+            }
+
+            // Draw (text mode only redraws when its text changed, at most every A11Y_REDRAW)
+            if self.a11y {
+                let lines = self.a11y_text();
+                let due = self
+                    .a11y_shown
+                    .as_ref()
+                    .is_none_or(|(at, prev)| *prev != lines && at.elapsed() >= A11Y_REDRAW);
+                if due {
+                    terminal.draw(|f| {
+                        let text = lines.join("\n");
+                        f.render_widget(
+                            ratatui::widgets::Paragraph::new(text)
+                                .wrap(ratatui::widgets::Wrap { trim: true }),
+                            f.area(),
+                        );
+                    })?;
+                    self.a11y_shown = Some((Instant::now(), lines));
+                }
+            } else if self.focus.draw_due(Instant::now()) {
+                let t = Instant::now();
+                terminal.draw(|f| self.draw(f))?;
+                if self.flush_raster()? {
+                    // Sixel pixels left where a chart went away: repaint everything
+                    terminal.clear()?;
+                    terminal.draw(|f| self.draw(f))?;
+                    self.flush_raster()?;
+                }
+                self.frame_budget.record(t.elapsed());
+                self.focus.drawn(t);
+            }
 
-            // Tick rate
-            sleep(self.metrics_interval).await;
+            // Tick rate (pushes pace the loop while subscribed)
+            if self.subscribed != Some(true) {
+                sleep(self.metrics_interval).await;
+            }
         }
 
         Ok(())
     }
 
+    // Connection dropped (or garbled reply): re-establish it on the same URL, backing off
+    // between failed attempts. Histories are kept, so the charts carry on from where they
+    // stopped (unless the host turns out to have rebooted or changed).
+    async fn try_reconnect<L: AgentLink>(&mut self, ws: &mut L) {
+        self.subscribed = None;
+        let now = Instant::now();
+        if !self
+            .reconnect
+            .get_or_insert_with(|| Backoff::new(now))
+            .due(now)
+        {
+            return;
+        }
+        match L::open(&self.ws_url, self.tls_ca.as_deref()).await {
+            Ok(new_ws) => {
+                *ws = new_ws;
+                self.reconnect = None;
+                self.reconnected = true;
+                // Offer the agent session back with the next sample (also refreshes scope)
+                self.hello_pending = true;
+            }
+            Err(_) => {
+                if let Some(b) = self.reconnect.as_mut() {
+                    b.failed(Instant::now());
+                }
+            }
+        }
+    }
+
+    // Pushes only replace plain polling: a bandwidth budget, unfocused throttling and the
+    // sampled process list on huge hosts all need the client to choose what to fetch and when
+    fn push_wanted(&self, m: &Metrics) -> bool {
+        m.has_capability("subscribe")
+            && self.budget.is_none()
+            && !self.focus.throttles_fetch()
+            && m.process_count.unwrap_or(0) <= SAMPLE_ABOVE
+    }
+
+    // Subscribed: apply processes pushes as they come and return the next metrics push
+    async fn next_pushed_metrics<L: AgentLink>(&mut self, ws: &mut L) -> Option<Metrics> {
+        // A few intervals without a push means the agent or the link stalled
+        let wait = self.metrics_interval * 3 + Duration::from_secs(1);
+        loop {
+            match ws.next_push(wait).await? {
+                Push::Metrics(m) => return Some(*m),
+                Push::Processes(p) => {
+                    self.apply_processes(p, false);
+                    self.last_procs_poll = Instant::now();
+                }
+            }
+        }
+    }
+
+    // A processes reply (polled or pushed) into the current snapshot; `sample` merges the
+    // rotating tail sample
+    fn apply_processes(&mut self, procs: ProcessesPayload, sample: bool) {
+        let Some(mm) = self.last_metrics.as_mut() else {
+            return;
+        };
+        let mut rows = if sample {
+            self.tail.merge(
+                Instant::now(),
+                procs.top_processes,
+                procs.tail,
+                procs.process_count,
+            )
+        } else {
+            procs.top_processes
+        };
+        self.suspect.processes = check_processes(&mut rows, mm.cpu_per_core.len());
+        self.proc_trends.record(&rows);
+        mm.top_processes = rows;
+        mm.process_count = Some(procs.process_count);
+        // Drop the selection once its process is gone
+        if let Some(pid) = self.procs_selected {
+            if !mm.top_processes.iter().any(|p| p.pid == pid) {
+                self.procs_selected = None;
+            }
+        }
+    }
+
     fn update_with_metrics(&mut self, mut m: Metrics) {
+        self.suspect.metrics = check_metrics(&mut m);
+
+        // After a reconnect, make sure we are still looking at the same host and boot
+        let ident = HostIdentity::from_metrics(&m);
+        if self.reconnected {
+            self.reconnected = false;
+            if let Some(prev) = &self.host_identity {
+                let change = prev.compare(&ident);
+                if change != IdentityChange::Same {
+                    self.host_notice = change.notice(&ident.hostname);
+                    self.reset_histories();
+                }
+            }
+        }
+        self.host_identity = Some(ident);
+
         if let Some(prev) = &self.last_metrics {
             // Preserve slower fields when the fast payload omits them
             if m.disks.is_empty() {
@@ -331,168 +991,912 @@ impl App {
         // CPU avg history
         let v = m.cpu_total.clamp(0.0, 100.0).round() as u64;
         push_capped(&mut self.cpu_hist, v, 600);
+        push_capped(&mut self.sample_ts, now_millis(), 600);
+        // Keep the crosshair on the sample it was pointing at
+        if let Some(back) = self.inspect.as_mut() {
+            *back = (*back + 1).min(self.sample_ts.len() - 1);
+        }
+
+        // Keep per-core rows stable when cores go offline
+        if !m.offline_cores.is_empty() {
+            m.cpu_per_core = stable_core_rows(&m.cpu_per_core, &m.offline_cores);
+            if let Some(irq) = m.cpu_irq.as_mut() {
+                *irq = stable_core_rows(irq, &m.offline_cores);
+            }
+        }
 
         // Per-core history (push current samples)
         self.per_core_hist.ensure_cores(m.cpu_per_core.len());
         self.per_core_hist.push_samples(&m.cpu_per_core);
 
-        // NET: sum across all ifaces, compute KB/s via elapsed time
+        // NET: sum across all ifaces, compute KB/s over the agent's sampling interval (arrival
+        // times for agents that don't stamp samples)
         let now = Instant::now();
         let rx_total = m.networks.iter().map(|n| n.received).sum::<u64>();
         let tx_total = m.networks.iter().map(|n| n.transmitted).sum::<u64>();
-        let (rx_kb, tx_kb) = if let Some((prx, ptx, pts)) = self.last_net_totals {
-            let dt = now.duration_since(pts).as_secs_f64().max(1e-6);
-            let rx = ((rx_total.saturating_sub(prx)) as f64 / dt / 1024.0).round() as u64;
-            let tx = ((tx_total.saturating_sub(ptx)) as f64 / dt / 1024.0).round() as u64;
-            (rx, tx)
-        } else {
-            (0, 0)
+        // After a counter reset (agent restarted, interface removed) hold the last rate instead
+        // of charting a bogus dip/spike; the first sample has no rate yet
+        let rx_kb = match self.rx_rate.update_stamped(rx_total, m.sample_ms, now) {
+            Some(bps) => (bps / 1024.0).round() as u64,
+            None => self.rx_hist.back().copied().unwrap_or(0),
+        };
+        let tx_kb = match self.tx_rate.update_stamped(tx_total, m.sample_ms, now) {
+            Some(bps) => (bps / 1024.0).round() as u64,
+            None => self.tx_hist.back().copied().unwrap_or(0),
         };
-        self.last_net_totals = Some((rx_total, tx_total, now));
         push_capped(&mut self.rx_hist, rx_kb, 600);
         push_capped(&mut self.tx_hist, tx_kb, 600);
         self.rx_peak = self.rx_peak.max(rx_kb);
         self.tx_peak = self.tx_peak.max(tx_kb);
 
+        if !self.derived.is_empty() {
+            let vars = sample_vars(&m, rx_kb, tx_kb);
+            for d in &mut self.derived {
+                d.update(&vars);
+            }
+        }
+
         // Store merged snapshot
         self.last_metrics = Some(m);
     }
 
+    // Bytes/s exchanged with the agent since the previous tick
+    fn update_wire_rate(&mut self) {
+        let total = crate::wire_stats::snapshot().total_wire();
+        if let Some(bps) = self.wire_rate.update(total, Instant::now()) {
+            self.wire_bps = bps;
+        }
+    }
+
+    // Warn when the agent's last collection took longer than the interval we poll at
+    fn cadence_warning(&self) -> Option<String> {
+        let meta = self.last_metrics.as_ref()?.meta?;
+        let mi = self.metrics_interval.as_millis() as f32;
+        let pi = self.procs_interval.as_millis() as f32;
+        if meta.metrics.last_ms >= mi {
+            Some(format!(
+                "⚠ agent slow: metrics {:.0}ms ≥ {mi:.0}ms interval",
+                meta.metrics.last_ms
+            ))
+        } else if meta.processes.last_ms >= pi {
+            Some(format!(
+                "⚠ agent slow: procs {:.0}ms ≥ {pi:.0}ms interval",
+                meta.processes.last_ms
+            ))
+        } else {
+            None
+        }
+    }
+
+    // Warn before the host runs out of file handles (fs.file-max); names the biggest holder
+    fn fd_warning(&self) -> Option<String> {
+        let fd = self.last_metrics.as_ref()?.fd.as_ref()?;
+        if fd.max == 0 || (fd.allocated as f64) < fd.max as f64 * FD_WARN_RATIO {
+            return None;
+        }
+        let pct = fd.allocated as f64 / fd.max as f64 * 100.0;
+        Some(match fd.top.first() {
+            Some(p) => format!(
+                "⚠ file handles {pct:.0}% of max (top: {} [{}] {} fds)",
+                p.name, p.pid, p.fds
+            ),
+            None => format!("⚠ file handles {pct:.0}% of max"),
+        })
+    }
+
+    // RNG starvation (mostly older kernels at boot without a hardware RNG)
+    fn entropy_warning(&self) -> Option<String> {
+        let e = self.last_metrics.as_ref()?.entropy.as_ref()?;
+        if e.avail_bits >= ENTROPY_WARN_BITS.min(e.pool_bits) {
+            return None;
+        }
+        let hw = e.hwrng.as_deref().unwrap_or("no hwrng");
+        Some(format!(
+            "⚠ low entropy {}/{} bits ({hw})",
+            e.avail_bits, e.pool_bits
+        ))
+    }
+
+    // Unsynchronized clocks break TLS, logs and cron; show the kernel's offset estimate
+    fn clock_warning(&self) -> Option<String> {
+        self.last_metrics.as_ref()?.clock?.warning()
+    }
+
+    // When the sample on screen was taken: now, or back then in a replay
+    fn sample_secs(&self) -> u64 {
+        self.replay
+            .as_ref()
+            .and_then(|r| r.lock().ok())
+            .map_or_else(now_secs, |p| p.position_unix())
+    }
+
+    // CPU, memory, swap, disk and temperature against the alert rules; only debounced
+    // transitions reach the log
+    fn record_threshold_alerts(&mut self, now: u64) {
+        let pct = |used: u64, total: u64| (total > 0).then(|| used as f64 / total as f64 * 100.0);
+        let m = self.last_metrics.as_ref();
+        let cpu = m.map(|mm| f64::from(mm.cpu_total));
+        let mem = m.and_then(|mm| pct(mm.mem_used, mm.mem_total));
+        let swap = m.and_then(|mm| pct(mm.swap_used, mm.swap_total));
+        // The fullest disk stands for all of them
+        let disk = m.and_then(|mm| {
+            mm.disks
+                .iter()
+                .filter_map(|d| Some((d, pct(d.total.saturating_sub(d.available), d.total)?)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(d, p)| (d.name.clone(), p))
+        });
+        let temp = m.and_then(|mm| mm.cpu_temp_c).map(f64::from);
+        if let Some(rule) = self.alert_rules.cpu {
+            let firing = self.cpu_alert.update(&rule, now, cpu);
+            let msg = format!(
+                "CPU {:.0}% busy (above {:.0}% for {}s)",
+                cpu.unwrap_or(0.0),
+                rule.above,
+                rule.for_secs
+            );
+            self.record_alert("cpu_high", firing, &msg);
+        }
+        if let Some(rule) = self.alert_rules.mem {
+            let firing = self.mem_alert.update(&rule, now, mem);
+            let msg = format!(
+                "memory {:.0}% used (above {:.0}% for {}s)",
+                mem.unwrap_or(0.0),
+                rule.above,
+                rule.for_secs
+            );
+            self.record_alert("mem_high", firing, &msg);
+        }
+        if let Some(rule) = self.alert_rules.swap {
+            let firing = self.swap_alert.update(&rule, now, swap);
+            let msg = format!(
+                "swap {:.0}% used (above {:.0}% for {}s)",
+                swap.unwrap_or(0.0),
+                rule.above,
+                rule.for_secs
+            );
+            self.record_alert("swap_high", firing, &msg);
+        }
+        if let Some(rule) = self.alert_rules.disk {
+            let firing = self
+                .disk_alert
+                .update(&rule, now, disk.as_ref().map(|d| d.1));
+            let (name, used) = disk.unwrap_or_default();
+            let msg = format!("disk {name} {used:.0}% full (above {:.0}%)", rule.above);
+            self.record_alert("disk_full", firing, &msg);
+        }
+        if let Some(rule) = self.alert_rules.temp {
+            let firing = self.temp_alert.update(&rule, now, temp);
+            let msg = format!(
+                "CPU at {:.0}°C (above {:.0}°C)",
+                temp.unwrap_or(0.0),
+                rule.above
+            );
+            self.record_alert("temp_high", firing, &msg);
+        }
+    }
+
+    // Log fired/cleared transitions for the alert panel (no-op while the state is unchanged).
+    // `message` describes the firing condition; clears get a generic one.
+    fn record_alert(&mut self, key: &str, firing: bool, message: &str) {
+        let host = self
+            .host_identity
+            .as_ref()
+            .map(|h| h.hostname.as_str())
+            .unwrap_or(&self.ws_url);
+        let now = self.sample_secs();
+        let message = if firing {
+            message.to_string()
+        } else {
+            format!("{key} cleared")
+        };
+        if !self.alerts.set(now, host, key, firing, &message) {
+            return;
+        }
+        if !firing {
+            self.alert_status.remove(key);
+            return;
+        }
+        // Sinks for its severity; maintenance keeps them quiet
+        if self.alerts.silenced_at(now) {
+            return;
+        }
+        let sinks = self.alert_rules.sinks.for_severity(Severity::of(key));
+        if sinks.bell {
+            let mut out = io::stdout();
+            let _ = out.write_all(b"\x07").and_then(|_| out.flush());
+        }
+        if sinks.flash {
+            self.alert_flash = Some((key.to_string(), Instant::now()));
+        }
+        if sinks.status {
+            self.alert_status.insert(key.to_string(), message);
+        }
+    }
+
+    // Start/end a maintenance window; persisted to the active profile so restarts keep it
+    fn apply_action(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::OpenPalette => self.palette = Some(Palette::default()),
+            Action::OpenSearch => {
+                self.search = Some(Search {
+                    query: self.search_query.clone().unwrap_or_default(),
+                    selected: 0,
+                })
+            }
+            Action::ToggleDebug => self.show_debug = !self.show_debug,
+            Action::ToggleAlerts => self.show_alerts = !self.show_alerts,
+            Action::ToggleReboot => self.show_reboot = !self.show_reboot,
+            Action::ToggleIrq => self.show_irq = !self.show_irq,
+            Action::ToggleMaintenance => self.toggle_maintenance(),
+            Action::ToggleInspect => self.inspect = self.inspect.is_none().then_some(0),
+            Action::SortByCpu => self.procs_sort_by = ProcSortBy::CpuDesc,
+            Action::SortByMem => self.procs_sort_by = ProcSortBy::MemDesc,
+            Action::SortByTime => self.procs_sort_by = ProcSortBy::TimeDesc,
+            Action::ClearSelection => self.procs_selected = None,
+            Action::CopyProcess => {
+                let line = self.procs_selected.and_then(|pid| {
+                    let m = self.last_metrics.as_ref()?;
+                    let p = m.top_processes.iter().find(|p| p.pid == pid)?;
+                    Some(crate::clipboard::process_line(p))
+                });
+                self.copy_to_clipboard("process", line);
+            }
+            Action::CopySnapshot => {
+                self.copy_to_clipboard("metrics snapshot", crate::crash::last_snapshot());
+            }
+            Action::ToggleProcessDetail => {
+                self.detail = match self.detail {
+                    Some(_) => None,
+                    None => Some(self.open_process_detail()),
+                }
+            }
+            Action::SetMark => {
+                if let Some(mm) = self.last_metrics.as_ref() {
+                    self.mark = Some(Mark::new(mm, now_secs()));
+                }
+            }
+            Action::ToggleMarkDiff => {
+                self.show_mark_diff = !self.show_mark_diff && self.mark.is_some()
+            }
+            Action::ShowPeers => self.peers = Some(self.open_peers()),
+            Action::ShowGovernor => self.governor = Some(self.open_governor()),
+            Action::ToggleIo => {
+                self.io = match self.io {
+                    Some(_) => None,
+                    None => Some(self.open_io_panel()),
+                }
+            }
+            Action::ReplayPause => self.with_replay(Playback::toggle_pause),
+            Action::ReplaySeekBack => self.seek_replay(false),
+            Action::ReplaySeekForward => self.seek_replay(true),
+            Action::SetInterval(ms) => {
+                // Same 1:4 metrics/processes ratio as the defaults
+                self.metrics_interval = Duration::from_millis(ms.max(100));
+                self.procs_interval = Duration::from_millis((ms * 4).max(200));
+            }
+        }
+    }
+
+    // Popup for the selected process; explains itself instead of fetching when it can't
+    fn open_process_detail(&self) -> ProcessDetail {
+        let m = self.last_metrics.as_ref();
+        let Some(p) = self
+            .procs_selected
+            .and_then(|pid| m?.top_processes.iter().find(|p| p.pid == pid))
+        else {
+            return ProcessDetail {
+                note: Some("Select a process first (click its row)".into()),
+                ..Default::default()
+            };
+        };
+        let note = if !m.is_some_and(|mm| mm.has_capability("process_inspect")) {
+            Some("This agent can't inspect processes (needs a newer Linux agent)")
+        } else if self.scope != Some(Scope::Control) {
+            Some("Needs a control-scope token (this one is read-only)")
+        } else {
+            None
+        };
+        ProcessDetail {
+            pid: p.pid,
+            name: p.name.clone(),
+            note: note.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    // Older and non-Linux agents have no get_io: say so instead of polling
+    fn open_io_panel(&self) -> IoPanel {
+        let supported = self
+            .last_metrics
+            .as_ref()
+            .is_some_and(|m| m.has_capability("io"));
+        IoPanel {
+            note: (!supported)
+                .then(|| "This agent doesn't report I/O wait (needs a newer Linux agent)".into()),
+            report: None,
+        }
+    }
+
+    fn open_peers(&self) -> PeerPicker {
+        let supported = self
+            .last_metrics
+            .as_ref()
+            .is_some_and(|m| m.has_capability("peers"));
+        PeerPicker {
+            note: (!supported)
+                .then(|| "This agent doesn't support peer discovery (needs a newer agent)".into()),
+            ..PeerPicker::default()
+        }
+    }
+
+    // Anyone may look; switching needs a control token and an agent that allows it (the
+    // `get_cpufreq` reply says whether it does)
+    fn open_governor(&self) -> GovernorPicker {
+        let m = self.last_metrics.as_ref();
+        if !m.is_some_and(|mm| mm.has_capability("cpufreq")) {
+            return GovernorPicker {
+                note: Some(
+                    "This agent doesn't report CPU governors (needs a newer Linux agent)".into(),
+                ),
+                ..GovernorPicker::default()
+            };
+        }
+        let locked = (self.scope != Some(Scope::Control))
+            .then(|| "Switching needs a control-scope token (this one is read-only)".to_string());
+        GovernorPicker {
+            locked,
+            ..GovernorPicker::default()
+        }
+    }
+
+    fn toggle_maintenance(&mut self) {
+        let now = now_secs();
+        let until = if self.alerts.silenced_at(now) {
+            None
+        } else {
+            Some(now + self.silence_minutes * 60)
+        };
+        self.alerts.silence_until(until);
+        if let Some(p) = self.profile.as_deref() {
+            let _ = crate::profiles::save_silence(p, until);
+        }
+    }
+
+    // Replay keys do nothing on a live connection
+    fn with_replay(&self, f: impl FnOnce(&mut Playback)) {
+        if let Some(mut p) = self.replay.as_ref().and_then(|r| r.lock().ok()) {
+            f(&mut p);
+        }
+    }
+
+    // The charts restart at the new position rather than joining two stretches of time
+    fn seek_replay(&mut self, forward: bool) {
+        if self.replay.is_some() {
+            self.with_replay(|p| p.seek(forward));
+            self.reset_histories();
+            // Processes and disks from the new position with its first sample
+            let due = |every: Duration| {
+                Instant::now()
+                    .checked_sub(every)
+                    .unwrap_or_else(Instant::now)
+            };
+            self.last_procs_poll = due(self.procs_interval);
+            self.last_disks_poll = due(self.disks_interval);
+        }
+    }
+
+    fn header_notice(&self) -> Option<String> {
+        // Maintenance hides the (expected) lag warnings; host changes are still shown
+        if let Some(until) = self.alerts.silenced_until(now_secs()) {
+            let end = crate::alerts::format_utc(until);
+            let m = format!("🔧 maintenance until {} UTC", &end[11..16]);
+            return Some(match self.host_notice.as_deref() {
+                Some(h) => format!("{h} | {m}"),
+                None => m,
+            });
+        }
+        let parts: Vec<String> = self
+            .host_notice
+            .clone()
+            .into_iter()
+            .chain(self.reconnect.as_ref().map(|b| b.status(Instant::now())))
+            .chain(self.alert_status.values().map(|m| format!("🔔 {m}")))
+            .chain(self.cadence_warning())
+            .chain(self.fd_warning())
+            .chain(self.entropy_warning())
+            .chain(self.clock_warning())
+            .chain(
+                self.copy_notice
+                    .as_ref()
+                    .filter(|(_, at)| at.elapsed() < COPY_NOTICE)
+                    .map(|(m, _)| m.clone()),
+            )
+            .chain(self.suspect.badge())
+            .chain(self.last_metrics.as_ref().and_then(Metrics::schema_notice))
+            .chain(
+                self.replay
+                    .as_ref()
+                    .and_then(|r| r.lock().ok().map(|p| p.status())),
+            )
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" | "))
+    }
+
+    // Seed CPU/net/per-core charts with the agent's downsampled history
+    fn prefill_from_history(&mut self, h: &AgentHistory) {
+        for (ts, p) in expand_history(&h.points, h.step_secs, self.metrics_interval) {
+            push_capped(
+                &mut self.cpu_hist,
+                p.cpu_total.clamp(0.0, 100.0).round() as u64,
+                600,
+            );
+            push_capped(&mut self.sample_ts, ts, 600);
+            if !p.cpu_per_core.is_empty() {
+                self.per_core_hist.push_samples(&p.cpu_per_core);
+            }
+            let (rx, tx) = (p.rx_bps / 1024, p.tx_bps / 1024);
+            push_capped(&mut self.rx_hist, rx, 600);
+            push_capped(&mut self.tx_hist, tx, 600);
+            self.rx_peak = self.rx_peak.max(rx);
+            self.tx_peak = self.tx_peak.max(tx);
+        }
+    }
+
+    // Reconnect to a peer agent, keeping our token; on failure stay on the current agent
+    async fn switch_to_peer<L: AgentLink>(&mut self, ws: &mut L, peer: &str) {
+        let url = switch_url(&self.ws_url, peer);
+        match L::open(&url, self.tls_ca.as_deref()).await {
+            Ok(new_ws) => {
+                *ws = new_ws;
+                crate::crash::set_url(&url);
+                self.ws_url = url;
+                self.host_notice = None;
+                self.host_identity = None;
+                // Another agent: new scope and session, no disk deltas or mark to carry over
+                self.hello_pending = true;
+                self.subscribed = None;
+                self.reconnect = None;
+                self.scope = None;
+                self.session_id = None;
+                self.disk_mirror = DiskMirror::default();
+                self.mark = None;
+                self.show_mark_diff = false;
+                self.reset_histories();
+            }
+            Err(e) => self.host_notice = Some(format!("could not switch to {peer}: {e}")),
+        }
+    }
+
+    // Drop everything derived from the previous host/boot so charts don't splice unrelated data
+    fn reset_histories(&mut self) {
+        self.prefill_pending = true;
+        self.last_metrics = None;
+        self.suspect = Suspect::default();
+        self.cpu_hist.clear();
+        self.per_core_hist = PerCoreHistory::new(60);
+        self.rx_rate = RateCounter::new();
+        self.tx_rate = RateCounter::new();
+        self.rx_hist.clear();
+        self.tx_hist.clear();
+        self.rx_peak = 0;
+        self.tx_peak = 0;
+        self.sample_ts.clear();
+        self.inspect = None;
+        self.procs_selected = None;
+        self.proc_trends.clear();
+    }
+
+    // Close the search popup, keep its query as the highlight and focus match `hit`
+    fn jump_to_search_hit(&mut self, hit: Option<usize>) {
+        let Some(s) = self.search.take() else {
+            return;
+        };
+        let hit = hit.and_then(|i| {
+            let mm = self.last_metrics.as_ref()?;
+            find(mm, &s.query).into_iter().nth(i)
+        });
+        self.search_query = Some(s.query).filter(|q| !q.trim().is_empty());
+        let Some(hit) = hit else {
+            return;
+        };
+        match hit.kind {
+            // draw() scrolls the table to the selected row
+            HitKind::Process => self.procs_selected = hit.pid,
+            HitKind::Disk => self.disk_focus = Some(hit.name),
+            HitKind::Interface | HitKind::Sensor => {}
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, what: &str, text: Option<String>) {
+        let msg = match text.map(|t| crate::clipboard::copy(&t)) {
+            None => format!("📋 nothing to copy (no {what} yet)"),
+            Some(Ok(b)) => format!("📋 copied {what} ({})", b.label()),
+            Some(Err(e)) => format!("📋 copy failed: {e}"),
+        };
+        self.copy_notice = Some((msg, Instant::now()));
+    }
+
+    // The `/` query as typed so far (or kept after Enter): filters the process table live
+    fn process_filter(&self) -> Option<&str> {
+        self.search
+            .as_ref()
+            .map(|s| s.query.as_str())
+            .or(self.search_query.as_deref())
+            .filter(|q| !q.trim().is_empty())
+    }
+
+    // Step the selection to the neighbouring row in display order
+    fn move_proc_selection(&mut self, down: bool) {
+        let Some(mm) = self.last_metrics.as_ref() else {
+            return;
+        };
+        let order = visible_rows(mm, self.procs_sort_by, self.process_filter());
+        let pids: Vec<u32> = order.iter().map(|&ix| mm.top_processes[ix].pid).collect();
+        let next = match pids.iter().position(|&p| Some(p) == self.procs_selected) {
+            Some(i) if down => pids.get((i + 1).min(pids.len() - 1)),
+            Some(i) => pids.get(i.saturating_sub(1)),
+            None => pids.first(),
+        };
+        self.procs_selected = next.copied();
+    }
+
+    // --a11y text view of the current state
+    fn a11y_text(&self) -> Vec<String> {
+        let notice = self.header_notice();
+        let mut lines = vec!["socktop (press q to quit, any other key to refresh)".to_string()];
+        lines.extend(a11y_lines(
+            self.last_metrics.as_ref(),
+            A11yStatus {
+                rx_kb: self.rx_hist.back().copied().unwrap_or(0),
+                tx_kb: self.tx_hist.back().copied().unwrap_or(0),
+                is_tls: self.is_tls,
+                has_token: self.has_token,
+                notice: notice.as_deref(),
+            },
+        ));
+        lines
+    }
+
+    // Crosshair overlays for inspection mode; the CPU chart carries the combined legend
+    fn draw_inspect(&self, f: &mut ratatui::Frame<'_>, cpu: Rect, rx: Rect, tx: Rect, back: usize) {
+        let len = self.sample_ts.len();
+        let Some(i) = len.checked_sub(back + 1) else {
+            return;
+        };
+        let at = |hist: &VecDeque<u64>| hist.get(i).copied().unwrap_or(0);
+        let ts = self.sample_ts[i];
+        let time = format!("{}.{} UTC", &format_utc(ts / 1000)[11..], ts % 1000 / 100);
+        let (c, r, t) = (at(&self.cpu_hist), at(&self.rx_hist), at(&self.tx_hist));
+        let legend = format!(" {time} · cpu {c}% · ↓{r} ↑{t} KB/s · ←/→ move, x exit ");
+        crate::ui::crosshair::draw_crosshair(f, cpu, len, back, &legend);
+        crate::ui::crosshair::draw_crosshair(f, rx, len, back, &format!(" ↓{r} KB/s @ {time} "));
+        crate::ui::crosshair::draw_crosshair(f, tx, len, back, &format!(" ↑{t} KB/s @ {time} "));
+    }
+
+    // Where each panel goes: the profile's layout (usually the full grid of header, derived
+    // metrics, cpu avg + per-core, memory, swap, bottom). Shared by draw() and the mouse/key
+    // hit-testing.
+    fn panel_areas(&self, area: Rect) -> PanelAreas {
+        let derived_h = if self.derived.is_empty() { 0 } else { 3 };
+        self.layout.areas(area, derived_h)
+    }
+
+    // Write the charts queued by the last draw; true when the screen needs a full repaint
+    fn flush_raster(&mut self) -> io::Result<bool> {
+        match self.raster.as_mut() {
+            Some(r) => r.flush(&mut io::stdout()),
+            None => Ok(false),
+        }
+    }
+
     pub fn draw(&mut self, f: &mut ratatui::Frame<'_>) {
         let area = f.area();
 
-        // Root rows: header, top (cpu avg + per-core), memory, swap, bottom
-        let rows = ratatui::layout::Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(1),   // header
-                Constraint::Ratio(1, 3), // top row
-                Constraint::Length(3),   // memory (left) + GPU (right, part 1)
-                Constraint::Length(3),   // swap (left)   + GPU (right, part 2)
-                Constraint::Min(10),     // bottom: disks + net (left), top procs (right)
-            ])
-            .split(area);
+        let areas = self.panel_areas(area);
 
         // Header
         draw_header(
             f,
-            rows[0],
+            areas.header,
             self.last_metrics.as_ref(),
             self.is_tls,
             self.has_token,
+            self.scope == Some(Scope::Read),
             self.metrics_interval,
             self.procs_interval,
+            self.header_notice().as_deref(),
         );
 
-        // Top row: left CPU avg, right Per-core (full top-right)
-        let top_lr = ratatui::layout::Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(66), Constraint::Percentage(34)])
-            .split(rows[1]);
+        if !self.derived.is_empty() {
+            crate::ui::derived::draw_derived(f, areas.derived, &self.derived);
+        }
 
-        draw_cpu_avg_graph(f, top_lr[0], &self.cpu_hist, self.last_metrics.as_ref());
-        draw_per_core_bars(
-            f,
-            top_lr[1],
-            self.last_metrics.as_ref(),
-            &self.per_core_hist,
-            self.per_core_scroll,
-        );
+        // Raster charts only while nothing is drawn over them
+        let overlay = self.inspect.is_some()
+            || self.show_reboot
+            || self.show_alerts
+            || self.palette.is_some()
+            || self.search.is_some()
+            || self.show_mark_diff
+            || self.detail.is_some()
+            || self.io.is_some()
+            || self.peers.is_some()
+            || self.governor.is_some()
+            || self.show_debug;
+        let mut raster = self.raster.as_mut().filter(|_| !overlay);
 
-        // Memory + Swap rows split into left/right columns
-        let mem_lr = ratatui::layout::Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(66), Constraint::Percentage(34)])
-            .split(rows[2]);
-        let swap_lr = ratatui::layout::Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(66), Constraint::Percentage(34)])
-            .split(rows[3]);
+        // Top row: left CPU avg, right Per-core (full top-right)
+        if let Some(cpu_area) = areas.cpu {
+            match raster.as_deref_mut() {
+                Some(r) => draw_raster_panel(
+                    f,
+                    cpu_area,
+                    cpu_avg_title(self.last_metrics.as_ref()),
+                    &self.cpu_hist,
+                    Some(100),
+                    theme().cpu,
+                    r,
+                ),
+                None => draw_cpu_avg_graph(f, cpu_area, &self.cpu_hist, self.last_metrics.as_ref()),
+            }
+        }
+        if let Some(cores_area) = areas.cores {
+            draw_per_core_bars(
+                f,
+                cores_area,
+                self.last_metrics.as_ref(),
+                &self.per_core_hist,
+                self.per_core_scroll,
+                self.show_irq,
+                self.frame_budget.divisor(),
+            );
+        }
 
         // Left: Memory + Swap
-        draw_mem(f, mem_lr[0], self.last_metrics.as_ref());
-        draw_swap(f, swap_lr[0], self.last_metrics.as_ref());
+        let m = self.last_metrics.as_ref();
+        if let Some(mem_area) = areas.mem {
+            let mem_key = key_of(m.map(|mm| (mm.mem_total, mm.mem_used)));
+            self.panel_cache
+                .draw(f, "mem", mem_area, mem_key, |f| draw_mem(f, mem_area, m));
+        }
+        if let Some(swap_area) = areas.swap {
+            let swap_key = key_of(m.map(|mm| (mm.swap_total, mm.swap_used)));
+            self.panel_cache.draw(f, "swap", swap_area, swap_key, |f| {
+                draw_swap(f, swap_area, m)
+            });
+        }
 
         // Right: GPU spans the same vertical space as Memory + Swap
-        let gpu_area = ratatui::layout::Rect {
-            x: mem_lr[1].x,
-            y: mem_lr[1].y,
-            width: mem_lr[1].width,
-            height: mem_lr[1].height + swap_lr[1].height,
-        };
-        draw_gpu(f, gpu_area, self.last_metrics.as_ref());
+        if let Some(gpu_area) = areas.gpu {
+            let gpu_key = debug_key(&m.map(|mm| {
+                (
+                    &mm.gpus,
+                    mm.collector_errors.get("gpu"),
+                    mm.has_capability("gpu"),
+                )
+            }));
+            self.panel_cache.draw(f, "gpu", gpu_area, gpu_key, |f| {
+                draw_gpu(f, gpu_area, m, self.metrics_interval)
+            });
+        }
 
         // Bottom area: left = Disks + Network, right = Top Processes
-        let bottom_lr = ratatui::layout::Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
-            .split(rows[4]);
-
-        // Left bottom: Disks + Net stacked (make net panes slightly taller)
-        let left_stack = ratatui::layout::Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Min(4),    // Disks shrink slightly
-                Constraint::Length(5), // Download taller
-                Constraint::Length(5), // Upload taller
-            ])
-            .split(bottom_lr[0]);
-
-        draw_disks(f, left_stack[0], self.last_metrics.as_ref());
-        draw_net_spark(
-            f,
-            left_stack[1],
-            &format!(
+        if let Some(disks_area) = areas.disks {
+            let disks_poll = next_poll(self.last_disks_poll, self.disks_interval);
+            let highlight = self.search_query.as_deref();
+            let focus = self.disk_focus.as_deref();
+            let disks_key = debug_key(&(
+                m.map(|mm| (&mm.disks, countdown(&mm.disks, disks_poll))),
+                highlight,
+                focus,
+            ));
+            self.panel_cache
+                .draw(f, "disks", disks_area, disks_key, |f| {
+                    draw_disks(f, disks_area, m, disks_poll, highlight, focus)
+                });
+        }
+        if let (Some(rx_area), Some(tx_area)) = (areas.rx, areas.tx) {
+            let mut rx_title = format!(
                 "Download (KB/s) — now: {} | peak: {}",
                 self.rx_hist.back().copied().unwrap_or(0),
                 self.rx_peak
-            ),
-            &self.rx_hist,
-            ratatui::style::Color::Green,
-        );
-        draw_net_spark(
-            f,
-            left_stack[2],
-            &format!(
+            );
+            // The totals cover every interface; name the ones the search matched
+            if let (Some(q), Some(mm)) = (self.search_query.as_deref(), m) {
+                let hits: Vec<&str> = mm
+                    .networks
+                    .iter()
+                    .filter(|n| matches(&n.name, q))
+                    .map(|n| n.name.as_str())
+                    .collect();
+                if !hits.is_empty() {
+                    rx_title.push_str(&format!(" | match: {}", hits.join(", ")));
+                }
+            }
+            let tx_title = format!(
                 "Upload (KB/s) — now: {} | peak: {}",
                 self.tx_hist.back().copied().unwrap_or(0),
                 self.tx_peak
-            ),
-            &self.tx_hist,
-            ratatui::style::Color::Blue,
-        );
+            );
+            let rx_color = theme().rx;
+            let tx_color = theme().tx;
+            match raster {
+                Some(r) => {
+                    draw_raster_panel(f, rx_area, rx_title, &self.rx_hist, None, rx_color, r);
+                    draw_raster_panel(f, tx_area, tx_title, &self.tx_hist, None, tx_color, r);
+                }
+                None => {
+                    draw_net_spark(f, rx_area, &rx_title, &self.rx_hist, rx_color);
+                    draw_net_spark(f, tx_area, &tx_title, &self.tx_hist, tx_color);
+                }
+            }
+        }
+
+        if let (Some(back), Some(cpu), Some(rx), Some(tx)) =
+            (self.inspect, areas.cpu, areas.rx, areas.tx)
+        {
+            self.draw_inspect(f, cpu, rx, tx, back);
+        }
 
         // Right bottom: Top Processes fills the column
-        let procs_area = bottom_lr[1];
         // Cache for input handlers
-        self.last_procs_area = Some(procs_area);
-        crate::ui::processes::draw_top_processes(
-            f,
-            procs_area,
+        self.last_procs_area = areas.processes;
+        if let Some(procs_area) = areas.processes {
+            let procs_poll = next_poll(self.last_procs_poll, self.procs_interval);
+            let filter = self.process_filter().map(str::to_string);
+            let procs_key = key_of((
+                m.map(|mm| {
+                    let rows: Vec<_> = mm
+                        .top_processes
+                        .iter()
+                        .map(|p| {
+                            let trend = self.proc_trends.trend(p.pid);
+                            (p.pid, &p.name, p.cpu_usage.to_bits(), p.mem_bytes, trend)
+                        })
+                        .collect();
+                    (
+                        mm.process_count,
+                        mm.mem_total,
+                        rows,
+                        countdown(&mm.top_processes, procs_poll),
+                    )
+                }),
+                self.procs_scroll_offset,
+                self.procs_sort_by,
+                self.procs_selected,
+                self.plain,
+                &filter,
+            ));
+            self.panel_cache
+                .draw(f, "processes", procs_area, procs_key, |f| {
+                    crate::ui::processes::draw_top_processes(
+                        f,
+                        procs_area,
+                        m,
+                        &mut self.procs_scroll_offset,
+                        self.procs_sort_by,
+                        self.procs_selected,
+                        procs_poll,
+                        !self.plain,
+                        filter.as_deref(),
+                        Some(&self.proc_trends),
+                    )
+                });
+        }
+
+        // Alert flash: the firing alert's panel (the header for host-wide ones) gets a colored border
+        if let Some((key, at)) = self.alert_flash.as_ref() {
+            if at.elapsed() < ALERT_FLASH {
+                let panel = match key.as_str() {
+                    "cpu_high" | "temp_high" => areas.cpu,
+                    "mem_high" => areas.mem,
+                    "swap_high" => areas.swap,
+                    "disk_full" => areas.disks,
+                    _ => None,
+                };
+                crate::ui::alerts::draw_flash(f, panel.unwrap_or(areas.header), Severity::of(key));
+            } else {
+                self.alert_flash = None;
+            }
+        }
+
+        if self.show_reboot {
+            if let Some(r) = self
+                .last_metrics
+                .as_ref()
+                .and_then(|mm| mm.reboot_required.as_ref())
+            {
+                crate::ui::reboot::draw_reboot_popup(f, area, r);
+            }
+        }
+
+        if self.show_alerts {
+            crate::ui::alerts::draw_alert_log(f, area, &self.alerts);
+        }
+
+        if let Some(p) = self.io.as_ref() {
+            draw_io_panel(f, area, p);
+        }
+
+        if let Some(d) = self.detail.as_ref() {
+            draw_process_detail(f, area, d);
+        }
+
+        if let (true, Some(mark), Some(mm)) = (
+            self.show_mark_diff,
+            self.mark.as_ref(),
             self.last_metrics.as_ref(),
-            self.procs_scroll_offset,
-            self.procs_sort_by,
-        );
+        ) {
+            let diff = mark.diff(mm, now_secs());
+            crate::ui::mark::draw_mark_diff(f, area, &diff, mark.at);
+        }
+
+        if let Some(p) = self.peers.as_ref() {
+            crate::ui::peers::draw_peers(f, area, p);
+        }
+
+        if let Some(g) = self.governor.as_ref() {
+            crate::ui::governor::draw_governor(f, area, g);
+        }
+
+        if let Some(p) = self.palette.as_ref() {
+            crate::ui::palette::draw_palette(f, area, p);
+        }
+
+        if let Some(s) = self.search.as_ref() {
+            let hits = self
+                .last_metrics
+                .as_ref()
+                .map(|mm| find(mm, &s.query))
+                .unwrap_or_default();
+            crate::ui::search::draw_search(f, area, s, &hits);
+        }
+
+        if self.show_debug {
+            crate::ui::debug::draw_debug_overlay(
+                f,
+                area,
+                &crate::wire_stats::snapshot(),
+                self.wire_bps,
+                &self.frame_budget,
+            );
+        }
     }
 }
 
+// Time until a payload polled every `interval` (last at `last`) is requested again
+fn next_poll(last: Instant, interval: Duration) -> Duration {
+    interval.saturating_sub(last.elapsed())
+}
+
+// Part of a panel's cache key: the "next poll in" countdown, in tenths, while the panel is empty
+fn countdown<T>(rows: &[T], next_poll: Duration) -> Option<u128> {
+    rows.is_empty().then_some(next_poll.as_millis() / 100)
+}
+
 impl Default for App {
     fn default() -> Self {
         Self {
             last_metrics: None,
             cpu_hist: VecDeque::with_capacity(600),
             per_core_hist: PerCoreHistory::new(60),
-            last_net_totals: None,
+            rx_rate: RateCounter::new(),
+            tx_rate: RateCounter::new(),
             rx_hist: VecDeque::with_capacity(600),
             tx_hist: VecDeque::with_capacity(600),
             rx_peak: 0,
             tx_peak: 0,
+            sample_ts: VecDeque::with_capacity(600),
+            inspect: None,
             should_quit: false,
             per_core_scroll: 0,
             per_core_drag: None,
             procs_scroll_offset: 0,
             procs_drag: None,
             procs_sort_by: ProcSortBy::CpuDesc,
+            procs_selected: None,
             last_procs_area: None,
             last_procs_poll: Instant::now()
                 .checked_sub(Duration::from_secs(2))
@@ -504,6 +1908,60 @@ impl Default for App {
             disks_interval: Duration::from_secs(5),
             metrics_interval: Duration::from_millis(500),
             ws_url: String::new(),
+            tls_ca: None,
+            host_identity: None,
+            reconnected: false,
+            host_notice: None,
+            show_debug: false,
+            frame_budget: FrameBudget::default(),
+            panel_cache: PanelCache::default(),
+            focus: FocusThrottle::default(),
+            alerts: AlertLog::default(),
+            show_alerts: false,
+            alert_rules: AlertRules::default(),
+            cpu_alert: Hysteresis::default(),
+            mem_alert: Hysteresis::default(),
+            swap_alert: Hysteresis::default(),
+            disk_alert: Hysteresis::default(),
+            temp_alert: Hysteresis::default(),
+            alert_status: BTreeMap::new(),
+            alert_flash: None,
+            copy_notice: None,
+            show_irq: false,
+            show_reboot: false,
+            prefill_pending: true,
+            scope: None,
+            session_id: None,
+            disk_mirror: DiskMirror::default(),
+            suspect: Suspect::default(),
+            hello_pending: true,
+            subscribed: None,
+            reconnect: None,
+            replay: None,
+            profile: None,
+            silence_minutes: 60,
+            wire_rate: RateCounter::new(),
+            wire_bps: 0.0,
+            budget: None,
+            derived: Vec::new(),
+            a11y: false,
+            plain: false,
+            raster: None,
+            palette: None,
+            search: None,
+            search_query: None,
+            disk_focus: None,
+            mark: None,
+            show_mark_diff: false,
+            peers: None,
+            governor: None,
+            peer_switch: None,
+            detail: None,
+            tail: TailSampler::default(),
+            proc_trends: ProcTrends::default(),
+            layout: Layout::default(),
+            io: None,
+            a11y_shown: None,
             is_tls: false,
             has_token: false,
         }
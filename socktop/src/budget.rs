@@ -0,0 +1,84 @@
+//! Wire bandwidth budget (`--max-bandwidth`): stretch polling intervals to stay under a byte rate.
+//!
+//! Disks are the only payload with a delta form (`disks_delta`), and the client asks for deltas
+//! on every connection with a session, budget or not. Metrics and processes are always sent
+//! whole, so under a budget they are cut down instead: longer intervals and top-K processes.
+
+use std::time::Duration;
+
+// Never poll slower than this multiple of the configured intervals
+const MAX_SCALE: f64 = 60.0;
+// Rows requested per processes poll while a budget is active (agent must support `processes_top`)
+pub const BUDGET_TOP_K: usize = 30;
+
+/// Parse a bandwidth like `10kbps`, `1Mbps`, `64KB/s`, `2000` (bytes/s) into bytes per second.
+/// Lowercase `b`/`bps` means bits, uppercase `B` means bytes.
+pub fn parse_bandwidth(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: f64 = num.parse().ok()?;
+    let unit = unit.trim();
+    let unit = unit.strip_suffix("/s").unwrap_or(unit);
+    let (prefix, bits) = if let Some(p) = unit.strip_suffix("bps") {
+        (p, true)
+    } else if let Some(p) = unit.strip_suffix("Bps") {
+        (p, false)
+    } else if let Some(p) = unit.strip_suffix('B') {
+        (p, false)
+    } else if let Some(p) = unit.strip_suffix('b') {
+        (p, true)
+    } else if unit.is_empty() {
+        ("", false)
+    } else {
+        return None;
+    };
+    let base = if bits { 1000.0 } else { 1024.0 };
+    let mult = match prefix.trim_end_matches('i') {
+        "" => 1.0,
+        "k" | "K" => base,
+        "m" | "M" => base * base,
+        "g" | "G" => base * base * base,
+        _ => return None,
+    };
+    let bytes = n * mult / if bits { 8.0 } else { 1.0 };
+    (bytes >= 1.0).then_some(bytes as u64)
+}
+
+#[derive(Debug, Clone)]
+pub struct BandwidthBudget {
+    pub limit_bps: f64,
+    ewma_bps: Option<f64>,
+    scale: f64,
+    base: [Duration; 3],
+}
+
+impl BandwidthBudget {
+    /// `base` = configured (metrics, processes, disks) intervals.
+    pub fn new(limit_bytes_per_sec: u64, base: [Duration; 3]) -> Self {
+        Self {
+            limit_bps: limit_bytes_per_sec as f64,
+            ewma_bps: None,
+            scale: 1.0,
+            base,
+        }
+    }
+
+    /// Feed the latest observed rate and return the intervals to use next.
+    pub fn adjust(&mut self, observed_bps: f64) -> [Duration; 3] {
+        let avg = match self.ewma_bps {
+            Some(prev) => prev * 0.8 + observed_bps * 0.2,
+            None => observed_bps,
+        };
+        self.ewma_bps = Some(avg);
+        if avg > self.limit_bps {
+            let over = (avg / self.limit_bps).min(2.0);
+            self.scale = (self.scale * over).min(MAX_SCALE);
+        } else if avg < self.limit_bps * 0.5 {
+            self.scale = (self.scale * 0.9).max(1.0);
+        }
+        self.base.map(|d| d.mul_f64(self.scale))
+    }
+}
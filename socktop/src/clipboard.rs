@@ -0,0 +1,132 @@
+//! Copying text to the user's clipboard: `wl-copy` on Wayland, `xclip`/`xsel` on X11, and
+//! the OSC 52 escape (the terminal sets its own clipboard) everywhere else.
+//!
+//! Inside SSH sessions OSC 52 wins even when a display is forwarded: the clipboard the user
+//! pastes from is on their side of the connection. `SOCKTOP_CLIPBOARD=wl-copy|xclip|xsel|osc52`
+//! forces a mechanism.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::types::ProcessInfo;
+use crate::ui::raster::base64;
+
+// Terminals drop larger OSC 52 payloads (xterm's default limit is about this)
+const OSC52_MAX: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    WlCopy,
+    Xclip,
+    Xsel,
+    Osc52,
+}
+
+impl Backend {
+    pub fn label(self) -> &'static str {
+        match self {
+            Backend::WlCopy => "wl-copy",
+            Backend::Xclip => "xclip",
+            Backend::Xsel => "xsel",
+            Backend::Osc52 => "osc52",
+        }
+    }
+
+    fn from_label(s: &str) -> Option<Self> {
+        [
+            Backend::WlCopy,
+            Backend::Xclip,
+            Backend::Xsel,
+            Backend::Osc52,
+        ]
+        .into_iter()
+        .find(|b| b.label() == s)
+    }
+}
+
+/// The mechanism to use, given environment lookups and whether a command is on PATH.
+pub fn detect(env: impl Fn(&str) -> Option<String>, has_cmd: impl Fn(&str) -> bool) -> Backend {
+    if let Some(b) = env("SOCKTOP_CLIPBOARD").and_then(|v| Backend::from_label(&v)) {
+        return b;
+    }
+    let set = |k: &str| env(k).is_some_and(|v| !v.is_empty());
+    if set("SSH_CONNECTION") || set("SSH_TTY") {
+        return Backend::Osc52;
+    }
+    if set("WAYLAND_DISPLAY") && has_cmd("wl-copy") {
+        return Backend::WlCopy;
+    }
+    if set("DISPLAY") {
+        if has_cmd("xclip") {
+            return Backend::Xclip;
+        }
+        if has_cmd("xsel") {
+            return Backend::Xsel;
+        }
+    }
+    Backend::Osc52
+}
+
+/// OSC 52 "set clipboard" for `text`, wrapped for tmux's passthrough when `tmux` is set.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let seq = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;\x1b{seq}\x1b\\")
+    } else {
+        seq
+    }
+}
+
+/// A process row as one tab-separated line: pid, name, CPU %, resident bytes.
+pub fn process_line(p: &ProcessInfo) -> String {
+    format!("{}\t{}\t{:.1}\t{}", p.pid, p.name, p.cpu_usage, p.mem_bytes)
+}
+
+fn on_path(cmd: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|p| std::env::split_paths(&p).any(|d| d.join(cmd).is_file()))
+}
+
+fn pipe_to(cmd: &str, args: &[&str], text: &str) -> Result<(), String> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{cmd}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("{cmd}: {e}"))?;
+    }
+    match child.wait() {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(format!("{cmd} exited with {s}")),
+        Err(e) => Err(format!("{cmd}: {e}")),
+    }
+}
+
+/// Copy `text` with the detected mechanism; which one was used.
+pub fn copy(text: &str) -> Result<Backend, String> {
+    let backend = detect(|k| std::env::var(k).ok(), on_path);
+    match backend {
+        Backend::WlCopy => pipe_to("wl-copy", &[], text),
+        Backend::Xclip => pipe_to("xclip", &["-selection", "clipboard"], text),
+        Backend::Xsel => pipe_to("xsel", &["--clipboard", "--input"], text),
+        Backend::Osc52 => {
+            let seq = osc52(text, std::env::var_os("TMUX").is_some());
+            if seq.len() > OSC52_MAX {
+                return Err(format!(
+                    "too large for the terminal clipboard ({} KB)",
+                    text.len() / 1024
+                ));
+            }
+            let mut out = std::io::stdout();
+            out.write_all(seq.as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(|e| e.to_string())
+        }
+    }?;
+    Ok(backend)
+}
@@ -0,0 +1,189 @@
+//! Crash recovery: a panic hook that puts the terminal back (raw mode off, main screen,
+//! cursor shown) before anything is printed, and writes a report with the panic, a backtrace
+//! and the last metrics snapshot received from the agent to
+//! `$XDG_STATE_HOME/socktop/crash-<unix secs>.txt`, so a crash leaves context instead of a
+//! broken terminal.
+
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableFocusChange, DisableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
+};
+
+use crate::alerts::{format_utc, now_secs};
+
+// Reports kept in the state dir; older ones are removed when a new one is written
+const KEEP_REPORTS: usize = 10;
+
+#[derive(Debug, Default)]
+struct Context {
+    url: Option<String>,
+    // Last get_metrics reply (JSON) and when it arrived (Unix secs)
+    snapshot: Option<(u64, String)>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    url: None,
+    snapshot: None,
+});
+
+/// Agent the session talks to, for the report (its token is redacted).
+pub fn set_url(url: &str) {
+    if let Ok(mut c) = CONTEXT.lock() {
+        c.url = Some(url.to_string());
+    }
+}
+
+/// Keep the latest metrics reply for a report; called for every `get_metrics` reply.
+pub fn remember_snapshot(json: &str) {
+    if let Ok(mut c) = CONTEXT.lock() {
+        let (at, s) = c.snapshot.get_or_insert_with(Default::default);
+        *at = now_secs();
+        s.clear();
+        s.push_str(json);
+    }
+}
+
+/// The latest metrics reply, e.g. to export it.
+pub fn last_snapshot() -> Option<String> {
+    CONTEXT
+        .lock()
+        .ok()?
+        .snapshot
+        .as_ref()
+        .map(|(_, s)| s.clone())
+}
+
+/// `?token=secret` -> `?token=REDACTED`, leaving the rest of the URL alone.
+pub fn redact_token(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|kv| match kv.split_once('=') {
+            Some(("token", _)) => "token=REDACTED".to_string(),
+            _ => kv.to_string(),
+        })
+        .collect();
+    format!("{base}?{}", query.join("&"))
+}
+
+/// Report text for a panic with `message` at `location`.
+pub fn report(
+    message: &str,
+    location: Option<&str>,
+    backtrace: &str,
+    url: Option<&str>,
+    snapshot: Option<(u64, &str)>,
+    now: u64,
+) -> String {
+    let mut out = format!(
+        "socktop {} crashed at {}\n",
+        env!("CARGO_PKG_VERSION"),
+        format_utc(now)
+    );
+    out.push_str(&format!("panic: {message}\n"));
+    if let Some(loc) = location {
+        out.push_str(&format!("location: {loc}\n"));
+    }
+    if let Some(url) = url {
+        out.push_str(&format!("agent: {}\n", redact_token(url)));
+    }
+    out.push_str(&format!("\nbacktrace:\n{backtrace}\n"));
+    match snapshot {
+        Some((at, json)) => {
+            let pretty = serde_json::from_str::<serde_json::Value>(json)
+                .and_then(|v| serde_json::to_string_pretty(&v))
+                .unwrap_or_else(|_| json.to_string());
+            out.push_str(&format!(
+                "\nlast metrics snapshot (received {}):\n{pretty}\n",
+                format_utc(at)
+            ));
+        }
+        None => out.push_str("\nno metrics snapshot received yet\n"),
+    }
+    out
+}
+
+/// Write `text` as a new report in `dir` and drop the oldest beyond `KEEP_REPORTS`.
+pub fn write_report(dir: &Path, now: u64, text: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{now}.txt"));
+    fs::write(&path, text)?;
+    let mut reports: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("crash-") && n.ends_with(".txt"))
+        })
+        .collect();
+    // crash-<secs>.txt: the timestamp orders them
+    reports.sort_by_key(|p| {
+        p.file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix("crash-"))
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0)
+    });
+    let excess = reports.len().saturating_sub(KEEP_REPORTS);
+    for old in &reports[..excess] {
+        let _ = fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+// Undo what App::run_with set up; harmless when the TUI never started
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableFocusChange,
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    );
+    let _ = io::stdout().flush();
+}
+
+/// Install the panic hook; the default hook still prints the panic afterwards.
+pub fn install() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(non-string panic payload)".into());
+        let location = info.location().map(|l| l.to_string());
+        let backtrace = Backtrace::force_capture().to_string();
+        let now = now_secs();
+        // try_lock: the panicking thread may be the one holding it
+        let text = match CONTEXT.try_lock() {
+            Ok(c) => report(
+                &message,
+                location.as_deref(),
+                &backtrace,
+                c.url.as_deref(),
+                c.snapshot.as_ref().map(|(at, s)| (*at, s.as_str())),
+                now,
+            ),
+            Err(_) => report(&message, location.as_deref(), &backtrace, None, None, now),
+        };
+        default(info);
+        match write_report(&crate::profiles::state_dir(), now, &text) {
+            Ok(path) => eprintln!("socktop: crash report written to {}", path.display()),
+            Err(e) => eprintln!("socktop: could not write a crash report: {e}"),
+        }
+    }));
+}
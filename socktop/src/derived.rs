@@ -0,0 +1,249 @@
+//! Derived metrics: user-defined expressions over each metrics sample (e.g. `mem_used/mem_total*100`,
+//! `rx+tx`), rendered as extra gauges/sparklines.
+//! Defined in $XDG_CONFIG_HOME/socktop/derived.json (fallback ~/.config/socktop/derived.json):
+//! `[{"name": "RAM %", "expr": "mem_used/mem_total*100", "kind": "gauge", "max": 100}]`
+
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use crate::types::Metrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelKind {
+    Gauge,
+    #[default]
+    Sparkline,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DerivedDef {
+    pub name: String,
+    pub expr: String,
+    #[serde(default)]
+    pub kind: PanelKind,
+    // Gauge full scale (default 100)
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Bin(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// Parse `+ - * /`, parentheses, numbers, variables and `min/max/abs(...)`.
+    pub fn parse(src: &str) -> Result<Expr, String> {
+        let mut p = Parser {
+            chars: src.chars().filter(|c| !c.is_whitespace()).collect(),
+            pos: 0,
+        };
+        let e = p.sum()?;
+        if p.pos != p.chars.len() {
+            return Err(format!("unexpected '{}' at {}", p.chars[p.pos], p.pos));
+        }
+        Ok(e)
+    }
+
+    /// Evaluate against named values; unknown variables and bad calls yield None.
+    pub fn eval(&self, vars: &HashMap<&str, f64>) -> Option<f64> {
+        let v = match self {
+            Expr::Num(n) => *n,
+            Expr::Var(name) => *vars.get(name.as_str())?,
+            Expr::Neg(e) => -e.eval(vars)?,
+            Expr::Bin(op, a, b) => {
+                let (a, b) = (a.eval(vars)?, b.eval(vars)?);
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ => a / b,
+                }
+            }
+            Expr::Call(f, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(vars))
+                    .collect::<Option<Vec<_>>>()?;
+                match (f.as_str(), args.as_slice()) {
+                    ("abs", [x]) => x.abs(),
+                    ("min", [x, rest @ ..]) => rest.iter().fold(*x, |m, v| m.min(*v)),
+                    ("max", [x, rest @ ..]) => rest.iter().fold(*x, |m, v| m.max(*v)),
+                    _ => return None,
+                }
+            }
+        };
+        v.is_finite().then_some(v)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.pos += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let e = self.sum()?;
+                self.expect(')')?;
+                Ok(e)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                let s: String = self.chars[start..self.pos].iter().collect();
+                s.parse()
+                    .map(Expr::Num)
+                    .map_err(|_| format!("bad number '{s}'"))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                if self.peek() != Some('(') {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = vec![self.sum()?];
+                while self.peek() == Some(',') {
+                    self.pos += 1;
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+                Ok(Expr::Call(name, args))
+            }
+            Some(c) => Err(format!("unexpected '{c}' at {}", self.pos)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{c}' at {}", self.pos))
+        }
+    }
+}
+
+/// Values available to expressions. `rx`/`tx` are the client-computed network rates in KB/s.
+pub fn sample_vars(m: &Metrics, rx_kb: u64, tx_kb: u64) -> HashMap<&'static str, f64> {
+    let mut v = HashMap::new();
+    v.insert("cpu_total", m.cpu_total as f64);
+    v.insert("cores", m.cpu_per_core.len() as f64);
+    v.insert("mem_used", m.mem_used as f64);
+    v.insert("mem_total", m.mem_total as f64);
+    v.insert("swap_used", m.swap_used as f64);
+    v.insert("swap_total", m.swap_total as f64);
+    v.insert("rx", rx_kb as f64);
+    v.insert("tx", tx_kb as f64);
+    if let Some(t) = m.cpu_temp_c {
+        v.insert("cpu_temp", t as f64);
+    }
+    if let Some(u) = m.uptime_secs {
+        v.insert("uptime", u as f64);
+    }
+    if let Some(n) = m.process_count {
+        v.insert("process_count", n as f64);
+    }
+    if !m.disks.is_empty() {
+        let total: u64 = m.disks.iter().map(|d| d.total).sum();
+        let avail: u64 = m.disks.iter().map(|d| d.available).sum();
+        v.insert("disk_total", total as f64);
+        v.insert("disk_used", total.saturating_sub(avail) as f64);
+    }
+    v
+}
+
+pub struct DerivedMetric {
+    pub def: DerivedDef,
+    expr: Expr,
+    pub last: Option<f64>,
+    pub hist: VecDeque<u64>,
+}
+
+impl DerivedMetric {
+    pub fn new(def: DerivedDef) -> Result<Self, String> {
+        let expr = Expr::parse(&def.expr).map_err(|e| format!("{}: {e}", def.name))?;
+        Ok(Self {
+            def,
+            expr,
+            last: None,
+            hist: VecDeque::with_capacity(600),
+        })
+    }
+
+    pub fn update(&mut self, vars: &HashMap<&str, f64>) {
+        self.last = self.expr.eval(vars);
+        let v = self.last.unwrap_or(0.0).max(0.0).round() as u64;
+        crate::history::push_capped(&mut self.hist, v, 600);
+    }
+}
+
+/// Load and compile definitions; invalid entries are reported on stderr and skipped.
+pub fn load_from(path: &Path) -> Vec<DerivedMetric> {
+    let Ok(s) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let defs: Vec<DerivedDef> = match serde_json::from_str(&s) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("socktop: ignoring {}: {e}", path.display());
+            return Vec::new();
+        }
+    };
+    defs.into_iter()
+        .filter_map(|d| {
+            DerivedMetric::new(d)
+                .map_err(|e| eprintln!("socktop: derived metric {e}"))
+                .ok()
+        })
+        .collect()
+}
@@ -0,0 +1,37 @@
+//! Client copy of the disk list kept current from `get_disks?delta=SEQ` replies.
+
+use crate::types::{DiskInfo, DisksDelta};
+
+#[derive(Debug, Default)]
+pub struct DiskMirror {
+    seq: u64,
+    disks: Vec<DiskInfo>,
+}
+
+impl DiskMirror {
+    /// `seq` of the last applied reply, to send with the next request (0: ask for everything).
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn disks(&self) -> &[DiskInfo] {
+        &self.disks
+    }
+
+    /// Apply a reply: a full list replaces everything; a delta updates entries in place by
+    /// name, appends new ones and drops the removed names.
+    pub fn apply(&mut self, delta: DisksDelta) {
+        self.seq = delta.seq;
+        if delta.full {
+            self.disks = delta.disks;
+            return;
+        }
+        self.disks.retain(|d| !delta.removed.contains(&d.name));
+        for d in delta.disks {
+            match self.disks.iter_mut().find(|e| e.name == d.name) {
+                Some(e) => *e = d,
+                None => self.disks.push(d),
+            }
+        }
+    }
+}
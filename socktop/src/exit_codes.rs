@@ -0,0 +1,115 @@
+//! Process exit codes and error reporting for scripts that launch socktop (`--error-format json`).
+
+use crate::preflight::{PreflightError, Stage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Normal exit, including the user quitting the TUI (main returning Ok)
+    #[allow(dead_code)]
+    Quit = 0,
+    /// Anything not covered below (terminal setup, I/O, ...)
+    Error = 1,
+    /// Bad command line or nothing to connect to
+    Usage = 2,
+    /// URL/DNS/TCP/WebSocket-level connection failure
+    Connect = 3,
+    /// Agent rejected (or requires) a token
+    Auth = 4,
+    /// TLS handshake / certificate failure
+    Tls = 5,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    fn kind(self) -> &'static str {
+        match self {
+            ExitStatus::Quit => "ok",
+            ExitStatus::Error => "error",
+            ExitStatus::Usage => "usage",
+            ExitStatus::Connect => "connect",
+            ExitStatus::Auth => "auth",
+            ExitStatus::Tls => "tls",
+        }
+    }
+}
+
+impl From<Stage> for ExitStatus {
+    fn from(s: Stage) -> Self {
+        match s {
+            Stage::Url => ExitStatus::Usage,
+            Stage::Dns | Stage::Tcp | Stage::Upgrade => ExitStatus::Connect,
+            Stage::Tls => ExitStatus::Tls,
+            Stage::Auth => ExitStatus::Auth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn parse(v: &str) -> Self {
+        if v.eq_ignore_ascii_case("json") {
+            ErrorFormat::Json
+        } else {
+            ErrorFormat::Text
+        }
+    }
+
+    /// Scan raw args for `--error-format json` (also needed when normal parsing fails).
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut it = args.into_iter();
+        while let Some(a) = it.next() {
+            let v = if a == "--error-format" {
+                it.next()
+            } else {
+                a.strip_prefix("--error-format=").map(str::to_string)
+            };
+            if let Some(v) = v {
+                return ErrorFormat::parse(&v);
+            }
+        }
+        ErrorFormat::Text
+    }
+}
+
+/// Print an error in the requested format and exit with the matching status code.
+pub fn exit_with(
+    format: ErrorFormat,
+    status: ExitStatus,
+    stage: Option<Stage>,
+    message: &str,
+    hint: Option<&str>,
+) -> ! {
+    match format {
+        ErrorFormat::Text => {
+            eprintln!("{message}");
+            if let Some(h) = hint {
+                eprintln!("  hint: {h}");
+            }
+        }
+        ErrorFormat::Json => {
+            let v = serde_json::json!({
+                "error": status.kind(),
+                "code": status.code(),
+                "stage": stage.map(|s| s.to_string()),
+                "message": message,
+                "hint": hint,
+            });
+            eprintln!("{v}");
+        }
+    }
+    std::process::exit(status.code())
+}
+
+pub fn exit_preflight(format: ErrorFormat, e: &PreflightError) -> ! {
+    let msg = format!("socktop: {} failed: {}", e.stage, e.detail);
+    exit_with(format, e.stage.into(), Some(e.stage), &msg, e.advice)
+}
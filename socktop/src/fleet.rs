@@ -0,0 +1,208 @@
+//! `socktop fleet snapshot`: connect to every profile (optionally one `groups` entry) in
+//! parallel, take one metrics + disks sample each, and print a combined capacity report.
+
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use serde::Serialize;
+use tokio::time::timeout;
+
+use crate::profiles::ProfileEntry;
+use crate::tunnel::SshTunnel;
+use crate::types::{DiskInfo, Metrics};
+use crate::ws::{auth_token, connect_with_token, request_disks, request_metrics};
+
+// Per-host budget for connect + both requests; one slow host doesn't hold up the report
+pub const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct SnapshotRow {
+    pub profile: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub hostname: String,
+    pub cores: usize,
+    pub cpu_pct: f32,
+    pub mem_used: u64,
+    pub mem_total: u64,
+    pub mem_pct: f64,
+    pub swap_used: u64,
+    pub swap_total: u64,
+    pub disk_used: u64,
+    pub disk_total: u64,
+    pub disk_pct: f64,
+    pub processes: usize,
+}
+
+fn pct(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (used as f64 / total as f64 * 1000.0).round() / 10.0
+    }
+}
+
+impl SnapshotRow {
+    pub fn from_sample(profile: &str, m: &Metrics, disks: &[DiskInfo]) -> Self {
+        let disk_total: u64 = disks.iter().map(|d| d.total).sum();
+        let disk_used: u64 = disks
+            .iter()
+            .map(|d| d.total.saturating_sub(d.available))
+            .sum();
+        SnapshotRow {
+            profile: profile.to_string(),
+            ok: true,
+            error: None,
+            hostname: m.hostname.clone(),
+            cores: m.cpu_per_core.len(),
+            cpu_pct: (m.cpu_total * 10.0).round() / 10.0,
+            mem_used: m.mem_used,
+            mem_total: m.mem_total,
+            mem_pct: pct(m.mem_used, m.mem_total),
+            swap_used: m.swap_used,
+            swap_total: m.swap_total,
+            disk_used,
+            disk_total,
+            disk_pct: pct(disk_used, disk_total),
+            processes: m.process_count.unwrap_or(m.top_processes.len()),
+        }
+    }
+
+    pub fn failed(profile: &str, error: String) -> Self {
+        SnapshotRow {
+            profile: profile.to_string(),
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Profiles in `group` (all profiles when None), by name.
+pub fn members<'a>(
+    profiles: impl IntoIterator<Item = (&'a String, &'a ProfileEntry)>,
+    group: Option<&str>,
+) -> Vec<(String, ProfileEntry)> {
+    profiles
+        .into_iter()
+        .filter(|(_, e)| group.is_none_or(|g| e.groups.iter().any(|x| x == g)))
+        .map(|(n, e)| (n.clone(), e.clone()))
+        .collect()
+}
+
+/// Sample every member concurrently; rows come back in the members' order.
+pub async fn snapshot(members: Vec<(String, ProfileEntry)>) -> Vec<SnapshotRow> {
+    join_all(members.iter().map(|(name, entry)| async move {
+        match timeout(SNAPSHOT_TIMEOUT, sample(entry)).await {
+            Ok(Ok((m, d))) => SnapshotRow::from_sample(name, &m, &d),
+            Ok(Err(e)) => SnapshotRow::failed(name, e),
+            Err(_) => SnapshotRow::failed(name, format!("timed out after {SNAPSHOT_TIMEOUT:?}")),
+        }
+    }))
+    .await
+}
+
+async fn sample(entry: &ProfileEntry) -> Result<(Metrics, Vec<DiskInfo>), String> {
+    // Keep the tunnel (if any) alive until both replies are in
+    let (_tunnel, url) = match entry.ssh.clone() {
+        Some(dest) => {
+            let (jumps, url) = (entry.ssh_jump.clone(), entry.url.clone());
+            let (t, local) =
+                tokio::task::spawn_blocking(move || SshTunnel::open(&dest, &jumps, &url))
+                    .await
+                    .map_err(|e| e.to_string())??;
+            (Some(t), local)
+        }
+        None => (None, entry.url.clone()),
+    };
+    let token = entry.token.clone().or_else(auth_token);
+    let mut ws = connect_with_token(&url, entry.tls_ca.as_deref(), token.as_deref())
+        .await
+        .map_err(|e| format!("connect {url}: {e}"))?;
+    let m = request_metrics(&mut ws)
+        .await
+        .ok_or_else(|| "no metrics reply".to_string())?;
+    // Older agents without get_disks still give a usable row
+    let disks = match request_disks(&mut ws).await {
+        Some(d) => d,
+        None => m.disks.clone(),
+    };
+    let _ = ws.close(None).await;
+    Ok((m, disks))
+}
+
+const CSV_HEADER: &str = "profile,ok,error,hostname,cores,cpu_pct,mem_used,mem_total,mem_pct,swap_used,swap_total,disk_used,disk_total,disk_pct,processes";
+
+pub fn to_csv(rows: &[SnapshotRow]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for r in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&r.profile),
+            r.ok,
+            csv_field(r.error.as_deref().unwrap_or("")),
+            csv_field(&r.hostname),
+            r.cores,
+            r.cpu_pct,
+            r.mem_used,
+            r.mem_total,
+            r.mem_pct,
+            r.swap_used,
+            r.swap_total,
+            r.disk_used,
+            r.disk_total,
+            r.disk_pct,
+            r.processes
+        ));
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Aligned text table for terminals (the default format).
+pub fn to_table(rows: &[SnapshotRow]) -> String {
+    let gb = |b: u64| b as f64 / (1024.0 * 1024.0 * 1024.0);
+    let mut out = format!(
+        "{:<16} {:<20} {:>5} {:>6} {:>15} {:>17} {:>6}\n",
+        "PROFILE", "HOST", "CORES", "CPU%", "MEM GB (%)", "DISK GB (%)", "PROCS"
+    );
+    for r in rows {
+        if !r.ok {
+            out.push_str(&format!(
+                "{:<16} error: {}\n",
+                r.profile,
+                r.error.as_deref().unwrap_or("unknown")
+            ));
+            continue;
+        }
+        out.push_str(&format!(
+            "{:<16} {:<20} {:>5} {:>6.1} {:>15} {:>17} {:>6}\n",
+            r.profile,
+            r.hostname,
+            r.cores,
+            r.cpu_pct,
+            format!(
+                "{:.1}/{:.1} ({:.0}%)",
+                gb(r.mem_used),
+                gb(r.mem_total),
+                r.mem_pct
+            ),
+            format!(
+                "{:.0}/{:.0} ({:.0}%)",
+                gb(r.disk_used),
+                gb(r.disk_total),
+                r.disk_pct
+            ),
+            r.processes
+        ));
+    }
+    out
+}
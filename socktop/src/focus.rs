@@ -0,0 +1,71 @@
+//! Self-throttling while the terminal pane is unfocused: redraw rarely and, when configured,
+//! poll the agent less often. Focus events restore the normal rate right away.
+
+use std::time::{Duration, Instant};
+
+// Redraw interval while unfocused (charts still advance, just in bigger steps)
+pub const UNFOCUSED_REDRAW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct FocusThrottle {
+    focused: bool,
+    // `--unfocused-poll-ms`: agent poll interval while unfocused (None keeps the normal rate)
+    unfocused_poll: Option<Duration>,
+    last_fetch: Option<Instant>,
+    last_draw: Option<Instant>,
+}
+
+impl Default for FocusThrottle {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl FocusThrottle {
+    pub fn new(unfocused_poll: Option<Duration>) -> Self {
+        Self {
+            focused: true,
+            unfocused_poll,
+            last_fetch: None,
+            last_draw: None,
+        }
+    }
+
+    /// Terminals that never report focus stay "focused", so nothing is throttled for them.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if focused {
+            // Catch up immediately instead of waiting out the unfocused intervals
+            self.last_fetch = None;
+            self.last_draw = None;
+        }
+    }
+
+    pub fn fetch_due(&self, now: Instant) -> bool {
+        match self.unfocused_poll.filter(|_| !self.focused) {
+            Some(every) => due(self.last_fetch, every, now),
+            None => true,
+        }
+    }
+
+    /// `--unfocused-poll-ms` is set, so the fetch rate depends on focus.
+    pub fn throttles_fetch(&self) -> bool {
+        self.unfocused_poll.is_some()
+    }
+
+    pub fn draw_due(&self, now: Instant) -> bool {
+        self.focused || due(self.last_draw, UNFOCUSED_REDRAW, now)
+    }
+
+    pub fn fetched(&mut self, now: Instant) {
+        self.last_fetch = Some(now);
+    }
+
+    pub fn drawn(&mut self, now: Instant) {
+        self.last_draw = Some(now);
+    }
+}
+
+fn due(last: Option<Instant>, every: Duration, now: Instant) -> bool {
+    last.is_none_or(|t| now.saturating_duration_since(t) >= every)
+}
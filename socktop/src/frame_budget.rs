@@ -0,0 +1,97 @@
+//! Frame render time budget: when `terminal.draw` runs over budget, per-core charts keep fewer
+//! points until redraws are fast again. Adjustments are logged for the debug overlay.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::history::push_capped;
+
+pub const FRAME_BUDGET: Duration = Duration::from_millis(40);
+// Chart points are divided by 2^level; level 3 keeps 1/8 of them
+const MAX_LEVEL: u8 = 3;
+// Consecutive frames under half the budget before restoring one level of detail
+const RECOVER_AFTER: u32 = 20;
+const LOG_CAP: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct FrameBudget {
+    budget: Duration,
+    last: Duration,
+    level: u8,
+    fast_streak: u32,
+    log: VecDeque<String>,
+}
+
+impl Default for FrameBudget {
+    fn default() -> Self {
+        Self::new(FRAME_BUDGET)
+    }
+}
+
+impl FrameBudget {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            last: Duration::ZERO,
+            level: 0,
+            fast_streak: 0,
+            log: VecDeque::with_capacity(LOG_CAP),
+        }
+    }
+
+    /// Record how long the last frame took and adjust chart detail.
+    pub fn record(&mut self, took: Duration) {
+        self.last = took;
+        if took > self.budget {
+            self.fast_streak = 0;
+            if self.level < MAX_LEVEL {
+                self.level += 1;
+                self.note(format!(
+                    "frame {:.1}ms > {}ms budget: chart points 1/{}",
+                    ms(took),
+                    self.budget.as_millis(),
+                    self.divisor()
+                ));
+            }
+        } else if took < self.budget / 2 && self.level > 0 {
+            self.fast_streak += 1;
+            if self.fast_streak >= RECOVER_AFTER {
+                self.fast_streak = 0;
+                self.level -= 1;
+                self.note(format!(
+                    "frames under {}ms: chart points 1/{}",
+                    self.budget.as_millis() / 2,
+                    self.divisor()
+                ));
+            }
+        } else {
+            self.fast_streak = 0;
+        }
+    }
+
+    /// Chart point counts are divided by this at the current detail level.
+    pub fn divisor(&self) -> usize {
+        1 << self.level
+    }
+
+    pub fn last(&self) -> Duration {
+        self.last
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Most recent adjustments, oldest first.
+    pub fn log(&self) -> impl Iterator<Item = &str> {
+        self.log.iter().map(String::as_str)
+    }
+
+    fn note(&mut self, msg: String) {
+        push_capped(&mut self.log, msg, LOG_CAP);
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,44 @@
+//! Checked binary frames (`--frame-check`): the agent prefixes every binary reply with
+//! `"SKF1" | payload length (u32 LE) | CRC-32 (u32 LE)`, and frames that arrive short or don't
+//! match their checksum are dropped and counted (debug overlay) instead of failing to decode
+//! somewhere further down. Agents without `frame_crc` keep sending plain frames, which pass
+//! through untouched.
+
+use flate2::Crc;
+
+pub const MAGIC: &[u8; 4] = b"SKF1";
+const HEADER_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// Fewer payload bytes than the header announced (or more)
+    Length,
+    Checksum,
+}
+
+/// `--frame-check` (or `SOCKTOP_FRAME_CHECK=1`) is on: ask for checked frames when connecting.
+pub fn enabled() -> bool {
+    std::env::var("SOCKTOP_FRAME_CHECK").ok().as_deref() == Some("1")
+}
+
+/// The payload of a checked frame; None when `frame` isn't one (an agent sending plain frames).
+pub fn unwrap(frame: &[u8]) -> Option<Result<&[u8], FrameError>> {
+    if !frame.starts_with(MAGIC) {
+        return None;
+    }
+    if frame.len() < HEADER_LEN {
+        return Some(Err(FrameError::Length));
+    }
+    let word =
+        |at: usize| u32::from_le_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]]);
+    let payload = &frame[HEADER_LEN..];
+    if payload.len() != word(4) as usize {
+        return Some(Err(FrameError::Length));
+    }
+    let mut crc = Crc::new();
+    crc.update(payload);
+    if crc.sum() != word(8) {
+        return Some(Err(FrameError::Checksum));
+    }
+    Some(Ok(payload))
+}
@@ -0,0 +1,87 @@
+//! CPU governor popup ('g'): each core's cpufreq governor (`get_cpufreq`), and switching all
+//! of them to another one the driver offers, e.g. `performance` for a benchmark.
+//!
+//! Switching changes the host, so Enter asks for confirmation first; it needs a control-scope
+//! token and an agent started with `--cpufreq-control` (`cpufreq_control` capability).
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::types::CpuFreq;
+
+#[derive(Debug, Clone, Default)]
+pub struct GovernorPicker {
+    /// Why nothing is fetched (agent without the `cpufreq` capability)
+    pub note: Option<String>,
+    /// None until the agent answered
+    pub info: Option<CpuFreq>,
+    pub selected: usize,
+    /// Why the list is view-only (read-only token, control off on the agent)
+    pub locked: Option<String>,
+    /// Governor awaiting y/n
+    pub confirm: Option<String>,
+    /// Confirmed governor, sent with the next poll
+    pub apply: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GovernorOutcome {
+    Open,
+    Close,
+}
+
+impl GovernorPicker {
+    /// Waiting for the `get_cpufreq` reply.
+    pub fn pending(&self) -> bool {
+        self.note.is_none() && self.info.is_none()
+    }
+
+    /// Take a `get_cpufreq`/`set_governor` reply. An agent without `--cpufreq-control` says so
+    /// in it, and the list turns view-only rather than offering a switch it would refuse.
+    pub fn set_info(&mut self, info: CpuFreq) {
+        if !info.control && self.locked.is_none() {
+            self.locked =
+                Some("Switching is off on this agent (start it with --cpufreq-control)".into());
+        }
+        self.info = Some(info);
+    }
+
+    /// The governor all cores share, "mixed" when they differ.
+    pub fn current(&self) -> Option<&str> {
+        let cores = &self.info.as_ref()?.cores;
+        let first = cores.first()?.governor.as_str();
+        if cores.iter().all(|c| c.governor == first) {
+            Some(first)
+        } else {
+            Some("mixed")
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> GovernorOutcome {
+        if let Some(name) = self.confirm.take() {
+            if matches!(
+                key.code,
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter
+            ) {
+                self.apply = Some(name);
+            }
+            return GovernorOutcome::Open;
+        }
+        let count = self.info.as_ref().map_or(0, |i| i.available.len());
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('g') | KeyCode::Char('G') => {
+                return GovernorOutcome::Close
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Enter if self.locked.is_none() && self.apply.is_none() => {
+                let name = self
+                    .info
+                    .as_ref()
+                    .and_then(|i| i.available.get(self.selected));
+                self.confirm = name.cloned();
+            }
+            _ => {}
+        }
+        GovernorOutcome::Open
+    }
+}
@@ -0,0 +1,54 @@
+//! Panel placeholders: say why a panel is empty and what to do about it, from the agent's
+//! collector capabilities and reported collector errors.
+
+use std::time::Duration;
+
+use crate::types::Metrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Gpu,
+    Temp,
+    Disks,
+    Processes,
+}
+
+/// Placeholder text for `panel`, or None when it has data to show. `next_poll` is the time until
+/// the client next asks the agent for this panel's payload.
+pub fn empty_hint(m: Option<&Metrics>, panel: Panel, next_poll: Duration) -> Option<String> {
+    let no_data = || format!("No data yet (next poll in {:.1}s)", next_poll.as_secs_f32());
+    let Some(mm) = m else {
+        return Some(no_data());
+    };
+    // None for older agents that don't report collector flags
+    let enabled = |cap: &str| {
+        mm.has_capability("collectors")
+            .then(|| mm.has_capability(cap))
+    };
+    match panel {
+        Panel::Gpu => {
+            if mm.gpus.as_ref().is_some_and(|g| !g.is_empty()) {
+                None
+            } else if let Some(err) = mm.collector_errors.get("gpu") {
+                Some(format!("GPU collector failed: {err}"))
+            } else if enabled("gpu") == Some(false) {
+                Some("GPU disabled on agent (SOCKTOP_AGENT_GPU=0)".into())
+            } else {
+                Some("No GPUs detected".into())
+            }
+        }
+        Panel::Temp => {
+            if mm.cpu_temp_c.is_some() {
+                None
+            } else if enabled("temp") == Some(false) {
+                Some("Temp disabled on agent (SOCKTOP_AGENT_TEMP=0)".into())
+            } else if let Some(err) = mm.collector_errors.get("temp") {
+                Some(format!("Temp collector failed: {err}"))
+            } else {
+                Some("Temp N/A (no CPU sensor)".into())
+            }
+        }
+        Panel::Disks => mm.disks.is_empty().then(no_data),
+        Panel::Processes => mm.top_processes.is_empty().then(no_data),
+    }
+}
@@ -1,6 +1,17 @@
 //! Small utilities to manage bounded history buffers for charts.
 
 use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::types::HistoryPoint;
+
+/// Unix time in milliseconds (chart sample timestamps).
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
 
 pub fn push_capped<T>(dq: &mut VecDeque<T>, v: T, cap: usize) {
     if dq.len() == cap {
@@ -9,6 +20,49 @@ pub fn push_capped<T>(dq: &mut VecDeque<T>, v: T, cap: usize) {
     dq.push_back(v);
 }
 
+/// Re-insert offline cores (as the default/0% value) so row `i` is always CPU id `i`, even across
+/// hotplug. `online` holds the online cores' values in ascending id order.
+pub fn stable_core_rows<T: Copy + Default>(online: &[T], offline: &[u32]) -> Vec<T> {
+    if offline.is_empty() {
+        return online.to_vec();
+    }
+    let total = online.len() + offline.len();
+    let mut values = online.iter();
+    (0..total as u32)
+        .map(|id| {
+            if offline.contains(&id) {
+                T::default()
+            } else {
+                values.next().copied().unwrap_or_default()
+            }
+        })
+        .collect()
+}
+
+/// Spread downsampled agent history over the client's sample rate: each point is repeated
+/// `step / interval` times (1..=100) so prefilled charts keep the live time scale. Each copy is
+/// paired with its interpolated Unix time in milliseconds.
+pub fn expand_history(
+    points: &[HistoryPoint],
+    step_secs: u64,
+    interval: Duration,
+) -> Vec<(u64, &HistoryPoint)> {
+    let interval_ms = interval.as_millis().max(1) as u64;
+    let per_step = (step_secs * 1000 / interval_ms).clamp(1, 100);
+    points
+        .iter()
+        .flat_map(|p| (0..per_step).map(move |k| (p.ts * 1000 + k * interval_ms, p)))
+        .collect()
+}
+
+/// Column (0-based, inside the chart borders) of the sample `back` steps before the newest one,
+/// for a chart `width` cells wide drawn from the last `width` of `len` samples. None when the
+/// sample has scrolled out of view.
+pub fn crosshair_column(len: usize, width: usize, back: usize) -> Option<usize> {
+    let visible = len.min(width);
+    (back < visible).then(|| visible - 1 - back)
+}
+
 // Keeps a history deque per core with a fixed capacity
 pub struct PerCoreHistory {
     pub deques: Vec<VecDeque<u16>>,
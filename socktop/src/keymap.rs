@@ -0,0 +1,164 @@
+//! Key bindings as named actions, so the keyboard handler and the `Ctrl-P` command palette
+//! run the same code paths.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    OpenPalette,
+    OpenSearch,
+    ToggleDebug,
+    ToggleAlerts,
+    ToggleReboot,
+    ToggleIrq,
+    ToggleMaintenance,
+    ToggleInspect,
+    ToggleIo,
+    SetMark,
+    ToggleMarkDiff,
+    ShowPeers,
+    ShowGovernor,
+    SortByCpu,
+    SortByMem,
+    SortByTime,
+    ClearSelection,
+    ToggleProcessDetail,
+    CopyProcess,
+    CopySnapshot,
+    // --replay only: Space, '[' and ']'
+    ReplayPause,
+    ReplaySeekBack,
+    ReplaySeekForward,
+    // Metrics poll interval in milliseconds (processes follow at 4x)
+    SetInterval(u64),
+}
+
+// Interval presets offered in the palette
+const INTERVALS_MS: [u64; 5] = [250, 500, 1_000, 2_000, 5_000];
+
+impl Action {
+    /// Everything the palette can run, in display order.
+    pub fn all() -> Vec<Action> {
+        let mut out = vec![
+            Action::OpenSearch,
+            Action::SortByCpu,
+            Action::SortByMem,
+            Action::SortByTime,
+            Action::ClearSelection,
+            Action::ToggleProcessDetail,
+            Action::CopyProcess,
+            Action::CopySnapshot,
+            Action::ToggleAlerts,
+            Action::ToggleIo,
+            Action::SetMark,
+            Action::ToggleMarkDiff,
+            Action::ShowPeers,
+            Action::ShowGovernor,
+            Action::ToggleIrq,
+            Action::ToggleReboot,
+            Action::ToggleDebug,
+            Action::ToggleInspect,
+            Action::ToggleMaintenance,
+        ];
+        out.extend(INTERVALS_MS.iter().map(|&ms| Action::SetInterval(ms)));
+        out.push(Action::Quit);
+        out
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Action::Quit => "Quit".into(),
+            Action::OpenPalette => "Command palette".into(),
+            Action::OpenSearch => "Search everything, filter processes by name or PID".into(),
+            Action::ToggleDebug => "Toggle debug overlay".into(),
+            Action::ToggleAlerts => "Toggle alert log panel".into(),
+            Action::ToggleReboot => "Toggle reboot-required details".into(),
+            Action::ToggleIrq => "Toggle per-core irq/softirq view".into(),
+            Action::ToggleMaintenance => "Toggle maintenance window (silence alerts)".into(),
+            Action::ToggleInspect => "Toggle chart inspect mode".into(),
+            Action::ToggleIo => {
+                "Toggle I/O wait panel (pressure, disk busy %, top I/O processes)".into()
+            }
+            Action::SetMark => "Mark this moment for a before/after diff".into(),
+            Action::ToggleMarkDiff => {
+                "Toggle changes since the mark (CPU time, network bytes, disk space)".into()
+            }
+            Action::ShowPeers => "Switch to a peer agent (gossip discovery)".into(),
+            Action::ShowGovernor => "Show or switch the CPU frequency governor".into(),
+            Action::SortByCpu => "Sort processes by CPU".into(),
+            Action::SortByMem => "Sort processes by memory".into(),
+            Action::SortByTime => "Sort processes by CPU time (Time+)".into(),
+            Action::ClearSelection => "Clear process selection".into(),
+            Action::ToggleProcessDetail => "Toggle selected process env & open files".into(),
+            Action::CopyProcess => "Copy selected process to the clipboard".into(),
+            Action::CopySnapshot => {
+                "Copy the latest metrics snapshot (JSON) to the clipboard".into()
+            }
+            Action::ReplayPause => "Pause or resume the replay".into(),
+            Action::ReplaySeekBack => "Replay: back 10s".into(),
+            Action::ReplaySeekForward => "Replay: forward 10s".into(),
+            Action::SetInterval(ms) if ms % 1_000 == 0 => {
+                format!("Set poll interval: {}s", ms / 1_000)
+            }
+            Action::SetInterval(ms) => format!("Set poll interval: {ms}ms"),
+        }
+    }
+
+    /// Direct key for the action, shown next to it in the palette.
+    pub fn key_hint(&self) -> Option<&'static str> {
+        match self {
+            Action::Quit => Some("q"),
+            Action::OpenPalette => Some("Ctrl-P"),
+            Action::OpenSearch => Some("/"),
+            Action::ToggleDebug => Some("d"),
+            Action::ToggleAlerts => Some("a"),
+            Action::ToggleReboot => Some("r"),
+            Action::ToggleIrq => Some("i"),
+            Action::ToggleMaintenance => Some("m"),
+            Action::ToggleInspect => Some("x"),
+            Action::ToggleIo => Some("o"),
+            Action::SetMark => Some("b"),
+            Action::ToggleMarkDiff => Some("v"),
+            Action::ShowPeers => Some("f"),
+            Action::ShowGovernor => Some("g"),
+            Action::ToggleProcessDetail => Some("Enter"),
+            Action::CopyProcess => Some("c"),
+            Action::ReplayPause => Some("Space"),
+            Action::ReplaySeekBack => Some("["),
+            Action::ReplaySeekForward => Some("]"),
+            _ => None,
+        }
+    }
+}
+
+/// The action bound to `key` on the dashboard, if any.
+pub fn action_for(key: KeyEvent) -> Option<Action> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return match key.code {
+            KeyCode::Char('p') | KeyCode::Char('P') => Some(Action::OpenPalette),
+            _ => None,
+        };
+    }
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => Some(Action::Quit),
+        KeyCode::Char('d') | KeyCode::Char('D') => Some(Action::ToggleDebug),
+        KeyCode::Char('a') | KeyCode::Char('A') => Some(Action::ToggleAlerts),
+        KeyCode::Char('r') | KeyCode::Char('R') => Some(Action::ToggleReboot),
+        KeyCode::Char('i') | KeyCode::Char('I') => Some(Action::ToggleIrq),
+        KeyCode::Char('m') | KeyCode::Char('M') => Some(Action::ToggleMaintenance),
+        KeyCode::Char('x') | KeyCode::Char('X') => Some(Action::ToggleInspect),
+        KeyCode::Char('o') | KeyCode::Char('O') => Some(Action::ToggleIo),
+        KeyCode::Char('b') | KeyCode::Char('B') => Some(Action::SetMark),
+        KeyCode::Char('v') | KeyCode::Char('V') => Some(Action::ToggleMarkDiff),
+        KeyCode::Char('f') | KeyCode::Char('F') => Some(Action::ShowPeers),
+        KeyCode::Char('g') | KeyCode::Char('G') => Some(Action::ShowGovernor),
+        KeyCode::Char('c') | KeyCode::Char('C') => Some(Action::CopyProcess),
+        KeyCode::Enter => Some(Action::ToggleProcessDetail),
+        KeyCode::Char('/') => Some(Action::OpenSearch),
+        KeyCode::Char(' ') => Some(Action::ReplayPause),
+        KeyCode::Char('[') => Some(Action::ReplaySeekBack),
+        KeyCode::Char(']') => Some(Action::ReplaySeekForward),
+        _ => None,
+    }
+}
@@ -0,0 +1,256 @@
+//! Dashboard layout: which panels are shown and whether one of them is maximized, set per
+//! profile (`"layout": "disks"`, `"panels": ["cpu", "mem", "disks", "net"]` in
+//! profiles.json) and applied when the TUI starts. Hidden panels give their space to the
+//! rest of their row or column; a maximized panel gets everything below the header.
+
+use ratatui::layout::{Constraint, Direction, Layout as Split, Rect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Cpu,
+    Cores,
+    Mem,
+    Swap,
+    Gpu,
+    Disks,
+    Net,
+    Processes,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 8] = [
+        Panel::Cpu,
+        Panel::Cores,
+        Panel::Mem,
+        Panel::Swap,
+        Panel::Gpu,
+        Panel::Disks,
+        Panel::Net,
+        Panel::Processes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Cpu => "cpu",
+            Panel::Cores => "cores",
+            Panel::Mem => "mem",
+            Panel::Swap => "swap",
+            Panel::Gpu => "gpu",
+            Panel::Disks => "disks",
+            Panel::Net => "net",
+            Panel::Processes => "processes",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Panel> {
+        let s = s.trim().to_ascii_lowercase();
+        Panel::ALL.into_iter().find(|p| p.name() == s)
+    }
+
+    // Bit in `Layout::shown`
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Panels shown and the maximized one, if any. The default shows everything in the usual grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    shown: u8,
+    maximized: Option<Panel>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            shown: u8::MAX,
+            maximized: None,
+        }
+    }
+}
+
+/// Where each panel goes this frame; None for panels not drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PanelAreas {
+    pub header: Rect,
+    pub derived: Rect,
+    pub cpu: Option<Rect>,
+    pub cores: Option<Rect>,
+    pub mem: Option<Rect>,
+    pub swap: Option<Rect>,
+    pub gpu: Option<Rect>,
+    pub disks: Option<Rect>,
+    pub rx: Option<Rect>,
+    pub tx: Option<Rect>,
+    pub processes: Option<Rect>,
+}
+
+// Split `area` into two columns by `left_pct`, or give it all to whichever side is present
+fn columns(area: Rect, left: bool, right: bool, left_pct: u16) -> (Option<Rect>, Option<Rect>) {
+    match (left, right) {
+        (true, true) => {
+            let c = Split::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(left_pct),
+                    Constraint::Percentage(100 - left_pct),
+                ])
+                .split(area);
+            (Some(c[0]), Some(c[1]))
+        }
+        (true, false) => (Some(area), None),
+        (false, true) => (None, Some(area)),
+        (false, false) => (None, None),
+    }
+}
+
+fn halves(area: Rect) -> (Rect, Rect) {
+    let r = Split::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+        .split(area);
+    (r[0], r[1])
+}
+
+impl Layout {
+    /// Profile settings: `layout` is `default` or the name of the panel to maximize, `panels`
+    /// lists the panels to show (empty: all). Unknown names are skipped and returned so the
+    /// caller can warn about them; a typo never hides the whole dashboard.
+    pub fn from_profile(layout: Option<&str>, panels: &[String]) -> (Layout, Vec<String>) {
+        let mut unknown = Vec::new();
+        let mut out = Layout::default();
+        let listed: Vec<Panel> = panels
+            .iter()
+            .filter_map(|p| {
+                let parsed = Panel::parse(p);
+                if parsed.is_none() {
+                    unknown.push(p.clone());
+                }
+                parsed
+            })
+            .collect();
+        if !listed.is_empty() {
+            out.shown = listed.iter().fold(0, |bits, p| bits | p.bit());
+        }
+        match layout.map(str::trim) {
+            None | Some("") | Some("default") => {}
+            Some(name) => match Panel::parse(name) {
+                Some(p) => out.maximized = Some(p),
+                None => unknown.push(name.to_string()),
+            },
+        }
+        (out, unknown)
+    }
+
+    pub fn shows(&self, p: Panel) -> bool {
+        self.maximized.map_or(self.shown & p.bit() != 0, |m| m == p)
+    }
+
+    /// Panel rectangles for a frame of `area`, with `derived_h` rows of derived metrics under
+    /// the header.
+    pub fn areas(&self, area: Rect, derived_h: u16) -> PanelAreas {
+        let s = |p| self.shows(p);
+        let top = s(Panel::Cpu) || s(Panel::Cores);
+        let gpu = s(Panel::Gpu);
+        let bottom = s(Panel::Disks) || s(Panel::Net) || s(Panel::Processes);
+        let rows = Split::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),         // header
+                Constraint::Length(derived_h), // derived metrics
+                // top row: cpu avg + per-core
+                match (top, bottom) {
+                    (true, true) => Constraint::Ratio(1, 3),
+                    (true, false) => Constraint::Min(0),
+                    (false, _) => Constraint::Length(0),
+                },
+                // memory (left) + GPU (right, part 1)
+                Constraint::Length(if s(Panel::Mem) || gpu { 3 } else { 0 }),
+                // swap (left) + GPU (right, part 2)
+                Constraint::Length(if s(Panel::Swap) || gpu { 3 } else { 0 }),
+                // bottom: disks + net (left), top procs (right)
+                Constraint::Min(if bottom { 10 } else { 0 }),
+            ])
+            .split(area);
+        let mut out = PanelAreas {
+            header: rows[0],
+            derived: rows[1],
+            ..Default::default()
+        };
+
+        if let Some(p) = self.maximized {
+            let body = Rect {
+                height: area.height.saturating_sub(rows[0].height + rows[1].height),
+                y: rows[1].y + rows[1].height,
+                ..area
+            };
+            match p {
+                Panel::Cpu => out.cpu = Some(body),
+                Panel::Cores => out.cores = Some(body),
+                Panel::Mem => out.mem = Some(body),
+                Panel::Swap => out.swap = Some(body),
+                Panel::Gpu => out.gpu = Some(body),
+                Panel::Disks => out.disks = Some(body),
+                Panel::Net => {
+                    let (rx, tx) = halves(body);
+                    (out.rx, out.tx) = (Some(rx), Some(tx));
+                }
+                Panel::Processes => out.processes = Some(body),
+            }
+            return out;
+        }
+
+        (out.cpu, out.cores) = columns(rows[2], s(Panel::Cpu), s(Panel::Cores), 66);
+        // Same columns in both rows, so the GPU panel lines up when only one of them is shown
+        let left = s(Panel::Mem) || s(Panel::Swap);
+        let (mem, gpu_top) = columns(rows[3], left, gpu, 66);
+        let (swap, gpu_bottom) = columns(rows[4], left, gpu, 66);
+        out.mem = mem.filter(|_| s(Panel::Mem));
+        out.swap = swap.filter(|_| s(Panel::Swap));
+        // GPU spans the same vertical space as Memory + Swap
+        out.gpu = match (gpu_top, gpu_bottom) {
+            (Some(t), Some(b)) => Some(Rect {
+                height: t.height + b.height,
+                ..t
+            }),
+            _ => None,
+        };
+
+        let (left, procs) = columns(
+            rows[5],
+            s(Panel::Disks) || s(Panel::Net),
+            s(Panel::Processes),
+            60,
+        );
+        out.processes = procs;
+        if let Some(left) = left {
+            let net = s(Panel::Net);
+            let stack = Split::default()
+                .direction(Direction::Vertical)
+                .constraints(match (s(Panel::Disks), net) {
+                    // Disks shrink slightly; net panes fixed and slightly taller
+                    (true, true) => [
+                        Constraint::Min(4),
+                        Constraint::Length(5),
+                        Constraint::Length(5),
+                    ],
+                    (true, false) => [
+                        Constraint::Min(0),
+                        Constraint::Length(0),
+                        Constraint::Length(0),
+                    ],
+                    _ => [
+                        Constraint::Length(0),
+                        Constraint::Ratio(1, 2),
+                        Constraint::Ratio(1, 2),
+                    ],
+                })
+                .split(left);
+            out.disks = Some(stack[0]).filter(|_| s(Panel::Disks));
+            if net {
+                (out.rx, out.tx) = (Some(stack[1]), Some(stack[2]));
+            }
+        }
+        out
+    }
+}
@@ -1,4 +1,44 @@
 //! Library surface for integration tests and reuse.
 
+pub mod a11y;
+pub mod agentless;
+pub mod alerts;
+pub mod api;
+pub mod budget;
+pub mod clipboard;
+pub mod crash;
+pub mod derived;
+pub mod disk_mirror;
+pub mod fleet;
+pub mod focus;
+pub mod frame_budget;
+pub mod framing;
+pub mod governor;
+pub mod hints;
+pub mod history;
+pub mod keymap;
+pub mod layout;
+pub mod mark;
+pub mod once;
+pub mod palette;
+pub mod peers;
+pub mod pipe;
+pub mod poll;
+pub mod proc_sample;
+pub mod proc_trend;
+pub mod profiles;
+pub mod prompt;
+pub mod rate;
+pub mod reconnect;
+pub mod recording;
+pub mod replay;
+pub mod sanity;
+pub mod search;
+pub mod session;
+pub mod tunnel;
 pub mod types;
+pub mod ui;
+pub mod wire_stats;
 pub mod ws;
+#[cfg(feature = "webtransport")]
+pub mod wt;
@@ -1,27 +1,118 @@
 //! Entry point for the socktop TUI. Parses args and runs the App.
 
+mod a11y;
+mod agentless;
+mod alerts;
 mod app;
+mod budget;
+mod clipboard;
+mod crash;
+mod derived;
+mod disk_mirror;
+mod exit_codes;
+mod fleet;
+mod focus;
+mod frame_budget;
+mod framing;
+mod governor;
+mod hints;
 mod history;
+mod keymap;
+mod layout;
+mod mark;
+mod once;
+mod palette;
+mod peers;
+#[cfg(windows)]
+mod pipe;
+mod poll;
+mod preflight;
+mod proc_sample;
+mod proc_trend;
 mod profiles;
+mod prompt;
+mod rate;
+mod reconnect;
+mod recording;
+mod replay;
+mod sanity;
+mod search;
+mod session;
+mod tunnel;
 mod types;
 mod ui;
+mod wire_stats;
 mod ws;
+#[cfg(feature = "webtransport")]
+mod wt;
 
 use app::App;
+use exit_codes::{exit_preflight, exit_with, ErrorFormat, ExitStatus};
+use once::OnceFormat;
 use profiles::{load_profiles, save_profiles, ProfileEntry, ProfileRequest, ResolveProfile};
+use std::collections::BTreeMap;
 use std::env;
-use std::io::{self, Write};
+use ui::raster;
 
 pub(crate) struct ParsedArgs {
     url: Option<String>,
     tls_ca: Option<String>,
+    tls_fingerprint: Option<String>,
+    token: Option<String>,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
     profile: Option<String>,
     save: bool,
     demo: bool,
     dry_run: bool, // hidden test helper: skip connecting
     metrics_interval_ms: Option<u64>,
     processes_interval_ms: Option<u64>,
+    unfocused_poll_ms: Option<u64>,
     verify_hostname: bool,
+    error_format: ErrorFormat,
+    max_bandwidth: Option<u64>,
+    silence_minutes: Option<u64>,
+    a11y: bool,
+    plain: bool,
+    graphics: Option<String>,
+    theme: Option<ui::theme::Theme>,
+    frame_check: bool,
+    once: bool,
+    format: OnceFormat,
+    record: Option<String>,
+    replay: Option<String>,
+}
+
+// One entry per option parse_args accepts (--dry-run stays undocumented), in --help order
+const USAGE_OPTIONS: &[&str] = &[
+    "[--tls-ca CERT_PEM|-t CERT_PEM]",
+    "[--tls-fingerprint SHA256]",
+    "[--tls-cert CLIENT_PEM --tls-key KEY_PEM]",
+    "[--token TOKEN]",
+    "[--verify-hostname]",
+    "[--profile NAME|-P NAME]",
+    "[--save]",
+    "[--demo]",
+    "[--error-format text|json]",
+    "[--max-bandwidth RATE]",
+    "[--silence MINUTES]",
+    "[--a11y]",
+    "[--plain]",
+    "[--frame-check]",
+    "[--graphics auto|kitty|sixel|off]",
+    "[--theme dark|light|mono]",
+    "[--once [--format plain|json]]",
+    "[--record FILE]",
+    "[--replay FILE]",
+    "[--metrics-interval-ms N]",
+    "[--processes-interval-ms N]",
+    "[--unfocused-poll-ms N]",
+    "[--version|-V]",
+    "[ws://HOST:PORT/ws | HOST[:PORT] | --ssh USER@HOST]",
+];
+
+fn usage(prog: &str) -> String {
+    format!("Usage: {prog} {}", USAGE_OPTIONS.join(" "))
 }
 
 pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<ParsedArgs, String> {
@@ -29,36 +120,108 @@ pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Pars
     let prog = it.next().unwrap_or_else(|| "socktop".into());
     let mut url: Option<String> = None;
     let mut tls_ca: Option<String> = None;
+    let mut tls_fingerprint: Option<String> = None;
+    let mut token: Option<String> = None;
+    let mut tls_cert: Option<String> = None;
+    let mut tls_key: Option<String> = None;
     let mut profile: Option<String> = None;
     let mut save = false;
     let mut demo = false;
     let mut dry_run = false;
     let mut metrics_interval_ms: Option<u64> = None;
     let mut processes_interval_ms: Option<u64> = None;
+    let mut unfocused_poll_ms: Option<u64> = None;
     let mut verify_hostname = false;
+    let mut error_format = ErrorFormat::Text;
+    let mut max_bandwidth: Option<u64> = None;
+    let mut silence_minutes: Option<u64> = None;
+    let mut a11y = false;
+    let mut plain = false;
+    let mut graphics: Option<String> = None;
+    let mut theme: Option<ui::theme::Theme> = None;
+    let mut frame_check = false;
+    let mut once = false;
+    let mut format = OnceFormat::default();
+    let mut record: Option<String> = None;
+    let mut replay: Option<String> = None;
     while let Some(arg) = it.next() {
         match arg.as_str() {
             "-h" | "--help" => {
-                return Err(format!("Usage: {prog} [--tls-ca CERT_PEM|-t CERT_PEM] [--verify-hostname] [--profile NAME|-P NAME] [--save] [--demo] [--metrics-interval-ms N] [--processes-interval-ms N] [ws://HOST:PORT/ws]\n"));
+                return Err(format!("{}\n", usage(&prog)));
             }
             "--tls-ca" | "-t" => {
                 tls_ca = it.next();
             }
+            "--tls-fingerprint" => {
+                tls_fingerprint = it.next();
+            }
+            "--token" => {
+                token = it.next();
+            }
+            "--tls-cert" => {
+                tls_cert = it.next();
+            }
+            "--tls-key" => {
+                tls_key = it.next();
+            }
             "--verify-hostname" => {
                 // opt-in hostname (SAN) verification
                 // default behavior is to skip it for easier home network usage
                 // (still pins the provided certificate)
                 verify_hostname = true;
             }
+            "--error-format" => {
+                error_format = it
+                    .next()
+                    .map(|v| ErrorFormat::parse(&v))
+                    .unwrap_or_default();
+            }
+            "--max-bandwidth" => {
+                max_bandwidth = it.next().as_deref().and_then(budget::parse_bandwidth);
+            }
+            "--silence" => {
+                silence_minutes = it.next().and_then(|v| v.parse().ok());
+            }
             "--profile" | "-P" => {
                 profile = it.next();
             }
             "--save" => {
                 save = true;
             }
+            "--a11y" => {
+                a11y = true;
+            }
+            "--plain" => {
+                plain = true;
+            }
+            "--frame-check" => {
+                frame_check = true;
+            }
+            "--graphics" => {
+                graphics = Some(graphics_mode(it.next().as_deref())?);
+            }
+            "--theme" => {
+                theme = Some(theme_name(it.next().as_deref())?);
+            }
             "--demo" => {
                 demo = true;
             }
+            "--once" => {
+                once = true;
+            }
+            "--format" => {
+                format = once_format(it.next().as_deref())?;
+            }
+            "--record" => {
+                record = it.next();
+            }
+            "--replay" => {
+                replay = it.next();
+            }
+            "--ssh" => {
+                // No agent on the host: read /proc over a plain SSH session
+                url = it.next().map(|dest| agentless::ssh_url(&dest));
+            }
             "--dry-run" => {
                 // intentionally undocumented
                 dry_run = true;
@@ -69,6 +232,28 @@ pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Pars
             "--processes-interval-ms" => {
                 processes_interval_ms = it.next().and_then(|v| v.parse().ok());
             }
+            "--unfocused-poll-ms" => {
+                unfocused_poll_ms = it.next().and_then(|v| v.parse().ok());
+            }
+            _ if arg.starts_with("--ssh=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    url = Some(agentless::ssh_url(v));
+                }
+            }
+            _ if arg.starts_with("--record=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        record = Some(v.to_string());
+                    }
+                }
+            }
+            _ if arg.starts_with("--replay=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        replay = Some(v.to_string());
+                    }
+                }
+            }
             _ if arg.starts_with("--tls-ca=") => {
                 if let Some((_, v)) = arg.split_once('=') {
                     if !v.is_empty() {
@@ -76,6 +261,64 @@ pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Pars
                     }
                 }
             }
+            _ if arg.starts_with("--tls-fingerprint=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        tls_fingerprint = Some(v.to_string());
+                    }
+                }
+            }
+            _ if arg.starts_with("--token=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        token = Some(v.to_string());
+                    }
+                }
+            }
+            _ if arg.starts_with("--tls-cert=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        tls_cert = Some(v.to_string());
+                    }
+                }
+            }
+            _ if arg.starts_with("--tls-key=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    if !v.is_empty() {
+                        tls_key = Some(v.to_string());
+                    }
+                }
+            }
+            _ if arg.starts_with("--error-format=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    error_format = ErrorFormat::parse(v);
+                }
+            }
+            _ if arg.starts_with("--max-bandwidth=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    max_bandwidth = budget::parse_bandwidth(v);
+                }
+            }
+            _ if arg.starts_with("--silence=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    silence_minutes = v.parse().ok();
+                }
+            }
+            _ if arg.starts_with("--graphics=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    graphics = Some(graphics_mode(Some(v))?);
+                }
+            }
+            _ if arg.starts_with("--theme=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    theme = Some(theme_name(Some(v))?);
+                }
+            }
+            _ if arg.starts_with("--format=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    format = once_format(Some(v))?;
+                }
+            }
             _ if arg.starts_with("--profile=") => {
                 if let Some((_, v)) = arg.split_once('=') {
                     if !v.is_empty() {
@@ -93,11 +336,16 @@ pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Pars
                     processes_interval_ms = v.parse().ok();
                 }
             }
+            _ if arg.starts_with("--unfocused-poll-ms=") => {
+                if let Some((_, v)) = arg.split_once('=') {
+                    unfocused_poll_ms = v.parse().ok();
+                }
+            }
             _ => {
                 if url.is_none() {
                     url = Some(arg);
                 } else {
-                    return Err(format!("Unexpected argument. Usage: {prog} [--tls-ca CERT_PEM|-t CERT_PEM] [--verify-hostname] [--profile NAME|-P NAME] [--save] [--demo] [ws://HOST:PORT/ws]"));
+                    return Err(format!("Unexpected argument. {}", usage(&prog)));
                 }
             }
         }
@@ -105,25 +353,155 @@ pub(crate) fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Pars
     Ok(ParsedArgs {
         url,
         tls_ca,
+        tls_fingerprint,
+        token,
+        tls_cert,
+        tls_key,
         profile,
         save,
         demo,
         dry_run,
         metrics_interval_ms,
         processes_interval_ms,
+        unfocused_poll_ms,
         verify_hostname,
+        error_format,
+        max_bandwidth,
+        silence_minutes,
+        a11y,
+        plain,
+        graphics,
+        theme,
+        frame_check,
+        once,
+        format,
+        record,
+        replay,
     })
 }
 
+fn graphics_mode(v: Option<&str>) -> Result<String, String> {
+    match v {
+        Some(m @ ("auto" | "kitty" | "sixel" | "off")) => Ok(m.to_string()),
+        _ => Err("--graphics takes auto, kitty, sixel or off".into()),
+    }
+}
+
+fn theme_name(v: Option<&str>) -> Result<ui::theme::Theme, String> {
+    v.and_then(ui::theme::Theme::named)
+        .ok_or_else(|| "--theme takes dark, light or mono".into())
+}
+
+fn once_format(v: Option<&str>) -> Result<OnceFormat, String> {
+    v.and_then(OnceFormat::parse)
+        .ok_or_else(|| "--format takes plain or json".into())
+}
+
+const FLEET_USAGE: &str = "Usage: socktop fleet snapshot [--group NAME] [--json|--csv]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FleetFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+struct FleetSnapshotCmd {
+    group: Option<String>,
+    format: FleetFormat,
+}
+
+/// `Ok(None)` means help was requested.
+fn parse_fleet_args(args: &[String]) -> Result<Option<FleetSnapshotCmd>, String> {
+    let mut it = args.iter();
+    match it.next().map(String::as_str) {
+        Some("snapshot") => {}
+        None | Some("-h") | Some("--help") => return Ok(None),
+        Some(other) => return Err(format!("unknown fleet command '{other}'")),
+    }
+    let mut cmd = FleetSnapshotCmd {
+        group: None,
+        format: FleetFormat::Table,
+    };
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "--group" | "-g" => {
+                cmd.group = Some(it.next().ok_or("--group needs a value")?.clone());
+            }
+            "--json" => cmd.format = FleetFormat::Json,
+            "--csv" => cmd.format = FleetFormat::Csv,
+            "--error-format" => {
+                it.next();
+            }
+            o if o.starts_with("--error-format=") => {}
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown fleet snapshot option '{other}'")),
+        }
+    }
+    Ok(Some(cmd))
+}
+
+async fn run_fleet_snapshot(
+    cmd: FleetSnapshotCmd,
+    fmt: ErrorFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pf = load_profiles();
+    let members = fleet::members(&pf.profiles, cmd.group.as_deref());
+    if members.is_empty() {
+        let msg = match &cmd.group {
+            Some(g) => format!("no profiles in group '{g}'"),
+            None => "no saved profiles".to_string(),
+        };
+        exit_with(
+            fmt,
+            ExitStatus::Usage,
+            None,
+            &msg,
+            Some("add \"groups\": [\"NAME\"] to entries in profiles.json"),
+        );
+    }
+    let rows = fleet::snapshot(members).await;
+    match cmd.format {
+        FleetFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+        FleetFormat::Csv => print!("{}", fleet::to_csv(&rows)),
+        FleetFormat::Table => print!("{}", fleet::to_table(&rows)),
+    }
+    // Partial reports are still printed; the exit status flags that some hosts failed
+    if rows.iter().any(|r| !r.ok) {
+        std::process::exit(ExitStatus::Connect as i32);
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // A panic restores the terminal and leaves a crash report instead of a garbled screen
+    crash::install();
+    // Non-interactive fleet report (no TUI): socktop fleet snapshot [--group G] [--json|--csv]
+    if env::args().nth(1).as_deref() == Some("fleet") {
+        let rest: Vec<String> = env::args().skip(2).collect();
+        let fmt = ErrorFormat::from_args(env::args());
+        return match parse_fleet_args(&rest) {
+            Ok(Some(cmd)) => run_fleet_snapshot(cmd, fmt).await,
+            Ok(None) => {
+                println!("{FLEET_USAGE}");
+                Ok(())
+            }
+            Err(msg) => exit_with(fmt, ExitStatus::Usage, None, &msg, Some(FLEET_USAGE)),
+        };
+    }
     let parsed = match parse_args(env::args()) {
         Ok(v) => v,
-        Err(msg) => {
+        Err(msg) if msg.starts_with("Usage:") => {
             eprintln!("{msg}");
             return Ok(());
         }
+        Err(msg) => {
+            let fmt = ErrorFormat::from_args(env::args());
+            exit_with(fmt, ExitStatus::Usage, None, &msg, None);
+        }
     };
+    let error_format = parsed.error_format;
 
     //support version flag (print and exit)
     if env::args().any(|a| a == "--version" || a == "-V") {
@@ -134,11 +512,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if parsed.demo || matches!(parsed.profile.as_deref(), Some("demo")) {
         return run_demo_mode(parsed.tls_ca.as_deref()).await;
     }
+    // --replay: a recorded session instead of an agent
+    if let Some(path) = parsed.replay.as_deref() {
+        return run_replay(&parsed, path).await;
+    }
 
     if parsed.verify_hostname {
         // Set env var consumed by ws::connect logic
         std::env::set_var("SOCKTOP_VERIFY_NAME", "1");
     }
+    if let Some(fp) = parsed.tls_fingerprint.as_deref() {
+        // Consumed by ws::connect (pins the leaf cert by SHA-256)
+        std::env::set_var("SOCKTOP_TLS_FINGERPRINT", fp);
+    }
+    if parsed.frame_check {
+        // Consumed by ws::connect (asks the agent for checksummed binary frames)
+        std::env::set_var("SOCKTOP_FRAME_CHECK", "1");
+    }
 
     let profiles_file = load_profiles();
     let req = ProfileRequest {
@@ -149,6 +539,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let resolved = req.resolve(&profiles_file);
     let mut profiles_mut = profiles_file.clone();
+    // Profile in use (named on the command line or picked interactively); maintenance state lives there
+    let mut active_profile = parsed.profile.clone();
     let (url, tls_ca, metrics_interval_ms, processes_interval_ms): (
         String,
         Option<String>,
@@ -171,20 +563,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 tls_ca: t.clone(),
                                 metrics_interval_ms: mi,
                                 processes_interval_ms: pi,
+                                silenced_until: None,
+                                ssh: None,
+                                ssh_jump: Vec::new(),
+                                groups: Vec::new(),
+                                layout: None,
+                                panels: Vec::new(),
+                                env: BTreeMap::new(),
+                                alerts: None,
+                                token: parsed.token.clone(),
+                                tls_cert: parsed.tls_cert.clone(),
+                                tls_key: parsed.tls_key.clone(),
                             },
                         );
                         let _ = save_profiles(&profiles_mut);
                         (u, t, mi, pi)
                     }
                     Some(entry) => {
-                        let changed = entry.url != u || entry.tls_ca != t;
+                        // Credentials only count as changed when given on this command line
+                        let differs = |flag: &Option<String>, stored: &Option<String>| {
+                            flag.as_ref().is_some_and(|v| stored.as_ref() != Some(v))
+                        };
+                        let changed = entry.url != u
+                            || entry.tls_ca != t
+                            || differs(&parsed.token, &entry.token)
+                            || differs(&parsed.tls_cert, &entry.tls_cert)
+                            || differs(&parsed.tls_key, &entry.tls_key);
                         if changed {
                             let overwrite = if parsed.save {
                                 true
                             } else {
-                                prompt_yes_no(&format!(
-                                    "Overwrite existing profile '{name}'? [y/N]: "
-                                ))
+                                prompt::confirm(
+                                    &format!("Overwrite existing profile '{name}'?"),
+                                    false,
+                                )?
                             };
                             if overwrite {
                                 let (mi, pi) = gather_intervals(
@@ -198,6 +610,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         tls_ca: t.clone(),
                                         metrics_interval_ms: mi,
                                         processes_interval_ms: pi,
+                                        silenced_until: entry.silenced_until,
+                                        ssh: entry.ssh.clone(),
+                                        ssh_jump: entry.ssh_jump.clone(),
+                                        groups: entry.groups.clone(),
+                                        layout: entry.layout.clone(),
+                                        panels: entry.panels.clone(),
+                                        env: entry.env.clone(),
+                                        alerts: entry.alerts,
+                                        token: parsed.token.clone().or_else(|| entry.token.clone()),
+                                        tls_cert: parsed
+                                            .tls_cert
+                                            .clone()
+                                            .or_else(|| entry.tls_cert.clone()),
+                                        tls_key: parsed
+                                            .tls_key
+                                            .clone()
+                                            .or_else(|| entry.tls_key.clone()),
                                     },
                                 );
                                 let _ = save_profiles(&profiles_mut);
@@ -234,61 +663,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             for (i, n) in names.iter().enumerate() {
                 eprintln!("  {}. {}", i + 1, n);
             }
-            eprint!("Enter number (or blank to abort): ");
-            let _ = io::stderr().flush();
-            let mut line = String::new();
-            if io::stdin().read_line(&mut line).is_ok() {
-                if let Ok(idx) = line.trim().parse::<usize>() {
-                    if idx >= 1 && idx <= names.len() {
-                        let name = &names[idx - 1];
-                        if name == "demo" {
-                            return run_demo_mode(parsed.tls_ca.as_deref()).await;
-                        }
-                        if let Some(entry) = profiles_mut.profiles.get(name) {
-                            (
-                                entry.url.clone(),
-                                entry.tls_ca.clone(),
-                                entry.metrics_interval_ms,
-                                entry.processes_interval_ms,
-                            )
-                        } else {
-                            return Ok(());
-                        }
-                    } else {
-                        return Ok(());
-                    }
-                } else {
-                    return Ok(());
+            let n = names.len();
+            let pick = prompt::ask("Enter number (or blank to abort)", None, |s| {
+                match s.parse::<usize>() {
+                    _ if s.is_empty() => Ok(()),
+                    Ok(i) if (1..=n).contains(&i) => Ok(()),
+                    _ => Err(format!("enter a number from 1 to {n}")),
                 }
-            } else {
+            })?;
+            let Ok(idx) = pick.parse::<usize>() else {
                 return Ok(());
+            };
+            let name = &names[idx - 1];
+            if name == "demo" {
+                return run_demo_mode(parsed.tls_ca.as_deref()).await;
             }
+            let Some(entry) = profiles_mut.profiles.get(name) else {
+                return Ok(());
+            };
+            active_profile = Some(name.clone());
+            (
+                entry.url.clone(),
+                entry.tls_ca.clone(),
+                entry.metrics_interval_ms,
+                entry.processes_interval_ms,
+            )
         }
         ResolveProfile::PromptCreate(name) => {
             eprintln!("Profile '{name}' does not exist yet.");
-            let url = prompt_string("Enter URL (ws://HOST:PORT/ws or wss://...): ")?;
-            if url.trim().is_empty() {
+            let url = prompt::ask(
+                "Enter URL (ws://HOST:PORT/ws, wss://... or blank to abort)",
+                None,
+                prompt::optional(prompt::valid_url),
+            )?;
+            if url.is_empty() {
                 return Ok(());
             }
-            let ca = prompt_string("Enter TLS CA path (or leave blank): ")?;
-            let ca_opt = if ca.trim().is_empty() {
-                None
-            } else {
-                Some(ca.trim().to_string())
-            };
+            let ca = prompt::ask(
+                "Enter TLS CA path (or leave blank)",
+                None,
+                prompt::optional(prompt::existing_file),
+            )?;
+            let ca_opt = (!ca.is_empty()).then_some(ca);
             let (mi, pi) =
                 gather_intervals(parsed.metrics_interval_ms, parsed.processes_interval_ms)?;
             profiles_mut.profiles.insert(
                 name.clone(),
                 ProfileEntry {
-                    url: url.trim().to_string(),
+                    url: url.clone(),
                     tls_ca: ca_opt.clone(),
                     metrics_interval_ms: mi,
                     processes_interval_ms: pi,
+                    silenced_until: None,
+                    ssh: None,
+                    ssh_jump: Vec::new(),
+                    groups: Vec::new(),
+                    layout: None,
+                    panels: Vec::new(),
+                    env: BTreeMap::new(),
+                    alerts: None,
+                    token: parsed.token.clone(),
+                    tls_cert: parsed.tls_cert.clone(),
+                    tls_key: parsed.tls_key.clone(),
                 },
             );
             let _ = save_profiles(&profiles_mut);
-            (url.trim().to_string(), ca_opt, mi, pi)
+            (url, ca_opt, mi, pi)
         }
         ResolveProfile::None => {
             //eprintln!("No URL provided and no profiles to select.");
@@ -299,44 +739,320 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprintln!("It looks like this is your first time running the application.");
                 eprintln!("You can connect to a socktop_agent instance to monitor system metrics and processes.");
                 eprintln!("If you don't have an agent running, you can try the demo mode.");
-                if prompt_yes_no("Would you like to start the demo mode now? [Y/n]: ") {
+                if prompt::confirm("Would you like to start the demo mode now?", true)? {
                     return run_demo_mode(parsed.tls_ca.as_deref()).await;
                 } else {
                     eprintln!("Aborting. You can run 'socktop --help' for usage information.");
                     return Ok(());
                 }
             }
-            return Err("No URL provided and no profiles to select.".into());
+            exit_with(
+                error_format,
+                ExitStatus::Usage,
+                None,
+                "No URL provided and no profiles to select.",
+                Some("run 'socktop --help' for usage"),
+            );
         }
     };
 
-    let is_tls = url.starts_with("wss://");
-    let has_token = url.contains("token=");
-    let mut app = App::new()
-        .with_intervals(metrics_interval_ms, processes_interval_ms)
-        .with_status(is_tls, has_token);
+    // Profile `env`: tuned settings for this host; flags and the real environment still win
+    if let Some(entry) = active_profile
+        .as_ref()
+        .and_then(|p| profiles_mut.profiles.get(p))
+    {
+        let (apply, ignored) = profiles::env_overrides(&entry.env, |k| env::var_os(k).is_some());
+        for name in ignored {
+            eprintln!("socktop: ignoring profile env '{name}' (only SOCKTOP_* variables apply)");
+        }
+        for (k, v) in apply {
+            env::set_var(k, v);
+        }
+    }
+    // Consumed by ws::auth_token: --token, else the profile's token unless SOCKTOP_TOKEN is set
+    let profile_token = active_profile
+        .as_ref()
+        .and_then(|p| profiles_mut.profiles.get(p))
+        .and_then(|e| e.token.clone())
+        .filter(|_| env::var_os("SOCKTOP_TOKEN").is_none());
+    if let Some(t) = parsed.token.clone().or(profile_token) {
+        env::set_var("SOCKTOP_TOKEN", t);
+    }
+    // Consumed by ws::with_client_cert, the same way: flag, else profile, else the environment
+    let active_entry = active_profile
+        .as_ref()
+        .and_then(|p| profiles_mut.profiles.get(p));
+    for (var, flag, stored) in [
+        (
+            "SOCKTOP_TLS_CERT",
+            &parsed.tls_cert,
+            active_entry.and_then(|e| e.tls_cert.clone()),
+        ),
+        (
+            "SOCKTOP_TLS_KEY",
+            &parsed.tls_key,
+            active_entry.and_then(|e| e.tls_key.clone()),
+        ),
+    ] {
+        let stored = stored.filter(|_| env::var_os(var).is_none());
+        if let Some(path) = flag.clone().or(stored) {
+            env::set_var(var, path);
+        }
+    }
+
     if parsed.dry_run {
         return Ok(());
     }
-    app.run(&url, tls_ca.as_deref()).await
+    // Profiles with `ssh` (and `ssh_jump` bastions) reach the agent through a local port forward
+    // (not for agentless ssh:// URLs, which are an SSH session themselves)
+    let agentless = agentless::is_ssh_url(&url);
+    let ssh_target = active_profile
+        .as_ref()
+        .filter(|_| !agentless)
+        .and_then(|p| profiles_mut.profiles.get(p))
+        .and_then(|e| Some((e.ssh.clone()?, e.ssh_jump.clone())));
+    let (_tunnel, url) = match ssh_target {
+        Some((dest, jumps)) => match tunnel::SshTunnel::open(&dest, &jumps, &url) {
+            Ok((t, local)) => (Some(t), local),
+            Err(e) => exit_with(
+                error_format,
+                ExitStatus::Connect,
+                None,
+                &e,
+                Some("check the profile's ssh/ssh_jump hosts with plain `ssh -J`"),
+            ),
+        },
+        None => (None, url),
+    };
+    // https:// selects the experimental WebTransport client, which skips the WebSocket probes
+    let webtransport = ws::is_webtransport_url(&url);
+    if webtransport && !cfg!(feature = "webtransport") {
+        exit_with(
+            error_format,
+            ExitStatus::Usage,
+            None,
+            &format!("{url} is a WebTransport URL, but this socktop was built without it"),
+            Some("rebuild with `--features webtransport` or use a ws:// / wss:// URL"),
+        );
+    }
+    // pipe:// reaches a local agent over a Windows named pipe; no network probes apply
+    let pipe = ws::is_pipe_url(&url);
+    if pipe && !cfg!(windows) {
+        exit_with(
+            error_format,
+            ExitStatus::Usage,
+            None,
+            &format!("{url} is a named pipe URL, but named pipes are only available on Windows"),
+            Some("use a ws:// / wss:// URL"),
+        );
+    }
+    // Accept `host[:port]` shorthand: try wss:// first, then ws://
+    let (url, probed) = match ws::resolve_url(&url, tls_ca.as_deref()).await {
+        Ok(r) => {
+            if let Some(why) = r.plaintext_fallback {
+                eprintln!(
+                    "socktop: no TLS agent at {why}; using {} without TLS",
+                    r.url
+                );
+            }
+            (r.url, r.ws)
+        }
+        // Let the preflight explain why the preferred (TLS) candidate failed
+        Err(_) => (ws::shorthand_candidates(&url).swap_remove(0), None),
+    };
+    // Diagnose connection problems before the alternate screen takes over the terminal; the
+    // shorthand probe already got as far as the upgrade, so only the token check is left
+    if !webtransport && !pipe && !agentless {
+        let checked = match probed {
+            Some(ws) => preflight::check_auth(ws, &url).await,
+            None => preflight::run(&url, tls_ca.as_deref()).await,
+        };
+        if let Err(e) = checked {
+            exit_preflight(error_format, &e);
+        }
+    }
+    // --once: one snapshot on stdout, no TUI
+    if parsed.once {
+        return run_once(&url, tls_ca.as_deref(), parsed.format, error_format).await;
+    }
+    let is_tls =
+        webtransport || agentless || url.starts_with("wss://") || parsed.tls_fingerprint.is_some();
+    let has_token = url.contains("token=") || ws::auth_token().is_some();
+    // Maintenance window: --silence N starts (or with 0 ends) one; otherwise resume the profile's
+    let now = alerts::now_secs();
+    let silenced_until = match parsed.silence_minutes {
+        Some(0) => None,
+        Some(min) => Some(now + min * 60),
+        None => active_profile
+            .as_ref()
+            .and_then(|p| profiles_mut.profiles.get(p))
+            .and_then(|e| e.silenced_until)
+            .filter(|&u| u > now),
+    };
+    if let (Some(p), Some(_)) = (active_profile.as_deref(), parsed.silence_minutes) {
+        let _ = profiles::save_silence(p, silenced_until);
+    }
+    let profile_alerts = active_profile
+        .as_ref()
+        .and_then(|p| profiles_mut.profiles.get(p))
+        .and_then(|e| e.alerts);
+    // Profile `layout`/`panels`: the dashboard the session opens with
+    let (startup_layout, unknown_panels) = active_profile
+        .as_ref()
+        .and_then(|p| profiles_mut.profiles.get(p))
+        .map(|e| layout::Layout::from_profile(e.layout.as_deref(), &e.panels))
+        .unwrap_or_default();
+    for name in unknown_panels {
+        eprintln!("socktop: ignoring unknown panel '{name}' in the profile layout");
+    }
+    load_theme(&parsed);
+    let mut app = App::new()
+        .with_intervals(metrics_interval_ms, processes_interval_ms)
+        .with_layout(startup_layout)
+        .with_status(is_tls, has_token)
+        .with_bandwidth_budget(parsed.max_bandwidth.or_else(|| {
+            env::var("SOCKTOP_MAX_BANDWIDTH")
+                .ok()
+                .and_then(|v| budget::parse_bandwidth(&v))
+        }))
+        .with_alert_log(alerts::AlertLog::open(
+            &profiles::state_dir().join("alerts.jsonl"),
+        ))
+        .with_alert_rules(profile_alerts.unwrap_or_else(|| {
+            alerts::AlertRules::load_from(&profiles::config_dir().join("alerts.json"))
+        }))
+        .with_maintenance(active_profile, silenced_until, parsed.silence_minutes)
+        .with_a11y(parsed.a11y || env_flag("SOCKTOP_A11Y"))
+        .with_plain(parsed.plain || env_flag("SOCKTOP_PLAIN"))
+        .with_graphics(
+            parsed
+                .graphics
+                .as_deref()
+                .and_then(|mode| raster::detect(mode, |k| env::var(k).ok())),
+        )
+        .with_unfocused_poll(parsed.unfocused_poll_ms)
+        .with_derived(derived::load_from(
+            &profiles::config_dir().join("derived.json"),
+        ));
+    if let Some(path) = parsed.record.as_deref() {
+        if let Err(e) = recording::configure(std::path::Path::new(path), &url) {
+            exit_with(
+                error_format,
+                ExitStatus::Usage,
+                None,
+                &format!("can't record to {path}: {e}"),
+                None,
+            );
+        }
+    }
+    if let Err(e) = app.run(&url, tls_ca.as_deref()).await {
+        exit_with(
+            error_format,
+            ExitStatus::Error,
+            None,
+            &format!("socktop: {e}"),
+            None,
+        );
+    }
+    Ok(())
+}
+
+/// Palette and breakpoints for this run: `--theme`, then SOCKTOP_THEME, then `theme.json`.
+fn load_theme(parsed: &ParsedArgs) {
+    let file = profiles::config_dir().join("theme.json");
+    let palette = parsed
+        .theme
+        .or_else(|| {
+            env::var("SOCKTOP_THEME")
+                .ok()
+                .and_then(|v| ui::theme::Theme::named(&v))
+        })
+        .or_else(|| ui::theme::Theme::load_from(&file))
+        .unwrap_or_default();
+    ui::theme::configure_theme(palette);
+    ui::theme::configure(ui::theme::Thresholds::load_from(&file));
 }
 
-fn prompt_yes_no(prompt: &str) -> bool {
-    eprint!("{prompt}");
-    let _ = io::stderr().flush();
-    let mut line = String::new();
-    if io::stdin().read_line(&mut line).is_ok() {
-        matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes")
-    } else {
-        false
+async fn run_replay(parsed: &ParsedArgs, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let fmt = parsed.error_format;
+    let playback = match recording::load(std::path::Path::new(path)) {
+        Ok(rec) => replay::Playback::new(rec),
+        Err(e) => exit_with(
+            fmt,
+            ExitStatus::Usage,
+            None,
+            &format!("can't replay {e}"),
+            Some("record one with `socktop --record FILE URL`"),
+        ),
+    };
+    load_theme(parsed);
+    // Same pace as the recording unless asked otherwise
+    let metrics_ms = parsed
+        .metrics_interval_ms
+        .or_else(|| playback.sample_interval().map(|d| d.as_millis() as u64));
+    // Same rules as a live session, but the alerts stay in memory: they happened back then
+    let rules = parsed
+        .profile
+        .as_ref()
+        .and_then(|p| load_profiles().profiles.get(p).and_then(|e| e.alerts))
+        .unwrap_or_else(|| {
+            alerts::AlertRules::load_from(&profiles::config_dir().join("alerts.json"))
+        });
+    let mut app = App::new()
+        .with_intervals(metrics_ms, parsed.processes_interval_ms)
+        .with_alert_log(alerts::AlertLog::default())
+        .with_alert_rules(rules)
+        .with_a11y(parsed.a11y || env_flag("SOCKTOP_A11Y"))
+        .with_plain(parsed.plain || env_flag("SOCKTOP_PLAIN"));
+    if let Err(e) = app.run_replay(playback).await {
+        exit_with(fmt, ExitStatus::Error, None, &format!("socktop: {e}"), None);
     }
+    Ok(())
 }
-fn prompt_string(prompt: &str) -> io::Result<String> {
-    eprint!("{prompt}");
-    let _ = io::stderr().flush();
-    let mut line = String::new();
-    io::stdin().read_line(&mut line)?;
-    Ok(line)
+
+async fn run_once(
+    url: &str,
+    tls_ca: Option<&str>,
+    format: OnceFormat,
+    error_format: ErrorFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use ws::AgentLink;
+    let mut link = match ws::Link::open(url, tls_ca).await {
+        Ok(l) => l,
+        Err(e) => exit_with(
+            error_format,
+            ExitStatus::Connect,
+            None,
+            &format!("socktop: {e}"),
+            None,
+        ),
+    };
+    let Some(json) = ws::request_metrics_json(&mut link).await else {
+        exit_with(
+            error_format,
+            ExitStatus::Connect,
+            None,
+            &format!("socktop: no metrics reply from {url}"),
+            None,
+        );
+    };
+    let m: types::Metrics = match serde_json::from_str(&json) {
+        Ok(m) => m,
+        Err(e) => exit_with(
+            error_format,
+            ExitStatus::Error,
+            None,
+            &format!("socktop: unreadable metrics reply: {e}"),
+            None,
+        ),
+    };
+    print!("{}", once::render(&m, &json, format));
+    Ok(())
+}
+
+// `NAME=1` (as set by a profile's `env`)
+fn env_flag(name: &str) -> bool {
+    env::var(name).ok().as_deref() == Some("1")
 }
 
 fn gather_intervals(
@@ -348,29 +1064,23 @@ fn gather_intervals(
     let metrics = match arg_metrics {
         Some(v) => Some(v),
         None => {
-            let inp = prompt_string(&format!(
-                "Metrics interval ms (default {default_metrics}, Enter for default): "
-            ))?;
-            let t = inp.trim();
-            if t.is_empty() {
-                Some(default_metrics)
-            } else {
-                Some(t.parse()?)
-            }
+            let inp = prompt::ask(
+                "Metrics interval ms",
+                Some(&default_metrics.to_string()),
+                prompt::number,
+            )?;
+            Some(inp.parse()?)
         }
     };
     let procs = match arg_procs {
         Some(v) => Some(v),
         None => {
-            let inp = prompt_string(&format!(
-                "Processes interval ms (default {default_procs}, Enter for default): "
-            ))?;
-            let t = inp.trim();
-            if t.is_empty() {
-                Some(default_procs)
-            } else {
-                Some(t.parse()?)
-            }
+            let inp = prompt::ask(
+                "Processes interval ms",
+                Some(&default_procs.to_string()),
+                prompt::number,
+            )?;
+            Some(inp.parse()?)
         }
     };
     Ok((metrics, procs))
@@ -0,0 +1,168 @@
+//! Diff mode: mark a moment ('b') and later show what changed since then ('v'): CPU time each
+//! process consumed, bytes the interfaces moved and how much space each disk gained or lost.
+//! For before/after measurements around a deployment or a batch job.
+//!
+//! Network bytes are accumulated sample by sample so counter resets don't lose the total.
+//! Processes outside the top rows at mark time are counted from the first sample that showed
+//! them, so their figure is a lower bound.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::rate::counter_delta;
+use crate::types::Metrics;
+
+#[derive(Debug, Clone)]
+struct ProcBase {
+    cpu_time_ms: u64,
+    // Seen in the marked sample (otherwise counted from its first sighting)
+    at_mark: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mark {
+    /// Unix seconds
+    pub at: u64,
+    procs: HashMap<u32, ProcBase>,
+    // Last totals per interface, to accumulate deltas
+    net_last: HashMap<String, (u64, u64)>,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    // Used bytes per disk at mark time
+    disks: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcDelta {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_time_ms: u64,
+    // Not in the marked sample: counted from when it first showed up
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskDelta {
+    pub name: String,
+    /// Change of used bytes (negative: space freed); None for disks that appeared since
+    pub change: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarkDiff {
+    pub elapsed_secs: u64,
+    /// Most CPU time consumed first; processes that consumed none are left out
+    pub processes: Vec<ProcDelta>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Disks whose used space changed, plus new ones
+    pub disks: Vec<DiskDelta>,
+    /// Disks present at mark time but not now
+    pub removed_disks: Vec<String>,
+}
+
+fn used(total: u64, available: u64) -> u64 {
+    total.saturating_sub(available)
+}
+
+impl Mark {
+    pub fn new(m: &Metrics, at: u64) -> Self {
+        let mut mark = Mark {
+            at,
+            procs: m
+                .top_processes
+                .iter()
+                .map(|p| {
+                    (
+                        p.pid,
+                        ProcBase {
+                            cpu_time_ms: p.cpu_time_ms,
+                            at_mark: true,
+                        },
+                    )
+                })
+                .collect(),
+            net_last: HashMap::new(),
+            rx_bytes: 0,
+            tx_bytes: 0,
+            disks: m
+                .disks
+                .iter()
+                .map(|d| (d.name.clone(), used(d.total, d.available)))
+                .collect(),
+        };
+        mark.observe(m);
+        mark
+    }
+
+    /// Feed every new sample: accumulates network bytes and starts counting new processes.
+    pub fn observe(&mut self, m: &Metrics) {
+        for n in &m.networks {
+            let now = (n.received, n.transmitted);
+            if let Some((rx, tx)) = self.net_last.insert(n.name.clone(), now) {
+                // A reset restarts from the new reading; the bytes before it are already counted
+                self.rx_bytes += counter_delta(rx, now.0).unwrap_or(0);
+                self.tx_bytes += counter_delta(tx, now.1).unwrap_or(0);
+            }
+        }
+        for p in &m.top_processes {
+            self.procs.entry(p.pid).or_insert(ProcBase {
+                cpu_time_ms: p.cpu_time_ms,
+                at_mark: false,
+            });
+        }
+    }
+
+    /// What changed between the mark and `m` (the latest sample), `now` in Unix seconds.
+    pub fn diff(&self, m: &Metrics, now: u64) -> MarkDiff {
+        let mut processes: Vec<ProcDelta> = m
+            .top_processes
+            .iter()
+            .filter_map(|p| {
+                let base = self.procs.get(&p.pid)?;
+                // Lower than the baseline: the pid was reused, count the new process from zero
+                let (cpu_time_ms, partial) = match p.cpu_time_ms.checked_sub(base.cpu_time_ms) {
+                    Some(d) => (d, !base.at_mark),
+                    None => (p.cpu_time_ms, false),
+                };
+                (cpu_time_ms > 0).then(|| ProcDelta {
+                    pid: p.pid,
+                    name: p.name.clone(),
+                    cpu_time_ms,
+                    partial,
+                })
+            })
+            .collect();
+        processes.sort_by(|a, b| b.cpu_time_ms.cmp(&a.cpu_time_ms).then(a.pid.cmp(&b.pid)));
+
+        let disks = m
+            .disks
+            .iter()
+            .filter_map(|d| {
+                let now_used = used(d.total, d.available);
+                let change = self
+                    .disks
+                    .get(&d.name)
+                    .map(|&before| now_used as i64 - before as i64);
+                (change != Some(0)).then(|| DiskDelta {
+                    name: d.name.clone(),
+                    change,
+                })
+            })
+            .collect();
+        let removed_disks = self
+            .disks
+            .keys()
+            .filter(|name| !m.disks.iter().any(|d| &d.name == *name))
+            .cloned()
+            .collect();
+
+        MarkDiff {
+            elapsed_secs: now.saturating_sub(self.at),
+            processes,
+            rx_bytes: self.rx_bytes,
+            tx_bytes: self.tx_bytes,
+            disks,
+            removed_disks,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! `socktop --once`: fetch one metrics snapshot, print it to stdout and exit, for `watch` and
+//! scripts. `--format json` prints the agent's reply as sent; `plain` (the default) prints one
+//! `label value` line per reading.
+
+use crate::types::Metrics;
+use crate::ui::util::human;
+
+// Processes listed in the plain output
+const ONCE_TOP_PROCS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnceFormat {
+    #[default]
+    Plain,
+    Json,
+}
+
+impl OnceFormat {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v {
+            "plain" => Some(OnceFormat::Plain),
+            "json" => Some(OnceFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+fn pct(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        used as f64 / total as f64 * 100.0
+    }
+}
+
+/// The plain-text snapshot: aligned `label value` lines, sizes human-readable.
+pub fn plain(m: &Metrics) -> String {
+    let mut rows: Vec<(String, String)> = vec![
+        ("host".into(), m.hostname.clone()),
+        (
+            "cpu".into(),
+            format!("{:.1}% ({} cores)", m.cpu_total, m.cpu_per_core.len()),
+        ),
+    ];
+    if let Some(g) = m.cpu_governor.as_deref() {
+        rows.push(("governor".into(), g.to_string()));
+    }
+    if let Some(t) = m.cpu_temp_c {
+        rows.push(("temp".into(), format!("{t:.0}°C")));
+    }
+    rows.push((
+        "mem".into(),
+        format!(
+            "{} / {} ({:.0}%)",
+            human(m.mem_used),
+            human(m.mem_total),
+            pct(m.mem_used, m.mem_total)
+        ),
+    ));
+    rows.push((
+        "swap".into(),
+        format!(
+            "{} / {} ({:.0}%)",
+            human(m.swap_used),
+            human(m.swap_total),
+            pct(m.swap_used, m.swap_total)
+        ),
+    ));
+    if let Some((one, five, fifteen)) = m.load_avg {
+        rows.push(("load".into(), format!("{one:.2} {five:.2} {fifteen:.2}")));
+    }
+    if let Some(up) = m.uptime_secs {
+        rows.push((
+            "uptime".into(),
+            format!("{}d {}h {}m", up / 86_400, up / 3600 % 24, up / 60 % 60),
+        ));
+    }
+    for d in &m.disks {
+        let used = d.total.saturating_sub(d.available);
+        rows.push((
+            format!("disk {}", d.name),
+            format!(
+                "{} / {} ({:.0}%)",
+                human(used),
+                human(d.total),
+                pct(used, d.total)
+            ),
+        ));
+    }
+    // Counters since boot: a single sample has no rate
+    for n in &m.networks {
+        rows.push((
+            format!("net {}", n.name),
+            format!("rx {} tx {} total", human(n.received), human(n.transmitted)),
+        ));
+    }
+    for g in m.gpus.iter().flatten() {
+        rows.push((
+            format!("gpu {}", g.name.as_deref().unwrap_or("?")),
+            g.utilization
+                .map_or("n/a".to_string(), |u| format!("{u:.0}%")),
+        ));
+    }
+    rows.push((
+        "processes".into(),
+        m.process_count.unwrap_or(m.top_processes.len()).to_string(),
+    ));
+    let mut top: Vec<_> = m.top_processes.iter().collect();
+    top.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+    for p in top.into_iter().take(ONCE_TOP_PROCS) {
+        rows.push((
+            format!("proc {}", p.pid),
+            format!("{} {:.1}% {}", p.name, p.cpu_usage, human(p.mem_bytes)),
+        ));
+    }
+
+    let width = rows
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(k, v)| format!("{k:<width$}  {v}\n"))
+        .collect()
+}
+
+/// What `--once` prints for a snapshot; `json` is the agent's reply text.
+pub fn render(m: &Metrics, json: &str, format: OnceFormat) -> String {
+    match format {
+        OnceFormat::Plain => plain(m),
+        OnceFormat::Json => format!("{}\n", json.trim_end()),
+    }
+}
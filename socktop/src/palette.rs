@@ -0,0 +1,81 @@
+//! `Ctrl-P` command palette: fuzzy-filter every action by name and run the chosen one.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::keymap::Action;
+
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteOutcome {
+    Open,
+    Close,
+    Run(Action),
+}
+
+impl Palette {
+    /// Actions matching the query, best match first.
+    pub fn matches(&self) -> Vec<Action> {
+        let mut scored: Vec<(i32, usize, Action)> = Action::all()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, a)| fuzzy_score(&self.query, &a.label()).map(|s| (s, i, a)))
+            .collect();
+        // Higher score first; ties keep the palette's own order
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored.into_iter().map(|(_, _, a)| a).collect()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PaletteOutcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return PaletteOutcome::Close,
+            KeyCode::Char('p') | KeyCode::Char('P') if ctrl => return PaletteOutcome::Close,
+            KeyCode::Enter => {
+                return match self.matches().get(self.selected) {
+                    Some(&a) => PaletteOutcome::Run(a),
+                    None => PaletteOutcome::Close,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected += 1,
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        self.selected = self.selected.min(self.matches().len().saturating_sub(1));
+        PaletteOutcome::Open
+    }
+}
+
+/// Case-insensitive subsequence match. Consecutive characters and matches at word starts
+/// score higher; None when some query character is missing.
+pub fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (pos..label.len()).find(|&i| label[i] == q)?;
+        score += 1;
+        if prev.is_some_and(|p| p + 1 == found) {
+            score += 3;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        prev = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
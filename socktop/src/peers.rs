@@ -0,0 +1,71 @@
+//! Peer switcher ('f'): the agents the connected one learned by gossip (`get_peers`), with
+//! Enter reconnecting the client to the chosen one.
+//!
+//! Agents list each other without tokens, so the token in the current URL is carried over to
+//! the peer (fleets usually share one).
+
+use crossterm::event::{KeyCode, KeyEvent};
+use url::Url;
+
+use crate::types::Peer;
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerPicker {
+    /// Why nothing is fetched (agent without the `peers` capability)
+    pub note: Option<String>,
+    /// None until the agent answered
+    pub peers: Option<Vec<Peer>>,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerOutcome {
+    Open,
+    Close,
+    // Enter on a peer: its URL as listed (no token)
+    Switch(String),
+}
+
+impl PeerPicker {
+    /// Waiting for the `get_peers` reply.
+    pub fn pending(&self) -> bool {
+        self.note.is_none() && self.peers.is_none()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PeerOutcome {
+        let count = self.peers.as_ref().map_or(0, Vec::len);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('f') | KeyCode::Char('F') => return PeerOutcome::Close,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Enter => {
+                let Some(p) = self.peers.as_ref().and_then(|l| l.get(self.selected)) else {
+                    return PeerOutcome::Close;
+                };
+                // The agent we're on (or one that isn't reachable by URL): nothing to switch to
+                if p.is_self || p.url.is_empty() {
+                    return PeerOutcome::Close;
+                }
+                return PeerOutcome::Switch(p.url.clone());
+            }
+            _ => {}
+        }
+        PeerOutcome::Open
+    }
+}
+
+/// `peer` with the `token` query parameter of `current`, if it has one.
+pub fn switch_url(current: &str, peer: &str) -> String {
+    let token = Url::parse(current).ok().and_then(|u| {
+        u.query_pairs()
+            .find(|(k, _)| k == "token")
+            .map(|(_, v)| v.into_owned())
+    });
+    match (Url::parse(peer), token) {
+        (Ok(mut u), Some(t)) => {
+            u.query_pairs_mut().append_pair("token", &t);
+            u.to_string()
+        }
+        _ => peer.to_string(),
+    }
+}
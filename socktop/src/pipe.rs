@@ -0,0 +1,93 @@
+//! Windows named pipe client for a local agent started with `--pipe`: `pipe://socktop_agent`
+//! (or `pipe://NAME?token=...`) connects to `\\.\pipe\NAME` without any TCP port.
+//!
+//! Frames are a 4-byte little-endian length plus payload. The first frame is the token (empty
+//! for none), acknowledged with `ok`. Each command frame then gets one reply frame: a kind
+//! byte (`0` text, `1` binary) and the same body a WebSocket frame would carry, or an empty
+//! frame when the agent has nothing to say.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::Message;
+
+const KIND_TEXT: u8 = 0;
+const KIND_BINARY: u8 = 1;
+// Far above any real reply (the agent caps processes at --max-payload-bytes)
+const MAX_REPLY: usize = 64 * 1024 * 1024;
+
+/// `pipe://NAME[?token=T]` -> (`\\.\pipe\NAME`, token); None when NAME is missing.
+pub fn parse_pipe_url(url: &str) -> Option<(String, Option<String>)> {
+    let rest = url.strip_prefix("pipe://")?;
+    let (name, query) = match rest.split_once('?') {
+        Some((n, q)) => (n, Some(q)),
+        None => (rest, None),
+    };
+    let name = name.trim_end_matches('/');
+    if name.is_empty() {
+        return None;
+    }
+    let token = query
+        .and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("token=")))
+        .map(str::to_string);
+    Some((format!(r"\\.\pipe\{name}"), token))
+}
+
+pub async fn write_request<W: AsyncWrite + Unpin>(w: &mut W, text: &str) -> std::io::Result<()> {
+    w.write_u32_le(text.len() as u32).await?;
+    w.write_all(text.as_bytes()).await?;
+    w.flush().await
+}
+
+/// Next reply frame as the message it stands for; None for an empty frame, an unknown kind
+/// byte or a broken pipe.
+pub async fn read_reply<R: AsyncRead + Unpin>(r: &mut R) -> Option<Message> {
+    let len = r.read_u32_le().await.ok()? as usize;
+    if len == 0 || len > MAX_REPLY {
+        return None;
+    }
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf).await.ok()?;
+    let kind = buf.remove(0);
+    match kind {
+        KIND_TEXT => String::from_utf8(buf).ok().map(Message::Text),
+        KIND_BINARY => Some(Message::Binary(buf)),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+pub struct PipeLink {
+    pipe: tokio::net::windows::named_pipe::NamedPipeClient,
+}
+
+#[cfg(windows)]
+impl crate::ws::AgentLink for PipeLink {
+    async fn open(url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        use tokio::net::windows::named_pipe::ClientOptions;
+        // Every pipe instance busy: another client is mid-connect, try again shortly
+        const ERROR_PIPE_BUSY: i32 = 231;
+
+        let (path, token) = parse_pipe_url(url).ok_or("pipe URLs look like pipe://NAME")?;
+        let mut tries = 0;
+        let mut pipe = loop {
+            match ClientOptions::new().open(&path) {
+                Ok(p) => break p,
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && tries < 20 => {
+                    tries += 1;
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                }
+                Err(e) => return Err(format!("{path}: {e}").into()),
+            }
+        };
+        let token = token.or_else(crate::ws::auth_token).unwrap_or_default();
+        write_request(&mut pipe, &token).await?;
+        match read_reply(&mut pipe).await {
+            Some(Message::Text(t)) if t == "ok" => Ok(PipeLink { pipe }),
+            _ => Err(format!("{path}: the agent rejected the token").into()),
+        }
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        write_request(&mut self.pipe, cmd).await.ok()?;
+        read_reply(&mut self.pipe).await
+    }
+}
@@ -0,0 +1,290 @@
+//! HTTP polling fallback for networks whose proxies or middleboxes refuse the WebSocket
+//! upgrade: each command is a `POST /poll` to the agent, answered with a kind byte (`0` text,
+//! `1` binary) and the same body a WebSocket frame would carry, or 204 when there is none.
+//!
+//! `ws://HOST:PORT/ws?token=T` polls `http://HOST:PORT/poll?token=T` (`wss` -> `https`, same
+//! TLS settings). The connection is kept alive between requests and reopened when the agent
+//! or a proxy closes it. The session from `hello` is echoed back in `x-socktop-session`.
+
+use std::io;
+use std::sync::Arc;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream};
+use url::Url;
+
+use crate::ws::{auth_token, tls_client_config, with_client_cert, AgentLink};
+
+const SESSION_HEADER: &str = "x-socktop-session";
+const KIND_TEXT: u8 = 0;
+const KIND_BINARY: u8 = 1;
+// Far above any real reply (the agent caps processes at --max-payload-bytes)
+const MAX_REPLY: usize = 64 * 1024 * 1024;
+// Header lines per response; more than this is not the agent
+const MAX_HEADERS: usize = 64;
+
+/// `ws[s]://HOST[:PORT]/PREFIX/ws[?QUERY]` -> `http[s]://HOST[:PORT]/PREFIX/poll[?QUERY]`;
+/// None for other schemes.
+pub fn poll_url(ws_url: &str) -> Option<Url> {
+    let mut u = Url::parse(ws_url).ok()?;
+    let scheme = match u.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        _ => return None,
+    };
+    u.set_scheme(scheme).ok()?;
+    let path = match u.path().strip_suffix("/ws") {
+        Some(prefix) => format!("{prefix}/poll"),
+        None => "/poll".to_string(),
+    };
+    u.set_path(&path);
+    Some(u)
+}
+
+/// One parsed HTTP response.
+#[derive(Debug, Default)]
+pub struct Response {
+    pub status: u16,
+    pub session: Option<String>,
+    // The server closes the connection after this response
+    pub close: bool,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// The reply as the message it stands for; None without a body, for errors and unknown
+    /// kind bytes.
+    pub fn into_message(self) -> Option<Message> {
+        if self.status != 200 {
+            return None;
+        }
+        let mut body = self.body;
+        if body.is_empty() {
+            return None;
+        }
+        match body.remove(0) {
+            KIND_TEXT => String::from_utf8(body).ok().map(Message::Text),
+            KIND_BINARY => Some(Message::Binary(body)),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(r: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    // Bounded: a line longer than this is not an HTTP header
+    if (&mut *r).take(8 * 1024).read_line(&mut line).await? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Read one HTTP/1.1 response: Content-Length, chunked, or read-until-close bodies.
+pub async fn read_response<R: AsyncBufRead + Unpin>(r: &mut R) -> io::Result<Response> {
+    // Nothing at all: the server dropped an idle keep-alive connection before this request
+    let status_line = read_line(r)
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::NotConnected, e))?;
+    let mut parts = status_line.split_whitespace();
+    let status: u16 = match (parts.next(), parts.next()) {
+        (Some(v), Some(code)) if v.starts_with("HTTP/") => {
+            code.parse().map_err(|_| invalid("bad status code"))?
+        }
+        _ => return Err(invalid("not an HTTP response")),
+    };
+    let mut resp = Response {
+        status,
+        close: status_line.starts_with("HTTP/1.0"),
+        ..Default::default()
+    };
+    let mut length: Option<usize> = None;
+    let mut chunked = false;
+    for _ in 0..=MAX_HEADERS {
+        let line = read_line(r).await?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                length = Some(value.parse().map_err(|_| invalid("bad content-length"))?)
+            }
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+            "connection" => resp.close = value.eq_ignore_ascii_case("close"),
+            SESSION_HEADER => resp.session = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if status == 204 || status == 304 || (100..200).contains(&status) {
+        return Ok(resp);
+    }
+    if chunked {
+        loop {
+            let line = read_line(r).await?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| invalid("bad chunk size"))?;
+            if size == 0 {
+                // Trailers, up to the blank line
+                while !read_line(r).await?.is_empty() {}
+                break;
+            }
+            if resp.body.len() + size > MAX_REPLY {
+                return Err(invalid("reply too large"));
+            }
+            let start = resp.body.len();
+            resp.body.resize(start + size, 0);
+            r.read_exact(&mut resp.body[start..]).await?;
+            read_line(r).await?;
+        }
+    } else if let Some(len) = length {
+        if len > MAX_REPLY {
+            return Err(invalid("reply too large"));
+        }
+        resp.body.resize(len, 0);
+        r.read_exact(&mut resp.body).await?;
+    } else {
+        // No length: the body runs until the server closes
+        (&mut *r)
+            .take(MAX_REPLY as u64)
+            .read_to_end(&mut resp.body)
+            .await?;
+        resp.close = true;
+    }
+    Ok(resp)
+}
+
+type Conn = BufReader<MaybeTlsStream<TcpStream>>;
+
+pub struct PollLink {
+    url: Url,
+    tls: Option<(TlsConnector, ServerName<'static>)>,
+    conn: Option<Conn>,
+    session: Option<String>,
+}
+
+impl PollLink {
+    /// Open a link and check that the agent answers on it; None when it doesn't (no `/poll`
+    /// endpoint, rejected token, or nothing listening).
+    pub async fn probe(url: &str, tls_ca: Option<&str>) -> Option<Self> {
+        let mut link = <Self as AgentLink>::open(url, tls_ca).await.ok()?;
+        link.exchange("get_metrics").await?;
+        Some(link)
+    }
+
+    fn host(&self) -> &str {
+        self.url.host_str().unwrap_or_default()
+    }
+
+    async fn connect(&self) -> io::Result<Conn> {
+        let port = self.url.port_or_known_default().unwrap_or(80);
+        let tcp = TcpStream::connect((self.host().trim_matches(['[', ']']), port)).await?;
+        let _ = tcp.set_nodelay(true);
+        let stream = match &self.tls {
+            Some((connector, name)) => {
+                MaybeTlsStream::Rustls(connector.connect(name.clone(), tcp).await?)
+            }
+            None => MaybeTlsStream::Plain(tcp),
+        };
+        Ok(BufReader::new(stream))
+    }
+
+    async fn round_trip(&mut self, cmd: &str) -> io::Result<Response> {
+        let mut conn = match self.conn.take() {
+            Some(c) => c,
+            None => self.connect().await?,
+        };
+        let target = match self.url.query() {
+            Some(q) => format!("{}?{q}", self.url.path()),
+            None => self.url.path().to_string(),
+        };
+        let host = match self.url.port() {
+            Some(p) => format!("{}:{p}", self.host()),
+            None => self.host().to_string(),
+        };
+        let mut req = format!(
+            "POST {target} HTTP/1.1\r\nHost: {host}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n",
+            cmd.len()
+        );
+        if let Some(s) = &self.session {
+            req.push_str(&format!("{SESSION_HEADER}: {s}\r\n"));
+        }
+        if let Some(t) = auth_token() {
+            req.push_str(&format!("Authorization: Bearer {t}\r\n"));
+        }
+        req.push_str("\r\n");
+        req.push_str(cmd);
+        // Write failures mean a connection that was already gone, like a missing status line
+        let stale = |e| io::Error::new(io::ErrorKind::NotConnected, e);
+        conn.get_mut()
+            .write_all(req.as_bytes())
+            .await
+            .map_err(stale)?;
+        conn.get_mut().flush().await.map_err(stale)?;
+        let resp = read_response(&mut conn).await?;
+        if !resp.close {
+            self.conn = Some(conn);
+        }
+        Ok(resp)
+    }
+}
+
+impl AgentLink for PollLink {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut u = poll_url(url).ok_or("the HTTP fallback needs a ws:// or wss:// URL")?;
+        let tls = match tls_client_config(tls_ca)? {
+            // --tls-ca/--tls-fingerprint upgrade to TLS, as they do for ws://
+            Some(cfg) => {
+                let _ = u.set_scheme("https");
+                Some(cfg)
+            }
+            // Same as the WebSocket client: no built-in roots, so plain https needs --tls-ca
+            None if u.scheme() == "https" => Some(Arc::new(with_client_cert(
+                ClientConfig::builder().with_root_certificates(RootCertStore::empty()),
+            )?)),
+            None => None,
+        };
+        let tls = match tls {
+            Some(cfg) => {
+                let host = u.host_str().ok_or("missing host")?.trim_matches(['[', ']']);
+                let name = ServerName::try_from(host.to_string())?;
+                Some((TlsConnector::from(cfg), name))
+            }
+            None => None,
+        };
+        let mut link = PollLink {
+            url: u,
+            tls,
+            conn: None,
+            session: None,
+        };
+        link.conn = Some(link.connect().await?);
+        Ok(link)
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        let reused = self.conn.is_some();
+        let resp = match self.round_trip(cmd).await {
+            Ok(r) => r,
+            // A kept-alive connection closed while idle; the command never reached the agent
+            Err(e) if reused && e.kind() == io::ErrorKind::NotConnected => {
+                self.round_trip(cmd).await.ok()?
+            }
+            Err(_) => return None,
+        };
+        if resp.session.is_some() {
+            self.session = resp.session.clone();
+        }
+        resp.into_message()
+    }
+}
@@ -0,0 +1,225 @@
+//! Connection preflight: walk DNS → TCP → TLS/WebSocket upgrade → auth before entering the TUI,
+//! and report which stage failed with a hint, instead of a raw error after the screen flashes.
+//! A failed upgrade is not fatal when the agent answers the HTTP polling fallback instead.
+
+use std::fmt;
+use std::time::Duration;
+
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+use url::Url;
+
+use crate::poll::PollLink;
+use crate::ws::{auth_token, connect, request_metrics, WsStream};
+
+const STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Url,
+    Dns,
+    Tcp,
+    Tls,
+    Upgrade,
+    Auth,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Stage::Url => "URL",
+            Stage::Dns => "DNS lookup",
+            Stage::Tcp => "TCP connect",
+            Stage::Tls => "TLS handshake",
+            Stage::Upgrade => "WebSocket upgrade",
+            Stage::Auth => "authentication",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug)]
+pub struct PreflightError {
+    pub stage: Stage,
+    pub detail: String,
+    pub advice: Option<&'static str>,
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "socktop: {} failed: {}", self.stage, self.detail)?;
+        if let Some(a) = self.advice {
+            write!(f, "\n  hint: {a}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PreflightError {}
+
+fn fail(stage: Stage, detail: impl Into<String>, advice: Option<&'static str>) -> PreflightError {
+    PreflightError {
+        stage,
+        detail: detail.into(),
+        advice,
+    }
+}
+
+/// Classify a failed TLS/WS connect by its error text (rustls errors surface wrapped in io errors).
+fn classify_connect_error(is_wss: bool, msg: &str) -> (Stage, Option<&'static str>) {
+    let m = msg.to_ascii_lowercase();
+    if m.contains("fingerprint mismatch") {
+        return (
+            Stage::Tls,
+            Some(
+                "certificate fingerprint does not match — the agent cert may have been regenerated",
+            ),
+        );
+    }
+    if m.contains("unknownissuer") || m.contains("unknown issuer") || m.contains("badsignature") {
+        return (
+            Stage::Tls,
+            Some("self-signed cert — pass --tls-ca <cert.pem> or --tls-fingerprint <sha256>"),
+        );
+    }
+    if m.contains("notvalidforname") {
+        return (
+            Stage::Tls,
+            Some("certificate SAN does not cover this host — drop --verify-hostname or add SOCKTOP_AGENT_EXTRA_SANS on the agent"),
+        );
+    }
+    if m.contains("expired") {
+        return (
+            Stage::Tls,
+            Some("agent certificate expired — delete cert.pem/key.pem on the agent and restart it"),
+        );
+    }
+    if is_wss && (m.contains("corrupt message") || m.contains("invalidcontenttype")) {
+        return (
+            Stage::Tls,
+            Some("agent does not appear to have TLS enabled — try ws:// instead of wss://"),
+        );
+    }
+    if m.contains("certificate") || m.contains("tls") {
+        return (Stage::Tls, None);
+    }
+    if !is_wss && (m.contains("httparse") || m.contains("http format") || m.contains("reset")) {
+        return (
+            Stage::Upgrade,
+            Some("agent may be running with TLS — try wss:// (and --tls-ca/--tls-fingerprint)"),
+        );
+    }
+    if m.contains("404") {
+        return (
+            Stage::Upgrade,
+            Some("no WebSocket endpoint at this path — the agent serves /ws"),
+        );
+    }
+    (Stage::Upgrade, None)
+}
+
+// Something on the path refused the upgrade, but the agent may still answer plain HTTP polls
+async fn poll_answers(url: &str, tls_ca: Option<&str>) -> bool {
+    matches!(
+        timeout(STAGE_TIMEOUT, PollLink::probe(url, tls_ca)).await,
+        Ok(Some(_))
+    )
+}
+
+/// Run every stage against `url`; Ok(()) means the TUI can start, over WebSocket or the HTTP
+/// polling fallback.
+pub async fn run(url: &str, tls_ca: Option<&str>) -> Result<(), PreflightError> {
+    let u = Url::parse(url).map_err(|e| {
+        fail(
+            Stage::Url,
+            format!("{url}: {e}"),
+            Some("expected ws://HOST:PORT/ws, wss://HOST:PORT/ws or HOST[:PORT]"),
+        )
+    })?;
+    let host = u
+        .host_str()
+        .ok_or_else(|| fail(Stage::Url, format!("{url}: missing host"), None))?
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = u.port_or_known_default().unwrap_or(80);
+    let is_wss = u.scheme() == "wss"
+        || tls_ca.is_some()
+        || std::env::var("SOCKTOP_TLS_FINGERPRINT").is_ok_and(|v| !v.is_empty());
+
+    let addrs: Vec<_> = match timeout(STAGE_TIMEOUT, lookup_host((host.as_str(), port))).await {
+        Ok(Ok(it)) => it.collect(),
+        Ok(Err(e)) => {
+            return Err(fail(
+                Stage::Dns,
+                format!("{host}: {e}"),
+                Some("check the hostname spelling or use an IP address"),
+            ))
+        }
+        Err(_) => return Err(fail(Stage::Dns, format!("{host}: timed out"), None)),
+    };
+    if addrs.is_empty() {
+        return Err(fail(Stage::Dns, format!("{host}: no addresses"), None));
+    }
+
+    match timeout(STAGE_TIMEOUT, TcpStream::connect(addrs.as_slice())).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            return Err(fail(
+                Stage::Tcp,
+                format!("{host}:{port}: {e}"),
+                Some("is socktop_agent running on that port? check firewalls (agent defaults: 3000, 8443 with TLS)"),
+            ))
+        }
+        Err(_) => {
+            return Err(fail(
+                Stage::Tcp,
+                format!("{host}:{port}: timed out"),
+                Some("host unreachable or port filtered by a firewall"),
+            ))
+        }
+    }
+
+    let ws = match timeout(STAGE_TIMEOUT, connect(url, tls_ca)).await {
+        Ok(Ok(ws)) => ws,
+        Ok(Err(e)) => {
+            let msg = e.to_string();
+            let (stage, advice) = classify_connect_error(is_wss, &msg);
+            if stage == Stage::Upgrade && poll_answers(url, tls_ca).await {
+                return Ok(());
+            }
+            return Err(fail(stage, msg, advice));
+        }
+        Err(_) => {
+            if poll_answers(url, tls_ca).await {
+                return Ok(());
+            }
+            return Err(fail(
+                Stage::Upgrade,
+                "timed out",
+                Some("port is open but no WebSocket answer — is this a socktop_agent?"),
+            ));
+        }
+    };
+
+    check_auth(ws, url).await
+}
+
+/// The last stage alone, on a connection that is already upgraded (the `host[:port]` probe's).
+pub async fn check_auth(mut ws: WsStream, url: &str) -> Result<(), PreflightError> {
+    // The agent accepts the upgrade but closes immediately on a bad/missing token
+    let ok = matches!(
+        timeout(STAGE_TIMEOUT, request_metrics(&mut ws)).await,
+        Ok(Some(_))
+    );
+    let _ = ws.close(None).await;
+    if !ok {
+        let in_url = Url::parse(url).is_ok_and(|u| u.query_pairs().any(|(k, _)| k == "token"));
+        let advice = if in_url || auth_token().is_some() {
+            "token rejected — check SOCKTOP_TOKEN on the agent"
+        } else {
+            "agent requires token — pass --token or append ?token=... to the URL"
+        };
+        return Err(fail(Stage::Auth, "no metrics reply", Some(advice)));
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Importance sampling of the process list on big hosts: each poll asks for the top K rows
+//! plus a slice of the long tail (`get_processes?top=K&tail=M&after=PID`, agents listing
+//! `processes_sample`). A token bucket sizes the slice so the whole tail is swept about once
+//! per `SWEEP` whatever the poll interval; sampled rows are kept between polls, so the table
+//! still lists every process without thousands of rows on the wire each refresh.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::types::ProcessInfo;
+
+/// Time to cycle through the whole tail once.
+pub const SWEEP: Duration = Duration::from_secs(60);
+// Tokens saved up while idle are capped at this much sweep time
+const BURST: Duration = Duration::from_secs(10);
+// Tail rows allowed per burst before the tail size is known (first poll)
+const MIN_BURST: f64 = 64.0;
+/// Top rows per sampled poll without a bandwidth budget (fills any screen).
+pub const SAMPLE_TOP_K: usize = 100;
+/// Hosts with more processes than this get sampled replies instead of the full list.
+pub const SAMPLE_ABOVE: usize = 1_000;
+
+#[derive(Debug, Default)]
+pub struct TailSampler {
+    tokens: f64,
+    last: Option<Instant>,
+    // Last tail PID received; the next slice starts after it
+    after: u32,
+    // Sampled rows not in the current top K, with when they were last seen
+    rows: HashMap<u32, (ProcessInfo, Instant)>,
+}
+
+impl TailSampler {
+    /// PID the next tail slice starts after.
+    pub fn after(&self) -> u32 {
+        self.after
+    }
+
+    /// Tail rows to ask for at `now`, out of `tail_len` (0 when unknown yet).
+    pub fn take(&mut self, now: Instant, tail_len: usize) -> usize {
+        let rate = tail_len as f64 / SWEEP.as_secs_f64();
+        let cap = (rate * BURST.as_secs_f64()).max(MIN_BURST);
+        self.tokens = match self.last {
+            Some(t) => self.tokens + rate * now.saturating_duration_since(t).as_secs_f64(),
+            None => cap,
+        }
+        .min(cap);
+        self.last = Some(now);
+        let mut n = self.tokens.floor() as usize;
+        if tail_len > 0 {
+            n = n.min(tail_len);
+        }
+        self.tokens -= n as f64;
+        n
+    }
+
+    /// Fold one sampled reply in: `top` is current, `tail` refreshes its slice of the tail.
+    /// Cached rows in the slice's PID range that didn't come back have exited; the rest age
+    /// out after two sweeps. Returns top rows followed by every cached tail row.
+    pub fn merge(
+        &mut self,
+        now: Instant,
+        top: Vec<ProcessInfo>,
+        tail: Vec<ProcessInfo>,
+        process_count: usize,
+    ) -> Vec<ProcessInfo> {
+        if top.len() + tail.len() >= process_count {
+            // The reply held every process
+            self.rows.clear();
+        } else if let Some(last) = tail.last() {
+            let (from, to) = (self.after, last.pid);
+            // The slice runs from just after `from` up to `to`, wrapping past the highest PID
+            let covered = |pid: u32| {
+                if from < to {
+                    pid > from && pid <= to
+                } else {
+                    pid > from || pid <= to
+                }
+            };
+            self.rows.retain(|pid, _| !covered(*pid));
+        }
+        if let Some(last) = tail.last() {
+            self.after = last.pid;
+        }
+        for p in tail {
+            self.rows.insert(p.pid, (p, now));
+        }
+        for p in &top {
+            self.rows.remove(&p.pid);
+        }
+        self.rows
+            .retain(|_, (_, seen)| now.saturating_duration_since(*seen) < SWEEP * 2);
+        let mut out = top;
+        let mut cached: Vec<ProcessInfo> = self.rows.values().map(|(p, _)| p.clone()).collect();
+        cached.sort_unstable_by_key(|p| p.pid);
+        out.extend(cached);
+        out
+    }
+}
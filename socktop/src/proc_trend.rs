@@ -0,0 +1,76 @@
+//! Short per-PID CPU history kept from the processes polls, so each row can show whether the
+//! process is ramping up or settling down (a glyph after its CPU %).
+//!
+//! Only the last `TREND_SAMPLES` polls are kept, and a PID is forgotten as soon as a poll no
+//! longer lists it.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::types::ProcessInfo;
+
+pub const TREND_SAMPLES: usize = 6;
+// Fewer samples than this: too early to call
+const MIN_SAMPLES: usize = 3;
+// Change (CPU percentage points) below which a process counts as steady, whatever its level
+const MIN_CHANGE: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    /// Glyph shown after the CPU % (blank while steady, to keep the table calm).
+    pub fn glyph(self) -> char {
+        match self {
+            Trend::Rising => '↑',
+            Trend::Falling => '↓',
+            Trend::Steady => ' ',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcTrends {
+    hist: HashMap<u32, VecDeque<f32>>,
+}
+
+impl ProcTrends {
+    /// Feed the rows of one processes poll.
+    pub fn record(&mut self, rows: &[ProcessInfo]) {
+        self.hist
+            .retain(|pid, _| rows.iter().any(|p| p.pid == *pid));
+        for p in rows {
+            let h = self.hist.entry(p.pid).or_default();
+            if h.len() == TREND_SAMPLES {
+                h.pop_front();
+            }
+            h.push_back(p.cpu_usage);
+        }
+    }
+
+    /// Newer half of the history against the older half: a move of at least `MIN_CHANGE`
+    /// points and a quarter of the older level is a trend.
+    pub fn trend(&self, pid: u32) -> Trend {
+        let Some(h) = self.hist.get(&pid).filter(|h| h.len() >= MIN_SAMPLES) else {
+            return Trend::Steady;
+        };
+        let half = h.len() / 2;
+        let old = h.iter().take(half).sum::<f32>() / half as f32;
+        let new = h.iter().rev().take(half).sum::<f32>() / half as f32;
+        let change = new - old;
+        if change.abs() < MIN_CHANGE.max(old * 0.25) {
+            Trend::Steady
+        } else if change > 0.0 {
+            Trend::Rising
+        } else {
+            Trend::Falling
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.hist.clear();
+    }
+}
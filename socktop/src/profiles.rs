@@ -1,8 +1,9 @@
-//! Connection profiles: load/save simple JSON mapping of profile name -> { url, tls_ca }
+//! Connection profiles: load/save simple JSON mapping of profile name -> { url, tls_ca, ... }
 //! Stored under XDG config dir: $XDG_CONFIG_HOME/socktop/profiles.json (fallback ~/.config/socktop/profiles.json)
 
+use crate::alerts::AlertRules;
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, io::Write, path::PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProfileEntry {
@@ -13,6 +14,73 @@ pub struct ProfileEntry {
     pub metrics_interval_ms: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub processes_interval_ms: Option<u64>,
+    /// Maintenance mode: alerts for this host are silenced until this Unix time (seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silenced_until: Option<u64>,
+    /// SSH destination (`user@host`) to tunnel through; `url` is then the agent address as seen
+    /// from that host (e.g. ws://127.0.0.1:3000/ws)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<String>,
+    /// Bastions to hop through before `ssh`, in order (ssh -J jump1,jump2)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_jump: Vec<String>,
+    /// Groups this host belongs to (`socktop fleet snapshot --group NAME`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Startup layout: `default`, or the panel to open maximized (e.g. `disks`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Panels to show (`cpu`, `cores`, `mem`, `swap`, `gpu`, `disks`, `net`, `processes`);
+    /// empty shows all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panels: Vec<String>,
+    /// Environment overrides for this host (`SOCKTOP_DEBUG`, `SOCKTOP_MAX_BANDWIDTH`,
+    /// `SOCKTOP_A11Y`, ...), set at connect time unless the variable is already set
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Alert thresholds for this host (same shape as alerts.json, which it replaces)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertRules>,
+    /// Auth token for this host, sent as `Authorization: Bearer` (profiles.json is then written
+    /// owner-only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Client certificate and key for agents started with `--require-client-cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+}
+
+/// Split a profile's `env` into the overrides to set and the names that are ignored (anything
+/// not `SOCKTOP_*`). Variables for which `is_set` is true are left alone: a flag or the real
+/// environment wins over the profile.
+pub fn env_overrides(
+    env: &BTreeMap<String, String>,
+    is_set: impl Fn(&str) -> bool,
+) -> (Vec<(&str, &str)>, Vec<&str>) {
+    let mut apply = Vec::new();
+    let mut ignored = Vec::new();
+    for (k, v) in env {
+        if !k.starts_with("SOCKTOP_") {
+            ignored.push(k.as_str());
+        } else if !is_set(k) {
+            apply.push((k.as_str(), v.as_str()));
+        }
+    }
+    (apply, ignored)
+}
+
+/// Persist (or clear) the maintenance window of an existing profile.
+pub fn save_silence(profile: &str, until: Option<u64>) -> std::io::Result<()> {
+    let mut pf = load_profiles();
+    match pf.profiles.get_mut(profile) {
+        Some(entry) => {
+            entry.silenced_until = until;
+            save_profiles(&pf)
+        }
+        None => Ok(()),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -33,6 +101,17 @@ pub fn config_dir() -> PathBuf {
     }
 }
 
+/// Client state (logs) under $XDG_STATE_HOME/socktop (fallback: local data dir).
+pub fn state_dir() -> PathBuf {
+    if let Some(xdg) = std::env::var_os("XDG_STATE_HOME") {
+        PathBuf::from(xdg).join("socktop")
+    } else {
+        dirs_next::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("socktop")
+    }
+}
+
 pub fn profiles_path() -> PathBuf {
     config_dir().join("profiles.json")
 }
@@ -51,7 +130,22 @@ pub fn save_profiles(p: &ProfilesFile) -> std::io::Result<()> {
         fs::create_dir_all(parent)?;
     }
     let data = serde_json::to_vec_pretty(p).expect("serialize profiles");
-    fs::write(path, data)
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    // Profiles can hold tokens: owner-only before anything is written
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts.open(&path)?;
+    // `mode` only applies to new files; tighten one left by an older version too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(&data)
 }
 
 pub enum ResolveProfile {
@@ -76,12 +170,11 @@ pub struct ProfileRequest {
 impl ProfileRequest {
     pub fn resolve(self, pf: &ProfilesFile) -> ResolveProfile {
         // Case: only profile name given -> try load
-        if self.url.is_none() && self.profile_name.is_some() {
-            let name = self.profile_name.unwrap();
-            if let Some(entry) = pf.profiles.get(&name) {
+        if let (None, Some(name)) = (&self.url, &self.profile_name) {
+            if let Some(entry) = pf.profiles.get(name) {
                 return ResolveProfile::Loaded(entry.url.clone(), entry.tls_ca.clone());
             } else {
-                return ResolveProfile::PromptCreate(name);
+                return ResolveProfile::PromptCreate(name.clone());
             }
         }
         // Both provided -> direct (maybe later saved by caller)
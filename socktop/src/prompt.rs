@@ -0,0 +1,213 @@
+//! Interactive setup prompts (first run, profile creation): a small line editor with
+//! defaults and validation. Ctrl-C aborts the whole setup like it would in a shell.
+
+use std::io::{self, IsTerminal, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use unicode_width::UnicodeWidthStr;
+
+/// Line buffer with a cursor, fed one key at a time.
+#[derive(Debug, Default, Clone)]
+pub struct LineEditor {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Continue,
+    Submit,
+    Abort,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn apply(&mut self, key: KeyEvent) -> Edit {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Edit::Abort,
+            // Ctrl-D on an empty line is end-of-input, as in a shell
+            KeyCode::Char('d') if ctrl && self.chars.is_empty() => return Edit::Abort,
+            KeyCode::Enter => return Edit::Submit,
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.chars.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.chars.truncate(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.chars.len(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.chars.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            _ => {}
+        }
+        Edit::Continue
+    }
+}
+
+/// Ask until `validate` accepts the answer; a blank answer means `default` when there is one.
+pub fn ask(
+    label: &str,
+    default: Option<&str>,
+    validate: impl Fn(&str) -> Result<(), String>,
+) -> io::Result<String> {
+    let prompt = match default {
+        Some(d) => format!("{label} [{d}]: "),
+        None => format!("{label}: "),
+    };
+    loop {
+        // End of piped input counts as a blank answer, once
+        let line = read_line(&prompt)?;
+        let answer = match (line.as_deref().map(str::trim), default) {
+            (None | Some(""), Some(d)) => d.to_string(),
+            (t, _) => t.unwrap_or_default().to_string(),
+        };
+        match validate(&answer) {
+            Ok(()) => return Ok(answer),
+            Err(e) if line.is_none() => {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, e))
+            }
+            Err(e) => eprintln!("  {e}"),
+        }
+    }
+}
+
+/// Yes/no question; a blank answer picks `default`.
+pub fn confirm(label: &str, default: bool) -> io::Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    let answer = ask(&format!("{label} [{hint}]"), None, |s| {
+        parse_yes_no(s)
+            .map(|_| ())
+            .ok_or_else(|| "please answer y or n".into())
+    })?;
+    Ok(parse_yes_no(&answer).flatten().unwrap_or(default))
+}
+
+// Some(None) for a blank answer, None for anything unrecognized
+fn parse_yes_no(s: &str) -> Option<Option<bool>> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "" => Some(None),
+        "y" | "yes" => Some(Some(true)),
+        "n" | "no" => Some(Some(false)),
+        _ => None,
+    }
+}
+
+/// Agent URL as accepted on the command line: ws://, wss://, https:// or `host[:port]`.
+pub fn valid_url(s: &str) -> Result<(), String> {
+    if s.contains("://") {
+        let u = url::Url::parse(s).map_err(|e| format!("not a valid URL: {e}"))?;
+        if !matches!(u.scheme(), "ws" | "wss" | "https") {
+            return Err(format!(
+                "unsupported scheme {}://, use ws:// or wss://",
+                u.scheme()
+            ));
+        }
+        if u.host_str().is_none_or(str::is_empty) {
+            return Err("URL needs a host".into());
+        }
+        return Ok(());
+    }
+    let authority = s.split(['/', '?']).next().unwrap_or_default();
+    if authority.is_empty() || authority.contains(char::is_whitespace) {
+        return Err("expected ws://HOST:PORT/ws, wss://... or HOST[:PORT]".into());
+    }
+    match authority.rsplit_once(':') {
+        Some((_, port)) if !authority.ends_with(']') && port.parse::<u16>().is_err() => {
+            Err(format!("invalid port '{port}'"))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn existing_file(s: &str) -> Result<(), String> {
+    if std::path::Path::new(s).is_file() {
+        Ok(())
+    } else {
+        Err(format!("no such file: {s}"))
+    }
+}
+
+pub fn number(s: &str) -> Result<(), String> {
+    s.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("'{s}' is not a whole number"))
+}
+
+/// Accept a blank answer, otherwise apply `validate`.
+pub fn optional(
+    validate: impl Fn(&str) -> Result<(), String>,
+) -> impl Fn(&str) -> Result<(), String> {
+    move |s| if s.is_empty() { Ok(()) } else { validate(s) }
+}
+
+// One answer line; None at the end of piped input
+fn read_line(prompt: &str) -> io::Result<Option<String>> {
+    if !io::stdin().is_terminal() {
+        // Piped input (scripts, tests): plain lines, no editing
+        eprint!("{prompt}");
+        let _ = io::stderr().flush();
+        let mut line = String::new();
+        let n = io::stdin().read_line(&mut line)?;
+        return Ok((n > 0).then_some(line));
+    }
+    enable_raw_mode()?;
+    let res = edit_line(prompt);
+    disable_raw_mode()?;
+    eprintln!();
+    match res? {
+        Some(line) => Ok(Some(line)),
+        None => {
+            eprintln!("Aborted.");
+            std::process::exit(130);
+        }
+    }
+}
+
+// Raw-mode editing loop; None when the user aborted
+fn edit_line(prompt: &str) -> io::Result<Option<String>> {
+    let mut ed = LineEditor::default();
+    let mut err = io::stderr();
+    loop {
+        // Redraw the whole line, then put the cursor back where the editor has it
+        let text = ed.text();
+        let tail: String = text.chars().skip(ed.cursor()).collect();
+        let back = UnicodeWidthStr::width(tail.as_str());
+        write!(err, "\r{prompt}{text}\x1b[K")?;
+        if back > 0 {
+            write!(err, "\x1b[{back}D")?;
+        }
+        err.flush()?;
+        let Event::Key(k) = event::read()? else {
+            continue;
+        };
+        if k.kind == KeyEventKind::Release {
+            continue;
+        }
+        match ed.apply(k) {
+            Edit::Continue => {}
+            Edit::Submit => return Ok(Some(ed.text())),
+            Edit::Abort => return Ok(None),
+        }
+    }
+}
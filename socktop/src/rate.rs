@@ -0,0 +1,62 @@
+//! Deltas and per-second rates from monotonic counters (network totals, jiffies) that tell a
+//! reset apart from "no change" instead of clamping both to zero.
+
+use std::time::Instant;
+
+// A backwards step smaller than this from a value that fits in 32 bits is treated as a 32-bit
+// wraparound (older kernels/drivers); anything else is a reset.
+const WRAP_LIMIT: u64 = 1 << 31;
+
+/// Increase of a counter between two readings. Handles 32-bit wraparound; None when the counter
+/// was reset (restart, interface removed, pid reused) and `prev` is no longer a usable baseline.
+pub fn counter_delta(prev: u64, now: u64) -> Option<u64> {
+    if now >= prev {
+        return Some(now - prev);
+    }
+    if prev <= u32::MAX as u64 {
+        let wrapped = (u32::MAX as u64 - prev) + now + 1;
+        if wrapped < WRAP_LIMIT {
+            return Some(wrapped);
+        }
+    }
+    None
+}
+
+/// Per-second rate of one monotonic counter.
+#[derive(Debug, Default, Clone)]
+pub struct RateCounter {
+    // value, arrival time, agent stamp (ms) when the reading carried one
+    last: Option<(u64, Instant, Option<u64>)>,
+}
+
+impl RateCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a reading. Returns the rate since the previous reading, or None on the first reading
+    /// and after a reset (the reading becomes the new baseline either way).
+    pub fn update(&mut self, value: u64, now: Instant) -> Option<f64> {
+        self.update_stamped(value, None, now)
+    }
+
+    /// Like `update`, for readings the agent stamped with when it took them (`sample_ms`): the
+    /// rate is taken over the agent's interval, so a frame that arrived late, or two that
+    /// arrived together, don't turn into a dip and a spike. A repeat of the previous stamp (the
+    /// agent answered from its cache) returns None and keeps the baseline; a stamp going
+    /// backwards (agent restarted) falls back to arrival times.
+    pub fn update_stamped(&mut self, value: u64, stamp: Option<u64>, now: Instant) -> Option<f64> {
+        if let (Some(s), Some((_, _, Some(prev)))) = (stamp, self.last) {
+            if s == prev {
+                return None;
+            }
+        }
+        let (pv, at, prev_stamp) = self.last.replace((value, now, stamp))?;
+        let d = counter_delta(pv, value)?;
+        let dt = match (prev_stamp, stamp) {
+            (Some(a), Some(b)) if b > a => (b - a) as f64 / 1000.0,
+            _ => now.checked_duration_since(at)?.as_secs_f64(),
+        };
+        Some(d as f64 / dt.max(1e-3))
+    }
+}
@@ -0,0 +1,53 @@
+//! Reconnecting after the agent went away: the first attempt is immediate, then the wait
+//! doubles from `FIRST_RETRY` up to `MAX_RETRY`, so a restarting agent is picked up within a
+//! second while one that is down for good isn't hammered. The header shows the state meanwhile.
+
+use std::time::{Duration, Instant};
+
+pub const FIRST_RETRY: Duration = Duration::from_secs(1);
+pub const MAX_RETRY: Duration = Duration::from_secs(30);
+
+/// Wait before the next attempt after `failures` failed ones.
+pub fn delay(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    (FIRST_RETRY * 2u32.pow(doublings)).min(MAX_RETRY)
+}
+
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    failures: u32,
+    next_at: Instant,
+}
+
+impl Backoff {
+    /// Connection just lost at `now`; the first attempt is due right away.
+    pub fn new(now: Instant) -> Self {
+        Backoff {
+            failures: 0,
+            next_at: now,
+        }
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        now >= self.next_at
+    }
+
+    /// The attempt started at `now` failed.
+    pub fn failed(&mut self, now: Instant) {
+        self.failures += 1;
+        self.next_at = now + delay(self.failures);
+    }
+
+    /// Header text, e.g. "⟳ reconnecting… (3 failed, next try in 4s)".
+    pub fn status(&self, now: Instant) -> String {
+        if self.failures == 0 {
+            return "⟳ reconnecting…".into();
+        }
+        let wait = self.next_at.saturating_duration_since(now);
+        format!(
+            "⟳ reconnecting… ({} failed, next try in {}s)",
+            self.failures,
+            wait.as_secs_f64().ceil() as u64
+        )
+    }
+}
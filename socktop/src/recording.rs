@@ -0,0 +1,150 @@
+//! `--record FILE`: every metrics, processes and disks sample the client receives, appended to
+//! a JSON-lines file that `--replay FILE` plays back (see replay).
+//!
+//! The first line is `{"socktop_recording":1,"url":...,"started_unix":...}`. Each following
+//! line is `{"at_ms":N,"kind":"metrics"|"processes"|"disks","data":...}`, with `at_ms` counted
+//! from the start of the recording and `data` the decoded sample (metrics exactly as the agent
+//! sent them). Lines are flushed as they are written, so an interrupted session keeps what it
+//! had.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Metrics,
+    Processes,
+    Disks,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Metrics => "metrics",
+            Kind::Processes => "processes",
+            Kind::Disks => "disks",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Header {
+    socktop_recording: u32,
+    url: String,
+    started_unix: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sample {
+    pub at_ms: u64,
+    pub kind: Kind,
+    pub data: serde_json::Value,
+}
+
+struct Recorder {
+    out: BufWriter<File>,
+    started: Instant,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Start recording the session with `url` to `path` (replacing the file).
+pub fn configure(path: &Path, url: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let header = Header {
+        socktop_recording: FORMAT_VERSION,
+        url: url.to_string(),
+        started_unix: crate::alerts::now_secs(),
+    };
+    writeln!(out, "{}", serde_json::to_string(&header)?)?;
+    out.flush()?;
+    if let Ok(mut r) = RECORDER.lock() {
+        *r = Some(Recorder {
+            out,
+            started: Instant::now(),
+        });
+    }
+    Ok(())
+}
+
+/// Append a sample whose JSON text is `json`; nothing happens unless recording.
+pub fn record(kind: Kind, json: &str) {
+    let Ok(mut guard) = RECORDER.lock() else {
+        return;
+    };
+    let Some(r) = guard.as_mut() else {
+        return;
+    };
+    // Raw newlines can only be whitespace between JSON tokens; each sample stays on one line
+    let data = json.trim().replace(['\n', '\r'], "");
+    let at_ms = r.started.elapsed().as_millis() as u64;
+    let line = format!(
+        "{{\"at_ms\":{at_ms},\"kind\":\"{}\",\"data\":{data}}}",
+        kind.label()
+    );
+    // A full disk (or a removed file) ends the recording rather than the session
+    if writeln!(r.out, "{line}")
+        .and_then(|_| r.out.flush())
+        .is_err()
+    {
+        *guard = None;
+    }
+}
+
+/// `record` for a decoded sample.
+pub fn record_value<T: Serialize>(kind: Kind, value: &T) {
+    if let Ok(json) = serde_json::to_string(value) {
+        record(kind, &json);
+    }
+}
+
+/// A loaded recording.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub url: String,
+    #[allow(dead_code)]
+    pub started_unix: u64,
+    pub samples: Vec<Sample>,
+}
+
+pub fn load(path: &Path) -> Result<Recording, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Parse a recording. A cut-off last line (the client was killed mid-write) is dropped.
+pub fn parse(text: &str) -> Result<Recording, String> {
+    let mut lines = text.lines();
+    let header: Header = lines
+        .next()
+        .and_then(|l| serde_json::from_str(l).ok())
+        .ok_or("not a socktop recording")?;
+    if header.socktop_recording > FORMAT_VERSION {
+        return Err(format!(
+            "recording format {} is newer than this socktop reads ({FORMAT_VERSION})",
+            header.socktop_recording
+        ));
+    }
+    let lines: Vec<&str> = lines.filter(|l| !l.trim().is_empty()).collect();
+    let mut samples = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str::<Sample>(line) {
+            Ok(s) => samples.push(s),
+            Err(_) if i + 1 == lines.len() => {}
+            Err(e) => return Err(format!("line {}: {e}", i + 2)),
+        }
+    }
+    Ok(Recording {
+        url: header.url,
+        started_unix: header.started_unix,
+        samples,
+    })
+}
@@ -0,0 +1,193 @@
+//! `--replay FILE`: plays a `--record` file back through the normal dashboard. `ReplayLink`
+//! answers the client's requests from the recording instead of an agent: each `get_metrics`
+//! takes the next recorded sample, and processes and disks come from the latest ones recorded
+//! before it. Space pauses, `[` and `]` seek 10 s back or forward.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::recording::{Kind, Recording};
+use crate::ws::AgentLink;
+
+pub const SEEK_STEP_MS: u64 = 10_000;
+
+/// Playback position and state, shared by the dashboard (keys, header) and `ReplayLink`.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    rec: Recording,
+    // Indices of the metrics samples, in order
+    metrics: Vec<usize>,
+    // Next metrics sample to serve (index into `metrics`)
+    next: usize,
+    paused: bool,
+    // After a seek: serve one sample even while paused, so the new position shows
+    show_one: bool,
+}
+
+pub type ReplayHandle = Arc<Mutex<Playback>>;
+
+fn mmss(ms: u64) -> String {
+    let s = ms / 1000;
+    format!("{:02}:{:02}", s / 60, s % 60)
+}
+
+impl Playback {
+    pub fn new(rec: Recording) -> Self {
+        let metrics = rec
+            .samples
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == Kind::Metrics)
+            .map(|(i, _)| i)
+            .collect();
+        Playback {
+            rec,
+            metrics,
+            next: 0,
+            paused: false,
+            show_one: false,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.rec.url
+    }
+
+    /// Median gap between metrics samples: the poll interval that plays back in real time.
+    pub fn sample_interval(&self) -> Option<Duration> {
+        let at = |i: usize| self.rec.samples[i].at_ms;
+        let mut gaps: Vec<u64> = self
+            .metrics
+            .windows(2)
+            .map(|w| at(w[1]).saturating_sub(at(w[0])))
+            .collect();
+        gaps.sort_unstable();
+        gaps.get(gaps.len() / 2)
+            .map(|&ms| Duration::from_millis(ms))
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.metrics.len()
+    }
+
+    /// Nothing new to show: paused (and not just moved) or at the end.
+    pub fn holding(&self) -> bool {
+        (self.paused && !self.show_one) || self.finished()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Recording time of the sample on screen.
+    pub fn position_ms(&self) -> u64 {
+        self.next
+            .checked_sub(1)
+            .and_then(|i| self.metrics.get(i))
+            .map_or(0, |&i| self.rec.samples[i].at_ms)
+    }
+
+    /// Wall-clock time (Unix seconds) the sample on screen was taken.
+    pub fn position_unix(&self) -> u64 {
+        self.rec.started_unix + self.position_ms() / 1000
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.rec.samples.last().map_or(0, |s| s.at_ms)
+    }
+
+    /// Jump `SEEK_STEP_MS` back or forward; the next sample served is the first at or after
+    /// the new position (at most the last one).
+    pub fn seek(&mut self, forward: bool) {
+        let at = |i: usize| self.rec.samples[i].at_ms;
+        // Seeks in a row count from where the previous one landed
+        let pos = match self.metrics.get(self.next) {
+            Some(&i) if self.show_one => at(i),
+            _ => self.position_ms(),
+        };
+        let target = if forward {
+            pos + SEEK_STEP_MS
+        } else {
+            pos.saturating_sub(SEEK_STEP_MS)
+        };
+        self.next = self
+            .metrics
+            .partition_point(|&i| at(i) < target)
+            .min(self.metrics.len().saturating_sub(1));
+        self.show_one = true;
+    }
+
+    /// Header text: state, position and length.
+    pub fn status(&self) -> String {
+        let state = if self.finished() {
+            "⏹"
+        } else if self.paused {
+            "⏸"
+        } else {
+            "▶"
+        };
+        format!(
+            "{state} replay {} / {}",
+            mmss(self.position_ms()),
+            mmss(self.duration_ms())
+        )
+    }
+
+    /// The recorded reply for `cmd`, as JSON text; None for what the recording doesn't hold.
+    pub fn answer(&mut self, cmd: &str) -> Option<String> {
+        let name = cmd.split('?').next().unwrap_or(cmd);
+        let kind = match name {
+            "get_metrics" => {
+                let &i = self.metrics.get(self.next)?;
+                self.next += 1;
+                self.show_one = false;
+                // Without capabilities the client sticks to the requests answered here (plain
+                // get_disks rather than deltas, no hello, history or subscribe)
+                let mut data = self.rec.samples[i].data.clone();
+                if let Some(obj) = data.as_object_mut() {
+                    obj.remove("capabilities");
+                }
+                return Some(data.to_string());
+            }
+            "get_processes" => Kind::Processes,
+            "get_disks" => Kind::Disks,
+            _ => return None,
+        };
+        // Latest of that kind recorded before the next metrics sample (the client fetches
+        // processes and disks after the metrics they go with)
+        let upto = self
+            .metrics
+            .get(self.next)
+            .copied()
+            .unwrap_or(self.rec.samples.len());
+        self.rec.samples[..upto]
+            .iter()
+            .rev()
+            .find(|s| s.kind == kind)
+            .map(|s| s.data.to_string())
+    }
+}
+
+/// Stands in for the agent connection during `--replay`.
+pub struct ReplayLink {
+    playback: ReplayHandle,
+}
+
+impl ReplayLink {
+    pub fn new(playback: ReplayHandle) -> Self {
+        ReplayLink { playback }
+    }
+}
+
+impl AgentLink for ReplayLink {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("a replay has nothing to reconnect to".into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        let reply = self.playback.lock().ok()?.answer(cmd)?;
+        Some(Message::Text(reply))
+    }
+}
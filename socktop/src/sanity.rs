@@ -0,0 +1,120 @@
+//! Sanity checks on agent payloads before they reach the charts. Impossible values (CPU over
+//! 100% per core, NaN/negative usage, used > total memory, swap or disk space) are clamped to
+//! something drawable, and the fields they came from are reported so the header can show a
+//! "suspect data" badge instead of silently rendering garbage.
+
+use std::collections::BTreeSet;
+
+use crate::types::{DiskInfo, Metrics, ProcessInfo};
+
+/// Names of the fields clamped in the latest payloads, per payload kind.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Suspect {
+    pub metrics: Vec<&'static str>,
+    pub processes: Vec<&'static str>,
+    pub disks: Vec<&'static str>,
+}
+
+impl Suspect {
+    /// Header badge, e.g. "⚠ suspect data: cpu_total, mem (clamped)"; None when all is well.
+    pub fn badge(&self) -> Option<String> {
+        let fields: BTreeSet<&str> = self
+            .metrics
+            .iter()
+            .chain(&self.processes)
+            .chain(&self.disks)
+            .copied()
+            .collect();
+        if fields.is_empty() {
+            return None;
+        }
+        let list: Vec<&str> = fields.into_iter().collect();
+        Some(format!("⚠ suspect data: {} (clamped)", list.join(", ")))
+    }
+}
+
+// Clamp a percentage into [0, max]; NaN becomes 0. True when it had to change.
+fn clamp_pct(v: &mut f32, max: f32) -> bool {
+    let fixed = if v.is_nan() { 0.0 } else { v.clamp(0.0, max) };
+    let changed = fixed != *v;
+    *v = fixed;
+    changed
+}
+
+fn clamp_used(used: &mut u64, total: u64) -> bool {
+    let over = *used > total;
+    if over {
+        *used = total;
+    }
+    over
+}
+
+/// Check a metrics sample in place; returns the clamped fields.
+pub fn check_metrics(m: &mut Metrics) -> Vec<&'static str> {
+    let mut bad = Vec::new();
+    if clamp_pct(&mut m.cpu_total, 100.0) {
+        bad.push("cpu_total");
+    }
+    let mut per_core = false;
+    for c in &mut m.cpu_per_core {
+        per_core |= clamp_pct(c, 100.0);
+    }
+    if per_core {
+        bad.push("cpu_per_core");
+    }
+    if clamp_used(&mut m.mem_used, m.mem_total) {
+        bad.push("mem");
+    }
+    if clamp_used(&mut m.swap_used, m.swap_total) {
+        bad.push("swap");
+    }
+    if m.cpu_temp_c.is_some_and(|t| !t.is_finite()) {
+        m.cpu_temp_c = None;
+        bad.push("cpu_temp");
+    }
+    if let Some(gpus) = m.gpus.as_mut() {
+        let mut gpu = false;
+        for g in gpus.iter_mut() {
+            if let Some(u) = g.utilization.as_mut() {
+                gpu |= clamp_pct(u, 100.0);
+            }
+            if let (Some(used), Some(total)) = (g.mem_used.as_mut(), g.mem_total) {
+                gpu |= clamp_used(used, total);
+            }
+        }
+        if gpu {
+            bad.push("gpu");
+        }
+    }
+    // Older agents still send disks and processes inline
+    bad.extend(check_disks(&mut m.disks));
+    bad.extend(check_processes(&mut m.top_processes, m.cpu_per_core.len()));
+    bad
+}
+
+/// Per-process CPU may exceed 100% on multi-core hosts, but never 100% per core.
+pub fn check_processes(rows: &mut [ProcessInfo], cores: usize) -> Vec<&'static str> {
+    let max = 100.0 * cores.max(1) as f32;
+    let mut bad = false;
+    for p in rows.iter_mut() {
+        bad |= clamp_pct(&mut p.cpu_usage, max);
+    }
+    if bad {
+        vec!["process_cpu"]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Available space can't exceed the disk's size.
+pub fn check_disks(disks: &mut [DiskInfo]) -> Vec<&'static str> {
+    let mut bad = false;
+    for d in disks.iter_mut() {
+        bad |= clamp_used(&mut d.available, d.total);
+    }
+    if bad {
+        vec!["disks"]
+    } else {
+        Vec::new()
+    }
+}
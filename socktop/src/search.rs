@@ -0,0 +1,150 @@
+//! `/` global search: one query matched against process names and PIDs, disk mounts, interface
+//! names and sensor labels. While it is typed, and until it is cleared, the process table only
+//! shows matching rows. Enter jumps to the chosen match (selects the process, brings the disk card
+//! into view) and keeps the query as a highlight in the other panels.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::types::{Metrics, ProcessInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitKind {
+    Process,
+    Disk,
+    Interface,
+    Sensor,
+}
+
+impl HitKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            HitKind::Process => "process",
+            HitKind::Disk => "disk",
+            HitKind::Interface => "net",
+            HitKind::Sensor => "sensor",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub kind: HitKind,
+    pub name: String,
+    // Process rows only
+    pub pid: Option<u32>,
+    // Short current value shown next to the match
+    pub detail: String,
+}
+
+/// Case-insensitive substring match; an empty query matches nothing.
+pub fn matches(name: &str, query: &str) -> bool {
+    let q = query.trim();
+    !q.is_empty() && name.to_lowercase().contains(&q.to_lowercase())
+}
+
+/// Process rows match on the name or a PID substring.
+pub fn process_matches(p: &ProcessInfo, query: &str) -> bool {
+    let q = query.trim();
+    matches(&p.name, q) || (!q.is_empty() && p.pid.to_string().contains(q))
+}
+
+/// Every match in `m`, panel by panel (processes, disks, interfaces, sensors).
+pub fn find(m: &Metrics, query: &str) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = m
+        .top_processes
+        .iter()
+        .filter(|p| process_matches(p, query))
+        .map(|p| Hit {
+            kind: HitKind::Process,
+            name: p.name.clone(),
+            pid: Some(p.pid),
+            detail: format!("pid {}  {:.1}% cpu", p.pid, p.cpu_usage),
+        })
+        .collect();
+    hits.extend(m.disks.iter().filter(|d| matches(&d.name, query)).map(|d| {
+        let used = d.total.saturating_sub(d.available);
+        let pct = if d.total > 0 {
+            used as f64 / d.total as f64 * 100.0
+        } else {
+            0.0
+        };
+        Hit {
+            kind: HitKind::Disk,
+            name: d.name.clone(),
+            pid: None,
+            detail: format!("{pct:.0}% used"),
+        }
+    }));
+    hits.extend(
+        m.networks
+            .iter()
+            .filter(|n| matches(&n.name, query))
+            .map(|n| Hit {
+                kind: HitKind::Interface,
+                name: n.name.clone(),
+                pid: None,
+                detail: format!("rx {}  tx {}", n.received, n.transmitted),
+            }),
+    );
+    // Temperature sensors: the CPU package and each GPU
+    let mut sensors: Vec<(String, Option<f32>)> = Vec::new();
+    if let Some(t) = m.cpu_temp_c {
+        sensors.push(("CPU temperature".into(), Some(t)));
+    }
+    for (i, g) in m.gpus.iter().flatten().enumerate() {
+        let name = g.name.clone().unwrap_or_else(|| format!("GPU {i}"));
+        sensors.push((name, g.temperature));
+    }
+    hits.extend(
+        sensors
+            .into_iter()
+            .filter(|(name, _)| matches(name, query))
+            .map(|(name, t)| Hit {
+                kind: HitKind::Sensor,
+                name,
+                pid: None,
+                detail: t.map(|t| format!("{t:.0}°C")).unwrap_or_default(),
+            }),
+    );
+    hits
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub query: String,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutcome {
+    Open,
+    // Esc: drop the query and its highlight
+    Close,
+    // Enter: jump to match `selected` (None when nothing matched) and keep the highlight
+    Jump(Option<usize>),
+}
+
+impl Search {
+    /// `hits` is the number of matches for the query as it stands.
+    pub fn handle_key(&mut self, key: KeyEvent, hits: usize) -> SearchOutcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return SearchOutcome::Close,
+            KeyCode::Enter => {
+                return SearchOutcome::Jump((self.selected < hits).then_some(self.selected));
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(hits.saturating_sub(1)),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        SearchOutcome::Open
+    }
+}
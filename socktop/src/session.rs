@@ -0,0 +1,88 @@
+//! Host identity tracking across reconnects so histories from different hosts/boots are not spliced.
+
+use std::time::Instant;
+
+use crate::types::Metrics;
+
+// Allowance for rounding and clock jitter when comparing uptimes
+const UPTIME_SLACK_SECS: u64 = 5;
+
+#[derive(Debug, Clone)]
+pub struct HostIdentity {
+    pub hostname: String,
+    pub instance: Option<String>,
+    pub machine_id: Option<String>,
+    pub boot_id: Option<String>,
+    pub uptime_secs: Option<u64>,
+    pub seen_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityChange {
+    /// Same host, same boot (or not enough data to tell otherwise)
+    Same,
+    /// Same host but boot id changed (or uptime went backwards)
+    Rebooted,
+    /// Machine id, hostname or agent instance differs from the previous session
+    DifferentHost,
+}
+
+impl HostIdentity {
+    pub fn from_metrics(m: &Metrics) -> Self {
+        Self {
+            hostname: m.hostname.clone(),
+            instance: m.instance.clone(),
+            machine_id: m.machine_id.clone(),
+            boot_id: m.boot_id.clone(),
+            uptime_secs: m.uptime_secs,
+            seen_at: Instant::now(),
+        }
+    }
+
+    /// Compare a freshly observed identity against this (previous) one.
+    /// Agent-provided ids are authoritative; hostname/uptime are the fallback for older agents.
+    pub fn compare(&self, next: &HostIdentity) -> IdentityChange {
+        // Another agent instance on the same machine is a different data source
+        if self.instance != next.instance {
+            return IdentityChange::DifferentHost;
+        }
+        match (&self.machine_id, &next.machine_id) {
+            (Some(a), Some(b)) if a != b => return IdentityChange::DifferentHost,
+            (Some(_), Some(_)) => {}
+            _ if self.hostname != next.hostname => return IdentityChange::DifferentHost,
+            _ => {}
+        }
+        if let (Some(a), Some(b)) = (&self.boot_id, &next.boot_id) {
+            return if a != b {
+                IdentityChange::Rebooted
+            } else {
+                IdentityChange::Same
+            };
+        }
+        if let (Some(prev), Some(now)) = (self.uptime_secs, next.uptime_secs) {
+            // Uptime should have grown by the time between the two samples; less means the
+            // host restarted meanwhile, even when it has been up for longer than before
+            let elapsed = next
+                .seen_at
+                .saturating_duration_since(self.seen_at)
+                .as_secs();
+            if now.saturating_add(UPTIME_SLACK_SECS) < prev.saturating_add(elapsed) {
+                return IdentityChange::Rebooted;
+            }
+        }
+        IdentityChange::Same
+    }
+}
+
+impl IdentityChange {
+    /// Header notice for a detected change (None when nothing changed).
+    pub fn notice(self, hostname: &str) -> Option<String> {
+        match self {
+            IdentityChange::Same => None,
+            IdentityChange::Rebooted => Some(format!("⚠ host rebooted ({hostname})")),
+            IdentityChange::DifferentHost => {
+                Some(format!("⚠ different host behind this URL ({hostname})"))
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! SSH tunnels for profiles with `ssh` (and optionally an `ssh_jump` bastion chain): runs the
+//! system `ssh` with a local port forward and points the WebSocket URL at it.
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+// How long to wait for ssh (through every jump host) to open the forwarded port
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `ssh` arguments forwarding `127.0.0.1:local_port` to `remote` (host:port as seen from `dest`),
+/// hopping through `jumps` in order.
+pub fn ssh_args(dest: &str, jumps: &[String], local_port: u16, remote: &str) -> Vec<String> {
+    let mut args = vec![
+        "-N".to_string(),
+        "-o".into(),
+        "ExitOnForwardFailure=yes".into(),
+        "-L".into(),
+        format!("127.0.0.1:{local_port}:{remote}"),
+    ];
+    if !jumps.is_empty() {
+        args.push("-J".into());
+        args.push(jumps.join(","));
+    }
+    args.push(dest.to_string());
+    args
+}
+
+/// Split a profile URL into the agent address as seen from the SSH host and the same URL
+/// rewritten to go through the local end of the tunnel.
+pub fn tunneled_url(url: &str, local_port: u16) -> Result<(String, String), String> {
+    // The agent's port as seen from the SSH host must be explicit, not guessed by probing
+    if !url.contains("://") {
+        return Err(format!(
+            "profiles with ssh need a full ws:// or wss:// URL, got {url}"
+        ));
+    }
+    let mut u = Url::parse(url).map_err(|e| format!("invalid URL {url}: {e}"))?;
+    let host = u
+        .host_str()
+        .ok_or_else(|| format!("URL {url} has no host"))?
+        .to_string();
+    let port = u
+        .port_or_known_default()
+        .ok_or_else(|| format!("URL {url} has no port"))?;
+    let remote = format!("{host}:{port}");
+    u.set_host(Some("127.0.0.1"))
+        .map_err(|e| format!("cannot rewrite {url}: {e}"))?;
+    u.set_port(Some(local_port))
+        .map_err(|_| format!("cannot set a port on {url}"))?;
+    Ok((remote, u.to_string()))
+}
+
+/// A running `ssh` port forward; the process is stopped when this is dropped.
+pub struct SshTunnel {
+    child: Child,
+}
+
+impl SshTunnel {
+    /// Start the tunnel and wait until its local port accepts connections. Returns the URL
+    /// to connect to instead of `url`.
+    pub fn open(dest: &str, jumps: &[String], url: &str) -> Result<(Self, String), String> {
+        let local_port = free_port().map_err(|e| format!("no free local port: {e}"))?;
+        let (remote, local_url) = tunneled_url(url, local_port)?;
+        let child = Command::new("ssh")
+            .args(ssh_args(dest, jumps, local_port, &remote))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("failed to run ssh: {e}"))?;
+        let mut tunnel = SshTunnel { child };
+        let deadline = Instant::now() + TUNNEL_TIMEOUT;
+        loop {
+            if TcpStream::connect(("127.0.0.1", local_port)).is_ok() {
+                return Ok((tunnel, local_url));
+            }
+            if let Ok(Some(status)) = tunnel.child.try_wait() {
+                return Err(format!("ssh to {dest} exited ({status})"));
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "ssh tunnel to {dest} not ready after {TUNNEL_TIMEOUT:?}"
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn free_port() -> io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
@@ -1,22 +1,48 @@
 //! Types that mirror the agent's JSON schema.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
     pub cpu_usage: f32,
     pub mem_bytes: u64,
+    /// CPU time used since the process started (user + system), ms; 0 from older agents
+    #[serde(default)]
+    pub cpu_time_ms: u64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl ProcessInfo {
+    /// Table order: CPU (or memory) descending, then the other key descending, then PID
+    /// ascending. A total order, so rows with equal usage don't swap places between refreshes.
+    pub fn table_cmp(&self, other: &Self, by_mem: bool) -> Ordering {
+        let cpu = other.cpu_usage.total_cmp(&self.cpu_usage);
+        let mem = other.mem_bytes.cmp(&self.mem_bytes);
+        let primary = if by_mem { mem.then(cpu) } else { cpu.then(mem) };
+        primary.then(self.pid.cmp(&other.pid))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiskInfo {
     pub name: String,
     pub total: u64,
     pub available: u64,
 }
 
+/// Reply to `get_disks?delta=SEQ` (agents advertising `disks_delta`); see disk_mirror.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DisksDelta {
+    pub seq: u64,
+    pub full: bool,
+    pub disks: Vec<DiskInfo>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct NetworkInfo {
     #[allow(dead_code)]
@@ -46,33 +72,418 @@ pub struct GpuInfo {
     #[serde(default, alias = "mem_total_bytes", alias = "vram_total_bytes")]
     pub mem_total: Option<u64>,
 
-    #[allow(dead_code)]
     #[serde(default, alias = "temp_c", alias = "temperature_c")]
     pub temperature: Option<f32>,
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct CoreIrq {
+    pub irq: f32,
+    pub softirq: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FdProcess {
+    pub pid: u32,
+    pub name: String,
+    pub fds: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FdUsage {
+    pub allocated: u64,
+    pub max: u64,
+    #[serde(default)]
+    pub top: Vec<FdProcess>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EntropyInfo {
+    pub avail_bits: u32,
+    pub pool_bits: u32,
+    #[serde(default)]
+    pub hwrng: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ClockSync {
+    pub synchronized: bool,
+    pub offset_ms: f64,
+    #[serde(default)]
+    pub max_error_ms: f64,
+}
+
+impl ClockSync {
+    /// Header warning while unsynchronized: the kernel's offset estimate and, from agents that
+    /// report it, the maximum error.
+    pub fn warning(&self) -> Option<String> {
+        if self.synchronized {
+            return None;
+        }
+        Some(if self.max_error_ms > 0.0 {
+            format!(
+                "🕒✗ clock unsynced (offset {:+.1}ms, max error {:.0}ms)",
+                self.offset_ms, self.max_error_ms
+            )
+        } else {
+            format!("🕒✗ clock unsynced (offset {:+.1}ms)", self.offset_ms)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PendingUpdates {
+    pub manager: String,
+    pub total: u32,
+    #[serde(default)]
+    pub security: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RebootRequired {
+    pub reason: String,
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+/// Newest agent payload schema this client was written against. Unknown fields are ignored,
+/// so a newer agent still works; the header just suggests an update.
+pub const SCHEMA_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Metrics {
+    // 0 for agents from before schema versioning
+    #[serde(default)]
+    pub schema_version: u32,
     pub cpu_total: f32,
     pub cpu_per_core: Vec<f32>,
+    // Present-but-offline CPU ids; cpu_per_core holds online cores only (client re-expands)
+    #[serde(default)]
+    pub offline_cores: Vec<u32>,
+    // Per-core irq/softirq % (Linux agents), aligned with cpu_per_core
+    #[serde(default)]
+    pub cpu_irq: Option<Vec<CoreIrq>>,
+    // Run queue (Linux agents): runnable vs blocked-on-I/O tasks
+    #[serde(default)]
+    pub procs_running: Option<u32>,
+    #[serde(default)]
+    pub procs_blocked: Option<u32>,
+    // File handles in use vs fs.file-max + top fd holders (Linux agents)
+    #[serde(default)]
+    pub fd: Option<FdUsage>,
+    // Kernel entropy / hardware RNG (Linux agents)
+    #[serde(default)]
+    pub entropy: Option<EntropyInfo>,
+    // Clock sync status (Linux agents)
+    #[serde(default)]
+    pub clock: Option<ClockSync>,
+    // Pending package updates (agents started with --updates)
+    #[serde(default)]
+    pub updates: Option<PendingUpdates>,
+    // Set when the agent host wants a reboot (Linux agents)
+    #[serde(default)]
+    pub reboot_required: Option<RebootRequired>,
     pub mem_total: u64,
     pub mem_used: u64,
     pub swap_total: u64,
     pub swap_used: u64,
     pub hostname: String,
+    // Used to tell a rebooted host apart from a dropped connection
+    #[serde(default)]
+    pub uptime_secs: Option<u64>,
+    // 1, 5 and 15 minute load averages (older and Windows agents omit them)
+    #[serde(default)]
+    pub load_avg: Option<(f64, f64, f64)>,
+    // Stable host id and per-boot id (older agents omit these)
+    #[serde(default)]
+    pub machine_id: Option<String>,
+    #[serde(default)]
+    pub boot_id: Option<String>,
     pub cpu_temp_c: Option<f32>,
+    // Governor shared by all cores, "mixed" when they differ (agents with `cpufreq`)
+    #[serde(default)]
+    pub cpu_governor: Option<String>,
     pub disks: Vec<DiskInfo>,
     pub networks: Vec<NetworkInfo>,
+    // When the agent read `networks`, ms on its monotonic clock (older agents omit it)
+    #[serde(default)]
+    pub sample_ms: Option<u64>,
     pub top_processes: Vec<ProcessInfo>,
     pub gpus: Option<Vec<GpuInfo>>,
     // New: keep the last reported total process count
     #[serde(default)]
     pub process_count: Option<usize>,
+    // Agent sampler cadence (older agents omit it)
+    #[serde(default)]
+    pub meta: Option<SamplerMeta>,
+    // Agent instance when several agents share a host (`--instance NAME`)
+    #[serde(default)]
+    pub instance: Option<String>,
+    // Agent-assigned host labels (`--label env=prod`)
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    // Optional protocol features advertised by the agent (e.g. "processes_top")
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    // Last error per collector (e.g. "gpu"), shown in the affected panel
+    #[serde(default)]
+    pub collector_errors: BTreeMap<String, String>,
+}
+
+/// Header label and capability name of each collector an agent can report as enabled.
+pub const COLLECTORS: [(&str, &str); 4] = [
+    ("GPU", "gpu"),
+    ("TEMP", "temp"),
+    ("UPD", "updates"),
+    ("HIST", "history"),
+];
+
+impl Metrics {
+    /// Header hint when the agent speaks a newer schema than this client knows.
+    pub fn schema_notice(&self) -> Option<String> {
+        (self.schema_version > SCHEMA_VERSION).then(|| {
+            format!(
+                "ℹ agent schema v{} > client v{SCHEMA_VERSION}: update socktop for new fields",
+                self.schema_version
+            )
+        })
+    }
+
+    pub fn has_capability(&self, name: &str) -> bool {
+        self.capabilities.iter().any(|c| c == name)
+    }
+
+    /// (label, enabled) per collector; None for agents that predate collector flags.
+    pub fn collector_status(&self) -> Option<Vec<(&'static str, bool)>> {
+        self.has_capability("collectors").then(|| {
+            COLLECTORS
+                .iter()
+                .map(|&(label, cap)| (label, self.has_capability(cap)))
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct CollectorTiming {
+    #[serde(default)]
+    pub last_ms: f32,
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct SamplerMeta {
+    #[serde(default)]
+    pub metrics: CollectorTiming,
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub disks: CollectorTiming,
+    #[serde(default)]
+    pub processes: CollectorTiming,
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessesPayload {
     pub process_count: usize,
     pub top_processes: Vec<ProcessInfo>,
+    /// Rotating long-tail sample that came with the top rows (`get_processes?tail=M`)
+    #[serde(default)]
+    pub tail: Vec<ProcessInfo>,
+}
+
+/// One downsampled point of the agent's `get_history` ring (only the fields the charts use).
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryPoint {
+    /// Unix seconds
+    pub ts: u64,
+    pub cpu_total: f32,
+    #[serde(default)]
+    pub cpu_per_core: Vec<f32>,
+    pub rx_bps: u64,
+    pub tx_bps: u64,
+}
+
+/// What the connection's token allows (agent `hello`). Anything unrecognised is treated as
+/// `Read` so a newer agent can't accidentally unlock actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Control,
+    #[serde(other)]
+    Read,
+}
+
+/// Reply to `subscribe?...` (agents advertising `subscribe`): the push intervals in effect.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscribeAck {
+    #[serde(default)]
+    pub metrics_ms: Option<u64>,
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub processes_ms: Option<u64>,
+    // Relayed connections can't be subscribed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Reply to `hello` (agents advertising the `hello` capability).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hello {
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub version: String,
+    pub scope: Scope,
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Session id to send back as `hello?resume=ID` after a reconnect
+    #[serde(default)]
+    pub session: Option<String>,
+    /// The agent reattached the session named in `resume`
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub resumed: bool,
+}
+
+/// One entry of `get_peers` (agents listing `peers`): an agent the connected one knows by gossip.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Peer {
+    /// Empty for an agent running without `--advertise-url`
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// None when the agent only heard of it through another peer
+    #[serde(default)]
+    pub seen_secs_ago: Option<u64>,
+    /// The agent that answered
+    #[serde(default, rename = "self")]
+    pub is_self: bool,
+}
+
+/// Reply to `get_cpufreq` and `set_governor?name=G` (agents listing `cpufreq`). `error` says
+/// why a `set_governor` changed nothing.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CpuFreq {
+    #[serde(default)]
+    pub cores: Vec<CoreFreq>,
+    #[serde(default)]
+    pub available: Vec<String>,
+    /// The agent allows `set_governor` (`--cpufreq-control`)
+    #[serde(default)]
+    pub control: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoreFreq {
+    pub cpu: u32,
+    pub governor: String,
+    #[serde(default)]
+    pub cur_khz: Option<u64>,
+}
+
+/// Reply to `get_process_env?pid=N` (agents listing `process_inspect`; control scope only).
+/// `error` explains an empty `vars` (wrong scope, process gone, another user's).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProcessEnv {
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub pid: u32,
+    #[serde(default)]
+    pub vars: Vec<EnvVar>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// `value` is None for names the agent doesn't allow-list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// Reply to `get_process_fds?pid=N`; `truncated` when the agent cut the list short.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProcessFds {
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub pid: u32,
+    #[serde(default)]
+    pub fds: Vec<OpenFd>,
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenFd {
+    pub fd: u32,
+    pub target: String,
+}
+
+/// Reply to `get_io` (agents listing `io`): what the system is waiting on. Rates cover the
+/// last `interval_ms`, which is None on the agent's first sample.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IoReport {
+    #[serde(default)]
+    pub interval_ms: Option<u64>,
+    #[serde(default)]
+    pub pressure: Option<IoPressure>,
+    #[serde(default)]
+    pub procs_blocked: Option<u32>,
+    #[serde(default)]
+    pub iowait_pct: Option<f32>,
+    #[serde(default)]
+    pub disks: Vec<DiskIo>,
+    #[serde(default)]
+    pub top: Vec<IoProcess>,
+}
+
+/// PSI io: % of time some / all tasks were stalled on I/O, 10s and 60s averages.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct IoPressure {
+    pub some_avg10: f32,
+    pub some_avg60: f32,
+    #[serde(default)]
+    pub full_avg10: f32,
+    #[serde(default)]
+    pub full_avg60: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiskIo {
+    pub name: String,
+    pub util_pct: f32,
+    #[serde(default)]
+    pub read_bps: u64,
+    #[serde(default)]
+    pub write_bps: u64,
+}
+
+/// `blocked`: in uninterruptible sleep (D state) when sampled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IoProcess {
+    pub pid: u32,
+    pub name: String,
+    #[serde(default)]
+    pub read_bps: u64,
+    #[serde(default)]
+    pub write_bps: u64,
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentHistory {
+    pub step_secs: u64,
+    pub points: Vec<HistoryPoint>,
 }
@@ -0,0 +1,78 @@
+//! Alert log panel: fired/cleared transitions, newest first.
+
+use crate::alerts::{format_utc, AlertLog, Severity, Transition};
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+};
+
+pub fn draw_alert_log(f: &mut ratatui::Frame<'_>, area: Rect, log: &AlertLog) {
+    let w = area.width.min(100);
+    let h = area.height.min(20);
+    if w < 30 || h < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    };
+    let visible = h.saturating_sub(2) as usize;
+    let mut lines: Vec<Line> = log
+        .events()
+        .rev()
+        .take(visible)
+        .map(|e| {
+            let (tag, color) = match e.transition {
+                Transition::Fired => ("FIRED  ", theme().crit),
+                Transition::Cleared => ("CLEARED", theme().ok),
+            };
+            Line::from(vec![
+                Span::raw(format!("{} ", format_utc(e.at))),
+                Span::styled(tag, Style::default().fg(color)),
+                Span::raw(format!(" {} — {}", e.host, e.message)),
+                Span::styled(
+                    if e.silenced { " (silenced)" } else { "" },
+                    Style::default().fg(theme().dim),
+                ),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from("no alerts recorded"));
+    }
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Alert log (a to close)")
+                .border_style(Style::default().fg(theme().warn)),
+        ),
+        rect,
+    );
+}
+
+/// `flash` sink: a thick border in the alert's color over `area` (its panel); too short for a
+/// border (the one-line header), the line is colored instead.
+pub fn draw_flash(f: &mut ratatui::Frame<'_>, area: Rect, severity: Severity) {
+    let color = match severity {
+        Severity::Critical => theme().crit,
+        Severity::Warning => theme().warn,
+    };
+    if area.height < 3 {
+        f.buffer_mut().set_style(area, theme().badge(color));
+        return;
+    }
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(color)),
+        area,
+    );
+}
@@ -0,0 +1,75 @@
+//! Panel render cache: a panel whose inputs hash to the same key as last frame (and whose area
+//! didn't move) is copied from the previous frame's cells instead of being drawn again.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Write};
+use std::hash::{Hash, Hasher};
+
+use ratatui::{buffer::Buffer, layout::Rect};
+
+struct Entry {
+    key: u64,
+    buf: Buffer,
+}
+
+#[derive(Default)]
+pub struct PanelCache {
+    entries: HashMap<&'static str, Entry>,
+}
+
+impl PanelCache {
+    /// Draw panel `name` into `area` with `draw`, unless the same key was drawn into the same
+    /// area last time, in which case the cached cells are reused.
+    pub fn draw(
+        &mut self,
+        f: &mut ratatui::Frame<'_>,
+        name: &'static str,
+        area: Rect,
+        key: u64,
+        draw: impl FnOnce(&mut ratatui::Frame<'_>),
+    ) {
+        if let Some(e) = self.entries.get(name) {
+            if e.key == key && e.buf.area == area {
+                copy_cells(&e.buf, f.buffer_mut(), area);
+                return;
+            }
+        }
+        draw(f);
+        let mut buf = Buffer::empty(area);
+        copy_cells(f.buffer_mut(), &mut buf, area);
+        self.entries.insert(name, Entry { key, buf });
+    }
+}
+
+fn copy_cells(from: &Buffer, to: &mut Buffer, area: Rect) {
+    let area = area.intersection(from.area).intersection(to.area);
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            if let (Some(src), Some(dst)) = (from.cell((x, y)), to.cell_mut((x, y))) {
+                *dst = src.clone();
+            }
+        }
+    }
+}
+
+pub fn key_of(v: impl Hash) -> u64 {
+    let mut h = DefaultHasher::new();
+    v.hash(&mut h);
+    h.finish()
+}
+
+/// Key from a value's Debug output, for inputs holding floats (which aren't `Hash`).
+/// Streams into the hasher without building the string.
+pub fn debug_key(v: &impl Debug) -> u64 {
+    struct HashWriter(DefaultHasher);
+    impl Write for HashWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+    let mut w = HashWriter(DefaultHasher::new());
+    let _ = write!(w, "{v:?}");
+    w.0.finish()
+}
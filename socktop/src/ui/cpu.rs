@@ -1,9 +1,9 @@
 //! CPU average sparkline + per-core mini bars.
 
-use crate::ui::theme::{SB_ARROW, SB_THUMB, SB_TRACK};
+use crate::ui::theme::{theme, thresholds, Thresholds};
 use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::style::Modifier;
-use ratatui::style::{Color, Style};
+use ratatui::style::Style;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     text::{Line, Span},
@@ -11,7 +11,7 @@ use ratatui::{
 };
 
 use crate::history::PerCoreHistory;
-use crate::types::Metrics;
+use crate::types::{CoreIrq, Metrics};
 
 /// State for dragging the scrollbar thumb
 #[derive(Clone, Copy, Debug, Default)]
@@ -133,11 +133,9 @@ pub fn per_core_handle_scrollbar_mouse(
     }
     let thumb_len = (track * view).div_ceil(total).max(1).min(track);
     let top_for_offset = |off: usize| -> usize {
-        if max_off == 0 {
-            0
-        } else {
-            ((track - thumb_len) * off + max_off / 2) / max_off
-        }
+        ((track - thumb_len) * off + max_off / 2)
+            .checked_div(max_off)
+            .unwrap_or(0)
     };
     let thumb_top = top_for_offset(offset);
 
@@ -233,6 +231,20 @@ pub fn per_core_clamp(scroll_offset: &mut usize, total_rows: usize, viewport_row
     }
 }
 
+/// Title of the CPU average panel (shared with the raster chart).
+pub fn cpu_avg_title(m: Option<&Metrics>) -> String {
+    if let Some(mm) = m {
+        let mut t = format!("CPU avg (now: {:>5.1}%)", mm.cpu_total);
+        // Many runnable tasks = CPU saturation; many blocked = waiting on I/O
+        if let (Some(r), Some(b)) = (mm.procs_running, mm.procs_blocked) {
+            t.push_str(&format!(" | run {r} · blocked {b}"));
+        }
+        t
+    } else {
+        "CPU avg".into()
+    }
+}
+
 /// Draws the CPU average sparkline graph.
 pub fn draw_cpu_avg_graph(
     f: &mut ratatui::Frame<'_>,
@@ -240,11 +252,7 @@ pub fn draw_cpu_avg_graph(
     hist: &std::collections::VecDeque<u64>,
     m: Option<&Metrics>,
 ) {
-    let title = if let Some(mm) = m {
-        format!("CPU avg (now: {:>5.1}%)", mm.cpu_total)
-    } else {
-        "CPU avg".into()
-    };
+    let title = cpu_avg_title(m);
     let max_points = area.width.saturating_sub(2) as usize;
     let start = hist.len().saturating_sub(max_points);
     let data: Vec<u64> = hist.iter().skip(start).cloned().collect();
@@ -252,10 +260,28 @@ pub fn draw_cpu_avg_graph(
         .block(Block::default().borders(Borders::ALL).title(title))
         .data(&data)
         .max(100)
-        .style(Style::default().fg(Color::Cyan));
+        .style(Style::default().fg(theme().cpu));
     f.render_widget(spark, area);
 }
 
+// Width of the per-core irq/softirq mini bar (cells); each cell is 5% of the core's time
+const IRQ_BAR_W: u16 = 5;
+
+// " ███░░": hard irq (magenta) then softirq (cyan), remainder dim
+fn irq_bar(c: &CoreIrq) -> Line<'static> {
+    let cells = |pct: f32| ((pct / 5.0).ceil().max(0.0) as usize).min(IRQ_BAR_W as usize);
+    let hard = cells(c.irq);
+    let soft = cells(c.irq + c.softirq).saturating_sub(hard);
+    let rest = IRQ_BAR_W as usize - hard - soft;
+    let t = theme();
+    Line::from(vec![
+        Span::raw(" "),
+        Span::styled("█".repeat(hard), Style::default().fg(t.mem)),
+        Span::styled("█".repeat(soft), Style::default().fg(t.cpu)),
+        Span::styled("░".repeat(rest), Style::default().fg(t.dim)),
+    ])
+}
+
 /// Draws the per-core CPU bars with sparklines and trends.
 pub fn draw_per_core_bars(
     f: &mut ratatui::Frame<'_>,
@@ -263,11 +289,20 @@ pub fn draw_per_core_bars(
     m: Option<&Metrics>,
     per_core_hist: &PerCoreHistory,
     scroll_offset: usize,
+    show_irq: bool,
+    point_div: usize,
 ) {
-    f.render_widget(
-        Block::default().borders(Borders::ALL).title("Per-core"),
-        area,
-    );
+    let irq = m.and_then(|mm| mm.cpu_irq.as_ref()).filter(|_| show_irq);
+    let mut title = match (show_irq, irq.is_some()) {
+        (true, true) => "Per-core (irq/softirq)",
+        (true, false) => "Per-core (irq n/a)",
+        _ => "Per-core",
+    }
+    .to_string();
+    if let Some(g) = m.and_then(|mm| mm.cpu_governor.as_deref()) {
+        title = format!("{title} · {g}");
+    }
+    f.render_widget(Block::default().borders(Borders::ALL).title(title), area);
     let Some(mm) = m else {
         return;
     };
@@ -304,11 +339,36 @@ pub fn draw_per_core_bars(
     for i in 0..show_n {
         let idx = offset + i;
         let rect = vchunks[i];
+        let irq_w = if irq.is_some() { IRQ_BAR_W + 1 } else { 0 };
         let hchunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(6), Constraint::Length(12)])
+            .constraints([
+                Constraint::Min(6),
+                Constraint::Length(irq_w),
+                Constraint::Length(12),
+            ])
             .split(rect);
 
+        if mm.offline_cores.contains(&(idx as u32)) {
+            let dim = Style::default().fg(theme().dim);
+            f.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    "╌".repeat(hchunks[0].width as usize),
+                    dim,
+                ))),
+                hchunks[0],
+            );
+            f.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    format!("cpu{idx:<2} offline"),
+                    dim,
+                )))
+                .right_aligned(),
+                hchunks[2],
+            );
+            continue;
+        }
+
         let curr = mm.cpu_per_core[idx].clamp(0.0, 100.0);
         let older = per_core_hist
             .deques
@@ -325,17 +385,14 @@ pub fn draw_per_core_bars(
             "╌"
         };
 
-        let fg = match curr {
-            x if x < 25.0 => Color::Green,
-            x if x < 60.0 => Color::Yellow,
-            _ => Color::Red,
-        };
+        let fg = Thresholds::level(thresholds().cpu, curr as f64).color();
 
         let hist: Vec<u64> = per_core_hist
             .deques
             .get(idx)
             .map(|d| {
-                let max_points = hchunks[0].width as usize;
+                // Fewer points when frames run over budget (see frame_budget)
+                let max_points = (hchunks[0].width as usize / point_div.max(1)).max(1);
                 let start = d.len().saturating_sub(max_points);
                 d.iter().skip(start).map(|&v| v as u64).collect()
             })
@@ -348,12 +405,16 @@ pub fn draw_per_core_bars(
 
         f.render_widget(spark, hchunks[0]);
 
+        if let Some(c) = irq.and_then(|v| v.get(idx)) {
+            f.render_widget(Paragraph::new(irq_bar(c)), hchunks[1]);
+        }
+
         let label = format!("cpu{idx:<2}{trend}{curr:>5.1}%");
         let line = Line::from(Span::styled(
             label,
             Style::default().fg(fg).add_modifier(Modifier::BOLD),
         ));
-        f.render_widget(Paragraph::new(line).right_aligned(), hchunks[1]);
+        f.render_widget(Paragraph::new(line).right_aligned(), hchunks[2]);
     }
 
     // Custom 1-col scrollbar with arrows, track, and exact mapping
@@ -370,23 +431,22 @@ pub fn draw_per_core_bars(
         let max_off = total.saturating_sub(view);
 
         let thumb_len = (track * view).div_ceil(total).max(1).min(track);
-        let thumb_top = if max_off == 0 {
-            0
-        } else {
-            ((track - thumb_len) * offset + max_off / 2) / max_off
-        };
+        let thumb_top = ((track - thumb_len) * offset + max_off / 2)
+            .checked_div(max_off)
+            .unwrap_or(0);
 
         // Build lines: top arrow, track (with thumb), bottom arrow
+        let sb = Style::default().fg(theme().scrollbar);
         let mut lines: Vec<Line> = Vec::with_capacity(scroll_area.height as usize);
-        lines.push(Line::from(Span::styled("▲", Style::default().fg(SB_ARROW))));
+        lines.push(Line::from(Span::styled("▲", sb)));
         for i in 0..track {
             if i >= thumb_top && i < thumb_top + thumb_len {
-                lines.push(Line::from(Span::styled("█", Style::default().fg(SB_THUMB))));
+                lines.push(Line::from(Span::styled("█", sb)));
             } else {
-                lines.push(Line::from(Span::styled("│", Style::default().fg(SB_TRACK))));
+                lines.push(Line::from(Span::styled("│", sb)));
             }
         }
-        lines.push(Line::from(Span::styled("▼", Style::default().fg(SB_ARROW))));
+        lines.push(Line::from(Span::styled("▼", sb)));
 
         f.render_widget(Paragraph::new(lines), scroll_area);
     }
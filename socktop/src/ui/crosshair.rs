@@ -0,0 +1,53 @@
+//! Chart inspection crosshair ('x'): highlighted sample column + value/time legend on the border.
+
+use crate::history::crosshair_column;
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Span,
+    widgets::Paragraph,
+};
+
+/// Overlay a crosshair on a bordered sparkline drawn from the last `len` samples. `back` counts
+/// samples before the newest; nothing is drawn once that sample has scrolled off the chart.
+pub fn draw_crosshair(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    len: usize,
+    back: usize,
+    legend: &str,
+) {
+    if area.width < 3 || area.height < 3 {
+        return;
+    }
+    let Some(col) = crosshair_column(len, area.width.saturating_sub(2) as usize, back) else {
+        return;
+    };
+    let x = area.x + 1 + col as u16;
+    let t = theme();
+    let column = match t.dim {
+        Color::Reset => t.badge(Color::Reset),
+        bg => Style::default().fg(t.highlight).bg(bg),
+    };
+    let buf = f.buffer_mut();
+    for y in area.y + 1..area.y + area.height - 1 {
+        if let Some(cell) = buf.cell_mut((x, y)) {
+            if cell.symbol() == " " {
+                cell.set_symbol("│");
+            }
+            cell.set_style(column);
+        }
+    }
+    // Legend sits on the bottom border so the chart itself stays unobstructed
+    let w = (legend.chars().count() as u16).min(area.width - 2);
+    f.render_widget(
+        Paragraph::new(Span::styled(legend.to_string(), t.badge(t.highlight))),
+        Rect {
+            x: area.x + 1,
+            y: area.y + area.height - 1,
+            width: w,
+            height: 1,
+        },
+    );
+}
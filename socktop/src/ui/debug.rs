@@ -0,0 +1,83 @@
+//! Debug overlay: wire vs decoded bytes per payload type, current bandwidth to the agent and
+//! frame render times.
+
+use crate::frame_budget::FrameBudget;
+use crate::ui::theme::theme;
+use crate::ui::util::human;
+use crate::wire_stats::{PayloadStats, WireStats};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+fn payload_line(name: &str, p: &PayloadStats) -> Line<'static> {
+    Line::from(format!(
+        "{name:<10} {:>6} frames ({:>3} gz)  wire {:>9}  decoded {:>9}  x{:.1}",
+        p.frames,
+        p.gzipped,
+        human(p.wire_bytes),
+        human(p.decoded_bytes),
+        p.ratio()
+    ))
+}
+
+pub fn draw_debug_overlay(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    stats: &WireStats,
+    bytes_per_sec: f64,
+    frame: &FrameBudget,
+) {
+    let w = area.width.min(84);
+    let h = area.height.min(10 + frame.log().count() as u16);
+    if w < 20 || h < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "bandwidth: {}/s   total: {}   sent: {}   dropped frames: {}",
+                human(bytes_per_sec.round() as u64),
+                human(stats.total_wire()),
+                human(stats.sent_bytes),
+                stats.dropped_frames
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        payload_line("metrics", &stats.metrics),
+        payload_line("disks", &stats.disks),
+        payload_line("processes", &stats.processes),
+        Line::from(""),
+        Line::from(format!(
+            "render: {:.1}ms (budget {}ms)  per-core chart points 1/{}",
+            frame.last().as_secs_f64() * 1000.0,
+            frame.budget().as_millis(),
+            frame.divisor()
+        )),
+    ];
+    lines.extend(frame.log().map(|l| {
+        Line::from(Span::styled(
+            l.to_string(),
+            Style::default().fg(theme().highlight),
+        ))
+    }));
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Debug (d to close)")
+                .border_style(Style::default().fg(theme().accent)),
+        ),
+        rect,
+    );
+}
@@ -0,0 +1,50 @@
+//! Row of user-defined derived metric panels (gauges / sparklines).
+
+use crate::derived::{DerivedMetric, PanelKind};
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    widgets::{Block, Borders, Gauge, Sparkline},
+};
+
+pub fn draw_derived(f: &mut ratatui::Frame<'_>, area: Rect, panels: &[DerivedMetric]) {
+    if panels.is_empty() {
+        return;
+    }
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Ratio(1, panels.len() as u32);
+            panels.len()
+        ])
+        .split(area);
+    for (d, col) in panels.iter().zip(cols.iter()) {
+        let now = d
+            .last
+            .map(|v| format!("{v:.1}"))
+            .unwrap_or_else(|| "n/a".into());
+        let title = format!("{} — {now}", d.def.name);
+        match d.def.kind {
+            PanelKind::Gauge => {
+                let max = d.def.max.filter(|m| *m > 0.0).unwrap_or(100.0);
+                let ratio = (d.last.unwrap_or(0.0) / max).clamp(0.0, 1.0);
+                let g = Gauge::default()
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .gauge_style(Style::default().fg(theme().cpu))
+                    .ratio(ratio);
+                f.render_widget(g, *col);
+            }
+            PanelKind::Sparkline => {
+                let max_points = col.width.saturating_sub(2) as usize;
+                let start = d.hist.len().saturating_sub(max_points);
+                let data: Vec<u64> = d.hist.iter().skip(start).cloned().collect();
+                let spark = Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .data(&data)
+                    .style(Style::default().fg(theme().cpu));
+                f.render_widget(spark, *col);
+            }
+        }
+    }
+}
@@ -1,15 +1,32 @@
-//! Disk cards with per-device gauge and title line.
+//! Disk cards with per-device gauge and title line. Cards matching the search are outlined in
+//! yellow; the focused one (last search jump) is scrolled into view.
 
+use crate::hints::{empty_hint, Panel};
+use crate::search::matches;
 use crate::types::Metrics;
+use crate::ui::theme::{theme, thresholds, Thresholds};
 use crate::ui::util::{disk_icon, human, truncate_middle};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::Style,
-    widgets::{Block, Borders, Gauge},
+    widgets::{Block, Borders, Gauge, Paragraph},
 };
 
-pub fn draw_disks(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
-    f.render_widget(Block::default().borders(Borders::ALL).title("Disks"), area);
+pub fn draw_disks(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    m: Option<&Metrics>,
+    next_poll: std::time::Duration,
+    highlight: Option<&str>,
+    focus: Option<&str>,
+) {
+    let block = Block::default().borders(Borders::ALL).title("Disks");
+    if let Some(hint) = empty_hint(m, Panel::Disks, next_poll) {
+        let p = Paragraph::new(hint).style(Style::default().fg(theme().dim));
+        f.render_widget(p.block(block), area);
+        return;
+    }
+    f.render_widget(block, area);
     let Some(mm) = m else {
         return;
     };
@@ -35,8 +52,13 @@ pub fn draw_disks(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
         .constraints(constraints)
         .split(inner);
 
+    // Start late enough that the focused disk gets the last card
+    let first = focus
+        .and_then(|name| mm.disks.iter().position(|d| d.name == name))
+        .map_or(0, |i| (i + 1).saturating_sub(max_cards));
+
     for (i, slot) in rows.iter().enumerate() {
-        let d = &mm.disks[i];
+        let d = &mm.disks[first + i];
         let used = d.total.saturating_sub(d.available);
         let ratio = if d.total > 0 {
             used as f64 / d.total as f64
@@ -45,13 +67,7 @@ pub fn draw_disks(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
         };
         let pct = (ratio * 100.0).round() as u16;
 
-        let color = if pct < 70 {
-            ratatui::style::Color::Green
-        } else if pct < 90 {
-            ratatui::style::Color::Yellow
-        } else {
-            ratatui::style::Color::Red
-        };
+        let color = Thresholds::level(thresholds().disk, pct as f64).color();
 
         let title = format!(
             "{} {}   {} / {}  ({}%)",
@@ -62,7 +78,10 @@ pub fn draw_disks(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
             pct
         );
 
-        let card = Block::default().borders(Borders::ALL).title(title);
+        let mut card = Block::default().borders(Borders::ALL).title(title);
+        if highlight.is_some_and(|q| matches(&d.name, q)) {
+            card = card.border_style(Style::default().fg(theme().highlight));
+        }
         f.render_widget(card, *slot);
 
         let inner_card = Rect {
@@ -0,0 +1,90 @@
+//! CPU governor popup: governors on offer (current one marked, selection highlighted), a y/n
+//! line while a switch awaits confirmation, then each core's governor and frequency.
+
+use crate::governor::GovernorPicker;
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+pub fn draw_governor(f: &mut ratatui::Frame<'_>, area: Rect, p: &GovernorPicker) {
+    let dim = Style::default().fg(theme().dim);
+    let mut lines = Vec::new();
+    match (&p.note, &p.info) {
+        (Some(note), _) => lines.push(Line::from(Span::styled(note.clone(), dim))),
+        (None, None) => lines.push(Line::from(Span::styled("asking the agent…", dim))),
+        (None, Some(info)) => {
+            let current = p.current();
+            for (i, name) in info.available.iter().enumerate() {
+                let mark = if current == Some(name.as_str()) {
+                    "●"
+                } else {
+                    " "
+                };
+                let mut style = Style::default();
+                if i == p.selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                lines.push(Line::from(Span::styled(
+                    format!(" {mark} {name:<16}"),
+                    style,
+                )));
+            }
+            if info.available.is_empty() {
+                lines.push(Line::from(Span::styled("no governors listed", dim)));
+            }
+            let status = if let Some(name) = &p.confirm {
+                Some(Span::styled(
+                    format!("Switch every core to {name}? (y/n)"),
+                    Style::default().fg(theme().warn),
+                ))
+            } else if let Some(name) = &p.apply {
+                Some(Span::styled(format!("switching to {name}…"), dim))
+            } else if let Some(e) = &info.error {
+                Some(Span::styled(e.clone(), Style::default().fg(theme().crit)))
+            } else {
+                p.locked.as_ref().map(|l| Span::styled(l.clone(), dim))
+            };
+            if let Some(s) = status {
+                lines.push(Line::from(""));
+                lines.push(Line::from(s));
+            }
+            lines.push(Line::from(""));
+            for c in &info.cores {
+                let freq = c
+                    .cur_khz
+                    .map(|k| format!("{:.2} GHz", k as f64 / 1e6))
+                    .unwrap_or_default();
+                lines.push(Line::from(vec![
+                    Span::raw(format!(" cpu{:<4} {:<14}", c.cpu, c.governor)),
+                    Span::styled(freq, dim),
+                ]));
+            }
+        }
+    }
+
+    let w = area.width.min(50);
+    let h = area.height.min(lines.len() as u16 + 2);
+    if w < 30 || h < 3 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + area.height / 5,
+        width: w,
+        height: h,
+    };
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("CPU governor (Enter to switch, Esc to close)")
+                .border_style(Style::default().fg(theme().accent)),
+        ),
+        rect,
+    );
+}
@@ -1,11 +1,13 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     text::Span,
     widgets::{Block, Borders, Gauge, Paragraph},
 };
 
+use crate::hints::{empty_hint, Panel};
 use crate::types::Metrics;
+use crate::ui::theme::theme;
 
 fn fmt_bytes(b: u64) -> String {
     const KB: f64 = 1024.0;
@@ -24,7 +26,12 @@ fn fmt_bytes(b: u64) -> String {
     }
 }
 
-pub fn draw_gpu(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
+pub fn draw_gpu(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    m: Option<&Metrics>,
+    next_poll: std::time::Duration,
+) {
     let mut area = area;
     let block = Block::default().borders(Borders::ALL).title("GPU");
     f.render_widget(block, area);
@@ -40,18 +47,16 @@ pub fn draw_gpu(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
     area.x += 1;
     area.width = area.width.saturating_sub(2);
 
-    let Some(metrics) = m else {
+    if let Some(hint) = empty_hint(m, Panel::Gpu, next_poll) {
+        f.render_widget(
+            Paragraph::new(hint).style(Style::default().fg(theme().dim)),
+            area,
+        );
         return;
-    };
-
-    let Some(gpus) = metrics.gpus.as_ref() else {
-        f.render_widget(Paragraph::new("No GPUs"), area);
+    }
+    let Some(gpus) = m.and_then(|mm| mm.gpus.as_ref()) else {
         return;
     };
-    if gpus.is_empty() {
-        f.render_widget(Paragraph::new("No GPUs"), area);
-        return;
-    }
 
     // Show 3 rows per GPU: name, util bar, vram bar.
     if area.height < 3 {
@@ -81,22 +86,27 @@ pub fn draw_gpu(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
     for i in 0..count {
         let g = &gpus[i];
 
-        // Row 1: GPU name
-        let name_text = g.name.as_deref().unwrap_or("GPU");
-        let name_p = Paragraph::new(Span::raw(name_text)).style(Style::default().fg(Color::Gray));
+        // Row 1: GPU name, numbered when there are several (identical cards share a name)
+        let name = g.name.as_deref().unwrap_or("GPU");
+        let name_text = if gpus.len() > 1 {
+            format!("{i}: {name}")
+        } else {
+            name.to_string()
+        };
+        let name_p = Paragraph::new(Span::raw(name_text)).style(Style::default().fg(theme().text));
         f.render_widget(name_p, rows[i * 3]);
 
         // Row 2: Utilization bar + right label
         let util_cols = split_bar(rows[i * 3 + 1]);
         let util = g.utilization.unwrap_or(0.0).clamp(0.0, 100.0) as u16;
         let util_gauge = Gauge::default()
-            .gauge_style(Style::default().fg(Color::Green))
+            .gauge_style(Style::default().fg(theme().ok))
             .label(Span::raw(""))
             .ratio(util as f64 / 100.0);
         f.render_widget(util_gauge, util_cols[0]);
         f.render_widget(
             Paragraph::new(Span::raw(format!("util: {util}%")))
-                .style(Style::default().fg(Color::Gray)),
+                .style(Style::default().fg(theme().text)),
             util_cols[1],
         );
 
@@ -108,7 +118,7 @@ pub fn draw_gpu(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
         let mem_pct = (mem_ratio * 100.0).round() as u16;
 
         let mem_gauge = Gauge::default()
-            .gauge_style(Style::default().fg(Color::LightMagenta))
+            .gauge_style(Style::default().fg(theme().mem))
             .label(Span::raw(""))
             .ratio(mem_ratio);
         f.render_widget(mem_gauge, mem_cols[0]);
@@ -116,7 +126,7 @@ pub fn draw_gpu(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
         let total_s = fmt_bytes(total);
         f.render_widget(
             Paragraph::new(Span::raw(format!("vram: {used_s}/{total_s} ({mem_pct}%)")))
-                .style(Style::default().fg(Color::Gray)),
+                .style(Style::default().fg(theme().text)),
             mem_cols[1],
         );
     }
@@ -1,22 +1,34 @@
-//! Top header with hostname and CPU temperature indicator.
+//! Top header with hostname, load average, uptime and CPU temperature indicator.
 
+use crate::hints::{empty_hint, Panel};
 use crate::types::Metrics;
+use crate::ui::util::{truncate_middle, uptime};
 use ratatui::{
     layout::Rect,
     widgets::{Block, Borders},
 };
 use std::time::Duration;
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_header(
     f: &mut ratatui::Frame<'_>,
     area: Rect,
     m: Option<&Metrics>,
     is_tls: bool,
     has_token: bool,
+    read_only: bool,
     metrics_interval: Duration,
     procs_interval: Duration,
+    notice: Option<&str>,
 ) {
     let base = if let Some(mm) = m {
+        // Leave room for the rest of the status line even with a very long (or wide) name
+        let host_max = (area.width as usize / 3).max(12);
+        let host = match mm.instance.as_deref() {
+            Some(i) => format!("{} ({i})", mm.hostname),
+            None => mm.hostname.clone(),
+        };
+        let host = truncate_middle(&host, host_max);
         let temp = mm
             .cpu_temp_c
             .map(|t| {
@@ -29,8 +41,29 @@ pub fn draw_header(
                 };
                 format!("CPU Temp: {t:.1}°C {icon}")
             })
+            .or_else(|| empty_hint(m, Panel::Temp, Duration::ZERO))
             .unwrap_or_else(|| "CPU Temp: N/A".into());
-        format!("socktop — host: {} | {}", mm.hostname, temp)
+        // Load right after the host: it is what shows a struggling box at a glance
+        let mut stats = Vec::new();
+        if let Some((one, five, fifteen)) = mm.load_avg {
+            stats.push(format!("load {one:.2} {five:.2} {fifteen:.2}"));
+        }
+        if let Some(up) = mm.uptime_secs {
+            stats.push(format!("up {}", uptime(up)));
+        }
+        stats.push(temp);
+        let temp = stats.join(" | ");
+        if mm.labels.is_empty() {
+            format!("socktop — host: {host} | {temp}")
+        } else {
+            let labels = mm
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("socktop — host: {host} [{labels}] | {temp}")
+        }
     } else {
         "socktop — connecting...".into()
     };
@@ -42,10 +75,36 @@ pub fn draw_header(
     let pi = procs_interval.as_millis();
     let intervals = format!("⏱  {mi}ms metrics | {pi}ms procs");
     let mut parts = vec![base, tls_txt.into()];
+    // Which collectors the agent has enabled, so an empty panel reads as "off" vs "nothing found"
+    if let Some(status) = m.and_then(|mm| mm.collector_status()) {
+        parts.push(
+            status
+                .iter()
+                .map(|(label, on)| format!("{label}{}", if *on { "✓" } else { "✗" }))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+    }
+    if m.is_some_and(|mm| mm.reboot_required.is_some()) {
+        parts.push("↻ reboot required (r)".into());
+    }
+    if let Some(u) = m.and_then(|mm| mm.updates.as_ref()).filter(|u| u.total > 0) {
+        parts.push(match u.security {
+            Some(s) if s > 0 => format!("📦 {} {} updates ({s} security)", u.total, u.manager),
+            _ => format!("📦 {} {} updates", u.total, u.manager),
+        });
+    }
     if !tok_txt.is_empty() {
         parts.push(tok_txt.into());
     }
+    // Viewer token: host-changing actions are hidden, say so instead of failing on use
+    if read_only {
+        parts.push("👁 read-only".into());
+    }
     parts.push(intervals);
+    if let Some(n) = notice {
+        parts.push(n.to_string());
+    }
     parts.push("(q to quit)".into());
     let title = parts.join(" | ");
     f.render_widget(Block::default().title(title).borders(Borders::BOTTOM), area);
@@ -0,0 +1,147 @@
+//! I/O wait panel ('o'): PSI io pressure, blocked tasks and iowait, per-disk utilization and
+//! the processes moving the most bytes, from the agent's `get_io`. Meant for the "load is high
+//! but the CPU is idle" case: which disk is saturated and who is waiting on it.
+
+use crate::types::IoReport;
+use crate::ui::theme::{theme, thresholds, Thresholds};
+use crate::ui::util::{human, truncate_end};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+const BAR_W: usize = 10;
+
+/// What the panel shows; `report` is refreshed with every metrics sample while it is open.
+#[derive(Debug, Clone, Default)]
+pub struct IoPanel {
+    /// Why nothing is fetched (older or non-Linux agent)
+    pub note: Option<String>,
+    pub report: Option<IoReport>,
+}
+
+// Severity color for a 0..100 share of time
+fn level(pct: f32) -> Color {
+    Thresholds::level(thresholds().io, pct as f64).color()
+}
+
+fn bar(pct: f32) -> String {
+    let filled = ((pct.clamp(0.0, 100.0) / 100.0) * BAR_W as f32).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_W - filled))
+}
+
+impl IoPanel {
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let dim = Style::default().fg(theme().dim);
+        if let Some(note) = &self.note {
+            return vec![Line::from(note.clone())];
+        }
+        let Some(r) = &self.report else {
+            return vec![Line::from(Span::styled("loading…", dim))];
+        };
+        let mut out = Vec::new();
+        out.push(match r.pressure {
+            Some(p) => Line::from(vec![
+                Span::raw("Pressure  some "),
+                Span::styled(
+                    format!("{:.1}%", p.some_avg10),
+                    Style::default().fg(level(p.some_avg10)),
+                ),
+                Span::styled(format!(" (60s {:.1}%)", p.some_avg60), dim),
+                Span::raw("  full "),
+                Span::styled(
+                    format!("{:.1}%", p.full_avg10),
+                    Style::default().fg(level(p.full_avg10)),
+                ),
+                Span::styled(format!(" (60s {:.1}%)", p.full_avg60), dim),
+            ]),
+            None => Line::from(vec![
+                Span::raw("Pressure  "),
+                Span::styled("not reported (kernel without PSI)", dim),
+            ]),
+        });
+        let mut waiting = Vec::new();
+        if let Some(b) = r.procs_blocked {
+            waiting.push(format!("{b} tasks blocked on I/O"));
+        }
+        if let Some(w) = r.iowait_pct {
+            waiting.push(format!("CPU iowait {w:.1}%"));
+        }
+        if !waiting.is_empty() {
+            out.push(Line::from(format!("Waiting   {}", waiting.join(" · "))));
+        }
+        if r.interval_ms.is_none() {
+            out.push(Line::from(""));
+            out.push(Line::from(Span::styled(
+                "sampling disks and processes…",
+                dim,
+            )));
+            return out;
+        }
+
+        out.push(Line::from(""));
+        out.push(Line::from(Span::styled("Disks", bold)));
+        if r.disks.is_empty() {
+            out.push(Line::from(Span::styled("  no block devices", dim)));
+        }
+        for d in &r.disks {
+            out.push(Line::from(vec![
+                Span::raw(format!("  {:<12} ", truncate_end(&d.name, 12))),
+                Span::styled(bar(d.util_pct), Style::default().fg(level(d.util_pct))),
+                Span::raw(format!(
+                    " {:>3.0}%  r {:>9}/s  w {:>9}/s",
+                    d.util_pct,
+                    human(d.read_bps),
+                    human(d.write_bps)
+                )),
+            ]));
+        }
+
+        out.push(Line::from(""));
+        out.push(Line::from(Span::styled("Top I/O processes", bold)));
+        if r.top.is_empty() {
+            out.push(Line::from(Span::styled("  no process I/O", dim)));
+        }
+        for p in &r.top {
+            let mut spans = vec![Span::raw(format!(
+                "  {:>7} {:<16} r {:>9}/s  w {:>9}/s",
+                p.pid,
+                truncate_end(&p.name, 16),
+                human(p.read_bps),
+                human(p.write_bps)
+            ))];
+            if p.blocked {
+                spans.push(Span::styled("  D", Style::default().fg(theme().crit)));
+            }
+            out.push(Line::from(spans));
+        }
+        out
+    }
+}
+
+pub fn draw_io_panel(f: &mut ratatui::Frame<'_>, area: Rect, p: &IoPanel) {
+    let w = area.width.saturating_sub(4).min(80);
+    let h = area.height.saturating_sub(4).min(30);
+    if w < 30 || h < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    };
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(p.lines()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("I/O wait (o to close; D = blocked in uninterruptible sleep)")
+                .border_style(Style::default().fg(theme().accent)),
+        ),
+        rect,
+    );
+}
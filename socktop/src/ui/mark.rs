@@ -0,0 +1,119 @@
+//! Diff popup ('v'): CPU time, network bytes and disk space change since the mark ('b').
+
+use crate::alerts::format_utc;
+use crate::mark::MarkDiff;
+use crate::ui::processes::fmt_cpu_time;
+use crate::ui::theme::theme;
+use crate::ui::util::{human, truncate_end};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+// Process rows listed; the rest are summed into one line
+const TOP_PROCS: usize = 10;
+
+fn elapsed(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3_600 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}h {:02}m", s / 3_600, s % 3_600 / 60),
+    }
+}
+
+fn signed_bytes(change: i64) -> String {
+    let sign = if change < 0 { "-" } else { "+" };
+    format!("{sign}{}", human(change.unsigned_abs()))
+}
+
+pub fn mark_lines(d: &MarkDiff, at: u64) -> Vec<Line<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(theme().dim);
+    let mut out = vec![
+        Line::from(vec![
+            Span::raw(format!("Since {} UTC ", format_utc(at))),
+            Span::styled(format!("({} ago)", elapsed(d.elapsed_secs)), dim),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("CPU time consumed", bold)),
+    ];
+    if d.processes.is_empty() {
+        out.push(Line::from(Span::styled("  none", dim)));
+    }
+    for p in d.processes.iter().take(TOP_PROCS) {
+        let mut spans = vec![
+            Span::styled(format!("  {:>7} ", p.pid), dim),
+            Span::raw(format!("{:<24} ", truncate_end(&p.name, 24))),
+            Span::raw(format!("{:>10}", fmt_cpu_time(p.cpu_time_ms))),
+        ];
+        if p.partial {
+            spans.push(Span::styled(" (since first seen)", dim));
+        }
+        out.push(Line::from(spans));
+    }
+    if d.processes.len() > TOP_PROCS {
+        let rest: u64 = d.processes[TOP_PROCS..].iter().map(|p| p.cpu_time_ms).sum();
+        out.push(Line::from(Span::styled(
+            format!(
+                "  … {} more, {} together",
+                d.processes.len() - TOP_PROCS,
+                fmt_cpu_time(rest)
+            ),
+            dim,
+        )));
+    }
+    out.push(Line::from(""));
+    out.push(Line::from(vec![
+        Span::styled("Network  ", bold),
+        Span::raw(format!("↓ {}  ↑ {}", human(d.rx_bytes), human(d.tx_bytes))),
+    ]));
+    out.push(Line::from(""));
+    out.push(Line::from(Span::styled("Disk space", bold)));
+    if d.disks.is_empty() && d.removed_disks.is_empty() {
+        out.push(Line::from(Span::styled("  unchanged", dim)));
+    }
+    for disk in &d.disks {
+        let (text, color) = match disk.change {
+            Some(c) if c > 0 => (signed_bytes(c), theme().warn),
+            Some(c) => (signed_bytes(c), theme().ok),
+            None => ("new".to_string(), theme().accent),
+        };
+        out.push(Line::from(vec![
+            Span::raw(format!("  {:<32} ", truncate_end(&disk.name, 32))),
+            Span::styled(text, Style::default().fg(color)),
+        ]));
+    }
+    for name in &d.removed_disks {
+        out.push(Line::from(vec![
+            Span::raw(format!("  {:<32} ", truncate_end(name, 32))),
+            Span::styled("gone", dim),
+        ]));
+    }
+    out
+}
+
+pub fn draw_mark_diff(f: &mut ratatui::Frame<'_>, area: Rect, d: &MarkDiff, at: u64) {
+    let w = area.width.saturating_sub(4).min(72);
+    let h = area.height.saturating_sub(4).min(30);
+    if w < 30 || h < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    };
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(mark_lines(d, at)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Since mark (v to close, b to mark again)")
+                .border_style(Style::default().fg(theme().accent)),
+        ),
+        rect,
+    );
+}
@@ -1,12 +1,26 @@
 //! UI module root: exposes drawing functions for individual panels.
 
+pub mod alerts;
+pub mod cache;
 pub mod cpu;
+pub mod crosshair;
+pub mod debug;
+pub mod derived;
 pub mod disks;
+pub mod governor;
 pub mod gpu;
 pub mod header;
+pub mod io;
+pub mod mark;
 pub mod mem;
 pub mod net;
+pub mod palette;
+pub mod peers;
+pub mod proc_detail;
 pub mod processes;
+pub mod raster;
+pub mod reboot;
+pub mod search;
 pub mod swap;
 pub mod theme;
 pub mod util;
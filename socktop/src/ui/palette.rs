@@ -0,0 +1,47 @@
+//! Command palette popup: query line plus the matching actions, selection highlighted.
+
+use crate::palette::Palette;
+use crate::ui::theme::theme;
+use crate::ui::util::ListPopup;
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+};
+
+pub fn draw_palette(f: &mut ratatui::Frame<'_>, area: Rect, p: &Palette) {
+    let matches = p.matches();
+    let mut head = vec![
+        Line::from(vec![
+            Span::styled("> ", Style::default().fg(theme().accent)),
+            Span::raw(p.query.clone()),
+            Span::styled("▏", Style::default().fg(theme().dim)),
+        ]),
+        Line::from(""),
+    ];
+    if matches.is_empty() {
+        head.push(Line::from(Span::styled(
+            "no matching action",
+            Style::default().fg(theme().dim),
+        )));
+    }
+    let popup = ListPopup {
+        title: "Command palette (Enter to run, Esc to close)",
+        width: 60,
+        min_width: 20,
+        head,
+        len: matches.len(),
+        selected: p.selected,
+    };
+    popup.draw(f, area, |i, style| {
+        let a = &matches[i];
+        let mut spans = vec![Span::styled(format!(" {} ", a.label()), style)];
+        if let Some(k) = a.key_hint() {
+            spans.push(Span::styled(
+                format!(" {k}"),
+                Style::default().fg(theme().dim),
+            ));
+        }
+        Line::from(spans)
+    });
+}
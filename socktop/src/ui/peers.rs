@@ -0,0 +1,60 @@
+//! Peer switcher popup: agents known to the connected one, selection highlighted.
+
+use crate::peers::PeerPicker;
+use crate::ui::theme::theme;
+use crate::ui::util::{truncate_end, ListPopup};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+};
+
+const WIDTH: u16 = 80;
+
+fn seen(secs: Option<u64>) -> String {
+    match secs {
+        None => "via peer".into(),
+        Some(s) if s < 60 => format!("{s}s ago"),
+        Some(s) => format!("{}m ago", s / 60),
+    }
+}
+
+pub fn draw_peers(f: &mut ratatui::Frame<'_>, area: Rect, p: &PeerPicker) {
+    let dim = Style::default().fg(theme().dim);
+    let mut head = Vec::new();
+    match (&p.note, &p.peers) {
+        (Some(note), _) => head.push(Line::from(Span::styled(note.clone(), dim))),
+        (None, None) => head.push(Line::from(Span::styled("asking the agent…", dim))),
+        (None, Some(l)) if l.is_empty() => {
+            head.push(Line::from(Span::styled("no peers known", dim)))
+        }
+        (None, Some(_)) => {}
+    }
+    let peers = p.peers.as_deref().unwrap_or_default();
+    let url_w = (area.width.min(WIDTH) as usize).saturating_sub(40);
+    let popup = ListPopup {
+        title: "Peers (Enter to switch, Esc to close)",
+        width: WIDTH,
+        min_width: 30,
+        head,
+        len: peers.len(),
+        selected: p.selected,
+    };
+    popup.draw(f, area, |i, style| {
+        let peer = &peers[i];
+        let mut host = peer.host.clone().unwrap_or_else(|| "?".into());
+        if let Some(inst) = &peer.instance {
+            host = format!("{host} [{inst}]");
+        }
+        let status = if peer.is_self {
+            "connected".to_string()
+        } else {
+            seen(peer.seen_secs_ago)
+        };
+        Line::from(vec![
+            Span::styled(format!(" {:<22} ", truncate_end(&host, 22)), style),
+            Span::raw(format!(" {:<url_w$}", truncate_end(&peer.url, url_w))),
+            Span::styled(format!(" {status}"), dim),
+        ])
+    });
+}
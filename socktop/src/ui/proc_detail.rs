@@ -0,0 +1,109 @@
+//! Detail popup for the selected process: its environment and open file descriptors, fetched
+//! from agents with the `process_inspect` capability (control-scope tokens only).
+
+use crate::types::{ProcessEnv, ProcessFds};
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// What the popup shows; `env`/`fds` stay None until the agent answered.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessDetail {
+    pub pid: u32,
+    pub name: String,
+    /// Why nothing was fetched (no selection, read-only token, older agent)
+    pub note: Option<String>,
+    pub env: Option<ProcessEnv>,
+    pub fds: Option<ProcessFds>,
+    pub scroll: u16,
+}
+
+impl ProcessDetail {
+    /// Waiting for the agent's replies.
+    pub fn pending(&self) -> bool {
+        self.note.is_none() && (self.env.is_none() || self.fds.is_none())
+    }
+
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let dim = Style::default().fg(theme().dim);
+        if let Some(note) = &self.note {
+            return vec![Line::from(note.clone())];
+        }
+        if self.pending() {
+            return vec![Line::from(Span::styled("loading…", dim))];
+        }
+        let mut out = Vec::new();
+        if let Some(env) = &self.env {
+            out.push(Line::from(Span::styled(
+                format!("Environment ({})", env.vars.len()),
+                bold,
+            )));
+            if let Some(e) = &env.error {
+                out.push(Line::from(Span::styled(format!("  {e}"), dim)));
+            }
+            for v in &env.vars {
+                out.push(match &v.value {
+                    Some(val) => Line::from(format!("  {}={val}", v.name)),
+                    None => Line::from(vec![
+                        Span::raw(format!("  {}=", v.name)),
+                        Span::styled("(hidden)", dim),
+                    ]),
+                });
+            }
+            out.push(Line::from(""));
+        }
+        if let Some(fds) = &self.fds {
+            let more = if fds.truncated { "+" } else { "" };
+            out.push(Line::from(Span::styled(
+                format!("Open files ({}{more})", fds.fds.len()),
+                bold,
+            )));
+            if let Some(e) = &fds.error {
+                out.push(Line::from(Span::styled(format!("  {e}"), dim)));
+            }
+            out.extend(
+                fds.fds
+                    .iter()
+                    .map(|f| Line::from(format!("  {:>4} → {}", f.fd, f.target))),
+            );
+        }
+        out
+    }
+}
+
+pub fn draw_process_detail(f: &mut ratatui::Frame<'_>, area: Rect, d: &ProcessDetail) {
+    let w = area.width.saturating_sub(4).min(100);
+    let h = area.height.saturating_sub(4);
+    if w < 20 || h < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height - h) / 2,
+        width: w,
+        height: h,
+    };
+    let title = if d.pid == 0 {
+        "Process details (Enter to close)".to_string()
+    } else {
+        format!(
+            "{} [{}] — env & open files (↑/↓ scroll, Enter to close)",
+            d.name, d.pid
+        )
+    };
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(d.lines()).scroll((d.scroll, 0)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(theme().accent)),
+        ),
+        rect,
+    );
+}
@@ -3,47 +3,143 @@
 use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
 use ratatui::style::Modifier;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Table},
 };
-use std::cmp::Ordering;
 
+use crate::hints::{empty_hint, Panel};
+use crate::proc_trend::{ProcTrends, Trend};
+use crate::search::{matches, process_matches};
 use crate::types::Metrics;
 use crate::ui::cpu::{per_core_clamp, per_core_handle_scrollbar_mouse};
-use crate::ui::theme::{SB_ARROW, SB_THUMB, SB_TRACK};
-use crate::ui::util::human;
+use crate::ui::theme::{theme, thresholds, Level, Thresholds};
+use crate::ui::util::{display_width, human, mini_bar, truncate_end};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+// Every order is descending; the suffix says so where the variants are used
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
 pub enum ProcSortBy {
     #[default]
     CpuDesc,
     MemDesc,
+    // Cumulative CPU time (Time+): long-running hogs that are idle right now
+    TimeDesc,
 }
 
-// Keep the original header widths here so drawing and hit-testing match.
-const COLS: [Constraint; 5] = [
-    Constraint::Length(8),      // PID
-    Constraint::Percentage(40), // Name
-    Constraint::Length(8),      // CPU %
-    Constraint::Length(12),     // Mem
-    Constraint::Length(8),      // Mem %
-];
+impl ProcSortBy {
+    /// `sort=` value of `get_processes?top=K`.
+    pub fn agent_key(self) -> &'static str {
+        match self {
+            ProcSortBy::CpuDesc => "cpu",
+            ProcSortBy::MemDesc => "mem",
+            ProcSortBy::TimeDesc => "time",
+        }
+    }
+}
+
+// Fixed columns around Name: PID, CPU %, Mem, Mem %, Time+
+const PID_W: u16 = 8;
+const CPU_W: u16 = 8;
+const MEM_W: u16 = 12;
+const MEM_PCT_W: u16 = 8;
+const TIME_W: u16 = 10;
+const COL_SPACING: u16 = 1;
+// Name column bounds: room for the header and short names, without stretching on wide screens
+pub const NAME_MIN: u16 = 8;
+pub const NAME_MAX: u16 = 64;
+
+/// Name column width for `content_width`: as wide as the longest name (within
+/// NAME_MIN..=NAME_MAX), limited to what the fixed columns leave over.
+pub fn name_column_width(mm: &Metrics, content_width: u16) -> u16 {
+    let fixed = PID_W + CPU_W + MEM_W + MEM_PCT_W + TIME_W + 5 * COL_SPACING;
+    let longest = mm
+        .top_processes
+        .iter()
+        .map(|p| display_width(&p.name))
+        .max()
+        .unwrap_or(0)
+        .min(NAME_MAX as usize) as u16;
+    let room = content_width.saturating_sub(fixed);
+    longest.clamp(NAME_MIN, NAME_MAX).min(room.max(NAME_MIN))
+}
+
+// Column widths shared by drawing and hit-testing so they match.
+fn columns(mm: &Metrics, content_width: u16) -> [Constraint; 6] {
+    [
+        Constraint::Length(PID_W),
+        Constraint::Length(name_column_width(mm, content_width)),
+        Constraint::Length(CPU_W),
+        Constraint::Length(MEM_W),
+        Constraint::Length(MEM_PCT_W),
+        Constraint::Length(TIME_W),
+    ]
+}
 
+/// Row order of `top_processes` as displayed (indices into the payload).
+pub fn sorted_rows(mm: &Metrics, sort_by: ProcSortBy) -> Vec<usize> {
+    let mut idxs: Vec<usize> = (0..mm.top_processes.len()).collect();
+    let rows = &mm.top_processes;
+    match sort_by {
+        ProcSortBy::TimeDesc => idxs.sort_by(|&a, &b| {
+            let time = rows[b].cpu_time_ms.cmp(&rows[a].cpu_time_ms);
+            time.then(rows[a].table_cmp(&rows[b], false))
+        }),
+        _ => {
+            let by_mem = sort_by == ProcSortBy::MemDesc;
+            idxs.sort_by(|&a, &b| rows[a].table_cmp(&rows[b], by_mem));
+        }
+    }
+    idxs
+}
+
+/// `sorted_rows` without the rows `filter` (the `/` query) doesn't match.
+pub fn visible_rows(mm: &Metrics, sort_by: ProcSortBy, filter: Option<&str>) -> Vec<usize> {
+    let mut idxs = sorted_rows(mm, sort_by);
+    if let Some(q) = filter.filter(|q| !q.trim().is_empty()) {
+        idxs.retain(|&ix| process_matches(&mm.top_processes[ix], q));
+    }
+    idxs
+}
+
+/// Draws the table, limited to the rows matching `filter`; with a selected PID the viewport scrolls (via `scroll_offset`) to keep it visible.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_top_processes(
     f: &mut ratatui::Frame<'_>,
     area: Rect,
     m: Option<&Metrics>,
-    scroll_offset: usize,
+    scroll_offset: &mut usize,
     sort_by: ProcSortBy,
+    selected: Option<u32>,
+    next_poll: std::time::Duration,
+    inline_bars: bool,
+    filter: Option<&str>,
+    trends: Option<&ProcTrends>,
 ) {
+    if let Some(hint) = empty_hint(m, Panel::Processes, next_poll) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Top Processes");
+        let p = Paragraph::new(hint).style(Style::default().fg(theme().dim));
+        f.render_widget(p.block(block), area);
+        return;
+    }
     // Draw outer block and title
     let Some(mm) = m else { return };
     let total = mm.process_count.unwrap_or(mm.top_processes.len());
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(format!("Top Processes ({total} total)"));
+    // Sort rows (by CPU% or Mem bytes), descending, with stable tie-breaks; drop filtered ones
+    let filter = filter.filter(|q| !q.trim().is_empty());
+    let idxs = visible_rows(mm, sort_by, filter);
+    let title = match filter {
+        Some(q) => format!(
+            "Top Processes ({total} total, /{}: {} shown)",
+            q.trim(),
+            idxs.len()
+        ),
+        None => format!("Top Processes ({total} total)"),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
     f.render_widget(block, area);
 
     // Inner area and content area (reserve 2 columns for scrollbar)
@@ -63,27 +159,24 @@ pub fn draw_top_processes(
         height: inner.height,
     };
 
-    // Sort rows (by CPU% or Mem bytes), descending.
-    let mut idxs: Vec<usize> = (0..mm.top_processes.len()).collect();
-    match sort_by {
-        ProcSortBy::CpuDesc => idxs.sort_by(|&a, &b| {
-            let aa = mm.top_processes[a].cpu_usage;
-            let bb = mm.top_processes[b].cpu_usage;
-            bb.partial_cmp(&aa).unwrap_or(Ordering::Equal)
-        }),
-        ProcSortBy::MemDesc => idxs.sort_by(|&a, &b| {
-            let aa = mm.top_processes[a].mem_bytes;
-            let bb = mm.top_processes[b].mem_bytes;
-            bb.cmp(&aa)
-        }),
-    }
-
     // Scrolling
     let total_rows = idxs.len();
     let header_rows = 1usize;
     let viewport_rows = content.height.saturating_sub(header_rows as u16) as usize;
     let max_off = total_rows.saturating_sub(viewport_rows);
-    let offset = scroll_offset.min(max_off);
+    let mut offset = (*scroll_offset).min(max_off);
+    // Follow the selected process wherever the new payload sorted it
+    if let Some(pos) =
+        selected.and_then(|pid| idxs.iter().position(|&ix| mm.top_processes[ix].pid == pid))
+    {
+        if pos < offset {
+            offset = pos;
+        } else if viewport_rows > 0 && pos >= offset + viewport_rows {
+            offset = pos + 1 - viewport_rows;
+        }
+    }
+    // Store the effective offset so click hit-testing matches what is on screen
+    *scroll_offset = offset;
     let show_n = total_rows.saturating_sub(offset).min(viewport_rows);
 
     // Build visible rows
@@ -94,38 +187,65 @@ pub fn draw_top_processes(
         .map(|p| p.cpu_usage)
         .fold(0.0_f32, f32::max);
 
+    // Long names end in `…` instead of being clipped at the column edge
+    let cols = columns(mm, content.width);
+    let name_width = name_column_width(mm, content.width) as usize;
+
     let rows_iter = idxs.iter().skip(offset).take(show_n).map(|&ix| {
         let p = &mm.top_processes[ix];
         let mem_pct = (p.mem_bytes as f64 / total_mem_bytes as f64) * 100.0;
 
         let cpu_val = p.cpu_usage;
-        let cpu_fg = match cpu_val {
-            x if x < 25.0 => Color::Green,
-            x if x < 60.0 => Color::Yellow,
-            _ => Color::Red,
-        };
-        let mem_fg = match mem_pct {
-            x if x < 5.0 => Color::Blue,
-            x if x < 20.0 => Color::Magenta,
-            _ => Color::Red,
+        let limits = thresholds();
+        let cpu_fg = Thresholds::level(limits.cpu, cpu_val as f64).color();
+        // Memory keeps its own palette so the two columns stay apart
+        let mem_fg = match Thresholds::level(limits.process_mem, mem_pct) {
+            Level::Normal => theme().info,
+            Level::Warn => theme().mem,
+            Level::Crit => theme().crit,
         };
 
-        let emphasis = if (cpu_val - peak_cpu).abs() < f32::EPSILON {
+        let mut emphasis = if (cpu_val - peak_cpu).abs() < f32::EPSILON {
             Style::default().add_modifier(Modifier::BOLD)
         } else {
             Style::default()
         };
+        if selected == Some(p.pid) {
+            emphasis = emphasis.add_modifier(Modifier::REVERSED);
+        }
 
-        let cpu_str = fmt_cpu_pct(cpu_val);
+        let mut cpu_str = fmt_cpu_pct(cpu_val);
+        let mut mem_pct_str = format!("{mem_pct:.2}%");
+        // Bar glyph in front of the value (both fit the 8-column cells); off with --plain
+        if inline_bars {
+            cpu_str = format!("{} {cpu_str}", mini_bar(cpu_val));
+            mem_pct_str = format!("{} {mem_pct_str}", mini_bar(mem_pct as f32));
+        }
+        // Ramping up / settling down over the last few polls, in the cell's spare column
+        let trend = trends.map_or(Trend::Steady, |t| t.trend(p.pid));
+        let trend_fg = match trend {
+            Trend::Rising => theme().crit,
+            _ => theme().ok,
+        };
 
         ratatui::widgets::Row::new(vec![
-            ratatui::widgets::Cell::from(p.pid.to_string())
-                .style(Style::default().fg(Color::DarkGray)),
-            ratatui::widgets::Cell::from(p.name.clone()),
-            ratatui::widgets::Cell::from(cpu_str).style(Style::default().fg(cpu_fg)),
+            ratatui::widgets::Cell::from(p.pid.to_string()).style(Style::default().fg(theme().dim)),
+            ratatui::widgets::Cell::from(truncate_end(&p.name, name_width)).style(
+                if filter.is_some_and(|q| matches(&p.name, q)) {
+                    Style::default()
+                        .fg(theme().highlight)
+                        .add_modifier(Modifier::UNDERLINED)
+                } else {
+                    Style::default()
+                },
+            ),
+            ratatui::widgets::Cell::from(Line::from(vec![
+                Span::styled(cpu_str, Style::default().fg(cpu_fg)),
+                Span::styled(trend.glyph().to_string(), Style::default().fg(trend_fg)),
+            ])),
             ratatui::widgets::Cell::from(human(p.mem_bytes)),
-            ratatui::widgets::Cell::from(format!("{mem_pct:.2}%"))
-                .style(Style::default().fg(mem_fg)),
+            ratatui::widgets::Cell::from(mem_pct_str).style(Style::default().fg(mem_fg)),
+            ratatui::widgets::Cell::from(format!("{:>9}", fmt_cpu_time(p.cpu_time_ms))),
         ])
         .style(emphasis)
     });
@@ -139,16 +259,22 @@ pub fn draw_top_processes(
         ProcSortBy::MemDesc => "Mem •",
         _ => "Mem",
     };
-    let header = ratatui::widgets::Row::new(vec!["PID", "Name", cpu_hdr, mem_hdr, "Mem %"]).style(
-        Style::default()
-            .fg(Color::Cyan)
-            .add_modifier(Modifier::BOLD),
-    );
+    let time_hdr = match sort_by {
+        ProcSortBy::TimeDesc => "Time+ •",
+        _ => "Time+",
+    };
+    let header =
+        ratatui::widgets::Row::new(vec!["PID", "Name", cpu_hdr, mem_hdr, "Mem %", time_hdr]).style(
+            Style::default()
+                .fg(theme().accent)
+                .add_modifier(Modifier::BOLD),
+        );
 
     // Render table inside content area (no borders here; outer block already drawn)
-    let table = Table::new(rows_iter, COLS.to_vec())
+    let table = Table::new(rows_iter, cols)
         .header(header)
-        .column_spacing(1);
+        .column_spacing(COL_SPACING)
+        .flex(Flex::Start);
     f.render_widget(table, content);
 
     // Draw scrollbar like CPU pane
@@ -165,23 +291,22 @@ pub fn draw_top_processes(
         let max_off = total.saturating_sub(view);
 
         let thumb_len = (track * view).div_ceil(total).max(1).min(track);
-        let thumb_top = if max_off == 0 {
-            0
-        } else {
-            ((track - thumb_len) * offset + max_off / 2) / max_off
-        };
+        let thumb_top = ((track - thumb_len) * offset + max_off / 2)
+            .checked_div(max_off)
+            .unwrap_or(0);
 
         // Build lines: top arrow, track (with thumb), bottom arrow
+        let sb = Style::default().fg(theme().scrollbar);
         let mut lines: Vec<Line> = Vec::with_capacity(scroll_area.height as usize);
-        lines.push(Line::from(Span::styled("▲", Style::default().fg(SB_ARROW))));
+        lines.push(Line::from(Span::styled("▲", sb)));
         for i in 0..track {
             if i >= thumb_top && i < thumb_top + thumb_len {
-                lines.push(Line::from(Span::styled("█", Style::default().fg(SB_THUMB))));
+                lines.push(Line::from(Span::styled("█", sb)));
             } else {
-                lines.push(Line::from(Span::styled("│", Style::default().fg(SB_TRACK))));
+                lines.push(Line::from(Span::styled("│", sb)));
             }
         }
-        lines.push(Line::from(Span::styled("▼", Style::default().fg(SB_ARROW))));
+        lines.push(Line::from(Span::styled("▼", sb)));
         f.render_widget(Paragraph::new(lines), scroll_area);
     }
 }
@@ -190,6 +315,16 @@ fn fmt_cpu_pct(v: f32) -> String {
     format!("{:>5.1}", v.clamp(0.0, 100.0))
 }
 
+/// CPU time like top's TIME+: `minutes:seconds.hundredths`, whole hours once it no longer fits.
+pub fn fmt_cpu_time(ms: u64) -> String {
+    let minutes = ms / 60_000;
+    if minutes < 10_000 {
+        format!("{minutes}:{:02}.{:02}", ms / 1_000 % 60, ms / 10 % 100)
+    } else {
+        format!("{}h", ms / 3_600_000)
+    }
+}
+
 /// Handle keyboard scrolling (Up/Down/PageUp/PageDown/Home/End)
 pub fn processes_handle_key(
     scroll_offset: &mut usize,
@@ -199,14 +334,33 @@ pub fn processes_handle_key(
     crate::ui::cpu::per_core_handle_key(scroll_offset, key, page_size);
 }
 
+/// Payload row position (in display order) under a left click on the table body, if any.
+pub fn processes_row_at(mouse: MouseEvent, area: Rect, scroll_offset: usize) -> Option<usize> {
+    if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+        return None;
+    }
+    // Content area as in draw_top_processes; the first row is the header
+    let body = Rect {
+        x: area.x + 1,
+        y: area.y + 2,
+        width: area.width.saturating_sub(4),
+        height: area.height.saturating_sub(3),
+    };
+    let inside = mouse.column >= body.x
+        && mouse.column < body.x + body.width
+        && mouse.row >= body.y
+        && mouse.row < body.y + body.height;
+    inside.then(|| scroll_offset + (mouse.row - body.y) as usize)
+}
+
 /// Handle mouse for content scrolling and scrollbar dragging.
-/// Returns Some(new_sort) if the header "CPU %" or "Mem" was clicked.
+/// Returns Some(new_sort) if the header "CPU %", "Mem" or "Time+" was clicked.
 pub fn processes_handle_mouse(
     scroll_offset: &mut usize,
     drag: &mut Option<crate::ui::cpu::PerCoreScrollDrag>,
     mouse: MouseEvent,
     area: Rect,
-    total_rows: usize,
+    mm: &Metrics,
 ) -> Option<ProcSortBy> {
     // Inner and content areas (match draw_top_processes)
     let inner = Rect {
@@ -225,6 +379,8 @@ pub fn processes_handle_mouse(
         height: inner.height,
     };
 
+    let total_rows = mm.top_processes.len();
+
     // Scrollbar interactions (click arrows/page/drag)
     per_core_handle_scrollbar_mouse(scroll_offset, drag, mouse, area, total_rows);
 
@@ -246,7 +402,9 @@ pub fn processes_handle_mouse(
         // Split header into the same columns
         let cols = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints(COLS.to_vec())
+            .constraints(columns(mm, content.width))
+            .spacing(COL_SPACING)
+            .flex(Flex::Start)
             .split(header_area);
         if mouse.column >= cols[2].x && mouse.column < cols[2].x + cols[2].width {
             return Some(ProcSortBy::CpuDesc);
@@ -254,6 +412,9 @@ pub fn processes_handle_mouse(
         if mouse.column >= cols[3].x && mouse.column < cols[3].x + cols[3].width {
             return Some(ProcSortBy::MemDesc);
         }
+        if mouse.column >= cols[5].x && mouse.column < cols[5].x + cols[5].width {
+            return Some(ProcSortBy::TimeDesc);
+        }
     }
 
     // Clamp to valid range
@@ -0,0 +1,348 @@
+//! Raster charts for the CPU and network history (`--graphics auto|kitty|sixel`). Terminals
+//! that speak the kitty graphics protocol or sixel get a real pixel area chart in place of the
+//! sparkline; everything else, and any frame with a popup over the charts, keeps sparklines.
+//!
+//! Panels still draw their border and title through ratatui. The image is written straight to
+//! the terminal after the frame, over the panel's inner area, and only when its data changed.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use flate2::{write::ZlibEncoder, Compression};
+use ratatui::{
+    layout::Rect,
+    style::Color,
+    widgets::{Block, Borders},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+/// Protocol for `--graphics MODE`; `auto` looks at the environment. None means sparklines.
+pub fn detect(mode: &str, env: impl Fn(&str) -> Option<String>) -> Option<Protocol> {
+    match mode {
+        "kitty" => return Some(Protocol::Kitty),
+        "sixel" => return Some(Protocol::Sixel),
+        "auto" => {}
+        _ => return None,
+    }
+    // tmux and screen swallow graphics escapes unless passthrough is set up
+    if env("TMUX").is_some() || env("STY").is_some() {
+        return None;
+    }
+    let term = env("TERM").unwrap_or_default();
+    let program = env("TERM_PROGRAM").unwrap_or_default();
+    if env("KITTY_WINDOW_ID").is_some()
+        || term.contains("kitty")
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+    {
+        Some(Protocol::Kitty)
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    }
+}
+
+pub const EMPTY: u8 = 0;
+pub const FILL: u8 = 1;
+pub const LINE: u8 = 2;
+
+/// Area chart of `values` stretched over `w`x`h` pixels and scaled to `max`: one byte per
+/// pixel, row-major from the top, `LINE` on the curve and `FILL` below it.
+pub fn plot(values: &[u64], max: u64, w: usize, h: usize) -> Vec<u8> {
+    let mut px = vec![EMPTY; w * h];
+    if values.is_empty() || w == 0 || h == 0 {
+        return px;
+    }
+    let max = max.max(1) as f64;
+    let top = (h - 1) as f64;
+    // Row of the curve at column x, interpolated between neighbouring samples
+    let row_at = |x: usize| {
+        let pos = if w > 1 {
+            x as f64 * (values.len() - 1) as f64 / (w - 1) as f64
+        } else {
+            0.0
+        };
+        let i = pos.floor() as usize;
+        let next = values.get(i + 1).unwrap_or(&values[i]);
+        let v = values[i] as f64 + (*next as f64 - values[i] as f64) * (pos - i as f64);
+        let frac = (v / max).clamp(0.0, 1.0);
+        (top - frac * top).round() as usize
+    };
+    let mut prev = row_at(0);
+    for x in 0..w {
+        let y = row_at(x);
+        // Join steep segments vertically so the line has no gaps
+        let (from, to) = (y.min(prev), y.max(prev));
+        for row in 0..h {
+            px[row * w + x] = if (from..=to).contains(&row) {
+                LINE
+            } else if row > y {
+                FILL
+            } else {
+                EMPTY
+            };
+        }
+        prev = y;
+    }
+    px
+}
+
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ABC: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ABC[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Kitty graphics escape placing `px` (from `plot`) as image `id` over `cols`x`rows` cells at the
+/// cursor: zlib-compressed RGBA, sent in 4 KiB chunks, without moving the cursor.
+pub fn kitty(id: u32, px: &[u8], w: usize, h: usize, rgb: [u8; 3], cols: u16, rows: u16) -> String {
+    let mut rgba = Vec::with_capacity(px.len() * 4);
+    for &p in px {
+        let alpha = match p {
+            LINE => 255,
+            FILL => 80,
+            _ => 0,
+        };
+        rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], alpha]);
+    }
+    let mut z = ZlibEncoder::new(Vec::new(), Compression::fast());
+    let _ = z.write_all(&rgba);
+    let payload = base64(&z.finish().unwrap_or_default());
+
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let body = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=32,o=z,s={w},v={h},i={id},c={cols},r={rows},C=1,q=2,m={more};{body}\x1b\\"
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={more};{body}\x1b\\"));
+        }
+    }
+    out
+}
+
+/// Kitty escape deleting image `id` and freeing its data.
+pub fn kitty_delete(id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={id},q=2\x1b\\")
+}
+
+/// Sixel escape for `px` (from `plot`): the line in `rgb`, the fill dimmed, and empty pixels
+/// painted with the terminal background so a redraw replaces the previous chart.
+pub fn sixel(px: &[u8], w: usize, h: usize, rgb: [u8; 3]) -> String {
+    let pct = |c: u8, scale: u32| c as u32 * scale / 255;
+    let mut out = format!("\x1bP0;0;0q\"1;1;{w};{h}");
+    out.push_str(&format!(
+        "#{FILL};2;{};{};{}",
+        pct(rgb[0], 40),
+        pct(rgb[1], 40),
+        pct(rgb[2], 40)
+    ));
+    out.push_str(&format!(
+        "#{LINE};2;{};{};{}",
+        pct(rgb[0], 100),
+        pct(rgb[1], 100),
+        pct(rgb[2], 100)
+    ));
+    for band in (0..h).step_by(6) {
+        for color in [FILL, LINE] {
+            out.push_str(&format!("#{color}"));
+            let mut run: Option<(u8, usize)> = None;
+            let flush = |out: &mut String, run: Option<(u8, usize)>| {
+                if let Some((bits, n)) = run {
+                    let ch = (63 + bits) as char;
+                    if n > 3 {
+                        out.push_str(&format!("!{n}{ch}"));
+                    } else {
+                        out.extend(std::iter::repeat_n(ch, n));
+                    }
+                }
+            };
+            for x in 0..w {
+                let mut bits = 0u8;
+                for dy in 0..6.min(h - band) {
+                    if px[(band + dy) * w + x] == color {
+                        bits |= 1 << dy;
+                    }
+                }
+                run = match run {
+                    Some((b, n)) if b == bits => Some((b, n + 1)),
+                    prev => {
+                        flush(&mut out, prev);
+                        Some((bits, 1))
+                    }
+                };
+            }
+            flush(&mut out, run);
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// One chart to place this frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub area: Rect,
+    pub values: Vec<u64>,
+    pub max: u64,
+    pub color: Color,
+}
+
+// Kitty image ids for our charts (one per queued slot)
+const FIRST_ID: u32 = 0x50c7;
+
+pub struct RasterCharts {
+    proto: Protocol,
+    queued: Vec<Chart>,
+    shown: Vec<Chart>,
+    shown_cell_px: (u16, u16),
+}
+
+impl RasterCharts {
+    pub fn new(proto: Protocol) -> Self {
+        Self {
+            proto,
+            queued: Vec::new(),
+            shown: Vec::new(),
+            shown_cell_px: (0, 0),
+        }
+    }
+
+    pub fn queue(&mut self, chart: Chart) {
+        self.queued.push(chart);
+    }
+
+    /// Write this frame's charts. Returns true when sixel pixels were left behind by a chart
+    /// that went away; the caller should clear the screen, draw again and flush again.
+    pub fn flush(&mut self, out: &mut impl Write) -> io::Result<bool> {
+        let queued = std::mem::take(&mut self.queued);
+        let cell_px = cell_pixels();
+        if queued == self.shown && cell_px == self.shown_cell_px {
+            return Ok(false);
+        }
+        let mut buf = String::new();
+        match self.proto {
+            Protocol::Kitty => {
+                for i in 0..self.shown.len() {
+                    buf.push_str(&kitty_delete(FIRST_ID + i as u32));
+                }
+            }
+            Protocol::Sixel => {
+                let gone = self
+                    .shown
+                    .iter()
+                    .any(|s| !queued.iter().any(|q| q.area == s.area));
+                if gone {
+                    self.shown.clear();
+                    return Ok(true);
+                }
+            }
+        }
+        for (i, c) in queued.iter().enumerate() {
+            let (w, h) = (
+                c.area.width as usize * cell_px.0 as usize,
+                c.area.height as usize * cell_px.1 as usize,
+            );
+            let px = plot(&c.values, c.max, w, h);
+            // Save cursor, move to the area's top-left cell, draw, restore
+            buf.push_str(&format!("\x1b7\x1b[{};{}H", c.area.y + 1, c.area.x + 1));
+            buf.push_str(&match self.proto {
+                Protocol::Kitty => kitty(
+                    FIRST_ID + i as u32,
+                    &px,
+                    w,
+                    h,
+                    rgb(c.color),
+                    c.area.width,
+                    c.area.height,
+                ),
+                Protocol::Sixel => sixel(&px, w, h, rgb(c.color)),
+            });
+            buf.push_str("\x1b8");
+        }
+        out.write_all(buf.as_bytes())?;
+        out.flush()?;
+        self.shown = queued;
+        self.shown_cell_px = cell_px;
+        Ok(false)
+    }
+}
+
+// Pixel size of one cell; terminals that don't report it get a common 8x16
+fn cell_pixels() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(s) if s.width > 0 && s.height > 0 && s.columns > 0 && s.rows > 0 => {
+            (s.width / s.columns, s.height / s.rows)
+        }
+        _ => (8, 16),
+    }
+}
+
+fn rgb(c: Color) -> [u8; 3] {
+    match c {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Cyan | Color::LightCyan => [0, 205, 205],
+        Color::Green | Color::LightGreen => [0, 205, 0],
+        Color::Blue | Color::LightBlue => [59, 120, 255],
+        Color::Red | Color::LightRed => [205, 49, 49],
+        Color::Magenta | Color::LightMagenta => [188, 63, 188],
+        Color::Yellow | Color::LightYellow => [229, 229, 16],
+        // Mono theme: a gray that reads on either background
+        Color::Reset => [128, 128, 128],
+        _ => [229, 229, 229],
+    }
+}
+
+/// Panel border and title through ratatui, with the last `inner width` samples of `hist` queued
+/// as a raster chart over the inner area. `max` None scales to the largest sample.
+pub fn draw_raster_panel(
+    f: &mut ratatui::Frame<'_>,
+    area: Rect,
+    title: String,
+    hist: &VecDeque<u64>,
+    max: Option<u64>,
+    color: Color,
+    charts: &mut RasterCharts,
+) {
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+    let start = hist.len().saturating_sub(inner.width as usize);
+    let values: Vec<u64> = hist.iter().skip(start).copied().collect();
+    let max = max.unwrap_or_else(|| values.iter().copied().max().unwrap_or(0));
+    charts.queue(Chart {
+        area: inner,
+        values,
+        max,
+        color,
+    });
+}
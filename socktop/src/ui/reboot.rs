@@ -0,0 +1,42 @@
+//! Reboot-required detail popup (reason + packages that requested it).
+
+use crate::types::RebootRequired;
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+pub fn draw_reboot_popup(f: &mut ratatui::Frame<'_>, area: Rect, r: &RebootRequired) {
+    let w = area.width.min(70);
+    let h = area.height.min(4 + r.packages.len() as u16).max(4);
+    if w < 20 || area.height < 4 {
+        return;
+    }
+    let rect = Rect {
+        x: area.x + (area.width - w) / 2,
+        y: area.y + (area.height.saturating_sub(h)) / 2,
+        width: w,
+        height: h.min(area.height),
+    };
+    let mut lines = vec![Line::from(Span::styled(
+        r.reason.clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    if !r.packages.is_empty() {
+        lines.push(Line::from("requested by:"));
+        lines.extend(r.packages.iter().map(|p| Line::from(format!("  {p}"))));
+    }
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Reboot required (r to close)")
+                .border_style(Style::default().fg(theme().warn)),
+        ),
+        rect,
+    );
+}
@@ -0,0 +1,54 @@
+//! Global search popup: query line plus matches from every panel, selection highlighted.
+
+use crate::search::{Hit, Search};
+use crate::ui::theme::theme;
+use crate::ui::util::{truncate_end, ListPopup};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+};
+
+const WIDTH: u16 = 70;
+
+pub fn draw_search(f: &mut ratatui::Frame<'_>, area: Rect, s: &Search, hits: &[Hit]) {
+    let mut head = vec![
+        Line::from(vec![
+            Span::styled("/ ", Style::default().fg(theme().accent)),
+            Span::raw(s.query.clone()),
+            Span::styled("▏", Style::default().fg(theme().dim)),
+        ]),
+        Line::from(""),
+    ];
+    if hits.is_empty() {
+        let msg = if s.query.trim().is_empty() {
+            "type to search processes, disks, interfaces and sensors"
+        } else {
+            "no matches"
+        };
+        head.push(Line::from(Span::styled(
+            msg,
+            Style::default().fg(theme().dim),
+        )));
+    }
+    let name_w = (area.width.min(WIDTH) as usize).saturating_sub(34);
+    let popup = ListPopup {
+        title: "Search (Enter to jump, Esc to clear)",
+        width: WIDTH,
+        min_width: 20,
+        head,
+        len: hits.len(),
+        selected: s.selected,
+    };
+    popup.draw(f, area, |i, style| {
+        let hit = &hits[i];
+        Line::from(vec![
+            Span::styled(
+                format!("{:<8}", hit.kind.label()),
+                Style::default().fg(theme().dim),
+            ),
+            Span::styled(format!(" {} ", truncate_end(&hit.name, name_w)), style),
+            Span::styled(format!(" {}", hit.detail), Style::default().fg(theme().dim)),
+        ])
+    });
+}
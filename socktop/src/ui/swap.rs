@@ -1,10 +1,11 @@
 //! Swap gauge.
 
 use crate::types::Metrics;
+use crate::ui::theme::theme;
 use crate::ui::util::human;
 use ratatui::{
     layout::Rect,
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Gauge},
 };
 
@@ -22,7 +23,7 @@ pub fn draw_swap(f: &mut ratatui::Frame<'_>, area: Rect, m: Option<&Metrics>) {
 
     let g = Gauge::default()
         .block(Block::default().borders(Borders::ALL).title("Swap"))
-        .gauge_style(Style::default().fg(Color::Yellow))
+        .gauge_style(Style::default().fg(theme().swap))
         .percent(pct)
         .label(format!("{} / {}", human(used), human(total)));
     f.render_widget(g, area);
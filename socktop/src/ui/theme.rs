@@ -1,8 +1,276 @@
-//! Shared UI theme constants.
+//! The color palette panels draw with, and the color breakpoints they share.
+//!
+//! Both come from `theme.json` in the config dir (`theme` and `thresholds`), loaded once at
+//! startup; `--theme` picks the palette over the file. Panels take their colors from `theme()`
+//! and go through `Thresholds::level` so a value is colored the same everywhere.
 
-use ratatui::style::Color;
+use std::path::Path;
+use std::sync::OnceLock;
 
-// Scrollbar colors (same look as before)
-pub const SB_ARROW: Color = Color::Rgb(170, 170, 180);
-pub const SB_TRACK: Color = Color::Rgb(170, 170, 180);
-pub const SB_THUMB: Color = Color::Rgb(170, 170, 180);
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+static THRESHOLDS: OnceLock<Thresholds> = OnceLock::new();
+
+/// Colors by role rather than by hue, so a palette can swap them all at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Normal level, cleared alerts
+    pub ok: Color,
+    /// Warn level, alert and reboot popups
+    pub warn: Color,
+    /// Crit level, fired alerts, errors
+    pub crit: Color,
+    /// Below-warn memory in the processes table
+    pub info: Color,
+    /// Popup borders, table headers, prompts
+    pub accent: Color,
+    /// Hints and secondary text
+    pub dim: Color,
+    /// Labels that aren't part of the terminal's default text
+    pub text: Color,
+    /// Search matches, the focused disk, the chart crosshair
+    pub highlight: Color,
+    /// CPU charts
+    pub cpu: Color,
+    /// Memory gauge, GPU memory, hard IRQ time
+    pub mem: Color,
+    /// Swap gauge
+    pub swap: Color,
+    /// Download chart
+    pub rx: Color,
+    /// Upload chart
+    pub tx: Color,
+    /// Scrollbar arrows, track and thumb
+    pub scrollbar: Color,
+}
+
+impl Theme {
+    /// The original colors, for dark terminals.
+    pub const DARK: Theme = Theme {
+        ok: Color::Green,
+        warn: Color::Yellow,
+        crit: Color::Red,
+        info: Color::Blue,
+        accent: Color::Cyan,
+        dim: Color::DarkGray,
+        text: Color::Gray,
+        highlight: Color::Yellow,
+        cpu: Color::Cyan,
+        mem: Color::Magenta,
+        swap: Color::Yellow,
+        rx: Color::Green,
+        tx: Color::Blue,
+        scrollbar: Color::Rgb(170, 170, 180),
+    };
+
+    /// Darker shades that keep their contrast on a white or cream background.
+    pub const LIGHT: Theme = Theme {
+        ok: Color::Rgb(0, 128, 0),
+        warn: Color::Rgb(175, 95, 0),
+        crit: Color::Rgb(190, 0, 0),
+        info: Color::Rgb(0, 0, 175),
+        accent: Color::Rgb(0, 95, 135),
+        dim: Color::Rgb(110, 110, 110),
+        text: Color::Rgb(60, 60, 60),
+        highlight: Color::Rgb(175, 95, 0),
+        cpu: Color::Rgb(0, 95, 135),
+        mem: Color::Rgb(135, 0, 135),
+        swap: Color::Rgb(175, 95, 0),
+        rx: Color::Rgb(0, 128, 0),
+        tx: Color::Rgb(0, 0, 175),
+        scrollbar: Color::Rgb(120, 120, 130),
+    };
+
+    /// The terminal's own foreground everywhere; emphasis comes from `badge` and modifiers.
+    pub const MONO: Theme = Theme {
+        ok: Color::Reset,
+        warn: Color::Reset,
+        crit: Color::Reset,
+        info: Color::Reset,
+        accent: Color::Reset,
+        dim: Color::Reset,
+        text: Color::Reset,
+        highlight: Color::Reset,
+        cpu: Color::Reset,
+        mem: Color::Reset,
+        swap: Color::Reset,
+        rx: Color::Reset,
+        tx: Color::Reset,
+        scrollbar: Color::Reset,
+    };
+
+    /// `dark`, `light` or `mono` (also `monochrome`).
+    pub fn named(name: &str) -> Option<Theme> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "dark" => Some(Theme::DARK),
+            "light" => Some(Theme::LIGHT),
+            "mono" | "monochrome" => Some(Theme::MONO),
+            _ => None,
+        }
+    }
+
+    /// Dark text on a `bg` block (flashing alerts, the crosshair readout); reversed video when
+    /// the palette has no colors to fill with.
+    pub fn badge(self, bg: Color) -> Style {
+        if bg == Color::Reset {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().fg(Color::Black).bg(bg)
+        }
+    }
+
+    /// `theme` of a theme file; None when it's missing, unset or not a palette name.
+    pub fn load_from(path: &Path) -> Option<Theme> {
+        let s = std::fs::read_to_string(path).ok()?;
+        let name = serde_json::from_str::<ThemeFile>(&s).ok()?.theme?;
+        let t = Theme::named(&name);
+        if t.is_none() {
+            eprintln!(
+                "socktop: {}: unknown theme \"{name}\" (dark, light, mono)",
+                path.display()
+            );
+        }
+        t
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
+
+/// How bad a value is against its breakpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Normal,
+    Warn,
+    Crit,
+}
+
+impl Level {
+    /// The theme's ok / warn / crit color.
+    pub fn color(self) -> Color {
+        let t = theme();
+        match self {
+            Level::Normal => t.ok,
+            Level::Warn => t.warn,
+            Level::Crit => t.crit,
+        }
+    }
+}
+
+/// `warn` and `crit` are the lowest values of their level.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Breakpoints {
+    pub warn: f64,
+    pub crit: f64,
+}
+
+impl Breakpoints {
+    const fn new(warn: f64, crit: f64) -> Self {
+        Breakpoints { warn, crit }
+    }
+}
+
+/// Percentages, one pair per kind of value.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    /// Per-core bars and the processes CPU column
+    pub cpu: Breakpoints,
+    /// Processes memory column (share of host memory)
+    pub process_mem: Breakpoints,
+    /// Disk cards (space used)
+    pub disk: Breakpoints,
+    /// I/O panel pressure and device busy time
+    pub io: Breakpoints,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            cpu: Breakpoints::new(25.0, 60.0),
+            process_mem: Breakpoints::new(5.0, 20.0),
+            disk: Breakpoints::new(70.0, 90.0),
+            io: Breakpoints::new(10.0, 50.0),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ThemeFile {
+    // Checked by `Theme::load_from`, so a bad name doesn't cost the thresholds
+    #[serde(default)]
+    theme: Option<String>,
+    #[serde(default)]
+    thresholds: Thresholds,
+}
+
+impl Thresholds {
+    /// The one severity function: where `value` falls against `b`.
+    pub fn level(b: Breakpoints, value: f64) -> Level {
+        if value >= b.crit {
+            Level::Crit
+        } else if value >= b.warn {
+            Level::Warn
+        } else {
+            Level::Normal
+        }
+    }
+
+    /// `thresholds` of a theme file; problems are reported on stderr and fall back to defaults.
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(s) = std::fs::read_to_string(path) else {
+            return Thresholds::default();
+        };
+        match serde_json::from_str::<ThemeFile>(&s) {
+            Ok(f) => f.thresholds.checked(),
+            Err(e) => {
+                eprintln!("socktop: ignoring {}: {e}", path.display());
+                Thresholds::default()
+            }
+        }
+    }
+
+    // A pair with crit below warn would skip a level: use the default for it
+    fn checked(self) -> Self {
+        let d = Thresholds::default();
+        let pick = |name: &str, b: Breakpoints, d: Breakpoints| {
+            if b.warn <= b.crit {
+                b
+            } else {
+                eprintln!("socktop: theme thresholds.{name}: warn is above crit, using defaults");
+                d
+            }
+        };
+        Thresholds {
+            cpu: pick("cpu", self.cpu, d.cpu),
+            process_mem: pick("process_mem", self.process_mem, d.process_mem),
+            disk: pick("disk", self.disk, d.disk),
+            io: pick("io", self.io, d.io),
+        }
+    }
+}
+
+/// Set the breakpoints for this run (first call wins).
+pub fn configure(t: Thresholds) {
+    let _ = THRESHOLDS.set(t);
+}
+
+/// The configured breakpoints, or the defaults.
+pub fn thresholds() -> Thresholds {
+    THRESHOLDS.get().copied().unwrap_or_default()
+}
+
+/// Set the palette for this run (first call wins).
+pub fn configure_theme(t: Theme) {
+    let _ = THEME.set(t);
+}
+
+/// The configured palette, or `Theme::DARK`.
+pub fn theme() -> Theme {
+    THEME.get().copied().unwrap_or_default()
+}
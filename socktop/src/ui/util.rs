@@ -1,4 +1,14 @@
-//! Small UI helpers: human-readable sizes, truncation, icons.
+//! Small UI helpers: human-readable sizes, truncation, icons, list popups.
+
+use crate::ui::theme::theme;
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 pub fn human(b: u64) -> String {
     const K: f64 = 1024.0;
@@ -22,17 +32,88 @@ pub fn human(b: u64) -> String {
     format!("{tb:.2}TB")
 }
 
+/// Uptime as its two largest units: `3d 4h`, `4h 12m`, `12m`.
+pub fn uptime(secs: u64) -> String {
+    let (d, h, m) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    if d > 0 {
+        format!("{d}d {h}h")
+    } else if h > 0 {
+        format!("{h}h {m}m")
+    } else {
+        format!("{m}m")
+    }
+}
+
+/// Display width of `s` in terminal columns (wide CJK/emoji count as two).
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Fit `s` into `max` columns by replacing its middle with `…`, cutting only between
+/// grapheme clusters so multi-byte names (CJK, emoji, combining marks) stay intact.
 pub fn truncate_middle(s: &str, max: usize) -> String {
-    if s.len() <= max {
+    if display_width(s) <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let keep = max - 1;
+    let left = take_width(s.graphemes(true), keep / 2);
+    let right_budget = keep - display_width(&left);
+    let mut right: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for g in s.graphemes(true).rev() {
+        let w = UnicodeWidthStr::width(g);
+        if used + w > right_budget {
+            break;
+        }
+        used += w;
+        right.push(g);
+    }
+    right.reverse();
+    format!("{left}{ELLIPSIS}{}", right.concat())
+}
+
+/// Fit `s` into `max` columns by cutting its end (grapheme-safe) and appending `…`.
+pub fn truncate_end(s: &str, max: usize) -> String {
+    if display_width(s) <= max {
         return s.to_string();
     }
-    if max <= 3 {
-        return "...".into();
+    if max == 0 {
+        return String::new();
+    }
+    let mut out = take_width(s.graphemes(true), max - 1);
+    out.push(ELLIPSIS);
+    out
+}
+
+const ELLIPSIS: char = '…';
+
+// Leading graphemes whose total width fits in `max` columns
+fn take_width<'a>(graphemes: impl Iterator<Item = &'a str>, max: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for g in graphemes {
+        let w = UnicodeWidthStr::width(g);
+        if used + w > max {
+            break;
+        }
+        used += w;
+        out.push_str(g);
+    }
+    out
+}
+
+/// One-glyph bar for a 0..100 share, shown in front of table percentages (blank at zero).
+pub fn mini_bar(pct: f32) -> char {
+    match pct {
+        x if x.is_nan() || x <= 0.0 => ' ',
+        x if x < 25.0 => '▁',
+        x if x < 50.0 => '▃',
+        x if x < 75.0 => '▅',
+        _ => '▇',
     }
-    let keep = max - 3;
-    let left = keep / 2;
-    let right = keep - left;
-    format!("{}...{}", &s[..left], &s[s.len() - right..])
 }
 
 pub fn disk_icon(name: &str) -> &'static str {
@@ -49,3 +130,62 @@ pub fn disk_icon(name: &str) -> &'static str {
         "🖴"
     }
 }
+
+/// A bordered popup centered near the top of the screen: `head` lines (query, notes) above
+/// `len` rows, which scroll to keep `selected` in view.
+pub struct ListPopup<'a> {
+    pub title: &'a str,
+    /// Popup width, capped to the screen
+    pub width: u16,
+    /// Below this the popup isn't drawn at all
+    pub min_width: u16,
+    pub head: Vec<Line<'a>>,
+    pub len: usize,
+    pub selected: usize,
+}
+
+impl<'a> ListPopup<'a> {
+    /// Draw it; `row(i, style)` renders row `i`, `style` being reversed for the selection.
+    pub fn draw(
+        self,
+        f: &mut ratatui::Frame<'_>,
+        area: Rect,
+        mut row: impl FnMut(usize, Style) -> Line<'a>,
+    ) {
+        let w = area.width.min(self.width);
+        let head_h = self.head.len() as u16;
+        // Starts a fifth of the way down, so it can't be taller than the rest
+        let top = area.height / 5;
+        let h = (area.height - top).min(2 + head_h + self.len as u16);
+        if w < self.min_width || h < 3 {
+            return;
+        }
+        let rect = Rect {
+            x: area.x + (area.width - w) / 2,
+            y: area.y + top,
+            width: w,
+            height: h,
+        };
+        // Keep the selection in view when the list is taller than the popup
+        let rows = h.saturating_sub(2 + head_h) as usize;
+        let first = self.selected.saturating_sub(rows.saturating_sub(1));
+        let mut lines = self.head;
+        for i in (first..self.len).take(rows) {
+            let mut style = Style::default();
+            if i == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            lines.push(row(i, style));
+        }
+        f.render_widget(Clear, rect);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.title)
+                    .border_style(Style::default().fg(theme().accent)),
+            ),
+            rect,
+        );
+    }
+}
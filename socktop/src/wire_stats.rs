@@ -0,0 +1,97 @@
+//! Bytes-on-the-wire vs decoded bytes per payload type (for the debug overlay / metered links).
+
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    Metrics,
+    Disks,
+    Processes,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PayloadStats {
+    pub frames: u64,
+    pub gzipped: u64,
+    pub wire_bytes: u64,
+    pub decoded_bytes: u64,
+}
+
+impl PayloadStats {
+    const ZERO: Self = Self {
+        frames: 0,
+        gzipped: 0,
+        wire_bytes: 0,
+        decoded_bytes: 0,
+    };
+
+    /// decoded / wire (1.0 when nothing was compressed)
+    pub fn ratio(&self) -> f64 {
+        if self.wire_bytes == 0 {
+            1.0
+        } else {
+            self.decoded_bytes as f64 / self.wire_bytes as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WireStats {
+    pub metrics: PayloadStats,
+    pub disks: PayloadStats,
+    pub processes: PayloadStats,
+    // Request frames we sent (text commands)
+    pub sent_bytes: u64,
+    // Checked frames (--frame-check) dropped as truncated or corrupted
+    pub dropped_frames: u64,
+}
+
+impl WireStats {
+    const ZERO: Self = Self {
+        metrics: PayloadStats::ZERO,
+        disks: PayloadStats::ZERO,
+        processes: PayloadStats::ZERO,
+        sent_bytes: 0,
+        dropped_frames: 0,
+    };
+
+    /// All bytes exchanged with the agent (both directions).
+    pub fn total_wire(&self) -> u64 {
+        self.metrics.wire_bytes
+            + self.disks.wire_bytes
+            + self.processes.wire_bytes
+            + self.sent_bytes
+    }
+}
+
+static STATS: Mutex<WireStats> = Mutex::new(WireStats::ZERO);
+
+pub fn record_sent(bytes: usize) {
+    if let Ok(mut s) = STATS.lock() {
+        s.sent_bytes += bytes as u64;
+    }
+}
+
+pub fn record_dropped() {
+    if let Ok(mut s) = STATS.lock() {
+        s.dropped_frames += 1;
+    }
+}
+
+pub fn record_received(kind: PayloadKind, wire: usize, decoded: usize, gzipped: bool) {
+    if let Ok(mut s) = STATS.lock() {
+        let p = match kind {
+            PayloadKind::Metrics => &mut s.metrics,
+            PayloadKind::Disks => &mut s.disks,
+            PayloadKind::Processes => &mut s.processes,
+        };
+        p.frames += 1;
+        p.gzipped += gzipped as u64;
+        p.wire_bytes += wire as u64;
+        p.decoded_bytes += decoded as u64;
+    }
+}
+
+pub fn snapshot() -> WireStats {
+    STATS.lock().map(|s| *s).unwrap_or_default()
+}
@@ -4,20 +4,35 @@ use flate2::bufread::GzDecoder;
 use futures_util::{SinkExt, StreamExt};
 use prost::Message as _;
 use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WantsClientCert;
 use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
-use rustls::{ClientConfig, RootCertStore};
+use rustls::{ClientConfig, ConfigBuilder, RootCertStore};
 use rustls::{DigitallySignedStruct, SignatureScheme};
 use rustls_pemfile::Item;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
 use std::io::Read;
+use std::time::Duration;
 use std::{fs::File, io::BufReader, sync::Arc};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
-    connect_async, connect_async_tls_with_config, tungstenite::client::IntoClientRequest,
-    tungstenite::Message, Connector, MaybeTlsStream, WebSocketStream,
+    connect_async, connect_async_tls_with_config,
+    tungstenite::client::IntoClientRequest,
+    tungstenite::handshake::client::Request,
+    tungstenite::http::header::{HeaderValue, AUTHORIZATION},
+    tungstenite::Message,
+    Connector, MaybeTlsStream, WebSocketStream,
 };
 use url::Url;
 
-use crate::types::{DiskInfo, Metrics, ProcessInfo, ProcessesPayload};
+use crate::crash::remember_snapshot;
+use crate::framing;
+use crate::recording;
+use crate::types::{
+    AgentHistory, CpuFreq, DiskInfo, DisksDelta, Hello, IoReport, Metrics, Peer, ProcessEnv,
+    ProcessFds, ProcessInfo, ProcessesPayload, SubscribeAck,
+};
+use crate::wire_stats::{record_dropped, record_received, record_sent, PayloadKind};
 
 mod pb {
     // generated by build.rs
@@ -26,23 +41,304 @@ mod pb {
 
 pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+/// A connection to the agent that answers one text command with one reply frame.
+/// WebSocket always; WebTransport with the `webtransport` feature.
+#[allow(async_fn_in_trait)]
+pub trait AgentLink: Sized {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>>;
+    async fn exchange(&mut self, cmd: &str) -> Option<Message>;
+
+    /// Ask the agent to push metrics and processes at these intervals (`subscribe`); false
+    /// when the link or the agent can't, and the caller keeps polling.
+    async fn subscribe(&mut self, _metrics: Duration, _processes: Duration) -> bool {
+        false
+    }
+
+    /// The next pushed sample, waiting at most `wait`; None on timeout or a dropped link.
+    async fn next_push(&mut self, _wait: Duration) -> Option<Push> {
+        None
+    }
+}
+
+impl AgentLink for WsStream {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        connect(url, tls_ca).await
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.send(Message::Text(cmd.into())).await.ok()?;
+        self.next().await?.ok()
+    }
+}
+
+/// A sample the agent pushed after `subscribe`.
+#[derive(Debug, Clone)]
+pub enum Push {
+    Metrics(Box<Metrics>),
+    Processes(ProcessesPayload),
+}
+
+/// A WebSocket that can be subscribed to agent pushes. Pushes arriving while a request waits
+/// for its reply are queued for `next_push`.
+pub struct PushStream {
+    ws: WsStream,
+    queued: VecDeque<Push>,
+}
+
+impl From<WsStream> for PushStream {
+    fn from(ws: WsStream) -> Self {
+        PushStream {
+            ws,
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+impl AgentLink for PushStream {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(connect(url, tls_ca).await?.into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.ws.send(Message::Text(cmd.into())).await.ok()?;
+        loop {
+            let msg = self.ws.next().await?.ok()?;
+            if !is_push(&msg) {
+                return Some(msg);
+            }
+            self.queued.extend(decode_push(msg));
+        }
+    }
+
+    async fn subscribe(&mut self, metrics: Duration, processes: Duration) -> bool {
+        let cmd = format!(
+            "subscribe?metrics={}&processes={}",
+            metrics.as_millis(),
+            processes.as_millis()
+        );
+        request_json::<_, SubscribeAck>(self, &cmd)
+            .await
+            .is_some_and(|a| a.error.is_none() && a.metrics_ms.is_some())
+    }
+
+    async fn next_push(&mut self, wait: Duration) -> Option<Push> {
+        if let Some(p) = self.queued.pop_front() {
+            return Some(p);
+        }
+        tokio::time::timeout(wait, async {
+            loop {
+                let msg = self.ws.next().await?.ok()?;
+                // A late reply to an abandoned request: nothing is waiting for it
+                if let Some(p) = decode_push(msg) {
+                    return Some(p);
+                }
+            }
+        })
+        .await
+        .ok()?
+    }
+}
+
+// Pushed frames are binary and start with "SKP1" (inside the checked-frame header, if any)
+const PUSH_MAGIC: &[u8; 4] = b"SKP1";
+
+fn is_push(msg: &Message) -> bool {
+    let Message::Binary(b) = msg else {
+        return false;
+    };
+    match framing::unwrap(b) {
+        Some(Ok(payload)) => payload.starts_with(PUSH_MAGIC),
+        Some(Err(_)) => false,
+        None => b.starts_with(PUSH_MAGIC),
+    }
+}
+
+/// Decode a pushed frame: `"SKP1" | kind | encoding | body`, where the body is the reply
+/// frame the matching `get_*` request would have received. None for anything else.
+pub fn decode_push(msg: Message) -> Option<Push> {
+    let Message::Binary(b) = check_frame(msg)? else {
+        return None;
+    };
+    let [kind, encoding, body @ ..] = b.strip_prefix(PUSH_MAGIC)? else {
+        return None;
+    };
+    let reply = match *encoding {
+        0 => Message::Text(String::from_utf8(body.to_vec()).ok()?),
+        _ => Message::Binary(body.to_vec()),
+    };
+    match *kind {
+        b'm' => {
+            let json = metrics_json(reply)?;
+            remember_snapshot(&json);
+            serde_json::from_str(&json).ok().map(Push::Metrics)
+        }
+        b'p' => processes_from(reply).map(Push::Processes),
+        _ => None,
+    }
+}
+
+/// Whichever transport the URL selects.
+pub enum Link {
+    Ws(PushStream),
+    Poll(crate::poll::PollLink),
+    Ssh(crate::agentless::SshLink),
+    #[cfg(windows)]
+    Pipe(crate::pipe::PipeLink),
+    #[cfg(feature = "webtransport")]
+    Wt(crate::wt::WtConn),
+}
+
+impl AgentLink for Link {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        #[cfg(feature = "webtransport")]
+        if is_webtransport_url(url) {
+            return Ok(Link::Wt(crate::wt::WtConn::open(url, tls_ca).await?));
+        }
+        #[cfg(windows)]
+        if is_pipe_url(url) {
+            return Ok(Link::Pipe(crate::pipe::PipeLink::open(url, tls_ca).await?));
+        }
+        if crate::agentless::is_ssh_url(url) {
+            return Ok(Link::Ssh(
+                crate::agentless::SshLink::open(url, tls_ca).await?,
+            ));
+        }
+        let err = match connect(url, tls_ca).await {
+            Ok(ws) => return Ok(Link::Ws(ws.into())),
+            Err(e) => e.to_string(),
+        };
+        // A proxy refusing the upgrade: plain HTTP polling if the agent answers on /poll
+        match crate::poll::PollLink::probe(url, tls_ca).await {
+            Some(link) => Ok(Link::Poll(link)),
+            None => Err(err.into()),
+        }
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        match self {
+            Link::Ws(l) => l.exchange(cmd).await,
+            Link::Poll(l) => l.exchange(cmd).await,
+            Link::Ssh(l) => l.exchange(cmd).await,
+            #[cfg(windows)]
+            Link::Pipe(l) => l.exchange(cmd).await,
+            #[cfg(feature = "webtransport")]
+            Link::Wt(l) => l.exchange(cmd).await,
+        }
+    }
+
+    async fn subscribe(&mut self, metrics: Duration, processes: Duration) -> bool {
+        match self {
+            Link::Ws(l) => l.subscribe(metrics, processes).await,
+            _ => false,
+        }
+    }
+
+    async fn next_push(&mut self, wait: Duration) -> Option<Push> {
+        match self {
+            Link::Ws(l) => l.next_push(wait).await,
+            _ => None,
+        }
+    }
+}
+
+/// `https://` URLs name a WebTransport endpoint (agent `--webtransport-port`).
+pub fn is_webtransport_url(url: &str) -> bool {
+    url.starts_with("https://")
+}
+
+/// `pipe://NAME` names a local agent's Windows named pipe (agent `--pipe`).
+pub fn is_pipe_url(url: &str) -> bool {
+    url.starts_with("pipe://")
+}
+
+/// The client's auth token (`--token`, a profile's `token`, or SOCKTOP_TOKEN), sent as an
+/// `Authorization: Bearer` header rather than in the URL, where it ends up in proxy logs.
+pub fn auth_token() -> Option<String> {
+    std::env::var("SOCKTOP_TOKEN")
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
 // Connect to the agent and return the WS stream
 pub async fn connect(
     url: &str,
     tls_ca: Option<&str>,
+) -> Result<WsStream, Box<dyn std::error::Error>> {
+    connect_with_token(url, tls_ca, auth_token().as_deref()).await
+}
+
+/// `connect` with an explicit token instead of the process-wide one (fleet profiles each
+/// carry their own).
+pub async fn connect_with_token(
+    url: &str,
+    tls_ca: Option<&str>,
+    token: Option<&str>,
 ) -> Result<WsStream, Box<dyn std::error::Error>> {
     let mut u = Url::parse(url)?;
+    if framing::enabled() {
+        u.query_pairs_mut().append_pair("frames", "crc");
+    }
+    // Fingerprint pinning (set by --tls-fingerprint) takes precedence over a CA file
+    if let Some(fp) = std::env::var("SOCKTOP_TLS_FINGERPRINT")
+        .ok()
+        .filter(|s| !s.is_empty())
+    {
+        if u.scheme() == "ws" {
+            let _ = u.set_scheme("wss");
+        }
+        return connect_with_fingerprint(authorized_request(u.as_str(), token)?, &fp).await;
+    }
     if let Some(ca_path) = tls_ca {
         if u.scheme() == "ws" {
             let _ = u.set_scheme("wss");
         }
-        return connect_with_ca(u.as_str(), ca_path).await;
+        return connect_with_ca(authorized_request(u.as_str(), token)?, ca_path).await;
     }
-    let (ws, _) = connect_async(u.as_str()).await?;
+    let (ws, _) = connect_async(authorized_request(u.as_str(), token)?).await?;
     Ok(ws)
 }
 
-async fn connect_with_ca(url: &str, ca_path: &str) -> Result<WsStream, Box<dyn std::error::Error>> {
+fn authorized_request(
+    url: &str,
+    token: Option<&str>,
+) -> Result<Request, Box<dyn std::error::Error>> {
+    let mut req = url.into_client_request()?;
+    if let Some(t) = token {
+        req.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {t}"))?,
+        );
+    }
+    Ok(req)
+}
+
+async fn connect_with_ca(
+    req: Request,
+    ca_path: &str,
+) -> Result<WsStream, Box<dyn std::error::Error>> {
+    let verify_domain = std::env::var("SOCKTOP_VERIFY_NAME").ok().as_deref() == Some("1");
+    let cfg = ca_client_config(ca_path)?;
+    let (ws, _) =
+        connect_async_tls_with_config(req, None, verify_domain, Some(Connector::Rustls(cfg)))
+            .await?;
+    Ok(ws)
+}
+
+/// rustls settings for an agent connection: the pinned `--tls-fingerprint` or the `--tls-ca`
+/// file; None when neither is set.
+pub(crate) fn tls_client_config(
+    tls_ca: Option<&str>,
+) -> Result<Option<Arc<ClientConfig>>, Box<dyn std::error::Error>> {
+    if let Some(fp) = std::env::var("SOCKTOP_TLS_FINGERPRINT")
+        .ok()
+        .filter(|s| !s.is_empty())
+    {
+        return Ok(Some(pinned_client_config(&fp)?));
+    }
+    tls_ca.map(ca_client_config).transpose()
+}
+
+fn ca_client_config(ca_path: &str) -> Result<Arc<ClientConfig>, Box<dyn std::error::Error>> {
     let mut root = RootCertStore::empty();
     let mut reader = BufReader::new(File::open(ca_path)?);
     let mut der_certs = Vec::new();
@@ -53,11 +349,8 @@ async fn connect_with_ca(url: &str, ca_path: &str) -> Result<WsStream, Box<dyn s
     }
     root.add_parsable_certificates(der_certs);
 
-    let mut cfg = ClientConfig::builder()
-        .with_root_certificates(root)
-        .with_no_client_auth();
+    let mut cfg = with_client_cert(ClientConfig::builder().with_root_certificates(root))?;
 
-    let req = url.into_client_request()?;
     let verify_domain = std::env::var("SOCKTOP_VERIFY_NAME").ok().as_deref() == Some("1");
     if !verify_domain {
         #[derive(Debug)]
@@ -101,24 +394,277 @@ async fn connect_with_ca(url: &str, ca_path: &str) -> Result<WsStream, Box<dyn s
         cfg.dangerous().set_certificate_verifier(Arc::new(NoVerify));
         eprintln!("socktop: hostname verification disabled (default). Use --verify-hostname to enable strict SAN checking.");
     }
-    let cfg = Arc::new(cfg);
+    Ok(Arc::new(cfg))
+}
+
+/// Expand `host[:port][/path][?query]` shorthand into candidate URLs, TLS first.
+/// Full URLs (with a scheme) are returned unchanged.
+pub fn shorthand_candidates(input: &str) -> Vec<String> {
+    let input = input.trim();
+    if input.contains("://") {
+        return vec![input.to_string()];
+    }
+    let (rest, query) = match input.split_once('?') {
+        Some((r, q)) => (r, Some(q)),
+        None => (input, None),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/ws"),
+    };
+    let (host, port) = split_host_port(authority);
+    let suffix = match query {
+        Some(q) => format!("{path}?{q}"),
+        None => path.to_string(),
+    };
+    match port {
+        // Explicit port: same port for both schemes
+        Some(p) => vec![
+            format!("wss://{host}:{p}{suffix}"),
+            format!("ws://{host}:{p}{suffix}"),
+        ],
+        // Agent defaults: 8443 with TLS, 3000 without
+        None => vec![
+            format!("wss://{host}:8443{suffix}"),
+            format!("ws://{host}:3000{suffix}"),
+        ],
+    }
+}
+
+fn split_host_port(authority: &str) -> (String, Option<u16>) {
+    if let Some(rest) = authority.strip_prefix('[') {
+        if let Some((h, tail)) = rest.split_once(']') {
+            let port = tail.strip_prefix(':').and_then(|p| p.parse().ok());
+            return (format!("[{h}]"), port);
+        }
+    }
+    // Bare IPv6 literal (several ':'), no port possible
+    if authority.matches(':').count() > 1 {
+        return (format!("[{authority}]"), None);
+    }
+    match authority.rsplit_once(':') {
+        Some((h, p)) => match p.parse() {
+            Ok(port) => (h.to_string(), Some(port)),
+            Err(_) => (authority.to_string(), None),
+        },
+        None => (authority.to_string(), None),
+    }
+}
+
+/// What `resolve_url` settled on. `ws` is the probe's open connection (None when the input
+/// was a full URL and nothing was probed), so callers can keep using it instead of connecting
+/// again.
+pub struct Resolved {
+    pub url: String,
+    pub ws: Option<WsStream>,
+    /// Set when the TLS candidate failed and the plaintext one answered: why TLS was skipped
+    pub plaintext_fallback: Option<String>,
+}
+
+// The server spoke TLS but its certificate was refused: an agent is there, so falling back to
+// plaintext would only hide the problem
+fn certificate_rejected(e: &dyn std::error::Error) -> bool {
+    let m = e.to_string().to_ascii_lowercase();
+    m.contains("invalid peer certificate") || m.contains("fingerprint mismatch")
+}
+
+/// Resolve user input to a working URL by trying each shorthand candidate in order. A TLS
+/// agent whose certificate isn't trusted stops the search (the error says why) rather than
+/// falling back to ws://.
+pub async fn resolve_url(
+    input: &str,
+    tls_ca: Option<&str>,
+) -> Result<Resolved, Box<dyn std::error::Error>> {
+    let candidates = shorthand_candidates(input);
+    if candidates.len() == 1 {
+        return Ok(Resolved {
+            url: candidates.into_iter().next().unwrap(),
+            ws: None,
+            plaintext_fallback: None,
+        });
+    }
+    let mut tls_failure: Option<String> = None;
+    let mut last_err: Option<Box<dyn std::error::Error>> = None;
+    for c in candidates {
+        match connect(&c, tls_ca).await {
+            Ok(ws) => {
+                return Ok(Resolved {
+                    url: c,
+                    ws: Some(ws),
+                    plaintext_fallback: tls_failure,
+                })
+            }
+            Err(e) if certificate_rejected(e.as_ref()) => return Err(e),
+            Err(e) => {
+                if c.starts_with("wss://") {
+                    tls_failure = Some(format!("{c}: {e}"));
+                }
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| format!("could not connect to {input}").into()))
+}
+
+/// Parse a SHA-256 fingerprint given as hex, with or without ':' separators.
+pub fn parse_fingerprint(s: &str) -> Option<Vec<u8>> {
+    let hex: String = s
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Pin the server's leaf certificate by SHA-256 instead of a CA file (as printed by `socktop_agent --print-url`)
+async fn connect_with_fingerprint(
+    req: Request,
+    fingerprint: &str,
+) -> Result<WsStream, Box<dyn std::error::Error>> {
+    let cfg = pinned_client_config(fingerprint)?;
     let (ws, _) =
-        connect_async_tls_with_config(req, None, verify_domain, Some(Connector::Rustls(cfg)))
-            .await?;
+        connect_async_tls_with_config(req, None, false, Some(Connector::Rustls(cfg))).await?;
     Ok(ws)
 }
 
-// Send a "get_metrics" request and await a single JSON reply
-pub async fn request_metrics(ws: &mut WsStream) -> Option<Metrics> {
-    if ws.send(Message::Text("get_metrics".into())).await.is_err() {
-        return None;
+fn pinned_client_config(
+    fingerprint: &str,
+) -> Result<Arc<ClientConfig>, Box<dyn std::error::Error>> {
+    let expected = parse_fingerprint(fingerprint)
+        .ok_or("invalid --tls-fingerprint (expected 64 hex chars, optionally ':'-separated)")?;
+
+    #[derive(Debug)]
+    struct PinnedFingerprint {
+        expected: Vec<u8>,
+        algs: rustls::crypto::WebPkiSupportedAlgorithms,
+    }
+    impl ServerCertVerifier for PinnedFingerprint {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            if Sha256::digest(end_entity.as_ref()).as_slice() == self.expected.as_slice() {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(rustls::Error::General(
+                    "server certificate fingerprint mismatch".into(),
+                ))
+            }
+        }
+        // Signatures are still verified so the pinned cert proves key possession
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algs)
+        }
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algs)
+        }
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.algs.supported_schemes()
+        }
     }
-    match ws.next().await {
-        Some(Ok(Message::Binary(b))) => {
-            gunzip_to_string(&b).and_then(|s| serde_json::from_str::<Metrics>(&s).ok())
+
+    // builder() installs the process-wide default provider if none is set yet
+    let builder = ClientConfig::builder();
+    let algs = rustls::crypto::CryptoProvider::get_default()
+        .map(|p| p.signature_verification_algorithms)
+        .ok_or("no TLS crypto provider available")?;
+    let cfg = with_client_cert(
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedFingerprint { expected, algs })),
+    )?;
+    Ok(Arc::new(cfg))
+}
+
+/// Finish `builder` with the `--tls-cert`/`--tls-key` client certificate (SOCKTOP_TLS_CERT,
+/// SOCKTOP_TLS_KEY), which agents started with `--require-client-cert` ask for.
+pub fn with_client_cert(
+    builder: ConfigBuilder<ClientConfig, WantsClientCert>,
+) -> Result<ClientConfig, Box<dyn std::error::Error>> {
+    let var = |k| std::env::var(k).ok().filter(|s: &String| !s.is_empty());
+    match (var("SOCKTOP_TLS_CERT"), var("SOCKTOP_TLS_KEY")) {
+        (Some(cert), Some(key)) => {
+            let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&cert)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            if certs.is_empty() {
+                return Err(format!("--tls-cert {cert}: no certificate in the file").into());
+            }
+            let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&key)?))?
+                .ok_or_else(|| format!("--tls-key {key}: no private key in the file"))?;
+            Ok(builder.with_client_auth_cert(certs, key)?)
+        }
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err("--tls-cert and --tls-key must be given together".into()),
+    }
+}
+
+// Send a "get_metrics" request and await a single JSON reply
+pub async fn request_metrics<L: AgentLink>(ws: &mut L) -> Option<Metrics> {
+    serde_json::from_str::<Metrics>(&request_metrics_json(ws).await?).ok()
+}
+
+/// The `get_metrics` reply as JSON text (decompressed), exactly as the agent sent it.
+pub async fn request_metrics_json<L: AgentLink>(ws: &mut L) -> Option<String> {
+    let s = metrics_json(send_request(ws, "get_metrics").await?)?;
+    remember_snapshot(&s);
+    Some(s)
+}
+
+// A metrics reply frame as JSON text (gzip binary or plain text), recorded under --record
+fn metrics_json(msg: Message) -> Option<String> {
+    let json = match msg {
+        Message::Binary(b) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Metrics, b.len(), s.len(), true);
+            s
+        }
+        Message::Text(json) => {
+            record_received(PayloadKind::Metrics, json.len(), json.len(), false);
+            json
+        }
+        _ => return None,
+    };
+    recording::record(recording::Kind::Metrics, &json);
+    Some(json)
+}
+
+// Send a text command, counting its bytes toward the wire stats, and await its reply
+async fn send_request<L: AgentLink>(ws: &mut L, cmd: &str) -> Option<Message> {
+    record_sent(cmd.len());
+    check_frame(ws.exchange(cmd).await?)
+}
+
+// Checked frames: strip the header, or drop (and count) a damaged frame
+fn check_frame(msg: Message) -> Option<Message> {
+    let Message::Binary(b) = &msg else {
+        return Some(msg);
+    };
+    match framing::unwrap(b) {
+        None => Some(msg),
+        Some(Ok(payload)) => Some(Message::Binary(payload.to_vec())),
+        Some(Err(_)) => {
+            record_dropped();
+            None
         }
-        Some(Ok(Message::Text(json))) => serde_json::from_str::<Metrics>(&json).ok(),
-        _ => None,
     }
 }
 
@@ -140,71 +686,227 @@ fn gunzip_to_vec(bytes: &[u8]) -> Option<Vec<u8>> {
 fn is_gzip(bytes: &[u8]) -> bool {
     bytes.len() >= 2 && bytes[0] == 0x1f && bytes[1] == 0x8b
 }
+
+// A JSON object rather than a protobuf message: `{` would be a start-group tag for field 15,
+// which the processes message never uses
+fn looks_like_json(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'{')
+}
 // Suppress dead_code until these are wired into the app
 #[allow(dead_code)]
 pub enum Payload {
-    Metrics(Metrics),
+    Metrics(Box<Metrics>),
     Disks(Vec<DiskInfo>),
     Processes(ProcessesPayload),
 }
 
 // Send a "get_disks" request and await a JSON Vec<DiskInfo>
-pub async fn request_disks(ws: &mut WsStream) -> Option<Vec<DiskInfo>> {
-    if ws.send(Message::Text("get_disks".into())).await.is_err() {
-        return None;
+pub async fn request_disks<L: AgentLink>(ws: &mut L) -> Option<Vec<DiskInfo>> {
+    match send_request(ws, "get_disks").await {
+        Some(Message::Binary(b)) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Disks, b.len(), s.len(), true);
+            serde_json::from_str::<Vec<DiskInfo>>(&s).ok()
+        }
+        Some(Message::Text(json)) => {
+            record_received(PayloadKind::Disks, json.len(), json.len(), false);
+            serde_json::from_str::<Vec<DiskInfo>>(&json).ok()
+        }
+        _ => None,
     }
-    match ws.next().await {
-        Some(Ok(Message::Binary(b))) => {
-            gunzip_to_string(&b).and_then(|s| serde_json::from_str::<Vec<DiskInfo>>(&s).ok())
+}
+
+// Only the disks that changed since delta reply `seq` (0 for a full list); needs a session
+// (`hello`) and the agent's `disks_delta` capability
+pub async fn request_disks_delta<L: AgentLink>(ws: &mut L, seq: u64) -> Option<DisksDelta> {
+    match send_request(ws, &format!("get_disks?delta={seq}")).await {
+        Some(Message::Binary(b)) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Disks, b.len(), s.len(), true);
+            serde_json::from_str::<DisksDelta>(&s).ok()
+        }
+        Some(Message::Text(json)) => {
+            record_received(PayloadKind::Disks, json.len(), json.len(), false);
+            serde_json::from_str::<DisksDelta>(&json).ok()
         }
-        Some(Ok(Message::Text(json))) => serde_json::from_str::<Vec<DiskInfo>>(&json).ok(),
         _ => None,
     }
 }
 
-// Send a "get_processes" request and await a ProcessesPayload decoded from protobuf (binary, may be gzipped)
-pub async fn request_processes(ws: &mut WsStream) -> Option<ProcessesPayload> {
-    if ws
-        .send(Message::Text("get_processes".into()))
-        .await
-        .is_err()
-    {
-        return None;
+// Version, token scope and session of this connection (agents advertising the `hello`
+// capability); `resume` asks for the session a dropped connection had. Also asks for protobuf
+// processes, which an agent serving JSON by default (`--processes-json`) would otherwise not send
+pub async fn request_hello<L: AgentLink>(ws: &mut L, resume: Option<&str>) -> Option<Hello> {
+    let cmd = match resume {
+        Some(id) => format!("hello?processes=pb&resume={id}"),
+        None => "hello?processes=pb".to_string(),
+    };
+    match send_request(ws, &cmd).await {
+        Some(Message::Binary(b)) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Metrics, b.len(), s.len(), true);
+            serde_json::from_str::<Hello>(&s).ok()
+        }
+        Some(Message::Text(json)) => {
+            record_received(PayloadKind::Metrics, json.len(), json.len(), false);
+            serde_json::from_str::<Hello>(&json).ok()
+        }
+        _ => None,
+    }
+}
+
+// Downsampled recent history (agents advertising the `history` capability)
+pub async fn request_history<L: AgentLink>(ws: &mut L) -> Option<AgentHistory> {
+    match send_request(ws, "get_history").await {
+        Some(Message::Binary(b)) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Metrics, b.len(), s.len(), true);
+            serde_json::from_str::<AgentHistory>(&s).ok()
+        }
+        Some(Message::Text(json)) => {
+            record_received(PayloadKind::Metrics, json.len(), json.len(), false);
+            serde_json::from_str::<AgentHistory>(&json).ok()
+        }
+        _ => None,
+    }
+}
+
+// Environment of one process (`process_inspect` capability, control-scope token)
+pub async fn request_process_env<L: AgentLink>(ws: &mut L, pid: u32) -> Option<ProcessEnv> {
+    request_json(ws, &format!("get_process_env?pid={pid}")).await
+}
+
+// Open file descriptors of one process (`process_inspect` capability, control-scope token)
+pub async fn request_process_fds<L: AgentLink>(ws: &mut L, pid: u32) -> Option<ProcessFds> {
+    request_json(ws, &format!("get_process_fds?pid={pid}")).await
+}
+
+// Pressure, disk utilization and top I/O processes for the I/O panel (`io` capability)
+pub async fn request_io<L: AgentLink>(ws: &mut L) -> Option<IoReport> {
+    request_json(ws, "get_io").await
+}
+
+// Agents the connected one knows by gossip (`peers` capability), itself first
+pub async fn request_peers<L: AgentLink>(ws: &mut L) -> Option<Vec<Peer>> {
+    request_json(ws, "get_peers").await
+}
+
+// Per-core governors and the ones on offer (`cpufreq` capability)
+pub async fn request_cpufreq<L: AgentLink>(ws: &mut L) -> Option<CpuFreq> {
+    request_json(ws, "get_cpufreq").await
+}
+
+// Switch every core's governor (`cpufreq_control`, control-scope token); the state afterwards
+pub async fn request_set_governor<L: AgentLink>(ws: &mut L, name: &str) -> Option<CpuFreq> {
+    request_json(ws, &format!("set_governor?name={name}")).await
+}
+
+// One JSON object reply, gzipped or not
+async fn request_json<L: AgentLink, T: serde::de::DeserializeOwned>(
+    ws: &mut L,
+    cmd: &str,
+) -> Option<T> {
+    match send_request(ws, cmd).await {
+        Some(Message::Binary(b)) => {
+            let s = gunzip_to_string(&b)?;
+            record_received(PayloadKind::Metrics, b.len(), s.len(), true);
+            serde_json::from_str(&s).ok()
+        }
+        Some(Message::Text(json)) => {
+            record_received(PayloadKind::Metrics, json.len(), json.len(), false);
+            serde_json::from_str(&json).ok()
+        }
+        _ => None,
     }
-    match ws.next().await {
-        Some(Ok(Message::Binary(b))) => {
+}
+
+// Send a "get_processes" request and await a ProcessesPayload decoded from protobuf (binary, may be gzipped)
+pub async fn request_processes<L: AgentLink>(ws: &mut L) -> Option<ProcessesPayload> {
+    request_processes_cmd(ws, "get_processes").await
+}
+
+// Only the `top` heaviest rows by `sort` (cpu, mem or time); requires the agent's
+// `processes_top` capability (agents before `time` fall back to cpu)
+pub async fn request_processes_top<L: AgentLink>(
+    ws: &mut L,
+    top: usize,
+    sort: &str,
+) -> Option<ProcessesPayload> {
+    request_processes_cmd(ws, &format!("get_processes?top={top}&sort={sort}")).await
+}
+
+// Top rows plus `tail` rows of the rest, PID-ordered from just after `after` (agents listing
+// `processes_sample`); the sample lands in `tail`
+pub async fn request_processes_sampled<L: AgentLink>(
+    ws: &mut L,
+    top: usize,
+    sort: &str,
+    tail: usize,
+    after: u32,
+) -> Option<ProcessesPayload> {
+    request_processes_cmd(
+        ws,
+        &format!("get_processes?top={top}&sort={sort}&tail={tail}&after={after}"),
+    )
+    .await
+}
+
+async fn request_processes_cmd<L: AgentLink>(ws: &mut L, cmd: &str) -> Option<ProcessesPayload> {
+    processes_from(send_request(ws, cmd).await?)
+}
+
+// A processes reply frame, decoded (and recorded under --record)
+fn processes_from(msg: Message) -> Option<ProcessesPayload> {
+    let procs = decode_processes(msg)?;
+    recording::record_value(recording::Kind::Processes, &procs);
+    Some(procs)
+}
+
+// Protobuf (binary, may be gzipped) or the JSON object
+fn decode_processes(msg: Message) -> Option<ProcessesPayload> {
+    match msg {
+        Message::Binary(b) => {
             let gz = is_gzip(&b);
+            let wire = b.len();
             let data = if gz { gunzip_to_vec(&b)? } else { b };
+            record_received(PayloadKind::Processes, wire, data.len(), gz);
+            // Agents before protobuf (or serving JSON) send the object as a binary frame too
+            if looks_like_json(&data) {
+                let s = String::from_utf8(data).ok()?;
+                return serde_json::from_str::<ProcessesPayload>(&s).ok();
+            }
             match pb::Processes::decode(data.as_slice()) {
                 Ok(pb) => {
-                    let rows: Vec<ProcessInfo> = pb
-                        .rows
-                        .into_iter()
-                        .map(|p: pb::Process| ProcessInfo {
-                            pid: p.pid,
-                            name: p.name,
-                            cpu_usage: p.cpu_usage,
-                            mem_bytes: p.mem_bytes,
-                        })
-                        .collect();
+                    let (tail, top): (Vec<pb::Process>, Vec<pb::Process>) =
+                        pb.rows.into_iter().partition(|p| p.sampled);
+                    let info = |p: pb::Process| ProcessInfo {
+                        pid: p.pid,
+                        name: p.name,
+                        cpu_usage: p.cpu_usage,
+                        mem_bytes: p.mem_bytes,
+                        cpu_time_ms: p.cpu_time_ms,
+                    };
                     Some(ProcessesPayload {
                         process_count: pb.process_count as usize,
-                        top_processes: rows,
+                        top_processes: top.into_iter().map(info).collect(),
+                        tail: tail.into_iter().map(info).collect(),
                     })
                 }
                 Err(e) => {
                     if std::env::var("SOCKTOP_DEBUG").ok().as_deref() == Some("1") {
                         eprintln!("protobuf decode failed: {e}");
                     }
-                    // Fallback: maybe it's JSON (bytes already decompressed if gz)
-                    match String::from_utf8(data) {
-                        Ok(s) => serde_json::from_str::<ProcessesPayload>(&s).ok(),
-                        Err(_) => None,
-                    }
+                    None
                 }
             }
         }
-        Some(Ok(Message::Text(json))) => serde_json::from_str::<ProcessesPayload>(&json).ok(),
+        Message::Text(json) => {
+            record_received(PayloadKind::Processes, json.len(), json.len(), false);
+            serde_json::from_str::<ProcessesPayload>(&json).ok()
+        }
         _ => None,
     }
 }
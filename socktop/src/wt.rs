@@ -0,0 +1,88 @@
+//! Experimental WebTransport (HTTP/3 over QUIC) client, feature `webtransport`.
+//!
+//! Each request opens its own bidirectional stream, so one lost packet only delays that
+//! reply instead of every frame queued behind it on a single TCP connection. The agent
+//! answers with a kind byte (`0` text, `1` binary) followed by the same body it would
+//! send as a WebSocket frame.
+
+use std::error::Error;
+
+use sha2::{Digest, Sha256};
+use tokio_tungstenite::tungstenite::Message;
+use wtransport::endpoint::ConnectOptions;
+use wtransport::tls::Sha256Digest;
+use wtransport::{ClientConfig, Connection, Endpoint};
+
+use crate::ws::{auth_token, parse_fingerprint, AgentLink};
+
+const KIND_TEXT: u8 = 0;
+const KIND_BINARY: u8 = 1;
+
+pub struct WtConn {
+    conn: Connection,
+}
+
+impl AgentLink for WtConn {
+    async fn open(url: &str, tls_ca: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let builder = ClientConfig::builder().with_bind_default();
+        // The agent's self-signed cert can't chain to a root, so pin it like the wss:// path.
+        // Pinning only works for certs valid 14 days or less: the agent's listener has its own
+        // (webtransport-cert.pem), replaced every 12 days, not the cert.pem used for wss://
+        let config = match pinned_hash(tls_ca)? {
+            Some(hash) => builder.with_server_certificate_hashes([hash]).build(),
+            None => builder.with_native_certs().build(),
+        };
+        let mut options = ConnectOptions::builder(url);
+        if let Some(t) = auth_token() {
+            options = options.add_header("authorization", format!("Bearer {t}"));
+        }
+        let conn = Endpoint::client(config)?.connect(options.build()).await?;
+        Ok(WtConn { conn })
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        let (mut send, mut recv) = self.conn.open_bi().await.ok()?.await.ok()?;
+        send.write_all(cmd.as_bytes()).await.ok()?;
+        send.finish().await.ok()?;
+        let mut buf = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut recv, &mut buf)
+            .await
+            .ok()?;
+        decode_reply(buf)
+    }
+}
+
+/// Split a stream reply into the message it stands for; None for an unknown kind byte.
+pub fn decode_reply(mut buf: Vec<u8>) -> Option<Message> {
+    if buf.is_empty() {
+        return None;
+    }
+    let kind = buf.remove(0);
+    match kind {
+        KIND_TEXT => String::from_utf8(buf).ok().map(Message::Text),
+        KIND_BINARY => Some(Message::Binary(buf)),
+        _ => None,
+    }
+}
+
+// --tls-fingerprint wins; otherwise pin the first certificate in --tls-ca
+fn pinned_hash(tls_ca: Option<&str>) -> Result<Option<Sha256Digest>, Box<dyn Error>> {
+    if let Some(fp) = std::env::var("SOCKTOP_TLS_FINGERPRINT")
+        .ok()
+        .filter(|s| !s.is_empty())
+    {
+        let bytes = parse_fingerprint(&fp).ok_or("invalid --tls-fingerprint")?;
+        let digest: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| "--tls-fingerprint must be a SHA-256 digest")?;
+        return Ok(Some(Sha256Digest::new(digest)));
+    }
+    let Some(path) = tls_ca else {
+        return Ok(None);
+    };
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let cert = rustls_pemfile::certs(&mut reader)
+        .next()
+        .ok_or_else(|| format!("no certificate in {path}"))??;
+    Ok(Some(Sha256Digest::new(Sha256::digest(&cert).into())))
+}
@@ -0,0 +1,44 @@
+//! --a11y text mode: labeled plain lines, no decorative glyphs.
+use socktop::a11y::{a11y_lines, plain, A11yStatus};
+use socktop::types::Metrics;
+
+#[test]
+fn plain_strips_glyphs_but_keeps_words() {
+    assert_eq!(
+        plain("⚠ agent slow | 🕒✗ clock unsynced (offset +1.5ms)"),
+        "agent slow | clock unsynced (offset +1.5ms)"
+    );
+}
+
+#[test]
+fn lines_label_every_value() {
+    let m: Metrics = serde_json::from_str(
+        r#"{"cpu_total":12.4,"cpu_per_core":[10.0,14.8],"mem_total":8589934592,
+            "mem_used":2147483648,"swap_total":0,"swap_used":0,"hostname":"pi",
+            "cpu_temp_c":48.6,"disks":[],"networks":[],"gpus":null,
+            "top_processes":[{"pid":7,"name":"sshd","cpu_usage":1.2,"mem_bytes":10485760}]}"#,
+    )
+    .unwrap();
+    let lines = a11y_lines(
+        Some(&m),
+        A11yStatus {
+            rx_kb: 3,
+            tx_kb: 1,
+            ..Default::default()
+        },
+    );
+    for want in [
+        "Host: pi",
+        "CPU: 12 percent average over 2 cores",
+        "CPU temperature: 49 degrees Celsius",
+        "Memory: 2.0 gigabytes used of 8.0 gigabytes (25 percent)",
+        "Network: download 3 kilobytes per second, upload 1 kilobytes per second",
+        "Top process 1: sshd (PID 7), 1 percent CPU, 10 megabytes memory",
+    ] {
+        assert!(
+            lines.iter().any(|l| l == want),
+            "missing {want:?} in {lines:#?}"
+        );
+    }
+    assert!(lines.iter().all(|l| l.is_ascii()));
+}
@@ -0,0 +1,89 @@
+//! Push mode: `subscribe`, then samples the agent sends on its own schedule.
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use socktop::ws::{decode_push, AgentLink, Push, PushStream};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+const METRICS: &str = r#"{"cpu_total":12.5,"cpu_per_core":[12.5],"mem_total":100,"mem_used":40,
+"swap_total":0,"swap_used":0,"hostname":"edge","cpu_temp_c":null,"disks":[],"networks":[],
+"top_processes":[],"gpus":null,"capabilities":["subscribe"]}"#;
+const PROCESSES: &str = r#"{"process_count":1,"top_processes":[{"pid":9,"name":"sshd","cpu_usage":1.0,"mem_bytes":4096}]}"#;
+
+// "SKP1" | kind | encoding (0 = text body) | body
+fn push(kind: u8, body: &str) -> Message {
+    let mut f = b"SKP1".to_vec();
+    f.extend_from_slice(&[kind, 0]);
+    f.extend_from_slice(body.as_bytes());
+    Message::Binary(f)
+}
+
+// Acks `subscribe`, pushes metrics then processes; a push also slips in before the get_disks reply
+async fn pushing_agent() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (sock, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(sock).await.unwrap();
+        while let Some(Ok(Message::Text(cmd))) = ws.next().await {
+            if cmd.starts_with("subscribe?") {
+                assert_eq!(cmd, "subscribe?metrics=500&processes=2000");
+                let ack = r#"{"schema_version":1,"metrics_ms":500,"processes_ms":2000}"#;
+                ws.send(Message::Text(ack.into())).await.unwrap();
+                ws.send(push(b'm', METRICS)).await.unwrap();
+                ws.send(push(b'p', PROCESSES)).await.unwrap();
+            } else if cmd == "get_disks" {
+                ws.send(push(b'm', METRICS)).await.unwrap();
+                ws.send(Message::Text("[]".into())).await.unwrap();
+            }
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn pushes_arrive_between_and_around_replies() {
+    let url = pushing_agent().await;
+    let mut link = PushStream::open(&url, None).await.unwrap();
+    assert!(
+        link.subscribe(Duration::from_millis(500), Duration::from_secs(2))
+            .await
+    );
+    let wait = Duration::from_secs(2);
+    let Some(Push::Metrics(m)) = link.next_push(wait).await else {
+        panic!("metrics push first");
+    };
+    assert_eq!((m.hostname.as_str(), m.cpu_total), ("edge", 12.5));
+    let Some(Push::Processes(p)) = link.next_push(wait).await else {
+        panic!("then processes");
+    };
+    assert_eq!(p.top_processes[0].name, "sshd");
+
+    // The reply still answers the request; the push that came first waits in the queue
+    let disks = socktop::ws::request_disks(&mut link).await.unwrap();
+    assert!(disks.is_empty());
+    assert!(matches!(link.next_push(wait).await, Some(Push::Metrics(_))));
+    // Nothing more pushed: a timeout, not a hang
+    assert!(link.next_push(Duration::from_millis(50)).await.is_none());
+}
+
+#[test]
+fn only_tagged_binary_frames_are_pushes() {
+    assert!(decode_push(Message::Text(METRICS.into())).is_none());
+    assert!(decode_push(push(b'x', METRICS)).is_none());
+    // A checked frame around the push is unwrapped first
+    let Message::Binary(inner) = push(b'p', PROCESSES) else {
+        unreachable!()
+    };
+    let mut crc = flate2::Crc::new();
+    crc.update(&inner);
+    let mut framed = b"SKF1".to_vec();
+    framed.extend_from_slice(&(inner.len() as u32).to_le_bytes());
+    framed.extend_from_slice(&crc.sum().to_le_bytes());
+    framed.extend_from_slice(&inner);
+    assert!(matches!(
+        decode_push(Message::Binary(framed)),
+        Some(Push::Processes(p)) if p.process_count == 1
+    ));
+}
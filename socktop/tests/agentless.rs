@@ -0,0 +1,175 @@
+//! Agentless (`--ssh`) collection: /proc output from a remote shell turned into agent replies.
+use std::time::{Duration, Instant};
+
+use socktop::agentless::{parse_diskstats, parse_proc_stats, script, sections_for, Collector};
+use socktop::types::{DiskInfo, IoReport, Metrics, ProcessesPayload};
+
+// What the remote shell prints for a script: each section after its header line
+fn output(sections: &[(&str, &str)]) -> String {
+    sections
+        .iter()
+        .map(|(name, body)| format!("@@socktop:{name}\n{body}"))
+        .collect()
+}
+
+const MEMINFO: &str = "MemTotal:        8000000 kB\nMemFree:         1000000 kB\n\
+MemAvailable:    6000000 kB\nSwapTotal:       2000000 kB\nSwapFree:        1500000 kB\n";
+const NETDEV: &str = "Inter-|   Receive                                                |  Transmit\n \
+face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    \
+lo:  5000      50    0    0    0     0          0         0     5000      50    0    0    0     0       0          0\n  \
+eth0: 123456    900    0    0    0     0          0         0    65432     700    0    0    0     0       0          0\n";
+
+fn stat(user: u64, idle: u64) -> String {
+    format!(
+        "cpu  {} 0 0 {} 0 0 0 0 0 0\ncpu0 {user} 0 0 {idle} 0 0 0 0 0 0\n\
+         cpu1 {user} 0 0 {idle} 0 0 0 0 0 0\nprocs_running 3\nprocs_blocked 1\n",
+        user * 2,
+        idle * 2
+    )
+}
+
+#[test]
+fn scripts_cover_only_what_agentless_mode_answers() {
+    let s = script(sections_for("get_metrics").unwrap());
+    assert!(s.contains("echo '@@socktop:stat'; cat /proc/stat 2>/dev/null\n"));
+    assert!(s.ends_with("echo '@@socktop:end'\n"));
+    assert!(sections_for("get_processes?top=50&sort=cpu").is_some());
+    assert!(sections_for("get_history").is_none());
+    assert!(sections_for("set_governor?name=performance").is_none());
+}
+
+#[test]
+fn metrics_parse_as_agent_metrics_with_cpu_from_the_second_sample() {
+    let mut c = Collector::default();
+    let now = Instant::now();
+    let sample = |user: u64, idle: u64| {
+        output(&[
+            ("stat", stat(user, idle).as_str()),
+            ("meminfo", MEMINFO),
+            ("netdev", NETDEV),
+            ("hostname", "router\n"),
+            ("uptime", "3600.52 7000.00\n"),
+            ("loadavg", "0.52 0.61 0.70 2/301 12345\n"),
+            ("boot_id", "4f1c-77\n"),
+            ("pids", "87\n"),
+        ])
+    };
+    let first: Metrics =
+        serde_json::from_str(&c.reply("get_metrics", &sample(100, 900), now).unwrap()).unwrap();
+    assert_eq!(first.cpu_total, 0.0);
+    let m: Metrics =
+        serde_json::from_str(&c.reply("get_metrics", &sample(125, 975), now).unwrap()).unwrap();
+    // 25 busy of 100 ticks on each core
+    assert_eq!(m.cpu_total, 25.0);
+    assert_eq!(m.cpu_per_core, [25.0, 25.0]);
+    assert_eq!(m.hostname, "router");
+    assert_eq!(m.mem_total, 8_000_000 * 1024);
+    assert_eq!(m.mem_used, 2_000_000 * 1024);
+    assert_eq!(m.swap_used, 500_000 * 1024);
+    assert_eq!(m.uptime_secs, Some(3600));
+    assert_eq!(m.load_avg, Some((0.52, 0.61, 0.70)));
+    assert_eq!(m.process_count, Some(87));
+    assert_eq!(m.procs_blocked, Some(1));
+    let eth0 = m.networks.iter().find(|n| n.name == "eth0").unwrap();
+    assert_eq!((eth0.received, eth0.transmitted), (123_456, 65_432));
+    assert!(m.has_capability("io"));
+    assert!(!m.has_capability("hello"));
+}
+
+#[test]
+fn processes_get_cpu_shares_from_tick_deltas() {
+    let line = |pid: u32, name: &str, ticks: u64| {
+        format!("{pid} ({name}) S 1 {pid} {pid} 0 -1 4194560 100 0 0 0 {ticks} 0 0 0 20 0 1 0 500 10000000 256 18446744073709551615\n")
+    };
+    assert_eq!(
+        parse_proc_stats(&line(7, "tmux: server) x", 40)),
+        [(7, "tmux: server) x".to_string(), 40, 256 * 4096)]
+    );
+
+    let mut c = Collector::default();
+    let now = Instant::now();
+    let sample = |total: u64, a: u64, b: u64| {
+        output(&[
+            ("stat", format!("cpu  {total} 0 0 0 0 0 0 0\n").as_str()),
+            (
+                "procs",
+                format!("{}{}", line(1, "init", a), line(42, "dropbear", b)).as_str(),
+            ),
+        ])
+    };
+    c.reply("get_processes", &sample(1000, 10, 100), now);
+    let p: ProcessesPayload = serde_json::from_str(
+        &c.reply("get_processes", &sample(1200, 10, 150), now)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(p.process_count, 2);
+    let db = p.top_processes.iter().find(|r| r.pid == 42).unwrap();
+    assert_eq!(db.cpu_usage, 25.0);
+    assert_eq!(db.cpu_time_ms, 1500);
+    assert_eq!(db.mem_bytes, 256 * 4096);
+}
+
+#[test]
+fn disks_and_io_come_from_df_and_diskstats() {
+    let mut c = Collector::default();
+    let df = "Filesystem     1024-blocks    Used Available Capacity Mounted on\n\
+              /dev/mmcblk0p2    1000000  400000    600000      40% /\n\
+              tmpfs               50000       0     50000       0% /run\n\
+              /dev/mmcblk0p2    1000000  400000    600000      40% /var/log\n";
+    let disks: Vec<DiskInfo> = serde_json::from_str(
+        &c.reply("get_disks", &output(&[("df", df)]), Instant::now())
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(disks.len(), 1);
+    assert_eq!(disks[0].name, "/dev/mmcblk0p2");
+    assert_eq!(disks[0].available, 600_000 * 1024);
+
+    let stats = |sectors: u64, io_ms: u64| {
+        format!(
+            " 179 0 mmcblk0 10 0 {sectors} 5 20 0 {sectors} 9 0 {io_ms} 14\n \
+             179 2 mmcblk0p2 10 0 {sectors} 5 20 0 {sectors} 9 0 {io_ms} 14\n \
+             7 0 loop0 1 0 8 0 0 0 0 0 0 0 0\n"
+        )
+    };
+    let names: Vec<String> = parse_diskstats(&stats(0, 0))
+        .into_iter()
+        .map(|d| d.0)
+        .collect();
+    assert_eq!(names, ["mmcblk0"]);
+
+    let start = Instant::now();
+    let sample = |sectors: u64, io_ms: u64, iowait: u64| {
+        output(&[
+            (
+                "stat",
+                format!("cpu  100 0 0 100 {iowait} 0 0 0\nprocs_blocked 2\n").as_str(),
+            ),
+            ("diskstats", stats(sectors, io_ms).as_str()),
+            (
+                "pressure",
+                "some avg10=1.50 avg60=0.75 avg300=0.10 total=1234\n",
+            ),
+        ])
+    };
+    let first: IoReport =
+        serde_json::from_str(&c.reply("get_io", &sample(0, 0, 0), start).unwrap()).unwrap();
+    assert_eq!(first.interval_ms, None);
+    assert!(first.disks.is_empty());
+    let r: IoReport = serde_json::from_str(
+        &c.reply(
+            "get_io",
+            &sample(2048, 500, 100),
+            start + Duration::from_secs(1),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(r.interval_ms, Some(1000));
+    assert_eq!(r.iowait_pct, Some(100.0));
+    assert_eq!(r.procs_blocked, Some(2));
+    assert_eq!(r.pressure.unwrap().some_avg10, 1.5);
+    assert_eq!(r.disks[0].util_pct, 50.0);
+    assert_eq!(r.disks[0].read_bps, 2048 * 512);
+}
@@ -0,0 +1,196 @@
+//! Alert log: only transitions are recorded and they persist across restarts; threshold alerts
+//! are debounced by a minimum duration and a clear-below mark; sinks (bell, flash, status line)
+//! are picked per severity.
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::Terminal;
+use socktop::alerts::{
+    format_utc, AlertLog, AlertRules, Hysteresis, Severity, Sinks, Threshold, Transition,
+};
+use socktop::ui::alerts::draw_flash;
+
+#[test]
+fn records_transitions_and_reloads_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state/alerts.jsonl");
+    let mut log = AlertLog::open(&path);
+    assert!(log.set(100, "web1", "agent_lag", true, "agent slow"));
+    // Still firing: no new entry
+    assert!(!log.set(101, "web1", "agent_lag", true, "agent slower"));
+    assert!(!log.set(102, "web1", "disconnected", false, "ok"));
+    assert!(log.set(103, "web1", "agent_lag", false, "agent_lag cleared"));
+    let kinds: Vec<Transition> = log.events().map(|e| e.transition).collect();
+    assert_eq!(kinds, vec![Transition::Fired, Transition::Cleared]);
+
+    let reloaded = AlertLog::open(&path);
+    let events: Vec<_> = reloaded.events().cloned().collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].at, 100);
+    assert_eq!(events[0].message, "agent slow");
+    assert_eq!(events[1].transition, Transition::Cleared);
+}
+
+#[test]
+fn formats_utc_timestamps() {
+    assert_eq!(format_utc(0), "1970-01-01 00:00:00");
+    assert_eq!(format_utc(951_782_400), "2000-02-29 00:00:00");
+    assert_eq!(format_utc(1_700_000_000), "2023-11-14 22:13:20");
+}
+
+#[test]
+fn transitions_inside_maintenance_window_are_marked_silenced() {
+    let mut log = AlertLog::default();
+    log.silence_until(Some(200));
+    assert!(log.silenced_at(150));
+    assert!(!log.silenced_at(200));
+    log.set(150, "db", "disconnected", true, "lost connection");
+    log.set(250, "db", "disconnected", false, "disconnected cleared");
+    let silenced: Vec<bool> = log.events().map(|e| e.silenced).collect();
+    assert_eq!(silenced, vec![true, false]);
+}
+
+#[test]
+fn threshold_fires_only_after_the_minimum_duration() {
+    let rule = Threshold::new(90.0, 85.0, 120);
+    let mut h = Hysteresis::default();
+    assert!(!h.update(&rule, 0, Some(95.0)));
+    assert!(!h.update(&rule, 60, Some(97.0)));
+    // A dip below the limit restarts the clock
+    assert!(!h.update(&rule, 90, Some(89.0)));
+    assert!(!h.update(&rule, 100, Some(95.0)));
+    assert!(!h.update(&rule, 219, Some(95.0)));
+    assert!(h.update(&rule, 220, Some(95.0)));
+}
+
+#[test]
+fn threshold_clears_only_below_the_lower_mark() {
+    let rule = Threshold::new(90.0, 85.0, 0);
+    let mut h = Hysteresis::default();
+    assert!(h.update(&rule, 0, Some(91.0)));
+    // Hovering around the limit doesn't flap
+    assert!(h.update(&rule, 1, Some(89.0)));
+    assert!(h.update(&rule, 2, Some(91.0)));
+    assert!(h.update(&rule, 3, Some(86.0)));
+    assert!(!h.update(&rule, 4, Some(84.0)));
+    assert!(!h.update(&rule, 5, Some(89.0)));
+    // No value (e.g. swap disabled) clears as well
+    assert!(h.update(&rule, 6, Some(91.0)));
+    assert!(!h.update(&rule, 7, None));
+}
+
+#[test]
+fn rules_load_from_json_with_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("alerts.json");
+    assert_eq!(AlertRules::load_from(&path), AlertRules::default());
+
+    std::fs::write(
+        &path,
+        r#"{"mem": {"above": 80, "for_secs": 30}, "swap": null}"#,
+    )
+    .unwrap();
+    let rules = AlertRules::load_from(&path);
+    assert_eq!(rules.swap, None);
+    let mem = rules.mem.unwrap();
+    assert_eq!((mem.above, mem.for_secs), (80.0, 30));
+    // Without clear_below the alert clears at the limit itself
+    let mut h = Hysteresis::default();
+    assert!(!h.update(&mem, 0, Some(81.0)));
+    assert!(h.update(&mem, 30, Some(81.0)));
+    assert!(!h.update(&mem, 31, Some(79.0)));
+
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(AlertRules::load_from(&path), AlertRules::default());
+}
+
+#[test]
+fn sinks_are_chosen_per_severity() {
+    assert_eq!(Severity::of("disconnected"), Severity::Critical);
+    assert_eq!(Severity::of("mem_high"), Severity::Critical);
+    assert_eq!(Severity::of("swap_high"), Severity::Warning);
+    assert_eq!(Severity::of("clock_unsynced"), Severity::Warning);
+
+    // Defaults: critical alerts flash and stay on the status line, warnings stay quiet
+    let d = AlertRules::default().sinks;
+    assert_eq!(
+        d.for_severity(Severity::Critical),
+        Sinks {
+            bell: false,
+            flash: true,
+            status: true
+        }
+    );
+    assert_eq!(d.for_severity(Severity::Warning), Sinks::default());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("alerts.json");
+    std::fs::write(
+        &path,
+        r#"{"sinks": {"critical": {"bell": true}, "warning": {"status": true}}}"#,
+    )
+    .unwrap();
+    let rules = AlertRules::load_from(&path);
+    // Thresholds keep their defaults next to a sinks section
+    assert_eq!(rules.mem, AlertRules::default().mem);
+    let critical = rules.sinks.for_severity(Severity::Critical);
+    assert!(critical.bell && !critical.flash && !critical.status);
+    let warning = rules.sinks.for_severity(Severity::Warning);
+    assert!(warning.status && !warning.bell && !warning.flash);
+}
+
+#[test]
+fn flash_borders_a_panel_and_colors_a_one_line_header() {
+    let mut term = Terminal::new(TestBackend::new(20, 6)).unwrap();
+    term.draw(|f| {
+        draw_flash(f, Rect::new(0, 0, 20, 1), Severity::Warning);
+        draw_flash(f, Rect::new(0, 1, 20, 5), Severity::Critical);
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    assert_eq!(buf[(5, 0)].bg, Color::Yellow);
+    assert_eq!(buf[(0, 1)].symbol(), "┏");
+    assert_eq!(buf[(0, 1)].fg, Color::Red);
+    // Inside the border is left alone
+    assert_eq!(buf[(5, 3)].symbol(), " ");
+    assert_eq!(buf[(5, 3)].fg, Color::Reset);
+}
+
+#[test]
+fn cpu_disk_and_temp_rules_default_on_and_can_come_from_a_profile() {
+    let d = AlertRules::default();
+    assert_eq!(d.cpu, Some(Threshold::new(90.0, 80.0, 30)));
+    assert_eq!(d.disk.map(|t| t.above), Some(95.0));
+    assert_eq!(d.temp.map(|t| t.above), Some(85.0));
+    for key in ["cpu_high", "disk_full", "temp_high"] {
+        assert_eq!(Severity::of(key), Severity::Critical);
+    }
+
+    // CPU has to stay busy for 30s before it fires
+    let cpu = d.cpu.unwrap();
+    let mut h = Hysteresis::default();
+    assert!(!h.update(&cpu, 100, Some(97.0)));
+    assert!(!h.update(&cpu, 129, Some(97.0)));
+    assert!(h.update(&cpu, 130, Some(97.0)));
+    assert!(h.update(&cpu, 131, Some(85.0)));
+    assert!(!h.update(&cpu, 132, Some(79.0)));
+
+    let entry: socktop::profiles::ProfileEntry = serde_json::from_str(
+        r#"{"url": "ws://nas:3000/ws",
+            "alerts": {"disk": {"above": 90}, "temp": null, "sinks": {"critical": {"bell": true}}}}"#,
+    )
+    .unwrap();
+    let rules = entry.alerts.unwrap();
+    assert_eq!(rules.disk.map(|t| t.above), Some(90.0));
+    assert_eq!(rules.temp, None);
+    assert_eq!(rules.cpu, d.cpu);
+    assert!(rules.sinks.for_severity(Severity::Critical).bell);
+    // Saved back unchanged; profiles without the section don't grow one
+    let saved = serde_json::to_value(&entry).unwrap();
+    assert_eq!(saved["alerts"]["disk"]["above"], 90.0);
+    let plain = socktop::profiles::ProfileEntry::default();
+    assert!(serde_json::to_value(&plain)
+        .unwrap()
+        .get("alerts")
+        .is_none());
+}
@@ -0,0 +1,31 @@
+//! --max-bandwidth parsing and interval stretching.
+use socktop::budget::{parse_bandwidth, BandwidthBudget};
+use std::time::Duration;
+
+#[test]
+fn parses_bits_and_bytes() {
+    assert_eq!(parse_bandwidth("10kbps"), Some(1250));
+    assert_eq!(parse_bandwidth("1Mbps"), Some(125_000));
+    assert_eq!(parse_bandwidth("64KB/s"), Some(65_536));
+    assert_eq!(parse_bandwidth("2000"), Some(2000));
+    assert_eq!(parse_bandwidth("fast"), None);
+    assert_eq!(parse_bandwidth("10 furlongs"), None);
+}
+
+#[test]
+fn intervals_stretch_over_budget_and_recover_under_it() {
+    let base = [
+        Duration::from_millis(500),
+        Duration::from_secs(2),
+        Duration::from_secs(5),
+    ];
+    let mut b = BandwidthBudget::new(1000, base);
+    let over = b.adjust(4000.0);
+    assert!(over[0] > base[0] && over[1] > base[1] && over[2] > base[2]);
+    // Sustained low usage shrinks back to (but never below) the configured intervals
+    let mut last = over;
+    for _ in 0..200 {
+        last = b.adjust(0.0);
+    }
+    assert_eq!(last, base);
+}
@@ -0,0 +1,69 @@
+//! Tokens go in an `Authorization: Bearer` header instead of the URL.
+use futures_util::{SinkExt, StreamExt};
+use socktop::profiles::ProfileEntry;
+use socktop::ws::connect_with_token;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_tungstenite::accept_hdr_async;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+// Reports the Authorization header and the request target of the one client it accepts.
+// The handshake callback's error type is tungstenite's, however large clippy finds it.
+#[allow(clippy::result_large_err)]
+async fn agent() -> (String, oneshot::Receiver<(Option<String>, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let (sock, _) = listener.accept().await.unwrap();
+        let mut seen = None;
+        let mut ws = accept_hdr_async(sock, |req: &Request, resp: Response| {
+            let auth = req
+                .headers()
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            seen = Some((auth, req.uri().to_string()));
+            Ok(resp)
+        })
+        .await
+        .unwrap();
+        let _ = tx.send(seen.unwrap());
+        while let Some(Ok(Message::Text(_))) = ws.next().await {
+            ws.send(Message::Text("{}".into())).await.unwrap();
+        }
+    });
+    (url, rx)
+}
+
+#[tokio::test]
+async fn token_is_sent_as_a_bearer_header() {
+    let (url, seen) = agent().await;
+    let _ws = connect_with_token(&url, None, Some("s3cret"))
+        .await
+        .unwrap();
+    let (auth, target) = seen.await.unwrap();
+    assert_eq!(auth.as_deref(), Some("Bearer s3cret"));
+    assert!(!target.contains("s3cret"));
+}
+
+#[tokio::test]
+async fn no_token_no_header() {
+    let (url, seen) = agent().await;
+    let _ws = connect_with_token(&url, None, None).await.unwrap();
+    assert_eq!(seen.await.unwrap().0, None);
+}
+
+#[test]
+fn profile_token_round_trips_and_is_omitted_when_unset() {
+    let entry: ProfileEntry =
+        serde_json::from_str(r#"{"url":"ws://pi4:3000/ws","token":"s3cret"}"#).unwrap();
+    assert_eq!(entry.token.as_deref(), Some("s3cret"));
+    let out = serde_json::to_value(ProfileEntry {
+        url: "ws://pi4:3000/ws".into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(out.get("token").is_none());
+}
@@ -0,0 +1,15 @@
+//! Crosshair column for chart inspection mode (newest sample is the rightmost drawn point).
+use socktop::history::crosshair_column;
+
+#[test]
+fn crosshair_tracks_samples_back_from_newest() {
+    // Fewer samples than columns: data is left-aligned
+    assert_eq!(crosshair_column(5, 20, 0), Some(4));
+    assert_eq!(crosshair_column(5, 20, 4), Some(0));
+    assert_eq!(crosshair_column(5, 20, 5), None);
+    // Full chart: newest sample sits in the last column
+    assert_eq!(crosshair_column(600, 80, 0), Some(79));
+    assert_eq!(crosshair_column(600, 80, 79), Some(0));
+    assert_eq!(crosshair_column(600, 80, 80), None);
+    assert_eq!(crosshair_column(0, 80, 0), None);
+}
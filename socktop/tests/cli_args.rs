@@ -73,3 +73,43 @@ fn test_tlc_ca_arg_long_and_short_parsed() {
     );
     assert!(text3.contains("Usage:"));
 }
+
+#[test]
+fn test_unexpected_argument_exits_with_usage_code_and_json() {
+    let out = Command::new(env!("CARGO_BIN_EXE_socktop"))
+        .args(["--error-format", "json", "ws://a/ws", "ws://b/ws"])
+        .output()
+        .expect("run socktop");
+    assert_eq!(out.status.code(), Some(2), "usage errors exit with 2");
+    let err = String::from_utf8_lossy(&out.stderr);
+    let v: serde_json::Value = serde_json::from_str(err.trim()).expect("json error line");
+    assert_eq!(v["error"], "usage");
+    assert_eq!(v["code"], 2);
+}
+
+#[test]
+fn test_unexpected_argument_usage_lists_every_option() {
+    let out = Command::new(env!("CARGO_BIN_EXE_socktop"))
+        .args(["ws://a/ws", "ws://b/ws"])
+        .output()
+        .expect("run socktop");
+    let err = String::from_utf8_lossy(&out.stderr);
+    let help = Command::new(env!("CARGO_BIN_EXE_socktop"))
+        .arg("--help")
+        .output()
+        .expect("run socktop --help");
+    let help = String::from_utf8_lossy(&help.stderr);
+    assert!(err.contains("Unexpected argument."), "{err}");
+    // Same option list as --help
+    assert!(err.contains(help.trim()), "{err}");
+    for flag in [
+        "--token",
+        "--tls-cert",
+        "--tls-key",
+        "--record",
+        "--replay",
+        "--once",
+    ] {
+        assert!(err.contains(flag), "usage misses {flag}: {err}");
+    }
+}
@@ -0,0 +1,42 @@
+//! `--tls-cert`/`--tls-key`: the client certificate for agents with `--require-client-cert`.
+use rustls::{ClientConfig, RootCertStore};
+use socktop::profiles::ProfileEntry;
+use socktop::ws::with_client_cert;
+
+fn builder() -> rustls::ConfigBuilder<ClientConfig, rustls::client::WantsClientCert> {
+    ClientConfig::builder().with_root_certificates(RootCertStore::empty())
+}
+
+// One test: the settings come from process-wide variables
+#[test]
+fn cert_and_key_go_together_and_must_exist() {
+    std::env::remove_var("SOCKTOP_TLS_CERT");
+    std::env::remove_var("SOCKTOP_TLS_KEY");
+    assert!(with_client_cert(builder()).is_ok());
+
+    std::env::set_var("SOCKTOP_TLS_CERT", "/nonexistent/client.pem");
+    let err = with_client_cert(builder()).unwrap_err();
+    assert!(err.to_string().contains("together"));
+
+    std::env::set_var("SOCKTOP_TLS_KEY", "/nonexistent/client.key");
+    assert!(with_client_cert(builder()).is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let empty = dir.path().join("client.pem");
+    std::fs::write(&empty, "").unwrap();
+    std::env::set_var("SOCKTOP_TLS_CERT", &empty);
+    let err = with_client_cert(builder()).unwrap_err();
+    assert!(err.to_string().contains("no certificate"));
+}
+
+#[test]
+fn profile_keeps_cert_paths() {
+    let entry: ProfileEntry = serde_json::from_str(
+        r#"{"url":"wss://pi4:8443/ws","tls_cert":"/home/me/pi4.pem","tls_key":"/home/me/pi4.key"}"#,
+    )
+    .unwrap();
+    assert_eq!(entry.tls_cert.as_deref(), Some("/home/me/pi4.pem"));
+    assert_eq!(entry.tls_key.as_deref(), Some("/home/me/pi4.key"));
+    let out = serde_json::to_value(ProfileEntry::default()).unwrap();
+    assert!(out.get("tls_cert").is_none() && out.get("tls_key").is_none());
+}
@@ -0,0 +1,63 @@
+//! Clipboard mechanism selection and the OSC 52 escape.
+use socktop::clipboard::{detect, osc52, process_line, Backend};
+use socktop::types::ProcessInfo;
+
+fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |k| {
+        vars.iter()
+            .find(|(n, _)| *n == k)
+            .map(|(_, v)| v.to_string())
+    }
+}
+
+#[test]
+fn local_sessions_use_the_display_tools() {
+    let all = |_: &str| true;
+    assert_eq!(
+        detect(
+            env(&[("WAYLAND_DISPLAY", "wayland-0"), ("DISPLAY", ":0")]),
+            all
+        ),
+        Backend::WlCopy
+    );
+    assert_eq!(detect(env(&[("DISPLAY", ":0")]), all), Backend::Xclip);
+    assert_eq!(
+        detect(env(&[("DISPLAY", ":0")]), |c| c == "xsel"),
+        Backend::Xsel
+    );
+    // A display without any tool, or no display at all
+    assert_eq!(detect(env(&[("DISPLAY", ":0")]), |_| false), Backend::Osc52);
+    assert_eq!(detect(env(&[]), all), Backend::Osc52);
+}
+
+#[test]
+fn ssh_sessions_use_osc52_unless_forced() {
+    let forwarded = [
+        ("SSH_CONNECTION", "10.0.0.2 5022 10.0.0.5 22"),
+        ("DISPLAY", "localhost:10.0"),
+    ];
+    assert_eq!(detect(env(&forwarded), |_| true), Backend::Osc52);
+    let forced = [("SSH_TTY", "/dev/pts/3"), ("SOCKTOP_CLIPBOARD", "xclip")];
+    assert_eq!(detect(env(&forced), |_| true), Backend::Xclip);
+    // An unknown name is ignored
+    let bogus = [("SOCKTOP_CLIPBOARD", "pbcopy"), ("DISPLAY", ":0")];
+    assert_eq!(detect(env(&bogus), |_| true), Backend::Xclip);
+}
+
+#[test]
+fn osc52_encodes_and_wraps_for_tmux() {
+    assert_eq!(osc52("hi", false), "\x1b]52;c;aGk=\x07");
+    assert_eq!(osc52("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+}
+
+#[test]
+fn process_line_is_tab_separated() {
+    let p = ProcessInfo {
+        pid: 42,
+        name: "nginx".into(),
+        cpu_usage: 3.5,
+        mem_bytes: 4096,
+        cpu_time_ms: 0,
+    };
+    assert_eq!(process_line(&p), "42\tnginx\t3.5\t4096");
+}
@@ -0,0 +1,34 @@
+//! Header collector glyphs come from the agent's capability list.
+use socktop::types::Metrics;
+
+fn metrics(caps: &[&str]) -> Metrics {
+    let caps = serde_json::to_string(caps).unwrap();
+    serde_json::from_str(&format!(
+        r#"{{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[],"gpus":null,"capabilities":{caps}}}"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn collectors_report_enabled_and_disabled() {
+    let m = metrics(&["processes_top", "collectors", "gpu", "history"]);
+    assert_eq!(
+        m.collector_status(),
+        Some(vec![
+            ("GPU", true),
+            ("TEMP", false),
+            ("UPD", false),
+            ("HIST", true)
+        ])
+    );
+}
+
+#[test]
+fn older_agents_show_no_collector_glyphs() {
+    assert_eq!(
+        metrics(&["processes_top", "history"]).collector_status(),
+        None
+    );
+}
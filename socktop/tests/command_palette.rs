@@ -0,0 +1,75 @@
+//! Ctrl-P palette: fuzzy matching over action names, keyboard navigation, and the shared
+//! key-to-action map.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use socktop::keymap::{action_for, Action};
+use socktop::palette::{fuzzy_score, Palette, PaletteOutcome};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn type_str(p: &mut Palette, s: &str) {
+    for c in s.chars() {
+        assert_eq!(p.handle_key(key(KeyCode::Char(c))), PaletteOutcome::Open);
+    }
+}
+
+#[test]
+fn fuzzy_matches_subsequences_and_prefers_word_starts() {
+    assert!(fuzzy_score("srtmem", "Sort processes by memory").is_some());
+    assert!(fuzzy_score("zzz", "Sort processes by memory").is_none());
+    assert_eq!(fuzzy_score("", "Quit"), Some(0));
+    let word_start = fuzzy_score("mem", "Sort processes by memory").unwrap();
+    let scattered = fuzzy_score("mem", "Toggle maintenance window").unwrap_or(0);
+    assert!(word_start > scattered);
+}
+
+#[test]
+fn typing_filters_and_enter_runs_the_best_match() {
+    let mut p = Palette::default();
+    assert_eq!(p.matches().len(), Action::all().len());
+    type_str(&mut p, "sort mem");
+    assert_eq!(p.matches()[0], Action::SortByMem);
+    assert_eq!(
+        p.handle_key(key(KeyCode::Enter)),
+        PaletteOutcome::Run(Action::SortByMem)
+    );
+}
+
+#[test]
+fn arrows_move_within_matches_and_esc_closes() {
+    let mut p = Palette::default();
+    type_str(&mut p, "interval");
+    let n = p.matches().len();
+    assert_eq!(n, 5);
+    for _ in 0..10 {
+        p.handle_key(key(KeyCode::Down));
+    }
+    assert_eq!(p.selected, n - 1);
+    p.handle_key(key(KeyCode::Backspace));
+    assert_eq!(p.selected, 0);
+    assert_eq!(p.handle_key(key(KeyCode::Esc)), PaletteOutcome::Close);
+
+    let mut empty = Palette::default();
+    type_str(&mut empty, "qqqq");
+    assert!(empty.matches().is_empty());
+    assert_eq!(empty.handle_key(key(KeyCode::Enter)), PaletteOutcome::Close);
+}
+
+#[test]
+fn keys_map_to_the_same_actions_as_the_palette() {
+    assert_eq!(action_for(key(KeyCode::Char('q'))), Some(Action::Quit));
+    assert_eq!(
+        action_for(key(KeyCode::Char('D'))),
+        Some(Action::ToggleDebug)
+    );
+    assert_eq!(
+        action_for(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+        Some(Action::OpenPalette)
+    );
+    assert_eq!(action_for(key(KeyCode::Char('p'))), None);
+    // Every palette entry has a readable, unique label
+    let labels: std::collections::HashSet<String> =
+        Action::all().iter().map(Action::label).collect();
+    assert_eq!(labels.len(), Action::all().len());
+}
@@ -0,0 +1,14 @@
+//! Offline cores keep their per-core row (row i == CPU id i).
+use socktop::history::stable_core_rows;
+
+#[test]
+fn offline_cores_are_reinserted_in_place() {
+    assert_eq!(stable_core_rows(&[10.0, 20.0], &[]), vec![10.0, 20.0]);
+    // cpu1 and cpu3 offline out of 0..=4
+    assert_eq!(
+        stable_core_rows(&[10.0, 30.0, 50.0], &[1, 3]),
+        vec![10.0, 0.0, 30.0, 0.0, 50.0]
+    );
+    // Trailing offline core
+    assert_eq!(stable_core_rows(&[10.0], &[1]), vec![10.0, 0.0]);
+}
@@ -0,0 +1,57 @@
+//! Counters that go backwards are a reset (or a 32-bit wrap), not a zero/negative rate.
+use std::time::{Duration, Instant};
+
+use socktop::rate::{counter_delta, RateCounter};
+
+#[test]
+fn backwards_totals_are_reported_as_reset() {
+    assert_eq!(counter_delta(1_000, 4_000), Some(3_000));
+    assert_eq!(counter_delta(4_000, 4_000), Some(0));
+    // Agent restarted and its totals started over
+    assert_eq!(counter_delta(9_000_000, 1_200), None);
+}
+
+#[test]
+fn wrapped_32bit_counter_keeps_its_delta() {
+    assert_eq!(counter_delta(u32::MAX as u64 - 99, 100), Some(200));
+    assert_eq!(counter_delta(u64::MAX - 99, 100), None);
+}
+
+#[test]
+fn rate_counter_rebaselines_after_reset() {
+    let t0 = Instant::now();
+    let mut r = RateCounter::new();
+    assert_eq!(r.update(10_240, t0), None);
+    assert_eq!(
+        r.update(30_720, t0 + Duration::from_secs(2)),
+        Some(10_240.0)
+    );
+    assert_eq!(r.update(512, t0 + Duration::from_secs(3)), None);
+    assert_eq!(r.update(2_560, t0 + Duration::from_secs(4)), Some(2_048.0));
+}
+
+#[test]
+fn stamped_rates_follow_the_agent_clock_not_arrival() {
+    let t0 = Instant::now();
+    let mut r = RateCounter::new();
+    assert_eq!(r.update_stamped(0, Some(1_000), t0), None);
+    // Sampled 1 s apart on the agent, but the first frame was held up: they arrive 0.1 s apart
+    assert_eq!(
+        r.update_stamped(4_096, Some(2_000), t0 + Duration::from_millis(100)),
+        Some(4_096.0)
+    );
+    // The same (cached) sample again: no new rate, baseline kept
+    assert_eq!(
+        r.update_stamped(4_096, Some(2_000), t0 + Duration::from_millis(600)),
+        None
+    );
+    assert_eq!(
+        r.update_stamped(6_144, Some(2_500), t0 + Duration::from_secs(5)),
+        Some(4_096.0)
+    );
+    // Agent restarted: its clock started over, so arrival times take over
+    assert_eq!(
+        r.update_stamped(8_192, Some(10), t0 + Duration::from_secs(7)),
+        Some(1_024.0)
+    );
+}
@@ -0,0 +1,50 @@
+//! Crash reports: panic details, the agent URL without its token, the last snapshot, and a
+//! bounded number of report files.
+use socktop::crash::{redact_token, report, write_report};
+
+#[test]
+fn tokens_are_redacted_from_the_agent_url() {
+    assert_eq!(
+        redact_token("wss://h:8443/ws?token=s3cret&upstream=leaf"),
+        "wss://h:8443/ws?token=REDACTED&upstream=leaf"
+    );
+    assert_eq!(redact_token("ws://h:3000/ws"), "ws://h:3000/ws");
+}
+
+#[test]
+fn report_carries_the_panic_and_the_last_snapshot() {
+    let text = report(
+        "index out of bounds",
+        Some("src/ui/cpu.rs:10:5"),
+        "0: main",
+        Some("ws://h:3000/ws?token=abc"),
+        Some((86_400, r#"{"hostname":"h","cpu_total":12.5}"#)),
+        86_460,
+    );
+    assert!(text.contains("panic: index out of bounds\n"));
+    assert!(text.contains("location: src/ui/cpu.rs:10:5\n"));
+    assert!(text.contains("agent: ws://h:3000/ws?token=REDACTED\n"));
+    assert!(!text.contains("abc"));
+    assert!(text.contains("\"hostname\": \"h\""));
+    assert!(text.contains("1970-01-02"));
+
+    let text = report("boom", None, "", None, None, 0);
+    assert!(text.contains("no metrics snapshot received yet"));
+}
+
+#[test]
+fn old_reports_are_pruned() {
+    let dir = tempfile::tempdir().unwrap();
+    for t in 1..=12u64 {
+        write_report(dir.path(), t, "x").unwrap();
+    }
+    let mut names: Vec<String> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 10);
+    assert!(!names.contains(&"crash-1.txt".to_string()));
+    assert!(!names.contains(&"crash-2.txt".to_string()));
+    assert!(names.contains(&"crash-12.txt".to_string()));
+}
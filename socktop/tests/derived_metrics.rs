@@ -0,0 +1,57 @@
+//! Derived metric expressions: parsing, evaluation and config loading.
+use socktop::derived::{load_from, Expr, PanelKind};
+use std::collections::HashMap;
+
+fn vars() -> HashMap<&'static str, f64> {
+    HashMap::from([
+        ("mem_used", 2.0),
+        ("mem_total", 8.0),
+        ("rx", 10.0),
+        ("tx", 5.0),
+    ])
+}
+
+#[test]
+fn evaluates_arithmetic_with_precedence_and_functions() {
+    let eval = |s: &str| Expr::parse(s).unwrap().eval(&vars());
+    assert_eq!(eval("mem_used/mem_total*100"), Some(25.0));
+    assert_eq!(eval("rx + tx * 2"), Some(20.0));
+    assert_eq!(eval("(rx + tx) * 2"), Some(30.0));
+    assert_eq!(eval("-rx + 1"), Some(-9.0));
+    assert_eq!(eval("max(rx, tx, 7)"), Some(10.0));
+    assert_eq!(eval("abs(tx - rx)"), Some(5.0));
+    // Unknown variable, division by zero, unknown function
+    assert_eq!(eval("cpu_temp + 1"), None);
+    assert_eq!(eval("rx / 0"), None);
+    assert_eq!(eval("sqrt(rx)"), None);
+}
+
+#[test]
+fn rejects_malformed_expressions() {
+    assert!(Expr::parse("rx +").is_err());
+    assert!(Expr::parse("(rx").is_err());
+    assert!(Expr::parse("rx $ tx").is_err());
+}
+
+#[test]
+fn loads_config_and_skips_invalid_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("derived.json");
+    std::fs::write(
+        &path,
+        r#"[
+            {"name": "RAM %", "expr": "mem_used/mem_total*100", "kind": "gauge", "max": 100},
+            {"name": "Net", "expr": "rx+tx"},
+            {"name": "Broken", "expr": "rx +"}
+        ]"#,
+    )
+    .unwrap();
+    let mut panels = load_from(&path);
+    assert_eq!(panels.len(), 2);
+    assert_eq!(panels[0].def.kind, PanelKind::Gauge);
+    assert_eq!(panels[1].def.kind, PanelKind::Sparkline);
+    panels[1].update(&vars());
+    assert_eq!(panels[1].last, Some(15.0));
+    assert_eq!(panels[1].hist.back(), Some(&15));
+    assert!(load_from(&dir.path().join("missing.json")).is_empty());
+}
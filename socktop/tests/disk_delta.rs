@@ -0,0 +1,56 @@
+//! Applying `get_disks?delta` replies to the client's disk list.
+use serde_json::json;
+use socktop::disk_mirror::DiskMirror;
+use socktop::types::DisksDelta;
+
+fn reply(v: serde_json::Value) -> DisksDelta {
+    serde_json::from_value(v).unwrap()
+}
+
+fn names(m: &DiskMirror) -> Vec<(&str, u64)> {
+    m.disks()
+        .iter()
+        .map(|d| (d.name.as_str(), d.available))
+        .collect()
+}
+
+#[test]
+fn deltas_update_in_place_append_and_remove() {
+    let mut m = DiskMirror::default();
+    assert_eq!(m.seq(), 0);
+    m.apply(reply(json!({"seq": 1, "full": true, "disks": [
+        {"name": "sda1", "total": 100, "available": 50},
+        {"name": "sdb1", "total": 200, "available": 20},
+    ]})));
+    assert_eq!(m.seq(), 1);
+
+    m.apply(reply(json!({"seq": 2, "full": false, "disks": [
+        {"name": "sdb1", "total": 200, "available": 19},
+        {"name": "usb0", "total": 32, "available": 30},
+    ]})));
+    assert_eq!(names(&m), [("sda1", 50), ("sdb1", 19), ("usb0", 30)]);
+
+    // Empty delta: nothing changes but the sequence number
+    m.apply(reply(json!({"seq": 3, "full": false, "disks": []})));
+    assert_eq!(m.seq(), 3);
+    assert_eq!(m.disks().len(), 3);
+
+    m.apply(reply(
+        json!({"seq": 4, "full": false, "disks": [], "removed": ["sda1"]}),
+    ));
+    assert_eq!(names(&m), [("sdb1", 19), ("usb0", 30)]);
+}
+
+#[test]
+fn full_reply_replaces_everything() {
+    let mut m = DiskMirror::default();
+    m.apply(reply(json!({"seq": 5, "full": false, "disks": [
+        {"name": "stale", "total": 1, "available": 1},
+    ]})));
+    // New session on the agent (or a lost reply): it starts over with a full list
+    m.apply(reply(json!({"seq": 1, "full": true, "disks": [
+        {"name": "nvme0n1p2", "total": 500, "available": 200},
+    ]})));
+    assert_eq!(names(&m), [("nvme0n1p2", 200)]);
+    assert_eq!(m.seq(), 1);
+}
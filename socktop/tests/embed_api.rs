@@ -0,0 +1,61 @@
+//! Embedding API: `connect` plus `subscribe` against a minimal WebSocket agent.
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use socktop::api::connect;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+fn metrics(host: &str, cpu: f32) -> String {
+    format!(
+        r#"{{"cpu_total":{cpu},"cpu_per_core":[{cpu}],"mem_total":100,"mem_used":40,
+            "swap_total":0,"swap_used":0,"hostname":"{host}","cpu_temp_c":null,
+            "disks":[],"networks":[],"top_processes":[],"gpus":null}}"#
+    )
+}
+
+// One connection answering `samples` get_metrics requests, then gone (no new connections)
+async fn fake_agent(samples: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (sock, _) = listener.accept().await.unwrap();
+        drop(listener);
+        let mut ws = accept_async(sock).await.unwrap();
+        for i in 0..samples {
+            match ws.next().await {
+                Some(Ok(Message::Text(cmd))) if cmd == "get_metrics" => {
+                    let reply = metrics("edge", 10.0 * (i + 1) as f32);
+                    ws.send(Message::Text(reply)).await.unwrap();
+                }
+                _ => return,
+            }
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn subscription_yields_samples_until_the_agent_is_gone() {
+    let url = fake_agent(2).await;
+    let mut conn = connect(&url, None).await.expect("connects");
+    let m = conn.metrics().await.expect("one sample");
+    assert_eq!(m.hostname, "edge");
+    assert_eq!(m.cpu_total, 10.0);
+
+    let samples: Vec<f32> = conn
+        .subscribe(Duration::from_millis(10))
+        .map(|m| m.cpu_total)
+        .collect()
+        .await;
+    // The reconnect after the second reply finds nothing listening, which ends the stream
+    assert_eq!(samples, [20.0]);
+}
+
+#[tokio::test]
+async fn connect_reports_an_unreachable_agent() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    drop(listener);
+    assert!(connect(&url, None).await.is_err());
+}
@@ -0,0 +1,133 @@
+//! Fleet snapshot: group filtering, per-host rows and the CSV/JSON report shapes.
+use serde_json::json;
+use socktop::fleet::{members, snapshot, to_csv, SnapshotRow};
+use socktop::profiles::ProfileEntry;
+use socktop::types::{DiskInfo, Metrics};
+use std::collections::BTreeMap;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+fn entry(url: &str, groups: &[&str]) -> ProfileEntry {
+    ProfileEntry {
+        url: url.into(),
+        groups: groups.iter().map(|g| g.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn sample() -> (Metrics, Vec<DiskInfo>) {
+    let m: Metrics = serde_json::from_value(json!({
+        "cpu_total": 37.46,
+        "cpu_per_core": [30.0, 40.0, 35.0, 45.0],
+        "mem_total": 16 * GB,
+        "mem_used": 4 * GB,
+        "swap_total": 0,
+        "swap_used": 0,
+        "hostname": "web-1",
+        "cpu_temp_c": null,
+        "disks": [],
+        "networks": [],
+        "top_processes": [],
+        "gpus": null,
+        "process_count": 212,
+    }))
+    .unwrap();
+    let disks = serde_json::from_value(json!([
+        { "name": "/dev/sda1", "total": 100 * GB, "available": 25 * GB },
+        { "name": "/dev/sdb1", "total": 100 * GB, "available": 75 * GB },
+    ]))
+    .unwrap();
+    (m, disks)
+}
+
+#[test]
+fn group_selects_only_tagged_profiles() {
+    let mut p = BTreeMap::new();
+    p.insert("a".to_string(), entry("ws://a:3000/ws", &["prod"]));
+    p.insert("b".to_string(), entry("ws://b:3000/ws", &["staging"]));
+    p.insert("c".to_string(), entry("ws://c:3000/ws", &["prod", "db"]));
+
+    let names = |g| {
+        members(&p, g)
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(Some("prod")), ["a", "c"]);
+    assert_eq!(names(None), ["a", "b", "c"]);
+    assert!(names(Some("nope")).is_empty());
+}
+
+#[test]
+fn groups_round_trip_and_default_to_empty() {
+    let e: ProfileEntry = serde_json::from_str(r#"{"url":"ws://x:3000/ws"}"#).unwrap();
+    assert!(e.groups.is_empty());
+    assert!(!serde_json::to_string(&e).unwrap().contains("groups"));
+
+    let e = entry("ws://x:3000/ws", &["prod"]);
+    let back: ProfileEntry = serde_json::from_str(&serde_json::to_string(&e).unwrap()).unwrap();
+    assert_eq!(back.groups, ["prod"]);
+}
+
+#[test]
+fn row_sums_disks_and_rounds_percentages() {
+    let (m, d) = sample();
+    let r = SnapshotRow::from_sample("web", &m, &d);
+    assert!(r.ok);
+    assert_eq!(r.hostname, "web-1");
+    assert_eq!(r.cores, 4);
+    assert_eq!(r.cpu_pct, 37.5);
+    assert_eq!(r.mem_pct, 25.0);
+    assert_eq!(r.disk_total, 200 * GB);
+    assert_eq!(r.disk_used, 100 * GB);
+    assert_eq!(r.disk_pct, 50.0);
+    assert_eq!(r.processes, 212);
+}
+
+#[test]
+fn csv_has_header_and_quotes_errors() {
+    let (m, d) = sample();
+    let rows = vec![
+        SnapshotRow::from_sample("web", &m, &d),
+        SnapshotRow::failed(
+            "db",
+            "connect ws://db:3000/ws: refused, \"os error 111\"".into(),
+        ),
+    ];
+    let csv = to_csv(&rows);
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("profile,ok,error,hostname,"));
+    assert!(lines[1].starts_with("web,true,,web-1,4,37.5,"));
+    assert!(lines[2]
+        .starts_with("db,false,\"connect ws://db:3000/ws: refused, \"\"os error 111\"\"\","));
+    // Every row has the header's column count (the quoted comma doesn't split)
+    assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+}
+
+#[test]
+fn json_omits_error_on_success() {
+    let (m, d) = sample();
+    let v = serde_json::to_value(vec![
+        SnapshotRow::from_sample("web", &m, &d),
+        SnapshotRow::failed("db", "timed out".into()),
+    ])
+    .unwrap();
+    assert!(v[0].get("error").is_none());
+    assert_eq!(v[1]["error"], "timed out");
+    assert_eq!(v[1]["ok"], false);
+}
+
+#[tokio::test]
+async fn unreachable_hosts_become_error_rows_in_order() {
+    // Port 9 on localhost: nothing listens, so connect fails fast
+    let rows = snapshot(vec![
+        ("x".into(), entry("ws://127.0.0.1:9/ws", &[])),
+        ("y".into(), entry("not a url", &[])),
+    ])
+    .await;
+    assert_eq!(rows.len(), 2);
+    assert_eq!((rows[0].profile.as_str(), rows[0].ok), ("x", false));
+    assert_eq!((rows[1].profile.as_str(), rows[1].ok), ("y", false));
+    assert!(rows.iter().all(|r| r.error.is_some()));
+}
@@ -0,0 +1,39 @@
+//! Unfocused panes redraw (and optionally poll) less often; focus restores the normal rate.
+use std::time::{Duration, Instant};
+
+use socktop::focus::{FocusThrottle, UNFOCUSED_REDRAW};
+
+#[test]
+fn unfocused_redraws_are_spaced_out_until_focus_returns() {
+    let t0 = Instant::now();
+    let mut ft = FocusThrottle::new(None);
+    assert!(ft.draw_due(t0));
+    ft.set_focused(false);
+    ft.drawn(t0);
+    assert!(!ft.draw_due(t0 + Duration::from_millis(500)));
+    assert!(ft.draw_due(t0 + UNFOCUSED_REDRAW));
+    // Polling keeps its normal rate unless --unfocused-poll-ms is set
+    ft.fetched(t0);
+    assert!(ft.fetch_due(t0));
+
+    ft.set_focused(true);
+    assert!(ft.draw_due(t0 + Duration::from_millis(1)));
+}
+
+#[test]
+fn unfocused_poll_interval_applies_only_while_unfocused() {
+    let t0 = Instant::now();
+    let mut ft = FocusThrottle::new(Some(Duration::from_secs(10)));
+    ft.fetched(t0);
+    assert!(ft.fetch_due(t0 + Duration::from_millis(1)));
+
+    ft.set_focused(false);
+    ft.fetched(t0);
+    assert!(!ft.fetch_due(t0 + Duration::from_secs(9)));
+    assert!(ft.fetch_due(t0 + Duration::from_secs(10)));
+
+    // Regaining focus fetches right away
+    ft.fetched(t0);
+    ft.set_focused(true);
+    assert!(ft.fetch_due(t0));
+}
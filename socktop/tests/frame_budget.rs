@@ -0,0 +1,44 @@
+//! Slow frames thin out per-core charts; a run of fast frames restores detail.
+use std::time::Duration;
+
+use socktop::frame_budget::FrameBudget;
+
+const MS: Duration = Duration::from_millis(1);
+
+#[test]
+fn slow_frames_reduce_detail_up_to_a_limit() {
+    let mut fb = FrameBudget::new(40 * MS);
+    fb.record(10 * MS);
+    assert_eq!(fb.divisor(), 1);
+    fb.record(90 * MS);
+    assert_eq!(fb.divisor(), 2);
+    for _ in 0..10 {
+        fb.record(90 * MS);
+    }
+    assert_eq!(fb.divisor(), 8);
+    // Only actual level changes are logged, and the log is bounded
+    assert_eq!(fb.log().count(), 3);
+    assert!(fb.log().next().unwrap().contains("90.0ms > 40ms budget"));
+    assert_eq!(fb.last(), 90 * MS);
+}
+
+#[test]
+fn fast_streak_restores_one_level_at_a_time() {
+    let mut fb = FrameBudget::new(40 * MS);
+    fb.record(90 * MS);
+    fb.record(90 * MS);
+    assert_eq!(fb.divisor(), 4);
+    for _ in 0..19 {
+        fb.record(5 * MS);
+    }
+    assert_eq!(fb.divisor(), 4);
+    // A frame between half and full budget breaks the streak
+    fb.record(30 * MS);
+    for _ in 0..19 {
+        fb.record(5 * MS);
+    }
+    assert_eq!(fb.divisor(), 4);
+    fb.record(5 * MS);
+    assert_eq!(fb.divisor(), 2);
+    assert!(fb.log().last().unwrap().contains("chart points 1/2"));
+}
@@ -0,0 +1,43 @@
+//! Checked frames (`--frame-check`): intact frames lose their header, damaged ones are
+//! reported, plain frames from older agents pass through.
+use flate2::Crc;
+use socktop::framing::{unwrap, FrameError, MAGIC};
+
+fn framed(payload: &[u8]) -> Vec<u8> {
+    let mut crc = Crc::new();
+    crc.update(payload);
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc.sum().to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+#[test]
+fn intact_frame_yields_its_payload() {
+    let f = framed(&[0x1f, 0x8b, 8, 0, 1, 2, 3]);
+    assert_eq!(unwrap(&f), Some(Ok(&[0x1f, 0x8b, 8, 0, 1, 2, 3][..])));
+    assert_eq!(unwrap(&framed(b"")), Some(Ok(&b""[..])));
+}
+
+#[test]
+fn truncated_frame_is_detected() {
+    let f = framed(b"processes payload");
+    assert_eq!(unwrap(&f[..f.len() - 3]), Some(Err(FrameError::Length)));
+    // Cut inside the header
+    assert_eq!(unwrap(&f[..6]), Some(Err(FrameError::Length)));
+}
+
+#[test]
+fn flipped_byte_fails_the_checksum() {
+    let mut f = framed(b"processes payload");
+    let last = f.len() - 1;
+    f[last] ^= 0x40;
+    assert_eq!(unwrap(&f), Some(Err(FrameError::Checksum)));
+}
+
+#[test]
+fn plain_frames_are_not_checked() {
+    assert_eq!(unwrap(&[0x1f, 0x8b, 8, 0]), None);
+    assert_eq!(unwrap(b""), None);
+}
@@ -0,0 +1,158 @@
+//! `/` search: one query over processes, disks, interfaces and sensors, popup keys, the
+//! process table filter and the highlight/focus it leaves in the disks panel.
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::style::Color;
+use ratatui::Terminal;
+use socktop::keymap::{action_for, Action};
+use socktop::search::{find, matches, HitKind, Search, SearchOutcome};
+use socktop::types::Metrics;
+use socktop::ui::disks::draw_disks;
+use socktop::ui::processes::{draw_top_processes, visible_rows, ProcSortBy};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn sample() -> Metrics {
+    serde_json::from_str(
+        r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1000,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":48.0,
+            "disks":[{"name":"/","total":100,"available":50},
+                     {"name":"/var/lib/docker","total":100,"available":10},
+                     {"name":"/srv/data","total":100,"available":90}],
+            "networks":[{"name":"eth0","received":1,"transmitted":2},
+                        {"name":"docker0","received":3,"transmitted":4}],
+            "top_processes":[{"pid":7,"name":"dockerd","cpu_usage":3.0,"mem_bytes":300},
+                             {"pid":8,"name":"sshd","cpu_usage":0.0,"mem_bytes":100}],
+            "gpus":[{"name":"NVIDIA RTX","temperature":61.0}]}"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn one_query_matches_every_panel() {
+    let m = sample();
+    let hits = find(&m, "DOCKER");
+    let kinds: Vec<(HitKind, &str)> = hits.iter().map(|h| (h.kind, h.name.as_str())).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (HitKind::Process, "dockerd"),
+            (HitKind::Disk, "/var/lib/docker"),
+            (HitKind::Interface, "docker0"),
+        ]
+    );
+    assert_eq!(hits[0].pid, Some(7));
+    assert_eq!(hits[1].detail, "90% used");
+
+    let sensors = find(&m, "temp");
+    assert_eq!(sensors.len(), 1);
+    assert_eq!(sensors[0].kind, HitKind::Sensor);
+    assert_eq!(sensors[0].detail, "48°C");
+    assert_eq!(find(&m, "rtx")[0].name, "NVIDIA RTX");
+
+    assert!(find(&m, "  ").is_empty());
+    assert!(!matches("sshd", ""));
+}
+
+#[test]
+fn popup_keys_select_jump_and_clear() {
+    assert_eq!(
+        action_for(key(KeyCode::Char('/'))),
+        Some(Action::OpenSearch)
+    );
+    let mut s = Search::default();
+    for c in "dock".chars() {
+        assert_eq!(s.handle_key(key(KeyCode::Char(c)), 3), SearchOutcome::Open);
+    }
+    assert_eq!(s.query, "dock");
+    for _ in 0..5 {
+        s.handle_key(key(KeyCode::Down), 3);
+    }
+    assert_eq!(s.selected, 2);
+    assert_eq!(
+        s.handle_key(key(KeyCode::Enter), 3),
+        SearchOutcome::Jump(Some(2))
+    );
+    s.handle_key(key(KeyCode::Backspace), 0);
+    assert_eq!((s.query.as_str(), s.selected), ("doc", 0));
+    assert_eq!(
+        s.handle_key(key(KeyCode::Enter), 0),
+        SearchOutcome::Jump(None)
+    );
+    assert_eq!(s.handle_key(key(KeyCode::Esc), 0), SearchOutcome::Close);
+}
+
+#[test]
+fn disks_panel_highlights_and_scrolls_to_the_focused_disk() {
+    let m = sample();
+    // Room for two cards only
+    let mut term = Terminal::new(TestBackend::new(60, 8)).unwrap();
+    term.draw(|f| {
+        draw_disks(
+            f,
+            f.area(),
+            Some(&m),
+            Duration::ZERO,
+            Some("data"),
+            Some("/srv/data"),
+        )
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    let text: String = buf.content().iter().map(|c| c.symbol()).collect();
+    assert!(text.contains("/srv/data"));
+    assert!(!text.contains("(50%)"));
+    // The matching card's border is yellow, the other one isn't
+    let fg = |y: u16| buf[(1, y)].fg;
+    assert_eq!(fg(1), Color::Reset);
+    assert_eq!(fg(4), Color::Yellow);
+}
+
+#[test]
+fn process_table_keeps_rows_matching_name_or_pid() {
+    let m = sample();
+    let pids = |q: Option<&str>| -> Vec<u32> {
+        visible_rows(&m, ProcSortBy::CpuDesc, q)
+            .into_iter()
+            .map(|i| m.top_processes[i].pid)
+            .collect()
+    };
+    assert_eq!(pids(None), [7, 8]);
+    assert_eq!(pids(Some("SSH")), [8]);
+    assert_eq!(pids(Some("7")), [7]);
+    assert_eq!(pids(Some("  ")), [7, 8]);
+    assert!(pids(Some("nginx")).is_empty());
+    // PIDs are searchable from the popup too
+    assert_eq!(find(&m, "8")[0].pid, Some(8));
+
+    let mut term = Terminal::new(TestBackend::new(80, 6)).unwrap();
+    let mut offset = 0;
+    term.draw(|f| {
+        draw_top_processes(
+            f,
+            f.area(),
+            Some(&m),
+            &mut offset,
+            ProcSortBy::CpuDesc,
+            None,
+            Duration::ZERO,
+            false,
+            Some("ssh"),
+            None,
+        )
+    })
+    .unwrap();
+    let text: String = term
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|c| c.symbol())
+        .collect();
+    assert!(text.contains("/ssh: 1 shown"));
+    assert!(text.contains("sshd") && !text.contains("dockerd"));
+}
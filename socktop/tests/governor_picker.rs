@@ -0,0 +1,98 @@
+//! CPU governor popup: `get_cpufreq` replies, and switching only after a y/n confirmation.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use socktop::governor::{GovernorOutcome, GovernorPicker};
+use socktop::keymap::{action_for, Action};
+use socktop::types::CpuFreq;
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn reply() -> CpuFreq {
+    serde_json::from_str(
+        r#"{"schema_version":1,"control":true,
+            "available":["performance","powersave","schedutil"],
+            "cores":[{"cpu":0,"governor":"schedutil","cur_khz":2400000},
+                     {"cpu":1,"governor":"schedutil","cur_khz":null}]}"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn get_cpufreq_reply_parses() {
+    let r = reply();
+    assert!(r.control);
+    assert_eq!(r.cores[0].cur_khz, Some(2_400_000));
+    assert_eq!(r.cores[1].cur_khz, None);
+    assert_eq!(r.error, None);
+    let p = GovernorPicker {
+        info: Some(r),
+        ..Default::default()
+    };
+    assert!(!p.pending());
+    assert_eq!(p.current(), Some("schedutil"));
+}
+
+#[test]
+fn enter_switches_only_after_confirmation() {
+    assert_eq!(
+        action_for(key(KeyCode::Char('g'))),
+        Some(Action::ShowGovernor)
+    );
+    let mut p = GovernorPicker::default();
+    assert!(p.pending());
+    p.info = Some(reply());
+
+    // "n" backs out of the confirmation without sending anything
+    p.handle_key(key(KeyCode::Enter));
+    assert_eq!(p.confirm.as_deref(), Some("performance"));
+    assert_eq!(p.handle_key(key(KeyCode::Char('n'))), GovernorOutcome::Open);
+    assert_eq!((p.confirm.as_deref(), p.apply.as_deref()), (None, None));
+
+    p.handle_key(key(KeyCode::Down));
+    p.handle_key(key(KeyCode::Enter));
+    p.handle_key(key(KeyCode::Char('y')));
+    assert_eq!(p.apply.as_deref(), Some("powersave"));
+    assert_eq!(p.handle_key(key(KeyCode::Esc)), GovernorOutcome::Close);
+}
+
+#[test]
+fn locked_popup_never_asks_to_switch() {
+    let mut p = GovernorPicker {
+        info: Some(reply()),
+        locked: Some("read-only token".into()),
+        ..Default::default()
+    };
+    p.handle_key(key(KeyCode::Enter));
+    assert_eq!(p.confirm, None);
+    assert_eq!(
+        p.handle_key(key(KeyCode::Char('g'))),
+        GovernorOutcome::Close
+    );
+}
+
+#[test]
+fn agent_without_control_makes_the_list_view_only() {
+    let mut p = GovernorPicker::default();
+    p.set_info(CpuFreq {
+        control: false,
+        ..reply()
+    });
+    assert!(p.locked.as_deref().unwrap().contains("--cpufreq-control"));
+    p.handle_key(key(KeyCode::Enter));
+    assert_eq!(p.confirm, None);
+
+    // A read-only token's reason is kept; a control-enabled reply leaves it open
+    let mut p = GovernorPicker {
+        locked: Some("read-only token".into()),
+        ..Default::default()
+    };
+    p.set_info(CpuFreq {
+        control: false,
+        ..reply()
+    });
+    assert_eq!(p.locked.as_deref(), Some("read-only token"));
+    let mut p = GovernorPicker::default();
+    p.set_info(reply());
+    assert_eq!(p.locked, None);
+}
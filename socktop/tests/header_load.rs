@@ -0,0 +1,66 @@
+//! Load average, uptime, clock status and pending updates in the header.
+use std::time::Duration;
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use socktop::types::{ClockSync, Metrics};
+use socktop::ui::header::draw_header;
+use socktop::ui::util::uptime;
+
+fn header(extra: &str) -> String {
+    let m: Metrics = serde_json::from_str(&format!(
+        r#"{{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"pi4","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[],"gpus":null{extra}}}"#
+    ))
+    .unwrap();
+    let mut term = Terminal::new(TestBackend::new(200, 2)).unwrap();
+    term.draw(|f| {
+        let d = Duration::from_secs(1);
+        draw_header(f, f.area(), Some(&m), false, false, false, d, d, None)
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    buf.content().iter().map(|c| c.symbol()).collect()
+}
+
+#[test]
+fn load_and_uptime_follow_the_host() {
+    let text = header(r#","load_avg":[3.5,1.25,0.5],"uptime_secs":273600"#);
+    assert!(text.contains("host: pi4 | load 3.50 1.25 0.50 | up 3d 4h |"));
+    // Older agents send neither
+    assert!(header("").contains("host: pi4 | Temp N/A"));
+}
+
+#[test]
+fn uptime_shows_its_two_largest_units() {
+    assert_eq!(uptime(59), "0m");
+    assert_eq!(uptime(15_120), "4h 12m");
+    assert_eq!(uptime(273_600), "3d 4h");
+}
+
+#[test]
+fn clock_warning_carries_the_max_error_when_known() {
+    let clock = |json: &str| serde_json::from_str::<ClockSync>(json).unwrap().warning();
+    assert_eq!(
+        clock(r#"{"synchronized":false,"offset_ms":-12.34,"max_error_ms":500.0}"#).as_deref(),
+        Some("🕒✗ clock unsynced (offset -12.3ms, max error 500ms)")
+    );
+    assert_eq!(
+        clock(r#"{"synchronized":false,"offset_ms":1.5}"#).as_deref(),
+        Some("🕒✗ clock unsynced (offset +1.5ms)")
+    );
+    assert_eq!(
+        clock(r#"{"synchronized":true,"offset_ms":0.2,"max_error_ms":16.0}"#),
+        None
+    );
+}
+
+#[test]
+fn pending_updates_name_the_package_manager() {
+    let text = header(r#","updates":{"manager":"apt","total":12,"security":3}"#);
+    // Wide glyphs are followed by a blank cell in the buffer
+    assert!(text.contains("12 apt updates (3 security)"), "{text}");
+    let text = header(r#","updates":{"manager":"pacman","total":4}"#);
+    assert!(text.contains("4 pacman updates |"));
+}
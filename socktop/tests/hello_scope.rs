@@ -0,0 +1,36 @@
+//! `hello` replies: token scope and session decoding; unknown scopes never unlock control.
+use socktop::types::{Hello, Scope};
+
+#[test]
+fn hello_decodes_scope_and_capabilities() {
+    let h: Hello = serde_json::from_str(
+        r#"{"version":"1.50.0","scope":"control","capabilities":["processes_top","hello"]}"#,
+    )
+    .unwrap();
+    assert_eq!(h.scope, Scope::Control);
+    assert_eq!(h.version, "1.50.0");
+    assert!(h.capabilities.iter().any(|c| c == "hello"));
+
+    let h: Hello = serde_json::from_str(r#"{"scope":"read"}"#).unwrap();
+    assert_eq!(h.scope, Scope::Read);
+    assert!(h.capabilities.is_empty());
+}
+
+#[test]
+fn unknown_scope_is_read_only() {
+    let h: Hello = serde_json::from_str(r#"{"scope":"superuser"}"#).unwrap();
+    assert_eq!(h.scope, Scope::Read);
+}
+
+#[test]
+fn hello_carries_a_resumable_session() {
+    let h: Hello =
+        serde_json::from_str(r#"{"scope":"read","session":"9f2c01","resumed":true}"#).unwrap();
+    assert_eq!(h.session.as_deref(), Some("9f2c01"));
+    assert!(h.resumed);
+
+    // Agents from before sessions: nothing to resume
+    let h: Hello = serde_json::from_str(r#"{"scope":"read"}"#).unwrap();
+    assert_eq!(h.session, None);
+    assert!(!h.resumed);
+}
@@ -0,0 +1,28 @@
+//! Agent history points are stretched to the client's sample rate before prefilling charts.
+use std::time::Duration;
+
+use socktop::history::expand_history;
+use socktop::types::AgentHistory;
+
+#[test]
+fn history_points_repeat_per_client_interval() {
+    let h: AgentHistory = serde_json::from_str(
+        r#"{"step_secs":10,"points":[
+            {"ts":0,"cpu_total":5.0,"mem_used":1,"mem_total":2,"swap_used":0,"rx_bps":10,"tx_bps":20},
+            {"ts":10,"cpu_total":7.0,"cpu_per_core":[1.0,2.0],"mem_used":1,"mem_total":2,"swap_used":0,"rx_bps":30,"tx_bps":40}
+        ]}"#,
+    )
+    .unwrap();
+
+    let out = expand_history(&h.points, h.step_secs, Duration::from_millis(500));
+    assert_eq!(out.len(), 40);
+    assert_eq!(out[19].1.cpu_total, 5.0);
+    assert_eq!(out[20].1.cpu_per_core, vec![1.0, 2.0]);
+    // Copies are spaced by the client interval
+    assert_eq!(out[19].0, 9_500);
+    assert_eq!(out[20].0, 10_000);
+
+    // Interval slower than the step still yields one sample per point
+    let out = expand_history(&h.points, h.step_secs, Duration::from_secs(30));
+    assert_eq!(out.len(), 2);
+}
@@ -0,0 +1,130 @@
+//! Rendering at scale: 256 cores, 10k processes and 50 disks through the real panel code.
+use std::time::{Duration, Instant};
+
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+use ratatui::Terminal;
+use serde_json::json;
+use socktop::history::PerCoreHistory;
+use socktop::types::Metrics;
+use socktop::ui::cpu::draw_per_core_bars;
+use socktop::ui::disks::draw_disks;
+use socktop::ui::gpu::draw_gpu;
+use socktop::ui::processes::{draw_top_processes, ProcSortBy};
+
+const CORES: usize = 256;
+const PROCESSES: usize = 10_000;
+const DISKS: usize = 50;
+const GB: u64 = 1024 * 1024 * 1024;
+
+// Generous so debug builds on slow CI pass; a regression to per-frame O(n^2) work blows past it
+const FRAME_BUDGET: Duration = Duration::from_secs(2);
+
+/// Agent-shaped JSON for a very large host, decoded the same way the client decodes replies.
+fn huge_metrics() -> Metrics {
+    let cores: Vec<f32> = (0..CORES).map(|i| (i * 37 % 100) as f32).collect();
+    let procs: Vec<_> = (0..PROCESSES as u32)
+        .map(|pid| {
+            json!({
+                "pid": pid + 1,
+                "name": format!("worker-{pid:05}"),
+                "cpu_usage": (pid % 1000) as f32 / 10.0,
+                "mem_bytes": (pid as u64 % 4096) * 1024 * 1024,
+            })
+        })
+        .collect();
+    let disks: Vec<_> = (0..DISKS as u64)
+        .map(|i| {
+            json!({
+                "name": format!("/dev/nvme{}n1p{}", i / 4, i % 4 + 1),
+                "total": (i + 1) * 512 * GB,
+                "available": (i + 1) * 128 * GB,
+            })
+        })
+        .collect();
+    let gpus: Vec<_> = (0..8u64)
+        .map(|i| {
+            json!({
+                "name": format!("Fixture GPU {i}"),
+                "utilization_gpu_pct": i * 12,
+                "mem_used_bytes": i * GB,
+                "mem_total_bytes": 80 * GB,
+            })
+        })
+        .collect();
+    serde_json::from_value(json!({
+        "cpu_total": cores.iter().sum::<f32>() / CORES as f32,
+        "cpu_per_core": cores,
+        "mem_total": 2048 * GB,
+        "mem_used": 1500 * GB,
+        "swap_total": 64 * GB,
+        "swap_used": GB,
+        "hostname": "fixture-huge",
+        "cpu_temp_c": 61.5,
+        "disks": disks,
+        "networks": [],
+        "top_processes": procs,
+        "gpus": gpus,
+        "process_count": PROCESSES,
+    }))
+    .expect("fixture decodes as Metrics")
+}
+
+fn render(draw: impl FnOnce(&mut ratatui::Frame<'_>, Rect)) -> (String, Duration) {
+    let mut term = Terminal::new(TestBackend::new(160, 48)).unwrap();
+    let t = Instant::now();
+    term.draw(|f| draw(f, f.area())).unwrap();
+    let took = t.elapsed();
+    let text = term
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|c| c.symbol())
+        .collect();
+    (text, took)
+}
+
+#[test]
+fn huge_host_panels_render_within_budget() {
+    let m = huge_metrics();
+    assert_eq!(m.cpu_per_core.len(), CORES);
+
+    let mut hist = PerCoreHistory::new(60);
+    hist.ensure_cores(CORES);
+    for _ in 0..60 {
+        hist.push_samples(&m.cpu_per_core);
+    }
+    let (_, took) = render(|f, a| draw_per_core_bars(f, a, Some(&m), &hist, 0, false, 1));
+    assert!(took < FRAME_BUDGET, "per-core bars took {took:?}");
+
+    // Scrolled past the end: the offset is clamped to the last page
+    let mut offset = usize::MAX;
+    let (text, took) = render(|f, a| {
+        draw_top_processes(
+            f,
+            a,
+            Some(&m),
+            &mut offset,
+            ProcSortBy::CpuDesc,
+            Some(1),
+            Duration::ZERO,
+            true,
+            None,
+            None,
+        )
+    });
+    assert!(took < FRAME_BUDGET, "process table took {took:?}");
+    assert!(text.contains("10000 total"));
+    // The selected pid (lowest CPU) forces the view onto the last page
+    assert!(offset < PROCESSES);
+    assert!(text.contains("worker-00000"));
+
+    let (text, took) = render(|f, a| draw_disks(f, a, Some(&m), Duration::ZERO, None, None));
+    assert!(took < FRAME_BUDGET, "disks took {took:?}");
+    assert!(text.contains("nvme0n1p1"));
+
+    let (text, took) = render(|f, a| draw_gpu(f, a, Some(&m), Duration::ZERO));
+    assert!(took < FRAME_BUDGET, "gpu took {took:?}");
+    assert!(text.contains("Fixture GPU 0"));
+}
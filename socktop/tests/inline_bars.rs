@@ -0,0 +1,58 @@
+//! Processes table CPU/Mem % cells carry a one-glyph bar unless --plain turns it off.
+use std::time::Duration;
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use socktop::types::Metrics;
+use socktop::ui::processes::{draw_top_processes, ProcSortBy};
+use socktop::ui::util::mini_bar;
+
+#[test]
+fn glyph_levels_follow_the_percentage() {
+    assert_eq!(mini_bar(0.0), ' ');
+    assert_eq!(mini_bar(f32::NAN), ' ');
+    assert_eq!(mini_bar(3.0), '▁');
+    assert_eq!(mini_bar(30.0), '▃');
+    assert_eq!(mini_bar(60.0), '▅');
+    assert_eq!(mini_bar(99.0), '▇');
+    assert_eq!(mini_bar(400.0), '▇');
+}
+
+fn render(inline_bars: bool) -> String {
+    let m: Metrics = serde_json::from_str(
+        r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1000,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[{"pid":7,"name":"busy","cpu_usage":80.0,"mem_bytes":300}],
+            "gpus":null}"#,
+    )
+    .unwrap();
+    let mut term = Terminal::new(TestBackend::new(80, 6)).unwrap();
+    let mut offset = 0;
+    term.draw(|f| {
+        draw_top_processes(
+            f,
+            f.area(),
+            Some(&m),
+            &mut offset,
+            ProcSortBy::CpuDesc,
+            None,
+            Duration::ZERO,
+            inline_bars,
+            None,
+            None,
+        )
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    buf.content().iter().map(|c| c.symbol()).collect()
+}
+
+#[test]
+fn bars_render_next_to_values_and_plain_mode_drops_them() {
+    let with = render(true);
+    assert!(with.contains("▇  80.0"), "{with}");
+    assert!(with.contains("▃ 30.00%"), "{with}");
+    let plain = render(false);
+    assert!(!plain.contains('▇') && !plain.contains('▃'), "{plain}");
+    assert!(plain.contains("30.00%"));
+}
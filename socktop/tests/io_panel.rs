@@ -0,0 +1,87 @@
+//! I/O wait panel: `get_io` replies decode, and the panel lines explain pressure, disks and waiters.
+use socktop::types::IoReport;
+use socktop::ui::io::IoPanel;
+use socktop::ws::{request_io, AgentLink};
+use tokio_tungstenite::tungstenite::Message;
+
+struct Canned {
+    reply: String,
+    sent: Vec<String>,
+}
+
+impl AgentLink for Canned {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("canned link".into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.sent.push(cmd.to_string());
+        Some(Message::Text(self.reply.clone()))
+    }
+}
+
+const REPLY: &str = r#"{"schema_version":1,"interval_ms":1000,
+    "pressure":{"some_avg10":62.5,"some_avg60":40.0,"full_avg10":12.0,"full_avg60":8.0},
+    "procs_blocked":4,"iowait_pct":35.0,
+    "disks":[{"name":"sda","util_pct":98.0,"read_bps":1048576,"write_bps":0}],
+    "top":[{"pid":812,"name":"postgres","read_bps":1048576,"write_bps":2048,"blocked":false},
+           {"pid":90,"name":"jbd2/sda1-8","read_bps":0,"write_bps":0,"blocked":true}]}"#;
+
+fn text(p: &IoPanel) -> Vec<String> {
+    p.lines().iter().map(|l| l.to_string()).collect()
+}
+
+#[tokio::test]
+async fn report_is_requested_and_decoded() {
+    let mut link = Canned {
+        reply: REPLY.into(),
+        sent: Vec::new(),
+    };
+    let r = request_io(&mut link).await.unwrap();
+    assert_eq!(link.sent, ["get_io"]);
+    assert_eq!(r.pressure.unwrap().some_avg10, 62.5);
+    assert_eq!(r.disks[0].name, "sda");
+    assert!(r.top[1].blocked);
+}
+
+#[test]
+fn panel_shows_pressure_disks_and_waiters() {
+    let p = IoPanel {
+        note: None,
+        report: Some(serde_json::from_str(REPLY).unwrap()),
+    };
+    let lines = text(&p);
+    assert!(lines[0].starts_with("Pressure  some 62.5% (60s 40.0%)  full 12.0%"));
+    assert_eq!(
+        lines[1],
+        "Waiting   4 tasks blocked on I/O · CPU iowait 35.0%"
+    );
+    let sda = lines.iter().find(|l| l.contains("sda")).unwrap();
+    assert!(sda.contains("██████████  98%"), "{sda}");
+    assert!(sda.contains("1.0MB/s"));
+    let jbd = lines.iter().find(|l| l.contains("jbd2")).unwrap();
+    assert!(jbd.ends_with("  D"), "{jbd}");
+    let pg = lines.iter().position(|l| l.contains("postgres")).unwrap();
+    assert!(pg < lines.iter().position(|l| l.contains("jbd2")).unwrap());
+}
+
+#[test]
+fn first_sample_notes_and_old_agents() {
+    let first: IoReport =
+        serde_json::from_str(r#"{"interval_ms":null,"pressure":null,"disks":[],"top":[]}"#)
+            .unwrap();
+    let p = IoPanel {
+        note: None,
+        report: Some(first),
+    };
+    let lines = text(&p);
+    assert_eq!(lines[0], "Pressure  not reported (kernel without PSI)");
+    assert_eq!(lines.last().unwrap(), "sampling disks and processes…");
+
+    assert_eq!(text(&IoPanel::default()), ["loading…"]);
+    let p = IoPanel {
+        note: Some("This agent doesn't report I/O wait".into()),
+        report: None,
+    };
+    assert_eq!(text(&p), ["This agent doesn't report I/O wait"]);
+}
@@ -0,0 +1,80 @@
+//! Startup layouts from profiles: the default grid, hidden panels and a maximized panel.
+use ratatui::layout::Rect;
+use socktop::layout::{Layout, Panel};
+
+const AREA: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 120,
+    height: 40,
+};
+
+fn names(v: &[&str]) -> Vec<String> {
+    v.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn default_layout_draws_every_panel() {
+    let a = Layout::default().areas(AREA, 0);
+    assert_eq!(a.header.height, 1);
+    for r in [
+        a.cpu,
+        a.cores,
+        a.mem,
+        a.swap,
+        a.gpu,
+        a.disks,
+        a.rx,
+        a.tx,
+        a.processes,
+    ] {
+        assert!(r.is_some_and(|r| r.area() > 0));
+    }
+    let (mem, gpu) = (a.mem.unwrap(), a.gpu.unwrap());
+    // GPU sits right of memory and spans memory + swap
+    assert_eq!(gpu.y, mem.y);
+    assert_eq!(gpu.height, 6);
+    assert!(gpu.x >= mem.x + mem.width);
+}
+
+#[test]
+fn hidden_panels_give_their_space_away() {
+    let (l, unknown) = Layout::from_profile(None, &names(&["cpu", "mem", "disks", "Net"]));
+    assert!(unknown.is_empty());
+    assert!(!l.shows(Panel::Processes));
+    let a = l.areas(AREA, 0);
+    assert_eq!(
+        (a.cores, a.swap, a.gpu, a.processes),
+        (None, None, None, None)
+    );
+    // Alone in their rows, cpu and disks take the full width
+    assert_eq!(a.cpu.unwrap().width, AREA.width);
+    assert_eq!(a.disks.unwrap().width, AREA.width);
+    assert_eq!(a.mem.unwrap().width, AREA.width);
+    assert!(a.rx.is_some() && a.tx.is_some());
+}
+
+#[test]
+fn maximized_panel_fills_the_body() {
+    let (l, unknown) = Layout::from_profile(Some("disks"), &[]);
+    assert!(unknown.is_empty());
+    assert!(l.shows(Panel::Disks) && !l.shows(Panel::Cpu));
+    let a = l.areas(AREA, 2);
+    assert_eq!(a.disks, Some(Rect::new(0, 3, 120, 37)));
+    assert_eq!((a.cpu, a.processes, a.rx), (None, None, None));
+
+    let a = Layout::from_profile(Some("net"), &[]).0.areas(AREA, 0);
+    assert_eq!(a.rx.unwrap().height + a.tx.unwrap().height, 39);
+}
+
+#[test]
+fn unknown_names_are_reported_not_applied() {
+    let (l, unknown) = Layout::from_profile(Some("diskz"), &names(&["cpu", "gpus"]));
+    assert_eq!(unknown, names(&["gpus", "diskz"]));
+    assert_eq!(l, Layout::from_profile(None, &names(&["cpu"])).0);
+    assert!(l.shows(Panel::Cpu) && !l.shows(Panel::Disks));
+
+    // Nothing recognised: keep the full dashboard rather than an empty screen
+    let (l, _) = Layout::from_profile(Some("default"), &names(&["nope"]));
+    assert_eq!(l, Layout::default());
+}
@@ -0,0 +1,58 @@
+//! List popups (palette, search, peers): the selection stays in view.
+use ratatui::backend::TestBackend;
+use ratatui::text::Line;
+use ratatui::Terminal;
+use socktop::ui::util::ListPopup;
+
+fn popup(height: u16, len: usize, selected: usize) -> Vec<String> {
+    let mut term = Terminal::new(TestBackend::new(40, height)).unwrap();
+    term.draw(|f| {
+        let popup = ListPopup {
+            title: "t",
+            width: 30,
+            min_width: 20,
+            head: vec![Line::from("> query")],
+            len,
+            selected,
+        };
+        popup.draw(f, f.area(), |i, _| Line::from(format!("row{i}")))
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    let w = buf.area.width as usize;
+    let cells: Vec<&str> = buf.content().iter().map(|c| c.symbol()).collect();
+    cells.chunks(w).map(|r| r.concat()).collect()
+}
+
+#[test]
+fn selection_scrolls_into_view() {
+    // 10 rows, starting on row 2: borders and the query line leave 5 list rows
+    let text = popup(10, 20, 12).concat();
+    assert!(text.contains("> query"));
+    assert!(text.contains("row8") && text.contains("row12"));
+    assert!(!text.contains("row7") && !text.contains("row13"));
+
+    // Short lists start at the top and size the popup to fit
+    let lines = popup(30, 2, 1);
+    assert!(lines.concat().contains("row0"));
+    assert_eq!(lines.iter().filter(|l| l.contains('│')).count(), 3);
+}
+
+#[test]
+fn too_narrow_draws_nothing() {
+    let mut term = Terminal::new(TestBackend::new(15, 10)).unwrap();
+    term.draw(|f| {
+        let popup = ListPopup {
+            title: "t",
+            width: 30,
+            min_width: 20,
+            head: Vec::new(),
+            len: 3,
+            selected: 0,
+        };
+        popup.draw(f, f.area(), |i, _| Line::from(format!("row{i}")))
+    })
+    .unwrap();
+    let buf = term.backend().buffer();
+    assert!(buf.content().iter().all(|c| c.symbol() == " "));
+}
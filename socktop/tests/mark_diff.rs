@@ -0,0 +1,104 @@
+//! Diff mode: CPU time, network bytes and disk space change between a mark and now.
+use serde_json::json;
+use socktop::keymap::{action_for, Action};
+use socktop::mark::{DiskDelta, Mark};
+use socktop::types::Metrics;
+use socktop::ui::mark::mark_lines;
+
+fn sample(procs: serde_json::Value, rx: u64, disks: serde_json::Value) -> Metrics {
+    serde_json::from_value(json!({
+        "cpu_total": 1.0, "cpu_per_core": [], "mem_total": 1000, "mem_used": 0,
+        "swap_total": 0, "swap_used": 0, "hostname": "h", "cpu_temp_c": null,
+        "disks": disks,
+        "networks": [{"name": "eth0", "received": rx, "transmitted": 10}],
+        "top_processes": procs,
+        "gpus": null
+    }))
+    .unwrap()
+}
+
+fn proc(pid: u32, name: &str, cpu_time_ms: u64) -> serde_json::Value {
+    json!({"pid": pid, "name": name, "cpu_usage": 1.0, "mem_bytes": 1, "cpu_time_ms": cpu_time_ms})
+}
+
+#[test]
+fn deltas_since_the_mark() {
+    let disks = json!([{"name": "/", "total": 1000, "available": 600},
+                       {"name": "/mnt/old", "total": 10, "available": 5}]);
+    let before = sample(
+        json!([proc(1, "deploy", 1_000), proc(2, "idle", 500)]),
+        100,
+        disks,
+    );
+    let mut mark = Mark::new(&before, 1_000);
+
+    // Counter reset in between: bytes before and after it both count
+    mark.observe(&sample(json!([]), 400, json!([])));
+    mark.observe(&sample(json!([proc(3, "late", 2_000)]), 50, json!([])));
+    let after = sample(
+        json!([
+            proc(1, "deploy", 61_000),
+            proc(2, "idle", 500),
+            proc(3, "late", 2_500)
+        ]),
+        150,
+        json!([{"name": "/", "total": 1000, "available": 400},
+               {"name": "/data", "total": 10, "available": 10}]),
+    );
+    mark.observe(&after);
+    let d = mark.diff(&after, 1_090);
+
+    assert_eq!(d.elapsed_secs, 90);
+    let procs: Vec<(u32, u64, bool)> = d
+        .processes
+        .iter()
+        .map(|p| (p.pid, p.cpu_time_ms, p.partial))
+        .collect();
+    assert_eq!(procs, vec![(1, 60_000, false), (3, 500, true)]);
+    assert_eq!(d.rx_bytes, 300 + 100);
+    assert_eq!(d.tx_bytes, 0);
+    assert_eq!(
+        d.disks,
+        vec![
+            DiskDelta {
+                name: "/".into(),
+                change: Some(200)
+            },
+            DiskDelta {
+                name: "/data".into(),
+                change: None
+            },
+        ]
+    );
+    assert_eq!(d.removed_disks, vec!["/mnt/old".to_string()]);
+
+    let text: Vec<String> = mark_lines(&d, 1_000)
+        .iter()
+        .map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    assert!(text[0].contains("1970-01-01 00:16:40 UTC (1m 30s ago)"));
+    assert!(text
+        .iter()
+        .any(|l| l.contains("deploy") && l.contains("1:00.00")));
+    assert!(text
+        .iter()
+        .any(|l| l.contains("late") && l.contains("since first seen")));
+    assert!(text.iter().any(|l| l.contains("↓ 400B")));
+    assert!(text.iter().any(|l| l.contains("+200B")));
+}
+
+#[test]
+fn reused_pid_counts_from_zero() {
+    let mark = Mark::new(&sample(json!([proc(9, "old", 90_000)]), 0, json!([])), 0);
+    let d = mark.diff(&sample(json!([proc(9, "new", 3_000)]), 0, json!([])), 5);
+    assert_eq!(d.processes[0].cpu_time_ms, 3_000);
+    assert_eq!(d.processes[0].name, "new");
+}
+
+#[test]
+fn mark_keys() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    let key = |c| action_for(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    assert_eq!(key('b'), Some(Action::SetMark));
+    assert_eq!(key('v'), Some(Action::ToggleMarkDiff));
+}
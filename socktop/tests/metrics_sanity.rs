@@ -0,0 +1,78 @@
+//! Impossible agent values are clamped and flagged instead of charted.
+use serde_json::json;
+use socktop::sanity::{check_disks, check_metrics, check_processes, Suspect};
+use socktop::types::{DiskInfo, Metrics, ProcessInfo};
+
+fn metrics(v: serde_json::Value) -> Metrics {
+    let mut base = json!({
+        "cpu_total": 12.0, "cpu_per_core": [10.0, 14.0], "mem_total": 1000, "mem_used": 400,
+        "swap_total": 0, "swap_used": 0, "hostname": "h", "cpu_temp_c": null, "disks": [],
+        "networks": [], "top_processes": [], "gpus": null
+    });
+    for (k, val) in v.as_object().unwrap() {
+        base[k] = val.clone();
+    }
+    serde_json::from_value(base).unwrap()
+}
+
+#[test]
+fn sane_samples_pass_untouched() {
+    let mut m = metrics(json!({}));
+    assert!(check_metrics(&mut m).is_empty());
+    assert_eq!(m.cpu_total, 12.0);
+    assert_eq!(Suspect::default().badge(), None);
+}
+
+#[test]
+fn impossible_values_are_clamped_and_named() {
+    let mut m = metrics(json!({
+        "cpu_total": 250.0,
+        "cpu_per_core": [-5.0, 14.0],
+        "mem_used": 4000,
+        "swap_used": 1,
+        "gpus": [{"name": "g", "utilization": 180.0, "mem_used": 9, "mem_total": 8}],
+    }));
+    let bad = check_metrics(&mut m);
+    assert_eq!(bad, ["cpu_total", "cpu_per_core", "mem", "swap", "gpu"]);
+    assert_eq!(m.cpu_total, 100.0);
+    assert_eq!(m.cpu_per_core, [0.0, 14.0]);
+    assert_eq!(m.mem_used, 1000);
+    assert_eq!(m.swap_used, 0);
+    let g = &m.gpus.as_ref().unwrap()[0];
+    assert_eq!((g.utilization, g.mem_used), (Some(100.0), Some(8)));
+}
+
+#[test]
+fn processes_and_disks_are_checked_per_payload() {
+    let row = |cpu: f32| ProcessInfo {
+        pid: 1,
+        name: "p".into(),
+        cpu_usage: cpu,
+        mem_bytes: 0,
+        cpu_time_ms: 0,
+    };
+    // 350% is fine on four cores, 450% is not; NaN never is
+    let mut rows = vec![row(350.0)];
+    assert!(check_processes(&mut rows, 4).is_empty());
+    let mut rows = vec![row(450.0), row(f32::NAN)];
+    assert_eq!(check_processes(&mut rows, 4), ["process_cpu"]);
+    assert_eq!((rows[0].cpu_usage, rows[1].cpu_usage), (400.0, 0.0));
+
+    let mut disks = vec![DiskInfo {
+        name: "sda1".into(),
+        total: 100,
+        available: 150,
+    }];
+    assert_eq!(check_disks(&mut disks), ["disks"]);
+    assert_eq!(disks[0].available, 100);
+
+    let s = Suspect {
+        metrics: vec!["mem"],
+        processes: vec!["process_cpu"],
+        disks: vec!["disks", "mem"],
+    };
+    assert_eq!(
+        s.badge().as_deref(),
+        Some("⚠ suspect data: disks, mem, process_cpu (clamped)")
+    );
+}
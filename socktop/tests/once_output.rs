@@ -0,0 +1,50 @@
+//! `socktop --once`: the snapshot printed instead of the TUI.
+use std::process::Command;
+
+use socktop::once::{plain, render, OnceFormat};
+use socktop::types::Metrics;
+
+const REPLY: &str = r#"{"cpu_total":37.3,"cpu_per_core":[50.0,24.5],"mem_total":8589934592,
+"mem_used":2147483648,"swap_total":0,"swap_used":0,"hostname":"pi4","uptime_secs":90061,"load_avg":[0.52,1.5,2.0],
+"cpu_temp_c":48.4,"disks":[{"name":"/dev/sda1","total":1073741824,"available":268435456}],
+"networks":[{"name":"eth0","received":2048,"transmitted":1024}],
+"top_processes":[{"pid":7,"name":"idle","cpu_usage":0.5,"mem_bytes":1024},
+{"pid":42,"name":"socktop_agent","cpu_usage":3.0,"mem_bytes":5242880}],"gpus":null}"#;
+
+#[test]
+fn plain_output_is_one_aligned_line_per_reading() {
+    let m: Metrics = serde_json::from_str(REPLY).unwrap();
+    let out = plain(&m);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "host            pi4");
+    assert_eq!(lines[1], "cpu             37.3% (2 cores)");
+    assert!(lines.contains(&"temp            48°C"));
+    assert!(lines.contains(&"mem             2.0GB / 8.0GB (25%)"));
+    assert!(lines.contains(&"load            0.52 1.50 2.00"));
+    assert!(lines.contains(&"uptime          1d 1h 1m"));
+    assert!(lines.contains(&"disk /dev/sda1  768.0MB / 1.0GB (75%)"));
+    assert!(lines.contains(&"net eth0        rx 2.0KB tx 1.0KB total"));
+    // Busiest process first
+    let procs: Vec<&&str> = lines.iter().filter(|l| l.starts_with("proc ")).collect();
+    assert_eq!(*procs[0], "proc 42         socktop_agent 3.0% 5.0MB");
+    assert_eq!(procs.len(), 2);
+}
+
+#[test]
+fn json_output_is_the_agent_reply_on_one_line() {
+    let m: Metrics = serde_json::from_str(REPLY).unwrap();
+    let out = render(&m, "{\"hostname\":\"pi4\"}\n\n", OnceFormat::Json);
+    assert_eq!(out, "{\"hostname\":\"pi4\"}\n");
+    assert_eq!(OnceFormat::parse("json"), Some(OnceFormat::Json));
+    assert_eq!(OnceFormat::parse("yaml"), None);
+}
+
+#[test]
+fn unknown_format_is_a_usage_error() {
+    let out = Command::new(env!("CARGO_BIN_EXE_socktop"))
+        .args(["--once", "--format", "yaml", "ws://127.0.0.1:1/ws"])
+        .output()
+        .expect("run socktop");
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--format takes plain or json"));
+}
@@ -0,0 +1,61 @@
+//! Unchanged panels are copied from the previous frame instead of being drawn again.
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Terminal;
+use socktop::ui::cache::{debug_key, key_of, PanelCache};
+
+fn frame(
+    term: &mut Terminal<TestBackend>,
+    cache: &mut PanelCache,
+    area: Rect,
+    key: u64,
+    text: &str,
+) -> bool {
+    let mut drew = false;
+    term.draw(|f| {
+        cache.draw(f, "panel", area, key, |f| {
+            drew = true;
+            f.render_widget(
+                Paragraph::new(text.to_string()).block(Block::default().borders(Borders::ALL)),
+                area,
+            );
+        })
+    })
+    .unwrap();
+    drew
+}
+
+fn screen(term: &Terminal<TestBackend>) -> String {
+    term.backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|c| c.symbol())
+        .collect()
+}
+
+#[test]
+fn same_key_and_area_reuses_cells() {
+    let mut term = Terminal::new(TestBackend::new(20, 5)).unwrap();
+    let mut cache = PanelCache::default();
+    let area = Rect::new(0, 0, 20, 5);
+
+    assert!(frame(&mut term, &mut cache, area, key_of(1), "first"));
+    // Same key: not redrawn, yet the cells are still on screen
+    assert!(!frame(&mut term, &mut cache, area, key_of(1), "ignored"));
+    assert!(screen(&term).contains("first"));
+
+    assert!(frame(&mut term, &mut cache, area, key_of(2), "second"));
+    assert!(screen(&term).contains("second"));
+
+    // Resize/relayout invalidates even with an unchanged key
+    let smaller = Rect::new(0, 0, 18, 5);
+    assert!(frame(&mut term, &mut cache, smaller, key_of(2), "third"));
+}
+
+#[test]
+fn debug_key_tracks_float_changes() {
+    assert_eq!(debug_key(&(1.5f32, "gpu")), debug_key(&(1.5f32, "gpu")));
+    assert_ne!(debug_key(&(1.5f32, "gpu")), debug_key(&(1.6f32, "gpu")));
+}
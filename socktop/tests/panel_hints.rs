@@ -0,0 +1,53 @@
+//! Empty-panel placeholders explain why a panel has nothing to show.
+use std::time::Duration;
+
+use socktop::hints::{empty_hint, Panel};
+use socktop::types::Metrics;
+
+fn metrics(extra: &str) -> Metrics {
+    serde_json::from_str(&format!(
+        r#"{{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[],"gpus":null{extra}}}"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn disabled_and_failed_collectors_say_so() {
+    let m = metrics(
+        r#","capabilities":["collectors","gpu"],"collector_errors":{"gpu":"no DRM device"}"#,
+    );
+    assert_eq!(
+        empty_hint(Some(&m), Panel::Gpu, Duration::ZERO).as_deref(),
+        Some("GPU collector failed: no DRM device")
+    );
+    assert_eq!(
+        empty_hint(Some(&m), Panel::Temp, Duration::ZERO).as_deref(),
+        Some("Temp disabled on agent (SOCKTOP_AGENT_TEMP=0)")
+    );
+    let m = metrics(r#","capabilities":["collectors","temp"]"#);
+    assert_eq!(
+        empty_hint(Some(&m), Panel::Gpu, Duration::ZERO).as_deref(),
+        Some("GPU disabled on agent (SOCKTOP_AGENT_GPU=0)")
+    );
+}
+
+#[test]
+fn missing_payloads_show_next_poll() {
+    let wait = Duration::from_millis(1200);
+    assert_eq!(
+        empty_hint(None, Panel::Disks, wait).as_deref(),
+        Some("No data yet (next poll in 1.2s)")
+    );
+    let m = metrics("");
+    assert_eq!(
+        empty_hint(Some(&m), Panel::Processes, wait).as_deref(),
+        Some("No data yet (next poll in 1.2s)")
+    );
+    // Older agents without collector flags fall back to neutral wording
+    assert_eq!(
+        empty_hint(Some(&m), Panel::Gpu, wait).as_deref(),
+        Some("No GPUs detected")
+    );
+}
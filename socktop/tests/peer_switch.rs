@@ -0,0 +1,67 @@
+//! Peer switcher: `get_peers` replies, popup keys and the token carried over to the peer URL.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use socktop::keymap::{action_for, Action};
+use socktop::peers::{switch_url, PeerOutcome, PeerPicker};
+use socktop::types::Peer;
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn reply() -> Vec<Peer> {
+    serde_json::from_str(
+        r#"[{"url":"","host":"edge","labels":{"env":"prod"},"seen_secs_ago":0,"self":true},
+            {"url":"ws://10.0.0.5:3000/ws","host":"web1","seen_secs_ago":12},
+            {"url":"ws://10.0.0.6:3000/ws","seen_secs_ago":null}]"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn get_peers_reply_parses() {
+    let peers = reply();
+    assert_eq!(peers.len(), 3);
+    assert!(peers[0].is_self);
+    assert_eq!(peers[1].host.as_deref(), Some("web1"));
+    assert_eq!(peers[1].seen_secs_ago, Some(12));
+    assert!(!peers[2].is_self);
+    assert_eq!(peers[2].host, None);
+}
+
+#[test]
+fn enter_switches_to_the_selected_peer_but_not_to_itself() {
+    assert_eq!(action_for(key(KeyCode::Char('f'))), Some(Action::ShowPeers));
+    let mut p = PeerPicker::default();
+    assert!(p.pending());
+    p.peers = Some(reply());
+    assert!(!p.pending());
+    assert_eq!(p.handle_key(key(KeyCode::Enter)), PeerOutcome::Close);
+
+    let mut p = PeerPicker {
+        peers: Some(reply()),
+        ..PeerPicker::default()
+    };
+    for _ in 0..5 {
+        assert_eq!(p.handle_key(key(KeyCode::Down)), PeerOutcome::Open);
+    }
+    assert_eq!(p.selected, 2);
+    p.handle_key(key(KeyCode::Up));
+    assert_eq!(
+        p.handle_key(key(KeyCode::Enter)),
+        PeerOutcome::Switch("ws://10.0.0.5:3000/ws".into())
+    );
+    assert_eq!(p.handle_key(key(KeyCode::Esc)), PeerOutcome::Close);
+}
+
+#[test]
+fn token_is_carried_over_to_the_peer() {
+    assert_eq!(
+        switch_url("wss://edge:3000/ws?token=s3cret", "wss://10.0.0.5:3000/ws"),
+        "wss://10.0.0.5:3000/ws?token=s3cret"
+    );
+    assert_eq!(
+        switch_url("ws://edge:3000/ws", "ws://10.0.0.5:3000/ws"),
+        "ws://10.0.0.5:3000/ws"
+    );
+    assert_eq!(switch_url("ws://edge/ws?token=t", "not a url"), "not a url");
+}
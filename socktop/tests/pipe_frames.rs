@@ -0,0 +1,46 @@
+//! Named pipe framing (the pipe itself is Windows-only; the frames work over any stream).
+use socktop::pipe::{parse_pipe_url, read_reply, write_request};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::Message;
+
+#[test]
+fn pipe_urls_name_the_pipe_and_token() {
+    assert_eq!(
+        parse_pipe_url("pipe://socktop_agent"),
+        Some((r"\\.\pipe\socktop_agent".to_string(), None))
+    );
+    assert_eq!(
+        parse_pipe_url("pipe://mon?token=s3cret"),
+        Some((r"\\.\pipe\mon".to_string(), Some("s3cret".to_string())))
+    );
+    assert_eq!(parse_pipe_url("pipe://"), None);
+    assert_eq!(parse_pipe_url("ws://host:3000/ws"), None);
+    assert!(socktop::ws::is_pipe_url("pipe://socktop_agent"));
+}
+
+#[tokio::test]
+async fn requests_and_replies_are_length_prefixed() {
+    let (mut client, mut agent) = tokio::io::duplex(1024);
+    write_request(&mut client, "get_metrics").await.unwrap();
+    assert_eq!(agent.read_u32_le().await.unwrap(), 11);
+    let mut cmd = [0u8; 11];
+    agent.read_exact(&mut cmd).await.unwrap();
+    assert_eq!(&cmd, b"get_metrics");
+
+    // Text reply, binary reply, then "no reply" (empty frame)
+    agent.write_u32_le(3).await.unwrap();
+    agent.write_all(&[0, b'o', b'k']).await.unwrap();
+    agent.write_u32_le(3).await.unwrap();
+    agent.write_all(&[1, 0x1f, 0x8b]).await.unwrap();
+    agent.write_u32_le(0).await.unwrap();
+
+    assert_eq!(
+        read_reply(&mut client).await,
+        Some(Message::Text("ok".into()))
+    );
+    assert_eq!(
+        read_reply(&mut client).await,
+        Some(Message::Binary(vec![0x1f, 0x8b]))
+    );
+    assert_eq!(read_reply(&mut client).await, None);
+}
@@ -0,0 +1,149 @@
+//! HTTP polling fallback: URL mapping, response framing and the session header, against a
+//! minimal HTTP server standing in for the agent's `/poll`.
+use socktop::poll::{poll_url, read_response, PollLink};
+use socktop::ws::{request_metrics, AgentLink};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+#[test]
+fn websocket_urls_map_to_the_poll_endpoint() {
+    let u = |s: &str| poll_url(s).map(|u| u.to_string());
+    assert_eq!(
+        u("ws://h:3000/ws?token=t").as_deref(),
+        Some("http://h:3000/poll?token=t")
+    );
+    assert_eq!(
+        u("wss://h:8443/socktop/ws").as_deref(),
+        Some("https://h:8443/socktop/poll")
+    );
+    assert_eq!(
+        u("ws://[::1]:3000/").as_deref(),
+        Some("http://[::1]:3000/poll")
+    );
+    assert_eq!(u("pipe://socktop_agent"), None);
+}
+
+#[tokio::test]
+async fn responses_with_length_chunks_or_no_body_parse() {
+    let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nx-socktop-session: ab\r\n\r\n\x00hi";
+    let r = read_response(&mut &raw[..]).await.unwrap();
+    assert_eq!(r.session.as_deref(), Some("ab"));
+    assert!(!r.close);
+    assert_eq!(r.into_message(), Some(Message::Text("hi".into())));
+
+    let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n\x01\x02\r\n1;x=y\r\n\x03\r\n0\r\n\r\n";
+    let r = read_response(&mut &raw[..]).await.unwrap();
+    assert_eq!(r.into_message(), Some(Message::Binary(vec![2, 3])));
+
+    let raw = b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+    let r = read_response(&mut &raw[..]).await.unwrap();
+    assert!(r.close);
+    assert_eq!(r.into_message(), None);
+
+    let raw = b"HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n";
+    assert_eq!(
+        read_response(&mut &raw[..]).await.unwrap().into_message(),
+        None
+    );
+}
+
+// Serve `replies` in order, one per request, over as many connections as the client opens.
+// Each request's head and body are sent back through the channel.
+async fn fake_agent(
+    replies: Vec<String>,
+) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut replies = replies.into_iter();
+        'conns: while let Ok((sock, _)) = listener.accept().await {
+            let mut sock = BufReader::new(sock);
+            loop {
+                let mut head = String::new();
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    if sock.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        continue 'conns;
+                    }
+                    if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        len = v.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let mut body = vec![0; len];
+                sock.read_exact(&mut body).await.unwrap();
+                head.push_str(&String::from_utf8(body).unwrap());
+                let _ = tx.send(head);
+                let Some(reply) = replies.next() else {
+                    return;
+                };
+                let close = reply.contains("Connection: close");
+                sock.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                if close {
+                    continue 'conns;
+                }
+            }
+        }
+    });
+    (format!("ws://127.0.0.1:{port}/ws?token=t"), rx)
+}
+
+fn text_reply(body: &str, extra: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n{extra}Content-Length: {}\r\n\r\n\x00{body}",
+        body.len() + 1
+    )
+}
+
+#[tokio::test]
+async fn link_posts_commands_and_echoes_the_session() {
+    let metrics = r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,"swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],"top_processes":[],"gpus":null}"#;
+    let (url, mut seen) = fake_agent(vec![
+        text_reply("{}", "x-socktop-session: s1\r\n"),
+        // The agent closes after this one; the next request reconnects
+        text_reply(metrics, "Connection: close\r\n"),
+        "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+    ])
+    .await;
+
+    let mut link = <PollLink as AgentLink>::open(&url, None).await.unwrap();
+    assert_eq!(
+        link.exchange("hello").await,
+        Some(Message::Text("{}".into()))
+    );
+    let first = seen.recv().await.unwrap();
+    assert!(
+        first.starts_with("POST /poll?token=t HTTP/1.1\r\n"),
+        "{first}"
+    );
+    assert!(first.ends_with("\r\nhello"));
+    assert!(!first.contains("x-socktop-session"));
+
+    let m = request_metrics(&mut link).await.unwrap();
+    assert_eq!(m.hostname, "h");
+    let second = seen.recv().await.unwrap();
+    assert!(second.contains("x-socktop-session: s1\r\n"));
+    assert!(second.ends_with("get_metrics"));
+
+    assert_eq!(link.exchange("unknown").await, None);
+    assert!(seen
+        .recv()
+        .await
+        .unwrap()
+        .contains("x-socktop-session: s1\r\n"));
+}
+
+#[tokio::test]
+async fn probe_fails_without_a_poll_endpoint() {
+    let (url, _seen) = fake_agent(vec![
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+    ])
+    .await;
+    assert!(PollLink::probe(&url, None).await.is_none());
+}
@@ -0,0 +1,104 @@
+//! Per-PID CPU trend: rising/falling calls over the last few polls, and the glyph in the table.
+use std::time::Duration;
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use socktop::proc_trend::{ProcTrends, Trend, TREND_SAMPLES};
+use socktop::types::{Metrics, ProcessInfo};
+use socktop::ui::processes::{draw_top_processes, ProcSortBy};
+
+fn row(pid: u32, cpu: f32) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: format!("p{pid}"),
+        cpu_usage: cpu,
+        mem_bytes: 0,
+        cpu_time_ms: 0,
+    }
+}
+
+fn feed(t: &mut ProcTrends, polls: &[&[(u32, f32)]]) {
+    for poll in polls {
+        let rows: Vec<_> = poll.iter().map(|&(pid, cpu)| row(pid, cpu)).collect();
+        t.record(&rows);
+    }
+}
+
+#[test]
+fn ramping_and_settling_processes_are_told_apart() {
+    let mut t = ProcTrends::default();
+    feed(
+        &mut t,
+        &[
+            &[(1, 5.0), (2, 80.0), (3, 0.4)],
+            &[(1, 10.0), (2, 60.0), (3, 1.2)],
+            &[(1, 30.0), (2, 20.0), (3, 0.3)],
+            &[(1, 55.0), (2, 5.0), (3, 1.0)],
+        ],
+    );
+    assert_eq!(t.trend(1), Trend::Rising);
+    assert_eq!(t.trend(2), Trend::Falling);
+    // Jitter below the minimum change is not a trend
+    assert_eq!(t.trend(3), Trend::Steady);
+    assert_eq!(t.trend(99), Trend::Steady);
+}
+
+#[test]
+fn too_few_samples_and_vanished_pids_are_steady() {
+    let mut t = ProcTrends::default();
+    feed(&mut t, &[&[(1, 1.0)], &[(1, 90.0)]]);
+    assert_eq!(t.trend(1), Trend::Steady);
+    feed(&mut t, &[&[(1, 95.0)]]);
+    assert_eq!(t.trend(1), Trend::Rising);
+    // Gone from a poll: its history is dropped and starts over
+    feed(&mut t, &[&[(2, 1.0)], &[(1, 95.0)], &[(1, 96.0)]]);
+    assert_eq!(t.trend(1), Trend::Steady);
+}
+
+#[test]
+fn only_the_last_samples_count() {
+    let mut t = ProcTrends::default();
+    let busy: Vec<&[(u32, f32)]> = vec![&[(1, 90.0)]; 3];
+    feed(&mut t, &busy);
+    let steady: Vec<&[(u32, f32)]> = vec![&[(1, 10.0)]; TREND_SAMPLES];
+    feed(&mut t, &steady);
+    assert_eq!(t.trend(1), Trend::Steady);
+}
+
+#[test]
+fn glyph_follows_the_cpu_value() {
+    let m: Metrics = serde_json::from_str(
+        r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1000,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[{"pid":7,"name":"busy","cpu_usage":80.0,"mem_bytes":300}],
+            "gpus":null}"#,
+    )
+    .unwrap();
+    let mut t = ProcTrends::default();
+    feed(&mut t, &[&[(7, 10.0)], &[(7, 40.0)], &[(7, 80.0)]]);
+    let mut term = Terminal::new(TestBackend::new(80, 6)).unwrap();
+    let mut offset = 0;
+    term.draw(|f| {
+        draw_top_processes(
+            f,
+            f.area(),
+            Some(&m),
+            &mut offset,
+            ProcSortBy::CpuDesc,
+            None,
+            Duration::ZERO,
+            true,
+            None,
+            Some(&t),
+        )
+    })
+    .unwrap();
+    let text: String = term
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|c| c.symbol())
+        .collect();
+    assert!(text.contains("▇  80.0↑"), "{text}");
+}
@@ -0,0 +1,51 @@
+//! The Name column follows its content: narrow terminals give it everything the fixed
+//! columns leave, wide ones stop at the longest name instead of padding 40% of the screen.
+use socktop::types::{Metrics, ProcessInfo};
+use socktop::ui::processes::{name_column_width, NAME_MAX, NAME_MIN};
+
+fn metrics_with(names: &[&str]) -> Metrics {
+    let mut m: Metrics = serde_json::from_str(
+        r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[],"gpus":null}"#,
+    )
+    .unwrap();
+    m.top_processes = names
+        .iter()
+        .enumerate()
+        .map(|(i, n)| ProcessInfo {
+            pid: i as u32,
+            name: n.to_string(),
+            cpu_usage: 0.0,
+            mem_bytes: 0,
+            cpu_time_ms: 0,
+        })
+        .collect();
+    m
+}
+
+#[test]
+fn wide_terminals_fit_the_longest_name() {
+    let m = metrics_with(&["sshd", "systemd-journald", "kworker/0:1"]);
+    assert_eq!(name_column_width(&m, 200), 16);
+    // Short names still leave room for the "Name" header
+    assert_eq!(name_column_width(&metrics_with(&["sh"]), 200), NAME_MIN);
+    let long = "x".repeat(500);
+    assert_eq!(name_column_width(&metrics_with(&[&long]), 300), NAME_MAX);
+}
+
+#[test]
+fn narrow_terminals_give_name_the_leftover_space() {
+    let long = "postgres: autovacuum launcher ".repeat(2);
+    let m = metrics_with(&[&long]);
+    // 80 columns minus 46 fixed (PID, CPU %, Mem, Mem %, Time+) and 5 separators
+    assert_eq!(name_column_width(&m, 80), 29);
+    // Never below the minimum, even when nothing is left
+    assert_eq!(name_column_width(&m, 20), NAME_MIN);
+}
+
+#[test]
+fn wide_characters_count_double() {
+    let m = metrics_with(&["データベース"]);
+    assert_eq!(name_column_width(&m, 200), 12);
+}
@@ -0,0 +1,96 @@
+//! Process detail popup: env/open-files replies decode and render, hidden values stay hidden.
+use socktop::types::{ProcessEnv, ProcessFds};
+use socktop::ui::proc_detail::ProcessDetail;
+use socktop::ws::{request_process_env, request_process_fds, AgentLink};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Answers every request with the same text frame and remembers the commands.
+struct Canned {
+    reply: String,
+    sent: Vec<String>,
+}
+
+impl AgentLink for Canned {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("canned link".into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.sent.push(cmd.to_string());
+        Some(Message::Text(self.reply.clone()))
+    }
+}
+
+fn text(d: &ProcessDetail) -> Vec<String> {
+    d.lines().iter().map(|l| l.to_string()).collect()
+}
+
+#[tokio::test]
+async fn replies_are_requested_by_pid_and_decoded() {
+    let mut link = Canned {
+        reply: r#"{"schema_version":1,"pid":42,"vars":[{"name":"PATH","value":"/bin"},{"name":"API_KEY"}]}"#.into(),
+        sent: Vec::new(),
+    };
+    let env = request_process_env(&mut link, 42).await.unwrap();
+    assert_eq!(env.vars.len(), 2);
+    assert_eq!(env.vars[1].value, None);
+
+    link.reply = r#"{"pid":42,"fds":[{"fd":0,"target":"/dev/null"},{"fd":3,"target":"socket:[991]"}],"truncated":true}"#.into();
+    let fds = request_process_fds(&mut link, 42).await.unwrap();
+    assert!(fds.truncated);
+    assert_eq!(fds.fds[1].target, "socket:[991]");
+    assert_eq!(
+        link.sent,
+        ["get_process_env?pid=42", "get_process_fds?pid=42"]
+    );
+}
+
+#[test]
+fn popup_lists_env_then_fds() {
+    let env: ProcessEnv = serde_json::from_str(
+        r#"{"pid":42,"vars":[{"name":"PATH","value":"/bin"},{"name":"API_KEY"}]}"#,
+    )
+    .unwrap();
+    let fds: ProcessFds = serde_json::from_str(
+        r#"{"pid":42,"fds":[{"fd":3,"target":"/var/log/app.log"}],"truncated":false}"#,
+    )
+    .unwrap();
+    let mut d = ProcessDetail {
+        pid: 42,
+        name: "app".into(),
+        ..Default::default()
+    };
+    assert!(d.pending());
+    assert_eq!(text(&d), ["loading…"]);
+
+    d.env = Some(env);
+    d.fds = Some(fds);
+    assert!(!d.pending());
+    let lines = text(&d);
+    assert_eq!(lines[0], "Environment (2)");
+    assert_eq!(lines[1], "  PATH=/bin");
+    assert_eq!(lines[2], "  API_KEY=(hidden)");
+    assert!(lines.contains(&"Open files (1)".to_string()));
+    assert!(lines.contains(&"     3 → /var/log/app.log".to_string()));
+}
+
+#[test]
+fn errors_and_notes_replace_the_lists() {
+    let env: ProcessEnv =
+        serde_json::from_str(r#"{"pid":1,"vars":[],"error":"needs a control-scope token"}"#)
+            .unwrap();
+    let d = ProcessDetail {
+        pid: 1,
+        env: Some(env),
+        fds: Some(ProcessFds::default()),
+        ..Default::default()
+    };
+    assert_eq!(text(&d)[1], "  needs a control-scope token");
+
+    let d = ProcessDetail {
+        note: Some("Select a process first (click its row)".into()),
+        ..Default::default()
+    };
+    assert!(!d.pending());
+    assert_eq!(text(&d), ["Select a process first (click its row)"]);
+}
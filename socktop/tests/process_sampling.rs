@@ -0,0 +1,117 @@
+//! Sampled process polls: the token bucket sizes tail slices to sweep the tail once a minute,
+//! and merged tables keep every process seen without keeping the ones that exited.
+use std::time::{Duration, Instant};
+
+use prost::encoding::{bool as pbool, bytes, string, uint32, uint64};
+use socktop::proc_sample::{TailSampler, SWEEP};
+use socktop::types::ProcessInfo;
+use socktop::ws::{request_processes_sampled, AgentLink};
+use tokio_tungstenite::tungstenite::Message;
+
+fn row(pid: u32) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: format!("p{pid}"),
+        cpu_usage: 0.0,
+        mem_bytes: 0,
+        cpu_time_ms: 0,
+    }
+}
+
+fn rows(pids: &[u32]) -> Vec<ProcessInfo> {
+    pids.iter().map(|&p| row(p)).collect()
+}
+
+fn pids(rows: &[ProcessInfo]) -> Vec<u32> {
+    rows.iter().map(|p| p.pid).collect()
+}
+
+#[test]
+fn bucket_sweeps_the_tail_once_per_minute() {
+    let mut s = TailSampler::default();
+    let t0 = Instant::now();
+    // Tail size unknown on the first poll: a small fixed burst
+    assert_eq!(s.take(t0, 0), 64);
+    // 6000 tail rows over 60s = 100 rows a second, whatever the poll spacing
+    assert_eq!(s.take(t0 + Duration::from_secs(2), 6_000), 200);
+    assert_eq!(s.take(t0 + Duration::from_millis(2_500), 6_000), 50);
+    // Idle for a long while: saved tokens are capped at ten seconds' worth
+    assert_eq!(s.take(t0 + Duration::from_secs(600), 6_000), 1_000);
+    // Never more than the tail itself
+    assert_eq!(s.take(t0 + Duration::from_secs(1_000), 10), 10);
+
+    let mut total = 0;
+    let mut s = TailSampler::default();
+    s.take(t0, 6_000);
+    for i in 1..=30 {
+        total += s.take(t0 + Duration::from_secs(2 * i), 6_000);
+    }
+    assert_eq!(total, 6_000);
+}
+
+#[test]
+fn merged_table_keeps_sampled_rows_and_drops_exited_ones() {
+    let mut s = TailSampler::default();
+    let t = Instant::now();
+    // 1 top row and a tail of 2..=9; first slice after 0 is 2, 3, 4
+    let out = s.merge(t, rows(&[1]), rows(&[2, 3, 4]), 9);
+    assert_eq!(pids(&out), [1, 2, 3, 4]);
+    assert_eq!(s.after(), 4);
+
+    let out = s.merge(t, rows(&[1]), rows(&[5, 6, 8]), 9);
+    assert_eq!(pids(&out), [1, 2, 3, 4, 5, 6, 8]);
+
+    // Wraps: 9, then 2 and 4 (3 exited and is dropped); 5..8 stay from the last slice
+    let out = s.merge(t, rows(&[1]), rows(&[9, 2, 4]), 8);
+    assert_eq!(pids(&out), [1, 2, 4, 5, 6, 8, 9]);
+    assert_eq!(s.after(), 4);
+
+    // A sampled process that became a top row isn't listed twice
+    let out = s.merge(t, rows(&[1, 6]), rows(&[5]), 8);
+    assert_eq!(pids(&out), [1, 6, 2, 4, 5, 8, 9]);
+
+    // Rows not refreshed for two sweeps age out
+    let out = s.merge(t + SWEEP * 2, rows(&[1]), rows(&[8]), 8);
+    assert_eq!(pids(&out), [1, 8]);
+
+    // A reply with every process replaces the cache
+    let out = s.merge(t + SWEEP * 2, rows(&[1]), rows(&[7]), 2);
+    assert_eq!(pids(&out), [1, 7]);
+}
+
+/// Answers with one canned frame and remembers the command.
+struct Canned(Vec<u8>, Vec<String>);
+
+impl AgentLink for Canned {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("canned link".into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.1.push(cmd.to_string());
+        Some(Message::Binary(self.0.clone()))
+    }
+}
+
+#[tokio::test]
+async fn sampled_rows_are_split_from_the_top() {
+    let mut msg = Vec::new();
+    uint64::encode(1, &5000, &mut msg);
+    for (pid, sampled) in [(7u32, false), (31, true), (40, true)] {
+        let mut r = Vec::new();
+        uint32::encode(1, &pid, &mut r);
+        string::encode(2, &format!("p{pid}"), &mut r);
+        if sampled {
+            pbool::encode(6, &true, &mut r);
+        }
+        bytes::encode(2, &r, &mut msg);
+    }
+    let mut link = Canned(msg, Vec::new());
+    let p = request_processes_sampled(&mut link, 100, "mem", 2, 30)
+        .await
+        .unwrap();
+    assert_eq!(link.1, ["get_processes?top=100&sort=mem&tail=2&after=30"]);
+    assert_eq!(p.process_count, 5000);
+    assert_eq!(pids(&p.top_processes), [7]);
+    assert_eq!(pids(&p.tail), [31, 40]);
+}
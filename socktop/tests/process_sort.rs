@@ -0,0 +1,82 @@
+//! Process table ordering is total, so equal rows keep their place across refreshes.
+use socktop::types::{Metrics, ProcessInfo};
+use socktop::ui::processes::{fmt_cpu_time, sorted_rows, ProcSortBy};
+
+fn row(pid: u32, cpu: f32, mem: u64) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: format!("p{pid}"),
+        cpu_usage: cpu,
+        mem_bytes: mem,
+        cpu_time_ms: 0,
+    }
+}
+
+fn order(rows: &mut [ProcessInfo], by_mem: bool) -> Vec<u32> {
+    rows.sort_by(|a, b| a.table_cmp(b, by_mem));
+    rows.iter().map(|r| r.pid).collect()
+}
+
+#[test]
+fn equal_cpu_breaks_ties_by_mem_then_pid() {
+    let mut rows = vec![
+        row(9, 0.0, 10),
+        row(3, 5.0, 10),
+        row(7, 0.0, 10),
+        row(5, 0.0, 20),
+    ];
+    assert_eq!(order(&mut rows, false), vec![3, 5, 7, 9]);
+    // Same result whatever order the payload arrived in
+    rows.reverse();
+    assert_eq!(order(&mut rows, false), vec![3, 5, 7, 9]);
+}
+
+#[test]
+fn mem_sort_breaks_ties_by_cpu_then_pid() {
+    let mut rows = vec![
+        row(4, 1.0, 50),
+        row(2, 1.0, 50),
+        row(8, 9.0, 50),
+        row(1, 0.0, 99),
+    ];
+    assert_eq!(order(&mut rows, true), vec![1, 8, 2, 4]);
+}
+
+#[test]
+fn time_sort_puts_long_running_idle_processes_first() {
+    let mut m: Metrics = serde_json::from_str(
+        r#"{"cpu_total":1.0,"cpu_per_core":[],"mem_total":1,"mem_used":0,"swap_total":0,
+            "swap_used":0,"hostname":"h","cpu_temp_c":null,"disks":[],"networks":[],
+            "top_processes":[],"gpus":null}"#,
+    )
+    .unwrap();
+    m.top_processes = vec![
+        row(1, 90.0, 10),
+        ProcessInfo {
+            cpu_time_ms: 7_200_000,
+            ..row(2, 0.0, 10)
+        },
+        ProcessInfo {
+            cpu_time_ms: 60_000,
+            ..row(3, 5.0, 10)
+        },
+    ];
+    let pids = |sort| -> Vec<u32> {
+        sorted_rows(&m, sort)
+            .into_iter()
+            .map(|i| m.top_processes[i].pid)
+            .collect()
+    };
+    assert_eq!(pids(ProcSortBy::TimeDesc), vec![2, 3, 1]);
+    assert_eq!(pids(ProcSortBy::CpuDesc), vec![1, 3, 2]);
+    assert_eq!(ProcSortBy::TimeDesc.agent_key(), "time");
+}
+
+#[test]
+fn cpu_time_reads_like_top() {
+    assert_eq!(fmt_cpu_time(0), "0:00.00");
+    assert_eq!(fmt_cpu_time(83_450), "1:23.45");
+    assert_eq!(fmt_cpu_time(7_200_000), "120:00.00");
+    // 10,000 minutes and up: whole hours
+    assert_eq!(fmt_cpu_time(600_000_000), "166h");
+}
@@ -0,0 +1,87 @@
+//! Processes over JSON: agents from before the protobuf reply (or serving JSON with
+//! `--processes-json`) must decode like protobuf ones, and hello asks for protobuf.
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+
+use socktop::ws::{request_hello, request_processes, request_processes_sampled, AgentLink};
+use tokio_tungstenite::tungstenite::Message;
+
+const OLD_REPLY: &str = r#"{"process_count":2,"top_processes":[
+    {"pid":10,"name":"nginx","cpu_usage":3.5,"mem_bytes":4096},
+    {"pid":11,"name":"sshd","cpu_usage":0.5,"mem_bytes":1024}]}"#;
+
+/// Answers every request with one canned frame and remembers the commands.
+struct Canned {
+    reply: Message,
+    sent: Vec<String>,
+}
+
+impl Canned {
+    fn new(reply: Message) -> Self {
+        Canned {
+            reply,
+            sent: Vec::new(),
+        }
+    }
+}
+
+impl AgentLink for Canned {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("canned link".into())
+    }
+
+    async fn exchange(&mut self, cmd: &str) -> Option<Message> {
+        self.sent.push(cmd.to_string());
+        Some(self.reply.clone())
+    }
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::fast());
+    enc.write_all(bytes).unwrap();
+    enc.finish().unwrap()
+}
+
+#[tokio::test]
+async fn json_processes_decode_as_text_raw_or_gzipped_frames() {
+    for reply in [
+        Message::Text(OLD_REPLY.into()),
+        Message::Binary(OLD_REPLY.as_bytes().to_vec()),
+        Message::Binary(gzip(OLD_REPLY.as_bytes())),
+    ] {
+        let p = request_processes(&mut Canned::new(reply))
+            .await
+            .expect("decodes");
+        assert_eq!(p.process_count, 2);
+        let names: Vec<&str> = p.top_processes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["nginx", "sshd"]);
+        // Agents that old don't report CPU time
+        assert_eq!(p.top_processes[0].cpu_time_ms, 0);
+    }
+}
+
+#[tokio::test]
+async fn json_tail_samples_land_in_tail() {
+    let body = r#"{"schema_version":1,"process_count":9,
+        "top_processes":[{"pid":1,"name":"init","cpu_usage":1.0,"mem_bytes":1,"cpu_time_ms":5}],
+        "tail":[{"pid":7,"name":"cron","cpu_usage":0.0,"mem_bytes":1,"cpu_time_ms":1}]}"#;
+    let mut link = Canned::new(Message::Binary(gzip(body.as_bytes())));
+    let p = request_processes_sampled(&mut link, 1, "cpu", 1, 0)
+        .await
+        .unwrap();
+    assert_eq!(p.top_processes[0].pid, 1);
+    assert_eq!(p.tail[0].pid, 7);
+}
+
+#[tokio::test]
+async fn hello_asks_for_protobuf_processes() {
+    let reply = Message::Text(r#"{"scope":"read","session":"s1","processes":"pb"}"#.into());
+    let mut link = Canned::new(reply);
+    let h = request_hello(&mut link, None).await.unwrap();
+    assert_eq!(h.session.as_deref(), Some("s1"));
+    request_hello(&mut link, Some("s1")).await.unwrap();
+    assert_eq!(
+        link.sent,
+        ["hello?processes=pb", "hello?processes=pb&resume=s1"]
+    );
+}
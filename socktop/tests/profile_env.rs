@@ -0,0 +1,45 @@
+//! Per-profile environment overrides: parsing from profiles.json and which ones get applied.
+use serde_json::json;
+use socktop::profiles::{env_overrides, ProfileEntry, ProfilesFile};
+
+#[test]
+fn env_is_read_from_the_profile_and_omitted_when_empty() {
+    let pf: ProfilesFile = serde_json::from_value(json!({
+        "profiles": {
+            "slow-link": {
+                "url": "ws://edge:3000/ws",
+                "env": { "SOCKTOP_MAX_BANDWIDTH": "10kbps", "SOCKTOP_DEBUG": "1" }
+            },
+            "plain": { "url": "ws://plain:3000/ws" }
+        }
+    }))
+    .unwrap();
+    let slow = &pf.profiles["slow-link"];
+    assert_eq!(slow.env["SOCKTOP_MAX_BANDWIDTH"], "10kbps");
+    assert!(pf.profiles["plain"].env.is_empty());
+
+    let out = serde_json::to_value(ProfileEntry {
+        url: "ws://plain:3000/ws".into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(out.get("env").is_none());
+}
+
+#[test]
+fn only_unset_socktop_variables_are_applied() {
+    let entry: ProfileEntry = serde_json::from_value(json!({
+        "url": "ws://big:3000/ws",
+        "env": {
+            "SOCKTOP_A11Y": "1",
+            "SOCKTOP_DEBUG": "1",
+            "SOCKTOP_FRAME_CHECK": "1",
+            "LD_PRELOAD": "/tmp/evil.so"
+        }
+    }))
+    .unwrap();
+    // --frame-check was given on the command line, so its variable is already set
+    let (apply, ignored) = env_overrides(&entry.env, |k| k == "SOCKTOP_FRAME_CHECK");
+    assert_eq!(apply, vec![("SOCKTOP_A11Y", "1"), ("SOCKTOP_DEBUG", "1")]);
+    assert_eq!(ignored, vec!["LD_PRELOAD"]);
+}
@@ -8,19 +8,40 @@ static ENV_LOCK: Mutex<()> = Mutex::new(());
 #[allow(dead_code)] // touch crate
 fn touch() {
     let _ = socktop::types::Metrics {
+        schema_version: 0,
         cpu_total: 0.0,
         cpu_per_core: vec![],
+        offline_cores: vec![],
+        cpu_irq: None,
+        procs_running: None,
+        procs_blocked: None,
+        fd: None,
+        entropy: None,
+        clock: None,
+        updates: None,
+        reboot_required: None,
         mem_total: 0,
         mem_used: 0,
         swap_total: 0,
         swap_used: 0,
         process_count: None,
         hostname: String::new(),
+        uptime_secs: None,
+        load_avg: None,
+        machine_id: None,
+        boot_id: None,
         cpu_temp_c: None,
+        cpu_governor: None,
         disks: vec![],
         networks: vec![],
+        sample_ms: None,
         top_processes: vec![],
         gpus: None,
+        meta: None,
+        instance: None,
+        labels: Default::default(),
+        capabilities: vec![],
+        collector_errors: Default::default(),
     };
 }
 
@@ -97,6 +118,24 @@ fn test_profile_overwrite_only_when_changed() {
     assert!(third.contains("two"), "Updated URL not written: {third}");
 }
 
+#[cfg(unix)]
+#[test]
+fn test_profiles_file_is_owner_only() {
+    use std::os::unix::fs::PermissionsExt;
+    let _guard = ENV_LOCK.lock().unwrap();
+    let td = tempfile::tempdir().unwrap();
+    std::env::set_var("XDG_CONFIG_HOME", td.path());
+    std::fs::create_dir_all(td.path().join("socktop")).unwrap();
+    let mode = || fs::metadata(profiles_path()).unwrap().permissions().mode() & 0o777;
+
+    let _ = run_socktop(&["--profile", "a", "ws://one/ws", "--dry-run"]);
+    assert_eq!(mode(), 0o600);
+    // A world-readable file from an older version is tightened on the next save
+    fs::set_permissions(profiles_path(), fs::Permissions::from_mode(0o644)).unwrap();
+    let _ = run_socktop(&["--profile", "b", "ws://two/ws", "--dry-run"]);
+    assert_eq!(mode(), 0o600);
+}
+
 #[test]
 fn test_profile_tls_ca_persisted() {
     let _guard = ENV_LOCK.lock().unwrap();
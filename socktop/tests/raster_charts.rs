@@ -0,0 +1,82 @@
+//! --graphics: protocol detection and the kitty/sixel chart encoders.
+use std::collections::HashMap;
+
+use socktop::ui::raster::{detect, kitty, plot, sixel, Protocol, EMPTY, FILL, LINE};
+
+fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let map: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    move |k| map.get(k).cloned()
+}
+
+#[test]
+fn auto_picks_the_protocol_from_the_environment() {
+    let kitty_env = env_of(&[("TERM", "xterm-kitty"), ("KITTY_WINDOW_ID", "1")]);
+    assert_eq!(detect("auto", &kitty_env), Some(Protocol::Kitty));
+    let wezterm = env_of(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]);
+    assert_eq!(detect("auto", &wezterm), Some(Protocol::Kitty));
+    assert_eq!(
+        detect("auto", env_of(&[("TERM", "foot")])),
+        Some(Protocol::Sixel)
+    );
+    // Unknown terminals and multiplexers keep the sparklines
+    assert_eq!(detect("auto", env_of(&[("TERM", "xterm-256color")])), None);
+    let tmux = env_of(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux-0/default")]);
+    assert_eq!(detect("auto", tmux), None);
+    // Explicit modes don't look at the environment
+    assert_eq!(detect("sixel", env_of(&[])), Some(Protocol::Sixel));
+    assert_eq!(detect("kitty", env_of(&[])), Some(Protocol::Kitty));
+    assert_eq!(detect("off", env_of(&[("TERM", "xterm-kitty")])), None);
+}
+
+#[test]
+fn plot_draws_the_curve_with_fill_below() {
+    // Flat half-height line over a 4x5 image: row 2 is the line, rows 3..5 the fill
+    let px = plot(&[50, 50], 100, 4, 5);
+    let rows: Vec<&[u8]> = px.chunks(4).collect();
+    assert!(rows[0].iter().chain(rows[1]).all(|&p| p == EMPTY));
+    assert!(rows[2].iter().all(|&p| p == LINE));
+    assert!(rows[3].iter().chain(rows[4]).all(|&p| p == FILL));
+
+    // A jump from 0 to max is joined by a vertical run, without gaps
+    let px = plot(&[0, 100], 100, 2, 4);
+    assert_eq!(px[3 * 2], LINE);
+    assert!((0..4).all(|row| px[row * 2 + 1] == LINE));
+
+    assert!(plot(&[], 100, 3, 3).iter().all(|&p| p == EMPTY));
+}
+
+#[test]
+fn kitty_escape_is_chunked_and_compressed() {
+    let (w, h) = (400, 200);
+    let px = plot(&(0..100).collect::<Vec<u64>>(), 100, w, h);
+    let esc = kitty(7, &px, w, h, [0, 205, 205], 50, 10);
+    assert!(esc.starts_with("\x1b_Ga=T,f=32,o=z,s=400,v=200,i=7,c=50,r=10,C=1,q=2,"));
+    let parts: Vec<&str> = esc.split("\x1b\\").filter(|p| !p.is_empty()).collect();
+    for (i, part) in parts.iter().enumerate() {
+        let (head, payload) = part.split_once(';').unwrap();
+        let more = if i + 1 < parts.len() { "m=1" } else { "m=0" };
+        assert!(head.ends_with(more), "{head}");
+        assert!(payload.len() <= 4096);
+        assert!(payload
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"+/=".contains(&b)));
+    }
+    // 320 KB of RGBA, mostly transparent, compresses to far less
+    assert!(esc.len() < 64 * 1024, "{} bytes", esc.len());
+}
+
+#[test]
+fn sixel_escape_has_size_palette_and_bands() {
+    let px = plot(&[100, 100], 100, 3, 12);
+    let esc = sixel(&px, 3, 12, [255, 0, 0]);
+    assert!(esc.starts_with("\x1bP0;0;0q\"1;1;3;12#1;2;40;0;0#2;2;100;0;0"));
+    assert!(esc.ends_with("\x1b\\"));
+    // 12 rows = two six-pixel bands
+    assert_eq!(esc.matches('-').count(), 2);
+    // Top band: the top row is line (bit 0 -> '@'), the five below it fill (bits 1..5 -> '}')
+    assert!(esc.contains("#2@@@$"));
+    assert!(esc.contains("#1}}}$"));
+}
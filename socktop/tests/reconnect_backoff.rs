@@ -0,0 +1,32 @@
+//! Reconnect backoff: immediate first try, then doubling waits up to a cap.
+use std::time::{Duration, Instant};
+
+use socktop::reconnect::{delay, Backoff, FIRST_RETRY, MAX_RETRY};
+
+#[test]
+fn waits_double_up_to_the_cap() {
+    let secs: Vec<u64> = (1..=7).map(|n| delay(n).as_secs()).collect();
+    assert_eq!(secs, [1, 2, 4, 8, 16, 30, 30]);
+    assert_eq!(delay(u32::MAX), MAX_RETRY);
+    assert_eq!(delay(0), FIRST_RETRY);
+}
+
+#[test]
+fn attempts_are_due_after_the_wait_and_the_header_counts_down() {
+    let t0 = Instant::now();
+    let mut b = Backoff::new(t0);
+    assert!(b.due(t0));
+    assert_eq!(b.status(t0), "⟳ reconnecting…");
+
+    b.failed(t0);
+    assert!(!b.due(t0 + Duration::from_millis(999)));
+    assert!(b.due(t0 + Duration::from_secs(1)));
+
+    let t1 = t0 + Duration::from_secs(1);
+    b.failed(t1);
+    assert_eq!(
+        b.status(t1 + Duration::from_millis(500)),
+        "⟳ reconnecting… (2 failed, next try in 2s)"
+    );
+    assert!(b.due(t1 + Duration::from_secs(2)));
+}
@@ -0,0 +1,137 @@
+//! `--record` / `--replay`: the session file and playing it back.
+use socktop::recording::{self, Kind};
+use socktop::replay::Playback;
+use socktop::types::{DiskInfo, Metrics};
+
+fn metrics(cpu: f32) -> String {
+    format!(
+        r#"{{"cpu_total":{cpu},"cpu_per_core":[{cpu}],"mem_total":100,"mem_used":40,
+"swap_total":0,"swap_used":0,"hostname":"pi4","cpu_temp_c":null,"disks":[],"networks":[],
+"top_processes":[],"gpus":null,"capabilities":["hello","disks_delta"]}}"#
+    )
+}
+
+// Metrics every second for 30s; processes after every fifth sample
+fn session() -> String {
+    let mut out = String::from(
+        r#"{"socktop_recording":1,"url":"ws://pi4:3000/ws","started_unix":1700000000}"#,
+    );
+    for s in 0..30u64 {
+        let m = metrics(s as f32).replace('\n', "");
+        out.push_str(&format!(
+            "\n{{\"at_ms\":{},\"kind\":\"metrics\",\"data\":{m}}}",
+            s * 1000
+        ));
+        if s % 5 == 0 {
+            out.push_str(&format!(
+                "\n{{\"at_ms\":{},\"kind\":\"processes\",\"data\":{{\"process_count\":{s},\"top_processes\":[]}}}}",
+                s * 1000 + 10
+            ));
+        }
+    }
+    out
+}
+
+fn cpu_of(reply: Option<String>) -> f32 {
+    serde_json::from_str::<Metrics>(&reply.expect("a sample"))
+        .unwrap()
+        .cpu_total
+}
+
+#[test]
+fn recorded_samples_load_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.skt");
+    recording::configure(&path, "ws://pi4:3000/ws").unwrap();
+    // Pretty-printed replies still take one line each
+    recording::record(Kind::Metrics, &metrics(12.5));
+    let disks = vec![DiskInfo {
+        name: "/dev/sda1".into(),
+        total: 10,
+        available: 4,
+    }];
+    recording::record_value(Kind::Disks, &disks);
+
+    let rec = recording::load(&path).unwrap();
+    assert_eq!(rec.url, "ws://pi4:3000/ws");
+    let kinds: Vec<Kind> = rec.samples.iter().map(|s| s.kind).collect();
+    assert_eq!(kinds, [Kind::Metrics, Kind::Disks]);
+    assert_eq!(rec.samples[1].data[0]["name"], "/dev/sda1");
+
+    let mut p = Playback::new(rec);
+    assert_eq!(cpu_of(p.answer("get_metrics")), 12.5);
+    assert!(p.answer("get_disks").unwrap().contains("sda1"));
+    assert!(p.finished());
+}
+
+#[test]
+fn truncated_tail_is_dropped_but_newer_formats_are_refused() {
+    let mut text = session();
+    text.push_str("\n{\"at_ms\":30000,\"kind\":\"met");
+    let rec = recording::parse(&text).unwrap();
+    assert_eq!(rec.samples.len(), 36);
+
+    let newer = text.replacen("\"socktop_recording\":1", "\"socktop_recording\":9", 1);
+    assert!(recording::parse(&newer).unwrap_err().contains("newer"));
+    assert!(recording::parse("cpu 12%\n").is_err());
+}
+
+#[test]
+fn playback_serves_samples_in_order_with_their_processes() {
+    let mut p = Playback::new(recording::parse(&session()).unwrap());
+    assert_eq!(p.url(), "ws://pi4:3000/ws");
+    assert_eq!(p.sample_interval(), Some(std::time::Duration::from_secs(1)));
+    assert_eq!(cpu_of(p.answer("get_metrics")), 0.0);
+    // Capabilities are stripped so the client only asks for what the file holds
+    assert!(!p.answer("get_metrics").unwrap().contains("capabilities"));
+    // Processes are the latest recorded with (or before) the sample on screen
+    let procs = p.answer("get_processes?top=50").unwrap();
+    assert!(procs.contains("\"process_count\":0"));
+    for _ in 0..5 {
+        p.answer("get_metrics");
+    }
+    assert!(p
+        .answer("get_processes")
+        .unwrap()
+        .contains("\"process_count\":5"));
+    assert!(p.answer("get_disks").is_none());
+    assert!(p.answer("hello").is_none());
+}
+
+#[test]
+fn pause_and_seek_move_the_position() {
+    let mut p = Playback::new(recording::parse(&session()).unwrap());
+    p.answer("get_metrics");
+    p.toggle_pause();
+    assert!(p.holding());
+    assert_eq!(p.status(), "⏸ replay 00:00 / 00:29");
+
+    // A seek shows one sample at the new position, then holds again
+    p.seek(true);
+    assert!(!p.holding());
+    assert_eq!(cpu_of(p.answer("get_metrics")), 10.0);
+    assert!(p.holding());
+    p.seek(false);
+    assert_eq!(cpu_of(p.answer("get_metrics")), 0.0);
+
+    p.toggle_pause();
+    p.seek(true);
+    p.seek(true);
+    p.seek(true);
+    assert_eq!(cpu_of(p.answer("get_metrics")), 29.0);
+    assert!(p.finished() && p.holding());
+    assert_eq!(p.status(), "⏹ replay 00:29 / 00:29");
+    assert!(p.answer("get_metrics").is_none());
+}
+
+#[test]
+fn samples_carry_the_time_they_were_recorded() {
+    // Alerts in a replay are stamped with this, not the time of watching
+    let mut p = Playback::new(recording::parse(&session()).unwrap());
+    p.answer("get_metrics");
+    assert_eq!(p.position_unix(), 1_700_000_000);
+    for _ in 0..12 {
+        p.answer("get_metrics");
+    }
+    assert_eq!(p.position_unix(), 1_700_000_012);
+}
@@ -0,0 +1,122 @@
+//! Cross-version payloads: a newer agent (higher `schema_version`, fields this client doesn't
+//! know) and an older one (no `schema_version`) must both decode, over JSON and protobuf.
+use prost::encoding::{bytes, float, string, uint32, uint64};
+use serde_json::json;
+use socktop::types::{AgentHistory, DisksDelta, Hello, Metrics, SCHEMA_VERSION};
+use socktop::ws::{request_processes, AgentLink};
+use tokio_tungstenite::tungstenite::Message;
+
+fn base_metrics() -> serde_json::Value {
+    json!({
+        "cpu_total": 12.0, "cpu_per_core": [10.0, 14.0], "mem_total": 1000, "mem_used": 400,
+        "swap_total": 0, "swap_used": 0, "hostname": "h", "cpu_temp_c": null, "disks": [],
+        "networks": [], "top_processes": [], "gpus": null
+    })
+}
+
+#[test]
+fn newer_agent_metrics_with_unknown_fields_decode() {
+    let mut v = base_metrics();
+    v["schema_version"] = json!(SCHEMA_VERSION + 1);
+    v["psi"] = json!({"cpu_some_avg10": 0.4});
+    v["disks"] = json!([{"name": "sda1", "total": 100, "available": 50, "fs_type": "ext4"}]);
+    v["networks"] = json!([{"name": "eth0", "received": 1, "transmitted": 2, "errors": 0}]);
+    v["meta"] = json!({
+        "metrics": {"last_ms": 1.5, "interval_ms": 500, "p99_ms": 3.0},
+        "caches": {"names_bytes": 0, "history_bytes": 4096, "limit_bytes": 16777216},
+    });
+    let m: Metrics = serde_json::from_value(v).unwrap();
+    assert_eq!(m.disks[0].available, 50);
+    assert_eq!(m.networks[0].transmitted, 2);
+    assert_eq!(m.meta.as_ref().unwrap().metrics.last_ms, 1.5);
+    assert!(m.schema_notice().unwrap().contains("update socktop"));
+}
+
+#[test]
+fn older_agent_metrics_without_a_version_decode() {
+    let m: Metrics = serde_json::from_value(base_metrics()).unwrap();
+    assert_eq!(m.schema_version, 0);
+    assert_eq!(m.schema_notice(), None);
+
+    let mut v = base_metrics();
+    v["schema_version"] = json!(SCHEMA_VERSION);
+    let m: Metrics = serde_json::from_value(v).unwrap();
+    assert_eq!(m.schema_notice(), None);
+}
+
+#[test]
+fn other_json_replies_ignore_unknown_fields() {
+    let h: Hello = serde_json::from_value(json!({
+        "schema_version": 7, "scope": "control", "session": "ab", "quota": {"rps": 5}
+    }))
+    .unwrap();
+    assert_eq!(h.session.as_deref(), Some("ab"));
+
+    let d: DisksDelta = serde_json::from_value(json!({
+        "schema_version": 7, "seq": 3, "full": false, "disks": [], "moved": []
+    }))
+    .unwrap();
+    assert_eq!(d.seq, 3);
+
+    let h: AgentHistory = serde_json::from_value(json!({
+        "schema_version": 7, "step_secs": 10, "resolution": "10s",
+        "points": [{"ts": 1, "cpu_total": 5.0, "rx_bps": 0, "tx_bps": 0, "load1": 0.2}]
+    }))
+    .unwrap();
+    assert_eq!(h.points.len(), 1);
+}
+
+/// Answers every request with one canned frame.
+struct Canned(Vec<u8>);
+
+impl AgentLink for Canned {
+    async fn open(_url: &str, _tls_ca: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("canned link".into())
+    }
+
+    async fn exchange(&mut self, _cmd: &str) -> Option<Message> {
+        Some(Message::Binary(self.0.clone()))
+    }
+}
+
+// Processes message by hand, optionally with fields a newer agent might add
+fn processes_frame(schema_version: Option<u32>, extra_fields: bool) -> Vec<u8> {
+    let mut row = Vec::new();
+    uint32::encode(1, &42, &mut row);
+    string::encode(2, &"nginx".to_string(), &mut row);
+    float::encode(3, &12.5, &mut row);
+    uint64::encode(4, &2048, &mut row);
+    if extra_fields {
+        string::encode(9, &"www-data".to_string(), &mut row);
+    }
+    let mut msg = Vec::new();
+    uint64::encode(1, &310, &mut msg);
+    bytes::encode(2, &row, &mut msg);
+    if let Some(v) = schema_version {
+        uint32::encode(3, &v, &mut msg);
+    }
+    if extra_fields {
+        uint64::encode(15, &99, &mut msg);
+    }
+    msg
+}
+
+#[tokio::test]
+async fn processes_protobuf_decodes_across_versions() {
+    for frame in [
+        processes_frame(None, false),
+        processes_frame(Some(SCHEMA_VERSION), false),
+        processes_frame(Some(SCHEMA_VERSION + 1), true),
+    ] {
+        let p = request_processes(&mut Canned(frame))
+            .await
+            .expect("decodes");
+        assert_eq!(p.process_count, 310);
+        let row = &p.top_processes[0];
+        assert_eq!(
+            (row.pid, row.name.as_str(), row.mem_bytes),
+            (42, "nginx", 2048)
+        );
+        assert_eq!(row.cpu_usage, 12.5);
+    }
+}
@@ -0,0 +1,93 @@
+//! Reconnect identity check: detect reboots and host swaps behind the same URL.
+use socktop::session::{HostIdentity, IdentityChange};
+use std::time::{Duration, Instant};
+
+fn ident(host: &str, uptime: Option<u64>) -> HostIdentity {
+    HostIdentity {
+        hostname: host.into(),
+        instance: None,
+        machine_id: None,
+        boot_id: None,
+        uptime_secs: uptime,
+        seen_at: Instant::now(),
+    }
+}
+
+#[test]
+fn same_host_with_growing_uptime_is_same() {
+    let prev = ident("nas", Some(1_000));
+    assert_eq!(
+        prev.compare(&ident("nas", Some(1_030))),
+        IdentityChange::Same
+    );
+    // Small backwards jitter is tolerated
+    assert_eq!(prev.compare(&ident("nas", Some(998))), IdentityChange::Same);
+}
+
+#[test]
+fn uptime_going_backwards_means_reboot() {
+    let prev = ident("nas", Some(86_400));
+    assert_eq!(
+        prev.compare(&ident("nas", Some(42))),
+        IdentityChange::Rebooted
+    );
+}
+
+#[test]
+fn uptime_growing_less_than_the_time_away_means_reboot() {
+    let mut prev = ident("nas", Some(1_000));
+    prev.seen_at = Instant::now()
+        .checked_sub(Duration::from_secs(120))
+        .unwrap();
+    // Gone for two minutes: 1_030 is short of the ~1_120 expected
+    assert_eq!(
+        prev.compare(&ident("nas", Some(1_030))),
+        IdentityChange::Rebooted
+    );
+    assert_eq!(
+        prev.compare(&ident("nas", Some(1_121))),
+        IdentityChange::Same
+    );
+}
+
+#[test]
+fn hostname_change_wins_and_missing_uptime_is_same() {
+    let prev = ident("nas", Some(86_400));
+    assert_eq!(
+        prev.compare(&ident("pi", Some(42))),
+        IdentityChange::DifferentHost
+    );
+    assert_eq!(prev.compare(&ident("nas", None)), IdentityChange::Same);
+}
+
+#[test]
+fn agent_ids_take_precedence_over_hostname_and_uptime() {
+    let mut prev = ident("nas", Some(10));
+    prev.machine_id = Some("m1".into());
+    prev.boot_id = Some("b1".into());
+
+    // Renamed host, same machine and boot: still the same session
+    let mut renamed = ident("nas-renamed", Some(5));
+    renamed.machine_id = Some("m1".into());
+    renamed.boot_id = Some("b1".into());
+    assert_eq!(prev.compare(&renamed), IdentityChange::Same);
+
+    // New boot id with uptime still growing (e.g. fast reboot + clock skew)
+    let mut rebooted = ident("nas", Some(500));
+    rebooted.machine_id = Some("m1".into());
+    rebooted.boot_id = Some("b2".into());
+    assert_eq!(prev.compare(&rebooted), IdentityChange::Rebooted);
+
+    let mut other = ident("nas", Some(500));
+    other.machine_id = Some("m2".into());
+    assert_eq!(prev.compare(&other), IdentityChange::DifferentHost);
+}
+
+#[test]
+fn different_instance_on_same_machine_is_different_host() {
+    let mut prev = ident("nas", Some(10));
+    prev.machine_id = Some("m1".into());
+    let mut next = prev.clone();
+    next.instance = Some("ci".into());
+    assert_eq!(prev.compare(&next), IdentityChange::DifferentHost);
+}
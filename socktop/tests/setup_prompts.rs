@@ -0,0 +1,74 @@
+//! Setup prompts: line editing keys and the validators used by the wizard and profile creation.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use socktop::prompt::{existing_file, number, optional, valid_url, Edit, LineEditor};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn ctrl(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn type_str(ed: &mut LineEditor, s: &str) {
+    for c in s.chars() {
+        assert_eq!(ed.apply(key(KeyCode::Char(c))), Edit::Continue);
+    }
+}
+
+#[test]
+fn editor_moves_inserts_and_deletes_at_the_cursor() {
+    let mut ed = LineEditor::default();
+    type_str(&mut ed, "ws://hst:3000");
+    for _ in 0..7 {
+        ed.apply(key(KeyCode::Left));
+    }
+    type_str(&mut ed, "o");
+    assert_eq!(ed.text(), "ws://host:3000");
+    ed.apply(key(KeyCode::End));
+    ed.apply(key(KeyCode::Backspace));
+    assert_eq!(ed.text(), "ws://host:300");
+    ed.apply(ctrl('a'));
+    ed.apply(key(KeyCode::Delete));
+    assert_eq!(ed.text(), "s://host:300");
+    ed.apply(key(KeyCode::Right));
+    ed.apply(ctrl('k'));
+    assert_eq!(ed.text(), "s");
+    ed.apply(ctrl('e'));
+    ed.apply(ctrl('u'));
+    assert_eq!((ed.text().as_str(), ed.cursor()), ("", 0));
+    assert_eq!(ed.apply(key(KeyCode::Enter)), Edit::Submit);
+}
+
+#[test]
+fn ctrl_c_aborts_and_ctrl_d_only_on_an_empty_line() {
+    let mut ed = LineEditor::default();
+    assert_eq!(ed.apply(ctrl('d')), Edit::Abort);
+    type_str(&mut ed, "x");
+    assert_eq!(ed.apply(ctrl('d')), Edit::Continue);
+    assert_eq!(ed.apply(ctrl('c')), Edit::Abort);
+}
+
+#[test]
+fn url_validation_matches_what_the_client_accepts() {
+    assert!(valid_url("ws://10.0.0.5:3000/ws").is_ok());
+    assert!(valid_url("wss://host/ws?token=x").is_ok());
+    assert!(valid_url("host:3000").is_ok());
+    assert!(valid_url("[::1]:3000").is_ok());
+    assert!(valid_url("myhost").is_ok());
+    assert!(valid_url("http://host:3000/ws").is_err());
+    assert!(valid_url("ws://").is_err());
+    assert!(valid_url("host:port").is_err());
+    assert!(valid_url("my host").is_err());
+}
+
+#[test]
+fn file_and_number_validators() {
+    let f = tempfile::NamedTempFile::new().unwrap();
+    assert!(existing_file(f.path().to_str().unwrap()).is_ok());
+    assert!(existing_file("/definitely/not/here.pem").is_err());
+    assert!(optional(existing_file)("").is_ok());
+    assert!(number("500").is_ok());
+    assert!(number("fast").is_err());
+    assert!(number("-1").is_err());
+}
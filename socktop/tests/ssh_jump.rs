@@ -0,0 +1,36 @@
+//! SSH tunnel arguments for profiles with an `ssh` host and an `ssh_jump` bastion chain.
+use socktop::tunnel::{ssh_args, tunneled_url};
+
+#[test]
+fn jump_chain_becomes_a_single_proxy_jump() {
+    let jumps = vec!["ops@jump1".to_string(), "jump2:2222".to_string()];
+    let args = ssh_args("pi@nas", &jumps, 40123, "127.0.0.1:3000");
+    assert_eq!(
+        args,
+        [
+            "-N",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-L",
+            "127.0.0.1:40123:127.0.0.1:3000",
+            "-J",
+            "ops@jump1,jump2:2222",
+            "pi@nas"
+        ]
+    );
+    // No bastions: plain tunnel straight to the host
+    assert!(!ssh_args("pi@nas", &[], 40123, "127.0.0.1:3000").contains(&"-J".to_string()));
+}
+
+#[test]
+fn url_is_rewritten_to_the_local_end() {
+    let (remote, local) = tunneled_url("wss://localhost:8443/ws?token=abc", 40123).unwrap();
+    assert_eq!(remote, "localhost:8443");
+    assert_eq!(local, "wss://127.0.0.1:40123/ws?token=abc");
+
+    // Default port of the scheme when none is given
+    let (remote, _) = tunneled_url("ws://10.0.0.5/ws", 1).unwrap();
+    assert_eq!(remote, "10.0.0.5:80");
+
+    assert!(tunneled_url("nas:3000", 1).is_err());
+}
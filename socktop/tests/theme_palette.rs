@@ -0,0 +1,68 @@
+//! Color palettes: `--theme` names and the `theme` key of `theme.json`.
+use ratatui::style::{Color, Modifier};
+use socktop::ui::theme::{Breakpoints, Theme, Thresholds};
+
+#[test]
+fn palettes_are_picked_by_name() {
+    assert_eq!(Theme::named("dark"), Some(Theme::DARK));
+    assert_eq!(Theme::named(" Light "), Some(Theme::LIGHT));
+    assert_eq!(Theme::named("monochrome"), Some(Theme::MONO));
+    assert_eq!(Theme::named("solarized"), None);
+    assert_eq!(Theme::default(), Theme::DARK);
+}
+
+#[test]
+fn light_avoids_the_colors_that_wash_out_on_white() {
+    let l = Theme::LIGHT;
+    for c in [l.ok, l.warn, l.accent, l.cpu, l.mem, l.highlight, l.rx] {
+        assert!(
+            !matches!(
+                c,
+                Color::Cyan | Color::Green | Color::Yellow | Color::Magenta
+            ),
+            "{c:?}"
+        );
+    }
+    assert_ne!(l.ok, l.crit);
+}
+
+#[test]
+fn mono_badges_are_reversed_video() {
+    let s = Theme::MONO.badge(Theme::MONO.crit);
+    assert!(s.add_modifier.contains(Modifier::REVERSED));
+    assert_eq!(s.bg, None);
+    let s = Theme::DARK.badge(Theme::DARK.crit);
+    assert_eq!((s.fg, s.bg), (Some(Color::Black), Some(Color::Red)));
+}
+
+#[test]
+fn theme_file_names_the_palette_next_to_the_thresholds() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("theme.json");
+    std::fs::write(
+        &path,
+        r#"{"theme": "light", "thresholds": {"disk": {"warn": 80, "crit": 95}}}"#,
+    )
+    .unwrap();
+    assert_eq!(Theme::load_from(&path), Some(Theme::LIGHT));
+    assert_eq!(
+        Thresholds::load_from(&path).disk,
+        Breakpoints {
+            warn: 80.0,
+            crit: 95.0
+        }
+    );
+
+    // An unknown palette doesn't cost the thresholds
+    std::fs::write(
+        &path,
+        r#"{"theme": "solarized", "thresholds": {"disk": {"warn": 80, "crit": 95}}}"#,
+    )
+    .unwrap();
+    assert_eq!(Theme::load_from(&path), None);
+    assert_eq!(Thresholds::load_from(&path).disk.warn, 80.0);
+
+    std::fs::write(&path, r#"{"thresholds": {}}"#).unwrap();
+    assert_eq!(Theme::load_from(&path), None);
+    assert_eq!(Theme::load_from(&dir.path().join("missing.json")), None);
+}
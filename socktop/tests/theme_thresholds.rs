@@ -0,0 +1,41 @@
+//! Color breakpoints: the shared severity function and `theme.json` overrides.
+use socktop::ui::theme::{Breakpoints, Level, Thresholds};
+
+#[test]
+fn level_uses_the_lowest_value_of_each_band() {
+    let cpu = Thresholds::default().cpu;
+    assert_eq!(Thresholds::level(cpu, 24.9), Level::Normal);
+    assert_eq!(Thresholds::level(cpu, 25.0), Level::Warn);
+    assert_eq!(Thresholds::level(cpu, 60.0), Level::Crit);
+    let disk = Thresholds::default().disk;
+    assert_eq!(Thresholds::level(disk, 89.0), Level::Warn);
+    assert_eq!(Thresholds::level(disk, 90.0), Level::Crit);
+}
+
+#[test]
+fn theme_file_overrides_some_kinds_and_rejects_inverted_pairs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("theme.json");
+    std::fs::write(
+        &path,
+        r#"{"thresholds": {"disk": {"warn": 80, "crit": 95}, "cpu": {"warn": 90, "crit": 50}}}"#,
+    )
+    .unwrap();
+    let t = Thresholds::load_from(&path);
+    assert_eq!(
+        t.disk,
+        Breakpoints {
+            warn: 80.0,
+            crit: 95.0
+        }
+    );
+    assert_eq!(t.cpu, Thresholds::default().cpu);
+    assert_eq!(t.io, Thresholds::default().io);
+
+    assert_eq!(
+        Thresholds::load_from(&dir.path().join("missing.json")),
+        Thresholds::default()
+    );
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(Thresholds::load_from(&path), Thresholds::default());
+}
@@ -0,0 +1,40 @@
+//! Truncation works on grapheme clusters and display width, so CJK/emoji names never panic
+//! or get split mid-character.
+use socktop::ui::util::{display_width, truncate_end, truncate_middle};
+
+#[test]
+fn ascii_names_keep_both_ends() {
+    assert_eq!(truncate_middle("short", 10), "short");
+    assert_eq!(truncate_middle("/dev/mapper/vg0-root", 11), "/dev/…-root");
+    assert_eq!(truncate_end("postgres: checkpointer", 9), "postgres…");
+    assert_eq!(truncate_middle("abc", 0), "");
+}
+
+#[test]
+fn cjk_names_are_cut_by_display_width() {
+    // Each ideograph is two columns wide; byte slicing would panic here
+    let name = "データベースサーバー";
+    for max in 0..=display_width(name) {
+        let t = truncate_middle(name, max);
+        assert!(display_width(&t) <= max, "{t:?} wider than {max}");
+        let e = truncate_end(name, max);
+        assert!(display_width(&e) <= max, "{e:?} wider than {max}");
+    }
+    assert_eq!(truncate_end(name, 7), "データ…");
+    assert_eq!(truncate_middle("日本語のディスク名", 9), "日本…ク名");
+}
+
+#[test]
+fn emoji_and_combining_marks_stay_whole() {
+    // Family emoji (ZWJ sequence) and an accented letter built from a combining mark
+    let family = "👨‍👩‍👧";
+    let name = format!("{family} café-worker");
+    assert_eq!(
+        truncate_end(&name, display_width(family) + 1),
+        format!("{family}…")
+    );
+    assert_eq!(
+        truncate_middle("cafe\u{301}-cafe\u{301}-cafe\u{301}", 7),
+        "caf…afe\u{301}"
+    );
+}
@@ -0,0 +1,73 @@
+//! host[:port] shorthand expansion and resolving it against a local agent.
+use socktop::ws::shorthand_candidates;
+
+#[test]
+fn full_urls_pass_through() {
+    assert_eq!(
+        shorthand_candidates("ws://nas.local:3000/ws"),
+        vec!["ws://nas.local:3000/ws".to_string()]
+    );
+}
+
+#[test]
+fn bare_host_uses_agent_default_ports_tls_first() {
+    assert_eq!(
+        shorthand_candidates("nas.local"),
+        vec![
+            "wss://nas.local:8443/ws".to_string(),
+            "ws://nas.local:3000/ws".to_string()
+        ]
+    );
+}
+
+#[test]
+fn explicit_port_path_and_query_are_kept() {
+    assert_eq!(
+        shorthand_candidates("nas.local:9000?token=abc"),
+        vec![
+            "wss://nas.local:9000/ws?token=abc".to_string(),
+            "ws://nas.local:9000/ws?token=abc".to_string()
+        ]
+    );
+    assert_eq!(
+        shorthand_candidates("[fd00::1]:8443/custom"),
+        vec![
+            "wss://[fd00::1]:8443/custom".to_string(),
+            "ws://[fd00::1]:8443/custom".to_string()
+        ]
+    );
+}
+
+// A plaintext agent on an explicit port: the wss:// attempt fails, ws:// answers and its
+// connection is handed back along with why TLS was skipped
+#[tokio::test]
+async fn plaintext_fallback_keeps_the_connection_and_says_why() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((sock, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                // The TLS ClientHello isn't an HTTP upgrade; that one just fails
+                if let Ok(mut ws) = tokio_tungstenite::accept_async(sock).await {
+                    use futures_util::StreamExt;
+                    while ws.next().await.is_some() {}
+                }
+            });
+        }
+    });
+
+    let r = socktop::ws::resolve_url(&addr.to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(r.url, format!("ws://{addr}/ws"));
+    assert!(r.ws.is_some());
+    assert!(r
+        .plaintext_fallback
+        .is_some_and(|why| why.starts_with(&format!("wss://{addr}/ws"))));
+
+    // Full URLs aren't probed
+    let r = socktop::ws::resolve_url("ws://nas.local:3000/ws", None)
+        .await
+        .unwrap();
+    assert!(r.ws.is_none() && r.plaintext_fallback.is_none());
+}
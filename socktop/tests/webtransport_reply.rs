@@ -0,0 +1,26 @@
+//! WebTransport stream replies: kind byte + body map back onto WebSocket-style frames.
+#![cfg(feature = "webtransport")]
+
+use socktop::wt::decode_reply;
+use tokio_tungstenite::tungstenite::Message;
+
+#[test]
+fn kind_byte_selects_text_or_binary() {
+    let mut text = vec![0u8];
+    text.extend_from_slice(br#"{"cpu_total":1.0}"#);
+    assert_eq!(
+        decode_reply(text),
+        Some(Message::Text(r#"{"cpu_total":1.0}"#.into()))
+    );
+    assert_eq!(
+        decode_reply(vec![1, 0x1f, 0x8b]),
+        Some(Message::Binary(vec![0x1f, 0x8b]))
+    );
+}
+
+#[test]
+fn empty_or_unknown_replies_are_dropped() {
+    assert_eq!(decode_reply(Vec::new()), None);
+    assert_eq!(decode_reply(vec![7, 1, 2]), None);
+    assert_eq!(decode_reply(vec![0, 0xff]), None);
+}
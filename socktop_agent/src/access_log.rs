@@ -0,0 +1,266 @@
+//! Per-request access log (`--access-log FILE`, `-` for stdout): one JSON line per command
+//! with the client address, duration and reply size, so operators can audit who is polling
+//! what. Auth failures and bans (auth_guard) are logged here too, as `event` lines.
+//! `--access-log-redact` rules keep addresses or query strings out of the file.
+
+use std::fs::OpenOptions;
+use std::io::{self, LineWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redact {
+    /// Drop the client address entirely
+    Client,
+    /// Keep only the network: /24 for IPv4, /48 for IPv6, no port
+    ClientPrefix,
+    /// Log `get_fleet?…` style commands without their arguments (label values, etc.)
+    Query,
+}
+
+impl Redact {
+    /// Comma-separated `client`, `client-prefix`, `query`.
+    pub fn parse_list(spec: &str) -> Result<Vec<Redact>, String> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| match s {
+                "client" => Ok(Redact::Client),
+                "client-prefix" => Ok(Redact::ClientPrefix),
+                "query" => Ok(Redact::Query),
+                other => Err(format!(
+                    "unknown redaction '{other}' (expected client, client-prefix or query)"
+                )),
+            })
+            .collect()
+    }
+}
+
+/// One request as seen by a transport.
+pub struct Request<'a> {
+    pub transport: &'static str,
+    pub client: Option<SocketAddr>,
+    // Relay mode: the leaf the request was forwarded to
+    pub upstream: Option<&'a str>,
+    pub command: &'a str,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Entry<'a> {
+    pub ts_ms: u64,
+    pub transport: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<&'a str>,
+    pub command: &'a str,
+    pub duration_ms: f64,
+    pub bytes: usize,
+}
+
+pub struct AccessLog {
+    out: Mutex<Box<dyn Write + Send>>,
+    redact: Vec<Redact>,
+}
+
+impl AccessLog {
+    pub fn open(target: &str, redact: Vec<Redact>) -> io::Result<Self> {
+        let out: Box<dyn Write + Send> = if target == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = OpenOptions::new().create(true).append(true).open(target)?;
+            Box::new(LineWriter::new(file))
+        };
+        Ok(Self::with_writer(out, redact))
+    }
+
+    pub fn with_writer(out: Box<dyn Write + Send>, redact: Vec<Redact>) -> Self {
+        Self {
+            out: Mutex::new(out),
+            redact,
+        }
+    }
+
+    fn client(&self, addr: Option<SocketAddr>) -> Option<String> {
+        if self.redact.contains(&Redact::Client) {
+            None
+        } else if self.redact.contains(&Redact::ClientPrefix) {
+            addr.map(|a| network_of(a.ip()))
+        } else {
+            addr.map(|a| a.to_string())
+        }
+    }
+
+    /// The log line for `req`, with redaction applied.
+    pub fn entry<'a>(&self, req: &Request<'a>, elapsed: Duration, bytes: usize) -> Entry<'a> {
+        let client = self.client(req.client);
+        let command = if self.redact.contains(&Redact::Query) {
+            req.command
+                .split_once('?')
+                .map_or(req.command, |(cmd, _)| cmd)
+        } else {
+            req.command
+        };
+        Entry {
+            ts_ms: now_ms(),
+            transport: req.transport,
+            client,
+            upstream: req.upstream,
+            command,
+            duration_ms: (elapsed.as_secs_f64() * 1e6).round() / 1e3,
+            bytes,
+        }
+    }
+
+    pub fn record(&self, req: &Request<'_>, elapsed: Duration, bytes: usize) {
+        self.write(&self.entry(req, elapsed, bytes));
+    }
+
+    /// Security event (auth failure, ban) from `client`, in the same file as request lines.
+    pub fn event(
+        &self,
+        transport: &'static str,
+        client: Option<SocketAddr>,
+        event: &'static str,
+        detail: String,
+    ) {
+        self.write(&Event {
+            ts_ms: now_ms(),
+            transport,
+            client: self.client(client),
+            event,
+            detail,
+        });
+    }
+
+    fn write<T: Serialize>(&self, line: &T) {
+        let Ok(line) = serde_json::to_string(line) else {
+            return;
+        };
+        // A full disk or closed stdout must not take the request path down with it
+        if let Ok(mut out) = self.out.lock() {
+            let _ = writeln!(out, "{line}");
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Event {
+    pub ts_ms: u64,
+    pub transport: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub event: &'static str,
+    pub detail: String,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn network_of(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}/24", Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => network_of(IpAddr::V4(v4)),
+            None => {
+                let s = v6.segments();
+                format!("{}/48", Ipv6Addr::new(s[0], s[1], s[2], 0, 0, 0, 0, 0))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn req(command: &str) -> Request<'_> {
+        Request {
+            transport: "ws",
+            client: Some("192.168.7.42:51234".parse().unwrap()),
+            upstream: None,
+            command,
+        }
+    }
+
+    #[test]
+    fn redaction_rules_parse_and_reject_unknown() {
+        assert_eq!(
+            Redact::parse_list("client-prefix, query").unwrap(),
+            vec![Redact::ClientPrefix, Redact::Query]
+        );
+        assert!(Redact::parse_list("").unwrap().is_empty());
+        assert!(Redact::parse_list("token").is_err());
+    }
+
+    #[test]
+    fn entries_apply_redaction() {
+        let ms = Duration::from_micros(1500);
+        let plain = AccessLog::with_writer(Box::new(io::sink()), vec![]);
+        let e = plain.entry(&req("get_fleet?label=env=prod"), ms, 900);
+        assert_eq!(e.client.as_deref(), Some("192.168.7.42:51234"));
+        assert_eq!(e.command, "get_fleet?label=env=prod");
+        assert_eq!((e.duration_ms, e.bytes), (1.5, 900));
+
+        let redacted = AccessLog::with_writer(
+            Box::new(io::sink()),
+            vec![Redact::ClientPrefix, Redact::Query],
+        );
+        let e = redacted.entry(&req("get_fleet?label=env=prod"), ms, 900);
+        assert_eq!(e.client.as_deref(), Some("192.168.7.0/24"));
+        assert_eq!(e.command, "get_fleet");
+
+        let hidden = AccessLog::with_writer(Box::new(io::sink()), vec![Redact::Client]);
+        assert_eq!(hidden.entry(&req("get_metrics"), ms, 1).client, None);
+    }
+
+    #[test]
+    fn ipv6_prefix_keeps_48_bits_and_unwraps_mapped_v4() {
+        assert_eq!(
+            network_of("2001:db8:1234:5678::1".parse().unwrap()),
+            "2001:db8:1234::/48"
+        );
+        assert_eq!(
+            network_of("::ffff:10.1.2.3".parse().unwrap()),
+            "10.1.2.0/24"
+        );
+    }
+
+    #[test]
+    fn record_writes_one_json_line_per_request() {
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let buf = Shared::default();
+        let log = AccessLog::with_writer(Box::new(buf.clone()), vec![Redact::Client]);
+        log.record(&req("get_metrics"), Duration::from_millis(2), 640);
+        log.record(&req("get_disks"), Duration::from_millis(1), 120);
+        let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["command"], "get_metrics");
+        assert_eq!(lines[0]["bytes"], 640);
+        assert!(lines[1].get("client").is_none());
+    }
+}
@@ -0,0 +1,268 @@
+//! Brute-force protection for token auth. Failed attempts are counted per source address
+//! (IPv6 per /64); every rejection is held back by a growing, jittered delay, and too many
+//! failures inside the window ban the address for a while. A successful login clears it.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+use crate::types::Scope;
+
+pub const DEFAULT_MAX_FAILURES: u32 = 5;
+pub const DEFAULT_BAN_SECS: u64 = 300;
+// Failures older than this don't count toward a ban
+const WINDOW: Duration = Duration::from_secs(60);
+// Rejection delay: STEP per failure so far, capped, plus up to JITTER random
+const DELAY_STEP: Duration = Duration::from_millis(250);
+const DELAY_MAX: Duration = Duration::from_secs(3);
+const JITTER_MS: u64 = 250;
+// Forget idle peers once this many are tracked, so spoofed floods can't grow the map forever
+const MAX_TRACKED: usize = 4096;
+
+static MAX_FAILURES: AtomicU32 = AtomicU32::new(DEFAULT_MAX_FAILURES);
+static BAN_SECS: AtomicU64 = AtomicU64::new(DEFAULT_BAN_SECS);
+
+/// Override the defaults (`--auth-max-failures`, `--auth-ban-secs`); 0 failures disables bans.
+pub fn configure(max_failures: Option<u32>, ban_secs: Option<u64>) {
+    if let Some(n) = max_failures {
+        MAX_FAILURES.store(n, Ordering::Relaxed);
+    }
+    if let Some(s) = ban_secs {
+        BAN_SECS.store(s, Ordering::Relaxed);
+    }
+}
+
+struct Peer {
+    failures: u32,
+    first: Instant,
+    banned_until: Option<Instant>,
+}
+
+/// What to do with one failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Failure {
+    pub failures: u32,
+    pub delay: Duration,
+    /// Set when this attempt started a ban
+    pub banned_for: Option<Duration>,
+}
+
+#[derive(Default)]
+pub struct AuthGuard {
+    peers: Mutex<HashMap<IpAddr, Peer>>,
+}
+
+impl AuthGuard {
+    /// Time left on a ban for `ip`, if any.
+    pub fn banned(&self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        let peers = self.peers.lock().ok()?;
+        let until = peers.get(&key_of(ip))?.banned_until?;
+        (until > now).then(|| until - now)
+    }
+
+    pub fn failure(&self, ip: IpAddr, now: Instant, jitter: Duration) -> Failure {
+        let max = MAX_FAILURES.load(Ordering::Relaxed);
+        let ban = Duration::from_secs(BAN_SECS.load(Ordering::Relaxed));
+        let Ok(mut peers) = self.peers.lock() else {
+            return Failure {
+                failures: 0,
+                delay: DELAY_MAX,
+                banned_for: None,
+            };
+        };
+        if peers.len() >= MAX_TRACKED {
+            peers.retain(|_, p| {
+                now.duration_since(p.first) < WINDOW || p.banned_until.is_some_and(|u| u > now)
+            });
+        }
+        let peer = peers.entry(key_of(ip)).or_insert(Peer {
+            failures: 0,
+            first: now,
+            banned_until: None,
+        });
+        // Start a fresh count once an earlier ban is over, or the window passed without one
+        let ban_over = peer.banned_until.is_some_and(|u| u <= now);
+        if ban_over || (peer.banned_until.is_none() && now.duration_since(peer.first) >= WINDOW) {
+            peer.failures = 0;
+            peer.first = now;
+            peer.banned_until = None;
+        }
+        peer.failures += 1;
+        let banned_for =
+            (max > 0 && peer.failures >= max && peer.banned_until.is_none()).then(|| {
+                peer.banned_until = Some(now + ban);
+                ban
+            });
+        Failure {
+            failures: peer.failures,
+            delay: (DELAY_STEP * peer.failures).min(DELAY_MAX) + jitter,
+            banned_for,
+        }
+    }
+
+    pub fn success(&self, ip: IpAddr) {
+        if let Ok(mut peers) = self.peers.lock() {
+            peers.remove(&key_of(ip));
+        }
+    }
+}
+
+// One IPv6 host usually owns a whole /64
+fn key_of(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => {
+                let s = v6.segments();
+                IpAddr::V6(Ipv6Addr::new(s[0], s[1], s[2], s[3], 0, 0, 0, 0))
+            }
+        },
+        v4 => v4,
+    }
+}
+
+fn jitter() -> Duration {
+    let mut b = [0u8; 2];
+    let _ = getrandom::getrandom(&mut b);
+    Duration::from_millis(u16::from_le_bytes(b) as u64 % (JITTER_MS + 1))
+}
+
+/// The token in an `Authorization: Bearer TOKEN` header value, which keeps it out of URLs
+/// (and so out of proxy logs and shell history).
+pub fn bearer(header: Option<&str>) -> Option<&str> {
+    let (scheme, token) = header?.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+/// `state.authorize` for a connection from `client`, with bans, delayed rejections and audit
+/// events. None means reject.
+pub async fn admit(
+    state: &AppState,
+    transport: &'static str,
+    client: SocketAddr,
+    token: Option<&str>,
+) -> Option<Scope> {
+    let ip = client.ip();
+    let log = |event, detail: String| {
+        if let Some(log) = state.access_log.as_deref() {
+            log.event(transport, Some(client), event, detail);
+        }
+    };
+    if let Some(left) = state.auth_guard.banned(ip, Instant::now()) {
+        // Reject without evaluating the token; not logged per attempt to keep floods out of the log
+        tracing::debug!("{client}: banned for another {}s", left.as_secs());
+        return None;
+    }
+    if let Some(scope) = state.authorize(token) {
+        state.auth_guard.success(ip);
+        return Some(scope);
+    }
+    let f = state.auth_guard.failure(ip, Instant::now(), jitter());
+    log(
+        "auth_failure",
+        format!(
+            "{} token, failure {}",
+            if token.is_some() { "bad" } else { "missing" },
+            f.failures
+        ),
+    );
+    if let Some(ban) = f.banned_for {
+        tracing::warn!(
+            "{client}: {} failed auth attempts, banned for {}s",
+            f.failures,
+            ban.as_secs()
+        );
+        log(
+            "auth_ban",
+            format!("{}s after {} failures", ban.as_secs(), f.failures),
+        );
+    }
+    tokio::time::sleep(f.delay).await;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_JITTER: Duration = Duration::ZERO;
+
+    #[test]
+    fn bearer_header_carries_the_token() {
+        assert_eq!(bearer(Some("Bearer s3cret")), Some("s3cret"));
+        assert_eq!(bearer(Some("bearer  s3cret ")), Some("s3cret"));
+        assert_eq!(bearer(Some("Basic dXNlcjpwdw==")), None);
+        assert_eq!(bearer(Some("Bearer ")), None);
+        assert_eq!(bearer(None), None);
+    }
+
+    #[test]
+    fn bans_after_max_failures_and_expires() {
+        let g = AuthGuard::default();
+        let ip: IpAddr = "203.0.113.9".parse().unwrap();
+        let t0 = Instant::now();
+        for n in 1..DEFAULT_MAX_FAILURES {
+            let f = g.failure(ip, t0, NO_JITTER);
+            assert_eq!(f.failures, n);
+            assert_eq!(f.banned_for, None);
+            assert_eq!(f.delay, DELAY_STEP * n);
+        }
+        assert!(g.banned(ip, t0).is_none());
+
+        let f = g.failure(ip, t0, NO_JITTER);
+        assert_eq!(f.banned_for, Some(Duration::from_secs(DEFAULT_BAN_SECS)));
+        assert!(g.banned(ip, t0 + Duration::from_secs(1)).is_some());
+        // Other addresses are unaffected
+        assert!(g.banned("203.0.113.10".parse().unwrap(), t0).is_none());
+
+        let later = t0 + Duration::from_secs(DEFAULT_BAN_SECS + 1);
+        assert!(g.banned(ip, later).is_none());
+        // After the ban the count starts over
+        assert_eq!(g.failure(ip, later, NO_JITTER).failures, 1);
+    }
+
+    #[test]
+    fn delay_is_capped_and_jittered() {
+        let g = AuthGuard::default();
+        let ip: IpAddr = "198.51.100.1".parse().unwrap();
+        let t0 = Instant::now();
+        let last = (0..50)
+            .map(|_| g.failure(ip, t0, Duration::from_millis(100)))
+            .last()
+            .unwrap();
+        assert_eq!(last.delay, DELAY_MAX + Duration::from_millis(100));
+        assert!(jitter() <= Duration::from_millis(JITTER_MS));
+    }
+
+    #[test]
+    fn old_failures_fall_out_of_the_window_and_success_clears() {
+        let g = AuthGuard::default();
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        let t0 = Instant::now();
+        g.failure(ip, t0, NO_JITTER);
+        g.failure(ip, t0, NO_JITTER);
+        assert_eq!(g.failure(ip, t0 + WINDOW, NO_JITTER).failures, 1);
+
+        g.failure(ip, t0 + WINDOW, NO_JITTER);
+        g.success(ip);
+        assert_eq!(g.failure(ip, t0 + WINDOW, NO_JITTER).failures, 1);
+    }
+
+    #[test]
+    fn ipv6_peers_are_grouped_per_64() {
+        let g = AuthGuard::default();
+        let t0 = Instant::now();
+        for i in 0..DEFAULT_MAX_FAILURES {
+            let ip: IpAddr = format!("2001:db8:1:2::{i:x}").parse().unwrap();
+            g.failure(ip, t0, NO_JITTER);
+        }
+        assert!(g
+            .banned("2001:db8:1:2::ffff".parse().unwrap(), t0)
+            .is_some());
+        assert!(g.banned("2001:db8:1:3::1".parse().unwrap(), t0).is_none());
+    }
+}
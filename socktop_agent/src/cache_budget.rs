@@ -0,0 +1,84 @@
+//! One memory ceiling (`--cache-memory-max BYTES`, default 16 MiB) shared by the agent's
+//! caches: the process name cache, the history ring and the reusable encode buffer. Each cache
+//! reports its approximate size after it changes, and when it no longer fits in what the others
+//! leave over it evicts its least recently used entries. Usage is reported in `Metrics.meta.caches`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::types::CacheUsage;
+
+pub const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Cache {
+    Names,
+    History,
+    Buffers,
+}
+
+static MAX_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BYTES);
+static USED: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+pub fn configure(max_bytes: Option<usize>) {
+    if let Some(n) = max_bytes {
+        MAX_BYTES.store(n, Ordering::Relaxed);
+    }
+}
+
+/// Record the current size of `cache`.
+pub fn set_used(cache: Cache, bytes: usize) {
+    USED[cache as usize].store(bytes, Ordering::Relaxed);
+}
+
+/// Bytes `cache` may hold right now: the ceiling minus what the other caches use.
+pub fn room(cache: Cache) -> usize {
+    let others: usize = USED
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != cache as usize)
+        .map(|(_, u)| u.load(Ordering::Relaxed))
+        .sum();
+    MAX_BYTES.load(Ordering::Relaxed).saturating_sub(others)
+}
+
+pub fn usage() -> CacheUsage {
+    let used = |c: Cache| USED[c as usize].load(Ordering::Relaxed) as u64;
+    CacheUsage {
+        names_bytes: used(Cache::Names),
+        history_bytes: used(Cache::History),
+        buffer_bytes: used(Cache::Buffers),
+        limit_bytes: MAX_BYTES.load(Ordering::Relaxed) as u64,
+    }
+}
+
+/// How many of `sizes` (oldest first) to evict so the rest fit in `room` bytes.
+pub fn evict_count(sizes: impl Iterator<Item = usize>, total: usize, room: usize) -> usize {
+    let mut over = total.saturating_sub(room);
+    let mut n = 0;
+    for size in sizes {
+        if over == 0 {
+            break;
+        }
+        over = over.saturating_sub(size);
+        n += 1;
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_just_enough_oldest_entries() {
+        let sizes = [10, 20, 30, 40];
+        assert_eq!(evict_count(sizes.into_iter(), 100, 100), 0);
+        assert_eq!(evict_count(sizes.into_iter(), 100, 90), 1);
+        assert_eq!(evict_count(sizes.into_iter(), 100, 75), 2);
+        assert_eq!(evict_count(sizes.into_iter(), 100, 0), 4);
+    }
+}
@@ -0,0 +1,71 @@
+//! Reply compression: payloads larger than `--compression-threshold` bytes (default 768) are
+//! gzipped at `--compression-level` (1 fastest .. 9 smallest, default 1; 0 turns compression
+//! off). Low-CPU devices can raise the threshold or switch it off and spend bandwidth instead.
+
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+pub const DEFAULT_LEVEL: u32 = 1;
+// Below this a gzip header and dictionary cost more than they save
+pub const DEFAULT_THRESHOLD: usize = 768;
+
+static LEVEL: AtomicU32 = AtomicU32::new(DEFAULT_LEVEL);
+static THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_THRESHOLD);
+
+pub fn configure(level: Option<u32>, threshold: Option<usize>) {
+    if let Some(l) = level {
+        LEVEL.store(l.min(9), Ordering::Relaxed);
+    }
+    if let Some(t) = threshold {
+        THRESHOLD.store(t, Ordering::Relaxed);
+    }
+}
+
+/// Gzip `buf` when compression is on and it is over the threshold; None means send it as is.
+pub fn compress(buf: &[u8]) -> Option<Vec<u8>> {
+    compress_with(
+        buf,
+        LEVEL.load(Ordering::Relaxed),
+        THRESHOLD.load(Ordering::Relaxed),
+    )
+}
+
+fn compress_with(buf: &[u8], level: u32, threshold: usize) -> Option<Vec<u8>> {
+    if level == 0 || buf.len() <= threshold {
+        return None;
+    }
+    // A new encoder per message so every frame carries its own gzip header
+    let mut enc = GzEncoder::new(Vec::with_capacity(buf.len() / 2), Compression::new(level));
+    enc.write_all(buf).ok()?;
+    enc.finish().ok()
+}
+
+/// Undo `compress` (for replies a relay has to look inside).
+pub fn decompress(buf: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(buf.len() * 4);
+    GzDecoder::new(buf).read_to_end(&mut out).ok()?;
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_and_level_decide_compression() {
+        let small = vec![b'a'; DEFAULT_THRESHOLD];
+        let big = b"{\"cpu\":12.5,\"name\":\"worker\"}".repeat(200);
+
+        assert!(compress_with(&small, DEFAULT_LEVEL, DEFAULT_THRESHOLD).is_none());
+        let fast = compress_with(&big, 1, DEFAULT_THRESHOLD).expect("over threshold");
+        assert!(fast.len() < big.len() / 4);
+        assert_eq!(&fast[..2], &[0x1f, 0x8b]);
+
+        // Off entirely, or a threshold above the payload
+        assert!(compress_with(&big, 0, DEFAULT_THRESHOLD).is_none());
+        assert!(compress_with(&big, 9, big.len()).is_none());
+        assert!(compress_with(&big, 9, 0).unwrap().len() <= fast.len());
+    }
+}
@@ -0,0 +1,227 @@
+//! CPU frequency scaling governors (Linux cpufreq, `/sys/devices/system/cpu/cpuN/cpufreq`).
+//!
+//! Metrics carry the governor all cores share (`cpu_governor`, "mixed" when they differ);
+//! `get_cpufreq` lists each core's governor and current frequency plus the governors the
+//! driver offers. `set_governor?name=G` switches every core, e.g. to `performance` for a
+//! benchmark. It changes the host, so it needs a control-scope token, and it is off unless
+//! the agent runs with `--cpufreq-control` (which also opens the cpufreq files in the sandbox).
+//! Writing the files needs root.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::{CoreFreq, CpuFreq, Scope, SCHEMA_VERSION};
+
+pub const CPU_DIR: &str = "/sys/devices/system/cpu";
+// The governor rarely changes; re-read it this often for metrics
+const SUMMARY_TTL: Duration = Duration::from_secs(5);
+
+static CONTROL: AtomicBool = AtomicBool::new(false);
+static SUMMARY: Mutex<Option<(Instant, Option<String>)>> = Mutex::new(None);
+
+pub fn configure(control: bool) {
+    CONTROL.store(control, Ordering::Relaxed);
+}
+
+pub fn control_enabled() -> bool {
+    CONTROL.load(Ordering::Relaxed)
+}
+
+fn read_trimmed(path: PathBuf) -> Option<String> {
+    let s = std::fs::read_to_string(path).ok()?;
+    Some(s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Cores with cpufreq under `root`, lowest number first.
+pub fn read_cores(root: &Path) -> Vec<CoreFreq> {
+    let Ok(dir) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut cores: Vec<CoreFreq> = dir
+        .flatten()
+        .filter_map(|e| {
+            let cpu: u32 = e.file_name().to_str()?.strip_prefix("cpu")?.parse().ok()?;
+            let freq = e.path().join("cpufreq");
+            Some(CoreFreq {
+                cpu,
+                governor: read_trimmed(freq.join("scaling_governor"))?,
+                cur_khz: read_trimmed(freq.join("scaling_cur_freq")).and_then(|v| v.parse().ok()),
+            })
+        })
+        .collect();
+    cores.sort_by_key(|c| c.cpu);
+    cores
+}
+
+/// Governors the driver offers (from the first core that lists them).
+pub fn available(root: &Path, cores: &[CoreFreq]) -> Vec<String> {
+    cores
+        .iter()
+        .find_map(|c| {
+            read_trimmed(
+                root.join(format!("cpu{}", c.cpu))
+                    .join("cpufreq/scaling_available_governors"),
+            )
+        })
+        .map(|s| s.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// The governor every core uses, "mixed" when they differ; None without cpufreq.
+pub fn summary(cores: &[CoreFreq]) -> Option<String> {
+    let first = &cores.first()?.governor;
+    if cores.iter().all(|c| &c.governor == first) {
+        Some(first.clone())
+    } else {
+        Some("mixed".into())
+    }
+}
+
+/// `summary` for the metrics payload, re-read every `SUMMARY_TTL`.
+pub fn cached_summary() -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mut c = SUMMARY.lock().ok()?;
+    match c.as_ref() {
+        Some((at, v)) if at.elapsed() < SUMMARY_TTL => v.clone(),
+        _ => {
+            let v = summary(&read_cores(Path::new(CPU_DIR)));
+            *c = Some((Instant::now(), v.clone()));
+            v
+        }
+    }
+}
+
+fn state(root: &Path, error: Option<String>) -> CpuFreq {
+    let cores = read_cores(root);
+    CpuFreq {
+        schema_version: SCHEMA_VERSION,
+        available: available(root, &cores),
+        cores,
+        control: control_enabled(),
+        error,
+    }
+}
+
+/// Reply to `get_cpufreq`.
+pub fn reply(root: &Path) -> CpuFreq {
+    let error = (!cfg!(target_os = "linux")).then(|| "not supported on this platform".to_string());
+    state(root, error)
+}
+
+/// Write `name` to every core's governor; the number of cores switched.
+pub fn set_all(root: &Path, name: &str) -> Result<usize, String> {
+    let cores = read_cores(root);
+    if cores.is_empty() {
+        return Err("no cpufreq on this host".into());
+    }
+    if !available(root, &cores).iter().any(|g| g == name) {
+        return Err(format!("unknown governor '{name}'"));
+    }
+    for c in &cores {
+        let path = root.join(format!("cpu{}/cpufreq/scaling_governor", c.cpu));
+        std::fs::write(&path, name).map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                "permission denied (the agent must run as root)".to_string()
+            }
+            _ => format!("cpu{}: {e}", c.cpu),
+        })?;
+    }
+    Ok(cores.len())
+}
+
+/// Reply to `set_governor?name=G`: the state afterwards, with `error` when nothing changed.
+pub fn set_reply(root: &Path, scope: Scope, query: &str) -> CpuFreq {
+    let name = query.split('&').find_map(|kv| kv.strip_prefix("name="));
+    let result = if scope != Scope::Control {
+        Err("needs a control-scope token".to_string())
+    } else if !control_enabled() {
+        Err("governor control is off (start the agent with --cpufreq-control)".to_string())
+    } else {
+        match name {
+            Some(n) => set_all(root, n),
+            None => Err("expected name=GOVERNOR".to_string()),
+        }
+    };
+    if result.is_ok() {
+        // The header should show the new governor on the next sample
+        if let Ok(mut c) = SUMMARY.lock() {
+            *c = None;
+        }
+    }
+    state(root, result.err())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cpuN/cpufreq files as a kernel would lay them out
+    fn fake_sysfs(governors: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, g) in governors.iter().enumerate() {
+            let freq = dir.path().join(format!("cpu{i}/cpufreq"));
+            std::fs::create_dir_all(&freq).unwrap();
+            std::fs::write(freq.join("scaling_governor"), format!("{g}\n")).unwrap();
+            std::fs::write(freq.join("scaling_cur_freq"), "2400000\n").unwrap();
+            std::fs::write(
+                freq.join("scaling_available_governors"),
+                "performance powersave schedutil\n",
+            )
+            .unwrap();
+        }
+        // Not a core
+        std::fs::create_dir_all(dir.path().join("cpufreq")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn cores_are_read_in_order_and_summarized() {
+        let dir = fake_sysfs(&["schedutil"; 12]);
+        let cores = read_cores(dir.path());
+        let ids: Vec<u32> = cores.iter().map(|c| c.cpu).collect();
+        assert_eq!(ids, (0..12).collect::<Vec<_>>());
+        assert_eq!(cores[0].cur_khz, Some(2_400_000));
+        assert_eq!(summary(&cores).as_deref(), Some("schedutil"));
+        assert_eq!(
+            available(dir.path(), &cores),
+            ["performance", "powersave", "schedutil"]
+        );
+
+        let mixed = read_cores(fake_sysfs(&["schedutil", "performance"]).path());
+        assert_eq!(summary(&mixed).as_deref(), Some("mixed"));
+        assert_eq!(summary(&[]), None);
+    }
+
+    #[test]
+    fn set_all_writes_every_core_but_only_known_governors() {
+        let dir = fake_sysfs(&["schedutil", "powersave"]);
+        assert_eq!(set_all(dir.path(), "performance"), Ok(2));
+        let cores = read_cores(dir.path());
+        assert!(cores.iter().all(|c| c.governor == "performance"));
+
+        assert_eq!(
+            set_all(dir.path(), "turbo"),
+            Err("unknown governor 'turbo'".into())
+        );
+        let empty = tempfile::tempdir().unwrap();
+        assert!(set_all(empty.path(), "performance").is_err());
+    }
+
+    #[test]
+    fn set_reply_needs_a_control_token() {
+        let dir = fake_sysfs(&["schedutil"]);
+        let r = set_reply(dir.path(), Scope::Read, "name=performance");
+        assert_eq!(r.error.as_deref(), Some("needs a control-scope token"));
+        assert_eq!(r.cores[0].governor, "schedutil");
+
+        configure(true);
+        let r = set_reply(dir.path(), Scope::Control, "name=performance");
+        assert_eq!(r.error, None);
+        assert!(r.control);
+        assert_eq!(r.cores[0].governor, "performance");
+    }
+}
@@ -0,0 +1,121 @@
+//! `get_disks?delta=SEQ`: per-session baseline of the last disks reply so later replies only
+//! carry the entries whose usage changed (plus removals). SEQ is the `seq` of the last reply
+//! the client applied; anything else (0, a lost reply, a new session) gets a full list.
+
+use std::collections::HashMap;
+
+use crate::types::{DiskInfo, DisksDelta, SCHEMA_VERSION};
+
+#[derive(Default)]
+pub struct DiskBaseline {
+    seq: u64,
+    // name -> (total, available) as last sent
+    sent: HashMap<String, (u64, u64)>,
+}
+
+impl DiskBaseline {
+    pub fn diff(&mut self, current: Vec<DiskInfo>, client_seq: u64) -> DisksDelta {
+        let full = client_seq == 0 || client_seq != self.seq;
+        let removed = if full {
+            Vec::new()
+        } else {
+            let mut gone: Vec<String> = self
+                .sent
+                .keys()
+                .filter(|name| !current.iter().any(|d| &d.name == *name))
+                .cloned()
+                .collect();
+            gone.sort();
+            gone
+        };
+        let prev = std::mem::take(&mut self.sent);
+        self.sent = current
+            .iter()
+            .map(|d| (d.name.clone(), (d.total, d.available)))
+            .collect();
+        self.seq += 1;
+        let disks = if full {
+            current
+        } else {
+            current
+                .into_iter()
+                .filter(|d| prev.get(&d.name) != Some(&(d.total, d.available)))
+                .collect()
+        };
+        DisksDelta {
+            schema_version: SCHEMA_VERSION,
+            seq: self.seq,
+            full,
+            disks,
+            removed,
+        }
+    }
+}
+
+/// `delta=N` from a `get_disks?…` query (None when the query doesn't ask for deltas).
+pub fn parse_query(query: &str) -> Option<u64> {
+    query
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("delta="))
+        .map(|v| v.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(name: &str, total: u64, available: u64) -> DiskInfo {
+        DiskInfo {
+            name: name.into(),
+            total,
+            available,
+        }
+    }
+
+    #[test]
+    fn only_changed_entries_follow_the_first_full_reply() {
+        let mut b = DiskBaseline::default();
+        let first = b.diff(vec![disk("sda1", 100, 50), disk("sdb1", 200, 20)], 0);
+        assert!(first.full);
+        assert_eq!(first.disks.len(), 2);
+
+        let second = b.diff(
+            vec![disk("sda1", 100, 49), disk("sdb1", 200, 20)],
+            first.seq,
+        );
+        assert!(!second.full);
+        assert_eq!(second.disks.len(), 1);
+        assert_eq!(second.disks[0].available, 49);
+
+        // Nothing changed: an empty delta
+        let third = b.diff(
+            vec![disk("sda1", 100, 49), disk("sdb1", 200, 20)],
+            second.seq,
+        );
+        assert!(!third.full && third.disks.is_empty() && third.removed.is_empty());
+
+        // Unmount one, mount another
+        let fourth = b.diff(vec![disk("sda1", 100, 49), disk("usb0", 32, 30)], third.seq);
+        assert_eq!(fourth.removed, vec!["sdb1".to_string()]);
+        assert_eq!(fourth.disks.len(), 1);
+        assert_eq!(fourth.disks[0].name, "usb0");
+    }
+
+    #[test]
+    fn stale_seq_gets_a_full_list() {
+        let mut b = DiskBaseline::default();
+        let first = b.diff(vec![disk("sda1", 100, 50)], 0);
+        let _lost = b.diff(vec![disk("sda1", 100, 40)], first.seq);
+        // The client never saw the second reply and asks relative to the first
+        let again = b.diff(vec![disk("sda1", 100, 40)], first.seq);
+        assert!(again.full);
+        assert_eq!(again.disks.len(), 1);
+    }
+
+    #[test]
+    fn query_parsing() {
+        assert_eq!(parse_query("delta=7"), Some(7));
+        assert_eq!(parse_query("delta=x"), Some(0));
+        assert_eq!(parse_query("other=1"), None);
+    }
+}
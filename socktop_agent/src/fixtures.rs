@@ -0,0 +1,95 @@
+//! Synthetic payloads for a very large host (256 cores, 10k processes, 50 disks), used by tests
+//! to check that encoding stays correct and reasonably sized at scale.
+
+use std::collections::BTreeMap;
+
+use crate::gpu::GpuMetrics;
+use crate::types::{
+    CoreIrq, DiskInfo, Metrics, NetworkInfo, ProcessInfo, ProcessesPayload, SamplerMeta,
+    CAPABILITIES,
+};
+
+pub const HUGE_CORES: usize = 256;
+pub const HUGE_PROCESSES: usize = 10_000;
+pub const HUGE_DISKS: usize = 50;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+pub fn huge_metrics() -> Metrics {
+    let cpu_per_core: Vec<f32> = (0..HUGE_CORES).map(|i| (i * 37 % 100) as f32).collect();
+    Metrics {
+        schema_version: crate::types::SCHEMA_VERSION,
+        cpu_total: cpu_per_core.iter().sum::<f32>() / HUGE_CORES as f32,
+        cpu_irq: Some(vec![CoreIrq::default(); HUGE_CORES]),
+        cpu_per_core,
+        offline_cores: Vec::new(),
+        procs_running: Some(64),
+        procs_blocked: Some(3),
+        fd: None,
+        entropy: None,
+        clock: None,
+        updates: None,
+        reboot_required: None,
+        mem_total: 2048 * GB,
+        mem_used: 1500 * GB,
+        swap_total: 64 * GB,
+        swap_used: GB,
+        hostname: "fixture-huge".into(),
+        uptime_secs: 86_400,
+        load_avg: Some((48.5, 40.25, 32.0)),
+        machine_id: Some("0123456789abcdef".into()),
+        boot_id: None,
+        instance: None,
+        labels: BTreeMap::from([("env".to_string(), "fixture".to_string())]),
+        cpu_temp_c: Some(61.5),
+        cpu_governor: Some("performance".into()),
+        disks: huge_disks(),
+        networks: (0..16)
+            .map(|i| NetworkInfo {
+                name: format!("eth{i}"),
+                received: i * GB,
+                transmitted: i * GB / 2,
+            })
+            .collect(),
+        sample_ms: 0,
+        top_processes: Vec::new(),
+        gpus: Some(
+            (0..8)
+                .map(|i| GpuMetrics {
+                    name: format!("Fixture GPU {i}"),
+                    utilization_gpu_pct: (i * 12) as u32,
+                    mem_used_bytes: i * GB,
+                    mem_total_bytes: 80 * GB,
+                })
+                .collect(),
+        ),
+        meta: SamplerMeta::default(),
+        capabilities: CAPABILITIES.to_vec(),
+        collector_errors: BTreeMap::new(),
+    }
+}
+
+pub fn huge_disks() -> Vec<DiskInfo> {
+    (0..HUGE_DISKS as u64)
+        .map(|i| DiskInfo {
+            name: format!("/dev/nvme{}n1p{}", i / 4, i % 4 + 1),
+            total: (i + 1) * 512 * GB,
+            available: (i + 1) * 128 * GB,
+        })
+        .collect()
+}
+
+pub fn huge_processes() -> ProcessesPayload {
+    ProcessesPayload {
+        process_count: HUGE_PROCESSES,
+        top_processes: (0..HUGE_PROCESSES as u32)
+            .map(|pid| ProcessInfo {
+                pid: pid + 1,
+                name: format!("worker-{pid:05}"),
+                cpu_usage: (pid % 1000) as f32 / 10.0,
+                mem_bytes: (pid as u64 % 4096) * 1024 * 1024,
+                cpu_time_ms: pid as u64 * 1_000,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,50 @@
+//! Checked binary frames for WebSocket clients that ask for them (`/ws?frames=crc`).
+//!
+//! Each binary reply is sent as `"SKF1" | payload length (u32 LE) | CRC-32 of payload (u32 LE)
+//! | payload`, so a client behind a proxy that truncates or mangles frames can tell a damaged
+//! reply from a slow one and drop it instead of failing to decode it. Text frames are sent as
+//! they are.
+
+use flate2::Crc;
+
+pub const MAGIC: &[u8; 4] = b"SKF1";
+pub const HEADER_LEN: usize = 12;
+
+/// `/ws?frames=...` value that turns checked frames on.
+pub fn requested(frames: Option<&str>) -> bool {
+    frames == Some("crc")
+}
+
+/// Prefix `payload` with the magic, its length and its checksum.
+pub fn wrap(payload: &[u8]) -> Vec<u8> {
+    let mut crc = Crc::new();
+    crc.update(payload);
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc.sum().to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_carries_length_and_crc() {
+        let framed = wrap(b"123456789");
+        assert_eq!(&framed[..4], MAGIC);
+        assert_eq!(&framed[4..8], &9u32.to_le_bytes());
+        // CRC-32 check value for "123456789"
+        assert_eq!(&framed[8..12], &0xCBF4_3926u32.to_le_bytes());
+        assert_eq!(&framed[HEADER_LEN..], b"123456789");
+    }
+
+    #[test]
+    fn only_crc_turns_it_on() {
+        assert!(requested(Some("crc")));
+        assert!(!requested(Some("none")));
+        assert!(!requested(None));
+    }
+}
@@ -1,5 +1,12 @@
-// gpu.rs
+//! GPU metrics, one entry per device: every NVIDIA card through NVML, every AMD card through
+//! amdgpu's sysfs files, and the single GPU gfxinfo picks when neither finds any (Intel, Apple,
+//! AMD on Windows).
+use std::fs;
+use std::path::Path;
+
 use gfxinfo::active_gpu;
+use nvml_wrapper::Nvml;
+use once_cell::sync::OnceCell;
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct GpuMetrics {
@@ -9,7 +16,18 @@ pub struct GpuMetrics {
     pub mem_total_bytes: u64,
 }
 
+// Loading libnvidia-ml is slow; None once it failed (no NVIDIA driver)
+static NVML: OnceCell<Option<Nvml>> = OnceCell::new();
+
+const AMD_VENDOR_ID: &str = "0x1002";
+
 pub fn collect_all_gpus() -> Result<Vec<GpuMetrics>, Box<dyn std::error::Error>> {
+    let mut gpus = nvidia_gpus();
+    gpus.extend(amd_gpus(Path::new("/sys/class/drm")));
+    if !gpus.is_empty() {
+        return Ok(gpus);
+    }
+
     let gpu = active_gpu()?; // Use ? to unwrap Result
     let info = gpu.info();
 
@@ -22,3 +40,109 @@ pub fn collect_all_gpus() -> Result<Vec<GpuMetrics>, Box<dyn std::error::Error>>
 
     Ok(vec![metrics])
 }
+
+fn nvidia_gpus() -> Vec<GpuMetrics> {
+    let Some(nvml) = NVML.get_or_init(|| Nvml::init().ok()).as_ref() else {
+        return Vec::new();
+    };
+    (0..nvml.device_count().unwrap_or(0))
+        .filter_map(|i| {
+            let dev = nvml.device_by_index(i).ok()?;
+            let mem = dev.memory_info().ok();
+            Some(GpuMetrics {
+                name: dev.name().unwrap_or_else(|_| format!("NVIDIA GPU {i}")),
+                utilization_gpu_pct: dev.utilization_rates().map_or(0, |u| u.gpu.min(100)),
+                mem_used_bytes: mem.as_ref().map_or(0, |m| m.used),
+                mem_total_bytes: mem.map_or(0, |m| m.total),
+            })
+        })
+        .collect()
+}
+
+/// AMD cards under `drm` (normally /sys/class/drm), in card order.
+fn amd_gpus(drm: &Path) -> Vec<GpuMetrics> {
+    let Ok(entries) = fs::read_dir(drm) else {
+        return Vec::new();
+    };
+    // cardN only; cardN-DP-1 and friends are its connectors
+    let mut cards: Vec<(u32, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let n = e.file_name().to_str()?.strip_prefix("card")?.parse().ok()?;
+            Some((n, e.path().join("device")))
+        })
+        .collect();
+    cards.sort();
+    cards
+        .into_iter()
+        .filter_map(|(n, dev)| {
+            let read = |f: &str| -> Option<String> {
+                let s = fs::read_to_string(dev.join(f)).ok()?;
+                Some(s.trim().to_string()).filter(|s| !s.is_empty())
+            };
+            if read("vendor")? != AMD_VENDOR_ID {
+                return None;
+            }
+            let num = |f: &str| read(f).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+            Some(GpuMetrics {
+                name: read("product_name").unwrap_or_else(|| format!("AMD GPU (card{n})")),
+                utilization_gpu_pct: num("gpu_busy_percent").min(100) as u32,
+                mem_used_bytes: num("mem_info_vram_used"),
+                mem_total_bytes: num("mem_info_vram_total"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(drm: &Path, name: &str, files: &[(&str, &str)]) {
+        let dev = drm.join(name).join("device");
+        fs::create_dir_all(&dev).unwrap();
+        for (f, v) in files {
+            fs::write(dev.join(f), format!("{v}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn every_amd_card_is_listed_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let drm = dir.path();
+        card(
+            drm,
+            "card1",
+            &[
+                ("vendor", "0x1002"),
+                ("product_name", "Radeon RX 7900 XTX"),
+                ("gpu_busy_percent", "87"),
+                ("mem_info_vram_used", "1073741824"),
+                ("mem_info_vram_total", "25753026560"),
+            ],
+        );
+        card(
+            drm,
+            "card0",
+            &[("vendor", "0x1002"), ("gpu_busy_percent", "3")],
+        );
+        // A connector, and an Intel iGPU that gfxinfo would cover
+        card(drm, "card0-DP-1", &[("vendor", "0x1002")]);
+        card(drm, "card2", &[("vendor", "0x8086")]);
+
+        let gpus = amd_gpus(drm);
+        let names: Vec<&str> = gpus.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["AMD GPU (card0)", "Radeon RX 7900 XTX"]);
+        assert_eq!(gpus[0].utilization_gpu_pct, 3);
+        assert_eq!(gpus[0].mem_total_bytes, 0);
+        assert_eq!(gpus[1].utilization_gpu_pct, 87);
+        assert_eq!(gpus[1].mem_used_bytes, 1 << 30);
+        assert_eq!(gpus[1].mem_total_bytes, 25_753_026_560);
+    }
+
+    #[test]
+    fn no_drm_directory_means_no_amd_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(amd_gpus(&dir.path().join("missing")).is_empty());
+    }
+}
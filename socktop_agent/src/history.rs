@@ -0,0 +1,355 @@
+//! Downsampled in-memory history (1 hour at 10 s steps) served via `get_history`, so a freshly
+//! connected client can draw an hour of context immediately. Disable with SOCKTOP_AGENT_HISTORY=0.
+//!
+//! The ring can also be exported for offline analysis: `get_history?format=csv|parquet` over the
+//! WebSocket, or `socktop_agent dump-history --format csv|parquet` against a running agent.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cache_budget::{self, Cache};
+use crate::metrics::collect_fast_metrics;
+use crate::rate::RateCounter;
+use crate::state::AppState;
+use crate::types::Metrics;
+
+pub const HISTORY_STEP: Duration = Duration::from_secs(10);
+pub const HISTORY_POINTS: usize = 360;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPoint {
+    /// Unix seconds
+    pub ts: u64,
+    pub cpu_total: f32,
+    pub cpu_per_core: Vec<f32>,
+    pub mem_used: u64,
+    pub mem_total: u64,
+    pub swap_used: u64,
+    // Network throughput across all interfaces since the previous point (bytes/s)
+    pub rx_bps: u64,
+    pub tx_bps: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryReply<'a> {
+    pub schema_version: u32,
+    pub step_secs: u64,
+    pub points: &'a VecDeque<HistoryPoint>,
+}
+
+pub struct HistoryRing {
+    points: VecDeque<HistoryPoint>,
+    cap: usize,
+    rx: RateCounter,
+    tx: RateCounter,
+}
+
+impl HistoryRing {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            points: VecDeque::with_capacity(cap),
+            cap,
+            rx: RateCounter::new(),
+            tx: RateCounter::new(),
+        }
+    }
+
+    pub fn push(&mut self, m: &Metrics, ts: u64, now: Instant) {
+        let rx = m.networks.iter().map(|n| n.received).sum::<u64>();
+        let tx = m.networks.iter().map(|n| n.transmitted).sum::<u64>();
+        // Hold the previous point's rate across a counter reset rather than recording a dip
+        let last = self.points.back().map(|p| (p.rx_bps, p.tx_bps));
+        let rx_bps = match self.rx.update(rx, now) {
+            Some(r) => r as u64,
+            None => last.map_or(0, |l| l.0),
+        };
+        let tx_bps = match self.tx.update(tx, now) {
+            Some(r) => r as u64,
+            None => last.map_or(0, |l| l.1),
+        };
+        if self.points.len() == self.cap {
+            self.points.pop_front();
+        }
+        self.points.push_back(HistoryPoint {
+            ts,
+            cpu_total: m.cpu_total,
+            cpu_per_core: m.cpu_per_core.clone(),
+            mem_used: m.mem_used,
+            mem_total: m.mem_total,
+            swap_used: m.swap_used,
+            rx_bps,
+            tx_bps,
+        });
+        self.trim();
+    }
+
+    /// Drop the oldest points (always keeping the newest) while the ring is over its share of
+    /// `--cache-memory-max`, and report what is left.
+    fn trim(&mut self) {
+        let bytes = |p: &HistoryPoint| {
+            std::mem::size_of::<HistoryPoint>()
+                + p.cpu_per_core.capacity() * std::mem::size_of::<f32>()
+        };
+        let total: usize = self.points.iter().map(bytes).sum();
+        let room = cache_budget::room(Cache::History);
+        let n = cache_budget::evict_count(self.points.iter().map(bytes), total, room)
+            .min(self.points.len().saturating_sub(1));
+        let evicted: usize = self.points.drain(..n).map(|p| bytes(&p)).sum();
+        cache_budget::set_used(Cache::History, total - evicted);
+    }
+
+    pub fn points(&self) -> &VecDeque<HistoryPoint> {
+        &self.points
+    }
+}
+
+impl Default for HistoryRing {
+    fn default() -> Self {
+        Self::new(HISTORY_POINTS)
+    }
+}
+
+/// Flat export formats for `get_history?format=` / `dump-history --format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// Encode the ring in `fmt`; per-core usage is flattened into `cpu0..cpuN` columns.
+pub fn export<'a, I>(points: I, fmt: ExportFormat) -> anyhow::Result<Vec<u8>>
+where
+    I: IntoIterator<Item = &'a HistoryPoint>,
+{
+    let points: Vec<&HistoryPoint> = points.into_iter().collect();
+    match fmt {
+        ExportFormat::Csv => Ok(to_csv(&points).into_bytes()),
+        ExportFormat::Parquet => to_parquet(&points),
+    }
+}
+
+const EXPORT_COLUMNS: [&str; 7] = [
+    "ts",
+    "cpu_total",
+    "mem_used",
+    "mem_total",
+    "swap_used",
+    "rx_bps",
+    "tx_bps",
+];
+
+fn core_columns(points: &[&HistoryPoint]) -> usize {
+    points
+        .iter()
+        .map(|p| p.cpu_per_core.len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn to_csv(points: &[&HistoryPoint]) -> String {
+    let cores = core_columns(points);
+    let mut out = EXPORT_COLUMNS.join(",");
+    for i in 0..cores {
+        out.push_str(&format!(",cpu{i}"));
+    }
+    out.push('\n');
+    for p in points {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{}",
+            p.ts, p.cpu_total, p.mem_used, p.mem_total, p.swap_used, p.rx_bps, p.tx_bps
+        ));
+        // Cores missing from a point (hotplug) are left empty
+        for i in 0..cores {
+            out.push(',');
+            if let Some(v) = p.cpu_per_core.get(i) {
+                out.push_str(&v.to_string());
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(feature = "parquet")]
+fn to_parquet(points: &[&HistoryPoint]) -> anyhow::Result<Vec<u8>> {
+    use parquet::data_type::{FloatType, Int64Type};
+    use parquet::file::{properties::WriterProperties, writer::SerializedFileWriter};
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    let cores = core_columns(points);
+    let mut schema = String::from(
+        "message history { REQUIRED INT64 ts; REQUIRED FLOAT cpu_total; \
+         REQUIRED INT64 mem_used; REQUIRED INT64 mem_total; REQUIRED INT64 swap_used; \
+         REQUIRED INT64 rx_bps; REQUIRED INT64 tx_bps;",
+    );
+    for i in 0..cores {
+        schema.push_str(&format!(" OPTIONAL FLOAT cpu{i};"));
+    }
+    schema.push_str(" }");
+
+    let mut buf = Vec::new();
+    let mut writer = SerializedFileWriter::new(
+        &mut buf,
+        Arc::new(parse_message_type(&schema)?),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+    let mut rg = writer.next_row_group()?;
+    let mut idx = 0;
+    while let Some(mut col) = rg.next_column()? {
+        let int = |f: fn(&HistoryPoint) -> u64| -> Vec<i64> {
+            points.iter().map(|p| f(p) as i64).collect()
+        };
+        match idx {
+            0 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.ts), None, None)?,
+            1 => {
+                let v: Vec<f32> = points.iter().map(|p| p.cpu_total).collect();
+                col.typed::<FloatType>().write_batch(&v, None, None)?
+            }
+            2 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.mem_used), None, None)?,
+            3 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.mem_total), None, None)?,
+            4 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.swap_used), None, None)?,
+            5 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.rx_bps), None, None)?,
+            6 => col
+                .typed::<Int64Type>()
+                .write_batch(&int(|p| p.tx_bps), None, None)?,
+            n => {
+                let core = n - EXPORT_COLUMNS.len();
+                let vals: Vec<f32> = points
+                    .iter()
+                    .filter_map(|p| p.cpu_per_core.get(core).copied())
+                    .collect();
+                let defs: Vec<i16> = points
+                    .iter()
+                    .map(|p| i16::from(p.cpu_per_core.len() > core))
+                    .collect();
+                col.typed::<FloatType>()
+                    .write_batch(&vals, Some(&defs), None)?
+            }
+        };
+        col.close()?;
+        idx += 1;
+    }
+    rg.close()?;
+    writer.close()?;
+    Ok(buf)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn to_parquet(_points: &[&HistoryPoint]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("parquet export not compiled in (build socktop_agent with --features parquet)")
+}
+
+/// `dump-history`: fetch `get_history?format=` from a running agent and return the raw export.
+pub async fn dump(url: &str, fmt: ExportFormat) -> anyhow::Result<Vec<u8>> {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+    let (mut ws, _) = connect_async(url).await?;
+    let name = match fmt {
+        ExportFormat::Csv => "csv",
+        ExportFormat::Parquet => "parquet",
+    };
+    ws.send(Message::Text(format!("get_history?format={name}")))
+        .await?;
+    let body = match ws.next().await {
+        Some(Ok(Message::Binary(b))) => b,
+        Some(Ok(Message::Text(t))) => {
+            if let Some(err) = t.strip_prefix(EXPORT_ERROR_PREFIX) {
+                anyhow::bail!("agent: {err}");
+            }
+            t.into_bytes()
+        }
+        Some(Ok(other)) => anyhow::bail!("unexpected reply: {other:?}"),
+        Some(Err(e)) => return Err(e.into()),
+        None => anyhow::bail!("connection closed before history reply"),
+    };
+    let _ = ws.close(None).await;
+    Ok(body)
+}
+
+/// Text reply prefix for a failed export (CSV never starts with it).
+pub const EXPORT_ERROR_PREFIX: &str = "error: ";
+
+pub fn enabled() -> bool {
+    std::env::var("SOCKTOP_AGENT_HISTORY")
+        .map(|v| v != "0")
+        .unwrap_or(true)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Background sampler feeding the ring. CPU usage is measured since the previous refresh, so with
+/// no client connected each point averages the whole step.
+pub fn spawn_sampler(state: AppState) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(HISTORY_STEP);
+        loop {
+            tick.tick().await;
+            crate::self_limit::throttle().await;
+            let m = collect_fast_metrics(&state).await;
+            if let Ok(mut h) = state.history.lock() {
+                h.push(&m, unix_now(), Instant::now());
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(ts: u64, cores: &[f32]) -> HistoryPoint {
+        HistoryPoint {
+            ts,
+            cpu_total: 12.5,
+            cpu_per_core: cores.to_vec(),
+            mem_used: 100,
+            mem_total: 200,
+            swap_used: 0,
+            rx_bps: 10,
+            tx_bps: 20,
+        }
+    }
+
+    #[test]
+    fn csv_export_flattens_cores_and_blanks_missing_ones() {
+        let pts = [point(1, &[1.0, 2.0]), point(11, &[3.0])];
+        let csv = String::from_utf8(export(&pts, ExportFormat::Csv).unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "ts,cpu_total,mem_used,mem_total,swap_used,rx_bps,tx_bps,cpu0,cpu1"
+        );
+        assert_eq!(lines[1], "1,12.5,100,200,0,10,20,1,2");
+        assert_eq!(lines[2], "11,12.5,100,200,0,10,20,3,");
+        assert_eq!(ExportFormat::parse("Parquet"), Some(ExportFormat::Parquet));
+        assert_eq!(ExportFormat::parse("json"), None);
+    }
+}
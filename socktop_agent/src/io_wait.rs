@@ -0,0 +1,341 @@
+//! `get_io`: where the I/O wait comes from, for the client's I/O panel. Combines PSI io
+//! pressure (/proc/pressure/io), CPU iowait and blocked tasks (/proc/stat), per-disk
+//! utilization (/proc/diskstats) and the processes moving the most bytes (/proc/PID/io).
+//!
+//! Utilization and byte rates are deltas against the previous sample, so the first reply
+//! after startup has `interval_ms: None` and empty lists. Walking every /proc/PID/io is not
+//! free; replies within `MIN_INTERVAL` of the last sample reuse it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::{DiskIo, IoPressure, IoProcess, IoReport, SCHEMA_VERSION};
+
+const MIN_INTERVAL: Duration = Duration::from_millis(900);
+const TOP_N: usize = 10;
+const SECTOR_BYTES: u64 = 512;
+
+/// `some`/`full` avg10 and avg60 from a PSI file; `full` is missing on older kernels.
+pub fn parse_pressure(s: &str) -> Option<IoPressure> {
+    let avgs = |kind: &str| {
+        let line = s.lines().find(|l| l.starts_with(kind))?;
+        let field = |name: &str| {
+            line.split_whitespace()
+                .find_map(|t| t.strip_prefix(name))
+                .and_then(|v| v.parse::<f32>().ok())
+        };
+        Some((field("avg10=")?, field("avg60=")?))
+    };
+    let (some_avg10, some_avg60) = avgs("some ")?;
+    let (full_avg10, full_avg60) = avgs("full ").unwrap_or((0.0, 0.0));
+    Some(IoPressure {
+        some_avg10,
+        some_avg60,
+        full_avg10,
+        full_avg60,
+    })
+}
+
+/// Cumulative counters of one block device from /proc/diskstats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiskCounters {
+    pub sectors_read: u64,
+    pub sectors_written: u64,
+    // Milliseconds the device had I/O in flight
+    pub io_ticks_ms: u64,
+}
+
+/// `major minor name reads merged sectors ms writes merged sectors ms in_flight io_ticks ...`
+pub fn parse_diskstats(s: &str) -> Vec<(String, DiskCounters)> {
+    s.lines()
+        .filter_map(|line| {
+            let f: Vec<&str> = line.split_whitespace().collect();
+            if f.len() < 13 {
+                return None;
+            }
+            let n = |i: usize| f[i].parse::<u64>().ok();
+            Some((
+                f[2].to_string(),
+                DiskCounters {
+                    sectors_read: n(5)?,
+                    sectors_written: n(9)?,
+                    io_ticks_ms: n(12)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// `read_bytes` / `write_bytes` from /proc/PID/io (bytes that actually hit storage).
+pub fn parse_proc_io(s: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        s.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some((field("read_bytes:")?, field("write_bytes:")?))
+}
+
+/// (iowait, total) jiffies from the aggregate `cpu` line of /proc/stat.
+pub fn parse_iowait(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().find(|l| l.starts_with("cpu "))?;
+    let v: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .filter_map(|t| t.parse().ok())
+        .collect();
+    (v.len() >= 5).then(|| (v[4], v.iter().sum()))
+}
+
+/// One pass over the counters the report is computed from.
+#[derive(Debug, Clone, Default)]
+pub struct IoSample {
+    pub iowait: Option<(u64, u64)>,
+    pub disks: Vec<(String, DiskCounters)>,
+    // pid -> (name, read_bytes, write_bytes, in uninterruptible sleep)
+    pub procs: HashMap<u32, (String, u64, u64, bool)>,
+}
+
+/// Rates between two samples `elapsed` apart; counters that went backwards count as zero.
+pub fn diff(
+    prev: &IoSample,
+    cur: &IoSample,
+    elapsed: Duration,
+) -> (Option<f32>, Vec<DiskIo>, Vec<IoProcess>) {
+    let ms = (elapsed.as_millis() as u64).max(1);
+    let per_sec = |d: u64| d * 1_000 / ms;
+    let iowait_pct = match (prev.iowait, cur.iowait) {
+        (Some((w0, t0)), Some((w1, t1))) if t1 > t0 => {
+            Some(w1.saturating_sub(w0) as f32 / (t1 - t0) as f32 * 100.0)
+        }
+        _ => None,
+    };
+    let disks = cur
+        .disks
+        .iter()
+        .filter_map(|(name, c)| {
+            let p = prev.disks.iter().find(|(n, _)| n == name)?.1;
+            Some(DiskIo {
+                name: name.clone(),
+                util_pct: (c.io_ticks_ms.saturating_sub(p.io_ticks_ms) as f32 / ms as f32 * 100.0)
+                    .min(100.0),
+                read_bps: per_sec(c.sectors_read.saturating_sub(p.sectors_read) * SECTOR_BYTES),
+                write_bps: per_sec(
+                    c.sectors_written.saturating_sub(p.sectors_written) * SECTOR_BYTES,
+                ),
+            })
+        })
+        .collect();
+    let mut top: Vec<IoProcess> = cur
+        .procs
+        .iter()
+        .filter_map(|(&pid, (name, r, w, blocked))| {
+            let (r0, w0) = prev.procs.get(&pid).map(|p| (p.1, p.2)).unwrap_or((*r, *w));
+            let p = IoProcess {
+                pid,
+                name: name.clone(),
+                read_bps: per_sec(r.saturating_sub(r0)),
+                write_bps: per_sec(w.saturating_sub(w0)),
+                blocked: *blocked,
+            };
+            // Blocked tasks are listed even when they moved nothing: they are the waiters
+            (p.blocked || p.read_bps + p.write_bps > 0).then_some(p)
+        })
+        .collect();
+    top.sort_by(|a, b| {
+        (b.read_bps + b.write_bps)
+            .cmp(&(a.read_bps + a.write_bps))
+            .then(b.blocked.cmp(&a.blocked))
+            .then(a.pid.cmp(&b.pid))
+    });
+    top.truncate(TOP_N);
+    (iowait_pct, disks, top)
+}
+
+#[cfg(target_os = "linux")]
+fn sample() -> IoSample {
+    use std::fs;
+    let disks = fs::read_to_string("/proc/diskstats")
+        .map(|s| parse_diskstats(&s))
+        .unwrap_or_default()
+        .into_iter()
+        // Whole devices only (partitions would double count); no loop or ram disks
+        .filter(|(name, _)| {
+            !name.starts_with("loop")
+                && !name.starts_with("ram")
+                && std::path::Path::new("/sys/block").join(name).exists()
+        })
+        .collect();
+    let mut procs = HashMap::new();
+    if let Ok(dir) = fs::read_dir("/proc") {
+        for e in dir.flatten() {
+            let Some(pid) = e.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+                continue;
+            };
+            // Other users' io is unreadable without root: skip those processes
+            let Some((r, w)) = fs::read_to_string(e.path().join("io"))
+                .ok()
+                .and_then(|s| parse_proc_io(&s))
+            else {
+                continue;
+            };
+            let stat = fs::read_to_string(e.path().join("stat")).unwrap_or_default();
+            // `pid (comm) S ...`: comm may contain spaces and parens, the state follows the last ')'
+            let (name, state) = match (stat.find('('), stat.rfind(')')) {
+                (Some(a), Some(b)) if a < b => (
+                    stat[a + 1..b].to_string(),
+                    stat[b + 1..].trim_start().chars().next(),
+                ),
+                _ => (String::new(), None),
+            };
+            procs.insert(pid, (name, r, w, state == Some('D')));
+        }
+    }
+    IoSample {
+        iowait: fs::read_to_string("/proc/stat")
+            .ok()
+            .and_then(|s| parse_iowait(&s)),
+        disks,
+        procs,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sample() -> IoSample {
+    IoSample::default()
+}
+
+struct Last {
+    at: Instant,
+    sample: IoSample,
+    report: IoReport,
+}
+
+static LAST: Mutex<Option<Last>> = Mutex::new(None);
+
+/// Reply to `get_io`.
+pub fn report() -> IoReport {
+    let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(l) = last.as_ref().filter(|l| l.at.elapsed() < MIN_INTERVAL) {
+        return l.report.clone();
+    }
+    let now = Instant::now();
+    let cur = sample();
+    let (interval_ms, (iowait_pct, disks, top)) = match last.as_ref() {
+        Some(l) => {
+            let elapsed = now - l.at;
+            (
+                Some(elapsed.as_millis() as u64),
+                diff(&l.sample, &cur, elapsed),
+            )
+        }
+        None => (None, (None, Vec::new(), Vec::new())),
+    };
+    let report = IoReport {
+        schema_version: SCHEMA_VERSION,
+        interval_ms,
+        pressure: pressure(),
+        procs_blocked: crate::procfs::run_queue().map(|(_, b)| b),
+        iowait_pct,
+        disks,
+        top,
+    };
+    *last = Some(Last {
+        at: now,
+        sample: cur,
+        report: report.clone(),
+    });
+    report
+}
+
+#[cfg(target_os = "linux")]
+fn pressure() -> Option<IoPressure> {
+    parse_pressure(&std::fs::read_to_string("/proc/pressure/io").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pressure() -> Option<IoPressure> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_with_and_without_full() {
+        let p = parse_pressure(
+            "some avg10=12.50 avg60=3.00 avg300=1.00 total=99\n\
+             full avg10=4.25 avg60=1.50 avg300=0.50 total=42\n",
+        )
+        .unwrap();
+        assert_eq!((p.some_avg10, p.some_avg60), (12.5, 3.0));
+        assert_eq!((p.full_avg10, p.full_avg60), (4.25, 1.5));
+        let p = parse_pressure("some avg10=1.00 avg60=2.00 avg300=0.00 total=1\n").unwrap();
+        assert_eq!(p.full_avg10, 0.0);
+        assert!(parse_pressure("").is_none());
+    }
+
+    #[test]
+    fn diskstats_and_proc_io_fields() {
+        let d = parse_diskstats(
+            "   8       0 sda 100 0 2000 50 300 0 4000 80 0 900 130 0 0 0 0\n\
+             bogus line\n",
+        );
+        assert_eq!(
+            d,
+            vec![(
+                "sda".to_string(),
+                DiskCounters {
+                    sectors_read: 2000,
+                    sectors_written: 4000,
+                    io_ticks_ms: 900,
+                }
+            )]
+        );
+        let io = "rchar: 10\nwchar: 20\nsyscr: 1\nsyscw: 2\nread_bytes: 4096\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+        assert_eq!(parse_proc_io(io), Some((4096, 8192)));
+        assert_eq!(
+            parse_iowait("cpu  10 0 10 60 20 0 0 0 0 0\ncpu0 1 0 1 6 2 0 0 0\n"),
+            Some((20, 100))
+        );
+    }
+
+    #[test]
+    fn rates_between_samples() {
+        let disk = |r, w, t| DiskCounters {
+            sectors_read: r,
+            sectors_written: w,
+            io_ticks_ms: t,
+        };
+        let prev = IoSample {
+            iowait: Some((100, 1_000)),
+            disks: vec![("sda".into(), disk(0, 0, 0))],
+            procs: HashMap::from([
+                (1, ("db".into(), 0, 0, false)),
+                (2, ("idle".into(), 5, 5, false)),
+                (3, ("nfs".into(), 0, 0, false)),
+            ]),
+        };
+        let cur = IoSample {
+            iowait: Some((150, 1_100)),
+            disks: vec![("sda".into(), disk(2_048, 0, 1_500))],
+            procs: HashMap::from([
+                (1, ("db".into(), 2_000_000, 0, false)),
+                (2, ("idle".into(), 5, 5, false)),
+                (3, ("nfs".into(), 0, 0, true)),
+            ]),
+        };
+        let (iowait, disks, top) = diff(&prev, &cur, Duration::from_secs(2));
+        assert_eq!(iowait, Some(50.0));
+        assert_eq!(disks[0].read_bps, 2_048 * 512 / 2);
+        assert_eq!(disks[0].util_pct, 75.0);
+        // Busy first, then the blocked process; the idle one is left out
+        let pids: Vec<u32> = top.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, [1, 3]);
+        assert_eq!(top[0].read_bps, 1_000_000);
+        assert!(top[1].blocked);
+    }
+}
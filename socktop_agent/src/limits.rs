@@ -0,0 +1,120 @@
+//! Size caps for pathological hosts: process and disk names longer than `--max-name-len`
+//! characters are cut with an ellipsis, and a processes reply encoding larger than
+//! `--max-payload-bytes` drops its lightest rows until it fits.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::proto::pb;
+
+// Generous for real names; a kernel comm is 15 bytes, most executables well under 100
+pub const DEFAULT_MAX_NAME_LEN: usize = 256;
+// Tens of thousands of rows before any cut on typical names
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 4 * 1024 * 1024;
+
+const ELLIPSIS: char = '…';
+
+static MAX_NAME_LEN: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_NAME_LEN);
+static MAX_PAYLOAD_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PAYLOAD_BYTES);
+
+pub fn configure(max_name_len: Option<usize>, max_payload_bytes: Option<usize>) {
+    if let Some(n) = max_name_len {
+        MAX_NAME_LEN.store(n.max(1), Ordering::Relaxed);
+    }
+    if let Some(n) = max_payload_bytes {
+        MAX_PAYLOAD_BYTES.store(n, Ordering::Relaxed);
+    }
+}
+
+pub fn max_name_len() -> usize {
+    MAX_NAME_LEN.load(Ordering::Relaxed)
+}
+
+pub fn max_payload_bytes() -> usize {
+    MAX_PAYLOAD_BYTES.load(Ordering::Relaxed)
+}
+
+/// Cut `s` to at most `max` characters (the last one an ellipsis), on a char boundary.
+pub fn truncate_name(s: &mut String, max: usize) {
+    if max == 0 {
+        s.clear();
+        return;
+    }
+    if let Some((cut, _)) = s.char_indices().nth(max - 1) {
+        if s[cut..].chars().nth(1).is_some() {
+            s.truncate(cut);
+            s.push(ELLIPSIS);
+        }
+    }
+}
+
+/// Row budget for a processes reply whose `rows` encoded to `encoded` bytes: None when it
+/// already fits, else a smaller count estimated from the average row size (always shrinking).
+pub fn rows_to_keep(rows: usize, encoded: usize, max: usize) -> Option<usize> {
+    if encoded <= max || rows == 0 {
+        return None;
+    }
+    let per_row = encoded.div_ceil(rows).max(1);
+    Some((max / per_row).min(rows - 1))
+}
+
+/// Keep the heaviest `keep` rows (CPU, then memory), the ones a client would show first.
+pub fn keep_heaviest(rows: &mut Vec<pb::Process>, keep: usize) {
+    rows.sort_unstable_by(|a, b| {
+        b.cpu_usage
+            .total_cmp(&a.cpu_usage)
+            .then(b.mem_bytes.cmp(&a.mem_bytes))
+            .then(a.pid.cmp(&b.pid))
+    });
+    rows.truncate(keep);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_cut_on_char_boundaries_with_an_ellipsis() {
+        let mut s = "short".to_string();
+        truncate_name(&mut s, 5);
+        assert_eq!(s, "short");
+
+        let mut s = "x".repeat(1_000_000);
+        truncate_name(&mut s, 8);
+        assert_eq!(s, "xxxxxxx…");
+        assert_eq!(s.chars().count(), 8);
+
+        // Multi-byte names must not be split inside a code point
+        let mut s = "процессов-процессов".to_string();
+        truncate_name(&mut s, 10);
+        assert_eq!(s, "процессов…");
+
+        let mut s = "日本語のプロセス名".to_string();
+        truncate_name(&mut s, 4);
+        assert_eq!(s, "日本語…");
+    }
+
+    #[test]
+    fn row_budget_shrinks_until_the_payload_fits() {
+        assert_eq!(rows_to_keep(100, 1_000, 2_000), None);
+        assert_eq!(rows_to_keep(100, 10_000, 2_000), Some(20));
+        // Always makes progress even when the estimate says nothing would change
+        assert_eq!(rows_to_keep(10, 1_001, 1_000), Some(9));
+        assert_eq!(rows_to_keep(1, 5_000, 10), Some(0));
+    }
+
+    #[test]
+    fn heaviest_rows_survive() {
+        let row = |pid, cpu, mem| pb::Process {
+            pid,
+            name: String::new(),
+            cpu_usage: cpu,
+            mem_bytes: mem,
+            cpu_time_ms: 0,
+            sampled: false,
+        };
+        let mut rows = vec![row(1, 1.0, 10), row(2, 9.0, 1), row(3, 1.0, 50)];
+        keep_heaviest(&mut rows, 2);
+        let pids: Vec<u32> = rows.iter().map(|r| r.pid).collect();
+        assert_eq!(pids, vec![2, 3]);
+    }
+}
@@ -1,24 +1,74 @@
 //! socktop agent entrypoint: sets up sysinfo handles and serves a WebSocket endpoint at /ws.
 
+mod access_log;
+mod auth_guard;
+mod cache_budget;
+mod compression;
+mod cpufreq;
+mod disk_delta;
+#[cfg(test)]
+mod fixtures;
+mod framing;
 mod gpu;
+mod history;
+mod io_wait;
+mod limits;
 mod metrics;
+mod peers;
+#[cfg(any(windows, test))]
+mod pipe;
+mod poll;
+mod proc_format;
+mod proc_inspect;
+mod procfs;
 mod proto;
+mod push;
+mod rate;
+mod relay;
+mod s3_push;
+mod sandbox;
+mod self_limit;
+mod selftest;
+mod sessions;
+mod startup;
 // sampler module removed (metrics now purely request-driven)
 mod state;
 mod types;
+mod updates;
 mod ws;
 
-use axum::{http::StatusCode, routing::get, Router};
+use axum::{
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
 use std::net::SocketAddr;
 use std::str::FromStr;
 
 mod tls;
+mod token_rotation;
+mod tokens;
+mod urls;
+#[cfg(feature = "webtransport")]
+mod webtransport;
 
 use state::AppState;
 
 fn arg_flag(name: &str) -> bool {
     std::env::args().any(|a| a == name)
 }
+fn arg_values(name: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut it = std::env::args();
+    while let Some(a) = it.next() {
+        if a == name {
+            if let Some(v) = it.next() {
+                out.push(v);
+            }
+        }
+    }
+    out
+}
 fn arg_value(name: &str) -> Option<String> {
     let mut it = std::env::args();
     while let Some(a) = it.next() {
@@ -29,8 +79,78 @@ fn arg_value(name: &str) -> Option<String> {
     None
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
+    // Landlock and seccomp only cover threads created after they are applied, so sandbox before
+    // the runtime spawns its workers. Subcommands and --version exit early and stay unconfined.
+    let subcommand = matches!(
+        std::env::args().nth(1).as_deref(),
+        Some("dump-history" | "token")
+    );
+    if !subcommand && !arg_flag("--version") && !arg_flag("-V") {
+        let disabled = arg_flag("--no-sandbox")
+            || std::env::var("SOCKTOP_AGENT_NO_SANDBOX").ok().as_deref() == Some("1");
+        let s = sandbox::apply(disabled, &sandbox_options());
+        println!(
+            "socktop_agent: sandbox landlock {}, seccomp {}",
+            s.landlock, s.seccomp
+        );
+    }
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+// Paths and features the sandbox has to leave reachable, from the same flags run() reads
+fn sandbox_options() -> sandbox::Options {
+    let config_dir = tls::config_dir()
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_default();
+    sandbox::Options {
+        config_dir,
+        tokens: tokens::default_path(),
+        token_file: token_file_path(),
+        client_ca: client_ca_path(),
+        access_log: arg_value("--access-log")
+            .or_else(|| std::env::var("SOCKTOP_ACCESS_LOG").ok())
+            .filter(|t| t != "-")
+            .map(Into::into),
+        updates: arg_flag("--updates")
+            || std::env::var("SOCKTOP_AGENT_UPDATES").ok().as_deref() == Some("1"),
+        cpu_limit: arg_value("--cpu-limit").is_some()
+            || std::env::var("SOCKTOP_AGENT_CPU_LIMIT").is_ok(),
+        cpufreq_control: cpufreq_control(),
+        library_path: std::env::var_os("LD_LIBRARY_PATH").unwrap_or_default(),
+    }
+}
+
+// --token-file / SOCKTOP_TOKEN_FILE: control tokens, one per line
+fn token_file_path() -> Option<std::path::PathBuf> {
+    arg_value("--token-file")
+        .or_else(|| std::env::var("SOCKTOP_TOKEN_FILE").ok())
+        .filter(|p| !p.is_empty())
+        .map(Into::into)
+}
+
+// --require-client-cert / SOCKTOP_REQUIRE_CLIENT_CERT: CA that client certificates must chain to
+fn client_ca_path() -> Option<std::path::PathBuf> {
+    arg_value("--require-client-cert")
+        .or_else(|| std::env::var("SOCKTOP_REQUIRE_CLIENT_CERT").ok())
+        .filter(|p| !p.is_empty())
+        .map(Into::into)
+}
+
+// --cpufreq-control: clients with a control token may switch the CPU governor
+fn cpufreq_control() -> bool {
+    arg_flag("--cpufreq-control")
+        || std::env::var("SOCKTOP_AGENT_CPUFREQ_CONTROL")
+            .ok()
+            .as_deref()
+            == Some("1")
+}
+
+async fn run() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
     // Version flag (print and exit). Keep before heavy initialization.
@@ -39,22 +159,303 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let state = AppState::new();
+    // Export a running agent's history ring and exit (no server started)
+    if std::env::args().nth(1).as_deref() == Some("dump-history") {
+        let fmt = arg_value("--format").unwrap_or_else(|| "csv".into());
+        let Some(fmt) = history::ExportFormat::parse(&fmt) else {
+            anyhow::bail!("--format must be csv or parquet, got '{fmt}'");
+        };
+        let url = arg_value("--url").unwrap_or_else(|| "ws://127.0.0.1:3000/ws".into());
+        let body = history::dump(&url, fmt).await?;
+        match arg_value("--out") {
+            Some(path) => std::fs::write(path, body)?,
+            None => std::io::Write::write_all(&mut std::io::stdout(), &body)?,
+        }
+        return Ok(());
+    }
+
+    // Issue/list/revoke hashed tokens and exit (no server started)
+    if std::env::args().nth(1).as_deref() == Some("token") {
+        let rest: Vec<String> = std::env::args().skip(2).collect();
+        return tokens::run_cli(&rest);
+    }
+
+    let mut profile = startup::StartupProfile::new(arg_flag("--profile-startup"));
+    let mut state = profile.time("sysinfo handles", AppState::new);
+
+    // Host labels from repeated --label and/or SOCKTOP_LABELS (comma-separated key=value)
+    let mut label_specs: Vec<String> = std::env::var("SOCKTOP_LABELS")
+        .map(|v| v.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    label_specs.extend(arg_values("--label"));
+    state.labels = std::sync::Arc::new(state::parse_labels(label_specs));
+
+    // Instance name when several agents share one host (carried in metrics and get_fleet)
+    state.instance = arg_value("--instance")
+        .or_else(|| std::env::var("SOCKTOP_INSTANCE").ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    state.issued_tokens = Some(std::sync::Arc::new(tokens::TokenFile::new(
+        tokens::default_path(),
+    )));
+    if let Some(path) = token_file_path() {
+        let list = token_rotation::TokenList::load(&path)
+            .map_err(|e| anyhow::anyhow!("--token-file {}: {e}", path.display()))?;
+        let list = std::sync::Arc::new(list);
+        token_rotation::reload_on_sighup(list.clone())?;
+        state.token_file = Some(list);
+    }
+
+    // Relay mode: upstreams from repeated --upstream and/or SOCKTOP_RELAY_UPSTREAMS (comma-separated)
+    if arg_flag("--relay") {
+        let mut specs = arg_values("--upstream");
+        if let Ok(env) = std::env::var("SOCKTOP_RELAY_UPSTREAMS") {
+            specs.extend(
+                env.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty()),
+            );
+        }
+        let relay = relay::Relay::from_specs(specs);
+        if relay.upstreams.is_empty() {
+            anyhow::bail!("--relay needs at least one --upstream [NAME=]ws://HOST:PORT/ws");
+        }
+        for u in &relay.upstreams {
+            println!("socktop_agent: relaying '{}' -> {}", u.name, u.url);
+        }
+        state.relay = Some(std::sync::Arc::new(relay));
+    }
+
+    // Peer discovery: seeds from repeated --peer-seed and/or SOCKTOP_PEER_SEEDS (comma-separated)
+    let mut seeds = arg_values("--peer-seed");
+    if let Ok(env) = std::env::var("SOCKTOP_PEER_SEEDS") {
+        seeds.extend(
+            env.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+        );
+    }
+    let advertise =
+        arg_value("--advertise-url").or_else(|| std::env::var("SOCKTOP_ADVERTISE_URL").ok());
+    if !seeds.is_empty() || advertise.is_some() {
+        let token = arg_value("--peer-token").or_else(|| std::env::var("SOCKTOP_PEER_TOKEN").ok());
+        // Learned peers are only dialed when their host is listed (--peer-allow, repeatable)
+        let mut allow = arg_values("--peer-allow");
+        if let Ok(env) = std::env::var("SOCKTOP_PEER_ALLOW") {
+            allow.extend(env.split(',').map(str::to_string));
+        }
+        let peers = peers::Peers::new(seeds, advertise, token, allow, std::time::Instant::now());
+        match peers.advertise() {
+            Some(url) => println!("socktop_agent: gossiping with peers as {url}"),
+            None => {
+                println!("socktop_agent: gossiping with peers (not advertised, no --advertise-url)")
+            }
+        }
+        let peers = std::sync::Arc::new(peers);
+        peers::spawn_gossip(peers.clone());
+        state.peers = Some(peers);
+    }
+
+    // Caps on name length and processes reply size (flags win over env)
+    let limit = |flag: &str, env: &str| {
+        arg_value(flag)
+            .or_else(|| std::env::var(env).ok())
+            .map(|v| {
+                v.parse::<usize>()
+                    .map_err(|_| anyhow::anyhow!("{flag} must be a number, got '{v}'"))
+            })
+            .transpose()
+    };
+    limits::configure(
+        limit("--max-name-len", "SOCKTOP_AGENT_MAX_NAME_LEN")?,
+        limit("--max-payload-bytes", "SOCKTOP_AGENT_MAX_PAYLOAD_BYTES")?,
+    );
+    cpufreq::configure(cpufreq_control());
+    // JSON processes replies for clients that predate the protobuf one
+    proc_format::configure(
+        arg_flag("--processes-json")
+            || std::env::var("SOCKTOP_AGENT_PROCESSES_JSON")
+                .ok()
+                .as_deref()
+                == Some("1"),
+    );
+    // One ceiling for the name cache, history ring and encode buffer
+    cache_budget::configure(limit(
+        "--cache-memory-max",
+        "SOCKTOP_AGENT_CACHE_MEMORY_MAX",
+    )?);
+    // gzip level (0 = off) and the reply size it kicks in at
+    compression::configure(
+        limit("--compression-level", "SOCKTOP_AGENT_COMPRESSION_LEVEL")?.map(|n| n as u32),
+        limit(
+            "--compression-threshold",
+            "SOCKTOP_AGENT_COMPRESSION_THRESHOLD",
+        )?,
+    );
+    // How long a dropped client can come back with `hello?resume=ID`
+    if let Some(secs) = limit("--session-ttl-secs", "SOCKTOP_SESSION_TTL_SECS")? {
+        state.sessions = std::sync::Arc::new(sessions::SessionTable::new(
+            std::time::Duration::from_secs(secs as u64),
+        ));
+    }
+    // Stay out of production's way: lower priority and/or a CPU cap
+    let nice = arg_value("--nice")
+        .or_else(|| std::env::var("SOCKTOP_AGENT_NICE").ok())
+        .map(|v| {
+            v.parse::<i32>()
+                .map_err(|_| anyhow::anyhow!("--nice must be a number, got '{v}'"))
+        })
+        .transpose()?;
+    let cpu_limit = arg_value("--cpu-limit")
+        .or_else(|| std::env::var("SOCKTOP_AGENT_CPU_LIMIT").ok())
+        .map(|v| self_limit::parse_cpu_limit(&v).map_err(anyhow::Error::msg))
+        .transpose()?;
+    self_limit::apply(nice, cpu_limit);
+    // Environment variables whose values get_process_env may send
+    proc_inspect::configure(
+        arg_value("--env-allow")
+            .or_else(|| std::env::var("SOCKTOP_AGENT_ENV_ALLOW").ok())
+            .as_deref(),
+    );
+    // Failed auth lockout: failures per minute before a ban, and its length
+    auth_guard::configure(
+        limit("--auth-max-failures", "SOCKTOP_AUTH_MAX_FAILURES")?.map(|n| n as u32),
+        limit("--auth-ban-secs", "SOCKTOP_AUTH_BAN_SECS")?.map(|n| n as u64),
+    );
+
+    // Per-request audit log: --access-log FILE (or - for stdout), redaction rules comma-separated
+    if let Some(target) =
+        arg_value("--access-log").or_else(|| std::env::var("SOCKTOP_ACCESS_LOG").ok())
+    {
+        let rules = arg_value("--access-log-redact")
+            .or_else(|| std::env::var("SOCKTOP_ACCESS_LOG_REDACT").ok())
+            .unwrap_or_default();
+        let redact = access_log::Redact::parse_list(&rules).map_err(anyhow::Error::msg)?;
+        let log = access_log::AccessLog::open(&target, redact)
+            .map_err(|e| anyhow::anyhow!("--access-log {target}: {e}"))?;
+        state.access_log = Some(std::sync::Arc::new(log));
+    }
+
+    // Optional slow collector: pending package updates
+    if arg_flag("--updates") || std::env::var("SOCKTOP_AGENT_UPDATES").ok().as_deref() == Some("1")
+    {
+        updates::enable();
+    }
+
+    // Run each collector once, report, and exit (deployment validation)
+    if arg_flag("--self-test") {
+        let code = selftest::run(&state).await;
+        std::process::exit(code);
+    }
+    // First sensor/GPU discovery happens in the background so early requests stay fast
+    metrics::spawn_probe(state.clone());
+    // Downsampled 1h ring buffer for get_history (the only background sampler)
+    if history::enabled() {
+        history::spawn_sampler(state.clone());
+    }
+    // Push batches of snapshots to S3-compatible storage (credentials from the AWS_* env vars)
+    if let Some(url) = arg_value("--s3-push").or_else(|| std::env::var("SOCKTOP_S3_PUSH").ok()) {
+        let env = |k: &str| std::env::var(k).unwrap_or_default();
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".into());
+        let target = s3_push::S3Target::parse(
+            &url,
+            env("AWS_ACCESS_KEY_ID"),
+            env("AWS_SECRET_ACCESS_KEY"),
+            region,
+        )
+        .map_err(|e| anyhow::anyhow!("--s3-push {url}: {e}"))?;
+        let interval = arg_value("--s3-interval")
+            .or_else(|| std::env::var("SOCKTOP_S3_INTERVAL").ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|s| std::time::Duration::from_secs(s.max(history::HISTORY_STEP.as_secs())))
+            .unwrap_or(s3_push::DEFAULT_INTERVAL);
+        let keep = arg_value("--s3-keep")
+            .or_else(|| std::env::var("SOCKTOP_S3_KEEP").ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        println!(
+            "socktop_agent: pushing metrics to {url} every {}s",
+            interval.as_secs()
+        );
+        s3_push::spawn(state.clone(), target, interval, keep);
+    }
+    let print_url = arg_flag("--print-url") || arg_flag("--qr");
+    let qr = arg_flag("--qr");
 
     // No background samplers: metrics collected on-demand per websocket request.
 
-    // Web app: route /ws to the websocket handler
+    // Web app: route /ws to the websocket handler, /poll to the plain HTTP fallback
     async fn healthz() -> StatusCode {
         println!("/healthz request");
         StatusCode::OK
     }
     let app = Router::new()
         .route("/ws", get(ws::ws_handler))
+        .route("/poll", post(poll::poll_handler))
         .route("/healthz", get(healthz))
         .with_state(state.clone());
 
+    // Experimental WebTransport listener next to /ws, with its own short-lived cert (even
+    // without --enableSSL)
+    if let Some(wt_port) = arg_value("--webtransport-port").and_then(|s| s.parse::<u16>().ok()) {
+        #[cfg(feature = "webtransport")]
+        {
+            let st = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = webtransport::serve(st, wt_port, tls::config_dir()).await {
+                    tracing::warn!("webtransport listener stopped: {e}");
+                }
+            });
+        }
+        #[cfg(not(feature = "webtransport"))]
+        anyhow::bail!("--webtransport-port {wt_port}: built without the `webtransport` feature");
+    }
+
+    // Local-only named pipe (Windows); --no-tcp serves nothing but the pipe
+    let pipe_name = if arg_flag("--pipe") {
+        Some(
+            arg_value("--pipe")
+                .filter(|v| !v.starts_with('-'))
+                .unwrap_or_default(),
+        )
+    } else {
+        std::env::var("SOCKTOP_AGENT_PIPE").ok()
+    };
+    if arg_flag("--no-tcp") && pipe_name.is_none() {
+        anyhow::bail!("--no-tcp needs --pipe, or nothing would serve clients");
+    }
+    if let Some(name) = pipe_name {
+        #[cfg(windows)]
+        {
+            let name = if name.is_empty() {
+                pipe::DEFAULT_NAME.to_string()
+            } else {
+                name
+            };
+            if arg_flag("--no-tcp") {
+                return pipe::serve(state, &name).await;
+            }
+            let st = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = pipe::serve(st, &name).await {
+                    tracing::warn!("named pipe listener stopped: {e}");
+                }
+            });
+        }
+        #[cfg(not(windows))]
+        anyhow::bail!("--pipe {name}: named pipes are only available on Windows");
+    }
+
     let enable_ssl =
         arg_flag("--enableSSL") || std::env::var("SOCKTOP_ENABLE_SSL").ok().as_deref() == Some("1");
+    if !enable_ssl && client_ca_path().is_some() {
+        anyhow::bail!(
+            "--require-client-cert needs --enableSSL (client certificates are a TLS feature)"
+        );
+    }
     if enable_ssl {
         // Port can be overridden by --port or SOCKTOP_PORT; default to 8443 when SSL
         let port = arg_value("--port")
@@ -63,13 +464,28 @@ async fn main() -> anyhow::Result<()> {
             .and_then(|s| s.parse::<u16>().ok())
             .unwrap_or(8443);
 
-        let (cert_path, key_path) = tls::ensure_self_signed_cert()?;
-        let cfg = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert_path, key_path).await?;
+        let (cert_path, key_path) = profile.time("tls cert", tls::ensure_self_signed_cert)?;
+        if print_url {
+            let fp = tls::cert_fingerprint(&cert_path)?;
+            urls::print_connect_urls(port, Some(&fp), state.auth_token.as_deref(), qr);
+        }
+        let t = std::time::Instant::now();
+        let cfg = match client_ca_path() {
+            Some(ca) => axum_server::tls_rustls::RustlsConfig::from_config(
+                tls::mutual_tls_config(&cert_path, &key_path, &ca)
+                    .map_err(|e| anyhow::anyhow!("--require-client-cert {}: {e}", ca.display()))?,
+            ),
+            None => {
+                axum_server::tls_rustls::RustlsConfig::from_pem_file(cert_path, key_path).await?
+            }
+        };
+        profile.record("tls config", t);
+        profile.report(&state).await;
 
         let addr = SocketAddr::from_str(&format!("0.0.0.0:{port}"))?;
         println!("socktop_agent: TLS enabled. Listening on wss://{addr}/ws");
         axum_server::bind_rustls(addr, cfg)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
         return Ok(());
     }
@@ -80,10 +496,14 @@ async fn main() -> anyhow::Result<()> {
         .or_else(|| std::env::var("SOCKTOP_PORT").ok())
         .and_then(|s| s.parse::<u16>().ok())
         .unwrap_or(3000);
+    if print_url {
+        urls::print_connect_urls(port, None, state.auth_token.as_deref(), qr);
+    }
+    profile.report(&state).await;
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("socktop_agent: Listening on ws://{addr}/ws");
     axum_server::bind(addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
     Ok(())
 }
@@ -1,16 +1,18 @@
 //! Metrics collection using sysinfo for socktop_agent.
 
 use crate::gpu::collect_all_gpus;
+use crate::rate::counter_delta;
 use crate::state::AppState;
 use crate::types::{DiskInfo, Metrics, NetworkInfo, ProcessInfo, ProcessesPayload};
 use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
 #[cfg(target_os = "linux")]
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 #[cfg(target_os = "linux")]
 use std::fs;
 #[cfg(target_os = "linux")]
 use std::io;
-use std::sync::Mutex;
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration as StdDuration;
 use std::time::{Duration, Instant};
 use sysinfo::{ProcessRefreshKind, ProcessesToUpdate};
@@ -49,8 +51,38 @@ struct GpuCache {
 }
 static GPUC: OnceCell<Mutex<GpuCache>> = OnceCell::new();
 
+// Per-process fd counts walk all of /proc; refresh them far less often than the fast metrics
+const FD_TTL: Duration = Duration::from_secs(10);
+const FD_TOP_N: usize = 5;
+struct FdCache {
+    at: Option<Instant>,
+    top: Vec<crate::types::FdProcess>,
+}
+static FDC: OnceCell<Mutex<FdCache>> = OnceCell::new();
+
+// Reboot markers change only on package installs; check them every minute
+const REBOOT_TTL: Duration = Duration::from_secs(60);
+struct RebootCache {
+    at: Option<Instant>,
+    v: Option<crate::types::RebootRequired>,
+}
+static REBOOTC: OnceCell<Mutex<RebootCache>> = OnceCell::new();
+
+fn cached_reboot_required() -> Option<crate::types::RebootRequired> {
+    let lock = REBOOTC.get_or_init(|| Mutex::new(RebootCache { at: None, v: None }));
+    let mut c = lock.lock().ok()?;
+    if c.at.is_none_or(|t| t.elapsed() >= REBOOT_TTL) {
+        c.v = crate::procfs::reboot_required()
+            .map(|(reason, packages)| crate::types::RebootRequired { reason, packages });
+        c.at = Some(Instant::now());
+    }
+    c.v.clone()
+}
+
 // Static caches for unchanging data
 static HOSTNAME: OnceCell<String> = OnceCell::new();
+static MACHINE_ID: OnceCell<Option<String>> = OnceCell::new();
+static BOOT_ID: OnceCell<Option<String>> = OnceCell::new();
 struct NetworkNameCache {
     names: Vec<String>,
     infos: Vec<NetworkInfo>,
@@ -106,7 +138,225 @@ fn set_gpus(v: Option<Vec<crate::gpu::GpuMetrics>>) {
     }
 }
 
+fn read_id_file(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// 1/5/15 minute load averages; Windows has no such thing (sysinfo reports zeros there).
+fn load_avg() -> Option<(f64, f64, f64)> {
+    if cfg!(windows) {
+        return None;
+    }
+    let l = sysinfo::System::load_average();
+    Some((l.one, l.five, l.fifteen))
+}
+
+// Machine id: a socktop-specific hash of the systemd/dbus id on Linux; None elsewhere (clients
+// fall back to hostname). machine-id(5) asks not to expose the raw id.
+fn machine_id() -> Option<String> {
+    MACHINE_ID
+        .get_or_init(|| {
+            if cfg!(target_os = "linux") {
+                read_id_file("/etc/machine-id")
+                    .or_else(|| read_id_file("/var/lib/dbus/machine-id"))
+                    .map(|id| app_specific_id(&id))
+            } else {
+                None
+            }
+        })
+        .clone()
+}
+
+// Same length as a machine id (128 bits in hex), stable per machine, not reversible
+fn app_specific_id(machine_id: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"socktop:")
+        .chain_update(machine_id.as_bytes())
+        .finalize();
+    digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// Boot id: kernel-provided UUID on Linux; boot timestamp elsewhere (unique per boot).
+fn boot_id() -> Option<String> {
+    BOOT_ID
+        .get_or_init(|| {
+            if cfg!(target_os = "linux") {
+                if let Some(id) = read_id_file("/proc/sys/kernel/random/boot_id") {
+                    return Some(id);
+                }
+            }
+            match sysinfo::System::boot_time() {
+                0 => None,
+                t => Some(format!("boot-{t}")),
+            }
+        })
+        .clone()
+}
+
+// Last GPU collector error, reported to clients until a collection succeeds
+static GPU_ERR: Mutex<Option<String>> = Mutex::new(None);
+
+fn probe_gpus() -> Option<Vec<crate::gpu::GpuMetrics>> {
+    let (v, err) = match collect_all_gpus() {
+        Ok(v) => ((!v.is_empty()).then_some(v), None),
+        Err(e) => {
+            warn!("gpu collection failed: {e}");
+            (None, Some(e.to_string()))
+        }
+    };
+    if let Ok(mut g) = GPU_ERR.lock() {
+        *g = err;
+    }
+    v
+}
+
+fn collector_errors() -> BTreeMap<&'static str, String> {
+    let mut errs = BTreeMap::new();
+    if let Some(e) = GPU_ERR.lock().ok().and_then(|g| g.clone()) {
+        errs.insert("gpu", e);
+    }
+    errs
+}
+
+/// Protocol features plus the collectors enabled on this agent:
+/// `history` (`get_history`), `gpu`, `temp` (CPU temperature), `updates` (`--updates`) and
+/// `cpufreq_control` (`--cpufreq-control`).
+pub fn capabilities() -> Vec<&'static str> {
+    let mut caps = crate::types::CAPABILITIES.to_vec();
+    for (name, on) in [
+        ("history", crate::history::enabled()),
+        ("gpu", gpu_enabled()),
+        ("temp", temp_enabled()),
+        ("updates", crate::updates::enabled()),
+        ("process_inspect", cfg!(target_os = "linux")),
+        ("io", cfg!(target_os = "linux")),
+        ("cpufreq", cfg!(target_os = "linux")),
+        ("cpufreq_control", crate::cpufreq::control_enabled()),
+    ]
+    .into_iter()
+    .chain(crate::sandbox::capability_flags())
+    {
+        if on {
+            caps.push(name);
+        }
+    }
+    caps
+}
+
+/// Probe sensors and GPUs once off the request path (discovery can take hundreds of ms on
+/// some hosts), then let collect_fast_metrics report them.
+pub fn spawn_probe(state: AppState) {
+    tokio::spawn(async move {
+        if temp_enabled() {
+            let val = {
+                let mut components = state.components.lock().await;
+                components.refresh(false);
+                cpu_temp_from(&components)
+            };
+            // Prime the TTL slot so the first full response reuses this reading
+            let _ = cached_temp();
+            set_temp(val);
+        }
+        if gpu_enabled() {
+            let found = tokio::task::spawn_blocking(probe_gpus)
+                .await
+                .unwrap_or(None);
+            state
+                .gpu_present
+                .store(found.is_some(), std::sync::atomic::Ordering::Release);
+            state
+                .gpu_checked
+                .store(true, std::sync::atomic::Ordering::Release);
+            let _ = cached_gpus();
+            set_gpus(found);
+        }
+        state
+            .probed
+            .store(true, std::sync::atomic::Ordering::Release);
+    });
+}
+
+/// First sensor that looks like a CPU package/die temperature.
+pub fn cpu_temp_from(components: &sysinfo::Components) -> Option<f32> {
+    components.iter().find_map(|c| {
+        let l = c.label().to_ascii_lowercase();
+        if l.contains("cpu") || l.contains("package") || l.contains("tctl") || l.contains("tdie") {
+            c.temperature()
+        } else {
+            None
+        }
+    })
+}
+
+// Per-core irq/softirq % from /proc/stat deltas; None on the first sample or off Linux.
+fn cpu_irq(state: &AppState) -> Option<Vec<crate::types::CoreIrq>> {
+    let now = crate::procfs::cpu_irq_jiffies();
+    if now.is_empty() {
+        return None;
+    }
+    let mut prev = state.irq_prev.lock().ok()?;
+    let out = if prev.is_empty() {
+        None
+    } else {
+        Some(
+            now.iter()
+                .map(|c| {
+                    let Some(p) = prev.iter().find(|p| p.id == c.id) else {
+                        return crate::types::CoreIrq::default();
+                    };
+                    let (Some(dt), Some(irq), Some(softirq)) = (
+                        counter_delta(p.total, c.total),
+                        counter_delta(p.irq, c.irq),
+                        counter_delta(p.softirq, c.softirq),
+                    ) else {
+                        return crate::types::CoreIrq::default();
+                    };
+                    let dt = dt.max(1) as f32;
+                    crate::types::CoreIrq {
+                        irq: irq as f32 / dt * 100.0,
+                        softirq: softirq as f32 / dt * 100.0,
+                    }
+                })
+                .collect(),
+        )
+    };
+    *prev = now;
+    out
+}
+
+fn fd_usage() -> Option<crate::types::FdUsage> {
+    let (allocated, max) = crate::procfs::file_handles()?;
+    let lock = FDC.get_or_init(|| {
+        Mutex::new(FdCache {
+            at: None,
+            top: Vec::new(),
+        })
+    });
+    let mut c = lock.lock().ok()?;
+    if c.at.is_none_or(|t| t.elapsed() >= FD_TTL) {
+        c.top = crate::procfs::top_fd_processes(FD_TOP_N)
+            .into_iter()
+            .map(|(pid, name, fds)| crate::types::FdProcess { pid, name, fds })
+            .collect();
+        c.at = Some(Instant::now());
+    }
+    Some(crate::types::FdUsage {
+        allocated,
+        max,
+        top: c.top.clone(),
+    })
+}
+
 // Collect only fast-changing metrics (CPU/mem/net + optional temps/gpus).
+// Milliseconds since the first call: a clock for `Metrics::sample_ms` that never steps
+fn monotonic_ms() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
 pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
     // TTL (ms) overridable via env, default 250ms
     let ttl_ms: u64 = std::env::var("SOCKTOP_AGENT_METRICS_TTL_MS")
@@ -122,6 +372,7 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
             }
         }
     }
+    let started = Instant::now();
     let mut sys = state.sys.lock().await;
     if let Err(e) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
         sys.refresh_cpu_usage();
@@ -149,25 +400,19 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
     let swap_used = sys.used_swap();
     drop(sys);
 
+    // Sensors/GPUs are first probed in the background; answer without them until that is done
+    let probed = state.probed.load(std::sync::atomic::Ordering::Acquire);
+
     // CPU temperature: only refresh sensors if cache is stale
-    let cpu_temp_c = if cached_temp().is_some() {
+    let cpu_temp_c = if !probed {
+        None
+    } else if cached_temp().is_some() {
         cached_temp()
     } else if temp_enabled() {
         let val = {
             let mut components = state.components.lock().await;
             components.refresh(false);
-            components.iter().find_map(|c| {
-                let l = c.label().to_ascii_lowercase();
-                if l.contains("cpu")
-                    || l.contains("package")
-                    || l.contains("tctl")
-                    || l.contains("tdie")
-                {
-                    c.temperature()
-                } else {
-                    None
-                }
-            })
+            cpu_temp_from(&components)
         };
         set_temp(val);
         val
@@ -175,10 +420,12 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
         None
     };
 
-    // Networks with reusable name cache
+    // Networks with reusable name cache, stamped with when they were read
+    let sample_ms;
     let networks = {
         let mut nets = state.networks.lock().await;
         nets.refresh(false);
+        sample_ms = monotonic_ms();
 
         // Get or initialize network cache
         let cache = NETWORK_CACHE.get_or_init(|| {
@@ -210,7 +457,7 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
     };
 
     // GPUs: if we already determined none exist, short-circuit (no repeated probing)
-    let gpus = if gpu_enabled() {
+    let gpus = if gpu_enabled() && probed {
         if state.gpu_checked.load(std::sync::atomic::Ordering::Acquire)
             && !state.gpu_present.load(std::sync::atomic::Ordering::Relaxed)
         {
@@ -218,14 +465,7 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
         } else if cached_gpus().is_some() {
             cached_gpus()
         } else {
-            let v = match collect_all_gpus() {
-                Ok(v) if !v.is_empty() => Some(v),
-                Ok(_) => None,
-                Err(e) => {
-                    warn!("gpu collection failed: {e}");
-                    None
-                }
-            };
+            let v = probe_gpus();
             // First probe records presence; subsequent calls rely on cache flags.
             if !state
                 .gpu_checked
@@ -248,19 +488,61 @@ pub async fn collect_fast_metrics(state: &AppState) -> Metrics {
         None
     };
 
+    let meta = match state.cadence.lock() {
+        Ok(mut c) => {
+            c.metrics.record(started);
+            c.snapshot()
+        }
+        Err(_) => Default::default(),
+    };
+
+    let run_queue = crate::procfs::run_queue();
     let metrics = Metrics {
+        schema_version: crate::types::SCHEMA_VERSION,
         cpu_total,
         cpu_per_core,
+        offline_cores: crate::procfs::offline_cores(),
+        cpu_irq: cpu_irq(state),
+        procs_running: run_queue.map(|(r, _)| r),
+        procs_blocked: run_queue.map(|(_, b)| b),
+        fd: fd_usage(),
+        updates: crate::updates::cached(),
+        reboot_required: cached_reboot_required(),
+        clock: crate::procfs::clock_sync().map(|(synchronized, offset_ms, max_error_ms)| {
+            crate::types::ClockSync {
+                synchronized,
+                offset_ms,
+                max_error_ms,
+            }
+        }),
+        entropy: crate::procfs::entropy().map(|(avail_bits, pool_bits, hwrng)| {
+            crate::types::EntropyInfo {
+                avail_bits,
+                pool_bits,
+                hwrng,
+            }
+        }),
         mem_total,
         mem_used,
         swap_total,
         swap_used,
         hostname,
+        uptime_secs: sysinfo::System::uptime(),
+        load_avg: load_avg(),
+        machine_id: machine_id(),
+        boot_id: boot_id(),
+        instance: state.instance.clone(),
+        labels: (*state.labels).clone(),
         cpu_temp_c,
+        cpu_governor: crate::cpufreq::cached_summary(),
         disks: Vec::new(),
         networks,
+        sample_ms,
         top_processes: Vec::new(),
         gpus,
+        meta,
+        capabilities: capabilities(),
+        collector_errors: collector_errors(),
     };
     {
         let mut cache = state.cache_metrics.lock().await;
@@ -284,6 +566,7 @@ pub async fn collect_disks(state: &AppState) -> Vec<DiskInfo> {
             }
         }
     }
+    let started = Instant::now();
     let mut disks_list = state.disks.lock().await;
     disks_list.refresh(false); // don't drop missing disks
     let disks: Vec<DiskInfo> = disks_list
@@ -294,6 +577,9 @@ pub async fn collect_disks(state: &AppState) -> Vec<DiskInfo> {
             available: d.available_space(),
         })
         .collect();
+    if let Ok(mut c) = state.cadence.lock() {
+        c.disks.record(started);
+    }
     {
         let mut cache = state.cache_disks.lock().await;
         cache.set(disks.clone());
@@ -336,6 +622,17 @@ fn read_proc_jiffies(pid: u32) -> Option<u64> {
     Some(utime.saturating_add(stime))
 }
 
+// /proc/PID/stat counts CPU time in USER_HZ ticks (100 on nearly every kernel)
+#[cfg(target_os = "linux")]
+fn jiffies_to_ms(jiffies: u64) -> u64 {
+    static HZ: OnceLock<u64> = OnceLock::new();
+    let hz = *HZ.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        n if n > 0 => n as u64,
+        _ => 100,
+    });
+    jiffies.saturating_mul(1000) / hz
+}
+
 /// Collect all processes (Linux): compute CPU% via /proc jiffies delta; sorting moved to client.
 #[cfg(target_os = "linux")]
 pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
@@ -353,6 +650,7 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
             }
         }
     }
+    let started = Instant::now();
     // Reuse shared System to avoid reallocation; refresh processes fully.
     let mut sys_guard = state.sys.lock().await;
     let sys = &mut *sys_guard;
@@ -402,8 +700,14 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
                 name: p.name().to_string_lossy().into_owned(),
                 cpu_usage: 0.0,
                 mem_bytes: p.memory(),
+                cpu_time_ms: current
+                    .get(&p.pid().as_u32())
+                    .map_or(0, |&j| jiffies_to_ms(j)),
             })
             .collect();
+        if let Ok(mut c) = state.cadence.lock() {
+            c.processes.record(started);
+        }
         return ProcessesPayload {
             process_count: total_count,
             top_processes: procs,
@@ -419,13 +723,15 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
             let pid = p.pid().as_u32();
             let now = current.get(&pid).copied().unwrap_or(0);
             let prev = last_map.remove(&pid).unwrap_or(0);
-            let du = now.saturating_sub(prev) as f32;
+            // A reused pid can start below the old process's jiffies
+            let du = counter_delta(prev, now).unwrap_or(0) as f32;
             let cpu = ((du / dt) * 100.0).clamp(0.0, 100.0);
             ProcessInfo {
                 pid,
                 name: p.name().to_string_lossy().into_owned(),
                 cpu_usage: cpu,
                 mem_bytes: p.memory(),
+                cpu_time_ms: jiffies_to_ms(now),
             }
         })
         .collect();
@@ -434,6 +740,9 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
         process_count: total_count,
         top_processes: procs,
     };
+    if let Ok(mut c) = state.cadence.lock() {
+        c.processes.record(started);
+    }
     {
         let mut cache = state.cache_processes.lock().await;
         cache.set(payload.clone());
@@ -455,6 +764,7 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
         }
     }
 
+    let started = Instant::now();
     // Single efficient refresh with optimized CPU collection
     let (total_count, procs) = {
         let mut sys = state.sys.lock().await;
@@ -480,17 +790,20 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
         // Reuse allocations via process cache
         let mut proc_cache = state.proc_cache.lock().await;
         proc_cache.reusable_vec.clear();
+        proc_cache.pass += 1;
+        let pass = proc_cache.pass;
 
         // Collect all processes, will sort by CPU later
         for p in sys.processes().values() {
             let pid = p.pid().as_u32();
 
             // Reuse cached name if available
-            let name = if let Some(cached) = proc_cache.names.get(&pid) {
+            let name = if let Some((cached, used)) = proc_cache.names.get_mut(&pid) {
+                *used = pass;
                 cached.clone()
             } else {
                 let new_name = p.name().to_string_lossy().into_owned();
-                proc_cache.names.insert(pid, new_name.clone());
+                proc_cache.names.insert(pid, (new_name.clone(), pass));
                 new_name
             };
 
@@ -504,6 +817,7 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
                 name,
                 cpu_usage: total_cpu,
                 mem_bytes: p.memory(),
+                cpu_time_ms: p.accumulated_cpu_time(),
             });
         }
 
@@ -533,6 +847,8 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
                 now.elapsed().as_millis()
             );
         }
+        // Stay within --cache-memory-max
+        proc_cache.trim_names();
 
         // Get all processes, take ownership of the vec (will be replaced with empty vec)
         (total_count, std::mem::take(&mut proc_cache.reusable_vec))
@@ -542,6 +858,9 @@ pub async fn collect_processes_all(state: &AppState) -> ProcessesPayload {
         process_count: total_count,
         top_processes: procs,
     };
+    if let Ok(mut c) = state.cadence.lock() {
+        c.processes.record(started);
+    }
 
     {
         let mut cache = state.cache_processes.lock().await;
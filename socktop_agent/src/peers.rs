@@ -0,0 +1,447 @@
+//! Peer discovery by gossip (`--peer-seed ws://other:3000/ws`, `--advertise-url URL`).
+//!
+//! Every `GOSSIP_INTERVAL` the agent sends `get_peers?announce=URL` to each peer it dials and
+//! merges the lists that come back, so a client connected to any one agent can ask it for the
+//! rest of the fleet with `get_peers`. The announced URL is how other agents and clients reach
+//! this one; without `--advertise-url` the agent still learns peers but doesn't add itself to
+//! their lists. Only control-scope connections may announce.
+//!
+//! Peers learned by announce or gossip are listed, but only dialed when their host is on
+//! `--peer-allow`; seeds are always dialed. `--peer-token` goes to seeds only, in an
+//! `Authorization: Bearer` header, so an announced URL can't collect it.
+//!
+//! Peers that don't answer for `PEER_TTL` (and that nobody else has vouched for since) drop
+//! out of the list; seeds stay. URLs are stored without their query, so tokens are never
+//! passed on.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::relay::{decode_json, Upstream};
+use crate::state::AppState;
+use crate::types::Scope;
+
+pub const GOSSIP_INTERVAL: Duration = Duration::from_secs(30);
+const PEER_TTL: Duration = Duration::from_secs(120);
+// Bound on the table, whatever peers claim
+const MAX_PEERS: usize = 256;
+
+/// One agent as listed in a `get_peers` reply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Seconds since this agent last heard from it directly; None when only known second hand
+    #[serde(default)]
+    pub seen_secs_ago: Option<u64>,
+    /// The agent answering the request
+    #[serde(default, rename = "self", skip_serializing_if = "std::ops::Not::not")]
+    pub is_self: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    host: Option<String>,
+    instance: Option<String>,
+    labels: BTreeMap<String, String>,
+    // Last direct contact (it answered us, or announced itself)
+    seen: Option<Instant>,
+    // Last time anyone mentioned it
+    heard: Instant,
+    seed: bool,
+}
+
+pub struct Peers {
+    advertise: Option<String>,
+    token: Option<String>,
+    // Hosts (`host` or `host:port`) that may be dialed besides the seeds
+    allow: Vec<String>,
+    table: Mutex<BTreeMap<String, Entry>>,
+}
+
+pub type SharedPeers = Arc<Peers>;
+
+/// `ws[s]://host:port/path` without query, fragment or trailing slash; None for other schemes.
+pub fn normalize(url: &str) -> Option<String> {
+    let mut u = url::Url::parse(url.trim()).ok()?;
+    if !matches!(u.scheme(), "ws" | "wss") || u.host_str().is_none() {
+        return None;
+    }
+    u.set_query(None);
+    u.set_fragment(None);
+    Some(u.as_str().trim_end_matches('/').to_string())
+}
+
+impl Peers {
+    pub fn new<I: IntoIterator<Item = String>>(
+        seeds: I,
+        advertise: Option<String>,
+        token: Option<String>,
+        allow: Vec<String>,
+        now: Instant,
+    ) -> Self {
+        let advertise = advertise.as_deref().and_then(normalize);
+        let table = seeds
+            .into_iter()
+            .filter_map(|s| {
+                let url = normalize(&s);
+                if url.is_none() {
+                    eprintln!("socktop_agent: ignoring invalid --peer-seed '{s}'");
+                }
+                url
+            })
+            .filter(|u| Some(u) != advertise.as_ref())
+            .map(|url| {
+                let entry = Entry {
+                    host: None,
+                    instance: None,
+                    labels: BTreeMap::new(),
+                    seen: None,
+                    heard: now,
+                    seed: true,
+                };
+                (url, entry)
+            })
+            .collect();
+        Peers {
+            advertise,
+            token: token.filter(|t| !t.is_empty()),
+            allow: allow
+                .into_iter()
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            table: Mutex::new(table),
+        }
+    }
+
+    pub fn advertise(&self) -> Option<&str> {
+        self.advertise.as_deref()
+    }
+
+    /// A peer announced itself with `get_peers?announce=URL`.
+    pub fn announce(&self, url: &str, now: Instant) {
+        if let Some(url) = normalize(url) {
+            self.touch(url, now, true);
+        }
+    }
+
+    fn touch(&self, url: String, now: Instant, direct: bool) {
+        if Some(&url) == self.advertise.as_ref() {
+            return;
+        }
+        let Ok(mut t) = self.table.lock() else {
+            return;
+        };
+        if !t.contains_key(&url) && t.len() >= MAX_PEERS {
+            return;
+        }
+        let e = t.entry(url).or_insert(Entry {
+            host: None,
+            instance: None,
+            labels: BTreeMap::new(),
+            seen: None,
+            heard: now,
+            seed: false,
+        });
+        e.heard = now;
+        if direct {
+            e.seen = Some(now);
+        }
+    }
+
+    /// `from` answered `get_peers` with `list`: it is alive, and so may be the peers it knows.
+    pub fn merge(&self, from: &str, list: Vec<PeerInfo>, now: Instant) {
+        self.touch(from.to_string(), now, true);
+        for p in list {
+            // Its own entry carries its host details (its URL may be empty: no --advertise-url)
+            if p.is_self {
+                if let Ok(mut t) = self.table.lock() {
+                    if let Some(e) = t.get_mut(from) {
+                        e.host = p.host;
+                        e.instance = p.instance;
+                        e.labels = p.labels;
+                    }
+                }
+                continue;
+            }
+            // Second hand: only keeps it listed while someone still mentions it
+            if let Some(url) = normalize(&p.url).filter(|u| u != from) {
+                self.touch(url, now, false);
+            }
+        }
+    }
+
+    /// Drop peers nobody has heard from for `PEER_TTL`; seeds are kept.
+    pub fn prune(&self, now: Instant) {
+        if let Ok(mut t) = self.table.lock() {
+            t.retain(|_, e| e.seed || now.saturating_duration_since(e.heard) < PEER_TTL);
+        }
+    }
+
+    /// The table for `get_peers`, `me` (the answering agent) first.
+    pub fn list(&self, me: PeerInfo, now: Instant) -> Vec<PeerInfo> {
+        let mut out = vec![me];
+        if let Ok(t) = self.table.lock() {
+            out.extend(t.iter().map(|(url, e)| PeerInfo {
+                url: url.clone(),
+                host: e.host.clone(),
+                instance: e.instance.clone(),
+                labels: e.labels.clone(),
+                seen_secs_ago: e.seen.map(|s| now.saturating_duration_since(s).as_secs()),
+                is_self: false,
+            }));
+        }
+        out
+    }
+
+    fn allowed(&self, url: &str) -> bool {
+        let Ok(u) = url::Url::parse(url) else {
+            return false;
+        };
+        let Some(host) = u.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let with_port = u.port().map(|p| format!("{host}:{p}"));
+        self.allow
+            .iter()
+            .any(|a| *a == host || Some(a) == with_port.as_ref())
+    }
+
+    // Peers to ask this round: the seeds, and learned ones on the allow-list
+    fn dial_targets(&self) -> Vec<(String, bool)> {
+        let Ok(t) = self.table.lock() else {
+            return Vec::new();
+        };
+        t.iter()
+            .filter(|(url, e)| e.seed || self.allowed(url))
+            .map(|(url, e)| (url.clone(), e.seed))
+            .collect()
+    }
+
+    // Connection to a peer; only seeds get our peer token
+    fn upstream(&self, url: &str, seed: bool) -> Option<Upstream> {
+        let token = self.token.clone().filter(|_| seed);
+        Some(Upstream::parse(url)?.with_token(token))
+    }
+
+    fn request(&self) -> String {
+        match &self.advertise {
+            Some(u) => format!("get_peers?announce={u}"),
+            None => "get_peers".to_string(),
+        }
+    }
+}
+
+/// The answering agent's own entry.
+pub fn local_entry(state: &AppState) -> PeerInfo {
+    PeerInfo {
+        url: state
+            .peers
+            .as_ref()
+            .and_then(|p| p.advertise())
+            .unwrap_or_default()
+            .to_string(),
+        host: Some(state.hostname.clone()),
+        instance: state.instance.clone(),
+        labels: (*state.labels).clone(),
+        seen_secs_ago: Some(0),
+        is_self: true,
+    }
+}
+
+/// `get_peers[?announce=URL]`: record an announcing peer (control scope only), reply with
+/// everything known.
+pub fn reply(state: &AppState, scope: Scope, query: Option<&str>) -> Vec<PeerInfo> {
+    let now = Instant::now();
+    let me = local_entry(state);
+    let Some(peers) = state.peers.as_ref() else {
+        return vec![me];
+    };
+    if let Some(url) = query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|kv| kv.strip_prefix("announce="))
+    {
+        if scope == Scope::Control {
+            peers.announce(url, now);
+        } else {
+            debug!("peers: ignoring announce of {url} from a read-scope connection");
+        }
+    }
+    peers.list(me, now)
+}
+
+// One gossip round: ask the peers we may dial, merge what comes back
+async fn round(peers: &Peers) {
+    let futs = peers
+        .dial_targets()
+        .into_iter()
+        .map(|(url, seed)| async move {
+            let up = peers.upstream(&url, seed)?;
+            let list: Vec<PeerInfo> =
+                serde_json::from_value(decode_json(up.request(&peers.request()).await?)?).ok()?;
+            Some((url, list))
+        });
+    let replies = futures_util::future::join_all(futs).await;
+    let now = Instant::now();
+    for (url, list) in replies.into_iter().flatten() {
+        debug!("peers: {url} knows {} agents", list.len());
+        peers.merge(&url, list, now);
+    }
+    peers.prune(now);
+}
+
+/// Gossip with the peers every `GOSSIP_INTERVAL` for as long as the agent runs.
+pub fn spawn_gossip(peers: SharedPeers) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(GOSSIP_INTERVAL);
+        loop {
+            tick.tick().await;
+            round(&peers).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(url: &str, host: &str, is_self: bool) -> PeerInfo {
+        PeerInfo {
+            url: url.into(),
+            host: Some(host.into()),
+            instance: None,
+            labels: BTreeMap::new(),
+            seen_secs_ago: None,
+            is_self,
+        }
+    }
+
+    fn urls(list: &[PeerInfo]) -> Vec<&str> {
+        list.iter().map(|p| p.url.as_str()).collect()
+    }
+
+    #[test]
+    fn urls_lose_their_query_and_need_a_ws_scheme() {
+        assert_eq!(
+            normalize("ws://h:3000/ws?token=secret").as_deref(),
+            Some("ws://h:3000/ws")
+        );
+        assert_eq!(normalize("wss://h/").as_deref(), Some("wss://h"));
+        assert_eq!(normalize("http://h:3000/ws"), None);
+        assert_eq!(normalize("not a url"), None);
+    }
+
+    #[test]
+    fn merge_learns_the_fleet_and_skips_itself() {
+        let t0 = Instant::now();
+        let peers = Peers::new(
+            ["ws://a:3000/ws".to_string(), "bogus".to_string()],
+            Some("ws://me:3000/ws".into()),
+            None,
+            Vec::new(),
+            t0,
+        );
+        assert_eq!(peers.request(), "get_peers?announce=ws://me:3000/ws");
+        peers.merge(
+            "ws://a:3000/ws",
+            vec![
+                info("ws://a:3000/ws", "a", true),
+                info("ws://b:3000/ws?token=x", "b", false),
+                info("ws://me:3000/ws", "me", false),
+            ],
+            t0,
+        );
+        let me = info("ws://me:3000/ws", "me", true);
+        let list = peers.list(me, t0 + Duration::from_secs(5));
+        assert_eq!(
+            urls(&list),
+            vec!["ws://me:3000/ws", "ws://a:3000/ws", "ws://b:3000/ws"]
+        );
+        assert_eq!(list[1].host.as_deref(), Some("a"));
+        assert_eq!(list[1].seen_secs_ago, Some(5));
+        // Known only through a
+        assert_eq!(list[2].seen_secs_ago, None);
+    }
+
+    #[test]
+    fn silent_peers_expire_but_seeds_stay() {
+        let t0 = Instant::now();
+        let peers = Peers::new(
+            ["ws://seed:3000/ws".to_string()],
+            None,
+            None,
+            Vec::new(),
+            t0,
+        );
+        peers.announce("ws://c:3000/ws", t0);
+        peers.announce("ftp://nope", t0);
+        peers.prune(t0 + PEER_TTL / 2);
+        let me = info("", "me", true);
+        assert_eq!(peers.list(me.clone(), t0).len(), 3);
+        peers.prune(t0 + PEER_TTL);
+        assert_eq!(urls(&peers.list(me, t0))[1..], ["ws://seed:3000/ws"]);
+    }
+
+    #[test]
+    fn only_seeds_and_allowed_hosts_are_dialed_and_only_seeds_get_the_token() {
+        let t0 = Instant::now();
+        let peers = Peers::new(
+            ["ws://a:3000/ws?token=leak".to_string()],
+            None,
+            Some("t0k".into()),
+            vec!["B".into(), "c:4000".into()],
+            t0,
+        );
+        peers.announce("ws://b:3000/ws", t0);
+        peers.announce("ws://c:3000/ws", t0);
+        peers.announce("ws://evil:3000/ws", t0);
+        peers.merge(
+            "ws://a:3000/ws",
+            vec![info("ws://c:4000/ws", "c", false)],
+            t0,
+        );
+        let targets = peers.dial_targets();
+        assert_eq!(
+            targets,
+            vec![
+                ("ws://a:3000/ws".to_string(), true),
+                ("ws://b:3000/ws".to_string(), false),
+                ("ws://c:4000/ws".to_string(), false),
+            ]
+        );
+
+        let seed = peers.upstream("ws://a:3000/ws", true).unwrap();
+        assert_eq!(
+            (seed.url.as_str(), seed.token.as_deref()),
+            ("ws://a:3000/ws", Some("t0k"))
+        );
+        assert_eq!(peers.upstream("ws://b:3000/ws", false).unwrap().token, None);
+        assert_eq!(peers.request(), "get_peers");
+    }
+
+    #[test]
+    fn read_scope_cannot_announce() {
+        let mut state = AppState::new();
+        state.peers = Some(Arc::new(Peers::new(
+            Vec::new(),
+            None,
+            None,
+            Vec::new(),
+            Instant::now(),
+        )));
+        let list = reply(&state, Scope::Read, Some("announce=ws://evil:3000/ws"));
+        assert_eq!(list.len(), 1, "only the answering agent");
+        let list = reply(&state, Scope::Control, Some("announce=ws://peer:3000/ws"));
+        assert_eq!(urls(&list)[1..], ["ws://peer:3000/ws"]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Windows named pipe listener (`--pipe [NAME]`, default `\\.\pipe\socktop_agent`) for local
+//! monitoring without opening a TCP port.
+//!
+//! Same commands and reply bytes as `/ws`. Every message is a frame: a 4-byte little-endian
+//! length, then the payload. The client's first frame is its token (empty for none) and the
+//! agent acknowledges it with `ok` or hangs up. After that each command frame gets one reply
+//! frame: a kind byte (`0` text, `1` binary, as over WebTransport) and the reply body, or an
+//! empty frame for commands without a reply.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::relay::Reply;
+
+pub const DEFAULT_NAME: &str = "socktop_agent";
+pub const KIND_TEXT: u8 = 0;
+pub const KIND_BINARY: u8 = 1;
+// Commands and tokens are short text; anything longer is not a socktop client
+const MAX_REQUEST: usize = 4 * 1024;
+
+/// `socktop_agent` or a full `\\.\pipe\...` path -> the full pipe path.
+pub fn pipe_path(name: &str) -> String {
+    if name.starts_with(r"\\") {
+        name.to_string()
+    } else {
+        format!(r"\\.\pipe\{name}")
+    }
+}
+
+/// One request frame; None on EOF, I/O errors or oversized frames.
+pub async fn read_request<R: AsyncRead + Unpin>(r: &mut R) -> Option<String> {
+    let len = r.read_u32_le().await.ok()? as usize;
+    if len > MAX_REQUEST {
+        return None;
+    }
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf).await.ok()?;
+    String::from_utf8(buf).ok()
+}
+
+/// Reply frame: kind byte + body, or an empty frame when there is nothing to send.
+pub async fn write_reply<W: AsyncWrite + Unpin>(
+    w: &mut W,
+    reply: Option<Reply>,
+) -> std::io::Result<()> {
+    let (kind, body) = match reply {
+        Some(Reply::Text(t)) => (Some(KIND_TEXT), t.into_bytes()),
+        Some(Reply::Binary(b)) => (Some(KIND_BINARY), b),
+        None => (None, Vec::new()),
+    };
+    let len = body.len() + kind.map_or(0, |_| 1);
+    w.write_u32_le(len as u32).await?;
+    if let Some(k) = kind {
+        w.write_u8(k).await?;
+    }
+    w.write_all(&body).await?;
+    w.flush().await
+}
+
+#[cfg(windows)]
+pub async fn serve(state: crate::state::AppState, name: &str) -> anyhow::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    use crate::access_log::Request;
+    use crate::sessions::Conn;
+    use crate::ws::respond_logged;
+
+    let path = pipe_path(name);
+    // Created before the loop so a second agent on the same name fails at startup
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&path)?;
+    println!("socktop_agent: named pipe on {path}");
+    loop {
+        server.connect().await?;
+        let mut pipe = server;
+        // Next instance for the next client before serving this one
+        server = ServerOptions::new().create(&path)?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            let Some(token) = read_request(&mut pipe).await else {
+                return;
+            };
+            let token = Some(token.as_str()).filter(|t| !t.is_empty());
+            let Some(scope) = state.authorize(token) else {
+                if let Some(log) = state.access_log.as_deref() {
+                    log.event("pipe", None, "auth_failure", "bad or missing token".into());
+                }
+                return;
+            };
+            let ok = Some(Reply::Text("ok".into()));
+            if write_reply(&mut pipe, ok).await.is_err() {
+                return;
+            }
+            let conn = Conn::new(scope);
+            while let Some(cmd) = read_request(&mut pipe).await {
+                let req = Request {
+                    transport: "pipe",
+                    client: None,
+                    upstream: None,
+                    command: cmd.trim(),
+                };
+                let reply = respond_logged(&state, &conn, &req).await;
+                if write_reply(&mut pipe, reply).await.is_err() {
+                    break;
+                }
+            }
+            conn.close(&state.sessions);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        let (mut client, mut agent) = tokio::io::duplex(1024);
+        client.write_u32_le(11).await.unwrap();
+        client.write_all(b"get_metrics").await.unwrap();
+        assert_eq!(
+            read_request(&mut agent).await.as_deref(),
+            Some("get_metrics")
+        );
+
+        write_reply(&mut agent, Some(Reply::Binary(vec![1, 2, 3])))
+            .await
+            .unwrap();
+        assert_eq!(client.read_u32_le().await.unwrap(), 4);
+        let mut body = [0u8; 4];
+        client.read_exact(&mut body).await.unwrap();
+        assert_eq!(body, [KIND_BINARY, 1, 2, 3]);
+
+        write_reply(&mut agent, None).await.unwrap();
+        assert_eq!(client.read_u32_le().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn oversized_requests_are_dropped() {
+        let (mut client, mut agent) = tokio::io::duplex(64);
+        client.write_u32_le(1 << 20).await.unwrap();
+        assert_eq!(read_request(&mut agent).await, None);
+    }
+
+    #[test]
+    fn names_expand_to_pipe_paths() {
+        assert_eq!(pipe_path(DEFAULT_NAME), r"\\.\pipe\socktop_agent");
+        assert_eq!(pipe_path(r"\\.\pipe\custom"), r"\\.\pipe\custom");
+    }
+}
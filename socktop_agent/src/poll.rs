@@ -0,0 +1,197 @@
+//! HTTP polling fallback (`POST /poll`) for networks whose proxies or middleboxes refuse the
+//! WebSocket upgrade.
+//!
+//! Same commands and reply bytes as `/ws`, one request per command: the body is the command
+//! text, and a 200 reply is a kind byte (`0` text, `1` binary, as over WebTransport) followed
+//! by the reply body; commands without a reply get 204. `?token=` and `?upstream=` work as on
+//! `/ws`. HTTP requests don't share a connection, so the session from `hello` travels in the
+//! `x-socktop-session` header instead: the agent sets it on replies and the client sends it
+//! back. Between requests the session stays detached, so it expires like a dropped WebSocket
+//! once the client stops polling.
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use crate::access_log::Request;
+use crate::auth_guard;
+use crate::relay::Reply;
+use crate::sessions::Conn;
+use crate::state::AppState;
+use crate::ws::respond_logged;
+
+pub const SESSION_HEADER: &str = "x-socktop-session";
+pub const KIND_TEXT: u8 = 0;
+pub const KIND_BINARY: u8 = 1;
+// Commands are short text lines; anything longer is not a socktop client
+const MAX_REQUEST: usize = 4 * 1024;
+
+pub async fn poll_handler(
+    State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Query(q): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let token = auth_guard::bearer(header).or(q.get("token").map(String::as_str));
+    let Some(scope) = auth_guard::admit(&state, "poll", client, token).await else {
+        return StatusCode::FORBIDDEN.into_response();
+    };
+    if body.len() > MAX_REQUEST {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    }
+    let Ok(cmd) = std::str::from_utf8(&body) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let cmd = cmd.trim();
+    // Relay mode: forward to one leaf agent, as /ws?upstream=NAME does
+    if let (Some(relay), Some(name)) = (state.relay.as_ref(), q.get("upstream")) {
+        let Some(upstream) = relay.get(name) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let started = Instant::now();
+        return match upstream.request(cmd).await {
+            Some(reply) => {
+                if let Some(log) = state.access_log.as_deref() {
+                    let req = Request {
+                        transport: "poll",
+                        client: Some(client),
+                        upstream: Some(name),
+                        command: cmd,
+                    };
+                    log.record(&req, started.elapsed(), reply.wire_len());
+                }
+                reply_response(Some(reply), None)
+            }
+            None => StatusCode::BAD_GATEWAY.into_response(),
+        };
+    }
+
+    let conn = Conn::new(scope);
+    // hello picks (or resumes) its own session; everything else continues the client's
+    if !cmd.starts_with("hello") {
+        let session = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok());
+        if let Some(id) = session {
+            if state.sessions.resume(id, scope, Instant::now()) {
+                conn.set_session(id.to_string());
+            }
+        }
+    }
+    let req = Request {
+        transport: "poll",
+        client: Some(client),
+        upstream: None,
+        command: cmd,
+    };
+    let reply = respond_logged(&state, &conn, &req).await;
+    let session = conn.session();
+    conn.close(&state.sessions);
+    reply_response(reply, session)
+}
+
+fn reply_response(reply: Option<Reply>, session: Option<String>) -> Response {
+    let mut resp = match reply {
+        Some(reply) => {
+            let (kind, body) = match reply {
+                Reply::Text(t) => (KIND_TEXT, t.into_bytes()),
+                Reply::Binary(b) => (KIND_BINARY, b),
+            };
+            let mut framed = Vec::with_capacity(body.len() + 1);
+            framed.push(kind);
+            framed.extend_from_slice(&body);
+            ([(header::CONTENT_TYPE, "application/octet-stream")], framed).into_response()
+        }
+        None => StatusCode::NO_CONTENT.into_response(),
+    };
+    if let Some(v) = session.and_then(|s| HeaderValue::from_str(&s).ok()) {
+        resp.headers_mut().insert(SESSION_HEADER, v);
+    }
+    // Replies are live data; keep caching proxies from serving a stale one
+    resp.headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Open agent regardless of SOCKTOP_TOKEN in the test environment
+    fn open_state() -> AppState {
+        let mut state = AppState::new();
+        state.auth_token = None;
+        state.read_token = None;
+        state
+    }
+
+    async fn poll(state: &AppState, session: Option<&str>, cmd: &str) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(s) = session {
+            headers.insert(SESSION_HEADER, HeaderValue::from_str(s).unwrap());
+        }
+        poll_handler(
+            State(state.clone()),
+            ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))),
+            Query(HashMap::new()),
+            headers,
+            Bytes::from(cmd.to_string()),
+        )
+        .await
+    }
+
+    async fn body(resp: Response) -> Vec<u8> {
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn session_travels_in_a_header_between_requests() {
+        let state = open_state();
+        let resp = poll(&state, None, "hello").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let id = resp.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+        let b = body(resp).await;
+        assert_eq!(b[0], KIND_TEXT);
+        let v: serde_json::Value = serde_json::from_slice(&b[1..]).unwrap();
+        assert_eq!(v["session"], id.as_str());
+
+        // Later requests carry it back and keep it; it is detached again in between
+        let resp = poll(&state, Some(&id), "get_disks?delta=0").await;
+        assert_eq!(resp.headers()[SESSION_HEADER], id.as_str());
+        let resp = poll(&state, None, &format!("hello?resume={id}")).await;
+        let v: serde_json::Value = serde_json::from_slice(&body(resp).await[1..]).unwrap();
+        assert_eq!(v["resumed"], true);
+
+        // Unknown commands have no reply
+        let resp = poll(&state, Some(&id), "no_such_command").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn bad_tokens_and_oversized_commands_are_refused() {
+        let mut state = open_state();
+        state.auth_token = Some("secret".into());
+        assert_eq!(
+            poll(&state, None, "get_metrics").await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let state = open_state();
+        let long = "x".repeat(MAX_REQUEST + 1);
+        assert_eq!(
+            poll(&state, None, &long).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
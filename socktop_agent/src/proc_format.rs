@@ -0,0 +1,65 @@
+//! Wire format of `get_processes` replies. Protobuf unless the client asks for JSON, either for
+//! its session (`hello?processes=json`) or for one request (`get_processes?format=json`).
+//! `--processes-json` makes JSON the default for clients that never say, such as old ones
+//! from before the protobuf reply; current clients send `hello?processes=pb` and still get it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_DEFAULT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcFormat {
+    Protobuf,
+    Json,
+}
+
+pub fn configure(json_default: bool) {
+    JSON_DEFAULT.store(json_default, Ordering::Relaxed);
+}
+
+impl ProcFormat {
+    /// For requests that named no format, in a session that didn't either.
+    pub fn fallback() -> Self {
+        if JSON_DEFAULT.load(Ordering::Relaxed) {
+            ProcFormat::Json
+        } else {
+            ProcFormat::Protobuf
+        }
+    }
+
+    /// `key=json|pb` in a query string (`processes` for hello, `format` for get_processes).
+    pub fn from_query(query: &str, key: &str) -> Option<Self> {
+        query.split('&').find_map(|kv| match kv.split_once('=') {
+            Some((k, "json")) if k == key => Some(ProcFormat::Json),
+            Some((k, "pb")) if k == key => Some(ProcFormat::Protobuf),
+            _ => None,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProcFormat::Protobuf => "pb",
+            ProcFormat::Json => "json",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_is_read_from_its_own_key_only() {
+        assert_eq!(
+            ProcFormat::from_query("resume=ab&processes=json", "processes"),
+            Some(ProcFormat::Json)
+        );
+        assert_eq!(
+            ProcFormat::from_query("top=5&format=pb", "format"),
+            Some(ProcFormat::Protobuf)
+        );
+        assert_eq!(ProcFormat::from_query("format=json", "processes"), None);
+        assert_eq!(ProcFormat::from_query("processes=xml", "processes"), None);
+        assert_eq!(ProcFormat::from_query("", "format"), None);
+    }
+}
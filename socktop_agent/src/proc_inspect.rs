@@ -0,0 +1,199 @@
+//! Environment and open files of one process for the client's detail popup:
+//! `get_process_env?pid=N` and `get_process_fds?pid=N` (Linux, `/proc/PID`).
+//!
+//! Both can expose secrets (tokens in the environment, paths of open files), so they need a
+//! control-scope token. Environment values are only sent for allow-listed names
+//! (`--env-allow NAME,PREFIX*`, default `DEFAULT_ENV_ALLOW`); other variables are listed by
+//! name with the value withheld.
+
+use std::sync::OnceLock;
+
+use crate::types::{EnvVar, OpenFd, ProcessEnv, ProcessFds, Scope, SCHEMA_VERSION};
+
+pub const DEFAULT_ENV_ALLOW: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "PWD", "LANG", "LC_*", "TERM", "TZ", "HOSTNAME",
+];
+// A process can hold a million fds; the popup only needs enough to spot a leak
+const MAX_FDS: usize = 512;
+
+static ENV_ALLOW: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Comma-separated names, `PREFIX*` for prefixes; None keeps the default list.
+pub fn configure(spec: Option<&str>) {
+    if let Some(spec) = spec {
+        let names = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        let _ = ENV_ALLOW.set(names);
+    }
+}
+
+fn env_allow() -> Vec<String> {
+    ENV_ALLOW
+        .get_or_init(|| DEFAULT_ENV_ALLOW.iter().map(|s| s.to_string()).collect())
+        .clone()
+}
+
+pub fn allowed(name: &str, allow: &[String]) -> bool {
+    allow.iter().any(|a| match a.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == a,
+    })
+}
+
+/// `pid=N` from a command's query string.
+pub fn pid_query(query: &str) -> Option<u32> {
+    query
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("pid="))
+        .and_then(|v| v.parse().ok())
+}
+
+/// NUL-separated `NAME=value` entries (`/proc/PID/environ`), values kept only when allowed.
+pub fn parse_environ(raw: &[u8], allow: &[String]) -> Vec<EnvVar> {
+    let mut vars: Vec<EnvVar> = raw
+        .split(|&b| b == 0)
+        .filter(|e| !e.is_empty())
+        .map(|e| {
+            let e = String::from_utf8_lossy(e);
+            let (name, value) = e.split_once('=').unwrap_or((&*e, ""));
+            EnvVar {
+                name: name.to_string(),
+                value: allowed(name, allow).then(|| value.to_string()),
+            }
+        })
+        .collect();
+    vars.sort_by(|a, b| a.name.cmp(&b.name));
+    vars
+}
+
+// Why a request for `pid` can't be answered, before touching /proc
+fn refusal(scope: Scope, pid: Option<u32>) -> Result<u32, String> {
+    if scope != Scope::Control {
+        return Err("needs a control-scope token".into());
+    }
+    let pid = pid.ok_or("expected pid=N")?;
+    if !cfg!(target_os = "linux") {
+        return Err("not supported on this platform".into());
+    }
+    Ok(pid)
+}
+
+fn io_error(pid: u32, e: std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => format!("no process {pid}"),
+        std::io::ErrorKind::PermissionDenied => {
+            format!("permission denied (process {pid} belongs to another user)")
+        }
+        _ => e.to_string(),
+    }
+}
+
+/// Reply to `get_process_env?QUERY`.
+pub fn env_reply(scope: Scope, query: &str) -> ProcessEnv {
+    let pid = pid_query(query);
+    let vars = refusal(scope, pid).and_then(|pid| {
+        std::fs::read(format!("/proc/{pid}/environ"))
+            .map(|raw| parse_environ(&raw, &env_allow()))
+            .map_err(|e| io_error(pid, e))
+    });
+    let (vars, error) = match vars {
+        Ok(v) => (v, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    ProcessEnv {
+        schema_version: SCHEMA_VERSION,
+        pid: pid.unwrap_or(0),
+        vars,
+        error,
+    }
+}
+
+/// Reply to `get_process_fds?QUERY`: fd numbers with their link targets
+/// (`/var/log/x.log`, `socket:[1234]`, `pipe:[56]`, ...), lowest first.
+pub fn fds_reply(scope: Scope, query: &str) -> ProcessFds {
+    let pid = pid_query(query);
+    let fds = refusal(scope, pid).and_then(|pid| {
+        let dir = std::fs::read_dir(format!("/proc/{pid}/fd")).map_err(|e| io_error(pid, e))?;
+        let mut fds: Vec<OpenFd> = dir
+            .flatten()
+            .filter_map(|e| {
+                let fd = e.file_name().to_str()?.parse().ok()?;
+                // Closed between listing and reading the link: skip it
+                let target = std::fs::read_link(e.path()).ok()?;
+                Some(OpenFd {
+                    fd,
+                    target: target.to_string_lossy().into_owned(),
+                })
+            })
+            .collect();
+        fds.sort_by_key(|f| f.fd);
+        Ok(fds)
+    });
+    let (mut fds, error) = match fds {
+        Ok(f) => (f, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    let truncated = fds.len() > MAX_FDS;
+    fds.truncate(MAX_FDS);
+    ProcessFds {
+        schema_version: SCHEMA_VERSION,
+        pid: pid.unwrap_or(0),
+        fds,
+        truncated,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn only_allow_listed_values_are_sent() {
+        let raw = b"PATH=/usr/bin\0DB_PASSWORD=hunter2\0LC_ALL=C\0EMPTY=\0";
+        let vars = parse_environ(raw, &allow(&["PATH", "LC_*", "EMPTY"]));
+        let got: Vec<(&str, Option<&str>)> = vars
+            .iter()
+            .map(|v| (v.name.as_str(), v.value.as_deref()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("DB_PASSWORD", None),
+                ("EMPTY", Some("")),
+                ("LC_ALL", Some("C")),
+                ("PATH", Some("/usr/bin")),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_scope_and_bad_queries_are_refused() {
+        let r = env_reply(Scope::Read, "pid=1");
+        assert_eq!(r.error.as_deref(), Some("needs a control-scope token"));
+        assert!(r.vars.is_empty());
+        let r = fds_reply(Scope::Control, "pid=abc");
+        assert_eq!(r.error.as_deref(), Some("expected pid=N"));
+        assert_eq!(pid_query("x=1&pid=42"), Some(42));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn own_process_is_readable() {
+        let me = std::process::id();
+        let fds = fds_reply(Scope::Control, &format!("pid={me}"));
+        assert_eq!(fds.error, None);
+        assert!(fds.fds.iter().any(|f| f.fd <= 2));
+        let env = env_reply(Scope::Control, &format!("pid={me}"));
+        assert_eq!(env.error, None);
+        assert_eq!(env.pid, me);
+    }
+}
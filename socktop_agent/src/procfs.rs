@@ -0,0 +1,309 @@
+//! Small Linux /proc and /sys readers for host details sysinfo doesn't expose.
+//! Every reader returns an empty/None value on other platforms or when the file is missing.
+
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Parse a kernel CPU list like `0-3,5,7-8` into ids.
+pub fn parse_cpu_list(s: &str) -> Vec<u32> {
+    let mut out = Vec::new();
+    for part in s.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((a, b)) => {
+                if let (Ok(a), Ok(b)) = (a.parse::<u32>(), b.parse::<u32>()) {
+                    out.extend(a..=b);
+                }
+            }
+            None => out.extend(part.parse::<u32>().ok()),
+        }
+    }
+    out
+}
+
+/// CPU ids that are present but not online (hotplugged out / parked).
+#[cfg(target_os = "linux")]
+pub fn offline_cores() -> Vec<u32> {
+    let read = |p: &str| fs::read_to_string(p).map(|s| parse_cpu_list(&s));
+    match (
+        read("/sys/devices/system/cpu/present"),
+        read("/sys/devices/system/cpu/online"),
+    ) {
+        (Ok(present), Ok(online)) => present
+            .into_iter()
+            .filter(|id| !online.contains(id))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn offline_cores() -> Vec<u32> {
+    Vec::new()
+}
+
+/// Cumulative per-CPU jiffies (online cores, id order): total, irq, softirq.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuIrqJiffies {
+    pub id: u32,
+    pub total: u64,
+    pub irq: u64,
+    pub softirq: u64,
+}
+
+/// Parse the `cpuN user nice system idle iowait irq softirq steal ...` lines of /proc/stat.
+pub fn parse_cpu_irq_jiffies(stat: &str) -> Vec<CpuIrqJiffies> {
+    stat.lines()
+        .filter_map(|line| {
+            let mut it = line.split_whitespace();
+            let id = it.next()?.strip_prefix("cpu")?.parse().ok()?;
+            let v: Vec<u64> = it.take(8).filter_map(|t| t.parse().ok()).collect();
+            (v.len() >= 7).then(|| CpuIrqJiffies {
+                id,
+                total: v.iter().sum(),
+                irq: v[5],
+                softirq: v[6],
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn cpu_irq_jiffies() -> Vec<CpuIrqJiffies> {
+    fs::read_to_string("/proc/stat")
+        .map(|s| parse_cpu_irq_jiffies(&s))
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_irq_jiffies() -> Vec<CpuIrqJiffies> {
+    Vec::new()
+}
+
+/// `procs_running` / `procs_blocked` from /proc/stat (runnable vs waiting on I/O).
+pub fn parse_run_queue(stat: &str) -> Option<(u32, u32)> {
+    let field = |name: &str| {
+        stat.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some((field("procs_running ")?, field("procs_blocked ")?))
+}
+
+#[cfg(target_os = "linux")]
+pub fn run_queue() -> Option<(u32, u32)> {
+    parse_run_queue(&fs::read_to_string("/proc/stat").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn run_queue() -> Option<(u32, u32)> {
+    None
+}
+
+/// System-wide file handles from /proc/sys/fs/file-nr ("allocated free max"): (in use, max).
+pub fn parse_file_nr(s: &str) -> Option<(u64, u64)> {
+    let v: Vec<u64> = s
+        .split_whitespace()
+        .filter_map(|t| t.parse().ok())
+        .collect();
+    match v.as_slice() {
+        [allocated, free, max] => Some((allocated.saturating_sub(*free), *max)),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn file_handles() -> Option<(u64, u64)> {
+    parse_file_nr(&fs::read_to_string("/proc/sys/fs/file-nr").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn file_handles() -> Option<(u64, u64)> {
+    None
+}
+
+/// The `n` processes holding the most open fds: (pid, name, count). Walks every /proc/<pid>/fd,
+/// so callers should cache it; processes we may not inspect are skipped.
+#[cfg(target_os = "linux")]
+pub fn top_fd_processes(n: usize) -> Vec<(u32, String, u32)> {
+    let Ok(dir) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut all: Vec<(u32, String, u32)> = dir
+        .flatten()
+        .filter_map(|e| {
+            let pid: u32 = e.file_name().to_str()?.parse().ok()?;
+            let fds = fs::read_dir(e.path().join("fd")).ok()?.count() as u32;
+            let name = fs::read_to_string(e.path().join("comm"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default();
+            Some((pid, name, fds))
+        })
+        .collect();
+    all.sort_unstable_by_key(|e| std::cmp::Reverse(e.2));
+    all.truncate(n);
+    all
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn top_fd_processes(_n: usize) -> Vec<(u32, String, u32)> {
+    Vec::new()
+}
+
+/// Kernel entropy estimate and pool size in bits, plus the active hardware RNG (if any).
+/// Kernels >= 5.18 always report a full 256-bit pool once the CRNG is seeded.
+#[cfg(target_os = "linux")]
+pub fn entropy() -> Option<(u32, u32, Option<String>)> {
+    let read = |p: &str| fs::read_to_string(p).ok().map(|s| s.trim().to_string());
+    let avail = read("/proc/sys/kernel/random/entropy_avail")?
+        .parse()
+        .ok()?;
+    let pool = read("/proc/sys/kernel/random/poolsize")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(4096);
+    let hwrng =
+        read("/sys/class/misc/hw_random/rng_current").filter(|s| !s.is_empty() && s != "none");
+    Some((avail, pool, hwrng))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn entropy() -> Option<(u32, u32, Option<String>)> {
+    None
+}
+
+/// Kernel clock discipline via adjtimex(2): (synchronized, offset ms, max error ms).
+/// Read-only query (modes = 0); "synchronized" means an NTP daemon is steering the clock.
+#[cfg(target_os = "linux")]
+pub fn clock_sync() -> Option<(bool, f64, f64)> {
+    const STA_UNSYNC: i32 = 0x0040;
+    const STA_NANO: i32 = 0x2000;
+    const TIME_ERROR: i32 = 5;
+    // SAFETY: timex is plain data; zeroed means modes = 0 (query only), and the kernel only
+    // writes into the struct we own for the duration of the call.
+    let (state, tx) = unsafe {
+        let mut tx: libc::timex = std::mem::zeroed();
+        (libc::adjtimex(&mut tx), tx)
+    };
+    if state < 0 {
+        return None;
+    }
+    let synced = state != TIME_ERROR && tx.status & STA_UNSYNC == 0;
+    let offset_ms = if tx.status & STA_NANO != 0 {
+        tx.offset as f64 / 1_000_000.0
+    } else {
+        tx.offset as f64 / 1_000.0
+    };
+    Some((synced, offset_ms, tx.maxerror as f64 / 1_000.0))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn clock_sync() -> Option<(bool, f64, f64)> {
+    None
+}
+
+/// Pick the newest installed kernel from /lib/modules names, comparing numeric components.
+pub fn newest_kernel<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Option<&'a str> {
+    fn key(v: &str) -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter(|p| !p.is_empty())
+            .filter_map(|p| p.parse().ok())
+            .collect()
+    }
+    names.into_iter().max_by(|a, b| key(a).cmp(&key(b)))
+}
+
+/// Reboot needed? Debian/Ubuntu drop /var/run/reboot-required (+ .pkgs); elsewhere (RPM) a newer
+/// kernel under /lib/modules than the running one. Returns (reason, packages).
+#[cfg(target_os = "linux")]
+pub fn reboot_required() -> Option<(String, Vec<String>)> {
+    if let Ok(s) = fs::read_to_string("/var/run/reboot-required") {
+        let pkgs = fs::read_to_string("/var/run/reboot-required.pkgs")
+            .map(|p| {
+                let mut v: Vec<String> = p.lines().map(|l| l.trim().to_string()).collect();
+                v.retain(|l| !l.is_empty());
+                v.dedup();
+                v
+            })
+            .unwrap_or_default();
+        let reason = s.trim().trim_start_matches("*** ").trim_end_matches(" ***");
+        return Some((reason.to_string(), pkgs));
+    }
+    let running = fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let running = running.trim();
+    let installed: Vec<String> = fs::read_dir("/lib/modules")
+        .ok()?
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect();
+    let newest = newest_kernel(installed.iter().map(String::as_str))?;
+    (newest != running).then(|| {
+        (
+            format!("kernel {newest} installed, running {running}"),
+            Vec::new(),
+        )
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reboot_required() -> Option<(String, Vec<String>)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_list_ranges_and_singles() {
+        assert_eq!(parse_cpu_list("0-3,5,7-8\n"), vec![0, 1, 2, 3, 5, 7, 8]);
+        assert_eq!(parse_cpu_list("0"), vec![0]);
+        assert!(parse_cpu_list("").is_empty());
+    }
+
+    #[test]
+    fn per_cpu_irq_jiffies_skip_aggregate_line() {
+        let stat = "cpu  10 0 10 100 0 5 5 0 0 0\n\
+                    cpu0 4 0 4 50 0 2 3 0 0 0\n\
+                    cpu2 6 0 6 50 0 3 2 0 0 0\n\
+                    intr 12345\n";
+        let v = parse_cpu_irq_jiffies(stat);
+        assert_eq!(v.len(), 2);
+        assert_eq!(
+            v[0],
+            CpuIrqJiffies {
+                id: 0,
+                total: 63,
+                irq: 2,
+                softirq: 3
+            }
+        );
+        assert_eq!(v[1].id, 2);
+    }
+
+    #[test]
+    fn run_queue_fields() {
+        let stat = "cpu  1 2 3\nprocs_running 3\nprocs_blocked 1\n";
+        assert_eq!(parse_run_queue(stat), Some((3, 1)));
+        assert_eq!(parse_run_queue("procs_running 3\n"), None);
+    }
+
+    #[test]
+    fn file_nr_in_use_and_max() {
+        assert_eq!(
+            parse_file_nr("9536\t0\t9223372036854775807\n"),
+            Some((9536, i64::MAX as u64))
+        );
+        assert_eq!(parse_file_nr("1000 200 5000"), Some((800, 5000)));
+        assert_eq!(parse_file_nr("garbage"), None);
+    }
+
+    #[test]
+    fn newest_kernel_compares_numerically() {
+        let k = [
+            "5.14.0-362.8.1.el9_3.x86_64",
+            "5.14.0-427.13.1.el9_4.x86_64",
+            "5.14.0-70.13.1.el9_0.x86_64",
+        ];
+        assert_eq!(newest_kernel(k), Some("5.14.0-427.13.1.el9_4.x86_64"));
+        assert_eq!(newest_kernel([]), None);
+    }
+}
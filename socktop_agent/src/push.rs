@@ -0,0 +1,222 @@
+//! Push mode for WebSocket clients: `subscribe?metrics=MS&processes=MS` makes the agent send
+//! metrics and processes on its own schedule instead of waiting for each `get_*` request.
+//!
+//! Each pushed frame is binary: `"SKP1" | kind | encoding | body`, where kind is `m`
+//! (metrics) or `p` (processes), encoding is `0` when the reply would have been a text frame
+//! and `1` for a binary one, and body is exactly what that reply frame would have carried.
+//! Requests keep working while subscribed; their replies are ordinary frames.
+
+use std::future::pending;
+use std::time::Duration;
+
+use tokio::time::{interval, Interval, MissedTickBehavior};
+
+use crate::relay::Reply;
+use crate::types::{SubscribeAck, SCHEMA_VERSION};
+
+pub const MAGIC: &[u8; 4] = b"SKP1";
+pub const HEADER_LEN: usize = 6;
+
+// Faster pushes would mostly resend the agent's cached sample
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Metrics,
+    Processes,
+}
+
+impl Kind {
+    fn tag(self) -> u8 {
+        match self {
+            Kind::Metrics => b'm',
+            Kind::Processes => b'p',
+        }
+    }
+}
+
+/// `subscribe...` and `unsubscribe`, which the WebSocket handler answers itself.
+pub fn is_command(text: &str) -> bool {
+    text == "subscribe" || text.starts_with("subscribe?") || text == "unsubscribe"
+}
+
+/// What a subscription pushes, and how often.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub metrics: Option<Duration>,
+    pub processes: Option<Duration>,
+    /// The request whose reply is pushed as processes (`top`/`sort` carried over)
+    pub processes_cmd: String,
+}
+
+impl Plan {
+    /// `subscribe?metrics=500&processes=2000&top=50&sort=mem`; intervals in ms, each stream
+    /// off when left out, raised to `MIN_INTERVAL`. None for `unsubscribe` or nothing to push.
+    pub fn parse(text: &str) -> Option<Self> {
+        let query = text.strip_prefix("subscribe")?;
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let ms = |v: &str| {
+            v.parse()
+                .ok()
+                .map(|n| Duration::from_millis(n).max(MIN_INTERVAL))
+        };
+        let mut plan = Plan {
+            metrics: None,
+            processes: None,
+            processes_cmd: "get_processes".into(),
+        };
+        let mut filter = Vec::new();
+        for kv in query.split('&') {
+            match kv.split_once('=') {
+                Some(("metrics", v)) => plan.metrics = ms(v),
+                Some(("processes", v)) => plan.processes = ms(v),
+                Some(("top" | "sort", _)) => filter.push(kv),
+                _ => {}
+            }
+        }
+        if !filter.is_empty() {
+            plan.processes_cmd = format!("get_processes?{}", filter.join("&"));
+        }
+        (plan.metrics.is_some() || plan.processes.is_some()).then_some(plan)
+    }
+
+    /// The request answered for a push of `kind`.
+    pub fn command(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Metrics => "get_metrics",
+            Kind::Processes => &self.processes_cmd,
+        }
+    }
+}
+
+/// Reply to `subscribe`/`unsubscribe`: the intervals in effect (null when off).
+pub fn ack(plan: Option<&Plan>) -> SubscribeAck {
+    let ms = |d: Option<Duration>| d.map(|d| d.as_millis() as u64);
+    SubscribeAck {
+        schema_version: SCHEMA_VERSION,
+        metrics_ms: ms(plan.and_then(|p| p.metrics)),
+        processes_ms: ms(plan.and_then(|p| p.processes)),
+        error: None,
+    }
+}
+
+/// A running subscription: one timer per stream.
+pub struct Schedule {
+    pub plan: Plan,
+    metrics: Option<Interval>,
+    processes: Option<Interval>,
+}
+
+fn timer(every: Option<Duration>) -> Option<Interval> {
+    let mut t = interval(every?);
+    // A slow client gets the next sample late rather than a burst of stale ones
+    t.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Some(t)
+}
+
+async fn tick(t: &mut Option<Interval>) {
+    match t {
+        Some(t) => {
+            t.tick().await;
+        }
+        None => pending().await,
+    }
+}
+
+impl Schedule {
+    pub fn new(plan: Plan) -> Self {
+        Schedule {
+            metrics: timer(plan.metrics),
+            processes: timer(plan.processes),
+            plan,
+        }
+    }
+
+    /// The next push that is due (the first of each comes right away).
+    pub async fn due(&mut self) -> Kind {
+        tokio::select! {
+            _ = tick(&mut self.metrics) => Kind::Metrics,
+            _ = tick(&mut self.processes) => Kind::Processes,
+        }
+    }
+}
+
+/// `due` on the connection's subscription; never resolves without one.
+pub async fn next(schedule: &mut Option<Schedule>) -> Kind {
+    match schedule {
+        Some(s) => s.due().await,
+        None => pending().await,
+    }
+}
+
+/// The binary push frame carrying `reply`.
+pub fn wrap(kind: Kind, reply: Reply) -> Vec<u8> {
+    let (encoding, body) = match reply {
+        Reply::Text(t) => (0, t.into_bytes()),
+        Reply::Binary(b) => (1, b),
+    };
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    out.extend_from_slice(MAGIC);
+    out.push(kind.tag());
+    out.push(encoding);
+    out.extend_from_slice(&body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribe_parses_intervals_and_the_process_filter() {
+        let p = Plan::parse("subscribe?metrics=500&processes=2000&top=50&sort=mem").unwrap();
+        assert_eq!(p.metrics, Some(Duration::from_millis(500)));
+        assert_eq!(p.processes, Some(Duration::from_secs(2)));
+        assert_eq!(p.command(Kind::Processes), "get_processes?top=50&sort=mem");
+        assert_eq!(p.command(Kind::Metrics), "get_metrics");
+
+        // Clamped, and only what was asked for
+        let p = Plan::parse("subscribe?metrics=5").unwrap();
+        assert_eq!(p.metrics, Some(MIN_INTERVAL));
+        assert_eq!(p.processes, None);
+        assert_eq!(p.command(Kind::Processes), "get_processes");
+
+        assert_eq!(Plan::parse("subscribe"), None);
+        assert_eq!(Plan::parse("unsubscribe"), None);
+        assert!(is_command("unsubscribe") && is_command("subscribe?metrics=1"));
+        assert!(!is_command("subscriber"));
+    }
+
+    #[test]
+    fn ack_reports_what_is_pushed() {
+        let p = Plan::parse("subscribe?processes=1500").unwrap();
+        let a = ack(Some(&p));
+        assert_eq!((a.metrics_ms, a.processes_ms), (None, Some(1500)));
+        let off = ack(None);
+        assert_eq!((off.metrics_ms, off.processes_ms), (None, None));
+    }
+
+    #[test]
+    fn frames_keep_the_reply_body_and_its_encoding() {
+        let f = wrap(Kind::Metrics, Reply::Text("{\"cpu_total\":1}".into()));
+        assert_eq!(&f[..4], MAGIC);
+        assert_eq!(&f[4..HEADER_LEN], b"m\0");
+        assert_eq!(&f[HEADER_LEN..], b"{\"cpu_total\":1}");
+        let f = wrap(Kind::Processes, Reply::Binary(vec![0x1f, 0x8b, 7]));
+        assert_eq!(&f[4..], &[b'p', 1, 0x1f, 0x8b, 7]);
+    }
+
+    #[tokio::test]
+    async fn both_streams_fire_on_their_own_schedule() {
+        let plan = Plan::parse("subscribe?metrics=100&processes=400").unwrap();
+        let mut s = Some(Schedule::new(plan));
+        let mut seen = Vec::new();
+        // Metrics at 0, 100, 200, 300 and 400 ms; processes at 0 and 400 ms
+        for _ in 0..7 {
+            seen.push(next(&mut s).await);
+        }
+        let count = |k| seen.iter().filter(|&&x| x == k).count();
+        assert_eq!(count(Kind::Metrics), 5);
+        assert_eq!(count(Kind::Processes), 2);
+    }
+}
@@ -0,0 +1,74 @@
+//! Deltas and per-second rates from monotonic counters (network totals, jiffies) that tell a
+//! reset apart from "no change" instead of clamping both to zero.
+
+use std::time::Instant;
+
+// A backwards step smaller than this from a value that fits in 32 bits is treated as a 32-bit
+// wraparound (older kernels/drivers); anything else is a reset.
+const WRAP_LIMIT: u64 = 1 << 31;
+
+/// Increase of a counter between two readings. Handles 32-bit wraparound; None when the counter
+/// was reset (restart, interface removed, pid reused) and `prev` is no longer a usable baseline.
+pub fn counter_delta(prev: u64, now: u64) -> Option<u64> {
+    if now >= prev {
+        return Some(now - prev);
+    }
+    if prev <= u32::MAX as u64 {
+        let wrapped = (u32::MAX as u64 - prev) + now + 1;
+        if wrapped < WRAP_LIMIT {
+            return Some(wrapped);
+        }
+    }
+    None
+}
+
+/// Per-second rate of one monotonic counter.
+#[derive(Debug, Default, Clone)]
+pub struct RateCounter {
+    last: Option<(u64, Instant)>,
+}
+
+impl RateCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a reading. Returns the rate since the previous reading, or None on the first reading
+    /// and after a reset (the reading becomes the new baseline either way).
+    pub fn update(&mut self, value: u64, now: Instant) -> Option<f64> {
+        let prev = self.last.replace((value, now));
+        let (pv, at) = prev?;
+        let d = counter_delta(pv, value)?;
+        let dt = now.checked_duration_since(at)?.as_secs_f64().max(1e-3);
+        Some(d as f64 / dt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn delta_growth_wrap_and_reset() {
+        assert_eq!(counter_delta(10, 25), Some(15));
+        assert_eq!(counter_delta(25, 25), Some(0));
+        // 32-bit counter wrapped just past u32::MAX
+        assert_eq!(counter_delta(u32::MAX as u64 - 4, 5), Some(10));
+        // Reset back to a small value
+        assert_eq!(counter_delta(9_000_000, 1_200), None);
+        // 64-bit counters don't wrap in practice; a backwards step is a reset
+        assert_eq!(counter_delta(u64::MAX - 4, 5), None);
+    }
+
+    #[test]
+    fn rate_rebaselines_after_reset() {
+        let t0 = Instant::now();
+        let mut r = RateCounter::new();
+        assert_eq!(r.update(1_000, t0), None);
+        assert_eq!(r.update(3_000, t0 + Duration::from_secs(2)), Some(1_000.0));
+        // Agent restart: no rate for this step, next one is measured from the new baseline
+        assert_eq!(r.update(100, t0 + Duration::from_secs(3)), None);
+        assert_eq!(r.update(600, t0 + Duration::from_secs(4)), Some(500.0));
+    }
+}
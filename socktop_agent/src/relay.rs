@@ -0,0 +1,201 @@
+//! Relay/aggregator mode (`--relay --upstream [NAME=]ws://leaf:3000/ws ...`).
+//!
+//! The relay keeps one WebSocket per leaf agent and:
+//! - proxies a client connected with `/ws?upstream=NAME` frame-for-frame to that leaf
+//!   (so the regular socktop client works unchanged), and
+//! - answers `get_fleet` with every host's fast metrics, tagged by host name.
+
+use flate2::read::GzDecoder;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::header::AUTHORIZATION, Message as UpMessage},
+    MaybeTlsStream, WebSocketStream,
+};
+use tracing::warn;
+
+type UpstreamWs = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Upstream {
+    pub name: String,
+    pub url: String,
+    /// Sent as `Authorization: Bearer`, so it stays out of URLs and their logs
+    pub token: Option<String>,
+    conn: Mutex<Option<UpstreamWs>>,
+}
+
+/// A reply frame from a leaf agent, forwarded to the client as-is.
+pub enum Reply {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Reply {
+    /// Bytes on the wire (after compression), as reported in the access log.
+    pub fn wire_len(&self) -> usize {
+        match self {
+            Reply::Text(t) => t.len(),
+            Reply::Binary(b) => b.len(),
+        }
+    }
+}
+
+impl Upstream {
+    /// Parse `NAME=URL` or a bare URL (name defaults to the URL host).
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (name, url) = match spec.split_once('=') {
+            Some((n, u)) if !n.contains("://") && !n.contains('?') => (n.to_string(), u),
+            _ => (String::new(), spec),
+        };
+        let parsed = url::Url::parse(url).ok()?;
+        let name = if name.is_empty() {
+            parsed.host_str()?.to_string()
+        } else {
+            name
+        };
+        Some(Self {
+            name,
+            url: url.to_string(),
+            token: None,
+            conn: Mutex::new(None),
+        })
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Send one request frame upstream and await its single reply, reconnecting lazily.
+    pub async fn request(&self, req: &str) -> Option<Reply> {
+        let mut guard = self.conn.lock().await;
+        if guard.is_none() {
+            let mut upgrade = self.url.as_str().into_client_request().ok()?;
+            if let Some(t) = &self.token {
+                upgrade
+                    .headers_mut()
+                    .insert(AUTHORIZATION, format!("Bearer {t}").parse().ok()?);
+            }
+            match tokio::time::timeout(UPSTREAM_TIMEOUT, connect_async(upgrade)).await {
+                Ok(Ok((ws, _))) => *guard = Some(ws),
+                Ok(Err(e)) => {
+                    warn!("relay: connect to {} failed: {e}", self.name);
+                    return None;
+                }
+                Err(_) => {
+                    warn!("relay: connect to {} timed out", self.name);
+                    return None;
+                }
+            }
+        }
+        let ws = guard.as_mut()?;
+        let reply = async {
+            ws.send(UpMessage::Text(req.to_string())).await.ok()?;
+            match ws.next().await {
+                Some(Ok(UpMessage::Text(t))) => Some(Reply::Text(t)),
+                Some(Ok(UpMessage::Binary(b))) => Some(Reply::Binary(b)),
+                _ => None,
+            }
+        };
+        match tokio::time::timeout(UPSTREAM_TIMEOUT, reply).await {
+            Ok(Some(r)) => Some(r),
+            _ => {
+                // Drop the connection; the next request reconnects
+                *guard = None;
+                None
+            }
+        }
+    }
+}
+
+pub struct Relay {
+    pub upstreams: Vec<Upstream>,
+}
+
+pub type SharedRelay = Arc<Relay>;
+
+impl Relay {
+    pub fn from_specs<I: IntoIterator<Item = String>>(specs: I) -> Self {
+        let upstreams = specs
+            .into_iter()
+            .filter_map(|s| {
+                let u = Upstream::parse(&s);
+                if u.is_none() {
+                    eprintln!("socktop_agent: ignoring invalid --upstream '{s}'");
+                }
+                u
+            })
+            .collect();
+        Self { upstreams }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Upstream> {
+        self.upstreams.iter().find(|u| u.name == name)
+    }
+}
+
+#[derive(Serialize)]
+pub struct FleetEntry {
+    pub host: String,
+    // Agent instance behind this entry (several agents may share a host)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub ok: bool,
+    pub labels: BTreeMap<String, String>,
+    pub metrics: Option<serde_json::Value>,
+}
+
+impl FleetEntry {
+    /// True when every `(key, value)` in `wanted` is among this host's labels.
+    pub fn matches(&self, wanted: &[(String, String)]) -> bool {
+        wanted
+            .iter()
+            .all(|(k, v)| self.labels.get(k).is_some_and(|have| have == v))
+    }
+}
+
+pub fn decode_json(reply: Reply) -> Option<serde_json::Value> {
+    match reply {
+        Reply::Text(t) => serde_json::from_str(&t).ok(),
+        Reply::Binary(b) => {
+            let mut s = String::new();
+            GzDecoder::new(b.as_slice()).read_to_string(&mut s).ok()?;
+            serde_json::from_str(&s).ok()
+        }
+    }
+}
+
+/// Fast metrics from every upstream, queried concurrently.
+pub async fn collect_fleet(relay: &Relay) -> Vec<FleetEntry> {
+    let futs = relay.upstreams.iter().map(|u| async move {
+        let metrics = u.request("get_metrics").await.and_then(decode_json);
+        // Older leaves send no labels
+        let labels = metrics
+            .as_ref()
+            .and_then(|m| m.get("labels"))
+            .and_then(|l| serde_json::from_value(l.clone()).ok())
+            .unwrap_or_default();
+        let instance = metrics
+            .as_ref()
+            .and_then(|m| m.get("instance"))
+            .and_then(|i| i.as_str())
+            .map(str::to_string);
+        FleetEntry {
+            host: u.name.clone(),
+            instance,
+            ok: metrics.is_some(),
+            labels,
+            metrics,
+        }
+    });
+    futures_util::future::join_all(futs).await
+}
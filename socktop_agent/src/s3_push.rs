@@ -0,0 +1,510 @@
+//! Push metrics to S3-compatible object storage (`--s3-push http://minio:9000/bucket/prefix`),
+//! for hosts that nothing can reach to pull from.
+//!
+//! A snapshot (the `get_metrics` JSON) is taken every `HISTORY_STEP`; every `--s3-interval`
+//! seconds (default 300) the batch is uploaded as one gzipped JSON-lines object named
+//! `PREFIX/HOST/YYYYMMDDTHHMMSSZ.jsonl.gz`. With `--s3-keep N` the agent deletes its oldest
+//! objects beyond N (only those uploaded since it started; use a bucket lifecycle rule for the
+//! rest). A failed upload is retried with the next batch, up to `MAX_PENDING_BATCHES` worth.
+//!
+//! Requests are signed with AWS Signature V4 from AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY
+//! (region from AWS_REGION, default us-east-1). URLs are path-style, over http or https; https
+//! checks the store's certificate against the system trust store (SSL_CERT_FILE / SSL_CERT_DIR
+//! point it at a private CA).
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use flate2::{write::GzEncoder, Compression};
+use hmac::{Hmac, Mac};
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::Request;
+use hyper_util::rt::TokioIo;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::warn;
+
+use crate::history::HISTORY_STEP;
+use crate::metrics::collect_fast_metrics;
+use crate::state::AppState;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+// Batches kept for retry while the store is unreachable; older snapshots are dropped
+const MAX_PENDING_BATCHES: usize = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// SigV4 URI encoding: everything but the unreserved characters
+const URI_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Target {
+    /// `host` or `host:port`, as sent in the Host header
+    host: String,
+    /// Host name alone, checked against the certificate over https
+    name: String,
+    addr: String,
+    tls: bool,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Target {
+    /// `http[s]://HOST[:PORT]/BUCKET[/PREFIX]` plus credentials.
+    pub fn parse(
+        url: &str,
+        access_key: String,
+        secret_key: String,
+        region: String,
+    ) -> anyhow::Result<Self> {
+        let u = url::Url::parse(url)?;
+        let tls = match u.scheme() {
+            "http" => false,
+            "https" => true,
+            _ => anyhow::bail!("only http:// and https:// endpoints are supported"),
+        };
+        let host_name = u
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("missing host"))?;
+        let port = u.port_or_known_default().unwrap_or(80);
+        // Url keeps the path percent-encoded; object paths are encoded once, when signing
+        let path = percent_decode_str(u.path()).decode_utf8()?;
+        let mut segs = path.trim_matches('/').splitn(2, '/');
+        let bucket = segs.next().filter(|b| !b.is_empty());
+        let Some(bucket) = bucket else {
+            anyhow::bail!("missing bucket (http://HOST:PORT/BUCKET[/PREFIX])");
+        };
+        if access_key.is_empty() || secret_key.is_empty() {
+            anyhow::bail!("AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set");
+        }
+        Ok(S3Target {
+            host: match u.port() {
+                Some(p) => format!("{host_name}:{p}"),
+                None => host_name.to_string(),
+            },
+            name: host_name.trim_matches(['[', ']']).to_string(),
+            addr: format!("{host_name}:{port}"),
+            tls,
+            bucket: bucket.to_string(),
+            prefix: segs.next().unwrap_or("").trim_end_matches('/').to_string(),
+            region,
+            access_key,
+            secret_key,
+        })
+    }
+
+    /// Object path (`/BUCKET/PREFIX/HOST/STAMP.jsonl.gz`, not yet URI-encoded) for a batch
+    /// uploaded at `ts`.
+    pub fn object_path(&self, host: &str, ts: u64) -> String {
+        let host: String = host
+            .chars()
+            .map(|c| match c {
+                'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        let mut path = format!("/{}", self.bucket);
+        if !self.prefix.is_empty() {
+            path.push('/');
+            path.push_str(&self.prefix);
+        }
+        format!("{path}/{host}/{}.jsonl.gz", amz_date(ts))
+    }
+
+    /// `method path` with `body`, signed for `ts`.
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Vec<u8>,
+        ts: u64,
+    ) -> anyhow::Result<Request<Full<Bytes>>> {
+        let amz = amz_date(ts);
+        let day = &amz[..8];
+        let payload = hex(&Sha256::digest(&body));
+        let uri = uri_encode_path(path);
+        let headers = [
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", payload.as_str()),
+            ("x-amz-date", amz.as_str()),
+        ];
+        let canonical = canonical_request(method, &uri, &headers, &payload);
+        let scope = format!("{day}/{}/s3/aws4_request", self.region);
+        let signature = signature(&self.secret_key, &self.region, "s3", &amz, &canonical);
+        let mut req = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        Ok(req
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={}, \
+                     Signature={signature}",
+                    self.access_key,
+                    signed_headers(&headers)
+                ),
+            )
+            .body(Full::new(Bytes::from(body)))?)
+    }
+
+    // One request; the HTTP status code
+    async fn send(&self, method: &str, path: &str, body: Vec<u8>) -> anyhow::Result<u16> {
+        let req = self.request(method, path, body, unix_now())?;
+        let exchange = async {
+            let tcp = TcpStream::connect(&self.addr).await?;
+            if self.tls {
+                let name = ServerName::try_from(self.name.clone())?;
+                let stream = TlsConnector::from(tls_config()).connect(name, tcp).await?;
+                round_trip(stream, req).await
+            } else {
+                round_trip(tcp, req).await
+            }
+        };
+        tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+            .await
+            .map_err(|_| anyhow::anyhow!("timed out"))?
+    }
+}
+
+async fn round_trip<S>(stream: S, req: Request<Full<Bytes>>) -> anyhow::Result<u16>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    // Drives the connection; ends once the response is dropped
+    tokio::spawn(async move {
+        let _ = conn.await;
+    });
+    Ok(sender.send_request(req).await?.status().as_u16())
+}
+
+// System roots, loaded on first https use
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let found = rustls_native_certs::load_native_certs();
+            for e in &found.errors {
+                warn!("s3 push: loading system certificates: {e}");
+            }
+            let mut roots = RootCertStore::empty();
+            roots.add_parsable_certificates(found.certs);
+            Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth(),
+            )
+        })
+        .clone()
+}
+
+// Each segment encoded once, as S3 expects (other services encode twice)
+fn uri_encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|seg| utf8_percent_encode(seg, URI_ENCODE).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// SigV4 canonical request without a query string. `headers` are lower-case names sorted by
+/// name, all of them signed.
+fn canonical_request(method: &str, uri: &str, headers: &[(&str, &str)], payload: &str) -> String {
+    let mut out = format!("{method}\n{uri}\n\n");
+    for (name, value) in headers {
+        out.push_str(&format!("{name}:{}\n", value.trim()));
+    }
+    format!("{out}\n{}\n{payload}", signed_headers(headers))
+}
+
+fn signed_headers(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Hex signature of `canonical` for the day of `amz` (YYYYMMDDTHHMMSSZ).
+fn signature(secret: &str, region: &str, service: &str, amz: &str, canonical: &str) -> String {
+    let day = &amz[..8];
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz}\n{day}/{region}/{service}/aws4_request\n{}",
+        hex(&Sha256::digest(canonical.as_bytes()))
+    );
+    hex(&hmac_sha256(
+        &signing_key(secret, day, region, service),
+        to_sign.as_bytes(),
+    ))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// SigV4 signing key for `day` (YYYYMMDD).
+fn signing_key(secret: &str, day: &str, region: &str, service: &str) -> [u8; 32] {
+    let k = hmac_sha256(format!("AWS4{secret}").as_bytes(), day.as_bytes());
+    let k = hmac_sha256(&k, region.as_bytes());
+    let k = hmac_sha256(&k, service.as_bytes());
+    hmac_sha256(&k, b"aws4_request")
+}
+
+// `YYYYMMDDTHHMMSSZ`
+fn amz_date(ts: u64) -> String {
+    time::OffsetDateTime::from_unix_timestamp(ts as i64)
+        .ok()
+        .and_then(|t| {
+            t.format(time::macros::format_description!(
+                "[year][month][day]T[hour][minute][second]Z"
+            ))
+            .ok()
+        })
+        .unwrap_or_else(|| "19700101T000000Z".into())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn gzip(lines: &VecDeque<String>) -> std::io::Result<Vec<u8>> {
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    for l in lines {
+        enc.write_all(l.as_bytes())?;
+        enc.write_all(b"\n")?;
+    }
+    enc.finish()
+}
+
+/// Sample every `HISTORY_STEP` and upload every `interval` for as long as the agent runs.
+pub fn spawn(state: AppState, target: S3Target, interval: Duration, keep: usize) {
+    let per_batch = (interval.as_secs() / HISTORY_STEP.as_secs()).max(1) as usize;
+    let max_lines = per_batch * MAX_PENDING_BATCHES;
+    let host = match &state.instance {
+        Some(i) => format!("{}-{i}", state.hostname),
+        None => state.hostname.clone(),
+    };
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(HISTORY_STEP);
+        let mut lines: VecDeque<String> = VecDeque::new();
+        let mut uploaded: VecDeque<String> = VecDeque::new();
+        let mut last_upload = Instant::now();
+        loop {
+            tick.tick().await;
+            crate::self_limit::throttle().await;
+            let m = collect_fast_metrics(&state).await;
+            if let Ok(line) = serde_json::to_string(&m) {
+                if lines.len() == max_lines {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+            if last_upload.elapsed() < interval {
+                continue;
+            }
+            last_upload = Instant::now();
+            let Ok(body) = gzip(&lines) else {
+                continue;
+            };
+            let path = target.object_path(&host, unix_now());
+            match target.send("PUT", &path, body).await {
+                Ok(200..=299) => {
+                    lines.clear();
+                    uploaded.push_back(path);
+                }
+                Ok(code) => warn!("s3 push: PUT {path} answered {code}"),
+                Err(e) => warn!("s3 push: PUT {path}: {e}"),
+            }
+            // Rotation: drop our oldest objects beyond --s3-keep
+            while keep > 0 && uploaded.len() > keep {
+                let Some(old) = uploaded.pop_front() else {
+                    break;
+                };
+                if let Err(e) = target.send("DELETE", &old, Vec::new()).await {
+                    warn!("s3 push: DELETE {old}: {e}");
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(url: &str) -> S3Target {
+        S3Target::parse(url, "AKID".into(), "secret".into(), "us-east-1".into()).unwrap()
+    }
+
+    #[test]
+    fn hmac_and_signing_key_match_published_vectors() {
+        // RFC 4231 test cases 2 and 6 (key longer than a block)
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        // AWS "deriving the signing key" example
+        assert_eq!(
+            hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn canonical_request_matches_aws_put_object_example() {
+        // "Example: PUT Object" from the S3 SigV4 header-based auth docs
+        let payload = hex(&Sha256::digest(b"Welcome to Amazon S3."));
+        let uri = uri_encode_path("/test$file.text");
+        assert_eq!(uri, "/test%24file.text");
+        let canonical = canonical_request(
+            "PUT",
+            &uri,
+            &[
+                ("date", "Fri, 24 May 2013 00:00:00 GMT"),
+                ("host", "examplebucket.s3.amazonaws.com"),
+                ("x-amz-content-sha256", &payload),
+                ("x-amz-date", "20130524T000000Z"),
+                ("x-amz-storage-class", "REDUCED_REDUNDANCY"),
+            ],
+            &payload,
+        );
+        assert_eq!(
+            signature(
+                "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+                "us-east-1",
+                "s3",
+                "20130524T000000Z",
+                &canonical
+            ),
+            "98ad721746da40c64f1a55b78f14c238d841ea1380cd77a1b5971af0ece108bd"
+        );
+    }
+
+    #[test]
+    fn url_names_bucket_prefix_and_host_header() {
+        let t = target("http://minio:9000/metrics/edge/site1/");
+        assert_eq!(t.host, "minio:9000");
+        assert_eq!(t.addr, "minio:9000");
+        assert!(!t.tls);
+        assert_eq!(
+            t.object_path("web 1", 1_700_000_000),
+            "/metrics/edge/site1/web_1/20231114T221320Z.jsonl.gz"
+        );
+        // Default port: no port in the Host header
+        let t = target("http://store.lan/bucket");
+        assert_eq!(
+            (t.host.as_str(), t.addr.as_str()),
+            ("store.lan", "store.lan:80")
+        );
+        assert_eq!(t.object_path("h", 0), "/bucket/h/19700101T000000Z.jsonl.gz");
+        let t = target("https://s3.eu-west-1.amazonaws.com/bucket");
+        assert!(t.tls);
+        assert_eq!(t.addr, "s3.eu-west-1.amazonaws.com:443");
+        assert_eq!(t.name, "s3.eu-west-1.amazonaws.com");
+        // The prefix is kept decoded and encoded once per request
+        let t = target("http://m/b/my%20site");
+        assert_eq!(t.prefix, "my site");
+
+        let parse = |u: &str| S3Target::parse(u, "a".into(), "s".into(), "r".into());
+        assert!(parse("ftp://s3.amazonaws.com/bucket").is_err());
+        assert!(parse("http://minio:9000/").is_err());
+        assert!(S3Target::parse("http://m/b", String::new(), "s".into(), "r".into()).is_err());
+    }
+
+    #[test]
+    fn request_is_signed_over_host_hash_and_date() {
+        let t = target("http://minio:9000/metrics");
+        let req = |path: &str, body: &[u8]| {
+            t.request("PUT", path, body.to_vec(), 1_700_000_000)
+                .unwrap()
+        };
+        let r = req("/metrics/h/x.jsonl.gz", b"body");
+        let header = |name: &str| r.headers()[name].to_str().unwrap().to_string();
+        assert_eq!(r.uri(), "/metrics/h/x.jsonl.gz");
+        assert_eq!(header("host"), "minio:9000");
+        assert_eq!(header("x-amz-date"), "20231114T221320Z");
+        assert_eq!(
+            header("x-amz-content-sha256"),
+            hex(&Sha256::digest(b"body"))
+        );
+        // Same steps computed independently (Python hmac/hashlib)
+        assert_eq!(
+            header("authorization"),
+            "AWS4-HMAC-SHA256 Credential=AKID/20231114/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=c8a7caa178450c725684217d92fa70d82b4d55b149cacaca67dd814b8a79eb0a"
+        );
+        let other = req("/metrics/h/x.jsonl.gz", b"other");
+        assert_ne!(
+            r.headers()["authorization"],
+            other.headers()["authorization"]
+        );
+        // Path segments go out encoded, the same as they were signed
+        assert_eq!(
+            req("/metrics/my site/x+y", b"").uri(),
+            "/metrics/my%20site/x%2By"
+        );
+    }
+
+    #[tokio::test]
+    async fn send_returns_the_store_status() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let store = tokio::spawn(async move {
+            let (mut s, _) = listener.accept().await.unwrap();
+            let mut req = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !req.ends_with(b"body") {
+                let n = s.read(&mut buf).await.unwrap();
+                assert!(n > 0, "request cut short");
+                req.extend_from_slice(&buf[..n]);
+            }
+            s.write_all(b"HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(req).unwrap()
+        });
+        let t = target(&format!("http://127.0.0.1:{port}/b"));
+        let code = t.send("PUT", "/b/a b", b"body".to_vec()).await.unwrap();
+        assert_eq!(code, 403);
+        let req = store.await.unwrap();
+        assert!(req.starts_with("PUT /b/a%20b HTTP/1.1\r\n"), "{req}");
+        assert!(req.contains("content-length: 4\r\n"), "{req}");
+        assert!(req.contains("authorization: AWS4-HMAC-SHA256 "), "{req}");
+    }
+}
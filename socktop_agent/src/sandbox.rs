@@ -0,0 +1,577 @@
+//! Self-sandboxing on Linux, on by default (`--no-sandbox` / SOCKTOP_AGENT_NO_SANDBOX=1 to
+//! skip): a Landlock ruleset limits the filesystem to what the collectors read (/proc, /sys,
+//! /etc, /run, libraries) plus the agent's own config dir and access log, and a seccomp filter
+//! refuses syscalls a monitoring agent never needs (module loading, mount, ptrace, kexec, ...).
+//!
+//! A Landlock rule on a file is bound to the inode open at startup, so files that get rotated
+//! by rename (token file, tokens.json, client CA, access log) are opened through their
+//! directory instead.
+//!
+//! Both only cover threads created after they are applied, so `apply` runs in `main` before
+//! the tokio runtime starts. Each layer is best effort: an old kernel without Landlock just
+//! leaves that layer off. What ended up active is published as the `sandbox_landlock` /
+//! `sandbox_seccomp` capabilities.
+
+// The syscall filter and rights masks are only wired up on Linux (x86_64/aarch64 for seccomp)
+#![cfg_attr(
+    not(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )),
+    allow(dead_code)
+)]
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Landlock filesystem rights (ABI 1; later ABIs only add rights we leave unhandled)
+pub const EXECUTE: u64 = 1 << 0;
+pub const WRITE_FILE: u64 = 1 << 1;
+pub const READ_FILE: u64 = 1 << 2;
+pub const READ_DIR: u64 = 1 << 3;
+pub const REMOVE_DIR: u64 = 1 << 4;
+pub const REMOVE_FILE: u64 = 1 << 5;
+pub const MAKE_CHAR: u64 = 1 << 6;
+pub const MAKE_DIR: u64 = 1 << 7;
+pub const MAKE_REG: u64 = 1 << 8;
+pub const MAKE_SOCK: u64 = 1 << 9;
+pub const MAKE_FIFO: u64 = 1 << 10;
+pub const MAKE_BLOCK: u64 = 1 << 11;
+pub const MAKE_SYM: u64 = 1 << 12;
+pub const HANDLED: u64 = EXECUTE
+    | WRITE_FILE
+    | READ_FILE
+    | READ_DIR
+    | REMOVE_DIR
+    | REMOVE_FILE
+    | MAKE_CHAR
+    | MAKE_DIR
+    | MAKE_REG
+    | MAKE_SOCK
+    | MAKE_FIFO
+    | MAKE_BLOCK
+    | MAKE_SYM;
+// The only rights a rule on a regular file (not a directory) may carry
+pub const FILE_RIGHTS: u64 = EXECUTE | WRITE_FILE | READ_FILE;
+
+pub const READ: u64 = READ_FILE | READ_DIR;
+pub const READ_EXEC: u64 = READ | EXECUTE;
+pub const READ_WRITE: u64 =
+    READ | WRITE_FILE | MAKE_REG | MAKE_DIR | REMOVE_FILE | REMOVE_DIR | MAKE_SYM;
+
+/// What the agent was started with that changes which paths it needs.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub config_dir: PathBuf,
+    pub tokens: PathBuf,
+    // --token-file, re-read on SIGHUP
+    pub token_file: Option<PathBuf>,
+    // --require-client-cert CA bundle
+    pub client_ca: Option<PathBuf>,
+    pub access_log: Option<PathBuf>,
+    // --updates runs the package manager's check command
+    pub updates: bool,
+    // --cpu-limit creates and writes its own cgroup
+    pub cpu_limit: bool,
+    // --cpufreq-control writes scaling_governor
+    pub cpufreq_control: bool,
+    // LD_LIBRARY_PATH at startup
+    pub library_path: std::ffi::OsString,
+}
+
+/// Landlock rules for `opts`: (path, rights). Paths that don't exist are skipped when applied.
+pub fn rules(opts: &Options) -> Vec<(PathBuf, u64)> {
+    let mut out: Vec<(PathBuf, u64)> = [
+        ("/proc", READ),
+        ("/sys", READ),
+        // machine-id, hostname, os-release, resolv.conf and CA roots for relay upstreams
+        ("/etc", READ),
+        ("/run", READ),
+        ("/var/run", READ),
+        ("/var/lib/dbus", READ),
+        ("/lib/modules", READ),
+        // dlopen'd GPU libraries and, with --updates, the package manager
+        ("/usr", READ_EXEC),
+        ("/lib", READ_EXEC),
+        ("/lib64", READ_EXEC),
+        ("/bin", READ_EXEC),
+        ("/sbin", READ_EXEC),
+        // Drivers installed outside the FHS tree (vendor bundles, Nix/Guix stores, snaps)
+        ("/opt", READ_EXEC),
+        ("/nix", READ_EXEC),
+        ("/gnu", READ_EXEC),
+        ("/snap", READ_EXEC),
+        // /dev/null for child stdio, GPU device nodes
+        ("/dev", READ | WRITE_FILE),
+    ]
+    .iter()
+    .map(|&(p, a)| (PathBuf::from(p), a))
+    .collect();
+    // ...and wherever LD_LIBRARY_PATH points the loader
+    out.extend(
+        std::env::split_paths(&opts.library_path)
+            .filter(|p| p.is_absolute())
+            .map(|p| (p, READ_EXEC)),
+    );
+    // TLS cert generation and the issued-tokens file live here
+    out.push((opts.config_dir.clone(), READ_WRITE));
+    if !opts.tokens.starts_with(&opts.config_dir) {
+        out.push((dir_of(&opts.tokens), READ));
+    }
+    if let Some(t) = &opts.token_file {
+        out.push((dir_of(t), READ));
+    }
+    if let Some(ca) = &opts.client_ca {
+        out.push((dir_of(ca), READ));
+    }
+    if let Some(log) = &opts.access_log {
+        // Created (or re-created after logrotate) in its directory
+        out.push((dir_of(log), WRITE_FILE | MAKE_REG | READ_FILE));
+    }
+    if opts.updates {
+        out.push(("/var".into(), READ));
+        // dnf/apt refresh their metadata caches while checking
+        out.push(("/var/cache".into(), READ_WRITE));
+        out.push(("/var/lib/dnf".into(), READ_WRITE));
+        out.push(("/tmp".into(), READ_WRITE));
+    }
+    if opts.cpu_limit {
+        out.push(("/sys/fs/cgroup".into(), READ_WRITE));
+    }
+    if opts.cpufreq_control {
+        out.push((crate::cpufreq::CPU_DIR.into(), READ | WRITE_FILE));
+    }
+    out
+}
+
+// Directory holding `file` ("." for a bare file name)
+fn dir_of(file: &Path) -> PathBuf {
+    file.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// State of one sandbox layer after startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    Active,
+    Disabled,
+    Unavailable(String),
+}
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer::Active => f.write_str("active"),
+            Layer::Disabled => f.write_str("disabled (--no-sandbox)"),
+            Layer::Unavailable(why) => write!(f, "unavailable ({why})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub landlock: Layer,
+    pub seccomp: Layer,
+}
+
+static STATUS: OnceLock<Status> = OnceLock::new();
+
+pub fn status() -> Status {
+    STATUS.get().cloned().unwrap_or(Status {
+        landlock: Layer::Disabled,
+        seccomp: Layer::Disabled,
+    })
+}
+
+/// (capability, active) pairs for metrics::capabilities.
+pub fn capability_flags() -> [(&'static str, bool); 2] {
+    let s = status();
+    [
+        ("sandbox_landlock", s.landlock == Layer::Active),
+        ("sandbox_seccomp", s.seccomp == Layer::Active),
+    ]
+}
+
+/// Sandbox this process (and every thread and child it creates later) unless `disabled`.
+pub fn apply(disabled: bool, opts: &Options) -> Status {
+    let status = if disabled {
+        Status {
+            landlock: Layer::Disabled,
+            seccomp: Layer::Disabled,
+        }
+    } else {
+        let _ = std::fs::create_dir_all(&opts.config_dir);
+        Status {
+            landlock: imp::landlock(&rules(opts)),
+            seccomp: imp::seccomp(),
+        }
+    };
+    let _ = STATUS.set(status.clone());
+    status
+}
+
+/// Syscalls refused with EPERM: kernel/host administration and poking at other processes.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn denied_syscalls() -> Vec<i64> {
+    vec![
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_reboot,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_setns,
+        libc::SYS_unshare,
+        libc::SYS_bpf,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_acct,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_sethostname,
+        libc::SYS_setdomainname,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_userfaultfd,
+    ]
+}
+
+/// Classic BPF instruction (`struct sock_filter`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SockFilter {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+// x32 syscalls share x86_64's audit arch but carry this bit in their number
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+pub const RET_ALLOW: u32 = 0x7fff_0000;
+pub const RET_EPERM: u32 = 0x0005_0000 | 1;
+
+/// Seccomp program: other ABIs (32-bit compat, x32) are refused, `denied` get EPERM, the rest
+/// pass.
+pub fn filter(audit_arch: u32, denied: &[i64]) -> Vec<SockFilter> {
+    let op = |code, jt, jf, k| SockFilter { code, jt, jf, k };
+    // seccomp_data: nr at offset 0, arch at offset 4
+    let mut prog = vec![
+        op(BPF_LD_W_ABS, 0, 0, 4),
+        op(BPF_JEQ_K, 1, 0, audit_arch),
+        op(BPF_RET_K, 0, 0, RET_EPERM),
+        op(BPF_LD_W_ABS, 0, 0, 0),
+        op(BPF_JGE_K, 0, 1, X32_SYSCALL_BIT),
+        op(BPF_RET_K, 0, 0, RET_EPERM),
+    ];
+    for &nr in denied {
+        prog.push(op(BPF_JEQ_K, 0, 1, nr as u32));
+        prog.push(op(BPF_RET_K, 0, 0, RET_EPERM));
+    }
+    prog.push(op(BPF_RET_K, 0, 0, RET_ALLOW));
+    prog
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{Layer, FILE_RIGHTS, HANDLED};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::PathBuf;
+
+    // Same numbers on every architecture (added after the syscall tables were unified)
+    const SYS_LANDLOCK_CREATE_RULESET: libc::c_long = 444;
+    const SYS_LANDLOCK_ADD_RULE: libc::c_long = 445;
+    const SYS_LANDLOCK_RESTRICT_SELF: libc::c_long = 446;
+    const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+    const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    fn no_new_privs() -> Result<(), String> {
+        // SAFETY: prctl with integer arguments only (unused ones must be 0)
+        let rc = unsafe {
+            libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            )
+        };
+        if rc == 0 {
+            Ok(())
+        } else {
+            Err(format!("no_new_privs: {}", std::io::Error::last_os_error()))
+        }
+    }
+
+    pub fn landlock(rules: &[(PathBuf, u64)]) -> Layer {
+        // SAFETY: a NULL attr with size 0 and the VERSION flag only queries the ABI
+        let abi = unsafe {
+            libc::syscall(
+                SYS_LANDLOCK_CREATE_RULESET,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Layer::Unavailable("kernel without Landlock".into());
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: HANDLED,
+        };
+        // SAFETY: attr outlives the call and its size is passed along
+        let ruleset = unsafe {
+            libc::syscall(
+                SYS_LANDLOCK_CREATE_RULESET,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if ruleset < 0 {
+            return Layer::Unavailable(format!(
+                "landlock ruleset: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let ruleset = ruleset as libc::c_int;
+        for (path, access) in rules {
+            let Ok(file) = std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+                .open(path)
+            else {
+                continue;
+            };
+            let is_dir = file.metadata().map(|m| m.is_dir()).unwrap_or(false);
+            let rule = PathBeneathAttr {
+                allowed_access: if is_dir {
+                    *access
+                } else {
+                    access & FILE_RIGHTS
+                },
+                parent_fd: file.as_raw_fd(),
+            };
+            // SAFETY: rule and the fd it names live until the call returns
+            let rc = unsafe {
+                libc::syscall(
+                    SYS_LANDLOCK_ADD_RULE,
+                    ruleset,
+                    LANDLOCK_RULE_PATH_BENEATH,
+                    &rule as *const PathBeneathAttr,
+                    0u32,
+                )
+            };
+            if rc != 0 {
+                tracing::debug!(
+                    "landlock rule for {}: {}",
+                    path.display(),
+                    std::io::Error::last_os_error()
+                );
+            }
+        }
+        let result = no_new_privs().and_then(|()| {
+            // SAFETY: ruleset is a valid fd from landlock_create_ruleset
+            let rc = unsafe { libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset, 0u32) };
+            if rc == 0 {
+                Ok(())
+            } else {
+                Err(format!(
+                    "landlock_restrict_self: {}",
+                    std::io::Error::last_os_error()
+                ))
+            }
+        });
+        // SAFETY: closing the ruleset fd we own; the domain stays in force
+        unsafe { libc::close(ruleset) };
+        match result {
+            Ok(()) => Layer::Active,
+            Err(e) => Layer::Unavailable(e),
+        }
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn seccomp() -> Layer {
+        #[cfg(target_arch = "x86_64")]
+        const AUDIT_ARCH: u32 = 0xc000_003e;
+        #[cfg(target_arch = "aarch64")]
+        const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+        #[repr(C)]
+        struct SockFprog {
+            len: libc::c_ushort,
+            filter: *const super::SockFilter,
+        }
+
+        if let Err(e) = no_new_privs() {
+            return Layer::Unavailable(e);
+        }
+        let prog = super::filter(AUDIT_ARCH, &super::denied_syscalls());
+        let fprog = SockFprog {
+            len: prog.len() as libc::c_ushort,
+            filter: prog.as_ptr(),
+        };
+        // SAFETY: fprog points at `prog`, which outlives the call; the kernel copies it
+        let rc = unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                &fprog as *const SockFprog,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            )
+        };
+        if rc == 0 {
+            Layer::Active
+        } else {
+            Layer::Unavailable(format!("seccomp: {}", std::io::Error::last_os_error()))
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn seccomp() -> Layer {
+        Layer::Unavailable("no syscall filter for this architecture".into())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::Layer;
+    use std::path::PathBuf;
+
+    pub fn landlock(_rules: &[(PathBuf, u64)]) -> Layer {
+        Layer::Unavailable("Linux only".into())
+    }
+
+    pub fn seccomp() -> Layer {
+        Layer::Unavailable("Linux only".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts() -> Options {
+        Options {
+            config_dir: "/home/a/.config/socktop_agent".into(),
+            tokens: "/home/a/.config/socktop_agent/tokens.json".into(),
+            ..Default::default()
+        }
+    }
+
+    fn access(rules: &[(PathBuf, u64)], path: &str) -> Option<u64> {
+        rules
+            .iter()
+            .find(|(p, _)| p == Path::new(path))
+            .map(|(_, a)| *a)
+    }
+
+    #[test]
+    fn collectors_read_only_config_dir_writable() {
+        let r = rules(&opts());
+        assert_eq!(access(&r, "/proc"), Some(READ));
+        assert_eq!(access(&r, "/sys"), Some(READ));
+        assert_eq!(access(&r, "/usr"), Some(READ_EXEC));
+        assert_eq!(access(&r, "/nix"), Some(READ_EXEC));
+        assert_eq!(
+            access(&r, "/home/a/.config/socktop_agent"),
+            Some(READ_WRITE)
+        );
+        // Tokens inside the config dir need no rule of their own; cgroups stay read-only
+        assert_eq!(
+            r.iter().filter(|(p, _)| p.ends_with("tokens.json")).count(),
+            0
+        );
+        assert_eq!(access(&r, "/sys/fs/cgroup"), None);
+        assert_eq!(access(&r, "/var"), None);
+        assert!(r.iter().all(|(_, a)| a & !HANDLED == 0));
+    }
+
+    #[test]
+    fn flags_open_what_they_need() {
+        let r = rules(&Options {
+            tokens: "/srv/tokens.json".into(),
+            token_file: Some("/etc/socktop/token".into()),
+            client_ca: Some("/srv/pki/clients.pem".into()),
+            access_log: Some("/nonexistent-dir/socktop/access.log".into()),
+            updates: true,
+            cpu_limit: true,
+            cpufreq_control: true,
+            library_path: "/opt/vendor/lib:relative".into(),
+            ..opts()
+        });
+        // Directories, not files: rotating by rename gets a new inode
+        assert_eq!(access(&r, "/srv"), Some(READ));
+        assert_eq!(access(&r, "/etc/socktop"), Some(READ));
+        assert_eq!(access(&r, "/srv/pki"), Some(READ));
+        assert_eq!(access(&r, "/srv/tokens.json"), None);
+        assert_eq!(access(&r, "/opt/vendor/lib"), Some(READ_EXEC));
+        assert_eq!(access(&r, "relative"), None);
+        assert_eq!(
+            access(&r, "/nonexistent-dir/socktop"),
+            Some(WRITE_FILE | MAKE_REG | READ_FILE)
+        );
+        assert_eq!(access(&r, "/var"), Some(READ));
+        assert_eq!(access(&r, "/sys/fs/cgroup"), Some(READ_WRITE));
+        assert_eq!(
+            access(&r, "/sys/devices/system/cpu"),
+            Some(READ | WRITE_FILE)
+        );
+    }
+
+    #[test]
+    fn filter_checks_arch_then_denies_listed_syscalls() {
+        let prog = filter(0xc000_003e, &[101, 165]);
+        assert_eq!(prog.len(), 6 + 2 * 2 + 1);
+        assert_eq!(prog[1].k, 0xc000_003e);
+        assert_eq!(prog[2].k, RET_EPERM);
+        assert_eq!((prog[4].code, prog[4].k), (BPF_JGE_K, X32_SYSCALL_BIT));
+        assert_eq!((prog[6].k, prog[7].k), (101, RET_EPERM));
+        assert_eq!((prog[8].k, prog[9].k), (165, RET_EPERM));
+        assert!(prog[6..].iter().all(|i| i.code != BPF_LD_W_ABS));
+        assert_eq!(prog.last().unwrap().k, RET_ALLOW);
+    }
+
+    #[test]
+    fn unapplied_sandbox_reports_nothing_active() {
+        assert_eq!(
+            capability_flags(),
+            [("sandbox_landlock", false), ("sandbox_seccomp", false)]
+        );
+        assert_eq!(
+            Layer::Unavailable("kernel without Landlock".into()).to_string(),
+            "unavailable (kernel without Landlock)"
+        );
+    }
+}
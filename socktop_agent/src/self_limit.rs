@@ -0,0 +1,226 @@
+//! Keep the agent out of the way of the workloads it watches: `--nice N` lowers its scheduling
+//! priority, and `--cpu-limit 5%` caps it at a share of one core. As root on cgroup v2 the cap
+//! is a kernel `cpu.max` on its own cgroup; otherwise request handling and the history sampler
+//! pause whenever the agent's own CPU time runs ahead of the budget (duty-cycling).
+
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+
+// Credit for idle time is forgotten after this, so a quiet hour doesn't buy a busy minute
+const WINDOW: Duration = Duration::from_secs(5);
+// Longest single pause, so a client never waits on one request for much longer than this
+const MAX_PAUSE: Duration = Duration::from_secs(2);
+#[cfg(target_os = "linux")]
+const CGROUP_DIR: &str = "/sys/fs/cgroup/socktop_agent";
+#[cfg(target_os = "linux")]
+const CGROUP_PERIOD_US: u64 = 100_000;
+
+static DUTY: OnceCell<Mutex<DutyCycle>> = OnceCell::new();
+
+/// `5%`, `5` or `0.5%` -> share of one core (0.05); must be above 0 and at most 100%.
+pub fn parse_cpu_limit(spec: &str) -> Result<f64, String> {
+    let pct: f64 = spec
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("--cpu-limit must look like 5%, got '{spec}'"))?;
+    if !(pct > 0.0 && pct <= 100.0) {
+        return Err(format!(
+            "--cpu-limit must be between 0% and 100%, got '{spec}'"
+        ));
+    }
+    Ok(pct / 100.0)
+}
+
+/// Apply `--nice` and `--cpu-limit`; failures are warnings, the agent runs either way.
+pub fn apply(nice: Option<i32>, cpu_limit: Option<f64>) {
+    if let Some(n) = nice {
+        match set_nice(n) {
+            Ok(()) => tracing::info!("running at nice {n}"),
+            Err(e) => tracing::warn!("--nice {n}: {e}"),
+        }
+    }
+    let Some(share) = cpu_limit else { return };
+    match cgroup_limit(share) {
+        Ok(()) => {
+            tracing::info!("cpu limited to {:.1}% of a core via cgroup", share * 100.0);
+            return;
+        }
+        Err(e) => tracing::debug!("cgroup cpu limit unavailable ({e}); duty-cycling instead"),
+    }
+    match cpu_time() {
+        Some(cpu) => {
+            let _ = DUTY.set(Mutex::new(DutyCycle::new(share, Instant::now(), cpu)));
+            tracing::info!(
+                "cpu limited to {:.1}% of a core by pausing work",
+                share * 100.0
+            );
+        }
+        None => tracing::warn!("--cpu-limit is not supported on this platform"),
+    }
+}
+
+/// Wait out any CPU time used beyond the duty-cycle budget. No-op unless it is in use.
+pub async fn throttle() {
+    let Some(duty) = DUTY.get() else { return };
+    let Some(cpu) = cpu_time() else { return };
+    let pause = match duty.lock() {
+        Ok(mut d) => d.pause(Instant::now(), cpu),
+        Err(_) => None,
+    };
+    if let Some(p) = pause {
+        tokio::time::sleep(p).await;
+    }
+}
+
+/// Budget bookkeeping for duty-cycling: CPU used since the window started may be at most
+/// `share` of the wall time passed.
+struct DutyCycle {
+    share: f64,
+    since: Instant,
+    cpu_since: Duration,
+}
+
+impl DutyCycle {
+    fn new(share: f64, now: Instant, cpu: Duration) -> Self {
+        Self {
+            share,
+            since: now,
+            cpu_since: cpu,
+        }
+    }
+
+    /// How long to wait before doing more work, given the process CPU time `cpu` at `now`.
+    fn pause(&mut self, now: Instant, cpu: Duration) -> Option<Duration> {
+        let elapsed = now.duration_since(self.since);
+        let used = cpu.saturating_sub(self.cpu_since);
+        let allowed = Duration::from_secs_f64(used.as_secs_f64() / self.share);
+        if allowed <= elapsed {
+            if elapsed >= WINDOW {
+                self.since = now;
+                self.cpu_since = cpu;
+            }
+            return None;
+        }
+        Some((allowed - elapsed).min(MAX_PAUSE))
+    }
+}
+
+// Linux nice values are per thread and the runtime's workers already exist, so renice every
+// thread; ones spawned later inherit from their (reniced) parent.
+#[cfg(target_os = "linux")]
+fn set_nice(n: i32) -> io::Result<()> {
+    let mut tids: Vec<libc::id_t> = std::fs::read_dir("/proc/self/task")?
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    if tids.is_empty() {
+        tids.push(0);
+    }
+    for tid in tids {
+        // SAFETY: plain syscall on one of our own threads; no pointers involved
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, n) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_nice(_n: i32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "not supported on this platform",
+    ))
+}
+
+/// Move the whole process into its own cgroup v2 with `cpu.max` set (root only).
+#[cfg(target_os = "linux")]
+fn cgroup_limit(share: f64) -> io::Result<()> {
+    use std::fs;
+    use std::path::Path;
+
+    // SAFETY: geteuid has no preconditions
+    if unsafe { libc::geteuid() } != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "not root"));
+    }
+    let controllers = fs::read_to_string("/sys/fs/cgroup/cgroup.controllers")?;
+    if !controllers.split_whitespace().any(|c| c == "cpu") {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "cgroup v2 cpu controller not available",
+        ));
+    }
+    let dir = Path::new(CGROUP_DIR);
+    fs::create_dir_all(dir)?;
+    // The kernel rejects quotas under 1ms
+    let quota = ((share * CGROUP_PERIOD_US as f64) as u64).max(1_000);
+    fs::write(dir.join("cpu.max"), format!("{quota} {CGROUP_PERIOD_US}"))?;
+    fs::write(dir.join("cgroup.procs"), std::process::id().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_limit(_share: f64) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no cgroups"))
+}
+
+/// User + system CPU time of the whole process.
+#[cfg(target_os = "linux")]
+fn cpu_time() -> Option<Duration> {
+    // SAFETY: rusage is plain data the kernel fills in for the duration of the call
+    let ru = unsafe {
+        let mut ru: libc::rusage = std::mem::zeroed();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut ru) != 0 {
+            return None;
+        }
+        ru
+    };
+    let tv = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1_000);
+    Some(tv(ru.ru_utime) + tv(ru.ru_stime))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_limit_parsing() {
+        assert_eq!(parse_cpu_limit("5%"), Ok(0.05));
+        assert_eq!(parse_cpu_limit("50"), Ok(0.5));
+        assert!(parse_cpu_limit("0%").is_err());
+        assert!(parse_cpu_limit("150%").is_err());
+        assert!(parse_cpu_limit("lots").is_err());
+    }
+
+    #[test]
+    fn duty_cycle_pauses_work_that_outruns_the_budget() {
+        let t0 = Instant::now();
+        let mut d = DutyCycle::new(0.25, t0, Duration::ZERO);
+        let ms = Duration::from_millis;
+
+        // 125ms of CPU in 500ms of wall time is exactly 25%
+        assert_eq!(d.pause(t0 + ms(500), ms(125)), None);
+        // 250ms of CPU needs a full second of wall time at 25%
+        assert_eq!(d.pause(t0 + ms(500), ms(250)), Some(ms(500)));
+        // A long burst waits at most MAX_PAUSE per call
+        assert_eq!(d.pause(t0 + ms(500), ms(5_000)), Some(MAX_PAUSE));
+    }
+
+    #[test]
+    fn idle_credit_expires_with_the_window() {
+        let t0 = Instant::now();
+        let mut d = DutyCycle::new(0.25, t0, Duration::ZERO);
+        // Idle for a minute: the window restarts instead of banking 15s of CPU
+        let later = t0 + Duration::from_secs(60);
+        assert_eq!(d.pause(later, Duration::ZERO), None);
+        let burst = Duration::from_secs(1);
+        assert_eq!(d.pause(later, burst), Some(MAX_PAUSE));
+    }
+}
@@ -0,0 +1,168 @@
+//! `--self-test`: run every collector once, report timing and outcome, exit non-zero if a core collector fails.
+
+use std::time::Instant;
+
+use crate::gpu::collect_all_gpus;
+use crate::metrics::{collect_disks, collect_processes_all, cpu_temp_from};
+use crate::state::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    // Optional collector unavailable on this host (not a failure)
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    core: bool,
+    outcome: Outcome,
+    ms: f64,
+    detail: String,
+}
+
+fn print_check(c: &Check) {
+    let tag = match c.outcome {
+        Outcome::Ok => " ok ",
+        Outcome::Warn => "warn",
+        Outcome::Fail => "FAIL",
+    };
+    let core = if c.core { "*" } else { " " };
+    println!(
+        "  [{tag}] {core}{:<12} {:>8.1}ms  {}",
+        c.name, c.ms, c.detail
+    );
+}
+
+/// Returns the process exit code: 0 when all core collectors succeeded, 1 otherwise.
+pub async fn run(state: &AppState) -> i32 {
+    println!("socktop_agent {} self-test", env!("CARGO_PKG_VERSION"));
+    let mut checks = Vec::new();
+
+    // CPU + memory
+    let t = Instant::now();
+    let (cores, mem_total) = {
+        let mut sys = state.sys.lock().await;
+        sys.refresh_cpu_usage();
+        sys.refresh_memory();
+        (sys.cpus().len(), sys.total_memory())
+    };
+    checks.push(Check {
+        name: "cpu/mem",
+        core: true,
+        outcome: if cores > 0 && mem_total > 0 {
+            Outcome::Ok
+        } else {
+            Outcome::Fail
+        },
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail: format!("{cores} cores, {mem_total} bytes RAM"),
+    });
+
+    // Networks
+    let t = Instant::now();
+    let ifaces = {
+        let mut nets = state.networks.lock().await;
+        nets.refresh(false);
+        nets.len()
+    };
+    checks.push(Check {
+        name: "network",
+        core: false,
+        outcome: if ifaces > 0 {
+            Outcome::Ok
+        } else {
+            Outcome::Warn
+        },
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail: format!("{ifaces} interfaces"),
+    });
+
+    // Temperature
+    let t = Instant::now();
+    let temp = {
+        let mut components = state.components.lock().await;
+        components.refresh(false);
+        cpu_temp_from(&components)
+    };
+    checks.push(Check {
+        name: "temperature",
+        core: false,
+        outcome: if temp.is_some() {
+            Outcome::Ok
+        } else {
+            Outcome::Warn
+        },
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail: temp
+            .map(|v| format!("{v:.1}°C"))
+            .unwrap_or_else(|| "no CPU sensor found".into()),
+    });
+
+    // GPU probe
+    let t = Instant::now();
+    let (outcome, detail) = match collect_all_gpus() {
+        Ok(v) if !v.is_empty() => (
+            Outcome::Ok,
+            v.iter()
+                .map(|g| g.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        Ok(_) => (Outcome::Warn, "no GPUs".to_string()),
+        Err(e) => (Outcome::Warn, format!("no GPU: {e}")),
+    };
+    checks.push(Check {
+        name: "gpu",
+        core: false,
+        outcome,
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail,
+    });
+
+    // Disks
+    let t = Instant::now();
+    let disks = collect_disks(state).await;
+    checks.push(Check {
+        name: "disks",
+        core: false,
+        outcome: if disks.is_empty() {
+            Outcome::Warn
+        } else {
+            Outcome::Ok
+        },
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail: format!("{} disks", disks.len()),
+    });
+
+    // Processes
+    let t = Instant::now();
+    let procs = collect_processes_all(state).await;
+    checks.push(Check {
+        name: "processes",
+        core: true,
+        outcome: if procs.process_count > 0 {
+            Outcome::Ok
+        } else {
+            Outcome::Fail
+        },
+        ms: t.elapsed().as_secs_f64() * 1000.0,
+        detail: format!("{} processes", procs.process_count),
+    });
+
+    for c in &checks {
+        print_check(c);
+    }
+    let failed = checks
+        .iter()
+        .filter(|c| c.core && c.outcome == Outcome::Fail)
+        .count();
+    if failed == 0 {
+        println!("self-test passed (* = core collector)");
+        0
+    } else {
+        println!("self-test FAILED: {failed} core collector(s) failed (* = core collector)");
+        1
+    }
+}
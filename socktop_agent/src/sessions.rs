@@ -0,0 +1,242 @@
+//! Connection sessions: `hello` hands out a session id, and a client that reconnects within
+//! the resume window (`--session-ttl-secs`, default 30) sends `hello?resume=ID` to pick up
+//! the same session, including per-session state such as delta baselines, instead of
+//! starting cold. The processes wire format a client picked with `hello?processes=` is kept
+//! there too, so it holds across `/poll` requests and reconnects.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::disk_delta::DiskBaseline;
+use crate::proc_format::ProcFormat;
+use crate::types::Scope;
+
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(30);
+// Upper bound on remembered sessions; the oldest detached ones go first, and new ones are
+// refused while every slot is attached
+const MAX_SESSIONS: usize = 1024;
+
+struct Session {
+    scope: Scope,
+    // None while a connection is attached
+    detached_at: Option<Instant>,
+    // get_disks?delta baseline
+    disks: DiskBaseline,
+    // hello?processes=json|pb; None follows --processes-json
+    processes: Option<ProcFormat>,
+}
+
+pub struct SessionTable {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionTable {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start a new attached session; None when every slot is held by an attached one.
+    pub fn open(&self, scope: Scope, now: Instant) -> Option<String> {
+        let mut s = self.sessions.lock().ok()?;
+        self.prune(&mut s, now);
+        Self::make_room(&mut s);
+        if s.len() >= MAX_SESSIONS {
+            return None;
+        }
+        let id = new_id();
+        s.insert(
+            id.clone(),
+            Session {
+                scope,
+                detached_at: None,
+                disks: DiskBaseline::default(),
+                processes: None,
+            },
+        );
+        Some(id)
+    }
+
+    /// Reattach `id` if it is detached, unexpired and was opened with the same scope (a
+    /// resumed session never changes what the new token allows).
+    pub fn resume(&self, id: &str, scope: Scope, now: Instant) -> bool {
+        let Ok(mut s) = self.sessions.lock() else {
+            return false;
+        };
+        self.prune(&mut s, now);
+        match s.get_mut(id) {
+            Some(sess) if sess.scope == scope && sess.detached_at.is_some() => {
+                sess.detached_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Run `f` on the session's disks baseline; None for unknown (or expired) sessions.
+    pub fn with_disks<R>(&self, id: &str, f: impl FnOnce(&mut DiskBaseline) -> R) -> Option<R> {
+        let mut s = self.sessions.lock().ok()?;
+        s.get_mut(id).map(|sess| f(&mut sess.disks))
+    }
+
+    /// Remember the processes format the client asked for in `hello`.
+    pub fn set_processes(&self, id: &str, format: ProcFormat) {
+        if let Ok(mut s) = self.sessions.lock() {
+            if let Some(sess) = s.get_mut(id) {
+                sess.processes = Some(format);
+            }
+        }
+    }
+
+    /// The processes format the session asked for, if any.
+    pub fn processes(&self, id: &str) -> Option<ProcFormat> {
+        self.sessions.lock().ok()?.get(id)?.processes
+    }
+
+    /// The connection holding `id` went away; keep it resumable for the TTL.
+    pub fn detach(&self, id: &str, now: Instant) {
+        if let Ok(mut s) = self.sessions.lock() {
+            if let Some(sess) = s.get_mut(id) {
+                sess.detached_at = Some(now);
+            }
+        }
+    }
+
+    fn prune(&self, s: &mut HashMap<String, Session>, now: Instant) {
+        s.retain(|_, sess| {
+            sess.detached_at
+                .is_none_or(|at| now.duration_since(at) < self.ttl)
+        });
+    }
+
+    // Make room for one more session: the oldest detached ones go first
+    fn make_room(s: &mut HashMap<String, Session>) {
+        let excess = (s.len() + 1).saturating_sub(MAX_SESSIONS);
+        if excess > 0 {
+            let mut detached: Vec<(Instant, String)> = s
+                .iter()
+                .filter_map(|(id, sess)| sess.detached_at.map(|at| (at, id.clone())))
+                .collect();
+            detached.sort_unstable();
+            for (_, id) in detached.into_iter().take(excess) {
+                s.remove(&id);
+            }
+        }
+    }
+}
+
+/// Per-connection context for `respond`: the token scope, and the session once `hello` ran.
+/// Cloned into each WebTransport stream, so the session slot is shared.
+#[derive(Clone)]
+pub struct Conn {
+    pub scope: Scope,
+    session: Arc<Mutex<Option<String>>>,
+}
+
+impl Conn {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn session(&self) -> Option<String> {
+        self.session.lock().ok()?.clone()
+    }
+
+    pub fn set_session(&self, id: String) {
+        if let Ok(mut s) = self.session.lock() {
+            *s = Some(id);
+        }
+    }
+
+    /// Connection closed: leave its session resumable.
+    pub fn close(&self, table: &SessionTable) {
+        if let Some(id) = self.session() {
+            table.detach(&id, Instant::now());
+        }
+    }
+}
+
+fn new_id() -> String {
+    let mut b = [0u8; 16];
+    let _ = getrandom::getrandom(&mut b);
+    b.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detached_sessions_resume_within_ttl_only() {
+        let t = SessionTable::new(Duration::from_secs(30));
+        let t0 = Instant::now();
+        let id = t.open(Scope::Read, t0).unwrap();
+
+        // Still attached: a second connection can't steal it
+        assert!(!t.resume(&id, Scope::Read, t0));
+
+        t.detach(&id, t0);
+        assert!(!t.resume(&id, Scope::Control, t0), "scope must match");
+        assert!(t.resume(&id, Scope::Read, t0 + Duration::from_secs(29)));
+
+        t.detach(&id, t0 + Duration::from_secs(29));
+        assert!(!t.resume(&id, Scope::Read, t0 + Duration::from_secs(60)));
+        assert!(!t.resume("unknown", Scope::Read, t0));
+    }
+
+    #[test]
+    fn processes_format_survives_a_resume() {
+        let t = SessionTable::new(DEFAULT_SESSION_TTL);
+        let t0 = Instant::now();
+        let id = t.open(Scope::Read, t0).unwrap();
+        assert_eq!(t.processes(&id), None);
+        t.set_processes(&id, ProcFormat::Json);
+        t.detach(&id, t0);
+        assert!(t.resume(&id, Scope::Read, t0));
+        assert_eq!(t.processes(&id), Some(ProcFormat::Json));
+        assert_eq!(t.processes("unknown"), None);
+    }
+
+    #[test]
+    fn ids_are_unique_and_conn_clones_share_the_session() {
+        let t = SessionTable::new(DEFAULT_SESSION_TTL);
+        let now = Instant::now();
+        let a = t.open(Scope::Read, now).unwrap();
+        let b = t.open(Scope::Read, now).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 32);
+
+        let conn = Conn::new(Scope::Read);
+        let stream = conn.clone();
+        stream.set_session(a.clone());
+        assert_eq!(conn.session(), Some(a.clone()));
+        conn.close(&t);
+        assert!(t.resume(&a, Scope::Read, Instant::now()));
+    }
+
+    #[test]
+    fn full_table_evicts_detached_sessions_and_refuses_when_all_are_attached() {
+        let t = SessionTable::new(DEFAULT_SESSION_TTL);
+        let t0 = Instant::now();
+        let ids: Vec<String> = (0..MAX_SESSIONS)
+            .map(|_| t.open(Scope::Read, t0).unwrap())
+            .collect();
+        assert_eq!(t.open(Scope::Read, t0), None, "every slot is attached");
+
+        // The oldest detached session makes room; attached ones are never evicted
+        t.detach(&ids[1], t0 + Duration::from_secs(2));
+        t.detach(&ids[0], t0 + Duration::from_secs(1));
+        let now = t0 + Duration::from_secs(3);
+        assert!(t.open(Scope::Read, now).is_some());
+        assert!(!t.resume(&ids[0], Scope::Read, now));
+        assert!(t.resume(&ids[1], Scope::Read, now));
+        assert_eq!(t.open(Scope::Read, now), None);
+    }
+}
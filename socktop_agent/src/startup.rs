@@ -0,0 +1,73 @@
+//! `--profile-startup`: wall time per initialization step, printed just before the agent starts
+//! serving, followed by the work kept off the startup path (GPU probing, which runs in the
+//! background, and the first `get_metrics`).
+
+use std::time::{Duration, Instant};
+
+use crate::gpu::collect_all_gpus;
+use crate::metrics::collect_fast_metrics;
+use crate::state::AppState;
+
+pub struct StartupProfile {
+    enabled: bool,
+    start: Instant,
+    steps: Vec<(&'static str, Duration)>,
+}
+
+impl StartupProfile {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            start: Instant::now(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Run `f`, recording its duration under `step`.
+    pub fn time<T>(&mut self, step: &'static str, f: impl FnOnce() -> T) -> T {
+        let t = Instant::now();
+        let out = f();
+        self.steps.push((step, t.elapsed()));
+        out
+    }
+
+    pub fn record(&mut self, step: &'static str, since: Instant) {
+        self.steps.push((step, since.elapsed()));
+    }
+
+    /// Print the startup steps, then time the work that startup skipped.
+    pub async fn report(&self, state: &AppState) {
+        if !self.enabled {
+            return;
+        }
+        println!(
+            "socktop_agent {} startup profile:",
+            env!("CARGO_PKG_VERSION")
+        );
+        for (step, d) in &self.steps {
+            println!("  {step:<24} {:>8.1}ms", ms(*d));
+        }
+        println!(
+            "  {:<24} {:>8.1}ms",
+            "ready to serve",
+            ms(self.start.elapsed())
+        );
+
+        println!("off the startup path:");
+        let t = Instant::now();
+        let gpu = tokio::task::spawn_blocking(|| match collect_all_gpus() {
+            Ok(v) => format!("{} found", v.len()),
+            Err(e) => format!("none ({e})"),
+        })
+        .await
+        .unwrap_or_default();
+        println!("  {:<24} {:>8.1}ms  {gpu}", "gpu probe", ms(t.elapsed()));
+        let t = Instant::now();
+        let _ = collect_fast_metrics(state).await;
+        println!("  {:<24} {:>8.1}ms", "first get_metrics", ms(t.elapsed()));
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
@@ -1,12 +1,15 @@
 //! Shared agent state: sysinfo handles and hot JSON cache.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use sysinfo::{Components, Disks, Networks, System};
 use tokio::sync::Mutex;
 
+use crate::tokens::same_secret;
+use crate::types::{CollectorTiming, SamplerMeta, Scope};
+
 pub type SharedSystem = Arc<Mutex<System>>;
 pub type SharedComponents = Arc<Mutex<Components>>;
 pub type SharedDisks = Arc<Mutex<Disks>>;
@@ -21,7 +24,9 @@ pub struct ProcCpuTracker {
 
 #[cfg(not(target_os = "linux"))]
 pub struct ProcessCache {
-    pub names: HashMap<u32, String>,
+    // pid -> (name, collection pass that last used it); the pass orders LRU eviction
+    pub names: HashMap<u32, (String, u64)>,
+    pub pass: u64,
     pub reusable_vec: Vec<crate::types::ProcessInfo>,
 }
 
@@ -30,11 +35,74 @@ impl Default for ProcessCache {
     fn default() -> Self {
         Self {
             names: HashMap::with_capacity(1000), // Pre-allocate for typical modern system process count
+            pass: 0,
             reusable_vec: Vec::with_capacity(1000),
         }
     }
 }
 
+#[cfg(not(target_os = "linux"))]
+impl ProcessCache {
+    /// Drop the least recently used names until the cache fits its share of
+    /// `--cache-memory-max`, and report what is left.
+    pub fn trim_names(&mut self) {
+        use crate::cache_budget::{self, Cache};
+        // Map slot (pid, String, pass) plus the name's heap bytes
+        let bytes = |name: &String| std::mem::size_of::<(u32, (String, u64))>() + name.capacity();
+        let mut total: usize = self.names.values().map(|(n, _)| bytes(n)).sum();
+        let room = cache_budget::room(Cache::Names);
+        if total > room {
+            let mut by_age: Vec<(u64, u32, usize)> = self
+                .names
+                .iter()
+                .map(|(pid, (n, used))| (*used, *pid, bytes(n)))
+                .collect();
+            by_age.sort_unstable();
+            let n = cache_budget::evict_count(by_age.iter().map(|e| e.2), total, room);
+            for (_, pid, size) in &by_age[..n] {
+                self.names.remove(pid);
+                total -= size;
+            }
+        }
+        cache_budget::set_used(Cache::Names, total);
+    }
+}
+
+/// Start time of the previous collection plus its timing, per collector.
+#[derive(Default)]
+pub struct CadenceStamp {
+    last_start: Option<Instant>,
+    pub timing: CollectorTiming,
+}
+
+impl CadenceStamp {
+    pub fn record(&mut self, started: Instant) {
+        self.timing.last_ms = started.elapsed().as_secs_f32() * 1000.0;
+        self.timing.interval_ms = self
+            .last_start
+            .map(|prev| started.duration_since(prev).as_millis() as u64);
+        self.last_start = Some(started);
+    }
+}
+
+#[derive(Default)]
+pub struct CadenceTracker {
+    pub metrics: CadenceStamp,
+    pub disks: CadenceStamp,
+    pub processes: CadenceStamp,
+}
+
+impl CadenceTracker {
+    pub fn snapshot(&self) -> SamplerMeta {
+        SamplerMeta {
+            metrics: self.metrics.timing,
+            disks: self.disks.timing,
+            processes: self.processes.timing,
+            caches: crate::cache_budget::usage(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub sys: SharedSystem,
@@ -42,6 +110,9 @@ pub struct AppState {
     pub disks: SharedDisks,
     pub networks: SharedNetworks,
     pub hostname: String,
+    // Distinguishes several agents on one host (`--instance NAME`, e.g. one per container)
+    pub instance: Option<String>,
+    pub labels: Arc<BTreeMap<String, String>>,
 
     // For correct per-process CPU% using /proc deltas (Linux only path uses this tracker)
     #[cfg(target_os = "linux")]
@@ -55,14 +126,45 @@ pub struct AppState {
     pub client_count: Arc<AtomicUsize>,
 
     pub auth_token: Option<String>,
+    // Viewer token (SOCKTOP_READ_TOKEN): accepted like auth_token but only grants Scope::Read
+    pub read_token: Option<String>,
+    // Control tokens from --token-file, re-read on SIGHUP
+    pub token_file: Option<Arc<crate::token_rotation::TokenList>>,
+    // Resumable connection sessions handed out by `hello`
+    pub sessions: Arc<crate::sessions::SessionTable>,
+    // Per-address failed auth counts and bans
+    pub auth_guard: Arc<crate::auth_guard::AuthGuard>,
+    // Hashed tokens issued by `socktop_agent token create` (auth required once the file exists)
+    pub issued_tokens: Option<Arc<crate::tokens::TokenFile>>,
     // GPU negative cache (probe once). gpu_checked=true after first attempt; gpu_present reflects result.
     pub gpu_checked: Arc<AtomicBool>,
     pub gpu_present: Arc<AtomicBool>,
+    // First sensor/GPU probe runs in the background (metrics::spawn_probe); until it finishes
+    // get_metrics answers without temperature and GPUs instead of waiting on it.
+    pub probed: Arc<AtomicBool>,
 
     // Lightweight on-demand caches (TTL based) to cap CPU under bursty polling.
     pub cache_metrics: Arc<Mutex<CacheEntry<crate::types::Metrics>>>,
     pub cache_disks: Arc<Mutex<CacheEntry<Vec<crate::types::DiskInfo>>>>,
     pub cache_processes: Arc<Mutex<CacheEntry<crate::types::ProcessesPayload>>>,
+
+    // Collection durations/intervals reported to clients in Metrics.meta
+    pub cadence: Arc<std::sync::Mutex<CadenceTracker>>,
+
+    // Previous /proc/stat per-CPU jiffies for irq/softirq deltas
+    pub irq_prev: Arc<std::sync::Mutex<Vec<crate::procfs::CpuIrqJiffies>>>,
+
+    // Downsampled metrics history served via get_history
+    pub history: Arc<std::sync::Mutex<crate::history::HistoryRing>>,
+
+    // Relay mode: leaf agents reachable via /ws?upstream=NAME and get_fleet
+    pub relay: Option<crate::relay::SharedRelay>,
+
+    // Gossip peer table (`--peer-seed` / `--advertise-url`) served by get_peers
+    pub peers: Option<crate::peers::SharedPeers>,
+
+    // Per-request audit log (`--access-log`)
+    pub access_log: Option<Arc<crate::access_log::AccessLog>>,
 }
 
 #[derive(Clone, Debug)]
@@ -90,12 +192,50 @@ impl<T> CacheEntry<T> {
     }
 }
 
+/// Parse `key=value` label specs; invalid entries (no `=`, empty key) are skipped.
+pub fn parse_labels<I: IntoIterator<Item = String>>(specs: I) -> BTreeMap<String, String> {
+    specs
+        .into_iter()
+        .filter_map(|s| {
+            let (k, v) = s.split_once('=')?;
+            let k = k.trim();
+            (!k.is_empty()).then(|| (k.to_string(), v.trim().to_string()))
+        })
+        .collect()
+}
+
 impl AppState {
+    /// Scope granted to a connection presenting `token` (`Authorization: Bearer` or the
+    /// `?token=` value); None rejects it. With no tokens configured every connection is let in
+    /// read-only. A token file counts as configured even while empty.
+    pub fn authorize(&self, token: Option<&str>) -> Option<Scope> {
+        let issued = self.issued_tokens.as_ref().and_then(|f| f.current());
+        if self.auth_token.is_none()
+            && self.read_token.is_none()
+            && self.token_file.is_none()
+            && issued.is_none()
+        {
+            return Some(Scope::Read);
+        }
+        let token = token?;
+        let matches =
+            |known: &Option<String>| known.as_deref().is_some_and(|k| same_secret(k, token));
+        if matches(&self.auth_token) || self.token_file.as_ref().is_some_and(|f| f.accepts(token)) {
+            Some(Scope::Control)
+        } else if matches(&self.read_token) {
+            Some(Scope::Read)
+        } else {
+            issued?.check(token, crate::tokens::now_secs())
+        }
+    }
+
     pub fn new() -> Self {
+        // Empty handles: each collector's first refresh(false) discovers sensors, disks and
+        // interfaces, so slow devices don't scan them all before serving.
         let sys = System::new();
-        let components = Components::new_with_refreshed_list();
-        let disks = Disks::new_with_refreshed_list();
-        let networks = Networks::new_with_refreshed_list();
+        let components = Components::new();
+        let disks = Disks::new();
+        let networks = Networks::new();
 
         Self {
             sys: Arc::new(Mutex::new(sys)),
@@ -103,6 +243,8 @@ impl AppState {
             disks: Arc::new(Mutex::new(disks)),
             networks: Arc::new(Mutex::new(networks)),
             hostname: System::host_name().unwrap_or_else(|| "unknown".into()),
+            instance: None,
+            labels: Arc::new(BTreeMap::new()),
             #[cfg(target_os = "linux")]
             proc_cpu: Arc::new(Mutex::new(ProcCpuTracker::default())),
             #[cfg(not(target_os = "linux"))]
@@ -111,11 +253,27 @@ impl AppState {
             auth_token: std::env::var("SOCKTOP_TOKEN")
                 .ok()
                 .filter(|s| !s.is_empty()),
+            read_token: std::env::var("SOCKTOP_READ_TOKEN")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            token_file: None,
+            issued_tokens: None,
+            auth_guard: Arc::new(Default::default()),
+            sessions: Arc::new(crate::sessions::SessionTable::new(
+                crate::sessions::DEFAULT_SESSION_TTL,
+            )),
             gpu_checked: Arc::new(AtomicBool::new(false)),
             gpu_present: Arc::new(AtomicBool::new(false)),
+            probed: Arc::new(AtomicBool::new(false)),
             cache_metrics: Arc::new(Mutex::new(CacheEntry::new())),
             cache_disks: Arc::new(Mutex::new(CacheEntry::new())),
             cache_processes: Arc::new(Mutex::new(CacheEntry::new())),
+            cadence: Arc::new(std::sync::Mutex::new(CadenceTracker::default())),
+            irq_prev: Arc::new(std::sync::Mutex::new(Vec::new())),
+            history: Arc::new(std::sync::Mutex::new(Default::default())),
+            relay: None,
+            peers: None,
+            access_log: None,
         }
     }
 }
@@ -4,10 +4,11 @@ use std::{
     io::Write,
     net::{IpAddr, Ipv4Addr},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use time::{Duration, OffsetDateTime};
 
-fn config_dir() -> PathBuf {
+pub fn config_dir() -> PathBuf {
     std::env::var_os("XDG_CONFIG_HOME")
         .map(PathBuf::from)
         .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
@@ -21,13 +22,45 @@ pub fn cert_paths() -> (PathBuf, PathBuf) {
     (dir.join("cert.pem"), dir.join("key.pem"))
 }
 
+/// Validity, from not_before, of the WebTransport listener's certificate. Browsers and
+/// wtransport clients only accept a certificate pinned by hash (`serverCertificateHashes`) if
+/// it is valid for at most 14 days.
+#[cfg(feature = "webtransport")]
+pub const WEBTRANSPORT_CERT_DAYS: i64 = 13;
+
 pub fn ensure_self_signed_cert() -> anyhow::Result<(PathBuf, PathBuf)> {
     let (cert_path, key_path) = cert_paths();
     if cert_path.exists() && key_path.exists() {
         return Ok((cert_path, key_path));
     }
-    fs::create_dir_all(cert_path.parent().unwrap())?;
+    write_self_signed(&cert_path, &key_path, Duration::days(397))?;
+    println!(
+        "socktop_agent: generated self-signed TLS certificate at {}",
+        cert_path.display()
+    );
+    println!("socktop_agent: private key at {}", key_path.display());
+    Ok((cert_path, key_path))
+}
 
+/// A new self-signed pair in `dir` for the WebTransport listener, replacing the previous one,
+/// valid for `WEBTRANSPORT_CERT_DAYS`: the long-lived cert can't be pinned over WebTransport.
+#[cfg(feature = "webtransport")]
+pub fn fresh_webtransport_cert(dir: &Path) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let (cert_path, key_path) = (
+        dir.join("webtransport-cert.pem"),
+        dir.join("webtransport-key.pem"),
+    );
+    write_self_signed(
+        &cert_path,
+        &key_path,
+        Duration::days(WEBTRANSPORT_CERT_DAYS),
+    )?;
+    Ok((cert_path, key_path))
+}
+
+// Self-signed cert for this host, valid from five minutes ago (clock skew) for `validity`
+fn write_self_signed(cert_path: &Path, key_path: &Path, validity: Duration) -> anyhow::Result<()> {
+    fs::create_dir_all(cert_path.parent().unwrap())?;
     let hostname = hostname::get()
         .ok()
         .and_then(|s| s.into_string().ok())
@@ -66,10 +99,9 @@ pub fn ensure_self_signed_cert() -> anyhow::Result<(PathBuf, PathBuf)> {
     dn.push(DnType::CommonName, hostname.clone());
     params.distinguished_name = dn;
     params.is_ca = IsCa::NoCa;
-    // Dynamic validity: start slightly in the past to avoid clock skew issues, end ~397 days later
     let now = OffsetDateTime::now_utc();
     params.not_before = now - Duration::minutes(5);
-    params.not_after = now + Duration::days(397);
+    params.not_after = params.not_before + validity;
 
     // Generate key pair (default is ECDSA P256 SHA256)
     let key_pair = rcgen::KeyPair::generate()?; // defaults to ECDSA P256 SHA256
@@ -77,15 +109,93 @@ pub fn ensure_self_signed_cert() -> anyhow::Result<(PathBuf, PathBuf)> {
     let cert_pem = cert.pem();
     let key_pem = key_pair.serialize_pem();
 
-    let mut f = fs::File::create(&cert_path)?;
+    let mut f = fs::File::create(cert_path)?;
     f.write_all(cert_pem.as_bytes())?;
-    let mut k = fs::File::create(&key_path)?;
+    let mut k = fs::File::create(key_path)?;
     k.write_all(key_pem.as_bytes())?;
 
-    println!(
-        "socktop_agent: generated self-signed TLS certificate at {}",
-        cert_path.display()
-    );
-    println!("socktop_agent: private key at {}", key_path.display());
-    Ok((cert_path, key_path))
+    Ok(())
+}
+
+/// SHA-256 fingerprint (lowercase hex) of the first certificate in a PEM file.
+pub fn cert_fingerprint(cert_path: &Path) -> anyhow::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut reader = std::io::BufReader::new(fs::File::open(cert_path)?);
+    let der = rustls_pemfile::certs(&mut reader)
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no certificate in {}", cert_path.display()))??;
+    let digest = Sha256::digest(der.as_ref());
+    Ok(digest.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn pem_certs(path: &Path) -> anyhow::Result<Vec<rustls_021::Certificate>> {
+    let mut reader = std::io::BufReader::new(fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .map(|c| c.map(|der| rustls_021::Certificate(der.to_vec())))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificate in {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// Server config for `--require-client-cert CA.pem`: the handshake fails unless the client
+/// presents a certificate that chains to one in `client_ca`.
+pub fn mutual_tls_config(
+    cert_path: &Path,
+    key_path: &Path,
+    client_ca: &Path,
+) -> anyhow::Result<Arc<rustls_021::ServerConfig>> {
+    use rustls_021::server::AllowAnyAuthenticatedClient;
+    use rustls_021::{PrivateKey, RootCertStore, ServerConfig};
+
+    let mut roots = RootCertStore::empty();
+    for ca in pem_certs(client_ca)? {
+        roots.add(&ca)?;
+    }
+    let mut reader = std::io::BufReader::new(fs::File::open(key_path)?);
+    let key = rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| anyhow::anyhow!("no private key in {}", key_path.display()))?;
+    let mut cfg = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(pem_certs(cert_path)?, PrivateKey(key.secret_der().to_vec()))?;
+    // Same protocols axum-server offers for a plain cert/key pair
+    cfg.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(cfg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (cert.pem, key.pem) of a fresh self-signed certificate in `dir`
+    fn write_cert(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let (c, k) = (
+            dir.join(format!("{name}.pem")),
+            dir.join(format!("{name}.key")),
+        );
+        fs::write(&c, cert.pem()).unwrap();
+        fs::write(&k, key.serialize_pem()).unwrap();
+        (c, k)
+    }
+
+    #[test]
+    fn client_ca_must_hold_a_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_cert(dir.path(), "localhost");
+        let (ca, _) = write_cert(dir.path(), "clients-ca");
+        assert!(mutual_tls_config(&cert, &key, &ca).is_ok());
+
+        let empty = dir.path().join("empty.pem");
+        fs::write(&empty, "").unwrap();
+        let err = mutual_tls_config(&cert, &key, &empty).unwrap_err();
+        assert!(err.to_string().contains("no certificate"));
+        assert!(mutual_tls_config(&cert, &key, &dir.path().join("missing.pem")).is_err());
+    }
 }
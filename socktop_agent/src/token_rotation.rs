@@ -0,0 +1,117 @@
+//! `--token-file PATH` (or SOCKTOP_TOKEN_FILE): control tokens read from a file instead of
+//! SOCKTOP_TOKEN, which shows up in the process environment and service definitions. One token
+//! per line (blank lines and `#` comments skipped), so rotating is: add the new token, SIGHUP,
+//! move the clients over, remove the old one, SIGHUP again. The agent re-reads the file on
+//! SIGHUP and keeps the previous tokens when the file can't be read.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::tokens::same_secret;
+
+pub struct TokenList {
+    path: PathBuf,
+    tokens: RwLock<Vec<String>>,
+}
+
+/// Tokens in a token file's text.
+pub fn parse(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+impl TokenList {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let tokens = parse(&fs::read_to_string(path)?);
+        Ok(TokenList {
+            path: path.to_path_buf(),
+            tokens: RwLock::new(tokens),
+        })
+    }
+
+    /// Re-read the file; returns how many tokens it now holds.
+    pub fn reload(&self) -> io::Result<usize> {
+        let tokens = parse(&fs::read_to_string(&self.path)?);
+        let n = tokens.len();
+        if let Ok(mut t) = self.tokens.write() {
+            *t = tokens;
+        }
+        Ok(n)
+    }
+
+    pub fn accepts(&self, token: &str) -> bool {
+        self.tokens
+            .read()
+            .is_ok_and(|t| t.iter().any(|k| same_secret(k, token)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Re-read `list` whenever the agent gets SIGHUP.
+#[cfg(unix)]
+pub fn reload_on_sighup(list: Arc<TokenList>) -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hup.recv().await.is_some() {
+            match list.reload() {
+                Ok(n) => println!(
+                    "socktop_agent: reloaded {} ({n} tokens)",
+                    list.path().display()
+                ),
+                // An error, not a warning (those are hidden by default): revoked tokens stay
+                // valid until the file can be read again
+                Err(e) => tracing::error!(
+                    "can't reload {}: {e}; the previous tokens stay valid",
+                    list.path().display()
+                ),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_list: Arc<TokenList>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_token_per_line_without_comments() {
+        let t = parse("# rotated 2026-10-01\nnew-token\n\n  old-token  \n");
+        assert_eq!(t, ["new-token", "old-token"]);
+        assert!(parse("# nothing yet\n").is_empty());
+    }
+
+    #[test]
+    fn reload_swaps_tokens_and_keeps_them_when_the_file_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        fs::write(&path, "old\n").unwrap();
+        let list = TokenList::load(&path).unwrap();
+        assert!(list.accepts("old") && !list.accepts("new"));
+
+        fs::write(&path, "new\nold\n").unwrap();
+        assert_eq!(list.reload().unwrap(), 2);
+        assert!(list.accepts("new") && list.accepts("old"));
+        fs::write(&path, "new\n").unwrap();
+        list.reload().unwrap();
+        assert!(!list.accepts("old"));
+
+        fs::remove_file(&path).unwrap();
+        assert!(list.reload().is_err());
+        assert!(list.accepts("new"));
+    }
+}
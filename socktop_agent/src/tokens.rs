@@ -0,0 +1,383 @@
+//! Issued tokens (`socktop_agent token create|list|revoke`). Only a SHA-256 of each token is
+//! stored, with its scope and optional expiry, in $XDG_CONFIG_HOME/socktop_agent/tokens.json
+//! (override with SOCKTOP_AGENT_TOKENS). Once that file exists every connection needs a token;
+//! the running agent picks up changes on the next connection.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::types::Scope;
+
+const PREFIX: &str = "skt_";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenEntry {
+    /// Public handle for `token revoke`; also the first part of the token itself
+    pub id: String,
+    /// Hex SHA-256 of the whole token
+    pub hash: String,
+    pub scope: Scope,
+    /// Unix seconds
+    pub created: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+}
+
+impl TokenEntry {
+    pub fn expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|e| now >= e)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenStore {
+    #[serde(default)]
+    pub tokens: Vec<TokenEntry>,
+}
+
+impl TokenStore {
+    /// Read the store; Ok(None) when the file does not exist (token auth not in use).
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let body = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+        // Owner-only from the start, not chmod'ed after the hashes are already on disk
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        opts.open(path)?.write_all(&body)
+    }
+
+    /// Issue a new token; the plaintext is returned once and never stored.
+    pub fn create(&mut self, scope: Scope, ttl: Option<Duration>, now: u64) -> io::Result<String> {
+        let id = hex(&random_bytes::<4>()?);
+        let token = format!("{PREFIX}{id}_{}", hex(&random_bytes::<24>()?));
+        self.tokens.push(TokenEntry {
+            id,
+            hash: digest(&token),
+            scope,
+            created: now,
+            expires: ttl.map(|d| now.saturating_add(d.as_secs())),
+        });
+        Ok(token)
+    }
+
+    /// Remove a token by id (or by the full token); false when nothing matched.
+    pub fn revoke(&mut self, id_or_token: &str) -> bool {
+        let hash = digest(id_or_token);
+        let before = self.tokens.len();
+        self.tokens
+            .retain(|t| t.id != id_or_token && !same_secret(&t.hash, &hash));
+        self.tokens.len() != before
+    }
+
+    /// Scope of a presented token that is known and not expired.
+    pub fn check(&self, token: &str, now: u64) -> Option<Scope> {
+        if !token.starts_with(PREFIX) {
+            return None;
+        }
+        let hash = digest(token);
+        self.tokens
+            .iter()
+            .find(|t| same_secret(&t.hash, &hash) && !t.expired(now))
+            .map(|t| t.scope)
+    }
+}
+
+/// Compare presented and known secrets in constant time, so response timing can't be used to
+/// guess a token byte by byte.
+pub fn same_secret(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// Store path, cached by modification time so `authorize` doesn't re-parse it per connection.
+pub struct TokenFile {
+    path: PathBuf,
+    cache: Mutex<Option<(SystemTime, TokenStore)>>,
+}
+
+impl TokenFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Current store; None when the file is absent. An unreadable file yields an empty store
+    /// so a corrupt file locks connections out rather than opening the agent.
+    pub fn current(&self) -> Option<TokenStore> {
+        let modified = match fs::metadata(&self.path) {
+            Ok(m) => m.modified().unwrap_or(UNIX_EPOCH),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                tracing::error!("{}: {e}; refusing every token", self.path.display());
+                return Some(TokenStore::default());
+            }
+        };
+        let mut cache = self.cache.lock().ok()?;
+        if let Some((at, store)) = cache.as_ref() {
+            if *at == modified {
+                return Some(store.clone());
+            }
+        }
+        let store = match TokenStore::load(&self.path) {
+            Ok(store) => store?,
+            Err(e) => {
+                tracing::warn!("{}: {e}", self.path.display());
+                TokenStore::default()
+            }
+        };
+        *cache = Some((modified, store.clone()));
+        Some(store)
+    }
+}
+
+pub fn default_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("SOCKTOP_AGENT_TOKENS") {
+        return PathBuf::from(p);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("socktop_agent")
+        .join("tokens.json")
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// `30d`, `12h`, `45m`, `90s`; `never` for no expiry.
+pub fn parse_ttl(spec: &str) -> Result<Option<Duration>, String> {
+    if spec == "never" {
+        return Ok(None);
+    }
+    let err = || format!("--expires must look like 30d, 12h, 45m or never, got '{spec}'");
+    let (num, unit) = spec.split_at(
+        spec.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(spec.len()),
+    );
+    let n: u64 = num.parse().map_err(|_| err())?;
+    let unit_secs = match unit {
+        "d" => 86_400,
+        "h" => 3_600,
+        "m" => 60,
+        "s" => 1,
+        _ => return Err(err()),
+    };
+    let secs = n
+        .checked_mul(unit_secs)
+        .ok_or_else(|| format!("--expires '{spec}' is too far in the future"))?;
+    Ok(Some(Duration::from_secs(secs)))
+}
+
+fn digest(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn random_bytes<const N: usize>() -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    getrandom::getrandom(&mut buf).map_err(io::Error::other)?;
+    Ok(buf)
+}
+
+fn format_time(secs: u64) -> String {
+    time::OffsetDateTime::from_unix_timestamp(secs as i64)
+        .ok()
+        .and_then(|t| {
+            t.format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        })
+        .unwrap_or_else(|| secs.to_string())
+}
+
+/// `socktop_agent token create [--scope read|control] [--expires 30d|never]`,
+/// `token list`, `token revoke ID`.
+pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    let path = default_path();
+    let value = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+    };
+    match args.first().map(String::as_str) {
+        Some("create") => {
+            let scope = match value("--scope").unwrap_or("read") {
+                "read" => Scope::Read,
+                "control" => Scope::Control,
+                other => anyhow::bail!("--scope must be read or control, got '{other}'"),
+            };
+            let ttl = parse_ttl(value("--expires").unwrap_or("never")).map_err(anyhow::Error::msg)?;
+            let mut store = TokenStore::load(&path)?.unwrap_or_default();
+            let now = now_secs();
+            let token = store.create(scope, ttl, now)?;
+            store.save(&path)?;
+            let entry = store.tokens.last().expect("just created");
+            println!("{token}");
+            eprintln!(
+                "id {} ({}), expires {}; stored hashed in {}. This token is not shown again.",
+                entry.id,
+                scope_name(entry.scope),
+                entry.expires.map_or("never".into(), format_time),
+                path.display()
+            );
+        }
+        Some("list") => {
+            let store = TokenStore::load(&path)?.unwrap_or_default();
+            let now = now_secs();
+            println!("{:<10} {:<8} {:<26} EXPIRES", "ID", "SCOPE", "CREATED");
+            for t in &store.tokens {
+                let expires = match t.expires {
+                    Some(e) if t.expired(now) => format!("{} (expired)", format_time(e)),
+                    Some(e) => format_time(e),
+                    None => "never".into(),
+                };
+                println!(
+                    "{:<10} {:<8} {:<26} {expires}",
+                    t.id,
+                    scope_name(t.scope),
+                    format_time(t.created)
+                );
+            }
+        }
+        Some("revoke") => {
+            let Some(id) = args.get(1) else {
+                anyhow::bail!("usage: socktop_agent token revoke ID");
+            };
+            let Some(mut store) = TokenStore::load(&path)? else {
+                anyhow::bail!("no tokens issued ({} does not exist)", path.display());
+            };
+            if !store.revoke(id) {
+                anyhow::bail!("no token with id '{id}'");
+            }
+            store.save(&path)?;
+            eprintln!("revoked {id}");
+        }
+        _ => anyhow::bail!(
+            "usage: socktop_agent token create [--scope read|control] [--expires 30d|never] | list | revoke ID"
+        ),
+    }
+    Ok(())
+}
+
+fn scope_name(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Read => "read",
+        Scope::Control => "control",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn created_tokens_check_by_hash_until_expiry() {
+        let mut store = TokenStore::default();
+        let now = 1_000_000;
+        let viewer = store
+            .create(Scope::Read, Some(Duration::from_secs(60)), now)
+            .unwrap();
+        let admin = store.create(Scope::Control, None, now).unwrap();
+
+        assert!(viewer.starts_with(PREFIX));
+        assert_ne!(viewer, admin);
+        // Nothing in the store reveals the token itself
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(!json.contains(&viewer[PREFIX.len() + 9..]));
+
+        assert_eq!(store.check(&viewer, now), Some(Scope::Read));
+        assert_eq!(store.check(&admin, now + 10_000_000), Some(Scope::Control));
+        assert_eq!(store.check(&viewer, now + 60), None, "expired");
+        assert_eq!(store.check("skt_nope", now), None);
+    }
+
+    #[test]
+    fn revoke_by_id_or_token() {
+        let mut store = TokenStore::default();
+        let a = store.create(Scope::Read, None, 0).unwrap();
+        let b = store.create(Scope::Read, None, 0).unwrap();
+        let a_id = store.tokens[0].id.clone();
+
+        assert!(store.revoke(&a_id));
+        assert!(!store.revoke(&a_id));
+        assert_eq!(store.check(&a, 0), None);
+        assert!(store.revoke(&b));
+        assert!(store.tokens.is_empty());
+    }
+
+    #[test]
+    fn store_round_trips_and_file_presence_enables_auth() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        let file = TokenFile::new(path.clone());
+        assert!(file.current().is_none());
+
+        let mut store = TokenStore::default();
+        let t = store.create(Scope::Control, None, now_secs()).unwrap();
+        store.save(&path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let loaded = file.current().expect("file exists now");
+        assert_eq!(loaded.check(&t, now_secs()), Some(Scope::Control));
+
+        // Revoking the last token leaves an empty store, which still requires a token
+        store.revoke(&t);
+        store.save(&path).unwrap();
+        assert!(TokenStore::load(&path).unwrap().unwrap().tokens.is_empty());
+    }
+
+    #[test]
+    fn ttl_parsing() {
+        assert_eq!(parse_ttl("30d"), Ok(Some(Duration::from_secs(30 * 86_400))));
+        assert_eq!(parse_ttl("12h"), Ok(Some(Duration::from_secs(12 * 3_600))));
+        assert_eq!(parse_ttl("never"), Ok(None));
+        assert!(parse_ttl("30").is_err());
+        assert!(parse_ttl("d").is_err());
+        assert!(parse_ttl("").is_err());
+        // Too large for seconds in a u64: an error, not an overflow
+        assert!(parse_ttl("999999999999999d")
+            .unwrap_err()
+            .contains("too far"));
+        assert_eq!(
+            parse_ttl("18446744073709551615s"),
+            Ok(Some(Duration::from_secs(u64::MAX)))
+        );
+    }
+}
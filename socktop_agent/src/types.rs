@@ -2,7 +2,78 @@
 //! Keep this module minimal and stable — it defines the wire format.
 
 use crate::gpu::GpuMetrics;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Wire schema of every JSON object reply and of the processes protobuf (`schema_version`).
+/// Readers on both sides ignore fields they don't know, so adding a field needs no bump; bump
+/// it when a field is removed or changes meaning. Bare-array replies (`get_disks`,
+/// `get_fleet`) stay arrays so older clients keep parsing them.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Optional protocol features this agent understands (advertised in Metrics).
+/// - `processes_top`: `get_processes?top=K&sort=cpu|mem`
+/// - `collectors`: collector entries (`history`, `gpu`, `temp`, `updates`) are listed exactly
+///   when enabled, see metrics::capabilities
+/// - `hello`: answers `hello` with this connection's token scope and a resumable session id
+/// - `disks_delta`: `get_disks?delta=SEQ` (after `hello`) sends only changed disks
+/// - `process_inspect` (Linux only, see metrics::capabilities): `get_process_env?pid=N` and
+///   `get_process_fds?pid=N` for control-scope tokens
+/// - `io` (Linux only): `get_io` pressure, disk utilization and top I/O processes
+/// - `sandbox_landlock` / `sandbox_seccomp`: listed when that sandbox layer is in force
+/// - `processes_sample`: `get_processes?top=K&tail=M&after=PID` adds a rotating long-tail sample
+pub const CAPABILITIES: &[&str] = &[
+    "processes_top",
+    "processes_sample",
+    "collectors",
+    "hello",
+    "disks_delta",
+    "peers",
+    "frame_crc",
+    "processes_pb",
+    "subscribe",
+];
+
+/// What a connection's token allows. `control` is required for commands that change the host;
+/// viewer tokens (SOCKTOP_READ_TOKEN, `token create --scope read`) and agents without any
+/// token only get `read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Control,
+}
+
+/// Reply to `hello` / `hello?resume=ID`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hello {
+    pub schema_version: u32,
+    pub version: &'static str,
+    pub scope: Scope,
+    pub capabilities: Vec<&'static str>,
+    /// Send back as `hello?resume=ID` after a reconnect (see sessions); left out when the
+    /// session table is full of attached sessions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// True when `resume` reattached the previous session
+    pub resumed: bool,
+    /// How `get_processes` replies will be encoded for this session: `pb` or `json`
+    pub processes: &'static str,
+    /// Why there is no session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Reply to `subscribe?...` / `unsubscribe` (see push): what the agent now pushes, in ms.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscribeAck {
+    pub schema_version: u32,
+    pub metrics_ms: Option<u64>,
+    pub processes_ms: Option<u64>,
+    /// Set when the connection can't be subscribed (relayed connections)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DiskInfo {
@@ -11,6 +82,118 @@ pub struct DiskInfo {
     pub available: u64,
 }
 
+/// Reply to `get_disks?delta=SEQ`: every disk when `full`, otherwise only the entries whose
+/// usage changed since reply SEQ, plus the names that disappeared.
+#[derive(Debug, Clone, Serialize)]
+pub struct DisksDelta {
+    pub schema_version: u32,
+    pub seq: u64,
+    pub full: bool,
+    pub disks: Vec<DiskInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+/// Reply to `get_cpufreq` and `set_governor?name=G` (see cpufreq). `error` says why a
+/// `set_governor` didn't change anything.
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuFreq {
+    pub schema_version: u32,
+    pub cores: Vec<CoreFreq>,
+    /// Governors the driver offers
+    pub available: Vec<String>,
+    /// `set_governor` is enabled on this agent (`--cpufreq-control`)
+    pub control: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoreFreq {
+    pub cpu: u32,
+    pub governor: String,
+    /// Current frequency (`scaling_cur_freq`)
+    pub cur_khz: Option<u64>,
+}
+
+/// Reply to `get_process_env?pid=N`. `error` is set instead of `vars` when the process can't
+/// be read (wrong scope, gone, another user's).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessEnv {
+    pub schema_version: u32,
+    pub pid: u32,
+    pub vars: Vec<EnvVar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One environment variable; `value` is left out for names not on the agent's allow-list.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvVar {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// Reply to `get_process_fds?pid=N`; `truncated` when the process had more fds than were sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessFds {
+    pub schema_version: u32,
+    pub pid: u32,
+    pub fds: Vec<OpenFd>,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenFd {
+    pub fd: u32,
+    pub target: String,
+}
+
+/// Reply to `get_io` (Linux): pressure, per-disk utilization and the busiest I/O processes.
+/// Rates cover the last `interval_ms`; None (and empty lists) on the first sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct IoReport {
+    pub schema_version: u32,
+    pub interval_ms: Option<u64>,
+    // PSI io pressure; None on kernels without CONFIG_PSI
+    pub pressure: Option<IoPressure>,
+    pub procs_blocked: Option<u32>,
+    pub iowait_pct: Option<f32>,
+    pub disks: Vec<DiskIo>,
+    pub top: Vec<IoProcess>,
+}
+
+/// Share of time (%) some / all non-idle tasks were stalled on I/O, over 10s and 60s.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct IoPressure {
+    pub some_avg10: f32,
+    pub some_avg60: f32,
+    pub full_avg10: f32,
+    pub full_avg60: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskIo {
+    pub name: String,
+    // Time with I/O in flight, % of the interval
+    pub util_pct: f32,
+    pub read_bps: u64,
+    pub write_bps: u64,
+}
+
+/// A process doing storage I/O, or blocked in uninterruptible sleep (`D` state).
+#[derive(Debug, Clone, Serialize)]
+pub struct IoProcess {
+    pub pid: u32,
+    pub name: String,
+    pub read_bps: u64,
+    pub write_bps: u64,
+    pub blocked: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct NetworkInfo {
     pub name: String,
@@ -24,22 +207,142 @@ pub struct ProcessInfo {
     pub name: String,
     pub cpu_usage: f32,
     pub mem_bytes: u64,
+    /// CPU time used since the process started (user + system), milliseconds
+    pub cpu_time_ms: u64,
+}
+
+/// Share of one core's time spent in hard/soft interrupt handlers since the previous sample.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CoreIrq {
+    pub irq: f32,
+    pub softirq: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FdProcess {
+    pub pid: u32,
+    pub name: String,
+    pub fds: u32,
+}
+
+/// System-wide file handles in use vs fs.file-max, plus the processes holding the most fds.
+#[derive(Debug, Clone, Serialize)]
+pub struct FdUsage {
+    pub allocated: u64,
+    pub max: u64,
+    pub top: Vec<FdProcess>,
+}
+
+/// Kernel RNG state: entropy estimate vs pool size (bits) and the active hardware RNG.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntropyInfo {
+    pub avail_bits: u32,
+    pub pool_bits: u32,
+    pub hwrng: Option<String>,
+}
+
+/// Whether NTP is disciplining the clock, with the kernel's current offset and error bound.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ClockSync {
+    pub synchronized: bool,
+    pub offset_ms: f64,
+    pub max_error_ms: f64,
+}
+
+/// Pending package updates from the optional slow collector (`--updates`).
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingUpdates {
+    pub manager: &'static str,
+    pub total: u32,
+    pub security: Option<u32>,
+}
+
+/// Why the host wants a reboot and (Debian) which packages asked for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RebootRequired {
+    pub reason: String,
+    pub packages: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Metrics {
+    pub schema_version: u32,
     pub cpu_total: f32,
     pub cpu_per_core: Vec<f32>,
+    // Present-but-offline CPU ids (Linux); cpu_per_core lists online cores only, in id order
+    pub offline_cores: Vec<u32>,
+    // Per online core, same order as cpu_per_core (Linux; None until two samples exist)
+    pub cpu_irq: Option<Vec<CoreIrq>>,
+    // Scheduler run queue (Linux): runnable vs blocked-on-I/O tasks
+    pub procs_running: Option<u32>,
+    pub procs_blocked: Option<u32>,
+    // File handle usage (Linux)
+    pub fd: Option<FdUsage>,
+    // Entropy pool / RNG health (Linux)
+    pub entropy: Option<EntropyInfo>,
+    // NTP/clock discipline (Linux)
+    pub clock: Option<ClockSync>,
+    // Pending package updates (only with --updates)
+    pub updates: Option<PendingUpdates>,
+    // Present only when a reboot is pending (Linux)
+    pub reboot_required: Option<RebootRequired>,
     pub mem_total: u64,
     pub mem_used: u64,
     pub swap_total: u64,
     pub swap_used: u64,
     pub hostname: String,
+    pub uptime_secs: u64,
+    // 1, 5 and 15 minute load averages; None where the OS has none (Windows)
+    pub load_avg: Option<(f64, f64, f64)>,
+    // Stable per-host id and per-boot id so clients can key history and detect reboots
+    pub machine_id: Option<String>,
+    pub boot_id: Option<String>,
+    // Agent instance on this host (`--instance NAME`); omitted for the default single agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    // Operator-assigned labels (`--label env=prod`), for grouping/filtering hosts
+    pub labels: BTreeMap<String, String>,
     pub cpu_temp_c: Option<f32>,
+    // cpufreq governor of all cores ("mixed" when they differ); omitted without cpufreq
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_governor: Option<String>,
     pub disks: Vec<DiskInfo>,
     pub networks: Vec<NetworkInfo>,
+    // When `networks` was read, in ms on the agent's monotonic clock (only differences mean
+    // anything), so clients compute rates over the agent's interval, not frame arrival times
+    pub sample_ms: u64,
     pub top_processes: Vec<ProcessInfo>,
     pub gpus: Option<Vec<GpuMetrics>>,
+    pub meta: SamplerMeta,
+    pub capabilities: Vec<&'static str>,
+    // Last error per collector (currently "gpu"); omitted when everything works
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub collector_errors: BTreeMap<&'static str, String>,
+}
+
+/// How long the last real (non-cached) collection took and how far apart collections ran.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CollectorTiming {
+    pub last_ms: f32,
+    pub interval_ms: Option<u64>,
+}
+
+/// Sampler cadence per payload type, so clients can tell when the agent can't keep up.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SamplerMeta {
+    pub metrics: CollectorTiming,
+    pub disks: CollectorTiming,
+    pub processes: CollectorTiming,
+    pub caches: CacheUsage,
+}
+
+/// Approximate bytes held by the agent's caches, against `--cache-memory-max`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheUsage {
+    pub names_bytes: u64,
+    pub history_bytes: u64,
+    pub buffer_bytes: u64,
+    pub limit_bytes: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -47,3 +350,14 @@ pub struct ProcessesPayload {
     pub process_count: usize,
     pub top_processes: Vec<ProcessInfo>,
 }
+
+/// `get_processes` as JSON (see `proc_format`), shaped like the protobuf message: long-tail
+/// sample rows from `tail=M` go in `tail` instead of being flagged.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessesJson {
+    pub schema_version: u32,
+    pub process_count: usize,
+    pub top_processes: Vec<ProcessInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tail: Vec<ProcessInfo>,
+}
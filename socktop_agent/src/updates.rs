@@ -0,0 +1,159 @@
+//! Optional slow collector (`--updates` / SOCKTOP_AGENT_UPDATES=1): pending package updates via
+//! apt, dnf or pacman. Checks run in the background at most hourly; metrics carry the last result.
+
+use once_cell::sync::OnceCell;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::PendingUpdates;
+
+const REFRESH: Duration = Duration::from_secs(3600);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct UpdatesCache {
+    at: Option<Instant>,
+    running: bool,
+    v: Option<PendingUpdates>,
+}
+static CACHE: OnceCell<Mutex<UpdatesCache>> = OnceCell::new();
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Last known result; kicks off a background refresh when stale. None when disabled or unknown.
+pub fn cached() -> Option<PendingUpdates> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let lock = CACHE.get_or_init(|| {
+        Mutex::new(UpdatesCache {
+            at: None,
+            running: false,
+            v: None,
+        })
+    });
+    let mut c = lock.lock().ok()?;
+    if !c.running && c.at.is_none_or(|t| t.elapsed() >= REFRESH) {
+        c.running = true;
+        tokio::task::spawn_blocking(|| {
+            let v = check();
+            if let Some(lock) = CACHE.get() {
+                if let Ok(mut c) = lock.lock() {
+                    c.v = v;
+                    c.at = Some(Instant::now());
+                    c.running = false;
+                }
+            }
+        });
+    }
+    c.v.clone()
+}
+
+fn run(cmd: &str, args: &[&str]) -> Option<std::process::Output> {
+    Command::new(cmd).args(args).output().ok()
+}
+
+/// Try each package manager in turn; the first one present wins.
+pub fn check() -> Option<PendingUpdates> {
+    // Ubuntu/Debian with update-notifier: "total;security" on stderr
+    if let Some(out) = run("/usr/lib/update-notifier/apt-check", &[]) {
+        if let Some((total, security)) = parse_apt_check(&String::from_utf8_lossy(&out.stderr)) {
+            return Some(PendingUpdates {
+                manager: "apt",
+                total,
+                security: Some(security),
+            });
+        }
+    }
+    if let Some(out) = run("apt-get", &["-s", "-q", "upgrade"]) {
+        if out.status.success() {
+            let (total, security) = parse_apt_simulate(&String::from_utf8_lossy(&out.stdout));
+            return Some(PendingUpdates {
+                manager: "apt",
+                total,
+                security: Some(security),
+            });
+        }
+    }
+    // dnf check-update exits 100 when updates are available, 0 when none
+    if let Some(out) = run("dnf", &["-q", "check-update"]) {
+        if matches!(out.status.code(), Some(0) | Some(100)) {
+            let total = parse_dnf_check_update(&String::from_utf8_lossy(&out.stdout));
+            let security = run("dnf", &["-q", "updateinfo", "list", "--security"])
+                .filter(|o| o.status.success())
+                .map(|o| count_lines(&String::from_utf8_lossy(&o.stdout)));
+            return Some(PendingUpdates {
+                manager: "dnf",
+                total,
+                security,
+            });
+        }
+    }
+    // Arch: checkupdates (pacman-contrib) exits 2 when there is nothing to update
+    if let Some(out) = run("checkupdates", &[]) {
+        if matches!(out.status.code(), Some(0) | Some(2)) {
+            return Some(PendingUpdates {
+                manager: "pacman",
+                total: count_lines(&String::from_utf8_lossy(&out.stdout)),
+                security: None,
+            });
+        }
+    }
+    None
+}
+
+fn count_lines(s: &str) -> u32 {
+    s.lines().filter(|l| !l.trim().is_empty()).count() as u32
+}
+
+/// `apt-check` output: `12;3`
+fn parse_apt_check(s: &str) -> Option<(u32, u32)> {
+    let (a, b) = s.trim().split_once(';')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+/// `apt-get -s upgrade`: one `Inst pkg [old] (new origin)` line per package.
+fn parse_apt_simulate(s: &str) -> (u32, u32) {
+    let inst: Vec<&str> = s.lines().filter(|l| l.starts_with("Inst ")).collect();
+    let security = inst.iter().filter(|l| l.contains("-security")).count();
+    (inst.len() as u32, security as u32)
+}
+
+/// `dnf -q check-update`: `name.arch version repo` rows; stops at the "Obsoleting" section.
+fn parse_dnf_check_update(s: &str) -> u32 {
+    s.lines()
+        .take_while(|l| !l.starts_with("Obsoleting"))
+        .filter(|l| l.split_whitespace().count() == 3)
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_package_manager_output() {
+        assert_eq!(parse_apt_check("12;3"), Some((12, 3)));
+        assert_eq!(parse_apt_check("oops"), None);
+
+        let sim = "Reading package lists...\n\
+                   Inst libssl3 [3.0.2-0ubuntu1.14] (3.0.2-0ubuntu1.15 Ubuntu:22.04/jammy-security [amd64])\n\
+                   Inst curl [7.81.0-1] (7.81.0-1ubuntu1.16 Ubuntu:22.04/jammy-updates [amd64])\n\
+                   Conf libssl3 (3.0.2-0ubuntu1.15 Ubuntu:22.04/jammy-security [amd64])\n";
+        assert_eq!(parse_apt_simulate(sim), (2, 1));
+
+        let dnf = "\nkernel.x86_64    6.8.9-300.fc40    updates\n\
+                   curl.x86_64      8.6.0-8.fc40      updates\n\
+                   Obsoleting Packages\n\
+                   foo.noarch       1.0-1             updates\n";
+        assert_eq!(parse_dnf_check_update(dnf), 2);
+    }
+}
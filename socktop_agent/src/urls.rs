@@ -0,0 +1,135 @@
+//! Ready-to-copy client connection strings (--print-url), optionally rendered as QR codes (--qr).
+
+use std::net::IpAddr;
+use sysinfo::Networks;
+
+/// Non-loopback, non-link-local addresses the agent is likely reachable on.
+pub fn reachable_addrs() -> Vec<IpAddr> {
+    let networks = Networks::new_with_refreshed_list();
+    let mut out: Vec<IpAddr> = networks
+        .values()
+        .flat_map(|data| data.ip_networks().iter().map(|n| n.addr))
+        .filter(|ip| !ip.is_loopback() && !ip.is_unspecified() && !is_link_local(ip))
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn is_link_local(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+/// Build the `socktop ...` command line for one address.
+pub fn client_command(
+    ip: IpAddr,
+    port: u16,
+    fingerprint: Option<&str>,
+    token: Option<&str>,
+) -> String {
+    let scheme = if fingerprint.is_some() { "wss" } else { "ws" };
+    let host = match ip {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => format!("[{v6}]"),
+    };
+    let mut url = format!("{scheme}://{host}:{port}/ws");
+    if let Some(t) = token {
+        url.push_str("?token=");
+        url.push_str(&percent_encode(t));
+    }
+    match fingerprint {
+        Some(fp) => format!("socktop --tls-fingerprint {fp} \"{url}\""),
+        None => format!("socktop \"{url}\""),
+    }
+}
+
+// Everything but RFC 3986 unreserved characters, so any token survives the query string
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+pub fn print_connect_urls(port: u16, fingerprint: Option<&str>, token: Option<&str>, qr: bool) {
+    let addrs = reachable_addrs();
+    if addrs.is_empty() {
+        println!(
+            "socktop_agent: no non-loopback addresses found; connect via ws://localhost:{port}/ws"
+        );
+        return;
+    }
+    println!("socktop_agent: connect with:");
+    for ip in addrs {
+        let cmd = client_command(ip, port, fingerprint, token);
+        println!("  {cmd}");
+        if qr {
+            print_qr(&cmd);
+        }
+    }
+    if token.is_some() {
+        println!("socktop_agent: these commands contain the token; it ends up in shell history and logs wherever they are run");
+    }
+}
+
+#[cfg(feature = "qr")]
+fn print_qr(data: &str) {
+    use qrcode::render::unicode::Dense1x2;
+    match qrcode::QrCode::new(data.as_bytes()) {
+        Ok(code) => {
+            let img = code
+                .render::<Dense1x2>()
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .build();
+            println!("{img}");
+        }
+        Err(e) => eprintln!("socktop_agent: QR encoding failed: {e}"),
+    }
+}
+
+#[cfg(not(feature = "qr"))]
+fn print_qr(_data: &str) {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    WARNED.call_once(|| {
+        eprintln!("socktop_agent: --qr requires building with `--features qr`");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn command_shapes() {
+        let v4 = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 5));
+        assert_eq!(
+            client_command(v4, 3000, None, None),
+            "socktop \"ws://192.168.1.5:3000/ws\""
+        );
+        assert_eq!(
+            client_command(v4, 8443, Some("ab12"), Some("s3cret")),
+            "socktop --tls-fingerprint ab12 \"wss://192.168.1.5:8443/ws?token=s3cret\""
+        );
+        assert_eq!(
+            client_command(v4, 3000, None, Some("a b&c=d/+")),
+            "socktop \"ws://192.168.1.5:3000/ws?token=a%20b%26c%3Dd%2F%2B\""
+        );
+        let v6 = IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1));
+        assert_eq!(
+            client_command(v6, 3000, None, None),
+            "socktop \"ws://[fd00::1]:3000/ws\""
+        );
+        assert!(is_link_local(&IpAddr::V6(Ipv6Addr::new(
+            0xfe80, 0, 0, 0, 0, 0, 0, 1
+        ))));
+    }
+}
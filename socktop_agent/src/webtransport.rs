@@ -0,0 +1,186 @@
+//! Experimental WebTransport (HTTP/3 over QUIC) listener, feature `webtransport`.
+//!
+//! Same commands and reply bytes as `/ws`, one bidirectional stream per request: the client
+//! writes the command and finishes its side; the agent answers with a kind byte (`0` text,
+//! `1` binary) followed by the reply body, then finishes. Requests don't queue behind each
+//! other, so a lost packet only stalls its own stream instead of the whole connection.
+//!
+//! Clients pin the listener's certificate by hash, which WebTransport only allows for
+//! certificates valid 14 days or less. The listener therefore has its own self-signed cert
+//! (`webtransport-cert.pem` next to the TLS cert), made at start and replaced every
+//! `ROTATE_AFTER` without dropping open sessions; clients pinning it need the new hash.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+use wtransport::{Endpoint, Identity, ServerConfig};
+
+use crate::access_log::Request;
+use crate::auth_guard;
+use crate::relay::Reply;
+use crate::sessions::Conn;
+use crate::state::AppState;
+use crate::tls;
+use crate::ws::respond_logged;
+
+// Commands are short text lines; anything longer is not a socktop client
+const MAX_REQUEST: u64 = 4 * 1024;
+// A day before the certificate runs out
+const ROTATE_AFTER: Duration =
+    Duration::from_secs((tls::WEBTRANSPORT_CERT_DAYS as u64 - 1) * 86400);
+
+pub const KIND_TEXT: u8 = 0;
+pub const KIND_BINARY: u8 = 1;
+
+/// Listen on UDP `port`, keeping the listener's certificate in `cert_dir`.
+pub async fn serve(state: AppState, port: u16, cert_dir: PathBuf) -> anyhow::Result<()> {
+    let server = Endpoint::server(config(port, &cert_dir).await?)?;
+    println!("socktop_agent: WebTransport (experimental) on https://0.0.0.0:{port}/ws");
+    let mut rotate =
+        tokio::time::interval_at(tokio::time::Instant::now() + ROTATE_AFTER, ROTATE_AFTER);
+    loop {
+        let incoming = tokio::select! {
+            incoming = server.accept() => incoming,
+            _ = rotate.tick() => {
+                let reloaded = match config(port, &cert_dir).await {
+                    Ok(c) => server.reload_config(c, false).map_err(anyhow::Error::from),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = reloaded {
+                    tracing::error!("webtransport: can't replace the certificate: {e}");
+                }
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let Ok(request) = incoming.await else {
+                return;
+            };
+            // Same optional token as the WebSocket endpoint: Authorization header or ?token=
+            let header = request.headers().get("authorization").map(String::as_str);
+            let token = auth_guard::bearer(header).or_else(|| {
+                request
+                    .path()
+                    .split_once('?')
+                    .and_then(|(_, q)| q.split('&').find_map(|kv| kv.strip_prefix("token=")))
+            });
+            let client = request.remote_address();
+            let Some(scope) = auth_guard::admit(&state, "webtransport", client, token).await else {
+                request.forbidden().await;
+                return;
+            };
+            let Ok(conn) = request.accept().await else {
+                return;
+            };
+            let session = Conn::new(scope);
+            while let Ok((mut send, recv)) = conn.accept_bi().await {
+                let (state, session) = (state.clone(), session.clone());
+                tokio::spawn(async move {
+                    let mut cmd = Vec::new();
+                    if recv.take(MAX_REQUEST).read_to_end(&mut cmd).await.is_err() {
+                        return;
+                    }
+                    let Ok(cmd) = String::from_utf8(cmd) else {
+                        return;
+                    };
+                    let req = Request {
+                        transport: "webtransport",
+                        client: Some(client),
+                        upstream: None,
+                        command: cmd.trim(),
+                    };
+                    if let Some(reply) = respond_logged(&state, &session, &req).await {
+                        let (kind, body) = match reply {
+                            Reply::Text(t) => (KIND_TEXT, t.into_bytes()),
+                            Reply::Binary(b) => (KIND_BINARY, b),
+                        };
+                        if send.write_all(&[kind]).await.is_ok()
+                            && send.write_all(&body).await.is_ok()
+                        {
+                            let _ = send.finish().await;
+                        }
+                    }
+                });
+            }
+            session.close(&state.sessions);
+        });
+    }
+}
+
+// Server config with a freshly made certificate, announced with the hash clients pin
+async fn config(port: u16, cert_dir: &Path) -> anyhow::Result<ServerConfig> {
+    let (cert, key) = tls::fresh_webtransport_cert(cert_dir)?;
+    println!(
+        "socktop_agent: WebTransport certificate {} (SHA-256 {}, valid {} days)",
+        cert.display(),
+        tls::cert_fingerprint(&cert)?,
+        tls::WEBTRANSPORT_CERT_DAYS
+    );
+    let identity = Identity::load_pemfiles(&cert, &key).await?;
+    Ok(ServerConfig::builder()
+        .with_bind_default(port)
+        .with_identity(identity)
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wtransport::tls::Sha256Digest;
+    use wtransport::ClientConfig;
+
+    #[tokio::test]
+    async fn client_pinning_the_short_lived_cert_gets_replies() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::new();
+        state.auth_token = None;
+        state.read_token = None;
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        tokio::spawn(serve(state, port, dir.path().to_path_buf()));
+
+        let cert = dir.path().join("webtransport-cert.pem");
+        let mut fingerprint = None;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if let Ok(fp) = tls::cert_fingerprint(&cert) {
+                fingerprint = Some(fp);
+                break;
+            }
+        }
+        let fingerprint = fingerprint.expect("listener wrote no certificate");
+        let digest: Vec<u8> = (0..fingerprint.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&fingerprint[i..i + 2], 16).unwrap())
+            .collect();
+        let hash = Sha256Digest::new(digest.try_into().unwrap());
+
+        // wtransport refuses hash pinning for certificates valid longer than 14 days
+        let config = ClientConfig::builder()
+            .with_bind_default()
+            .with_server_certificate_hashes([hash])
+            .build();
+        let exchange = async {
+            let conn = Endpoint::client(config)?
+                .connect(format!("https://127.0.0.1:{port}/ws"))
+                .await?;
+            let (mut send, mut recv) = conn.open_bi().await?.await?;
+            send.write_all(b"get_disks").await?;
+            send.finish().await?;
+            let mut reply = Vec::new();
+            AsyncReadExt::read_to_end(&mut recv, &mut reply).await?;
+            anyhow::Ok(reply)
+        };
+        let reply = tokio::time::timeout(Duration::from_secs(10), exchange)
+            .await
+            .expect("timed out")
+            .unwrap();
+        assert!(matches!(reply.first(), Some(&(KIND_TEXT | KIND_BINARY))));
+        assert!(reply.len() > 1);
+    }
+}
@@ -1,23 +1,43 @@
-//! WebSocket upgrade and per-connection handler (request-driven).
+//! WebSocket upgrade and per-connection handler (request-driven, or pushed after `subscribe`).
 
 use axum::{
     extract::ws::{Message, WebSocket},
-    extract::{Query, State, WebSocketUpgrade},
+    extract::{ConnectInfo, Query, State, WebSocketUpgrade},
+    http::{header::AUTHORIZATION, HeaderMap},
     response::Response,
 };
-use flate2::{write::GzEncoder, Compression};
 use futures_util::StreamExt;
 use once_cell::sync::OnceCell;
 use std::collections::HashMap;
-use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Instant;
 use tokio::sync::Mutex;
 
-use crate::metrics::{collect_disks, collect_fast_metrics, collect_processes_all};
+use crate::access_log::Request;
+use crate::auth_guard;
+use crate::cache_budget::{self, Cache};
+use crate::compression;
+use crate::cpufreq;
+use crate::disk_delta;
+use crate::framing;
+use crate::history::{export, ExportFormat, HistoryReply, EXPORT_ERROR_PREFIX, HISTORY_STEP};
+use crate::io_wait;
+use crate::limits;
+use crate::metrics::{capabilities, collect_disks, collect_fast_metrics, collect_processes_all};
+use crate::peers;
+use crate::proc_format::ProcFormat;
+use crate::proc_inspect;
 use crate::proto::pb;
+use crate::push::{self, Plan, Schedule};
+use crate::relay::{collect_fleet, FleetEntry, Reply, SharedRelay};
+use crate::self_limit;
+use crate::sessions::Conn;
 use crate::state::AppState;
-
-// Compression threshold based on typical payload size
-const COMPRESSION_THRESHOLD: usize = 768;
+use crate::types::{
+    CpuFreq, DiskInfo, DisksDelta, Hello, ProcessesJson, ProcessesPayload, Scope, SubscribeAck,
+    SCHEMA_VERSION,
+};
 
 // Reusable buffer for compression to avoid allocations
 struct CompressionCache {
@@ -37,99 +57,561 @@ static COMPRESSION_CACHE: OnceCell<Mutex<CompressionCache>> = OnceCell::new();
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     Query(q): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> Response {
-    // optional auth
-    if let Some(expected) = state.auth_token.as_ref() {
-        if q.get("token") != Some(expected) {
+    // optional auth; the token also decides what this connection may do
+    let header = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let token = auth_guard::bearer(header).or(q.get("token").map(String::as_str));
+    let Some(scope) = auth_guard::admit(&state, "ws", client, token).await else {
+        return ws.on_upgrade(|socket| async move {
+            let _ = socket.close().await;
+        });
+    };
+    // Checked binary frames (`frames=crc`), for clients behind proxies that damage frames
+    let checked = framing::requested(q.get("frames").map(String::as_str));
+    // Relay mode: proxy this connection to one leaf agent
+    if let (Some(relay), Some(name)) = (state.relay.clone(), q.get("upstream")) {
+        if relay.get(name).is_none() {
             return ws.on_upgrade(|socket| async move {
                 let _ = socket.close().await;
             });
         }
+        let name = name.clone();
+        return ws.on_upgrade(move |socket| {
+            handle_relay_socket(socket, state, relay, name, client, scope, checked)
+        });
     }
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    ws.on_upgrade(move |socket| handle_socket(socket, state, client, Conn::new(scope), checked))
 }
 
-async fn handle_socket(mut socket: WebSocket, state: AppState) {
-    state
-        .client_count
-        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+async fn handle_relay_socket(
+    mut socket: WebSocket,
+    state: AppState,
+    relay: SharedRelay,
+    name: String,
+    client: SocketAddr,
+    scope: Scope,
+    checked: bool,
+) {
+    let Some(upstream) = relay.get(&name) else {
+        return;
+    };
     while let Some(Ok(msg)) = socket.next().await {
         match msg {
-            Message::Text(ref text) if text == "get_metrics" => {
-                let m = collect_fast_metrics(&state).await;
-                let _ = send_json(&mut socket, &m).await;
+            // The leaf link is request/response only: pushes would land in later replies
+            Message::Text(text) if push::is_command(&text) => {
+                let ack = SubscribeAck {
+                    error: Some("subscribe is not available through a relay".into()),
+                    ..push::ack(None)
+                };
+                let _ = socket.send(to_message(json_reply(&ack), checked)).await;
             }
-            Message::Text(ref text) if text == "get_disks" => {
-                let d = collect_disks(&state).await;
-                let _ = send_json(&mut socket, &d).await;
+            // The leaf trusts the relay's token: refuse control commands here, not there
+            Message::Text(text) if scope != Scope::Control && control_only(&text) => {
+                let _ = socket.send(to_message(relay_refusal(&text), checked)).await;
             }
-            Message::Text(ref text) if text == "get_processes" => {
-                let payload = collect_processes_all(&state).await;
-
-                // Map to protobuf message
-                // Get cached buffers
-                let cache = COMPRESSION_CACHE.get_or_init(|| Mutex::new(CompressionCache::new()));
-                let mut cache = cache.lock().await;
-
-                // Reuse process vector to build the list
-                cache.processes_vec.clear();
-                cache
-                    .processes_vec
-                    .extend(payload.top_processes.into_iter().map(|p| pb::Process {
-                        pid: p.pid,
-                        name: p.name,
-                        cpu_usage: p.cpu_usage,
-                        mem_bytes: p.mem_bytes,
-                    }));
-
-                let pb = pb::Processes {
-                    process_count: payload.process_count as u64,
-                    rows: std::mem::take(&mut cache.processes_vec),
-                };
-
-                let mut buf = Vec::with_capacity(8 * 1024);
-                if prost::Message::encode(&pb, &mut buf).is_err() {
-                    let _ = socket.send(Message::Close(None)).await;
-                } else {
-                    // compress if large
-                    if buf.len() <= COMPRESSION_THRESHOLD {
-                        let _ = socket.send(Message::Binary(buf)).await;
+            Message::Text(text) => match timed(upstream.request(&text)).await {
+                (Some(reply), elapsed) => {
+                    let reply = if text == "hello" || text.starts_with("hello?") {
+                        with_scope(reply, scope)
                     } else {
-                        // Create a new encoder for each message to ensure proper gzip headers
-                        let mut encoder =
-                            GzEncoder::new(Vec::with_capacity(buf.len()), Compression::fast());
-                        match encoder.write_all(&buf).and_then(|_| encoder.finish()) {
-                            Ok(compressed) => {
-                                let _ = socket.send(Message::Binary(compressed)).await;
-                            }
-                            Err(_) => {
-                                let _ = socket.send(Message::Binary(buf)).await;
-                            }
-                        }
+                        reply
+                    };
+                    if let Some(log) = state.access_log.as_deref() {
+                        let req = Request {
+                            transport: "ws",
+                            client: Some(client),
+                            upstream: Some(&name),
+                            command: &text,
+                        };
+                        log.record(&req, elapsed, reply.wire_len());
                     }
+                    let _ = socket.send(to_message(reply, checked)).await;
+                }
+                // Leaf unreachable: close so the client can reconnect/report
+                (None, _) => {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+}
+
+const CONTROL_COMMANDS: &[&str] = &["get_process_env?", "get_process_fds?", "set_governor?"];
+
+// Commands that need a control-scope token
+fn control_only(text: &str) -> bool {
+    CONTROL_COMMANDS.iter().any(|c| text.starts_with(c))
+}
+
+// The relay's answer to a control command from a read-scope connection
+fn relay_refusal(text: &str) -> Reply {
+    if let Some(query) = text.strip_prefix("get_process_env?") {
+        return json_reply(&proc_inspect::env_reply(Scope::Read, query));
+    }
+    if let Some(query) = text.strip_prefix("get_process_fds?") {
+        return json_reply(&proc_inspect::fds_reply(Scope::Read, query));
+    }
+    // Not the local cores: this connection is looking at the leaf
+    json_reply(&CpuFreq {
+        schema_version: SCHEMA_VERSION,
+        cores: Vec::new(),
+        available: Vec::new(),
+        control: false,
+        error: Some("needs a control-scope token".into()),
+    })
+}
+
+// The leaf's `hello` reports the relay's scope; the caller gets its own
+fn with_scope(reply: Reply, scope: Scope) -> Reply {
+    let json = match &reply {
+        Reply::Text(t) => Some(t.clone().into_bytes()),
+        Reply::Binary(b) => compression::decompress(b),
+    };
+    let Some(mut v) = json.and_then(|j| serde_json::from_slice::<serde_json::Value>(&j).ok())
+    else {
+        return reply;
+    };
+    match v.get_mut("scope") {
+        Some(s) => *s = serde_json::json!(scope),
+        None => return reply,
+    }
+    json_reply(&v)
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    state: AppState,
+    client: SocketAddr,
+    conn: Conn,
+    checked: bool,
+) {
+    state
+        .client_count
+        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut schedule: Option<Schedule> = None;
+    loop {
+        let msg = tokio::select! {
+            msg = socket.next() => msg,
+            kind = push::next(&mut schedule) => {
+                let Some(s) = schedule.as_ref() else { continue };
+                let req = Request {
+                    transport: "ws",
+                    client: Some(client),
+                    upstream: None,
+                    command: s.plan.command(kind),
+                };
+                if let Some(reply) = respond_logged(&state, &conn, &req).await {
+                    let frame = Reply::Binary(push::wrap(kind, reply));
+                    if socket.send(to_message(frame, checked)).await.is_err() {
+                        break;
+                    }
+                }
+                continue;
+            }
+        };
+        let Some(Ok(msg)) = msg else { break };
+        match msg {
+            Message::Text(ref text) if push::is_command(text) => {
+                let plan = Plan::parse(text);
+                let ack = push::ack(plan.as_ref());
+                schedule = plan.map(Schedule::new);
+                let _ = socket.send(to_message(json_reply(&ack), checked)).await;
+            }
+            Message::Text(ref text) => {
+                let req = Request {
+                    transport: "ws",
+                    client: Some(client),
+                    upstream: None,
+                    command: text,
+                };
+                if let Some(reply) = respond_logged(&state, &conn, &req).await {
+                    let _ = socket.send(to_message(reply, checked)).await;
                 }
-                drop(cache); // Explicit drop to release mutex early
             }
             Message::Close(_) => break,
             _ => {}
         }
     }
+    conn.close(&state.sessions);
     state
         .client_count
         .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
 }
 
-// Small, cheap gzip for larger payloads; send text for small.
-async fn send_json<T: serde::Serialize>(ws: &mut WebSocket, value: &T) -> Result<(), axum::Error> {
+/// `respond`, plus an access-log line when `--access-log` is on.
+pub async fn respond_logged(state: &AppState, conn: &Conn, req: &Request<'_>) -> Option<Reply> {
+    // --cpu-limit without a cgroup: hold requests back while over budget
+    self_limit::throttle().await;
+    let (reply, elapsed) = timed(respond(state, conn, req.command)).await;
+    if let Some(log) = state.access_log.as_deref() {
+        log.record(req, elapsed, reply.as_ref().map_or(0, Reply::wire_len));
+    }
+    reply
+}
+
+async fn timed<T>(fut: impl std::future::Future<Output = T>) -> (T, std::time::Duration) {
+    let started = Instant::now();
+    let out = fut.await;
+    (out, started.elapsed())
+}
+
+/// Answer one text command. Shared by every transport (WebSocket, `/poll`, and WebTransport
+/// when built with that feature); None for unknown commands, which are ignored. `conn` carries what the
+/// connection's token allows and its session.
+pub async fn respond(state: &AppState, conn: &Conn, text: &str) -> Option<Reply> {
+    match text {
+        _ if text == "hello" || text.starts_with("hello?") => {
+            let now = Instant::now();
+            let query = text.strip_prefix("hello?").unwrap_or_default();
+            let resume = query.split('&').find_map(|kv| kv.strip_prefix("resume="));
+            let resumed = resume.is_some_and(|id| state.sessions.resume(id, conn.scope, now));
+            let session = match resume {
+                Some(id) if resumed => Some(id.to_string()),
+                _ => state.sessions.open(conn.scope, now),
+            };
+            // A second hello on one connection moves it to the new session
+            conn.close(&state.sessions);
+            if let Some(id) = &session {
+                conn.set_session(id.clone());
+                if let Some(fmt) = ProcFormat::from_query(query, "processes") {
+                    state.sessions.set_processes(id, fmt);
+                }
+            }
+            let processes = session
+                .as_deref()
+                .and_then(|id| state.sessions.processes(id))
+                .unwrap_or_else(ProcFormat::fallback);
+            let error = session
+                .is_none()
+                .then(|| "too many sessions; this connection has none".to_string());
+            Some(json_reply(&Hello {
+                schema_version: SCHEMA_VERSION,
+                version: env!("CARGO_PKG_VERSION"),
+                scope: conn.scope,
+                capabilities: capabilities(),
+                session,
+                resumed,
+                processes: processes.label(),
+                error,
+            }))
+        }
+        "get_metrics" => {
+            let m = collect_fast_metrics(state).await;
+            Some(json_reply(&m))
+        }
+        "get_disks" => Some(json_reply(&capped_disks(state).await)),
+        _ if text.starts_with("get_disks?") => {
+            let d = capped_disks(state).await;
+            let Some(seq) = disk_delta::parse_query(&text["get_disks?".len()..]) else {
+                return Some(json_reply(&d));
+            };
+            // Without a session (no hello yet) there is no baseline: always a full list
+            let delta = conn
+                .session()
+                .and_then(|id| state.sessions.with_disks(&id, |b| b.diff(d.clone(), seq)))
+                .unwrap_or(DisksDelta {
+                    schema_version: SCHEMA_VERSION,
+                    seq: 0,
+                    full: true,
+                    disks: d,
+                    removed: Vec::new(),
+                });
+            Some(json_reply(&delta))
+        }
+        "get_processes" => {
+            let payload = collect_processes_all(state).await;
+            match processes_format(state, conn, "") {
+                ProcFormat::Protobuf => encode_processes(payload, None).await.map(Reply::Binary),
+                ProcFormat::Json => Some(processes_json(payload, None)),
+            }
+        }
+        _ if text.starts_with("get_processes?") => {
+            let query = &text["get_processes?".len()..];
+            let mut payload = collect_processes_all(state).await;
+            let sampled_from = apply_top_k(&mut payload, query);
+            match processes_format(state, conn, query) {
+                ProcFormat::Protobuf => encode_processes(payload, sampled_from)
+                    .await
+                    .map(Reply::Binary),
+                ProcFormat::Json => Some(processes_json(payload, sampled_from)),
+            }
+        }
+        "get_history" => {
+            let body = state.history.lock().ok().and_then(|h| {
+                serde_json::to_value(HistoryReply {
+                    schema_version: SCHEMA_VERSION,
+                    step_secs: HISTORY_STEP.as_secs(),
+                    points: h.points(),
+                })
+                .ok()
+            });
+            body.map(|v| json_reply(&v))
+        }
+        _ if text.starts_with("get_history?") => {
+            // Offline export: CSV as a text frame, Parquet as a raw binary frame
+            let query = &text["get_history?".len()..];
+            let reply = match export_format(query) {
+                Some(fmt) => state
+                    .history
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("history unavailable"))
+                    .and_then(|h| export(h.points(), fmt))
+                    .map(|body| match fmt {
+                        ExportFormat::Csv => {
+                            Reply::Text(String::from_utf8(body).unwrap_or_default())
+                        }
+                        ExportFormat::Parquet => Reply::Binary(body),
+                    }),
+                None => Err(anyhow::anyhow!("format must be csv or parquet")),
+            };
+            Some(reply.unwrap_or_else(|e| Reply::Text(format!("{EXPORT_ERROR_PREFIX}{e}"))))
+        }
+        _ if text.starts_with("get_process_env?") => Some(json_reply(&proc_inspect::env_reply(
+            conn.scope,
+            &text["get_process_env?".len()..],
+        ))),
+        _ if text.starts_with("get_process_fds?") => Some(json_reply(&proc_inspect::fds_reply(
+            conn.scope,
+            &text["get_process_fds?".len()..],
+        ))),
+        "get_cpufreq" => Some(json_reply(&cpufreq::reply(Path::new(cpufreq::CPU_DIR)))),
+        _ if text.starts_with("set_governor?") => Some(json_reply(&cpufreq::set_reply(
+            Path::new(cpufreq::CPU_DIR),
+            conn.scope,
+            &text["set_governor?".len()..],
+        ))),
+        "get_io" => {
+            // Walks every /proc/PID/io: keep it off the async workers
+            let report = tokio::task::spawn_blocking(io_wait::report).await.ok()?;
+            Some(json_reply(&report))
+        }
+        _ if text == "get_peers" || text.starts_with("get_peers?") => Some(json_reply(
+            &peers::reply(state, conn.scope, text.strip_prefix("get_peers?")),
+        )),
+        _ if text == "get_fleet" || text.starts_with("get_fleet?") => {
+            // Local host first, then every relayed leaf (empty when not relaying)
+            let local = collect_fast_metrics(state).await;
+            let mut fleet = vec![FleetEntry {
+                host: state.hostname.clone(),
+                instance: state.instance.clone(),
+                ok: true,
+                labels: (*state.labels).clone(),
+                metrics: serde_json::to_value(&local).ok(),
+            }];
+            if let Some(relay) = state.relay.as_ref() {
+                fleet.extend(collect_fleet(relay).await);
+            }
+            if let Some(query) = text.strip_prefix("get_fleet?") {
+                let wanted = label_filter(query);
+                fleet.retain(|e| e.matches(&wanted));
+            }
+            Some(json_reply(&fleet))
+        }
+        _ => None,
+    }
+}
+
+// Disks with names capped like process names
+async fn capped_disks(state: &AppState) -> Vec<DiskInfo> {
+    let mut d = collect_disks(state).await;
+    let max_name = limits::max_name_len();
+    for disk in &mut d {
+        limits::truncate_name(&mut disk.name, max_name);
+    }
+    d
+}
+
+fn to_message(reply: Reply, checked: bool) -> Message {
+    match reply {
+        Reply::Text(t) => Message::Text(t),
+        Reply::Binary(b) if checked => Message::Binary(framing::wrap(&b)),
+        Reply::Binary(b) => Message::Binary(b),
+    }
+}
+
+// Encode processes as protobuf (gzip when large) for a binary frame. Rows from
+// `sampled_from` on are flagged as long-tail samples.
+async fn encode_processes(
+    payload: ProcessesPayload,
+    sampled_from: Option<usize>,
+) -> Option<Vec<u8>> {
+    // Get cached buffers
+    let cache = COMPRESSION_CACHE.get_or_init(|| Mutex::new(CompressionCache::new()));
+    let mut cache = cache.lock().await;
+
+    // Reuse process vector to build the list (names capped so one huge argv can't bloat it)
+    let max_name = limits::max_name_len();
+    cache.processes_vec.clear();
+    cache
+        .processes_vec
+        .extend(payload.top_processes.into_iter().enumerate().map(|(i, p)| {
+            let mut name = p.name;
+            limits::truncate_name(&mut name, max_name);
+            pb::Process {
+                pid: p.pid,
+                name,
+                cpu_usage: p.cpu_usage,
+                mem_bytes: p.mem_bytes,
+                cpu_time_ms: p.cpu_time_ms,
+                sampled: sampled_from.is_some_and(|s| i >= s),
+            }
+        }));
+
+    let mut pb = pb::Processes {
+        schema_version: SCHEMA_VERSION,
+        process_count: payload.process_count as u64,
+        rows: std::mem::take(&mut cache.processes_vec),
+    };
+    drop(cache); // Release the mutex before encoding/compressing
+
+    let mut buf = Vec::with_capacity(8 * 1024);
+    loop {
+        buf.clear();
+        prost::Message::encode(&pb, &mut buf).ok()?;
+        // Over --max-payload-bytes: drop the lightest rows (process_count stays the total)
+        match limits::rows_to_keep(pb.rows.len(), buf.len(), limits::max_payload_bytes()) {
+            Some(keep) => limits::keep_heaviest(&mut pb.rows, keep),
+            None => break,
+        }
+    }
+
+    // Hand the row buffer back for the next reply unless it no longer fits the cache budget
+    let mut rows = pb.rows;
+    rows.clear();
+    let row_bytes = std::mem::size_of::<pb::Process>();
+    if rows.capacity() * row_bytes > cache_budget::room(Cache::Buffers) {
+        rows = Vec::new();
+    }
+    cache_budget::set_used(Cache::Buffers, rows.capacity() * row_bytes);
+    COMPRESSION_CACHE
+        .get_or_init(|| Mutex::new(CompressionCache::new()))
+        .lock()
+        .await
+        .processes_vec = rows;
+
+    Some(gzip_if_large(buf))
+}
+
+// `format=` on the request, else what the session asked for in hello, else the agent default
+fn processes_format(state: &AppState, conn: &Conn, query: &str) -> ProcFormat {
+    ProcFormat::from_query(query, "format")
+        .or_else(|| conn.session().and_then(|id| state.sessions.processes(&id)))
+        .unwrap_or_else(ProcFormat::fallback)
+}
+
+// Processes as a JSON reply for clients that can't read the protobuf. Names and size are
+// capped like `encode_processes`; over the size cap the tail sample goes first.
+fn processes_json(payload: ProcessesPayload, sampled_from: Option<usize>) -> Reply {
+    let max_name = limits::max_name_len();
+    let mut rows = payload.top_processes;
+    for p in &mut rows {
+        limits::truncate_name(&mut p.name, max_name);
+    }
+    let tail = sampled_from.map_or_else(Vec::new, |s| rows.split_off(s.min(rows.len())));
+    let mut reply = ProcessesJson {
+        schema_version: SCHEMA_VERSION,
+        process_count: payload.process_count,
+        top_processes: rows,
+        tail,
+    };
+    loop {
+        let encoded = serde_json::to_vec(&reply).map_or(0, |v| v.len());
+        let rows = reply.top_processes.len() + reply.tail.len();
+        match limits::rows_to_keep(rows, encoded, limits::max_payload_bytes()) {
+            Some(_) if !reply.tail.is_empty() => reply.tail.clear(),
+            Some(keep) => {
+                reply.top_processes.sort_unstable_by(|a, b| {
+                    b.cpu_usage
+                        .total_cmp(&a.cpu_usage)
+                        .then(b.mem_bytes.cmp(&a.mem_bytes))
+                        .then(a.pid.cmp(&b.pid))
+                });
+                reply.top_processes.truncate(keep);
+            }
+            None => break,
+        }
+    }
+    json_reply(&reply)
+}
+
+// Compress protobuf payloads above the threshold; falls back to the raw bytes on error.
+fn gzip_if_large(buf: Vec<u8>) -> Vec<u8> {
+    compression::compress(&buf).unwrap_or(buf)
+}
+
+/// `get_processes?top=K&sort=cpu|mem|time`: keep only the K heaviest rows (process_count stays
+/// the total). `&tail=M&after=PID` appends M more rows from the rest in PID order, starting
+/// after PID and wrapping, so a client that moves `after` along sees every process in turn.
+/// Returns the index the tail rows start at when a tail was asked for.
+fn apply_top_k(payload: &mut ProcessesPayload, query: &str) -> Option<usize> {
+    let mut top: Option<usize> = None;
+    let mut sort = "cpu";
+    let mut tail: Option<usize> = None;
+    let mut after = 0u32;
+    for kv in query.split('&') {
+        match kv.split_once('=') {
+            Some(("top", v)) => top = v.parse().ok(),
+            Some(("sort", v)) => sort = v,
+            Some(("tail", v)) => tail = v.parse().ok(),
+            Some(("after", v)) => after = v.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    let k = top?;
+    let rows = &mut payload.top_processes;
+    // Tie-break on the other key, then PID, so equal rows don't flip in and out of the top K
+    rows.sort_unstable_by(|a, b| {
+        let cpu = b.cpu_usage.total_cmp(&a.cpu_usage);
+        let mem = b.mem_bytes.cmp(&a.mem_bytes);
+        let primary = match sort {
+            "mem" => mem.then(cpu),
+            "time" => b.cpu_time_ms.cmp(&a.cpu_time_ms).then(cpu),
+            _ => cpu.then(mem),
+        };
+        primary.then(a.pid.cmp(&b.pid))
+    });
+    let mut rest = rows.split_off(k.min(rows.len()));
+    let m = tail?;
+    rest.sort_unstable_by_key(|p| p.pid);
+    let start = rest.partition_point(|p| p.pid <= after);
+    rest.rotate_left(start);
+    rest.truncate(m);
+    let sampled_from = rows.len();
+    rows.extend(rest);
+    Some(sampled_from)
+}
+
+/// `label=env=prod&label=rack=12` -> [("env","prod"), ("rack","12")]
+fn label_filter(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter_map(|kv| kv.strip_prefix("label="))
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// `get_history?format=csv|parquet` -> export format (None for anything else)
+fn export_format(query: &str) -> Option<ExportFormat> {
+    query
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("format="))
+        .and_then(ExportFormat::parse)
+}
+
+// Gzip for larger payloads (per --compression-level/threshold); send text for small.
+fn json_reply<T: serde::Serialize>(value: &T) -> Reply {
     let json = serde_json::to_string(value).expect("serialize");
-    if json.len() <= COMPRESSION_THRESHOLD {
-        return ws.send(Message::Text(json)).await;
+    match compression::compress(json.as_bytes()) {
+        Some(bin) => Reply::Binary(bin),
+        None => Reply::Text(json),
     }
-    let mut enc = GzEncoder::new(Vec::new(), Compression::fast());
-    enc.write_all(json.as_bytes()).ok();
-    let bin = enc.finish().unwrap_or_else(|_| json.into_bytes());
-    ws.send(Message::Binary(bin)).await
 }
 
 #[cfg(test)]
@@ -138,6 +620,369 @@ mod tests {
     use prost::Message as ProstMessage;
     use sysinfo::System;
 
+    use crate::types::ProcessInfo;
+
+    fn proc_row(pid: u32, cpu: f32, mem: u64) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: format!("p{pid}"),
+            cpu_usage: cpu,
+            mem_bytes: mem,
+            cpu_time_ms: 0,
+        }
+    }
+
+    #[test]
+    fn top_k_truncates_by_requested_key_and_keeps_total() {
+        let rows = vec![
+            proc_row(1, 5.0, 300),
+            proc_row(2, 50.0, 100),
+            proc_row(3, 20.0, 200),
+        ];
+        let mut p = ProcessesPayload {
+            process_count: 3,
+            top_processes: rows.clone(),
+        };
+        apply_top_k(&mut p, "top=2");
+        let pids: Vec<u32> = p.top_processes.iter().map(|r| r.pid).collect();
+        assert_eq!(pids, vec![2, 3]);
+        assert_eq!(p.process_count, 3);
+
+        let mut p = ProcessesPayload {
+            process_count: 3,
+            top_processes: rows,
+        };
+        apply_top_k(&mut p, "top=1&sort=mem");
+        assert_eq!(p.top_processes[0].pid, 1);
+        assert_eq!(p.top_processes.len(), 1);
+
+        // Long-running but idle now: first by CPU time
+        let mut p = ProcessesPayload {
+            process_count: 2,
+            top_processes: vec![
+                proc_row(4, 90.0, 100),
+                ProcessInfo {
+                    cpu_time_ms: 9_000_000,
+                    ..proc_row(5, 0.0, 100)
+                },
+            ],
+        };
+        apply_top_k(&mut p, "top=1&sort=time");
+        assert_eq!(p.top_processes[0].pid, 5);
+    }
+
+    #[tokio::test]
+    async fn tail_sample_rotates_through_the_rest() {
+        let payload = || ProcessesPayload {
+            process_count: 6,
+            top_processes: (1..=6)
+                .map(|pid| proc_row(pid, if pid == 4 { 90.0 } else { 1.0 }, pid as u64))
+                .collect(),
+        };
+        // Top 1 by CPU (pid 4), then two of the rest in PID order after 2: 3, 5
+        let mut p = payload();
+        assert_eq!(apply_top_k(&mut p, "top=1&tail=2&after=2"), Some(1));
+        let pids: Vec<u32> = p.top_processes.iter().map(|r| r.pid).collect();
+        assert_eq!(pids, vec![4, 3, 5]);
+        assert_eq!(p.process_count, 6);
+
+        // Wraps past the highest PID back to the lowest
+        let mut p = payload();
+        apply_top_k(&mut p, "top=1&tail=3&after=5");
+        let pids: Vec<u32> = p.top_processes.iter().map(|r| r.pid).collect();
+        assert_eq!(pids, vec![4, 6, 1, 2]);
+
+        // Without tail nothing is flagged; with it the sample rows are
+        let mut p = payload();
+        assert_eq!(apply_top_k(&mut p, "top=1"), None);
+        let mut p = payload();
+        let from = apply_top_k(&mut p, "top=2&tail=1");
+        let wire = encode_processes(p, from).await.unwrap();
+        let decoded = pb::Processes::decode(wire.as_slice()).unwrap();
+        let flags: Vec<bool> = decoded.rows.iter().map(|r| r.sampled).collect();
+        assert_eq!(flags, vec![false, false, true]);
+    }
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        use std::io::Read;
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(bytes)
+            .read_to_end(&mut out)
+            .expect("gunzip");
+        out
+    }
+
+    #[test]
+    fn huge_host_payloads_encode_and_round_trip() {
+        use crate::fixtures::{huge_metrics, huge_processes, HUGE_CORES, HUGE_DISKS};
+
+        let Reply::Binary(bin) = json_reply(&huge_metrics()) else {
+            panic!("large metrics should be gzipped");
+        };
+        let v: serde_json::Value = serde_json::from_slice(&gunzip(&bin)).unwrap();
+        assert_eq!(v["cpu_per_core"].as_array().unwrap().len(), HUGE_CORES);
+        assert_eq!(v["disks"].as_array().unwrap().len(), HUGE_DISKS);
+
+        let mut p = huge_processes();
+        let total = p.process_count;
+        let pb = pb::Processes {
+            schema_version: SCHEMA_VERSION,
+            process_count: total as u64,
+            rows: std::mem::take(&mut p.top_processes)
+                .into_iter()
+                .map(|r| pb::Process {
+                    pid: r.pid,
+                    name: r.name,
+                    cpu_usage: r.cpu_usage,
+                    mem_bytes: r.mem_bytes,
+                    cpu_time_ms: r.cpu_time_ms,
+                    sampled: false,
+                })
+                .collect(),
+        };
+        let raw = pb.encode_to_vec();
+        let wire = gzip_if_large(raw.clone());
+        assert!(wire.len() < raw.len() / 2, "10k rows should compress well");
+        let decoded = pb::Processes::decode(gunzip(&wire).as_slice()).unwrap();
+        assert_eq!(decoded.rows.len(), total);
+
+        // Clients on a slow link ask for a top-K slice instead
+        let mut top = huge_processes();
+        apply_top_k(&mut top, "top=50");
+        assert_eq!(top.top_processes.len(), 50);
+        assert_eq!(top.process_count, total);
+    }
+
+    #[tokio::test]
+    async fn megabyte_process_names_are_capped_in_the_reply() {
+        let mut rows = vec![proc_row(1, 1.0, 1), proc_row(2, 2.0, 2)];
+        rows[0].name = "a".repeat(1 << 20);
+        let payload = ProcessesPayload {
+            process_count: 2,
+            top_processes: rows,
+        };
+        let wire = encode_processes(payload, None).await.unwrap();
+        assert!(wire.len() < 4096, "name should be cut before encoding");
+        let decoded = pb::Processes::decode(wire.as_slice()).unwrap();
+        assert_eq!(
+            decoded.rows[0].name.chars().count(),
+            crate::limits::DEFAULT_MAX_NAME_LEN
+        );
+        assert!(decoded.rows[0].name.ends_with('…'));
+        assert_eq!(decoded.rows[1].name, "p2");
+    }
+
+    #[tokio::test]
+    async fn replies_carry_the_schema_version() {
+        let state = AppState::new();
+        let v = hello(&state, &Conn::new(Scope::Read), "hello").await;
+        assert_eq!(v["schema_version"], SCHEMA_VERSION);
+
+        let payload = ProcessesPayload {
+            process_count: 1,
+            top_processes: vec![proc_row(1, 1.0, 1)],
+        };
+        let wire = encode_processes(payload, None).await.unwrap();
+        let decoded = pb::Processes::decode(wire.as_slice()).unwrap();
+        assert_eq!(decoded.schema_version, SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn hello_reports_the_token_scope() {
+        let mut state = AppState::new();
+        state.auth_token = Some("admin".into());
+        state.read_token = Some("viewer".into());
+
+        assert_eq!(state.authorize(Some("admin")), Some(Scope::Control));
+        assert_eq!(state.authorize(Some("viewer")), Some(Scope::Read));
+        assert_eq!(state.authorize(Some("guess")), None);
+        assert_eq!(state.authorize(None), None);
+
+        let Some(Reply::Text(t)) = respond(&state, &Conn::new(Scope::Read), "hello").await else {
+            panic!("hello should be a small text reply");
+        };
+        let v: serde_json::Value = serde_json::from_str(&t).unwrap();
+        assert_eq!(v["scope"], "read");
+        assert!(v["capabilities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c == "hello"));
+
+        // No tokens configured: open, but never control
+        let mut open = AppState::new();
+        open.auth_token = None;
+        open.read_token = None;
+        assert_eq!(open.authorize(None), Some(Scope::Read));
+        assert_eq!(open.authorize(Some("anything")), Some(Scope::Read));
+    }
+
+    #[test]
+    fn relay_refuses_control_commands_and_reports_the_callers_scope() {
+        assert!(control_only("set_governor?name=performance"));
+        assert!(control_only("get_process_env?pid=1"));
+        assert!(!control_only("get_cpufreq"));
+        assert!(!control_only("hello"));
+
+        let Reply::Text(t) = relay_refusal("get_process_fds?pid=7") else {
+            panic!("a refusal is a small text reply");
+        };
+        let v: serde_json::Value = serde_json::from_str(&t).unwrap();
+        assert_eq!(
+            (v["pid"].as_u64(), v["error"].as_str()),
+            (Some(7), Some("needs a control-scope token"))
+        );
+        let Reply::Text(t) = relay_refusal("set_governor?name=performance") else {
+            panic!("a refusal is a small text reply");
+        };
+        let v: serde_json::Value = serde_json::from_str(&t).unwrap();
+        assert_eq!(v["cores"].as_array().map(Vec::len), Some(0));
+
+        // The leaf saw the relay's control token; the caller only has read
+        let leaf = Reply::Text(r#"{"scope":"control","session":"s1"}"#.into());
+        let Reply::Text(t) = with_scope(leaf, Scope::Read) else {
+            panic!("hello should be a small text reply");
+        };
+        let v: serde_json::Value = serde_json::from_str(&t).unwrap();
+        assert_eq!(
+            (v["scope"].as_str(), v["session"].as_str()),
+            (Some("read"), Some("s1"))
+        );
+        let Reply::Text(t) = with_scope(Reply::Text("not json".into()), Scope::Read) else {
+            panic!("left as is");
+        };
+        assert_eq!(t, "not json");
+    }
+
+    async fn hello(state: &AppState, conn: &Conn, cmd: &str) -> serde_json::Value {
+        let Some(Reply::Text(t)) = respond(state, conn, cmd).await else {
+            panic!("hello should be a small text reply");
+        };
+        serde_json::from_str(&t).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reconnect_resumes_the_session_from_hello() {
+        let state = AppState::new();
+        let first = Conn::new(Scope::Read);
+        let v = hello(&state, &first, "hello").await;
+        let id = v["session"].as_str().unwrap().to_string();
+        assert_eq!(v["resumed"], false);
+
+        // Connection drops; a new one asks for the old session back
+        first.close(&state.sessions);
+        let second = Conn::new(Scope::Read);
+        let v = hello(&state, &second, &format!("hello?resume={id}")).await;
+        assert_eq!(
+            (v["session"].as_str(), v["resumed"].as_bool()),
+            (Some(id.as_str()), Some(true))
+        );
+        assert_eq!(second.session().as_deref(), Some(id.as_str()));
+
+        // Unknown (or agent restarted): a fresh session instead
+        let third = Conn::new(Scope::Read);
+        let v = hello(&state, &third, "hello?resume=deadbeef").await;
+        assert_eq!(v["resumed"], false);
+        assert_ne!(v["session"], "deadbeef");
+    }
+
+    fn reply_json(reply: Reply) -> serde_json::Value {
+        match reply {
+            Reply::Text(t) => serde_json::from_str(&t).unwrap(),
+            Reply::Binary(b) => serde_json::from_slice(&gunzip(&b)).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn old_clients_can_ask_for_json_processes() {
+        let state = AppState::new();
+        let conn = Conn::new(Scope::Read);
+        // New clients never have to ask: protobuf by default
+        let v = hello(&state, &conn, "hello").await;
+        assert_eq!(v["processes"], "pb");
+        assert!(v["capabilities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c == "processes_pb"));
+        let Some(Reply::Binary(wire)) = respond(&state, &conn, "get_processes?top=3").await else {
+            panic!("protobuf processes are a binary frame");
+        };
+        let raw = if wire.starts_with(&[0x1f, 0x8b]) {
+            gunzip(&wire)
+        } else {
+            wire
+        };
+        assert!(pb::Processes::decode(raw.as_slice()).unwrap().rows.len() <= 3);
+
+        // One request in JSON, then the whole session
+        let reply = respond(&state, &conn, "get_processes?top=3&format=json").await;
+        let v = reply_json(reply.unwrap());
+        assert!(v["top_processes"].as_array().unwrap().len() <= 3);
+        assert_eq!(v["schema_version"], SCHEMA_VERSION);
+        let v = hello(&state, &conn, "hello?processes=json").await;
+        assert_eq!(v["processes"], "json");
+        let v = reply_json(respond(&state, &conn, "get_processes").await.unwrap());
+        assert!(v["process_count"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn json_processes_put_samples_in_tail_and_respect_the_caps() {
+        let mut rows = vec![
+            proc_row(1, 1.0, 1),
+            proc_row(2, 2.0, 2),
+            proc_row(3, 0.5, 3),
+        ];
+        rows[0].name = "a".repeat(1 << 20);
+        let payload = ProcessesPayload {
+            process_count: 3,
+            top_processes: rows,
+        };
+        let v = reply_json(processes_json(payload, Some(2)));
+        assert_eq!(v["process_count"], 3);
+        let top = v["top_processes"].as_array().unwrap();
+        assert_eq!(top.len(), 2);
+        assert_eq!(
+            top[0]["name"].as_str().unwrap().chars().count(),
+            crate::limits::DEFAULT_MAX_NAME_LEN
+        );
+        assert_eq!(v["tail"][0]["pid"], 3);
+
+        // Without a tail the key is left out, as older clients expect
+        let payload = ProcessesPayload {
+            process_count: 1,
+            top_processes: vec![proc_row(7, 1.0, 1)],
+        };
+        let v = reply_json(processes_json(payload, None));
+        assert!(v.get("tail").is_none());
+    }
+
+    #[test]
+    fn fleet_label_filter_requires_all_labels() {
+        let wanted = label_filter("label=env=prod&label=rack=12&other=1");
+        assert_eq!(
+            wanted,
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("rack".to_string(), "12".to_string())
+            ]
+        );
+        let entry = |labels: &[(&str, &str)]| FleetEntry {
+            host: "h".into(),
+            instance: None,
+            ok: true,
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            metrics: None,
+        };
+        assert!(entry(&[("env", "prod"), ("rack", "12"), ("x", "y")]).matches(&wanted));
+        assert!(!entry(&[("env", "prod")]).matches(&wanted));
+        assert!(!entry(&[("env", "dev"), ("rack", "12")]).matches(&wanted));
+        assert!(entry(&[]).matches(&[]));
+    }
+
     #[tokio::test]
     async fn test_process_list_not_empty() {
         // Initialize system data first to ensure we have processes
@@ -167,10 +1012,13 @@ mod tests {
                 name: p.name,
                 cpu_usage: p.cpu_usage,
                 mem_bytes: p.mem_bytes,
+                cpu_time_ms: p.cpu_time_ms,
+                sampled: false,
             }));
 
         // Create the protobuf message
         let pb = pb::Processes {
+            schema_version: SCHEMA_VERSION,
             process_count: processes.process_count as u64,
             rows: cache.processes_vec.clone(),
         };
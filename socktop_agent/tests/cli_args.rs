@@ -26,3 +26,17 @@ fn test_help_and_port_short_long() {
     let _ = child2.kill();
     let _ = child2.wait();
 }
+
+#[test]
+fn test_self_test_reports_collectors_and_passes() {
+    let out = Command::new(env!("CARGO_BIN_EXE_socktop_agent"))
+        .arg("--self-test")
+        .env("SOCKTOP_AGENT_GPU", "0")
+        .output()
+        .expect("run agent --self-test");
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "self-test failed:\n{text}");
+    for name in ["cpu/mem", "temperature", "gpu", "disks", "processes"] {
+        assert!(text.contains(name), "missing {name} line:\n{text}");
+    }
+}